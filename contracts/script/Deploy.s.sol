@@ -23,10 +23,12 @@ contract Deploy is Script {
         address secp256r1Precompile = address(0); // Not available on Sepolia
         address sp1Verifier = SP1_VERIFIER_SEPOLIA;
         address tokenAddress = USDC_SEPOLIA; // Use USDC for $ denominated balances
+        // vkey of the guest ELF (`sp1-host vkey`); `sp1-host deploy` sets this for you
+        bytes32 programVKey = vm.envBytes32("PROGRAM_VKEY");
 
         // Deploy contract with SP1 verifier and ERC20 token support
         // Note: Merkle tree initializes to empty state automatically
-        PrivateUTXOLedger ledger = new PrivateUTXOLedger(secp256r1Precompile, sp1Verifier, tokenAddress);
+        PrivateUTXOLedger ledger = new PrivateUTXOLedger(secp256r1Precompile, sp1Verifier, tokenAddress, programVKey);
 
         vm.stopBroadcast();
 
@@ -20,7 +20,12 @@ contract DeployLocal is Script {
         MockERC20 token = new MockERC20("USDC", "USDC", 6);
 
         // 3. Deploy Ledger
-        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(verifier), address(token));
+        PrivateUTXOLedger ledger = new PrivateUTXOLedger(
+            address(0),
+            address(verifier),
+            address(token),
+            vm.envOr("PROGRAM_VKEY", keccak256("local-program-vkey")) // mock verifier ignores it
+        );
 
         // 4. Deploy EncryptedContacts
         EncryptedContacts contacts = new EncryptedContacts();
@@ -6,6 +6,8 @@ import {IERC20} from "@openzeppelin/contracts/token/ERC20/IERC20.sol";
 import {SafeERC20} from "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
 import {ISignatureTransfer} from "permit2/src/interfaces/ISignatureTransfer.sol";
 import {MerkleTree} from "./MerkleTree.sol";
+import {PublicValuesCompression} from "./PublicValuesCompression.sol";
+import {PublicValuesTrailer} from "./PublicValuesTrailer.sol";
 
 contract PrivateUTXOLedger {
     using SafeERC20 for IERC20;
@@ -37,8 +39,8 @@ contract PrivateUTXOLedger {
     address public immutable sp1Verifier;
 
     /// @notice Verification key for the UTXO SP1 program
-    /// Updated for PublicOutputs without newRoot (2025-01-13)
-    bytes32 public constant UTXO_PROGRAM_VKEY = 0x00655733d455a47f7eae3e285dafc9c0467cfd564376eeda78fea2528b8b765d;
+    /// @dev Fixed at deployment; `sp1-host deploy` passes the embedded ELF's vkey
+    bytes32 public immutable UTXO_PROGRAM_VKEY;
 
     /// @notice Permit2 canonical address (same on all EVM chains)
     ISignatureTransfer public constant PERMIT2 = ISignatureTransfer(0x000000000022D473030F116dDEE9F6B43aC78BA3);
@@ -46,11 +48,23 @@ contract PrivateUTXOLedger {
     mapping(bytes32 => bytes) public commitmentMetadata;
 
     struct PublicOutputs {
+        uint64 chainId;     // Chain the proof is bound to; must be block.chainid
         bytes32 oldRoot;
         bytes32[] nullifiers;
         bytes32[] outputCommitments;
     }
 
+    /// @dev Public values of a withdrawal: the transfer fields plus the value
+    /// paid out, which the input owners signed over
+    struct WithdrawalOutputs {
+        uint64 chainId;
+        bytes32 oldRoot;
+        bytes32[] nullifiers;
+        bytes32[] outputCommitments;
+        uint64 publicAmount;
+        address recipient;
+    }
+
     struct OutputCiphertext {
         bytes32 commitment;
         uint8 keyType;
@@ -60,6 +74,7 @@ contract PrivateUTXOLedger {
     }
 
     event RootUpdated(bytes32 indexed oldRoot, bytes32 indexed newRoot);
+    event NullifierUsed(bytes32 indexed nullifier);
     event OutputCommitted(
         bytes32 indexed commitment,
         uint8 keyType,
@@ -75,7 +90,9 @@ contract PrivateUTXOLedger {
 
     mapping(bytes32 => bool) public validRoots;
 
-    constructor(address _secp256r1Precompile, address _sp1Verifier, address _token) {
+    constructor(address _secp256r1Precompile, address _sp1Verifier, address _token, bytes32 _programVKey) {
+        require(_programVKey != bytes32(0), "Program vkey required");
+        UTXO_PROGRAM_VKEY = _programVKey;
         secp256r1Precompile = _secp256r1Precompile;
         sp1Verifier = _sp1Verifier;
         token = _token;
@@ -253,8 +270,48 @@ contract PrivateUTXOLedger {
 
         // SECURITY FIX: Decode outputs directly from the proven publicValues
         // This ensures the values we use are exactly what was proven in the ZK circuit
-        PublicOutputs memory outputs = abi.decode(publicValues, (PublicOutputs));
+        require(!_isWithdrawal(publicValues), "Withdrawal proof; use withdraw");
+        PublicOutputs memory outputs = abi.decode(PublicValuesTrailer.base(publicValues), (PublicOutputs));
+
+        _applyTransfer(encryptedOutputs, metadata, outputs);
+    }
+
+    /// @dev Whether public values use the WithdrawalOutputs layout: its
+    /// six-field head puts the nullifier list at offset 0xc0, where
+    /// PublicOutputs has it at 0x80 (a trailer is ignored)
+    function _isWithdrawal(bytes calldata publicValues) internal pure returns (bool) {
+        bytes calldata values = PublicValuesTrailer.base(publicValues);
+        return values.length >= 128 && uint256(bytes32(values[96:128])) == 0xc0;
+    }
+
+    /// @notice Submit a many-output transaction whose public values commit list roots
+    /// @dev The full lists are supplied as calldata and re-hashed against the proven roots
+    function submitTxCompressed(
+        OutputCiphertext[] calldata encryptedOutputs,
+        bytes calldata proof,
+        bytes calldata publicValues,  // ABI-encoded CompressedPublicOutputs from SP1 zkVM
+        bytes32[] calldata nullifiers,
+        bytes32[] calldata outputCommitments
+    ) external {
+        require(sp1Verifier != address(0), "SP1 verifier not configured");
+        ISP1Verifier(sp1Verifier).verifyProof(UTXO_PROGRAM_VKEY, publicValues, proof);
+
+        PublicOutputs memory outputs;
+        (outputs.oldRoot, outputs.chainId) = PublicValuesCompression.verifyLists(
+            PublicValuesTrailer.base(publicValues), nullifiers, outputCommitments
+        );
+        outputs.nullifiers = nullifiers;
+        outputs.outputCommitments = outputCommitments;
 
+        _applyTransfer(encryptedOutputs, new bytes[](0), outputs);
+    }
+
+    function _applyTransfer(
+        OutputCiphertext[] calldata encryptedOutputs,
+        bytes[] memory metadata,
+        PublicOutputs memory outputs
+    ) internal {
+        require(outputs.chainId == block.chainid, "Wrong chain");
         require(validRoots[outputs.oldRoot], "Invalid old root");
         require(metadata.length == 0 || metadata.length == encryptedOutputs.length, "Metadata length mismatch");
 
@@ -263,6 +320,7 @@ contract PrivateUTXOLedger {
             bytes32 nullifier = outputs.nullifiers[i];
             require(!nullifierUsed[nullifier], "Nullifier already used");
             nullifierUsed[nullifier] = true;
+            emit NullifierUsed(nullifier);
         }
 
         require(encryptedOutputs.length == outputs.outputCommitments.length, "Ciphertext count mismatch");
@@ -301,11 +359,12 @@ contract PrivateUTXOLedger {
     }
 
     /// @notice Withdraw funds with optional change output (SECURITY: outputs decoded from publicValues)
+    /// @dev `recipient` and `amount` must be the ones proven in publicValues
     function withdraw(
         address recipient,
         uint256 amount,
         bytes calldata proof,
-        bytes calldata publicValues,  // ABI-encoded PublicOutputs from SP1 zkVM
+        bytes calldata publicValues,  // ABI-encoded WithdrawalOutputs from SP1 zkVM
         OutputCiphertext[] calldata encryptedOutputs  // Change outputs (if any)
     ) external {
         // Always verify SP1 Groth16 proof
@@ -315,10 +374,15 @@ contract PrivateUTXOLedger {
         ISP1Verifier(sp1Verifier).verifyProof(UTXO_PROGRAM_VKEY, publicValues, proof);
 
         // SECURITY FIX: Decode outputs directly from the proven publicValues
-        PublicOutputs memory outputs = abi.decode(publicValues, (PublicOutputs));
+        require(_isWithdrawal(publicValues), "Not a withdrawal proof");
+        WithdrawalOutputs memory outputs = abi.decode(PublicValuesTrailer.base(publicValues), (WithdrawalOutputs));
 
+        require(outputs.chainId == block.chainid, "Wrong chain");
         require(validRoots[outputs.oldRoot], "Invalid old root");
+        require(recipient == outputs.recipient, "Recipient mismatch");
+        require(amount == outputs.publicAmount, "Amount mismatch");
         require(amount > 0, "Amount must be positive");
+        _checkHiddenAmounts(publicValues, outputs);
         require(amount <= totalDeposited, "Insufficient contract balance");
 
         // Verify encrypted outputs match proven commitments (for change notes)
@@ -332,6 +396,7 @@ contract PrivateUTXOLedger {
             bytes32 nullifier = outputs.nullifiers[i];
             require(!nullifierUsed[nullifier], "Nullifier already used");
             nullifierUsed[nullifier] = true;
+            emit NullifierUsed(nullifier);
         }
 
         // Insert change outputs into Merkle tree
@@ -359,6 +424,23 @@ contract PrivateUTXOLedger {
         emit Withdrawn(recipient, amount);
     }
 
+    /// @dev A hidden-amounts proof also commits its net public amount and one
+    /// amount commitment per output in a trailer section (see core
+    /// `amount_commitment::encode_hidden_amounts`). The net is everything
+    /// leaving the pool, so it must cover the payout; the rest is the fee.
+    function _checkHiddenAmounts(bytes calldata publicValues, WithdrawalOutputs memory outputs) internal pure {
+        (bool found, bytes calldata hidden) =
+            PublicValuesTrailer.section(publicValues, PublicValuesTrailer.SECTION_HIDDEN_AMOUNTS);
+        if (!found) {
+            return;
+        }
+        require(hidden.length >= 12, "Malformed hidden amounts");
+        uint256 count = uint32(bytes4(hidden[8:12]));
+        require(hidden.length == 12 + count * 33, "Malformed hidden amounts");
+        require(count == outputs.outputCommitments.length, "Hidden amounts count mismatch");
+        require(uint64(bytes8(hidden[:8])) >= outputs.publicAmount, "Hidden amounts below payout");
+    }
+
     /// @notice Deposit and transfer atomically (SECURITY: outputs decoded from publicValues)
     function depositAndTransfer(
         bytes32 depositCommitment,
@@ -395,8 +477,10 @@ contract PrivateUTXOLedger {
         ISP1Verifier(sp1Verifier).verifyProof(UTXO_PROGRAM_VKEY, publicValues, proof);
 
         // SECURITY FIX: Decode outputs directly from the proven publicValues
-        PublicOutputs memory transferOutputs = abi.decode(publicValues, (PublicOutputs));
+        require(!_isWithdrawal(publicValues), "Withdrawal proof; use withdraw");
+        PublicOutputs memory transferOutputs = abi.decode(PublicValuesTrailer.base(publicValues), (PublicOutputs));
 
+        require(transferOutputs.chainId == block.chainid, "Wrong chain");
         require(transferOutputs.outputCommitments.length > 0, "Must have outputs");
         require(validRoots[transferOutputs.oldRoot], "Transfer oldRoot mismatch");
 
@@ -404,6 +488,7 @@ contract PrivateUTXOLedger {
             bytes32 nf = transferOutputs.nullifiers[i];
             require(!nullifierUsed[nf], "Nullifier already used");
             nullifierUsed[nf] = true;
+            emit NullifierUsed(nf);
         }
 
         // Insert output commitments
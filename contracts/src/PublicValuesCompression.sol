@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+/**
+ * @title PublicValuesCompression
+ * @notice Decoding of compressed SP1 public values for many-output transactions
+ * @dev Matches core/src/public_values.rs
+ *
+ * Instead of the full nullifier and commitment arrays, the guest commits each
+ * list's length and a Keccak Merkle root over it (zero-padded to a power of
+ * two). The full lists are supplied as calldata and re-hashed here, so the
+ * proven public values stay a fixed 192 bytes regardless of batch size.
+ */
+library PublicValuesCompression {
+    struct CompressedPublicOutputs {
+        uint64 chainId;
+        bytes32 oldRoot;
+        uint32 nullifierCount;
+        bytes32 nullifiersRoot;
+        uint32 commitmentCount;
+        bytes32 commitmentsRoot;
+    }
+
+    /// @notice ABI-encoded length of CompressedPublicOutputs
+    uint256 internal constant COMPRESSED_LENGTH = 192;
+
+    /// @notice Keccak Merkle root over a list, zero-padded to a power of two
+    /// @dev Empty list => bytes32(0); a single item is its own root
+    function listRoot(bytes32[] calldata items) internal pure returns (bytes32) {
+        uint256 n = items.length;
+        if (n == 0) return bytes32(0);
+
+        uint256 width = 1;
+        while (width < n) width <<= 1;
+
+        bytes32[] memory level = new bytes32[](width);
+        for (uint256 i = 0; i < n; i++) {
+            level[i] = items[i];
+        }
+        while (width > 1) {
+            width >>= 1;
+            for (uint256 i = 0; i < width; i++) {
+                level[i] = keccak256(abi.encodePacked(level[2 * i], level[2 * i + 1]));
+            }
+        }
+        return level[0];
+    }
+
+    /// @notice Check calldata lists against compressed public values
+    /// @return oldRoot The proven pre-transaction root
+    /// @return chainId The chain the proof is bound to
+    function verifyLists(
+        bytes calldata publicValues,
+        bytes32[] calldata nullifiers,
+        bytes32[] calldata outputCommitments
+    ) internal pure returns (bytes32 oldRoot, uint64 chainId) {
+        require(publicValues.length == COMPRESSED_LENGTH, "Not compressed public values");
+        CompressedPublicOutputs memory c = abi.decode(publicValues, (CompressedPublicOutputs));
+
+        require(c.nullifierCount == nullifiers.length, "Nullifier count mismatch");
+        require(c.commitmentCount == outputCommitments.length, "Commitment count mismatch");
+        require(listRoot(nullifiers) == c.nullifiersRoot, "Nullifiers root mismatch");
+        require(listRoot(outputCommitments) == c.commitmentsRoot, "Commitments root mismatch");
+
+        return (c.oldRoot, c.chainId);
+    }
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+/**
+ * @title PublicValuesTrailer
+ * @notice Stripping of the tagged trailer some guest modes append to their public values
+ * @dev Matches the trailer in core/src/public_values.rs
+ *
+ * Guests built with extra modes (hidden amounts, SMT nullifier roots) commit tagged sections
+ * after the ABI-encoded outputs, then a 32-byte footer: a 28-byte magic and
+ * the sections' length (uint32, big-endian). The ledger's layouts are
+ * recognised by length and offsets, so it decodes the values without the
+ * trailer; the proof is still verified over all of them. Sections are
+ * `tag (1) || length (uint32 BE) || body`; `section` looks one up.
+ */
+library PublicValuesTrailer {
+    /// @notice First 28 bytes of the footer
+    bytes28 internal constant MAGIC = bytes28("ghostclaw-pv-trailer-v1");
+
+    /// @notice Length of the footer (magic and sections length)
+    uint256 internal constant FOOTER_LENGTH = 32;
+
+    /// @notice Section holding the hidden-amounts net public amount and amount commitments
+    uint8 internal constant SECTION_HIDDEN_AMOUNTS = 1;
+
+    /// @notice The ABI-encoded public values, without a trailer if there is one
+    function base(bytes calldata publicValues) internal pure returns (bytes calldata) {
+        uint256 n = publicValues.length;
+        if (n < FOOTER_LENGTH || bytes28(publicValues[n - FOOTER_LENGTH:n - 4]) != MAGIC) {
+            return publicValues;
+        }
+        uint256 sectionsLength = uint32(bytes4(publicValues[n - 4:n]));
+        require(sectionsLength + FOOTER_LENGTH <= n, "Malformed public values trailer");
+        return publicValues[:n - FOOTER_LENGTH - sectionsLength];
+    }
+
+    /// @notice The body of the trailer section tagged `tag`, if there is one
+    function section(bytes calldata publicValues, uint8 tag) internal pure returns (bool found, bytes calldata body) {
+        uint256 n = publicValues.length;
+        uint256 end = n < FOOTER_LENGTH ? 0 : n - FOOTER_LENGTH;
+        uint256 offset = base(publicValues).length;
+        while (offset < end) {
+            require(offset + 5 <= end, "Truncated public values trailer section");
+            uint8 sectionTag = uint8(publicValues[offset]);
+            uint256 length = uint32(bytes4(publicValues[offset + 1:offset + 5]));
+            require(offset + 5 + length <= end, "Truncated public values trailer section");
+            if (sectionTag == tag) {
+                return (true, publicValues[offset + 5:offset + 5 + length]);
+            }
+            offset += 5 + length;
+        }
+        return (false, publicValues[n:]);
+    }
+}
@@ -3,6 +3,7 @@ pragma solidity ^0.8.20;
 
 import "forge-std/Test.sol";
 import "../src/PrivateUTXOLedger.sol";
+import {TEST_PROGRAM_VKEY} from "./PrivateUTXOLedger_Base.t.sol";
 import "../src/MerkleTree.sol";
 
 contract MockSP1VerifierForConcurrency {
@@ -20,7 +21,7 @@ contract ConcurrencyTest is Test {
 
     function setUp() public {
         mockVerifier = new MockSP1VerifierForConcurrency();
-        ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
     }
 
     function _encodePublicValues(PrivateUTXOLedger.PublicOutputs memory outputs) internal pure returns (bytes memory) {
@@ -73,6 +74,7 @@ contract ConcurrencyTest is Test {
         // This should SUCCEED with the fix.
 
         PrivateUTXOLedger.PublicOutputs memory outputs;
+        outputs.chainId = uint64(block.chainid);
         outputs.oldRoot = rootAfterA; // HISTORIC ROOT
         // The ZK proof would have calculated a new root based strictly on A + B.
         // But the contract will ignore this and calculate C + B.
@@ -3,6 +3,7 @@ pragma solidity ^0.8.20;
 
 import "forge-std/Test.sol";
 import "../src/PrivateUTXOLedger.sol";
+import {TEST_PROGRAM_VKEY} from "./PrivateUTXOLedger_Base.t.sol";
 import "../src/EncryptedContacts.sol";
 import "../src/PaymentRequests.sol";
 import "../src/MerkleTree.sol";
@@ -12,6 +13,7 @@ import "./mocks/MockERC20.sol";
 /// @notice Complete end-to-end tests that mimic the real Sepolia testnet setup
 /// @dev Uses mock USDC and mock SP1 verifier for fast local testing
 contract E2E_USDC_Test is Test {
+
     // ============================================
     // CONTRACTS
     // ============================================
@@ -73,7 +75,8 @@ contract E2E_USDC_Test is Test {
         ledger = new PrivateUTXOLedger(
             address(0), // no secp256r1 precompile
             address(mockVerifier),
-            address(usdc)
+            address(usdc),
+            TEST_PROGRAM_VKEY
         );
 
         // Deploy auxiliary contracts
@@ -110,8 +113,9 @@ contract E2E_USDC_Test is Test {
         bytes32, // newRoot removed from struct
         bytes32[] memory nullifiers,
         bytes32[] memory outputCommitments
-    ) internal pure returns (bytes memory) {
+    ) internal view returns (bytes memory) {
         PrivateUTXOLedger.PublicOutputs memory outputs = PrivateUTXOLedger.PublicOutputs({
+            chainId: uint64(block.chainid),
             oldRoot: oldRoot,
             nullifiers: nullifiers,
             outputCommitments: outputCommitments
@@ -119,6 +123,25 @@ contract E2E_USDC_Test is Test {
         return abi.encode(outputs);
     }
 
+    function _createWithdrawal(
+        bytes32 oldRoot,
+        bytes32, // newRoot removed from struct
+        bytes32[] memory nullifiers,
+        bytes32[] memory outputCommitments,
+        uint256 amount,
+        address recipient
+    ) internal view returns (bytes memory) {
+        PrivateUTXOLedger.WithdrawalOutputs memory outputs = PrivateUTXOLedger.WithdrawalOutputs({
+            chainId: uint64(block.chainid),
+            oldRoot: oldRoot,
+            nullifiers: nullifiers,
+            outputCommitments: outputCommitments,
+            publicAmount: uint64(amount),
+            recipient: recipient
+        });
+        return abi.encode(outputs);
+    }
+
     function _emptyNullifiers() internal pure returns (bytes32[] memory) {
         return new bytes32[](0);
     }
@@ -236,7 +259,7 @@ contract E2E_USDC_Test is Test {
             // No new outputs, so root stays the same
             bytes32 root3 = root1;
 
-            bytes memory pv = _createPublicOutputs(root1, root3, nf, outs);
+            bytes memory pv = _createWithdrawal(root1, root3, nf, outs, 600 * ONE_USDC, bob);
             PrivateUTXOLedger.OutputCiphertext[] memory enc = new PrivateUTXOLedger.OutputCiphertext[](0);
 
             uint256 bobBefore = usdc.balanceOf(bob);
@@ -380,7 +403,7 @@ contract E2E_USDC_Test is Test {
         bytes32[] memory outputs = new bytes32[](1);
         outputs[0] = changeNote;
 
-        bytes memory publicValues = _createPublicOutputs(currentRoot, newRoot, nullifiers, outputs);
+        bytes memory publicValues = _createWithdrawal(currentRoot, newRoot, nullifiers, outputs, withdrawAmount, bob);
 
         PrivateUTXOLedger.OutputCiphertext[] memory changeOutputs = new PrivateUTXOLedger.OutputCiphertext[](1);
         changeOutputs[0] = _createEncrypted(changeNote);
@@ -592,7 +615,7 @@ contract E2E_USDC_Test is Test {
         bytes32[] memory nullifiers = new bytes32[](1);
         nullifiers[0] = keccak256("nf1");
         bytes32[] memory outputs = new bytes32[](0);
-        bytes memory pv = _createPublicOutputs(currentRoot, currentRoot, nullifiers, outputs);
+        bytes memory pv = _createWithdrawal(currentRoot, currentRoot, nullifiers, outputs, 300 * ONE_USDC, bob);
         PrivateUTXOLedger.OutputCiphertext[] memory empty = new PrivateUTXOLedger.OutputCiphertext[](0);
 
         ledger.withdraw(bob, 300 * ONE_USDC, "", pv, empty);
@@ -604,7 +627,7 @@ contract E2E_USDC_Test is Test {
         // Withdraw another 200 USDC
         currentRoot = ledger.currentRoot();
         nullifiers[0] = keccak256("nf2");
-        pv = _createPublicOutputs(currentRoot, currentRoot, nullifiers, outputs);
+        pv = _createWithdrawal(currentRoot, currentRoot, nullifiers, outputs, 200 * ONE_USDC, bob);
 
         ledger.withdraw(bob, 200 * ONE_USDC, "", pv, empty);
 
@@ -667,7 +690,7 @@ contract E2E_USDC_Test is Test {
         bytes32[] memory nullifiers = new bytes32[](1);
         nullifiers[0] = keccak256("nf");
         bytes32[] memory outputs = new bytes32[](0);
-        bytes memory pv = _createPublicOutputs(currentRoot, currentRoot, nullifiers, outputs);
+        bytes memory pv = _createWithdrawal(currentRoot, currentRoot, nullifiers, outputs, 100 * ONE_USDC, bob);
         PrivateUTXOLedger.OutputCiphertext[] memory empty = new PrivateUTXOLedger.OutputCiphertext[](0);
 
         ledger.withdraw(bob, 100 * ONE_USDC, "", pv, empty);
@@ -736,14 +759,14 @@ contract E2E_USDC_Test is Test {
         // Alice withdraws 200 (no new outputs, root stays same)
         bytes32[] memory nf2 = new bytes32[](1);
         nf2[0] = keccak256("withdraw_nf");
-        bytes memory pv2 = _createPublicOutputs(root3, root3, nf2, _emptyOutputs());
+        bytes memory pv2 = _createWithdrawal(root3, root3, nf2, _emptyOutputs(), 200 * ONE_USDC, alice);
         ledger.withdraw(alice, 200 * ONE_USDC, "", pv2, new PrivateUTXOLedger.OutputCiphertext[](0));
         assertEq(ledger.getBalance(), 1300 * ONE_USDC, "Step 4: 1300 USDC after withdraw");
 
         // Bob withdraws 300 (no new outputs, root stays same)
         bytes32[] memory nf3 = new bytes32[](1);
         nf3[0] = keccak256("bob_withdraw_nf");
-        bytes memory pv3 = _createPublicOutputs(root3, root3, nf3, _emptyOutputs());
+        bytes memory pv3 = _createWithdrawal(root3, root3, nf3, _emptyOutputs(), 300 * ONE_USDC, bob);
         ledger.withdraw(bob, 300 * ONE_USDC, "", pv3, new PrivateUTXOLedger.OutputCiphertext[](0));
         assertEq(ledger.getBalance(), 1000 * ONE_USDC, "Step 5: 1000 USDC after Bob withdraw");
 
@@ -770,6 +793,7 @@ contract MockSP1Verifier {
 /// @title Adversarial E2E Tests
 /// @notice Tests designed to break the system - edge cases, attacks, and malicious inputs
 contract E2E_Adversarial_Test is Test {
+
     PrivateUTXOLedger public ledger;
     EncryptedContacts public contacts;
     PrivatePaymentRequests public paymentRequests;
@@ -798,7 +822,8 @@ contract E2E_Adversarial_Test is Test {
         ledger = new PrivateUTXOLedger(
             address(0),
             address(mockVerifier),
-            address(usdc)
+            address(usdc),
+            TEST_PROGRAM_VKEY
         );
 
         contacts = new EncryptedContacts();
@@ -831,8 +856,9 @@ contract E2E_Adversarial_Test is Test {
         bytes32, // newRoot removed from struct
         bytes32[] memory nullifiers,
         bytes32[] memory outputCommitments
-    ) internal pure returns (bytes memory) {
+    ) internal view returns (bytes memory) {
         PrivateUTXOLedger.PublicOutputs memory outputs = PrivateUTXOLedger.PublicOutputs({
+            chainId: uint64(block.chainid),
             oldRoot: oldRoot,
             nullifiers: nullifiers,
             outputCommitments: outputCommitments
@@ -840,6 +866,25 @@ contract E2E_Adversarial_Test is Test {
         return abi.encode(outputs);
     }
 
+    function _createWithdrawal(
+        bytes32 oldRoot,
+        bytes32, // newRoot removed from struct
+        bytes32[] memory nullifiers,
+        bytes32[] memory outputCommitments,
+        uint256 amount,
+        address recipient
+    ) internal view returns (bytes memory) {
+        PrivateUTXOLedger.WithdrawalOutputs memory outputs = PrivateUTXOLedger.WithdrawalOutputs({
+            chainId: uint64(block.chainid),
+            oldRoot: oldRoot,
+            nullifiers: nullifiers,
+            outputCommitments: outputCommitments,
+            publicAmount: uint64(amount),
+            recipient: recipient
+        });
+        return abi.encode(outputs);
+    }
+
     /// @notice Compute the Merkle root for a single leaf at index 0
     function _computeRootForSingleLeaf(bytes32 leaf) internal pure returns (bytes32) {
         bytes32 current = leaf;
@@ -915,7 +960,7 @@ contract E2E_Adversarial_Test is Test {
         bytes32[] memory nullifiers = new bytes32[](1);
         nullifiers[0] = keccak256("nf");
         bytes32[] memory outputs = new bytes32[](0);
-        bytes memory pv = _createPublicOutputs(currentRoot, keccak256("newroot"), nullifiers, outputs);
+        bytes memory pv = _createWithdrawal(currentRoot, keccak256("newroot"), nullifiers, outputs, 0, bob);
 
         vm.expectRevert("Amount must be positive");
         ledger.withdraw(bob, 0, "", pv, new PrivateUTXOLedger.OutputCiphertext[](0));
@@ -935,7 +980,7 @@ contract E2E_Adversarial_Test is Test {
         bytes32[] memory nullifiers = new bytes32[](1);
         nullifiers[0] = keccak256("nf");
         bytes32[] memory outputs = new bytes32[](0);
-        bytes memory pv = _createPublicOutputs(currentRoot, keccak256("newroot"), nullifiers, outputs);
+        bytes memory pv = _createWithdrawal(currentRoot, keccak256("newroot"), nullifiers, outputs, 200 * ONE_USDC, bob);
 
         vm.expectRevert("Insufficient contract balance");
         ledger.withdraw(bob, 200 * ONE_USDC, "", pv, new PrivateUTXOLedger.OutputCiphertext[](0));
@@ -970,7 +1015,7 @@ contract E2E_Adversarial_Test is Test {
         // This should fail due to balance check
         bytes32[] memory nf = new bytes32[](1);
         nf[0] = keccak256("nf");
-        bytes memory pv = _createPublicOutputs(currentRoot, keccak256("r2"), nf, new bytes32[](0));
+        bytes memory pv = _createWithdrawal(currentRoot, keccak256("r2"), nf, new bytes32[](0), 200 * ONE_USDC, bob);
 
         vm.expectRevert("Insufficient contract balance");
         ledger.withdraw(bob, 200 * ONE_USDC, "", pv, new PrivateUTXOLedger.OutputCiphertext[](0));
@@ -1598,7 +1643,8 @@ contract E2E_Adversarial_Test is Test {
         PrivateUTXOLedger ethLedger = new PrivateUTXOLedger(
             address(0),
             address(mockVerifier),
-            address(0) // ETH ledger
+            address(0), // ETH ledger
+            TEST_PROGRAM_VKEY
         );
 
         bytes32 commitment = keccak256("deposit");
@@ -1680,7 +1726,8 @@ contract E2E_Adversarial_Test is Test {
         PrivateUTXOLedger noVerifierLedger = new PrivateUTXOLedger(
             address(0),
             address(0), // No verifier
-            address(usdc)
+            address(usdc),
+            TEST_PROGRAM_VKEY
         );
 
         usdc.mint(alice, 1000 * ONE_USDC);
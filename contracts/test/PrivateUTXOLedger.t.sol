@@ -3,6 +3,7 @@ pragma solidity ^0.8.20;
 
 import "forge-std/Test.sol";
 import "../src/PrivateUTXOLedger.sol";
+import {TEST_PROGRAM_VKEY} from "./PrivateUTXOLedger_Base.t.sol";
 import "../src/MerkleTree.sol";
 
 /// @notice Mock SP1 verifier that always passes verification (for testing)
@@ -45,11 +46,12 @@ contract PrivateUTXOLedgerTest is Test {
     function testRootsMatchRust() public {
         // Empty transaction: no nullifiers, no outputs, root stays the same
         PrivateUTXOLedger.PublicOutputs memory outputs;
+        outputs.chainId = uint64(block.chainid);
         outputs.oldRoot = EMPTY_TREE_ROOT;
         outputs.nullifiers = new bytes32[](0);
         outputs.outputCommitments = new bytes32[](0);
 
-        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
         bytes memory dummyProof = hex"";
         bytes memory publicValues = _encodePublicValues(outputs);
         PrivateUTXOLedger.OutputCiphertext[] memory emptyEncrypted =
@@ -64,11 +66,12 @@ contract PrivateUTXOLedgerTest is Test {
     /// @dev This mirrors the require(outputs.oldRoot == currentRoot) check.
     function testRevertsOnWrongOldRoot() public {
         PrivateUTXOLedger.PublicOutputs memory outputs;
+        outputs.chainId = uint64(block.chainid);
         outputs.oldRoot = bytes32(uint256(123)); // clearly not EMPTY_TREE_ROOT
         outputs.nullifiers = new bytes32[](0);
         outputs.outputCommitments = new bytes32[](0);
 
-        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
         bytes memory dummyProof = hex"";
         bytes memory publicValues = _encodePublicValues(outputs);
         PrivateUTXOLedger.OutputCiphertext[] memory emptyEncrypted =
@@ -78,6 +81,22 @@ contract PrivateUTXOLedgerTest is Test {
         ledger.submitTx(emptyEncrypted, dummyProof, publicValues);
     }
 
+    /// @notice Public values bound to another chain must revert (no cross-chain replay).
+    function testRevertsOnWrongChain() public {
+        PrivateUTXOLedger.PublicOutputs memory outputs;
+        outputs.chainId = uint64(block.chainid) + 1;
+        outputs.oldRoot = EMPTY_TREE_ROOT;
+        outputs.nullifiers = new bytes32[](0);
+        outputs.outputCommitments = new bytes32[](0);
+
+        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
+        PrivateUTXOLedger.OutputCiphertext[] memory emptyEncrypted =
+            new PrivateUTXOLedger.OutputCiphertext[](0);
+
+        vm.expectRevert(bytes("Wrong chain"));
+        ledger.submitTx(emptyEncrypted, hex"", _encodePublicValues(outputs));
+    }
+
     /// @notice Test that replay protection is enforced via nullifiers, not root freshness.
     /// @dev The contract allows old roots for backward compatibility with delayed proofs.
     ///      Replay protection comes from nullifier uniqueness - if you try to spend the
@@ -89,13 +108,14 @@ contract PrivateUTXOLedgerTest is Test {
         bytes32 nullifier = bytes32(uint256(0xdead)); // Simulated nullifier
 
         PrivateUTXOLedger.PublicOutputs memory outputs;
+        outputs.chainId = uint64(block.chainid);
         outputs.oldRoot = EMPTY_TREE_ROOT;
         outputs.nullifiers = new bytes32[](1);
         outputs.nullifiers[0] = nullifier;
         outputs.outputCommitments = new bytes32[](1);
         outputs.outputCommitments[0] = commitment;
 
-        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
         bytes memory dummyProof = hex"";
         bytes memory publicValues = _encodePublicValues(outputs);
 
@@ -5,6 +5,9 @@ import "forge-std/Test.sol";
 import "../src/PrivateUTXOLedger.sol";
 import "../src/MerkleTree.sol";
 
+/// @notice Program vkey the test ledgers are deployed with (mock verifiers ignore it)
+bytes32 constant TEST_PROGRAM_VKEY = keccak256("test-program-vkey");
+
 /// @notice Mock SP1 verifier that always passes verification (for testing)
 contract MockSP1Verifier {
     function verifyProof(bytes32, bytes calldata, bytes calldata) external pure {
@@ -21,14 +24,15 @@ abstract contract PrivateUTXOLedgerBase is Test {
 
     function setUp() public virtual {
         mockVerifier = new MockSP1Verifier();
-        ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        ledger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
     }
 
     function _emptyOutputs(bytes32 oldRoot, bytes32 newRoot)
         internal
-        pure
+        view
         returns (PrivateUTXOLedger.PublicOutputs memory outputs)
     {
+        outputs.chainId = uint64(block.chainid);
         outputs.oldRoot = oldRoot;
         outputs.nullifiers = new bytes32[](0);
         outputs.outputCommitments = new bytes32[](0);
@@ -39,7 +43,8 @@ abstract contract PrivateUTXOLedgerBase is Test {
         bytes32 newRoot,
         bytes32[] memory nullifiers,
         bytes32[] memory outputCommitments
-    ) internal pure returns (PrivateUTXOLedger.PublicOutputs memory outputs) {
+    ) internal view returns (PrivateUTXOLedger.PublicOutputs memory outputs) {
+        outputs.chainId = uint64(block.chainid);
         outputs.oldRoot = oldRoot;
         outputs.nullifiers = nullifiers;
         outputs.outputCommitments = outputCommitments;
@@ -80,6 +85,24 @@ abstract contract PrivateUTXOLedgerBase is Test {
         return abi.encode(outputs);
     }
 
+    /// @notice Helper to encode withdrawal publicValues: the transfer fields plus the proven payout
+    function _encodeWithdrawal(PrivateUTXOLedger.PublicOutputs memory outputs, uint256 amount, address recipient)
+        internal
+        pure
+        returns (bytes memory)
+    {
+        return abi.encode(
+            PrivateUTXOLedger.WithdrawalOutputs({
+                chainId: outputs.chainId,
+                oldRoot: outputs.oldRoot,
+                nullifiers: outputs.nullifiers,
+                outputCommitments: outputs.outputCommitments,
+                publicAmount: uint64(amount),
+                recipient: recipient
+            })
+        );
+    }
+
     /// @notice Compute the Merkle root for a single leaf at index 0
     /// @dev This matches MerkleTree.insert() for the first leaf
     function _computeRootForSingleLeaf(bytes32 leaf) internal pure returns (bytes32) {
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "./PrivateUTXOLedger_Base.t.sol";
+
+/// @notice Cross-checks note commitments with the Rust core
+/// @dev Reads core/test-vectors/note_commitments.json, the fixture
+///      core/src/commitment_vectors.rs checks commit() against. Each preimage
+///      is rebuilt with abi.encodePacked and each commitment deposited, so a
+///      byte-order or padding mismatch fails here.
+contract PrivateUTXOLedgerCommitmentVectorsTest is PrivateUTXOLedgerBase {
+    event Deposited(address indexed from, uint256 amount, bytes32 commitment, uint256 leafIndex);
+
+    string internal constant FIXTURE = "/../core/test-vectors/note_commitments.json";
+
+    function _fixture() internal view returns (string memory) {
+        return vm.readFile(string.concat(vm.projectRoot(), FIXTURE));
+    }
+
+    function _path(uint256 i, string memory field) internal pure returns (string memory) {
+        return string.concat(".vectors[", vm.toString(i), "].", field);
+    }
+
+    /// @dev u64 as 8 little-endian bytes, matching Rust's to_le_bytes()
+    function _le64(uint64 value) internal pure returns (bytes8 out) {
+        for (uint256 i = 0; i < 8; i++) {
+            out |= bytes8(bytes1(uint8(value >> (8 * i)))) >> (8 * i);
+        }
+    }
+
+    function testPackedPreimagesMatchRust() public view {
+        string memory json = _fixture();
+        bytes memory domain = vm.parseJsonBytes(json, ".domain");
+        assertEq(domain, bytes("NOTE_COMMITMENT_v1"), "domain separator");
+
+        uint256 count = vm.parseJsonBytes32Array(json, ".vectors[*].commitment").length;
+        assertGt(count, 0, "fixture has no vectors");
+        for (uint256 i = 0; i < count; i++) {
+            uint256 amount = vm.parseJsonUint(json, _path(i, "amount"));
+            assertLe(amount, type(uint64).max, "amount fits u64");
+
+            bytes memory packed = abi.encodePacked(
+                domain,
+                _le64(uint64(amount)),
+                vm.parseJsonBytes32(json, _path(i, "ownerPubkey")),
+                vm.parseJsonBytes32(json, _path(i, "blinding"))
+            );
+            assertEq(packed, vm.parseJsonBytes(json, _path(i, "preimage")), "preimage layout");
+        }
+    }
+
+    function testDepositedCommitmentsMatchRustRoots() public {
+        string memory json = _fixture();
+        bytes32[] memory commitments = vm.parseJsonBytes32Array(json, ".vectors[*].commitment");
+
+        for (uint256 i = 0; i < commitments.length; i++) {
+            PrivateUTXOLedger.OutputCiphertext memory encrypted = PrivateUTXOLedger.OutputCiphertext({
+                commitment: commitments[i],
+                keyType: 0,
+                ephemeralPubkey: new bytes(33),
+                nonce: bytes12(0),
+                ciphertext: new bytes(0)
+            });
+
+            vm.expectEmit(true, false, false, true, address(ledger));
+            emit Deposited(address(this), 1, commitments[i], i);
+            ledger.deposit{value: 1}(commitments[i], encrypted, 0);
+
+            assertEq(ledger.currentRoot(), vm.parseJsonBytes32(json, _path(i, "rootAfter")), "root after deposit");
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "./PrivateUTXOLedger_Base.t.sol";
+import "../src/PublicValuesCompression.sol";
+
+/// @notice Tests for compressed public values (list roots + calldata lists).
+contract PrivateUTXOLedgerCompressionTest is PrivateUTXOLedgerBase {
+    /// @notice Test-side reimplementation of the list root (power-of-two, zero-padded)
+    function _listRoot(bytes32[] memory items) internal pure returns (bytes32) {
+        if (items.length == 0) return bytes32(0);
+        uint256 width = 1;
+        while (width < items.length) width <<= 1;
+        bytes32[] memory level = new bytes32[](width);
+        for (uint256 i = 0; i < items.length; i++) level[i] = items[i];
+        for (; width > 1; width >>= 1) {
+            for (uint256 i = 0; i < width / 2; i++) {
+                level[i] = keccak256(abi.encodePacked(level[2 * i], level[2 * i + 1]));
+            }
+        }
+        return level[0];
+    }
+
+    function _compressed(bytes32 oldRoot, bytes32[] memory nullifiers, bytes32[] memory commitments)
+        internal
+        view
+        returns (bytes memory)
+    {
+        return abi.encode(
+            PublicValuesCompression.CompressedPublicOutputs({
+                chainId: uint64(block.chainid),
+                oldRoot: oldRoot,
+                nullifierCount: uint32(nullifiers.length),
+                nullifiersRoot: _listRoot(nullifiers),
+                commitmentCount: uint32(commitments.length),
+                commitmentsRoot: _listRoot(commitments)
+            })
+        );
+    }
+
+    function _lists(uint256 nNullifiers, uint256 nCommitments)
+        internal
+        pure
+        returns (bytes32[] memory nullifiers, bytes32[] memory commitments)
+    {
+        nullifiers = new bytes32[](nNullifiers);
+        for (uint256 i = 0; i < nNullifiers; i++) nullifiers[i] = keccak256(abi.encode("nf", i));
+        commitments = new bytes32[](nCommitments);
+        for (uint256 i = 0; i < nCommitments; i++) commitments[i] = keccak256(abi.encode("cm", i));
+    }
+
+    function testCompressedSubmitAppliesFullLists() public {
+        (bytes32[] memory nullifiers, bytes32[] memory commitments) = _lists(3, 18);
+        bytes memory publicValues = _compressed(EMPTY_TREE_ROOT, nullifiers, commitments);
+        assertEq(publicValues.length, 192, "compressed public values are fixed size");
+
+        ledger.submitTxCompressed(
+            _dummyEncryptedOutputs(commitments), _dummyProof(), publicValues, nullifiers, commitments
+        );
+
+        for (uint256 i = 0; i < nullifiers.length; i++) {
+            assertTrue(ledger.nullifierUsed(nullifiers[i]), "nullifier should be used");
+        }
+        assertEq(ledger.nextLeafIndex(), commitments.length, "all commitments inserted");
+        assertEq(ledger.currentRoot(), _computeRootForLeaves(commitments), "root should match");
+    }
+
+    function testCompressedSubmitRejectsTamperedCommitments() public {
+        (bytes32[] memory nullifiers, bytes32[] memory commitments) = _lists(2, 17);
+        bytes memory publicValues = _compressed(EMPTY_TREE_ROOT, nullifiers, commitments);
+
+        commitments[5] = keccak256("injected");
+        PrivateUTXOLedger.OutputCiphertext[] memory encrypted = _dummyEncryptedOutputs(commitments);
+        vm.expectRevert("Commitments root mismatch");
+        ledger.submitTxCompressed(encrypted, _dummyProof(), publicValues, nullifiers, commitments);
+    }
+
+    function testCompressedSubmitRejectsDroppedNullifier() public {
+        (bytes32[] memory nullifiers, bytes32[] memory commitments) = _lists(2, 17);
+        bytes memory publicValues = _compressed(EMPTY_TREE_ROOT, nullifiers, commitments);
+
+        bytes32[] memory fewer = new bytes32[](1);
+        fewer[0] = nullifiers[0];
+        PrivateUTXOLedger.OutputCiphertext[] memory encrypted = _dummyEncryptedOutputs(commitments);
+        vm.expectRevert("Nullifier count mismatch");
+        ledger.submitTxCompressed(encrypted, _dummyProof(), publicValues, fewer, commitments);
+    }
+
+    function testCompressedSubmitRejectsFullEncoding() public {
+        (bytes32[] memory nullifiers, bytes32[] memory commitments) = _lists(1, 1);
+        bytes memory full = _encodePublicValues(_buildOutputs(EMPTY_TREE_ROOT, bytes32(0), nullifiers, commitments));
+
+        PrivateUTXOLedger.OutputCiphertext[] memory encrypted = _dummyEncryptedOutputs(commitments);
+        vm.expectRevert("Not compressed public values");
+        ledger.submitTxCompressed(encrypted, _dummyProof(), full, nullifiers, commitments);
+    }
+}
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "./PrivateUTXOLedger_Base.t.sol";
+
+/// @notice Cross-checks the Rust replica of the incremental tree
+/// @dev Reads core/test-vectors/contract_tree_roots.json, the fixture
+///      MerkleTree::insert_like_contract is tested against in core/src/merkle.rs.
+///      Each commitment is deposited in order and the index, RootUpdated event
+///      and currentRoot compared, so a change to insertion order or to the
+///      zero-leaf convention fails on whichever side drifted.
+contract PrivateUTXOLedgerContractTreeTest is PrivateUTXOLedgerBase {
+    event RootUpdated(bytes32 indexed oldRoot, bytes32 indexed newRoot);
+    event Deposited(address indexed from, uint256 amount, bytes32 commitment, uint256 leafIndex);
+
+    string internal constant FIXTURE = "/../core/test-vectors/contract_tree_roots.json";
+
+    function _path(uint256 i, string memory field) internal pure returns (string memory) {
+        return string.concat(".insertions[", vm.toString(i), "].", field);
+    }
+
+    function testInsertionsMatchRustReplica() public {
+        string memory json = vm.readFile(string.concat(vm.projectRoot(), FIXTURE));
+        bytes32[] memory commitments = vm.parseJsonBytes32Array(json, ".insertions[*].commitment");
+        assertGt(commitments.length, 0, "fixture has no insertions");
+
+        bytes32 oldRoot = vm.parseJsonBytes32(json, ".emptyRoot");
+        assertEq(ledger.currentRoot(), oldRoot, "empty root");
+
+        for (uint256 i = 0; i < commitments.length; i++) {
+            bytes32 rootAfter = vm.parseJsonBytes32(json, _path(i, "rootAfter"));
+            uint256 leafIndex = vm.parseJsonUint(json, _path(i, "leafIndex"));
+
+            PrivateUTXOLedger.OutputCiphertext memory encrypted = PrivateUTXOLedger.OutputCiphertext({
+                commitment: commitments[i],
+                keyType: 0,
+                ephemeralPubkey: new bytes(33),
+                nonce: bytes12(0),
+                ciphertext: new bytes(0)
+            });
+
+            vm.expectEmit(true, true, false, false, address(ledger));
+            emit RootUpdated(oldRoot, rootAfter);
+            vm.expectEmit(true, false, false, true, address(ledger));
+            emit Deposited(address(this), 1, commitments[i], leafIndex);
+            ledger.deposit{value: 1}(commitments[i], encrypted, 0);
+
+            assertEq(ledger.currentRoot(), rootAfter, "root after insertion");
+            assertTrue(ledger.validRoots(rootAfter), "root is known");
+            oldRoot = rootAfter;
+        }
+    }
+}
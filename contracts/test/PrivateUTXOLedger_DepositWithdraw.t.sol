@@ -79,7 +79,7 @@ contract PrivateUTXOLedgerDepositWithdrawTest is PrivateUTXOLedgerBase {
         emit Withdrawn(recipient, withdrawAmount);
         
         // Withdraw - SECURITY FIX: outputs now passed via publicValues
-        bytes memory publicValues = _encodePublicValues(outputs);
+        bytes memory publicValues = _encodeWithdrawal(outputs, withdrawAmount, recipient);
         PrivateUTXOLedger.OutputCiphertext[] memory emptyOutputs = new PrivateUTXOLedger.OutputCiphertext[](0);
         ledger.withdraw(recipient, withdrawAmount, _dummyProof(), publicValues, emptyOutputs);
 
@@ -113,11 +113,76 @@ contract PrivateUTXOLedgerDepositWithdrawTest is PrivateUTXOLedgerBase {
             new bytes32[](0)
         );
         
-        bytes memory publicValues = _encodePublicValues(outputs);
+        bytes memory publicValues = _encodeWithdrawal(outputs, 2 ether, address(0x123));
         PrivateUTXOLedger.OutputCiphertext[] memory emptyOutputs = new PrivateUTXOLedger.OutputCiphertext[](0);
         vm.expectRevert(bytes("Insufficient contract balance"));
         ledger.withdraw(address(0x123), 2 ether, _dummyProof(), publicValues, emptyOutputs);
     }
+
+    /// @notice Test the payout is the one proven, and withdrawals can't be submitted as transfers
+    function testWithdrawMustMatchProvenPayout() public {
+        bytes32 commitment = keccak256("deposit");
+        ledger.deposit{value: 2 ether}(commitment, _dummyEncryptedOutputs(_single(commitment))[0], 0);
+
+        bytes32[] memory nullifiers = _single(keccak256("spend-nullifier"));
+        PrivateUTXOLedger.PublicOutputs memory outputs =
+            _buildOutputs(ledger.currentRoot(), bytes32(0), nullifiers, new bytes32[](0));
+        bytes memory publicValues = _encodeWithdrawal(outputs, 1 ether, address(0x123));
+        PrivateUTXOLedger.OutputCiphertext[] memory emptyOutputs = _emptyEncryptedOutputs();
+
+        vm.expectRevert(bytes("Recipient mismatch"));
+        ledger.withdraw(address(0x456), 1 ether, _dummyProof(), publicValues, emptyOutputs);
+
+        vm.expectRevert(bytes("Amount mismatch"));
+        ledger.withdraw(address(0x123), 2 ether, _dummyProof(), publicValues, emptyOutputs);
+
+        vm.expectRevert(bytes("Not a withdrawal proof"));
+        ledger.withdraw(address(0x123), 1 ether, _dummyProof(), _encodePublicValues(outputs), emptyOutputs);
+
+        // Burning the nullifiers without the payout would lose the owner's funds
+        vm.expectRevert(bytes("Withdrawal proof; use withdraw"));
+        ledger.submitTx(emptyOutputs, _dummyProof(), publicValues);
+
+        assertFalse(ledger.nullifierUsed(nullifiers[0]), "Nullifier should be unspent");
+    }
+
+    /// @notice Test a hidden-amounts trailer must cover the payout, with one commitment per output
+    function testWithdrawChecksHiddenAmounts() public {
+        bytes32 commitment = keccak256("deposit");
+        ledger.deposit{value: 2 ether}(commitment, _dummyEncryptedOutputs(_single(commitment))[0], 0);
+
+        bytes32[] memory nullifiers = _single(keccak256("spend-nullifier"));
+        PrivateUTXOLedger.PublicOutputs memory outputs =
+            _buildOutputs(ledger.currentRoot(), bytes32(0), nullifiers, new bytes32[](0));
+        bytes memory publicValues = _encodeWithdrawal(outputs, 1 ether, address(0x123));
+        PrivateUTXOLedger.OutputCiphertext[] memory emptyOutputs = _emptyEncryptedOutputs();
+
+        vm.expectRevert(bytes("Hidden amounts below payout"));
+        ledger.withdraw(address(0x123), 1 ether, _dummyProof(), _withHiddenAmounts(publicValues, 0.5 ether, 0), emptyOutputs);
+
+        vm.expectRevert(bytes("Hidden amounts count mismatch"));
+        ledger.withdraw(address(0x123), 1 ether, _dummyProof(), _withHiddenAmounts(publicValues, 1 ether, 1), emptyOutputs);
+
+        // The net public amount also covers the fee
+        ledger.withdraw(address(0x123), 1 ether, _dummyProof(), _withHiddenAmounts(publicValues, 1.1 ether, 0), emptyOutputs);
+        assertTrue(ledger.nullifierUsed(nullifiers[0]), "Nullifier should be marked used");
+    }
+
+    /// @dev Append a trailer with a hidden-amounts section (see core `public_values::encode_trailer`)
+    function _withHiddenAmounts(bytes memory publicValues, uint64 net, uint32 count)
+        internal
+        pure
+        returns (bytes memory)
+    {
+        bytes memory body = abi.encodePacked(net, count, new bytes(uint256(count) * 33));
+        bytes memory section = abi.encodePacked(uint8(1), uint32(body.length), body);
+        return abi.encodePacked(publicValues, section, bytes28("ghostclaw-pv-trailer-v1"), uint32(section.length));
+    }
+
+    function _single(bytes32 value) internal pure returns (bytes32[] memory values) {
+        values = new bytes32[](1);
+        values[0] = value;
+    }
     
     /// @notice Test deposit must include ETH
     function testDepositRequiresETH() public {
@@ -43,7 +43,7 @@ contract PrivateUTXOLedgerEncryptionTest is PrivateUTXOLedgerBase {
         });
 
         // Create ledger with mock verifier
-        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
 
         // Expect OutputCommitted event (note: leafIndex is now included)
         vm.expectEmit(true, false, false, true, address(testLedger));
@@ -67,4 +67,13 @@ contract PrivateUTXOLedgerEventsAndViewsTest is PrivateUTXOLedgerBase {
         bytes memory publicValues = _encodePublicValues(outputs);
         ledger.submitTx(_dummyEncryptedOutputs(commitments), _dummyProof(), publicValues);
     }
-}
\ No newline at end of file
+
+    function testProgramVKeySetAtDeployment() public view {
+        assertEq(ledger.UTXO_PROGRAM_VKEY(), TEST_PROGRAM_VKEY, "vkey should be the constructor argument");
+    }
+
+    function testZeroProgramVKeyRejected() public {
+        vm.expectRevert("Program vkey required");
+        new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), bytes32(0));
+    }
+}
@@ -24,7 +24,7 @@ contract PrivateUTXOLedgerIntegrationTest is PrivateUTXOLedgerBase {
             _buildOutputs(oldRoot, newRoot, nullifiers, commitments);
 
         // Create ledger starting from empty tree, with mock verifier
-        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
 
         // Submit transaction
         bytes memory publicValues = _encodePublicValues(outputs);
@@ -42,7 +42,7 @@ contract PrivateUTXOLedgerMerkleTest is PrivateUTXOLedgerBase {
         }
 
         // Create fresh ledger and insert via submitTx
-        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
 
         bytes32[] memory nullifiers = new bytes32[](0);
         bytes32[] memory commitments = new bytes32[](1);
@@ -82,7 +82,7 @@ contract PrivateUTXOLedgerMerkleTest is PrivateUTXOLedgerBase {
         }
 
         // First insert leaf1
-        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
 
         bytes32 rootAfterLeaf1 = _computeRootForSingleLeaf(leaf1);
 
@@ -162,7 +162,7 @@ contract PrivateUTXOLedgerMerkleTest is PrivateUTXOLedgerBase {
 
     /// @notice Test multiple sequential inserts
     function testMultipleSequentialInserts() public {
-        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger testLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
 
         bytes32[] memory leaves = new bytes32[](4);
         leaves[0] = keccak256("leaf-0");
@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "./PrivateUTXOLedger_Base.t.sol";
+import {SP1Verifier} from "@sp1-contracts/v5.0.0/SP1VerifierGroth16.sol";
+
+/// @notice Replays real prover output against the ledger
+/// @dev Reads every JSON file in test/fixtures/prover/, as written by
+///      `sp1-host --emit-foundry-fixture test/fixtures/prover`. Each proof is
+///      checked by the SP1 Groth16 verifier (the mock verifier for fixtures
+///      flagged `mock`) under its own vkey, then the ledger transition is
+///      checked: nullifiers marked used, commitments appended in order, a
+///      withdrawal paid. The fixture's old root is marked valid directly,
+///      since the tree that produced it isn't part of the fixture.
+contract PrivateUTXOLedgerProverFixturesTest is PrivateUTXOLedgerBase {
+    using stdStorage for StdStorage;
+
+    string internal constant FIXTURE_DIR = "/test/fixtures/prover";
+
+    function _isJson(string memory path) internal pure returns (bool) {
+        bytes memory b = bytes(path);
+        return b.length > 5 && b[b.length - 5] == "." && b[b.length - 4] == "j" && b[b.length - 3] == "s"
+            && b[b.length - 2] == "o" && b[b.length - 1] == "n";
+    }
+
+    function testProverFixturesApply() public {
+        Vm.DirEntry[] memory entries = vm.readDir(string.concat(vm.projectRoot(), FIXTURE_DIR));
+        uint256 replayed;
+        for (uint256 i = 0; i < entries.length; i++) {
+            if (entries[i].isDir || !_isJson(entries[i].path)) continue;
+            _replay(vm.readFile(entries[i].path));
+            replayed++;
+        }
+        emit log_named_uint("prover fixtures replayed", replayed);
+        // An empty or unreadable directory must not pass silently
+        assertGt(replayed, 0, "no prover fixtures in test/fixtures/prover");
+    }
+
+    function _replay(string memory json) internal {
+        uint256 snapshot = vm.snapshotState();
+        string memory name = vm.parseJsonString(json, ".name");
+        vm.chainId(vm.parseJsonUint(json, ".chainId"));
+
+        address verifier = vm.parseJsonBool(json, ".mock") ? address(mockVerifier) : address(new SP1Verifier());
+        PrivateUTXOLedger fixtureLedger =
+            new PrivateUTXOLedger(address(0), verifier, address(0), vm.parseJsonBytes32(json, ".vkeyHash"));
+
+        uint256 withdrawalAmount = vm.parseJsonUint(json, ".withdrawalAmount");
+        if (withdrawalAmount > 0) {
+            // Fund the pool so the payout can be made
+            PrivateUTXOLedger.OutputCiphertext memory funding = _dummyEncryptedOutputs(_one(keccak256("fixture-funding")))[0];
+            fixtureLedger.deposit{value: withdrawalAmount}(funding.commitment, funding, 0);
+        }
+        bytes32 oldRoot = vm.parseJsonBytes32(json, ".oldRoot");
+        stdstore.target(address(fixtureLedger)).sig("validRoots(bytes32)").with_key(oldRoot).checked_write(true);
+
+        bytes32[] memory nullifiers = vm.parseJsonBytes32Array(json, ".nullifiers");
+        bytes32[] memory commitments = vm.parseJsonBytes32Array(json, ".outputCommitments");
+        for (uint256 i = 0; i < nullifiers.length; i++) {
+            assertFalse(fixtureLedger.nullifierUsed(nullifiers[i]), string.concat(name, ": nullifier used before"));
+        }
+        uint256 leavesBefore = fixtureLedger.nextLeafIndex();
+
+        bytes memory proof = vm.parseJsonBytes(json, ".proof");
+        bytes memory publicValues = vm.parseJsonBytes(json, ".publicValues");
+        PrivateUTXOLedger.OutputCiphertext[] memory encrypted = _dummyEncryptedOutputs(commitments);
+        if (withdrawalAmount > 0) {
+            address recipient = vm.parseJsonAddress(json, ".withdrawalRecipient");
+            uint256 balanceBefore = recipient.balance;
+            fixtureLedger.withdraw(recipient, withdrawalAmount, proof, publicValues, encrypted);
+            assertEq(recipient.balance, balanceBefore + withdrawalAmount, string.concat(name, ": payout"));
+        } else if (vm.parseJsonBool(json, ".compressed")) {
+            fixtureLedger.submitTxCompressed(encrypted, proof, publicValues, nullifiers, commitments);
+        } else {
+            fixtureLedger.submitTx(encrypted, proof, publicValues);
+        }
+
+        for (uint256 i = 0; i < nullifiers.length; i++) {
+            assertTrue(fixtureLedger.nullifierUsed(nullifiers[i]), string.concat(name, ": nullifier not marked used"));
+        }
+        assertEq(fixtureLedger.nextLeafIndex(), leavesBefore + commitments.length, string.concat(name, ": leaves appended"));
+        assertTrue(fixtureLedger.validRoots(fixtureLedger.currentRoot()), string.concat(name, ": new root recorded"));
+        vm.revertToState(snapshot);
+    }
+
+    function _one(bytes32 value) internal pure returns (bytes32[] memory values) {
+        values = new bytes32[](1);
+        values[0] = value;
+    }
+}
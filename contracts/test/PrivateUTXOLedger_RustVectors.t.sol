@@ -20,12 +20,13 @@ contract PrivateUTXOLedgerRustVectorsTest is PrivateUTXOLedgerBase {
         bytes32 newRoot = _computeRootForSingleLeaf(commitments[0]);
 
         PrivateUTXOLedger.PublicOutputs memory outputs;
+        outputs.chainId = uint64(block.chainid);
         outputs.oldRoot = oldRoot;
         outputs.nullifiers = nullifiers;
         outputs.outputCommitments = commitments;
 
         // Create ledger with mock verifier
-        PrivateUTXOLedger rustLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0));
+        PrivateUTXOLedger rustLedger = new PrivateUTXOLedger(address(0), address(mockVerifier), address(0), TEST_PROGRAM_VKEY);
 
         // Apply the transition
         bytes memory publicValues = _encodePublicValues(outputs);
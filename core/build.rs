@@ -0,0 +1,37 @@
+//! Hashes the crate's sources so the prover host can tell whether its
+//! embedded guest ELF was built from them (see `guest_elf`).
+
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = Vec::new();
+    collect(&root.join("src"), &mut files);
+    files.sort();
+
+    // Relative path and contents of every file, NUL-separated
+    let mut hasher = Sha256::new();
+    for file in &files {
+        let relative = file.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+        hasher.update(relative.as_bytes());
+        hasher.update([0]);
+        hasher.update(std::fs::read(file).unwrap());
+        hasher.update([0]);
+    }
+    let hash: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    println!("cargo:rustc-env=GHOSTCLAW_CORE_SOURCE_SHA256={}", hash);
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use utxo_prototype::encryption::{encrypt_note, generate_keypair, decrypt_note};
+use ghostclaw_core::encryption::{encrypt_note, generate_keypair, decrypt_note};
 
 fn main() {
     println!("=== Testing Real Encryption ===\n");
@@ -0,0 +1,294 @@
+//! Pedersen amount commitments (hidden-amounts mode)
+//!
+//! A note's amount is in the clear for anyone holding the note: the prover,
+//! a relayer that sees the request, a recipient who later forwards it. In
+//! hidden-amounts mode each output additionally gets a Pedersen commitment
+//! `C = amount·H + r·G` on secp256k1, where `H` is a generator with no known
+//! discrete log relative to `G` and `r` is the amount blinding: a separate
+//! secret the output's creator draws at random and sends the recipient with
+//! the note. It isn't derived from the note's blinding, so the two
+//! commitments share no secret and opening one (a note handed to a prover,
+//! a recovered wallet) doesn't open the other. The witness carries one per
+//! input and output (`Witness::input_amount_blindings`). Commitments are
+//! additively homomorphic, so
+//!
+//! `Σ C_in - Σ C_out - public·H = (Σ r_in - Σ r_out)·G`
+//!
+//! holds exactly when inputs and outputs balance up to the public amount
+//! (fee or withdrawal). Anyone holding the input commitments and the excess
+//! blinding can check a transaction's balance without learning any amount.
+//!
+//! The guest (`hidden-amounts` feature) computes the output commitments from
+//! the note openings, so each one provably opens to a `u64`: that is the
+//! range proof, and it rules out a negative amount wrapping around the group
+//! order. Only the net public amount is revealed, in a tagged trailer
+//! section after the ABI public values (see `public_values`). The ledger's
+//! `withdraw` requires the net to cover the proven payout (the rest is the
+//! fee) and one commitment per output.
+
+use blake3::Hasher;
+use k256::elliptic_curve::group::GroupEncoding;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::sec1::FromEncodedPoint;
+use k256::{AffinePoint, EncodedPoint, ProjectivePoint, Scalar, U256};
+
+use crate::note::Note;
+use crate::sp1_types::Witness;
+
+const VALUE_GENERATOR_DOMAIN: &[u8] = b"GHOSTCLAW_PEDERSEN_H_v1";
+
+/// A compressed secp256k1 point committing to an amount.
+pub type AmountCommitment = [u8; 33];
+
+/// Secret blinding an amount commitment, reduced mod the group order.
+pub type AmountBlinding = [u8; 32];
+
+/// The value generator `H`: the first x-coordinate on the curve obtained by
+/// hashing the domain with a counter (even y). Nobody knows `log_G(H)`.
+pub fn value_generator() -> ProjectivePoint {
+    for counter in 0u32.. {
+        let mut hasher = Hasher::new();
+        hasher.update(VALUE_GENERATOR_DOMAIN);
+        hasher.update(&counter.to_le_bytes());
+        let mut encoded = [0u8; 33];
+        encoded[0] = 0x02;
+        encoded[1..].copy_from_slice(hasher.finalize().as_bytes());
+        let Ok(point) = EncodedPoint::from_bytes(encoded) else { continue };
+        if let Some(point) = Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&point)) {
+            return point.into();
+        }
+    }
+    unreachable!("half of all x-coordinates are on the curve")
+}
+
+fn blinding_scalar(blinding: &AmountBlinding) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(blinding.into())
+}
+
+/// `amount·h + r·G`; `h` is `value_generator()`, which hashes to the curve,
+/// so callers committing several amounts compute it once.
+fn commitment_point(h: &ProjectivePoint, amount: u64, blinding: &Scalar) -> ProjectivePoint {
+    *h * Scalar::from(amount) + ProjectivePoint::GENERATOR * blinding
+}
+
+fn encode(point: &ProjectivePoint) -> AmountCommitment {
+    point.to_affine().to_bytes().into()
+}
+
+fn decode(commitment: &AmountCommitment) -> Result<ProjectivePoint, String> {
+    let point = EncodedPoint::from_bytes(commitment).map_err(|e| format!("Invalid amount commitment: {}", e))?;
+    Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&point))
+        .map(ProjectivePoint::from)
+        .ok_or_else(|| "Amount commitment is not a curve point".to_string())
+}
+
+/// `amount·H + r·G`.
+pub fn commit_amount(amount: u64, blinding: &AmountBlinding) -> AmountCommitment {
+    commit_amount_with(&value_generator(), amount, blinding)
+}
+
+fn commit_amount_with(h: &ProjectivePoint, amount: u64, blinding: &AmountBlinding) -> AmountCommitment {
+    encode(&commitment_point(h, amount, &blinding_scalar(blinding)))
+}
+
+/// Whether `commitment` opens to `amount` under `blinding`.
+pub fn verify_opening(commitment: &AmountCommitment, amount: u64, blinding: &AmountBlinding) -> bool {
+    commit_amount(amount, blinding) == *commitment
+}
+
+/// What hidden-amounts mode reveals about a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HiddenAmounts {
+    /// One per output, in output order
+    pub amount_commitments: Vec<AmountCommitment>,
+    /// Value leaving the shielded pool: inputs minus outputs (fee or withdrawal)
+    pub public_amount: u64,
+    /// `Σ r_in - Σ r_out`, for whoever checks the balance with the input
+    /// commitments (wallet audits); never committed publicly
+    pub excess_blinding: [u8; 32],
+}
+
+/// Compute the output amount commitments and public amount for `witness`,
+/// checking the balance equation on the openings.
+///
+/// # Errors
+/// Fails if outputs exceed inputs (the public amount would be negative), or
+/// the witness lacks an amount blinding for an input or output.
+pub fn hidden_amounts(witness: &Witness) -> Result<HiddenAmounts, String> {
+    witness.validate_value_conservation()?;
+    if witness.input_amount_blindings.len() != witness.input_notes.len()
+        || witness.output_amount_blindings.len() != witness.output_notes.len()
+    {
+        return Err(format!(
+            "Hidden amounts need an amount blinding per input and output: {} for {} inputs, {} for {} outputs",
+            witness.input_amount_blindings.len(),
+            witness.input_notes.len(),
+            witness.output_amount_blindings.len(),
+            witness.output_notes.len()
+        ));
+    }
+    let public_amount = witness.total_input_value() - witness.total_output_value();
+
+    let sum = |blindings: &[AmountBlinding]| blindings.iter().map(blinding_scalar).fold(Scalar::ZERO, |a, b| a + b);
+    let excess = sum(&witness.input_amount_blindings) - sum(&witness.output_amount_blindings);
+
+    let h = value_generator();
+    let commit_all = |notes: &[Note], blindings: &[AmountBlinding]| -> Vec<AmountCommitment> {
+        notes.iter().zip(blindings).map(|(note, r)| commit_amount_with(&h, note.amount, r)).collect()
+    };
+    let inputs = commit_all(&witness.input_notes, &witness.input_amount_blindings);
+    let amount_commitments = commit_all(&witness.output_notes, &witness.output_amount_blindings);
+    let excess_blinding: [u8; 32] = excess.to_bytes().into();
+    if !verify_balance_with(&h, &inputs, &amount_commitments, public_amount, &excess_blinding)? {
+        return Err("Amount commitments do not balance".to_string());
+    }
+
+    Ok(HiddenAmounts { amount_commitments, public_amount, excess_blinding })
+}
+
+/// Check `Σ inputs - Σ outputs - public_amount·H == excess·G`.
+///
+/// # Errors
+/// Fails if a commitment isn't a valid point.
+pub fn verify_balance(
+    inputs: &[AmountCommitment],
+    outputs: &[AmountCommitment],
+    public_amount: u64,
+    excess_blinding: &[u8; 32],
+) -> Result<bool, String> {
+    verify_balance_with(&value_generator(), inputs, outputs, public_amount, excess_blinding)
+}
+
+fn verify_balance_with(
+    h: &ProjectivePoint,
+    inputs: &[AmountCommitment],
+    outputs: &[AmountCommitment],
+    public_amount: u64,
+    excess_blinding: &[u8; 32],
+) -> Result<bool, String> {
+    let mut sum = ProjectivePoint::IDENTITY;
+    for commitment in inputs {
+        sum += decode(commitment)?;
+    }
+    for commitment in outputs {
+        sum -= decode(commitment)?;
+    }
+    sum -= *h * Scalar::from(public_amount);
+    Ok(sum == ProjectivePoint::GENERATOR * blinding_scalar(excess_blinding))
+}
+
+/// Encode the public part of `hidden` (public amount and output amount
+/// commitments) as the guest commits it, in the
+/// `public_values::SECTION_HIDDEN_AMOUNTS` trailer section:
+/// `public_amount (u64 BE) || count (u32 BE) || count × 33-byte commitment`.
+pub fn encode_hidden_amounts(hidden: &HiddenAmounts) -> Vec<u8> {
+    let mut out = Vec::with_capacity(12 + hidden.amount_commitments.len() * 33);
+    out.extend_from_slice(&hidden.public_amount.to_be_bytes());
+    out.extend_from_slice(&(hidden.amount_commitments.len() as u32).to_be_bytes());
+    for commitment in &hidden.amount_commitments {
+        out.extend_from_slice(commitment);
+    }
+    out
+}
+
+/// Decode `encode_hidden_amounts` output into the public amount and the
+/// output amount commitments.
+///
+/// # Errors
+/// Fails on a truncated or over-long encoding.
+pub fn decode_hidden_amounts(bytes: &[u8]) -> Result<(u64, Vec<AmountCommitment>), String> {
+    if bytes.len() < 12 {
+        return Err(format!("Hidden amounts section too short: {} bytes", bytes.len()));
+    }
+    let public_amount = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+    let count = u32::from_be_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    let body = &bytes[12..];
+    if body.len() != count * 33 {
+        return Err(format!("Hidden amounts section has {} bytes for {} commitments", body.len(), count));
+    }
+    let commitments = body.chunks_exact(33).map(|chunk| chunk.try_into().unwrap()).collect();
+    Ok((public_amount, commitments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn witness(inputs: &[u64], outputs: &[u64]) -> Witness {
+        let notes = |amounts: &[u64], salt: u8| -> Vec<Note> {
+            amounts.iter().enumerate().map(|(i, &a)| Note::new(a, [7; 32], [salt + i as u8; 32])).collect()
+        };
+        let blindings = |count: usize, salt: u8| -> Vec<AmountBlinding> { (0..count).map(|i| [salt + i as u8; 32]).collect() };
+        let sigs = vec![vec![0u8; 65]; inputs.len()];
+        let mut witness =
+            Witness::new_without_proofs(notes(inputs, 1), (0..inputs.len()).collect(), sigs.clone(), sigs, notes(outputs, 100));
+        witness.input_amount_blindings = blindings(inputs.len(), 50);
+        witness.output_amount_blindings = blindings(outputs.len(), 150);
+        witness
+    }
+
+    #[test]
+    fn test_value_generator_is_independent_of_g() {
+        let h = value_generator();
+        assert_eq!(h, value_generator());
+        assert_ne!(h, ProjectivePoint::GENERATOR);
+        assert_ne!(h, ProjectivePoint::IDENTITY);
+    }
+
+    #[test]
+    fn test_commitments_hide_and_bind_amounts() {
+        let commitment = commit_amount(500, &[1; 32]);
+        assert!(verify_opening(&commitment, 500, &[1; 32]));
+
+        // Same amount, different blinding: unrelated commitment
+        assert_ne!(commit_amount(500, &[2; 32]), commitment);
+        // Same blinding, different amount: doesn't open
+        assert!(!verify_opening(&commitment, 501, &[1; 32]));
+    }
+
+    #[test]
+    fn test_balance_holds_for_conserving_transactions() {
+        let hidden = hidden_amounts(&witness(&[600, 400], &[700, 250])).unwrap();
+        assert_eq!(hidden.public_amount, 50);
+        assert_eq!(hidden.amount_commitments.len(), 2);
+
+        let w = witness(&[600, 400], &[700, 250]);
+        let inputs: Vec<AmountCommitment> =
+            w.input_notes.iter().zip(&w.input_amount_blindings).map(|(n, r)| commit_amount(n.amount, r)).collect();
+        assert!(verify_balance(&inputs, &hidden.amount_commitments, 50, &hidden.excess_blinding).unwrap());
+        // Claiming a different public amount breaks the equation
+        assert!(!verify_balance(&inputs, &hidden.amount_commitments, 49, &hidden.excess_blinding).unwrap());
+        assert!(!verify_balance(&inputs, &hidden.amount_commitments[..1], 50, &hidden.excess_blinding).unwrap());
+    }
+
+    #[test]
+    fn test_hidden_amounts_encoding_roundtrip() {
+        let hidden = hidden_amounts(&witness(&[1000], &[400, 590])).unwrap();
+        let encoded = encode_hidden_amounts(&hidden);
+        assert_eq!(encoded.len(), 12 + 2 * 33);
+        assert_eq!(decode_hidden_amounts(&encoded).unwrap(), (10, hidden.amount_commitments));
+        assert!(decode_hidden_amounts(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_overspend_has_no_hidden_amounts() {
+        assert!(hidden_amounts(&witness(&[100], &[60, 50])).is_err());
+        assert!(decode(&[0u8; 33]).is_err());
+    }
+
+    #[test]
+    fn test_amount_blindings_are_independent_of_note_blindings() {
+        let mut w = witness(&[1000], &[400, 590]);
+        let hidden = hidden_amounts(&w).unwrap();
+
+        // Same notes, other amount blindings: other commitments
+        w.output_amount_blindings[0] = [0xee; 32];
+        assert_ne!(hidden_amounts(&w).unwrap().amount_commitments[0], hidden.amount_commitments[0]);
+        // The note's own blinding doesn't open its amount commitment
+        let note = &w.output_notes[1];
+        assert!(!verify_opening(&hidden.amount_commitments[1], note.amount, &note.blinding));
+
+        w.output_amount_blindings.pop();
+        assert!(hidden_amounts(&w).unwrap_err().contains("amount blinding"));
+    }
+}
@@ -0,0 +1,333 @@
+//! Proof of balance for credit checks
+//!
+//! A lender or KYC partner wants to know that an owner controls at least
+//! `threshold` in unspent notes, without learning which notes they are. The
+//! `balance-proof` guest checks a `BalanceWitness` with
+//! `verify_balance_witness` and commits only the `BalanceStatement`: the
+//! root, the threshold, the verifier's challenge, the owner and a hash of the
+//! spent-nullifier set the notes were checked against.
+//!
+//! A note counts if it is in the tree at `root`, its nullifier signature is
+//! by the owner, and that nullifier isn't in `spent_nullifiers`. The verifier
+//! must check that `root` is a root the ledger knows and that
+//! `spent_nullifiers_hash` matches the ledger's `NullifierUsed` events up to
+//! that root; otherwise a spent note would still count. As for spends,
+//! unspentness relies on the owner's nullifier signatures being
+//! deterministic (RFC 6979, low-s): a second valid signature over the same
+//! note would give a nullifier the ledger hasn't seen.
+//!
+//! The owner also signs `balance_challenge_message`, so a proof answers one
+//! verifier's challenge and can't be replayed by whoever holds the notes'
+//! nullifier signatures.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::ledger::recover_ethereum_key;
+use crate::merkle::{MerkleProof, MerkleTree};
+use crate::note::{commit, compute_nullifier, Note};
+use crate::signatures::{is_canonical_nullifier_signature, nullifier_message};
+
+/// Domain separator for the ownership signature.
+const BALANCE_PROOF_DOMAIN: &[u8] = b"ghostclaw-balance-proof-v1";
+
+/// Private input to the `balance-proof` guest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceWitness {
+    /// Root the notes are proven against
+    pub root: [u8; 32],
+    /// Minimum total the notes must reach
+    pub threshold: u128,
+    /// Verifier-chosen nonce
+    pub challenge: [u8; 32],
+    pub notes: Vec<Note>,
+    pub proofs: Vec<MerkleProof>,
+    /// One per note, over `nullifier_message(commitment)`
+    pub nullifier_signatures: Vec<Vec<u8>>,
+    /// Over `balance_challenge_message(root, threshold, challenge)`
+    pub ownership_signature: Vec<u8>,
+    /// Nullifiers spent on-chain as of `root`, strictly ascending
+    pub spent_nullifiers: Vec<[u8; 32]>,
+}
+
+/// What a balance proof establishes; committed by the guest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceStatement {
+    pub root: [u8; 32],
+    pub threshold: u128,
+    pub challenge: [u8; 32],
+    pub owner: [u8; 32],
+    pub spent_nullifiers_hash: [u8; 32],
+}
+
+/// Message the owner signs to answer `challenge`:
+/// Keccak256(domain || root || threshold (u128 BE) || challenge).
+pub fn balance_challenge_message(root: &[u8; 32], threshold: u128, challenge: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(BALANCE_PROOF_DOMAIN);
+    hasher.update(root);
+    hasher.update(threshold.to_be_bytes());
+    hasher.update(challenge);
+    hasher.finalize().into()
+}
+
+/// Keccak256 over a sorted spent-nullifier list, for the verifier to compare
+/// with its own view of the ledger.
+pub fn spent_nullifiers_hash(sorted_nullifiers: &[[u8; 32]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for nullifier in sorted_nullifiers {
+        hasher.update(nullifier);
+    }
+    hasher.finalize().into()
+}
+
+/// Check a balance witness (the guest's logic).
+///
+/// # Errors
+/// Fails on mismatched lengths, an unsorted spent list, notes with different
+/// owners, a note not under `root`, a bad or spent nullifier, a note counted
+/// twice, or a total below the threshold.
+pub fn verify_balance_witness(witness: &BalanceWitness) -> Result<BalanceStatement, String> {
+    let count = witness.notes.len();
+    if count == 0 {
+        return Err("No notes".to_string());
+    }
+    if witness.proofs.len() != count || witness.nullifier_signatures.len() != count {
+        return Err(format!(
+            "{} notes, {} proofs, {} nullifier signatures",
+            count,
+            witness.proofs.len(),
+            witness.nullifier_signatures.len()
+        ));
+    }
+    if !witness.spent_nullifiers.windows(2).all(|pair| pair[0] < pair[1]) {
+        return Err("Spent nullifiers must be strictly ascending".to_string());
+    }
+
+    let owner = witness.notes[0].owner_pubkey;
+    let message = balance_challenge_message(&witness.root, witness.threshold, &witness.challenge);
+    match recover_ethereum_key(&message, &witness.ownership_signature) {
+        Ok(signer) if signer == owner => {}
+        Ok(_) => return Err("Ownership signature is not from the note owner".to_string()),
+        Err(e) => return Err(format!("Ownership signature recovery failed: {}", e)),
+    }
+
+    let mut nullifiers: Vec<[u8; 32]> = Vec::with_capacity(count);
+    let mut total: u128 = 0;
+    for (i, ((note, proof), signature)) in witness
+        .notes
+        .iter()
+        .zip(&witness.proofs)
+        .zip(&witness.nullifier_signatures)
+        .enumerate()
+    {
+        if note.owner_pubkey != owner {
+            return Err(format!("Note {} has a different owner", i));
+        }
+        let commitment = commit(note);
+        if !MerkleTree::verify_proof(commitment, proof, witness.root) {
+            return Err(format!("Note {} is not in the tree at the root", i));
+        }
+
+        // Any other encoding would hash to a nullifier outside the spent set
+        if !is_canonical_nullifier_signature(signature) {
+            return Err(format!("Nullifier signature {} is not canonical (low-s, v = 27 or 28)", i));
+        }
+        match recover_ethereum_key(&nullifier_message(&commitment), signature) {
+            Ok(signer) if signer == owner => {}
+            Ok(_) => return Err(format!("Nullifier signature {} is not from the owner", i)),
+            Err(e) => return Err(format!("Nullifier signature {} recovery failed: {}", i, e)),
+        }
+        let nullifier = compute_nullifier(signature);
+        if witness.spent_nullifiers.binary_search(&nullifier).is_ok() {
+            return Err(format!("Note {} is spent", i));
+        }
+        if nullifiers.contains(&nullifier) {
+            return Err(format!("Note {} is counted twice", i));
+        }
+        nullifiers.push(nullifier);
+        total += note.amount as u128;
+    }
+
+    if total < witness.threshold {
+        return Err(format!("Balance {} is below the threshold {}", total, witness.threshold));
+    }
+    Ok(BalanceStatement {
+        root: witness.root,
+        threshold: witness.threshold,
+        challenge: witness.challenge,
+        owner,
+        spent_nullifiers_hash: spent_nullifiers_hash(&witness.spent_nullifiers),
+    })
+}
+
+/// Build a signed balance witness from the wallet's notes (wallet/host side).
+///
+/// Picks unspent notes owned by the seed's key, largest first, skipping any
+/// whose nullifier is in `spent_nullifiers` (so a stale wallet state doesn't
+/// produce a witness the guest rejects), until `threshold` is covered.
+///
+/// # Errors
+/// Fails if the unspent notes don't cover `threshold`, or if a proof from
+/// `proofs` doesn't place a selected note under its root.
+#[cfg(feature = "encryption")]
+pub fn prepare_balance_witness(
+    seed: &[u8],
+    threshold: u128,
+    challenge: [u8; 32],
+    state: &crate::wallet::WalletState,
+    proofs: &impl crate::prepare::ProofSource,
+    mut spent_nullifiers: Vec<[u8; 32]>,
+) -> Result<BalanceWitness, String> {
+    use crate::merkle::LeafIndex;
+    use crate::prepare::{derive_spending_key, owner_pubkey};
+    use crate::signatures::sign_message;
+
+    let spending_key = derive_spending_key(seed)?;
+    let owner = owner_pubkey(&spending_key)?;
+    spent_nullifiers.sort_unstable();
+    spent_nullifiers.dedup();
+
+    let mut candidates: Vec<_> = state.unspent().filter(|n| n.note.owner_pubkey == owner).collect();
+    candidates.sort_by(|a, b| b.note.amount.cmp(&a.note.amount).then(a.leaf_index.cmp(&b.leaf_index)));
+
+    let root = proofs.root()?;
+    let mut witness = BalanceWitness {
+        root,
+        threshold,
+        challenge,
+        notes: Vec::new(),
+        proofs: Vec::new(),
+        nullifier_signatures: Vec::new(),
+        ownership_signature: sign_message(&spending_key, &balance_challenge_message(&root, threshold, &challenge))?.into(),
+        spent_nullifiers,
+    };
+    let mut total: u128 = 0;
+    for owned in candidates {
+        if total >= threshold && !witness.notes.is_empty() {
+            break;
+        }
+        let signature = sign_message(&spending_key, &nullifier_message(&owned.commitment))?;
+        if witness.spent_nullifiers.binary_search(&compute_nullifier(&signature)).is_ok() {
+            continue;
+        }
+        let proof = proofs.proof(owned.leaf_index)?;
+        if proof.leaf_index != LeafIndex::from(owned.leaf_index) || !MerkleTree::verify_proof(owned.commitment, &proof, root) {
+            return Err(format!("Proof for leaf {} does not match the note or root", owned.leaf_index));
+        }
+        witness.notes.push(owned.note.clone());
+        witness.proofs.push(proof);
+        witness.nullifier_signatures.push(signature.into());
+        total += owned.note.amount as u128;
+    }
+    if total < threshold || witness.notes.is_empty() {
+        return Err(format!("Insufficient funds: {} unspent, {} needed", total, threshold));
+    }
+    Ok(witness)
+}
+
+#[cfg(feature = "abi")]
+alloy_sol_types::sol! {
+    /// Public values of the `balance-proof` guest
+    struct BalanceProofOutputs {
+        bytes32 root;
+        uint128 threshold;
+        bytes32 challenge;
+        bytes32 owner;
+        bytes32 spentNullifiersHash;
+    }
+}
+
+/// ABI-encode a statement as the guest's public values.
+#[cfg(feature = "abi")]
+pub fn encode_balance_statement(statement: &BalanceStatement) -> Vec<u8> {
+    use alloy_sol_types::SolType;
+
+    BalanceProofOutputs::abi_encode(&BalanceProofOutputs {
+        root: statement.root.into(),
+        threshold: statement.threshold,
+        challenge: statement.challenge.into(),
+        owner: statement.owner.into(),
+        spentNullifiersHash: statement.spent_nullifiers_hash.into(),
+    })
+}
+
+/// Decode public values committed by the `balance-proof` guest.
+#[cfg(feature = "abi")]
+pub fn decode_balance_statement(public_values: &[u8]) -> Result<BalanceStatement, String> {
+    use alloy_sol_types::SolType;
+
+    let outputs = BalanceProofOutputs::abi_decode(public_values, true)
+        .map_err(|e| format!("Invalid balance proof public values: {}", e))?;
+    Ok(BalanceStatement {
+        root: outputs.root.0,
+        threshold: outputs.threshold,
+        challenge: outputs.challenge.0,
+        owner: outputs.owner.0,
+        spent_nullifiers_hash: outputs.spentNullifiersHash.0,
+    })
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+    use crate::prepare::{derive_spending_key, owner_pubkey};
+    use crate::wallet::WalletState;
+
+    const SEED: &[u8] = b"balance-proof-test-seed";
+
+    fn setup() -> (MerkleTree, WalletState, [u8; 32]) {
+        let owner = owner_pubkey(&derive_spending_key(SEED).unwrap()).unwrap();
+        let mut tree = MerkleTree::new();
+        let mut state = WalletState::new();
+        tree.push_note(&Note::new(500, [0xee; 32], [0xee; 32]));
+        for (amount, blinding) in [(30, 1), (80, 2), (10, 3)] {
+            let note = Note::new(amount, owner, [blinding; 32]);
+            let index = tree.push_note(&note).index;
+            state.add_note(note, u64::try_from(index).unwrap());
+        }
+        (tree, state, owner)
+    }
+
+    #[test]
+    fn test_balance_proof_roundtrip() {
+        let (tree, state, owner) = setup();
+        let witness = prepare_balance_witness(SEED, 100, [9; 32], &state, &tree, Vec::new()).unwrap();
+        assert_eq!(witness.notes.len(), 2, "80 + 30 covers 100");
+
+        let statement = verify_balance_witness(&witness).unwrap();
+        assert_eq!(statement.owner, owner);
+        assert_eq!(statement.root, tree.root());
+        assert_eq!(statement.spent_nullifiers_hash, spent_nullifiers_hash(&[]));
+        #[cfg(feature = "abi")]
+        assert_eq!(decode_balance_statement(&encode_balance_statement(&statement)).unwrap(), statement);
+
+        let mut short = witness.clone();
+        short.threshold = 111;
+        assert!(verify_balance_witness(&short).unwrap_err().contains("Ownership signature"));
+        let mut doubled = witness.clone();
+        doubled.notes.push(witness.notes[0].clone());
+        doubled.proofs.push(witness.proofs[0].clone());
+        doubled.nullifier_signatures.push(witness.nullifier_signatures[0].clone());
+        assert!(verify_balance_witness(&doubled).unwrap_err().contains("counted twice"));
+    }
+
+    #[test]
+    fn test_spent_notes_do_not_count() {
+        let (tree, state, _) = setup();
+        let witness = prepare_balance_witness(SEED, 100, [9; 32], &state, &tree, Vec::new()).unwrap();
+        let spent = compute_nullifier(&witness.nullifier_signatures[0]);
+
+        let mut stale = witness.clone();
+        stale.spent_nullifiers = vec![spent];
+        assert!(verify_balance_witness(&stale).unwrap_err().contains("spent"));
+        // Re-encoding the spent note's recovery byte as raw 0/1 doesn't escape the spent set
+        stale.nullifier_signatures[0][64] -= 27;
+        assert!(verify_balance_witness(&stale).unwrap_err().contains("not canonical"));
+
+        // The 80 note is skipped; 30 + 10 doesn't reach 100
+        assert!(prepare_balance_witness(SEED, 100, [9; 32], &state, &tree, vec![spent]).is_err());
+        let witness = prepare_balance_witness(SEED, 40, [9; 32], &state, &tree, vec![spent]).unwrap();
+        assert_eq!(verify_balance_witness(&witness).unwrap().spent_nullifiers_hash, spent_nullifiers_hash(&[spent]));
+    }
+}
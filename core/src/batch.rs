@@ -0,0 +1,250 @@
+//! Chained transaction batches
+//!
+//! A batch may spend notes created earlier in the same batch ("deposit then
+//! immediately pay"). The planner orders transactions so every producer runs
+//! before its consumers, replays the insertions on a local copy of the tree,
+//! and rewrites each transaction's membership proofs against the root it
+//! will actually see on-chain.
+//!
+//! The projected roots only hold if nothing else is inserted into the ledger
+//! between the batch's transactions, so the batch must be submitted in plan
+//! order and re-planned if another commitment lands first.
+//!
+//! Each transaction is still proven and submitted on its own: the plan
+//! sequences proofs, it doesn't aggregate them (see the host's
+//! `prove-chained`).
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::hex::encode_hex;
+use crate::merkle::MerkleTree;
+use crate::note::commit;
+use crate::sp1_types::{PublicInputs, Witness};
+
+/// One transaction of a planned batch, ready to prove.
+#[derive(Debug, Clone)]
+pub struct BatchStep {
+    /// Position of this transaction in the submitted batch
+    pub tx_index: usize,
+    /// Root the transaction is proven against (projected for chained steps)
+    pub public_inputs: PublicInputs,
+    /// Witness with input indices and proofs rewritten for `public_inputs`
+    pub witness: Witness,
+    /// Root after this transaction's outputs are appended
+    pub new_root: [u8; 32],
+}
+
+/// Execution order and projected tree states for a batch.
+#[derive(Debug, Clone)]
+pub struct BatchPlan {
+    pub steps: Vec<BatchStep>,
+    /// Root after every transaction in the batch has been applied
+    pub final_root: [u8; 32],
+}
+
+impl BatchPlan {
+    /// Original batch positions in execution order.
+    pub fn order(&self) -> Vec<usize> {
+        self.steps.iter().map(|s| s.tx_index).collect()
+    }
+}
+
+/// Order a batch and compute membership proofs against projected roots.
+///
+/// Inputs already in `base` are proven at their existing leaf; inputs equal
+/// to an output of another transaction in the batch make that transaction a
+/// dependency and are proven at the leaf it will be appended to.
+///
+/// # Errors
+/// - An input is neither in `base` nor produced by the batch
+/// - A note is spent twice within the batch
+/// - Two transactions create the same commitment
+/// - Transactions depend on each other cyclically
+pub fn plan_chained_batch(base: &MerkleTree, txs: Vec<Witness>) -> Result<BatchPlan, String> {
+    let base_leaves: HashMap<[u8; 32], usize> = base
+        .leaves()
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, leaf)| (*leaf, i))
+        .collect();
+
+    // Which transaction creates each new commitment
+    let mut producers: HashMap<[u8; 32], usize> = HashMap::new();
+    for (tx_index, tx) in txs.iter().enumerate() {
+        for note in &tx.output_notes {
+            let commitment = commit(note);
+            if let Some(other) = producers.insert(commitment, tx_index) {
+                return Err(format!(
+                    "Transactions {} and {} both create commitment 0x{}",
+                    other, tx_index, hex_prefix(&commitment)
+                ));
+            }
+        }
+    }
+
+    // Dependency edges: producer -> consumers
+    let mut spent = HashSet::new();
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); txs.len()];
+    let mut pending: Vec<usize> = vec![0; txs.len()];
+    for (tx_index, tx) in txs.iter().enumerate() {
+        for note in &tx.input_notes {
+            let commitment = commit(note);
+            if !spent.insert(commitment) {
+                return Err(format!(
+                    "Note 0x{} is spent twice within the batch",
+                    hex_prefix(&commitment)
+                ));
+            }
+            if base_leaves.contains_key(&commitment) {
+                continue;
+            }
+            match producers.get(&commitment) {
+                Some(&producer) if producer == tx_index => {
+                    return Err(format!("Transaction {} spends its own output", tx_index));
+                }
+                Some(&producer) => {
+                    dependents[producer].push(tx_index);
+                    pending[tx_index] += 1;
+                }
+                None => {
+                    return Err(format!(
+                        "Transaction {} input 0x{} is not in the tree or created by the batch",
+                        tx_index, hex_prefix(&commitment)
+                    ));
+                }
+            }
+        }
+    }
+
+    // Kahn's algorithm, lowest batch position first for a stable order
+    let mut ready: BTreeSet<usize> = (0..txs.len()).filter(|&i| pending[i] == 0).collect();
+    let mut order = Vec::with_capacity(txs.len());
+    while let Some(tx_index) = ready.pop_first() {
+        order.push(tx_index);
+        for &dependent in &dependents[tx_index] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                ready.insert(dependent);
+            }
+        }
+    }
+    if order.len() != txs.len() {
+        return Err("Batch contains a dependency cycle".to_string());
+    }
+
+    // Replay insertions on a projected copy of the tree
+    let mut projected = base.clone();
+    let mut leaf_of: HashMap<[u8; 32], usize> = base_leaves;
+    let mut slots: Vec<Option<Witness>> = txs.into_iter().map(Some).collect();
+    let mut steps = Vec::with_capacity(order.len());
+
+    for tx_index in order {
+        let mut witness = slots[tx_index].take().expect("each transaction is planned once");
+        let old_root = projected.root();
+
+        let mut indices = Vec::with_capacity(witness.input_notes.len());
+        let mut proofs = Vec::with_capacity(witness.input_notes.len());
+        for note in &witness.input_notes {
+            let leaf = leaf_of[&commit(note)];
+            let proof = projected
+                .prove(leaf)
+                .ok_or_else(|| format!("Failed to prove leaf {}", leaf))?;
+            indices.push(leaf);
+            proofs.push(proof);
+        }
+        witness.input_indices = indices;
+        witness.input_proofs = proofs;
+
+        for note in &witness.output_notes {
+            let leaf = projected.push_note(note).index as usize;
+            leaf_of.entry(commit(note)).or_insert(leaf);
+        }
+
+        steps.push(BatchStep {
+            tx_index,
+            public_inputs: PublicInputs::new(old_root),
+            witness,
+            new_root: projected.root(),
+        });
+    }
+
+    Ok(BatchPlan { steps, final_root: projected.root() })
+}
+
+fn hex_prefix(bytes: &[u8; 32]) -> String {
+    encode_hex(&bytes[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+
+    fn tx(inputs: Vec<Note>, outputs: Vec<Note>) -> Witness {
+        let sigs = vec![vec![0u8; 65]; inputs.len()];
+        let indices = vec![0; inputs.len()];
+        Witness::new_without_proofs(inputs, indices, sigs.clone(), sigs, outputs)
+    }
+
+    #[test]
+    fn test_deposit_then_pay_is_reordered_and_proven_against_projected_root() {
+        let deposit = Note::new(100, [1; 32], [1; 32]);
+        let change = Note::new(60, [1; 32], [2; 32]);
+        let payment = Note::new(40, [2; 32], [3; 32]);
+        let onward = Note::new(40, [3; 32], [4; 32]);
+
+        let mut base = MerkleTree::new();
+        base.push_note(&Note::new(5, [9; 32], [9; 32]));
+        base.push_note(&deposit);
+
+        // Pay (spends `payment`) submitted before the transfer that creates it
+        let pay = tx(vec![payment.clone()], vec![onward.clone()]);
+        let split = tx(vec![deposit], vec![change.clone(), payment.clone()]);
+        let plan = plan_chained_batch(&base, vec![pay, split]).unwrap();
+
+        assert_eq!(plan.order(), vec![1, 0]);
+        assert_eq!(plan.steps[0].public_inputs.old_root, base.root());
+        assert_eq!(plan.steps[1].public_inputs.old_root, plan.steps[0].new_root);
+
+        for step in &plan.steps {
+            for (note, proof) in step.witness.input_notes.iter().zip(&step.witness.input_proofs) {
+                assert!(MerkleTree::verify_proof(commit(note), proof, step.public_inputs.old_root));
+            }
+        }
+        assert_eq!(plan.steps[1].witness.input_indices, vec![3]);
+
+        let mut expected = base.clone();
+        expected.push_note(&change);
+        expected.push_note(&payment);
+        expected.push_note(&onward);
+        assert_eq!(plan.final_root, expected.root());
+    }
+
+    #[test]
+    fn test_unknown_input_rejected() {
+        let base = MerkleTree::new();
+        let result = plan_chained_batch(&base, vec![tx(vec![Note::new(1, [1; 32], [1; 32])], vec![])]);
+        assert!(result.unwrap_err().contains("not in the tree"));
+    }
+
+    #[test]
+    fn test_double_spend_within_batch_rejected() {
+        let note = Note::new(10, [1; 32], [1; 32]);
+        let mut base = MerkleTree::new();
+        base.push_note(&note);
+
+        let a = tx(vec![note.clone()], vec![Note::new(10, [2; 32], [2; 32])]);
+        let b = tx(vec![note], vec![Note::new(10, [3; 32], [3; 32])]);
+        assert!(plan_chained_batch(&base, vec![a, b]).unwrap_err().contains("spent twice"));
+    }
+
+    #[test]
+    fn test_cycle_rejected() {
+        let x = Note::new(10, [1; 32], [1; 32]);
+        let y = Note::new(10, [2; 32], [2; 32]);
+        let a = tx(vec![x.clone()], vec![y.clone()]);
+        let b = tx(vec![y], vec![x]);
+        assert!(plan_chained_batch(&MerkleTree::new(), vec![a, b]).unwrap_err().contains("cycle"));
+    }
+}
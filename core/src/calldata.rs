@@ -0,0 +1,155 @@
+//! Calldata size of the ledger call that submits a proof
+//!
+//! A proof that fits the guest can still be too large to submit: relayers'
+//! nodes and some chains cap transaction size (128 KiB of calldata is a
+//! common limit), and that only shows up when `eth_sendRawTransaction`
+//! fails. The prover checks the call it expects the relayer to make against
+//! the chain's `CalldataLimits` before returning the proof.
+//!
+//! Sizes follow the ABI encoding of the `PrivateUTXOLedger` entry points:
+//! `submitTx` for transfers, `submitTxCompressed` when the public values are
+//! compressed (the lists travel as calldata) and `withdraw` for withdrawals.
+//! Each output is assumed to carry one `OutputCiphertext` of a note sealed
+//! with `encrypt_note`; metadata (`submitTxWithMetadata`) isn't counted.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::PublicOutputs;
+
+/// Calldata budget when a chain doesn't configure one.
+pub const DEFAULT_MAX_CALLDATA_BYTES: usize = 128 * 1024;
+
+/// ABI size of one `OutputCiphertext` in the array, including its offset:
+/// offset (32) + head of 5 words (160) + 33-byte ephemeral key (32 + 64) +
+/// ciphertext of at most 128 bytes (32 + 128). A note plaintext is under
+/// 90 bytes and AES-GCM adds a 16-byte tag.
+pub const OUTPUT_CIPHERTEXT_CALLDATA_BYTES: usize = 32 + 160 + 96 + 160;
+
+/// Size limits of one chain's ledger calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalldataLimits {
+    pub max_calldata_bytes: usize,
+    /// Unlimited (beyond the calldata budget) when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_proof_bytes: Option<usize>,
+}
+
+impl Default for CalldataLimits {
+    fn default() -> Self {
+        Self { max_calldata_bytes: DEFAULT_MAX_CALLDATA_BYTES, max_proof_bytes: None }
+    }
+}
+
+/// The ledger function a proof is submitted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerCall {
+    SubmitTx,
+    SubmitTxCompressed,
+    Withdraw,
+}
+
+impl LedgerCall {
+    pub fn for_outputs(outputs: &PublicOutputs, compressed: bool) -> Self {
+        match (outputs.withdrawal.is_some(), compressed) {
+            (true, _) => LedgerCall::Withdraw,
+            (false, true) => LedgerCall::SubmitTxCompressed,
+            (false, false) => LedgerCall::SubmitTx,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LedgerCall::SubmitTx => "submitTx",
+            LedgerCall::SubmitTxCompressed => "submitTxCompressed",
+            LedgerCall::Withdraw => "withdraw",
+        }
+    }
+}
+
+/// Encoded size of a `bytes` argument's tail.
+fn bytes_len(len: usize) -> usize {
+    32 + len.div_ceil(32) * 32
+}
+
+/// Calldata length of submitting a proof of `outputs`: selector, head and
+/// every dynamic argument.
+pub fn ledger_calldata_len(outputs: &PublicOutputs, compressed: bool, proof_len: usize, public_values_len: usize) -> usize {
+    let ciphertexts = 32 + outputs.output_commitments.len() * OUTPUT_CIPHERTEXT_CALLDATA_BYTES;
+    let proof_and_values = bytes_len(proof_len) + bytes_len(public_values_len);
+    4 + match LedgerCall::for_outputs(outputs, compressed) {
+        // (encryptedOutputs, proof, publicValues)
+        LedgerCall::SubmitTx => 3 * 32 + ciphertexts + proof_and_values,
+        // (encryptedOutputs, proof, publicValues, nullifiers, outputCommitments)
+        LedgerCall::SubmitTxCompressed => {
+            let lists = 32 + outputs.nullifiers.len() * 32 + 32 + outputs.output_commitments.len() * 32;
+            5 * 32 + ciphertexts + proof_and_values + lists
+        }
+        // (recipient, amount, proof, publicValues, encryptedOutputs)
+        LedgerCall::Withdraw => 5 * 32 + ciphertexts + proof_and_values,
+    }
+}
+
+/// Check a proof's submission against `limits`.
+///
+/// # Errors
+/// Names the call, its size and the limit, and suggests splitting the
+/// transaction into chained ones.
+pub fn check_calldata_size(
+    outputs: &PublicOutputs,
+    compressed: bool,
+    proof_len: usize,
+    public_values_len: usize,
+    limits: &CalldataLimits,
+) -> Result<(), String> {
+    if let Some(max) = limits.max_proof_bytes.filter(|&max| proof_len > max) {
+        return Err(format!("Proof is {} bytes, over this chain's {}-byte limit", proof_len, max));
+    }
+    let len = ledger_calldata_len(outputs, compressed, proof_len, public_values_len);
+    if len > limits.max_calldata_bytes {
+        return Err(format!(
+            "{} calldata would be about {} bytes ({} nullifiers, {} outputs), over this chain's {}-byte limit; \
+             split the transaction into chained ones (see `plan_chained_batch`)",
+            LedgerCall::for_outputs(outputs, compressed).name(),
+            len,
+            outputs.nullifiers.len(),
+            outputs.output_commitments.len(),
+            limits.max_calldata_bytes
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(nullifiers: usize, commitments: usize) -> PublicOutputs {
+        PublicOutputs {
+            chain_id: 1,
+            old_root: [1; 32],
+            nullifiers: vec![[2; 32]; nullifiers],
+            output_commitments: vec![[3; 32]; commitments],
+            withdrawal: None,
+            external_nullifier: None,
+        }
+    }
+
+    #[test]
+    fn test_submit_tx_calldata_len() {
+        // Groth16 proof (260 bytes) and full public values for 1 input, 2 outputs
+        let len = ledger_calldata_len(&outputs(1, 2), false, 260, 288);
+        assert_eq!(len, 4 + 96 + (32 + 2 * OUTPUT_CIPHERTEXT_CALLDATA_BYTES) + (32 + 288) + (32 + 288));
+        assert!(check_calldata_size(&outputs(1, 2), false, 260, 288, &CalldataLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn test_oversize_call_suggests_chaining() {
+        let big = outputs(16, 400);
+        let err = check_calldata_size(&big, true, 260, 192, &CalldataLimits::default()).unwrap_err();
+        assert!(err.contains("submitTxCompressed") && err.contains("chained"), "{}", err);
+
+        let limits = CalldataLimits { max_proof_bytes: Some(256), ..CalldataLimits::default() };
+        assert!(check_calldata_size(&outputs(1, 2), false, 260, 288, &limits).unwrap_err().contains("Proof is 260 bytes"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Per-chain ledger state
+//!
+//! The same guest (and vkey) can back ledgers on several chains. Each
+//! deployment has its own commitment tree, nullifier set and root history,
+//! and a proof only applies on the chain it's bound to (`chainId` in the
+//! public values). `ChainRegistry` keeps one `ChainState` per chain ID so an
+//! indexer or prover serving several deployments routes every request and
+//! event to the right one.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::{verify_transition_on, PublicOutputs, TransitionReport};
+use crate::merkle::{LeafIndex, MerkleTree};
+use crate::note::Nullifier;
+use crate::nullifier_set::NullifierSet;
+use crate::proof_request::ProofRequest;
+
+/// Where a ledger is deployed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainDeployment {
+    pub chain_id: u64,
+    /// Ledger contract address
+    pub ledger: String,
+}
+
+/// One deployment's view of the ledger, mirrored from its events.
+#[derive(Debug, Clone)]
+pub struct ChainState {
+    pub deployment: ChainDeployment,
+    tree: MerkleTree,
+    nullifiers: NullifierSet,
+    /// Every root the tree has had, oldest first (the ledger accepts any)
+    root_history: Vec<[u8; 32]>,
+}
+
+impl ChainState {
+    pub fn new(deployment: ChainDeployment) -> Self {
+        let tree = MerkleTree::new();
+        let root_history = vec![tree.root()];
+        Self { deployment, tree, nullifiers: NullifierSet::new(), root_history }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.deployment.chain_id
+    }
+
+    pub fn tree(&self) -> &MerkleTree {
+        &self.tree
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn root_history(&self) -> &[[u8; 32]] {
+        &self.root_history
+    }
+
+    pub fn is_known_root(&self, root: &[u8; 32]) -> bool {
+        self.root_history.contains(root)
+    }
+
+    pub fn is_spent(&self, nullifier: &Nullifier) -> bool {
+        self.nullifiers.contains(nullifier)
+    }
+
+    pub fn nullifiers(&self) -> &NullifierSet {
+        &self.nullifiers
+    }
+
+    /// Append a commitment (from `OutputCommitted`), recording the new root.
+    pub fn insert_commitment(&mut self, commitment: [u8; 32]) -> LeafIndex {
+        let insertion = self.tree.push_leaf(commitment);
+        self.root_history.push(insertion.new_root);
+        insertion.index
+    }
+
+    /// Mark a nullifier spent (from `NullifierUsed`).
+    pub fn spend(&mut self, nullifier: Nullifier) -> Result<(), String> {
+        self.nullifiers
+            .insert(nullifier)
+            .map_err(|e| format!("{} on chain {}", e, self.chain_id()))
+    }
+
+    /// `Ledger::verify_transition` against this chain's state: whether the
+    /// ledger here would accept `outputs`, and the root it would move to.
+    ///
+    /// # Errors
+    /// Fails if the outputs are bound to another chain, or on any of
+    /// `verify_transition`'s checks.
+    pub fn verify_transition(&self, outputs: &PublicOutputs) -> Result<TransitionReport, String> {
+        if outputs.chain_id != self.chain_id() {
+            return Err(format!("Proof is bound to chain {}, not {}", outputs.chain_id, self.chain_id()));
+        }
+        verify_transition_on(&self.tree, |n| self.is_spent(n), |root| self.is_known_root(root), outputs)
+            .map_err(|e| format!("{} on chain {}", e, self.chain_id()))
+    }
+
+    /// Apply a proven transaction as the ledger on this chain would.
+    ///
+    /// # Errors
+    /// Fails, without changing state, if `verify_transition` does.
+    pub fn apply(&mut self, outputs: &PublicOutputs) -> Result<TransitionReport, String> {
+        let report = self.verify_transition(outputs)?;
+        for nullifier in &outputs.nullifiers {
+            self.spend(*nullifier)?;
+        }
+        for commitment in &outputs.output_commitments {
+            self.insert_commitment(*commitment);
+        }
+        Ok(report)
+    }
+}
+
+/// State for every tracked deployment, keyed by chain ID.
+#[derive(Debug, Clone, Default)]
+pub struct ChainRegistry {
+    chains: BTreeMap<u64, ChainState>,
+}
+
+impl ChainRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking a deployment.
+    ///
+    /// # Errors
+    /// Fails if the chain is already tracked (one ledger per chain).
+    pub fn register(&mut self, deployment: ChainDeployment) -> Result<&mut ChainState, String> {
+        let chain_id = deployment.chain_id;
+        if let Some(existing) = self.chains.get(&chain_id) {
+            return Err(format!("Chain {} is already tracked (ledger {})", chain_id, existing.deployment.ledger));
+        }
+        Ok(self.chains.entry(chain_id).or_insert_with(|| ChainState::new(deployment)))
+    }
+
+    pub fn chain_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.chains.keys().copied()
+    }
+
+    pub fn get(&self, chain_id: u64) -> Result<&ChainState, String> {
+        self.chains.get(&chain_id).ok_or_else(|| self.unknown(chain_id))
+    }
+
+    pub fn get_mut(&mut self, chain_id: u64) -> Result<&mut ChainState, String> {
+        let unknown = self.unknown(chain_id);
+        self.chains.get_mut(&chain_id).ok_or(unknown)
+    }
+
+    /// The state a request's `chainId` selects, checking its `oldRoot` is
+    /// from that chain.
+    pub fn route(&self, request: &ProofRequest) -> Result<&ChainState, String> {
+        let state = self.get(request.chain_id)?;
+        if !state.is_known_root(&request.old_root.0) {
+            return Err(format!("oldRoot is not a root of the ledger on chain {}", request.chain_id));
+        }
+        Ok(state)
+    }
+
+    fn unknown(&self, chain_id: u64) -> String {
+        let tracked: Vec<String> = self.chain_ids().map(|id| id.to_string()).collect();
+        format!("Chain {} is not tracked (tracked: {})", chain_id, tracked.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ChainRegistry {
+        let mut registry = ChainRegistry::new();
+        for (chain_id, ledger) in [(1, "0x1111"), (8453, "0x2222")] {
+            registry.register(ChainDeployment { chain_id, ledger: ledger.to_string() }).unwrap();
+        }
+        registry
+    }
+
+    fn request(chain_id: u64, old_root: [u8; 32]) -> ProofRequest {
+        serde_json::from_value(serde_json::json!({
+            "inputNotes": [], "outputNotes": [], "nullifierSignatures": [], "txSignatures": [],
+            "inputIndices": [], "inputProofs": [], "oldRoot": crate::hex::encode_hex(&old_root), "chainId": chain_id,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_chains_keep_separate_state() {
+        let mut registry = registry();
+        registry.get_mut(1).unwrap().insert_commitment([7; 32]);
+        registry.get_mut(1).unwrap().spend([9; 32]).unwrap();
+
+        let mainnet = registry.get(1).unwrap();
+        let base = registry.get(8453).unwrap();
+        assert_ne!(mainnet.root(), base.root());
+        assert_eq!(mainnet.root_history().len(), 2);
+        assert!(mainnet.is_spent(&[9; 32]));
+        assert!(!base.is_spent(&[9; 32]));
+        assert!(registry.register(ChainDeployment { chain_id: 1, ledger: "0x3333".to_string() }).is_err());
+    }
+
+    #[test]
+    fn test_requests_route_by_chain_id() {
+        let mut registry = registry();
+        let root = {
+            let state = registry.get_mut(1).unwrap();
+            state.insert_commitment([7; 32]);
+            state.root()
+        };
+
+        assert_eq!(registry.route(&request(1, root)).unwrap().deployment.ledger, "0x1111");
+        // A root from chain 1 means nothing on chain 8453
+        assert!(registry.route(&request(8453, root)).unwrap_err().contains("chain 8453"));
+        assert!(registry.route(&request(10, root)).unwrap_err().contains("tracked: 1, 8453"));
+    }
+
+    #[test]
+    fn test_apply_requires_matching_chain_binding() {
+        let mut registry = registry();
+        let state = registry.get_mut(8453).unwrap();
+        let mut outputs = PublicOutputs {
+            chain_id: 1,
+            old_root: state.root(),
+            nullifiers: vec![[1; 32]],
+            output_commitments: vec![[2; 32]],
+            withdrawal: None,
+            external_nullifier: None,
+        };
+
+        assert!(state.apply(&outputs).unwrap_err().contains("bound to chain 1"));
+        assert!(!state.is_spent(&[1; 32]));
+
+        outputs.chain_id = 8453;
+        let report = state.apply(&outputs).unwrap();
+        assert!(state.is_spent(&[1; 32]));
+        assert_eq!(state.tree().leaf_count(), 1);
+        assert_eq!(report.new_root, state.root());
+        // Replaying the same nullifier fails
+        outputs.old_root = state.root();
+        assert!(state.apply(&outputs).unwrap_err().contains("already spent on chain 8453"));
+
+        // Any earlier root is still accepted; a zero commitment takes a leaf
+        // without changing the root, as in the contract
+        outputs.old_root = state.root_history()[0];
+        outputs.nullifiers = vec![[3; 32]];
+        outputs.output_commitments = vec![[0; 32]];
+        let root = state.root();
+        assert_eq!(state.apply(&outputs).unwrap().new_root, root);
+        assert_eq!(state.root(), root);
+    }
+}
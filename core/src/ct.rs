@@ -0,0 +1,38 @@
+//! Constant-time comparisons
+//!
+//! `==` on byte arrays stops at the first differing byte, so how long it
+//! takes tells the caller how much of a guess was right. The host and the
+//! wallet compare values derived from attacker-supplied input (recovered
+//! signers, nullifiers, backup digests, blindings) against secret-adjacent
+//! expected ones, and a prover-server answers fast enough for that timing
+//! to be measured, so those comparisons use `eq`, which always reads every
+//! byte.
+//!
+//! Comparisons where both sides are public (leaf commitments, roots, key
+//! ids) keep `==`. Hash-map and set lookups of nullifiers and commitments
+//! hash their key first, and AES-GCM checks its tag in constant time
+//! itself.
+
+use subtle::ConstantTimeEq;
+
+/// Whether `a == b`, in time independent of where they differ.
+pub fn eq<const N: usize>(a: &[u8; N], b: &[u8; N]) -> bool {
+    a[..].ct_eq(&b[..]).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eq() {
+        let a = [7u8; 32];
+        let mut b = a;
+        assert!(eq(&a, &b));
+        b[31] ^= 1;
+        assert!(!eq(&a, &b));
+        b = a;
+        b[0] ^= 0x80;
+        assert!(!eq(&a, &b));
+    }
+}
@@ -0,0 +1,180 @@
+//! Transaction size limits from measured guest cycles
+//!
+//! What a transaction costs to prove grows with its inputs (a Merkle path
+//! and two signature recoveries each) and outputs (a commitment each), and
+//! the per-item cost moves with every guest build. So the prover caps each
+//! transaction at a cycle ceiling instead of a fixed input count, and the
+//! input limit clients see is derived from cycles measured on the ELF in
+//! use:
+//!
+//! - `check_cycles` enforces the ceiling on a witness's measured cycles, and
+//!   suggests how far to split a transaction that exceeds it
+//! - `CycleModel::fit` turns cycles measured on sample shapes into a
+//!   conservative linear estimate
+//! - `CycleLimits` is what the prover publishes: the ceiling, the samples,
+//!   the model and the most inputs that fit for each output count
+
+use serde::{Deserialize, Serialize};
+
+/// Cycles measured executing a transaction with this shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CycleSample {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub cycles: u64,
+}
+
+/// `base + inputs * per_input + outputs * per_output`, fitted to be at least
+/// every sample's measured cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CycleModel {
+    pub base: u64,
+    pub per_input: u64,
+    pub per_output: u64,
+}
+
+impl CycleModel {
+    /// Fit the model, taking the steepest per-input and per-output slope
+    /// between samples so it over- rather than under-estimates.
+    ///
+    /// # Errors
+    /// Fails unless some samples differ only in their input count and some
+    /// only in their output count, so both slopes are measured.
+    pub fn fit(samples: &[CycleSample]) -> Result<Self, String> {
+        let per_input = steepest_slope(samples, |s| (s.outputs, s.inputs))
+            .ok_or("Need samples that differ only in their input count")?;
+        let per_output = steepest_slope(samples, |s| (s.inputs, s.outputs))
+            .ok_or("Need samples that differ only in their output count")?;
+        let variable = |s: &CycleSample| (s.inputs as u64 * per_input).saturating_add(s.outputs as u64 * per_output);
+        let base = samples.iter().map(|s| s.cycles.saturating_sub(variable(s))).max().unwrap_or(0);
+        Ok(Self { base, per_input, per_output })
+    }
+
+    pub fn estimate(&self, inputs: usize, outputs: usize) -> u64 {
+        self.base
+            .saturating_add((inputs as u64).saturating_mul(self.per_input))
+            .saturating_add((outputs as u64).saturating_mul(self.per_output))
+    }
+
+    /// The most inputs a transaction with `outputs` outputs is estimated to
+    /// spend within `max_cycles` (0 if not even one fits).
+    pub fn max_inputs(&self, outputs: usize, max_cycles: u64) -> usize {
+        let Some(remaining) = max_cycles.checked_sub(self.estimate(0, outputs)) else {
+            return 0;
+        };
+        match remaining.checked_div(self.per_input) {
+            Some(inputs) => usize::try_from(inputs).unwrap_or(usize::MAX),
+            None => usize::MAX,
+        }
+    }
+}
+
+/// Steepest cycles-per-item slope between samples sharing `key(s).0`,
+/// ordered by `key(s).1`; `None` if no two samples differ only in `.1`.
+fn steepest_slope(samples: &[CycleSample], key: impl Fn(&CycleSample) -> (usize, usize)) -> Option<u64> {
+    let mut sorted: Vec<(usize, usize, u64)> = samples.iter().map(|s| (key(s).0, key(s).1, s.cycles)).collect();
+    sorted.sort_unstable();
+    sorted
+        .windows(2)
+        .filter(|pair| pair[0].0 == pair[1].0 && pair[0].1 < pair[1].1)
+        .map(|pair| {
+            let items = (pair[1].1 - pair[0].1) as u64;
+            pair[1].2.saturating_sub(pair[0].2).div_ceil(items)
+        })
+        .max()
+}
+
+/// Largest input count that fits for one output count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeLimit {
+    pub outputs: usize,
+    pub max_inputs: usize,
+}
+
+/// A prover's published transaction limits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CycleLimits {
+    /// Cycle ceiling a transaction's measured cycles must stay within
+    pub max_cycles: u64,
+    pub samples: Vec<CycleSample>,
+    pub model: CycleModel,
+    /// One entry per output count, from 1 to `max_outputs`
+    pub shapes: Vec<ShapeLimit>,
+}
+
+impl CycleLimits {
+    /// # Errors
+    /// Fails if the model can't be fitted to `samples` (see `CycleModel::fit`).
+    pub fn new(max_cycles: u64, samples: Vec<CycleSample>, max_outputs: usize) -> Result<Self, String> {
+        let model = CycleModel::fit(&samples)?;
+        let shapes = (1..=max_outputs)
+            .map(|outputs| ShapeLimit { outputs, max_inputs: model.max_inputs(outputs, max_cycles) })
+            .collect();
+        Ok(Self { max_cycles, samples, model, shapes })
+    }
+}
+
+/// Enforce `max_cycles` on a witness measured at `sample`.
+///
+/// # Errors
+/// Says by how much the transaction is over, and how many inputs per
+/// transaction to split it into, scaled down from its own measurement.
+pub fn check_cycles(sample: &CycleSample, max_cycles: u64) -> Result<(), String> {
+    if sample.cycles <= max_cycles {
+        return Ok(());
+    }
+    let over = format!(
+        "Transaction with {} inputs and {} outputs executes in {} cycles, over the {} cycle ceiling",
+        sample.inputs, sample.outputs, sample.cycles, max_cycles
+    );
+    let fitting = (sample.inputs as u128 * max_cycles as u128 / sample.cycles as u128) as usize;
+    if fitting == 0 {
+        return Err(format!("{}; it can't be split into fewer inputs", over));
+    }
+    Err(format!(
+        "{}; split it into transactions spending at most {} inputs each (consolidate first, or prove them as a batch)",
+        over, fitting
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(inputs: usize, outputs: usize, cycles: u64) -> CycleSample {
+        CycleSample { inputs, outputs, cycles }
+    }
+
+    #[test]
+    fn test_fit_covers_samples() {
+        let samples = vec![sample(1, 1, 1_300), sample(2, 1, 2_300), sample(4, 1, 4_100), sample(1, 2, 1_400)];
+        let model = CycleModel::fit(&samples).unwrap();
+        assert_eq!(model.per_input, 1_000);
+        assert_eq!(model.per_output, 100);
+        for s in &samples {
+            assert!(model.estimate(s.inputs, s.outputs) >= s.cycles);
+        }
+        assert_eq!(model.max_inputs(1, 10_000), 9);
+        assert_eq!(model.max_inputs(2, 250), 0);
+
+        let limits = CycleLimits::new(10_000, samples.clone(), 3).unwrap();
+        assert_eq!(limits.shapes.len(), 3);
+        assert!(limits.shapes.windows(2).all(|w| w[0].max_inputs >= w[1].max_inputs));
+
+        assert!(CycleModel::fit(&samples[..3]).is_err());
+        assert!(CycleModel::fit(&[sample(1, 1, 10), sample(2, 2, 20)]).is_err());
+    }
+
+    #[test]
+    fn test_check_cycles() {
+        assert!(check_cycles(&sample(4, 2, 1_000), 1_000).is_ok());
+        let e = check_cycles(&sample(8, 2, 2_000), 1_000).unwrap_err();
+        assert!(e.contains("at most 4 inputs"), "{}", e);
+        let e = check_cycles(&sample(1, 2, 2_000), 1_000).unwrap_err();
+        assert!(e.contains("can't be split"), "{}", e);
+    }
+}
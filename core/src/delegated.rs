@@ -0,0 +1,297 @@
+//! Witness encryption for delegated proving
+//!
+//! A `ProofRequest` holds note blindings and spend signatures, so whoever
+//! relays it can read (and front-run) the wallet's notes. When proving is
+//! outsourced, the wallet instead seals the request to a key the prover
+//! publishes: only the host process holding the keyring can open it, and it
+//! does so in memory. The prover-server queues, persists and routes the
+//! sealed form; `chainId` and `traceId` stay in the clear for that.
+//!
+//! Sealing is ECIES over secp256k1 (as for notes, with its own KDF label),
+//! with the key id, format version and chain id bound as associated data.
+//! `sealed_fields` seals only the witness fields to the same keys.
+//!
+//! Keys rotate: `ProverKeyring::rotate` adds a new current key and gives the
+//! previous ones a grace period, so requests sealed just before a rotation
+//! still open. Expired keys are refused and dropped at the next rotation.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use hkdf::Hkdf;
+use secp256k1::{ecdh::SharedSecret, PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::encryption::{ViewPublicKey, ViewSecretKey};
+use crate::proof_request::ProofRequest;
+
+/// Format version of `EncryptedRequest`.
+pub const ENCRYPTED_REQUEST_VERSION: u8 = 1;
+
+/// A prover's published encryption key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProverKey {
+    /// First 8 bytes of Blake3(public key), hex
+    pub key_id: String,
+    #[serde(with = "crate::hex::bytes")]
+    pub public_key: ViewPublicKey,
+    /// Unix seconds after which requests sealed to this key are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl ProverKey {
+    pub fn new(public_key: ViewPublicKey) -> Self {
+        Self { key_id: key_id(&public_key), public_key, expires_at: None }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|t| now >= t)
+    }
+}
+
+/// Key id for a prover public key.
+pub fn key_id(public_key: &ViewPublicKey) -> String {
+    crate::hex::encode_hex(&blake3::hash(public_key).as_bytes()[..8])
+}
+
+/// A proof request sealed to a prover key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EncryptedRequest {
+    pub version: u8,
+    pub key_id: String,
+    /// Copied from the request so the server can route it; bound as AAD
+    pub chain_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(with = "crate::hex::bytes")]
+    pub ephemeral_pubkey: ViewPublicKey,
+    #[serde(with = "crate::hex::bytes")]
+    pub nonce: [u8; 12],
+    #[serde(with = "crate::hex::bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedRequest {
+    /// Seal `request` to `key` (wallet side).
+    ///
+    /// # Errors
+    /// Fails if the key has expired or isn't a valid public key.
+    pub fn seal(request: &ProofRequest, key: &ProverKey, now: u64) -> Result<Self, String> {
+        let plaintext = serde_json::to_vec(request).map_err(|e| format!("Serialize failed: {}", e))?;
+        let aad = associated_data(ENCRYPTED_REQUEST_VERSION, &key.key_id, request.chain_id);
+        let sealed = seal_to(key, now, &plaintext, &aad)?;
+        Ok(Self {
+            version: ENCRYPTED_REQUEST_VERSION,
+            key_id: key.key_id.clone(),
+            chain_id: request.chain_id,
+            trace_id: request.trace_id.clone(),
+            ephemeral_pubkey: sealed.ephemeral_pubkey,
+            nonce: sealed.nonce,
+            ciphertext: sealed.ciphertext,
+        })
+    }
+}
+
+/// A plaintext sealed to a prover key by `seal_to`.
+pub(crate) struct Sealed {
+    pub ephemeral_pubkey: ViewPublicKey,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+/// Seal `plaintext` to `key`, binding `aad`.
+///
+/// # Errors
+/// Fails if the key has expired or isn't a valid public key.
+pub(crate) fn seal_to(key: &ProverKey, now: u64, plaintext: &[u8], aad: &[u8]) -> Result<Sealed, String> {
+    if key.is_expired(now) {
+        return Err(format!("Prover key {} has expired", key.key_id));
+    }
+    if key.key_id != key_id(&key.public_key) {
+        return Err(format!("Prover key id {} doesn't match its public key", key.key_id));
+    }
+    let recipient = PublicKey::from_slice(&key.public_key).map_err(|e| format!("Invalid prover key: {}", e))?;
+    let (ephemeral_sk, ephemeral_pk) = Secp256k1::new().generate_keypair(&mut rand::thread_rng());
+    let cipher = cipher(&SharedSecret::new(&recipient, &ephemeral_sk))?;
+
+    let nonce: [u8; 12] = rand::random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+    Ok(Sealed { ephemeral_pubkey: ephemeral_pk.serialize(), nonce, ciphertext })
+}
+
+/// One keyring entry: a published key and its secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProverKeyEntry {
+    #[serde(flatten)]
+    pub key: ProverKey,
+    #[serde(with = "crate::hex::bytes32")]
+    secret_key: ViewSecretKey,
+    pub created_at: u64,
+}
+
+/// The host's decryption keys, newest (current) first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProverKeyring {
+    keys: Vec<ProverKeyEntry>,
+}
+
+impl ProverKeyring {
+    /// A keyring with one fresh key.
+    pub fn generate(now: u64) -> Self {
+        let mut keyring = Self::default();
+        keyring.rotate(now, 0);
+        keyring
+    }
+
+    /// Add a new current key. Keys without an expiry get `grace_secs` from
+    /// now; already-expired keys are dropped.
+    pub fn rotate(&mut self, now: u64, grace_secs: u64) -> &ProverKey {
+        self.keys.retain(|entry| !entry.key.is_expired(now));
+        for entry in &mut self.keys {
+            entry.key.expires_at.get_or_insert(now.saturating_add(grace_secs));
+        }
+        let (secret_key, public_key) = crate::encryption::generate_keypair();
+        self.keys.insert(0, ProverKeyEntry { key: ProverKey::new(public_key), secret_key, created_at: now });
+        &self.keys[0].key
+    }
+
+    /// The current key, for wallets to seal new requests to.
+    pub fn current(&self) -> Option<&ProverKey> {
+        self.keys.first().map(|entry| &entry.key)
+    }
+
+    /// Keys that still open requests, current first.
+    pub fn published(&self, now: u64) -> Vec<ProverKey> {
+        self.keys.iter().filter(|e| !e.key.is_expired(now)).map(|e| e.key.clone()).collect()
+    }
+
+    /// Decrypt a sealed request.
+    ///
+    /// # Errors
+    /// Fails on an unknown or expired key, tampering (including a changed
+    /// `chainId`), or a plaintext that isn't a valid `ProofRequest` for the
+    /// sealed chain.
+    pub fn open(&self, sealed: &EncryptedRequest, now: u64) -> Result<ProofRequest, String> {
+        if sealed.version != ENCRYPTED_REQUEST_VERSION {
+            return Err(format!("Unsupported encrypted request version {}", sealed.version));
+        }
+        let aad = associated_data(sealed.version, &sealed.key_id, sealed.chain_id);
+        let plaintext =
+            self.decrypt(&sealed.key_id, &sealed.ephemeral_pubkey, &sealed.nonce, &sealed.ciphertext, &aad, now)?;
+
+        // Serde errors can quote the input, so don't pass them on
+        let request: ProofRequest =
+            serde_json::from_slice(&plaintext).map_err(|_| "Decrypted request is not a valid ProofRequest".to_string())?;
+        if request.chain_id != sealed.chain_id {
+            return Err(format!("Sealed for chain {} but the request is for chain {}", sealed.chain_id, request.chain_id));
+        }
+        Ok(request)
+    }
+}
+
+impl ProverKeyring {
+    /// Open a plaintext `seal_to` sealed to the key `key_id`.
+    ///
+    /// # Errors
+    /// Fails on an unknown or expired key, or a wrong key or tampered
+    /// ciphertext or `aad`.
+    pub(crate) fn decrypt(
+        &self,
+        key_id: &str,
+        ephemeral_pubkey: &ViewPublicKey,
+        nonce: &[u8; 12],
+        ciphertext: &[u8],
+        aad: &[u8],
+        now: u64,
+    ) -> Result<Vec<u8>, String> {
+        let entry = self
+            .keys
+            .iter()
+            .find(|e| e.key.key_id == key_id)
+            .ok_or_else(|| format!("Unknown prover key {}", key_id))?;
+        if entry.key.is_expired(now) {
+            return Err(format!("Prover key {} has expired", key_id));
+        }
+
+        let secret = SecretKey::from_slice(&entry.secret_key).map_err(|e| format!("Invalid keyring entry: {}", e))?;
+        let ephemeral =
+            PublicKey::from_slice(ephemeral_pubkey).map_err(|e| format!("Invalid ephemeral key: {}", e))?;
+        cipher(&SharedSecret::new(&ephemeral, &secret))?
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| "Failed to decrypt (wrong key or tampered)".to_string())
+    }
+}
+
+fn associated_data(version: u8, key_id: &str, chain_id: u64) -> Vec<u8> {
+    let mut aad = vec![version];
+    aad.extend_from_slice(&chain_id.to_be_bytes());
+    aad.extend_from_slice(key_id.as_bytes());
+    aad
+}
+
+fn cipher(shared_secret: &SharedSecret) -> Result<Aes256Gcm, String> {
+    let hkdf = Hkdf::<Sha256>::new(None, shared_secret.as_ref());
+    let mut key = [0u8; 32];
+    hkdf.expand(b"ghostclaw-delegated-request-v1", &mut key)
+        .map_err(|e| format!("HKDF expand failed: {}", e))?;
+    Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to create cipher: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(chain_id: u64) -> ProofRequest {
+        serde_json::from_value(serde_json::json!({
+            "inputNotes": [], "outputNotes": [], "nullifierSignatures": [], "txSignatures": [],
+            "inputIndices": [], "inputProofs": [], "oldRoot": crate::hex::encode_hex(&[3; 32]),
+            "chainId": chain_id, "traceId": "t-1",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let keyring = ProverKeyring::generate(100);
+        let key = keyring.current().unwrap();
+        let sealed = EncryptedRequest::seal(&request(1), key, 100).unwrap();
+
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(!json.contains("inputNotes"));
+        let sealed: EncryptedRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(sealed.trace_id.as_deref(), Some("t-1"));
+        let opened = keyring.open(&sealed, 100).unwrap();
+        assert_eq!(serde_json::to_value(opened).unwrap(), serde_json::to_value(request(1)).unwrap());
+
+        // The clear chainId is authenticated
+        let mut rerouted = sealed.clone();
+        rerouted.chain_id = 2;
+        assert!(keyring.open(&rerouted, 100).is_err());
+        assert!(ProverKeyring::generate(100).open(&sealed, 100).unwrap_err().contains("Unknown prover key"));
+    }
+
+    #[test]
+    fn test_rotation_grace_period() {
+        let mut keyring = ProverKeyring::generate(100);
+        let old = keyring.current().unwrap().clone();
+        let sealed = EncryptedRequest::seal(&request(1), &old, 100).unwrap();
+
+        let new = keyring.rotate(200, 60).clone();
+        assert_ne!(new.key_id, old.key_id);
+        assert_eq!(keyring.published(200).len(), 2);
+        assert!(keyring.open(&sealed, 259).is_ok());
+        assert!(keyring.open(&sealed, 260).unwrap_err().contains("expired"));
+        assert!(EncryptedRequest::seal(&request(1), &keyring.published(200)[1], 260).is_err());
+
+        keyring.rotate(300, 60);
+        assert_eq!(keyring.published(300).len(), 2, "expired key dropped, previous one in grace");
+    }
+}
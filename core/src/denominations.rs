@@ -0,0 +1,203 @@
+//! Standard note denominations
+//!
+//! Note amounts are hidden by the commitment, but not from everyone: a
+//! recipient sees what they were paid, and a withdrawal publishes its
+//! amount. An exact amount like 1_234_567 is close to unique, so when the
+//! same value turns up later (withdrawn whole, or paid on) it links the two
+//! transactions. Splitting outputs into powers of ten makes most notes hold
+//! one of a handful of values that every other wallet also uses.
+//!
+//! `denominate` gives the split, `OutputPolicy` is the hook `prepare` uses to
+//! apply it to payments and change, and `analyze_outputs` reports how
+//! identifying a proposed set of output amounts is. Each extra output is
+//! another commitment inserted on-chain, so `Denominations` caps the notes
+//! per output and folds whatever doesn't fit into one remainder note.
+
+use serde::{Deserialize, Serialize};
+
+/// Whether `amount` is a standard denomination (a power of ten).
+pub fn is_standard(amount: u64) -> bool {
+    let mut power = 1u64;
+    while power < amount {
+        match power.checked_mul(10) {
+            Some(next) => power = next,
+            None => return false,
+        }
+    }
+    power == amount
+}
+
+/// Digits of `amount` once trailing zeros are dropped (0 for zero).
+pub fn significant_digits(amount: u64) -> u32 {
+    let mut amount = amount;
+    while amount != 0 && amount.is_multiple_of(10) {
+        amount /= 10;
+    }
+    if amount == 0 { 0 } else { amount.ilog10() + 1 }
+}
+
+/// Split `amount` into powers of ten, one per unit of each decimal digit,
+/// largest first: 3_020 is `[1000, 1000, 1000, 10, 10]`.
+pub fn denominate(amount: u64) -> Vec<u64> {
+    let mut notes = Vec::new();
+    let mut rest = amount;
+    while rest > 0 {
+        let power = 10u64.pow(rest.ilog10());
+        for _ in 0..rest / power {
+            notes.push(power);
+        }
+        rest %= power;
+    }
+    notes
+}
+
+/// `denominate`, keeping at most `max_notes` notes: the largest
+/// `max_notes - 1` denominations, then everything else as one remainder.
+pub fn denominate_within(amount: u64, max_notes: usize) -> Vec<u64> {
+    let mut notes = denominate(amount);
+    let max_notes = max_notes.max(1);
+    if notes.len() > max_notes {
+        let remainder: u64 = notes.drain(max_notes - 1..).sum();
+        notes.push(remainder);
+    }
+    notes
+}
+
+/// How an output's value is split into notes.
+pub trait OutputPolicy {
+    /// Note amounts for an output of `amount` (non-zero, summing to it).
+    fn split(&self, amount: u64) -> Vec<u64>;
+}
+
+/// One note per output, for the exact amount.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExactAmounts;
+
+impl OutputPolicy for ExactAmounts {
+    fn split(&self, amount: u64) -> Vec<u64> {
+        vec![amount]
+    }
+}
+
+/// Split every output into standard denominations (`denominate_within`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Denominations {
+    /// Most notes one payment or change output is split into
+    pub max_notes_per_output: usize,
+}
+
+impl Default for Denominations {
+    fn default() -> Self {
+        Self { max_notes_per_output: 4 }
+    }
+}
+
+impl OutputPolicy for Denominations {
+    fn split(&self, amount: u64) -> Vec<u64> {
+        denominate_within(amount, self.max_notes_per_output)
+    }
+}
+
+/// How identifying one output amount is.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmountExposure {
+    pub amount: u64,
+    pub standard: bool,
+    pub significant_digits: u32,
+    /// log2 of the non-standard amounts with as many significant digits at
+    /// this magnitude (0 for standard amounts): roughly how much the amount
+    /// narrows down who it could be
+    pub identifying_bits: f64,
+}
+
+impl AmountExposure {
+    pub fn of(amount: u64) -> Self {
+        let standard = amount == 0 || is_standard(amount);
+        let significant_digits = significant_digits(amount);
+        let identifying_bits = if standard {
+            0.0
+        } else {
+            // 9 * 10^(d-1) amounts have d significant digits, one of them standard
+            (9.0 * 10f64.powi(significant_digits as i32 - 1) - 1.0).log2()
+        };
+        Self { amount, standard, significant_digits, identifying_bits }
+    }
+}
+
+/// How identifying a set of output amounts is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputAnalysis {
+    pub outputs: Vec<AmountExposure>,
+    /// Outputs that aren't a standard denomination
+    pub nonstandard: usize,
+    /// Sum of the outputs' `identifying_bits`
+    pub identifying_bits: f64,
+    /// The most identifying output, if any isn't standard
+    pub most_identifying: Option<u64>,
+}
+
+impl OutputAnalysis {
+    /// Whether every output is a standard denomination.
+    pub fn is_standard(&self) -> bool {
+        self.nonstandard == 0
+    }
+}
+
+/// Report how identifying `amounts` (a transaction's outputs) are.
+pub fn analyze_outputs(amounts: &[u64]) -> OutputAnalysis {
+    let outputs: Vec<AmountExposure> = amounts.iter().copied().map(AmountExposure::of).collect();
+    let most_identifying = outputs
+        .iter()
+        .filter(|o| !o.standard)
+        .max_by(|a, b| a.identifying_bits.total_cmp(&b.identifying_bits))
+        .map(|o| o.amount);
+    OutputAnalysis {
+        nonstandard: outputs.iter().filter(|o| !o.standard).count(),
+        identifying_bits: outputs.iter().map(|o| o.identifying_bits).sum(),
+        most_identifying,
+        outputs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denominate() {
+        assert_eq!(denominate(3_020), vec![1000, 1000, 1000, 10, 10]);
+        assert_eq!(denominate(0), Vec::<u64>::new());
+        assert_eq!(denominate(u64::MAX).iter().sum::<u64>(), u64::MAX);
+        assert!(denominate(987_654_321).iter().all(|&n| is_standard(n)));
+
+        assert_eq!(denominate_within(3_020, 3), vec![1000, 1000, 1020]);
+        assert_eq!(denominate_within(3_020, 0), vec![3_020]);
+        assert_eq!(Denominations::default().split(200), vec![100, 100]);
+        assert_eq!(ExactAmounts.split(3_020), vec![3_020]);
+    }
+
+    #[test]
+    fn test_is_standard() {
+        assert!(is_standard(1) && is_standard(1000) && is_standard(10_000_000_000_000_000_000));
+        assert!(!is_standard(0) && !is_standard(20) && !is_standard(u64::MAX));
+        assert_eq!(significant_digits(1_230_000), 3);
+        assert_eq!(significant_digits(0), 0);
+    }
+
+    #[test]
+    fn test_analysis_ranks_unique_amounts() {
+        let report = analyze_outputs(&[1000, 100, 1_234_567, 2_000]);
+        assert_eq!(report.nonstandard, 2);
+        assert_eq!(report.most_identifying, Some(1_234_567));
+        assert!(report.outputs[2].identifying_bits > report.outputs[3].identifying_bits);
+        assert_eq!(report.outputs[0].identifying_bits, 0.0);
+        assert!(!report.is_standard());
+
+        let denominated: Vec<u64> = denominate(1_234_567);
+        assert!(analyze_outputs(&denominated).is_standard());
+        assert_eq!(analyze_outputs(&[]).most_identifying, None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Denylist exclusion proofs
+//!
+//! A denylist is a set of frozen note commitments, committed to as a Merkle
+//! root over its sorted entries bracketed by the `0x00..00` and `0xff..ff`
+//! sentinels. A commitment is proven *not* denied by opening the two adjacent
+//! leaves that bracket it: `low < commitment < high` with `high` directly
+//! after `low` in the tree.
+//!
+//! The ledger doesn't enforce a denylist yet; when it publishes a
+//! `denylistRoot()`, the guest can check these proofs against it for every
+//! input. Until then the host only uses them to refuse spends that a
+//! denylisting deployment would reject.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hex::{encode_hex, Bytes32};
+use crate::merkle::{MerkleProof, MerkleTree};
+
+const LOW_SENTINEL: [u8; 32] = [0x00; 32];
+const HIGH_SENTINEL: [u8; 32] = [0xff; 32];
+
+/// Sorted denylist and its Merkle tree.
+#[derive(Debug, Clone)]
+pub struct Denylist {
+    /// Sentinel-bracketed, sorted, deduplicated entries (the tree's leaves)
+    leaves: Vec<[u8; 32]>,
+    tree: MerkleTree,
+}
+
+/// Proof that a commitment falls strictly between two adjacent denylist leaves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExclusionProof {
+    pub low: Bytes32,
+    pub high: Bytes32,
+    pub low_proof: MerkleProof,
+    pub high_proof: MerkleProof,
+}
+
+impl Denylist {
+    /// Build a denylist from `entries` (any order, duplicates ignored).
+    pub fn new(entries: impl IntoIterator<Item = [u8; 32]>) -> Self {
+        let mut leaves: Vec<[u8; 32]> = entries
+            .into_iter()
+            .filter(|e| *e != LOW_SENTINEL && *e != HIGH_SENTINEL)
+            .collect();
+        leaves.sort_unstable();
+        leaves.dedup();
+        leaves.insert(0, LOW_SENTINEL);
+        leaves.push(HIGH_SENTINEL);
+        let tree = MerkleTree::with_leaves(leaves.clone());
+        Self { leaves, tree }
+    }
+
+    /// Root the ledger would publish for this denylist.
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    /// Number of denied commitments (sentinels excluded).
+    pub fn len(&self) -> usize {
+        self.leaves.len() - 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, commitment: &[u8; 32]) -> bool {
+        *commitment != LOW_SENTINEL && *commitment != HIGH_SENTINEL && self.leaves.binary_search(commitment).is_ok()
+    }
+
+    /// Prove `commitment` is not on the denylist.
+    pub fn prove_exclusion(&self, commitment: &[u8; 32]) -> Result<ExclusionProof, String> {
+        let high_index = match self.leaves.binary_search(commitment) {
+            Ok(_) => return Err(format!("Commitment 0x{} is denylisted", encode_hex(commitment))),
+            Err(0) => return Err("Commitment 0x00..00 can't be proven excluded".to_string()),
+            Err(i) if i == self.leaves.len() => return Err("Commitment 0xff..ff can't be proven excluded".to_string()),
+            Err(i) => i,
+        };
+        let low_index = high_index - 1;
+        Ok(ExclusionProof {
+            low: Bytes32(self.leaves[low_index]),
+            high: Bytes32(self.leaves[high_index]),
+            low_proof: self.tree.prove(low_index).expect("low leaf in tree"),
+            high_proof: self.tree.prove(high_index).expect("high leaf in tree"),
+        })
+    }
+}
+
+/// Check that `proof` shows `commitment` is absent from the denylist with `root`.
+pub fn verify_exclusion(commitment: &[u8; 32], proof: &ExclusionProof, root: [u8; 32]) -> bool {
+    proof.low.0 < *commitment
+        && *commitment < proof.high.0
+        && proof.high_proof.leaf_index == proof.low_proof.leaf_index + 1
+        && MerkleTree::verify_proof(proof.low.0, &proof.low_proof, root)
+        && MerkleTree::verify_proof(proof.high.0, &proof.high_proof, root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(byte: u8) -> [u8; 32] {
+        [byte; 32]
+    }
+
+    #[test]
+    fn test_exclusion_proof_round_trip() {
+        let denylist = Denylist::new([entry(0x40), entry(0x10), entry(0x80), entry(0x10)]);
+        assert_eq!(denylist.len(), 3);
+
+        for candidate in [entry(0x01), entry(0x20), entry(0x7f), entry(0xfe)] {
+            let proof = denylist.prove_exclusion(&candidate).unwrap();
+            assert!(verify_exclusion(&candidate, &proof, denylist.root()));
+        }
+    }
+
+    #[test]
+    fn test_denylisted_commitment_has_no_proof() {
+        let denylist = Denylist::new([entry(0x40)]);
+        assert!(denylist.contains(&entry(0x40)));
+        assert!(denylist.prove_exclusion(&entry(0x40)).is_err());
+        assert!(!denylist.contains(&LOW_SENTINEL));
+    }
+
+    #[test]
+    fn test_proof_rejected_for_other_commitment_or_root() {
+        let denylist = Denylist::new([entry(0x40), entry(0x80)]);
+        let proof = denylist.prove_exclusion(&entry(0x50)).unwrap();
+
+        // The bracketing leaves don't cover a denied commitment
+        assert!(!verify_exclusion(&entry(0x40), &proof, denylist.root()));
+        assert!(!verify_exclusion(&entry(0x90), &proof, denylist.root()));
+        // A denylist that gained an entry between low and high has a new root
+        let grown = Denylist::new([entry(0x40), entry(0x50), entry(0x80)]);
+        assert!(!verify_exclusion(&entry(0x50), &proof, grown.root()));
+        // Non-adjacent leaves can't bracket a commitment
+        let wide = ExclusionProof {
+            low: Bytes32(LOW_SENTINEL),
+            high: proof.high,
+            low_proof: denylist.prove_exclusion(&entry(0x01)).unwrap().low_proof,
+            high_proof: proof.high_proof.clone(),
+        };
+        assert!(!verify_exclusion(&entry(0x50), &wide, denylist.root()));
+    }
+}
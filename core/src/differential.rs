@@ -0,0 +1,171 @@
+//! Differential testing of the verification paths
+//!
+//! The same transaction can be verified three ways:
+//! - **full**: `Ledger::apply_tx`, which looks the inputs up in the ledger and
+//!   recovers every signature from scratch (the original security model)
+//! - **precomputed**: `simulate_witness` on a witness carrying host-computed
+//!   nullifiers and commitments (what the guest runs on the optimized path)
+//! - **derived**: `simulate_witness` after `strip_derivable` and
+//!   `with_derived_values` (what the guest runs on a network-stripped witness)
+//!
+//! `run_differential` runs all three on one witness. They must accept and
+//! reject the same transactions and, when accepting, commit identical public
+//! outputs; any disagreement is semantic drift between the security models.
+//! Failure reasons are allowed to differ.
+
+use crate::ledger::{simulate_witness, Ledger, PublicOutputs};
+use crate::sp1_types::Witness;
+
+/// Outcome of each verification path for one witness.
+#[derive(Debug, Clone)]
+pub struct DifferentialResult {
+    pub full: Result<PublicOutputs, String>,
+    pub precomputed: Result<PublicOutputs, String>,
+    pub derived: Result<PublicOutputs, String>,
+}
+
+impl DifferentialResult {
+    /// Every path accepted with the same outputs, or every path rejected.
+    pub fn agrees(&self) -> bool {
+        match (&self.full, &self.precomputed, &self.derived) {
+            (Ok(full), Ok(precomputed), Ok(derived)) => full == precomputed && precomputed == derived,
+            (Err(_), Err(_), Err(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// The agreed outcome, or a description of the drift.
+    pub fn into_agreed(self) -> Result<Result<PublicOutputs, String>, String> {
+        if self.agrees() {
+            return Ok(self.precomputed);
+        }
+        Err(format!(
+            "Verification paths disagree:\n  full:        {}\n  precomputed: {}\n  derived:     {}",
+            describe(&self.full),
+            describe(&self.precomputed),
+            describe(&self.derived)
+        ))
+    }
+}
+
+fn describe(outcome: &Result<PublicOutputs, String>) -> String {
+    match outcome {
+        Ok(outputs) => format!("accepted {:?}", outputs),
+        Err(e) => format!("rejected ({})", e),
+    }
+}
+
+/// The full path: structure and value checks as in the guest, then
+/// `apply_tx` against the ledger's own notes. The witness's notes must be the
+/// ones stored at its input indices (the full path's notion of membership).
+fn full_path(ledger: &Ledger, witness: &Witness) -> Result<PublicOutputs, String> {
+    witness.validate_structure()?;
+    witness.validate_value_conservation()?;
+    for (i, (&index, note)) in witness.input_indices.iter().zip(&witness.input_notes).enumerate() {
+        if ledger.get_note(index) != Some(note) {
+            return Err(format!("Input {} is not the ledger's note at index {}", i, index));
+        }
+    }
+    ledger.clone().apply_tx(
+        &witness.input_indices,
+        &witness.nullifier_signatures,
+        &witness.tx_signatures,
+        witness.output_notes.clone(),
+    )
+}
+
+fn simulated_path(ledger: &Ledger, witness: &Witness) -> Result<PublicOutputs, String> {
+    witness.validate_structure()?;
+    witness.validate_value_conservation()?;
+    simulate_witness(&mut ledger.clone(), witness, ledger.current_root()).into_result()
+}
+
+/// Verify `witness` against `ledger` on every path; the ledger is not modified.
+///
+/// Precomputed values are recomputed from the witness first, so the paths
+/// are compared on the same transaction rather than on host honesty (a
+/// forged precomputed value is rejected by design and has no full-path
+/// counterpart).
+pub fn run_differential(ledger: &Ledger, witness: &Witness) -> DifferentialResult {
+    let precomputed = witness.clone().with_precomputed_values();
+    let derived = precomputed.clone().strip_derivable().with_derived_values();
+    DifferentialResult {
+        full: full_path(ledger, witness),
+        precomputed: simulated_path(ledger, &precomputed),
+        derived: simulated_path(ledger, &derived),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::{commit, compute_nullifier, Note};
+    use crate::signatures::{sign_message, tx_message};
+    use crate::testing::{TestOwner, WitnessBuilder};
+
+    /// Ledger holding one note per amount, and a signed witness spending them all.
+    fn setup(amounts: &[u64], outputs: Vec<Note>) -> (Ledger, Witness) {
+        let builder = amounts.iter().fold(WitnessBuilder::new(), |b, &amount| b.input(&TestOwner::random(), amount));
+        let (_, witness) = outputs.into_iter().fold(builder, WitnessBuilder::output_note).build().unwrap();
+        let mut ledger = Ledger::new();
+        for note in &witness.input_notes {
+            ledger.add_note(note.clone());
+        }
+        (ledger, witness)
+    }
+
+    #[test]
+    fn test_paths_agree_on_valid_transactions() {
+        for (amounts, outputs) in [
+            (vec![100], vec![Note::new(100, [9; 32], [1; 32])]),
+            (vec![60, 40], vec![Note::new(70, [9; 32], [1; 32]), Note::new(25, [8; 32], [2; 32])]),
+            (vec![50, 50, 50], vec![Note::new(0, [9; 32], [1; 32])]),
+        ] {
+            let (ledger, witness) = setup(&amounts, outputs);
+            let result = run_differential(&ledger, &witness);
+            let outputs = result.into_agreed().unwrap().unwrap();
+            assert_eq!(outputs.nullifiers.len(), amounts.len());
+            assert_eq!(outputs.old_root, ledger.current_root());
+        }
+    }
+
+    #[test]
+    fn test_paths_agree_on_rejections() {
+        let outputs = vec![Note::new(90, [9; 32], [1; 32]), Note::new(10, [8; 32], [2; 32])];
+
+        // Tx signature by someone other than the owner
+        let (ledger, mut witness) = setup(&[100], outputs.clone());
+        let nullifier = compute_nullifier(&witness.nullifier_signatures[0]);
+        let output_commitments: Vec<[u8; 32]> = witness.output_notes.iter().map(commit).collect();
+        witness.tx_signatures[0] = sign_message(&[0x42; 32], &tx_message(&nullifier, 0, &output_commitments)).unwrap().to_vec();
+        assert!(run_differential(&ledger, &witness).into_agreed().unwrap().is_err());
+
+        // Output changed after signing
+        let (ledger, mut witness) = setup(&[100], outputs.clone());
+        witness.output_notes[1].amount = 5;
+        assert!(run_differential(&ledger, &witness).into_agreed().unwrap().is_err());
+
+        // Outputs exceed inputs
+        let (ledger, mut witness) = setup(&[100], outputs.clone());
+        witness.output_notes[0].amount = 95;
+        assert!(run_differential(&ledger, &witness).into_agreed().unwrap().is_err());
+
+        // The same note spent twice in one transaction
+        let (ledger, mut witness) = setup(&[100, 100], outputs);
+        witness.input_notes[1] = witness.input_notes[0].clone();
+        witness.input_indices[1] = witness.input_indices[0];
+        witness.input_proofs[1] = witness.input_proofs[0].clone();
+        witness.nullifier_signatures[1] = witness.nullifier_signatures[0].clone();
+        witness.tx_signatures[1] = witness.tx_signatures[0].clone();
+        assert!(run_differential(&ledger, &witness).into_agreed().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_disagreement_is_reported() {
+        let (ledger, witness) = setup(&[100], vec![Note::new(100, [9; 32], [1; 32])]);
+        let mut result = run_differential(&ledger, &witness);
+        result.derived = Err("guest-only rejection".to_string());
+        assert!(!result.agrees());
+        assert!(result.into_agreed().unwrap_err().contains("guest-only rejection"));
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(feature = "encryption")]
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "encryption")]
-use crate::note::Note;
+use crate::note::{Note, NOTE_VERSION_V1};
 #[cfg(feature = "encryption")]
 use crate::encryption::{encrypt_note, decrypt_note, EncryptedNote, ViewPublicKey, ViewSecretKey};
 
@@ -24,13 +24,31 @@ impl NotePlaintext {
     }
     
     /// Serialize to bytes for encryption
+    ///
+    /// v1 notes keep the layout from before notes carried a version, so
+    /// recipients running older wallets can still decrypt them.
     pub fn to_bytes(&self) -> Vec<u8> {
+        if self.note.version == NOTE_VERSION_V1 {
+            let legacy = LegacyNotePlaintext {
+                amount: self.note.amount,
+                owner_pubkey: self.note.owner_pubkey,
+                blinding: self.note.blinding,
+                leaf_index_hint: self.leaf_index_hint,
+            };
+            return bincode::serialize(&legacy).expect("Serialization should not fail");
+        }
         bincode::serialize(self).expect("Serialization should not fail")
     }
     
-    /// Deserialize from decrypted bytes
+    /// Deserialize from decrypted bytes (either layout)
+    ///
+    /// The layouts differ in length for every note (the versioned one has
+    /// one more byte), so each is only accepted if it consumes all of `data`.
     pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
-        bincode::deserialize(data).map_err(|e| format!("Deserialization failed: {}", e))
+        if let Some(legacy) = deserialize_exact::<LegacyNotePlaintext>(data) {
+            return Ok(Self::new(Note::new(legacy.amount, legacy.owner_pubkey, legacy.blinding), legacy.leaf_index_hint));
+        }
+        deserialize_exact(data).ok_or_else(|| "Deserialization failed: not a note plaintext".to_string())
     }
     
     /// Encrypt this note for a recipient
@@ -46,6 +64,22 @@ impl NotePlaintext {
     }
 }
 
+/// `NotePlaintext` as encoded before `Note::version` existed (always v1)
+#[cfg(feature = "encryption")]
+#[derive(Serialize, Deserialize)]
+struct LegacyNotePlaintext {
+    amount: u64,
+    owner_pubkey: [u8; 32],
+    blinding: [u8; 32],
+    leaf_index_hint: Option<u64>,
+}
+
+#[cfg(feature = "encryption")]
+fn deserialize_exact<T: Serialize + for<'de> Deserialize<'de>>(data: &[u8]) -> Option<T> {
+    let value: T = bincode::deserialize(data).ok()?;
+    (bincode::serialized_size(&value).ok()? == data.len() as u64).then_some(value)
+}
+
 #[cfg(all(test, feature = "encryption"))]
 mod tests {
     use super::*;
@@ -69,6 +103,25 @@ mod tests {
         assert_eq!(decrypted.leaf_index_hint, Some(42));
     }
     
+    #[test]
+    fn test_plaintext_layouts_round_trip() {
+        for hint in [None, Some(7)] {
+            // v1 plaintexts are byte-identical to the pre-version layout
+            let v1 = NotePlaintext::new(Note::new(100, [1; 32], [2; 32]), hint);
+            let legacy = LegacyNotePlaintext { amount: 100, owner_pubkey: [1; 32], blinding: [2; 32], leaf_index_hint: hint };
+            assert_eq!(v1.to_bytes(), bincode::serialize(&legacy).unwrap());
+            assert_eq!(NotePlaintext::from_bytes(&v1.to_bytes()).unwrap().note, v1.note);
+
+            for version in [0, 2, 255] {
+                let note = Note::new(100, [1; 32], [2; 32]).with_version(version);
+                let parsed = NotePlaintext::from_bytes(&NotePlaintext::new(note.clone(), hint).to_bytes()).unwrap();
+                assert_eq!(parsed.note, note);
+                assert_eq!(parsed.leaf_index_hint, hint);
+            }
+        }
+        assert!(NotePlaintext::from_bytes(&[0u8; 10]).is_err());
+    }
+
     #[test]
     fn test_decrypt_with_wrong_key_returns_none() {
         let (_, public1) = generate_keypair();
@@ -104,6 +104,12 @@ pub fn encrypt_note(
 /// - Wrong key (ECDH produces different shared secret)
 /// - Corrupted ciphertext (GCM auth fails)
 /// - Wrong curve type
+///
+/// Scanners call this on every ciphertext on chain, so its timing must not
+/// depend on the view key: the early returns only look at the public key
+/// type and ephemeral key, secp256k1's ECDH is constant-time, and AES-GCM
+/// compares the tag in constant time, so a forged ciphertext fails in the
+/// same time however much of its tag is right.
 #[cfg(feature = "encryption")]
 pub fn decrypt_note(
     encrypted: &EncryptedNote,
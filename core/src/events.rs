@@ -0,0 +1,239 @@
+//! PrivateUTXOLedger event schemas
+//!
+//! Typed definitions of the ledger contract's events, shared by the indexer
+//! and relayer so log decoding can't drift from the on-chain ABI. Keep these
+//! in sync with `contracts/src/PrivateUTXOLedger.sol`.
+//!
+//! Commitment appends are emitted as `OutputCommitted` (one per leaf, with
+//! its `leafIndex`); every insert is followed by `RootUpdated`.
+
+use alloy_sol_types::{sol, SolEvent, Word};
+
+use crate::note::{commit, Note};
+use crate::wallet::OwnedNote;
+
+sol! {
+    /// Merkle root changed after a commitment insert
+    #[derive(Debug, PartialEq, Eq)]
+    event RootUpdated(bytes32 indexed oldRoot, bytes32 indexed newRoot);
+
+    /// A nullifier was marked spent
+    #[derive(Debug, PartialEq, Eq)]
+    event NullifierUsed(bytes32 indexed nullifier);
+
+    /// A commitment was appended to the tree, with its encrypted note
+    #[derive(Debug, PartialEq, Eq)]
+    event OutputCommitted(
+        bytes32 indexed commitment,
+        uint8 keyType,
+        bytes ephemeralPubkey,
+        bytes12 nonce,
+        bytes ciphertext,
+        uint256 leafIndex
+    );
+
+    #[derive(Debug, PartialEq, Eq)]
+    event Deposited(address indexed from, uint256 amount, bytes32 commitment, uint256 leafIndex);
+
+    #[derive(Debug, PartialEq, Eq)]
+    event Withdrawn(address indexed to, uint256 amount);
+
+    #[derive(Debug, PartialEq, Eq)]
+    event MetadataPosted(bytes32 indexed commitment, uint256 metadataSize);
+}
+
+/// Any event emitted by the ledger contract.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LedgerEvent {
+    RootUpdated(RootUpdated),
+    NullifierUsed(NullifierUsed),
+    OutputCommitted(OutputCommitted),
+    Deposited(Deposited),
+    Withdrawn(Withdrawn),
+    MetadataPosted(MetadataPosted),
+}
+
+/// Decode a raw log (topics + data) emitted by the ledger.
+///
+/// # Returns
+/// `Ok(None)` for events not covered here (e.g. `DepositAndTransfer`),
+/// `Err` if the signature matches but the payload doesn't decode.
+pub fn decode_ledger_log(topics: &[[u8; 32]], data: &[u8]) -> Result<Option<LedgerEvent>, String> {
+    let Some(topic0) = topics.first() else {
+        return Ok(None);
+    };
+    let words = topics.iter().map(|t| Word::from(*t));
+
+    fn decode<E: SolEvent>(
+        words: impl IntoIterator<Item = Word>,
+        data: &[u8],
+    ) -> Result<E, String> {
+        E::decode_raw_log(words, data, true)
+            .map_err(|e| format!("Failed to decode {}: {}", E::SIGNATURE, e))
+    }
+
+    let event = match Word::from(*topic0) {
+        RootUpdated::SIGNATURE_HASH => LedgerEvent::RootUpdated(decode(words, data)?),
+        NullifierUsed::SIGNATURE_HASH => LedgerEvent::NullifierUsed(decode(words, data)?),
+        OutputCommitted::SIGNATURE_HASH => LedgerEvent::OutputCommitted(decode(words, data)?),
+        Deposited::SIGNATURE_HASH => LedgerEvent::Deposited(decode(words, data)?),
+        Withdrawn::SIGNATURE_HASH => LedgerEvent::Withdrawn(decode(words, data)?),
+        MetadataPosted::SIGNATURE_HASH => LedgerEvent::MetadataPosted(decode(words, data)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+/// The wallet entry for a note the user shielded, from the logs of its
+/// deposit transaction's receipt as `(topics, data)` pairs.
+///
+/// Finds the `Deposited` event for `commit(note)` and takes its leaf index.
+/// The depositor knows the note's opening; the event only confirms where it
+/// landed.
+///
+/// # Errors
+/// Fails if no `Deposited` log carries the note's commitment, or if the
+/// deposited amount differs from the note's (the wrong opening was supplied).
+pub fn owned_note_from_deposit<'a>(
+    note: Note,
+    logs: impl IntoIterator<Item = (&'a [[u8; 32]], &'a [u8])>,
+) -> Result<OwnedNote, String> {
+    let commitment = commit(&note);
+    for (topics, data) in logs {
+        let Ok(Some(LedgerEvent::Deposited(deposit))) = decode_ledger_log(topics, data) else {
+            continue;
+        };
+        if deposit.commitment.0 != commitment {
+            continue;
+        }
+        if u64::try_from(deposit.amount).ok() != Some(note.amount) {
+            return Err(format!("Deposit of {} does not match the note amount {}", deposit.amount, note.amount));
+        }
+        let leaf_index = u64::try_from(deposit.leafIndex).map_err(|_| format!("Leaf index {} overflows u64", deposit.leafIndex))?;
+        return Ok(OwnedNote::new(note, leaf_index));
+    }
+    Err(format!("No Deposited event for commitment 0x{}", crate::hex::encode_hex(&commitment)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::{Digest, Keccak256};
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn topic(signature: &str) -> [u8; 32] {
+        Keccak256::digest(signature.as_bytes()).into()
+    }
+
+    #[test]
+    fn test_signatures_match_contract_abi() {
+        assert_eq!(RootUpdated::SIGNATURE_HASH.0, topic("RootUpdated(bytes32,bytes32)"));
+        assert_eq!(NullifierUsed::SIGNATURE_HASH.0, topic("NullifierUsed(bytes32)"));
+        assert_eq!(
+            OutputCommitted::SIGNATURE_HASH.0,
+            topic("OutputCommitted(bytes32,uint8,bytes,bytes12,bytes,uint256)")
+        );
+        assert_eq!(Deposited::SIGNATURE_HASH.0, topic("Deposited(address,uint256,bytes32,uint256)"));
+    }
+
+    #[test]
+    fn test_decode_output_committed_log() {
+        // keyType 0, 33-byte ephemeral key, 12-byte nonce, 40-byte ciphertext, leaf 7
+        let data = from_hex(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "00000000000000000000000000000000000000000000000000000000000000a0",
+            "0b0b0b0b0b0b0b0b0b0b0b0b0000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000100",
+            "0000000000000000000000000000000000000000000000000000000000000007",
+            "0000000000000000000000000000000000000000000000000000000000000021",
+            "02aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aa00000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000028",
+            "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc",
+            "cccccccccccccccc000000000000000000000000000000000000000000000000",
+        ));
+        let topics = [topic("OutputCommitted(bytes32,uint8,bytes,bytes12,bytes,uint256)"), [0x42; 32]];
+
+        let Some(LedgerEvent::OutputCommitted(event)) = decode_ledger_log(&topics, &data).unwrap() else {
+            panic!("expected OutputCommitted");
+        };
+        assert_eq!(event.commitment.0, [0x42; 32]);
+        assert_eq!(event.keyType, 0);
+        assert_eq!(event.ephemeralPubkey.len(), 33);
+        assert_eq!(event.nonce.0, [0x0b; 12]);
+        assert_eq!(event.ciphertext.to_vec(), vec![0xcc; 40]);
+        assert_eq!(event.leafIndex.to::<u64>(), 7);
+    }
+
+    #[test]
+    fn test_decode_deposit_root_and_nullifier_logs() {
+        let mut from = [0u8; 32];
+        from[12..].copy_from_slice(&[0x99; 20]);
+        let data = from_hex(concat!(
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "1111111111111111111111111111111111111111111111111111111111111111",
+            "0000000000000000000000000000000000000000000000000000000000000003",
+        ));
+        let deposit = decode_ledger_log(&[topic("Deposited(address,uint256,bytes32,uint256)"), from], &data);
+        let Some(LedgerEvent::Deposited(deposit)) = deposit.unwrap() else {
+            panic!("expected Deposited");
+        };
+        assert_eq!(deposit.from.0 .0, [0x99; 20]);
+        assert_eq!(deposit.amount.to::<u128>(), 1_000_000_000_000_000_000);
+        assert_eq!(deposit.commitment.0, [0x11; 32]);
+
+        let root = decode_ledger_log(&[topic("RootUpdated(bytes32,bytes32)"), [1; 32], [2; 32]], &[]).unwrap();
+        assert!(matches!(root, Some(LedgerEvent::RootUpdated(e)) if e.oldRoot.0 == [1; 32] && e.newRoot.0 == [2; 32]));
+
+        let nf = decode_ledger_log(&[topic("NullifierUsed(bytes32)"), [3; 32]], &[]).unwrap();
+        assert!(matches!(nf, Some(LedgerEvent::NullifierUsed(e)) if e.nullifier.0 == [3; 32]));
+    }
+
+    #[test]
+    fn test_owned_note_from_deposit_receipt() {
+        let note = Note::new(1000, [0x22; 32], [0x33; 32]);
+        let deposited = |amount: u64, commitment: [u8; 32]| {
+            let mut data = vec![0u8; 96];
+            data[24..32].copy_from_slice(&amount.to_be_bytes());
+            data[32..64].copy_from_slice(&commitment);
+            data[95] = 5;
+            data
+        };
+        let topics = vec![topic("Deposited(address,uint256,bytes32,uint256)"), [0; 32]];
+        let root_topics = vec![topic("RootUpdated(bytes32,bytes32)"), [1; 32], [2; 32]];
+        let other = deposited(1000, [0x44; 32]);
+        let ours = deposited(1000, commit(&note));
+        let logs = [(root_topics.as_slice(), &[][..]), (topics.as_slice(), other.as_slice()), (topics.as_slice(), ours.as_slice())];
+
+        let owned = owned_note_from_deposit(note.clone(), logs).unwrap();
+        assert_eq!(owned.leaf_index, 5);
+        assert_eq!(owned.commitment, commit(&note));
+        assert!(!owned.spent);
+
+        let mut wrong_amount = note.clone();
+        wrong_amount.amount = 999;
+        assert!(owned_note_from_deposit(wrong_amount, logs).unwrap_err().contains("No Deposited event"));
+        let short = deposited(999, commit(&note));
+        let err = owned_note_from_deposit(note, [(topics.as_slice(), short.as_slice())]).unwrap_err();
+        assert!(err.contains("does not match"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_and_malformed_logs() {
+        assert_eq!(decode_ledger_log(&[[0xee; 32]], &[]).unwrap(), None);
+        assert_eq!(decode_ledger_log(&[], &[]).unwrap(), None);
+
+        // Legacy OutputCommitted without leafIndex has a different topic
+        let legacy = topic("OutputCommitted(bytes32,uint8,bytes,bytes12,bytes)");
+        assert_eq!(decode_ledger_log(&[legacy, [0; 32]], &[]).unwrap(), None);
+
+        // Missing indexed topic
+        assert!(decode_ledger_log(&[topic("NullifierUsed(bytes32)")], &[]).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Cost of relaying a transaction, in the shielded token
+//!
+//! A relayer pays the gas of the ledger call. `FeeMarket::quote` prices that
+//! call for a transaction shape: the verifier's gas for the proof type (from
+//! `bench-proofs` measurements when available), the ledger's own execution,
+//! and calldata, at the current base fee plus priority fee, converted to the
+//! token and marked up by the relayer's margin, as `relay_cost`.
+//!
+//! This is what relaying costs, not a fee the relayer is repaid from: the
+//! ledger pays nothing out to the submitter, and the value a transaction
+//! leaves in the pool (inputs minus outputs, which the tx signatures cover)
+//! isn't in the public values, so it stays in the pool unclaimed. A relayer
+//! that charges for relaying collects it some other way.
+//!
+//! Execution gas is an estimate (`LedgerGas`), not a simulation; operators
+//! with gas reports for their deployment should override it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::calldata::ledger_calldata_len;
+use crate::ledger::PublicOutputs;
+use crate::public_values::{encode_compressed, encode_full, encode_withdrawal, should_compress};
+use crate::sp1_types::Withdrawal;
+
+/// Wei per ETH; `token_per_eth` prices this many wei.
+const WEI_PER_ETH: u128 = 1_000_000_000_000_000_000;
+
+/// Gas per calldata byte, counting every byte as nonzero (an upper bound).
+const CALLDATA_GAS_PER_BYTE: u64 = 16;
+
+/// Proof system a transaction is submitted on-chain with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofType {
+    Groth16,
+    Plonk,
+}
+
+impl ProofType {
+    /// Bytes of the proof argument (4-byte verifier selector + proof).
+    pub fn proof_len(self) -> usize {
+        match self {
+            ProofType::Groth16 => 4 + 8 * 32,
+            ProofType::Plonk => 4 + 27 * 32,
+        }
+    }
+}
+
+impl std::str::FromStr for ProofType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "groth16" => Ok(ProofType::Groth16),
+            "plonk" => Ok(ProofType::Plonk),
+            other => Err(format!("Unknown proof type {:?} (groth16, plonk)", other)),
+        }
+    }
+}
+
+/// Gas the SP1 verifier uses per proof type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyGas {
+    pub groth16: u64,
+    pub plonk: u64,
+}
+
+impl Default for VerifyGas {
+    /// Typical v5 verifier costs, used until benchmarks are supplied
+    fn default() -> Self {
+        Self { groth16: 280_000, plonk: 310_000 }
+    }
+}
+
+impl VerifyGas {
+    pub fn for_type(&self, proof_type: ProofType) -> u64 {
+        match proof_type {
+            ProofType::Groth16 => self.groth16,
+            ProofType::Plonk => self.plonk,
+        }
+    }
+
+    /// The highest measured gas per proof type (as `bench-proofs` reports
+    /// it), keeping the default for types without measurements.
+    pub fn from_benchmarks(measurements: impl IntoIterator<Item = (ProofType, u64)>) -> Self {
+        let mut measured: [Option<u64>; 2] = [None, None];
+        for (proof_type, gas) in measurements {
+            let slot = &mut measured[proof_type as usize];
+            *slot = Some(slot.map_or(gas, |max| max.max(gas)));
+        }
+        let default = Self::default();
+        Self { groth16: measured[0].unwrap_or(default.groth16), plonk: measured[1].unwrap_or(default.plonk) }
+    }
+}
+
+/// Estimated execution gas of the ledger call, excluding the verifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LedgerGas {
+    /// Intrinsic transaction gas plus the root history update
+    pub base: u64,
+    /// Nullifier check and store, per input
+    pub per_input: u64,
+    /// Tree insertion and `OutputCommitted` event, per output
+    pub per_output: u64,
+    /// Token transfer to the recipient
+    pub withdrawal: u64,
+}
+
+impl Default for LedgerGas {
+    fn default() -> Self {
+        Self { base: 21_000 + 45_000, per_input: 25_000, per_output: 50_000, withdrawal: 35_000 }
+    }
+}
+
+/// Prices and gas tables a quote is computed from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeMarket {
+    pub base_fee_wei: u128,
+    pub priority_fee_wei: u128,
+    /// Token base units worth 1 ETH (e.g. 3_000_000_000 for USDC at $3000)
+    pub token_per_eth: u128,
+    /// Relayer margin over the gas cost, in basis points
+    pub margin_bps: u32,
+    #[serde(default)]
+    pub verify_gas: VerifyGas,
+    #[serde(default)]
+    pub ledger_gas: LedgerGas,
+}
+
+/// The relaying cost of one transaction shape, with its breakdown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeQuote {
+    pub proof_type: ProofType,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub withdrawal: bool,
+    pub verify_gas: u64,
+    pub execution_gas: u64,
+    pub calldata_gas: u64,
+    pub total_gas: u64,
+    pub gas_price_wei: u128,
+    pub cost_wei: u128,
+    /// `cost_wei` plus margin, in token base units, rounded up
+    pub relay_cost: u64,
+}
+
+impl FeeMarket {
+    /// Quote the relaying cost of a transaction with `inputs` and `outputs`,
+    /// optionally paying out a withdrawal.
+    ///
+    /// # Errors
+    /// Fails on an empty transaction, a zero token price, or a cost that
+    /// overflows the note amount type.
+    pub fn quote(&self, proof_type: ProofType, inputs: usize, outputs: usize, withdrawal: bool) -> Result<FeeQuote, String> {
+        if inputs == 0 {
+            return Err("A relayed transaction spends at least one input".to_string());
+        }
+        if outputs == 0 && !withdrawal {
+            return Err("A transaction with no outputs must be a withdrawal".to_string());
+        }
+        if self.token_per_eth == 0 {
+            return Err("Token price (token per ETH) must be nonzero".to_string());
+        }
+
+        let verify_gas = self.verify_gas.for_type(proof_type);
+        let execution_gas = self.ledger_gas.base
+            + self.ledger_gas.per_input * inputs as u64
+            + self.ledger_gas.per_output * outputs as u64
+            + if withdrawal { self.ledger_gas.withdrawal } else { 0 };
+        let calldata_gas = calldata_len(proof_type, inputs, outputs, withdrawal) as u64 * CALLDATA_GAS_PER_BYTE;
+        let total_gas = verify_gas + execution_gas + calldata_gas;
+
+        let overflow = || "Relay cost quote overflows".to_string();
+        let gas_price_wei = self.base_fee_wei.checked_add(self.priority_fee_wei).ok_or_else(overflow)?;
+        let cost_wei = gas_price_wei.checked_mul(total_gas as u128).ok_or_else(overflow)?;
+        let with_margin = cost_wei
+            .checked_mul(10_000 + self.margin_bps as u128)
+            .ok_or_else(overflow)?
+            .div_ceil(10_000);
+        let relay_cost = with_margin
+            .checked_mul(self.token_per_eth)
+            .ok_or_else(overflow)?
+            .div_ceil(WEI_PER_ETH);
+        let relay_cost =
+            u64::try_from(relay_cost).map_err(|_| format!("Cost of {} token units overflows u64", relay_cost))?;
+
+        Ok(FeeQuote {
+            proof_type,
+            inputs,
+            outputs,
+            withdrawal,
+            verify_gas,
+            execution_gas,
+            calldata_gas,
+            total_gas,
+            gas_price_wei,
+            cost_wei,
+            relay_cost,
+        })
+    }
+}
+
+/// Calldata of the ledger call for this shape, as the prover sizes it.
+fn calldata_len(proof_type: ProofType, inputs: usize, outputs: usize, withdrawal: bool) -> usize {
+    let public_outputs = PublicOutputs {
+        chain_id: 0,
+        old_root: [0; 32],
+        nullifiers: vec![[0; 32]; inputs],
+        output_commitments: vec![[0; 32]; outputs],
+        withdrawal: withdrawal.then_some(Withdrawal { public_amount: 0, recipient: Default::default() }),
+        external_nullifier: None,
+    };
+    let (public_values, compressed) = match &public_outputs.withdrawal {
+        Some(w) => (encode_withdrawal(&public_outputs, w), false),
+        None if should_compress(&public_outputs) => (encode_compressed(&public_outputs), true),
+        None => (encode_full(&public_outputs), false),
+    };
+    ledger_calldata_len(&public_outputs, compressed, proof_type.proof_len(), public_values.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market() -> FeeMarket {
+        FeeMarket {
+            base_fee_wei: 10_000_000_000,
+            priority_fee_wei: 1_000_000_000,
+            token_per_eth: 3_000_000_000,
+            margin_bps: 0,
+            verify_gas: VerifyGas::default(),
+            ledger_gas: LedgerGas::default(),
+        }
+    }
+
+    #[test]
+    fn test_quote_covers_gas_at_current_price() {
+        let quote = market().quote(ProofType::Groth16, 2, 2, false).unwrap();
+        assert_eq!(quote.total_gas, quote.verify_gas + quote.execution_gas + quote.calldata_gas);
+        assert_eq!(quote.cost_wei, quote.total_gas as u128 * 11_000_000_000);
+        // Rounded up: never less than the gas cost
+        assert!(quote.relay_cost as u128 * WEI_PER_ETH >= quote.cost_wei * 3_000_000_000);
+
+        let margin = FeeMarket { margin_bps: 1_000, ..market() }.quote(ProofType::Groth16, 2, 2, false).unwrap();
+        assert!(margin.relay_cost >= quote.relay_cost * 11 / 10);
+
+        let plonk = market().quote(ProofType::Plonk, 2, 2, false).unwrap();
+        assert!(plonk.calldata_gas > quote.calldata_gas && plonk.relay_cost > quote.relay_cost);
+        let bigger = market().quote(ProofType::Groth16, 4, 4, false).unwrap();
+        assert!(bigger.relay_cost > quote.relay_cost);
+    }
+
+    #[test]
+    fn test_benchmarks_override_defaults() {
+        let gas = VerifyGas::from_benchmarks([(ProofType::Groth16, 250_000), (ProofType::Groth16, 260_000)]);
+        assert_eq!(gas.groth16, 260_000);
+        assert_eq!(gas.plonk, VerifyGas::default().plonk);
+    }
+
+    #[test]
+    fn test_invalid_quotes_rejected() {
+        assert!(market().quote(ProofType::Groth16, 0, 2, false).is_err());
+        assert!(market().quote(ProofType::Groth16, 1, 0, false).is_err());
+        assert!(market().quote(ProofType::Groth16, 1, 0, true).is_ok());
+        assert!(FeeMarket { token_per_eth: 0, ..market() }.quote(ProofType::Groth16, 1, 1, false).is_err());
+        assert!(FeeMarket { token_per_eth: u128::MAX, ..market() }.quote(ProofType::Groth16, 1, 1, false).is_err());
+    }
+}
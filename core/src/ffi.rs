@@ -0,0 +1,300 @@
+//! UniFFI bindings for mobile wallets
+//!
+//! Exposes the canonical commitment, nullifier, Merkle, note-encryption and
+//! witness-building code to Kotlin and Swift, so wallets don't re-implement
+//! it. Byte strings cross the boundary as `Vec<u8>` (UniFFI has no
+//! fixed-size arrays) and are length-checked on the way in; structured
+//! values (`ProofRequest`, `WalletState`) cross as JSON.
+//!
+//! Build the shared library, then point uniffi-bindgen (0.28) at it:
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! uniffi-bindgen generate --library target/release/libghostclaw_core.so --language kotlin --out-dir <dir>
+
+use std::fmt;
+
+use crate::encrypted_note::NotePlaintext;
+use crate::encryption::EncryptedNote;
+use crate::hex::fixed;
+use crate::merkle::{MerkleProof, MerkleTree};
+use crate::note::{self, Note};
+use crate::owner;
+use crate::prepare::{self, Recipient};
+use crate::proof_request::ProofRequest;
+use crate::sp1_types::{GuestInput, PublicInputs};
+use crate::wallet::WalletState;
+
+/// Errors surfaced to the foreign side.
+#[derive(Debug, uniffi::Error)]
+pub enum FfiError {
+    /// An argument had the wrong length or failed to parse
+    InvalidInput { reason: String },
+    /// The operation itself failed (bad signature, insufficient funds, ...)
+    Failed { reason: String },
+}
+
+impl fmt::Display for FfiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FfiError::InvalidInput { reason } => write!(f, "Invalid input: {}", reason),
+            FfiError::Failed { reason } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for FfiError {}
+
+/// A length check (`hex::fixed`) that failed.
+impl From<String> for FfiError {
+    fn from(reason: String) -> Self {
+        invalid(reason)
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> FfiError {
+    FfiError::InvalidInput { reason: reason.into() }
+}
+
+fn failed(reason: impl Into<String>) -> FfiError {
+    FfiError::Failed { reason: reason.into() }
+}
+
+/// A note, as passed to and from the foreign side.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiNote {
+    pub amount: u64,
+    /// 32 bytes
+    pub owner_pubkey: Vec<u8>,
+    /// 32 bytes
+    pub blinding: Vec<u8>,
+    /// Note format version (1 for every note created so far)
+    pub version: u8,
+}
+
+impl TryFrom<FfiNote> for Note {
+    type Error = FfiError;
+
+    fn try_from(note: FfiNote) -> Result<Self, FfiError> {
+        Ok(Note::new(
+            note.amount,
+            fixed("owner_pubkey", &note.owner_pubkey)?,
+            fixed("blinding", &note.blinding)?,
+        )
+        .with_version(note.version))
+    }
+}
+
+impl From<Note> for FfiNote {
+    fn from(note: Note) -> Self {
+        Self {
+            amount: note.amount,
+            owner_pubkey: note.owner_pubkey.to_vec(),
+            blinding: note.blinding.to_vec(),
+            version: note.version,
+        }
+    }
+}
+
+/// A decrypted note with its optional leaf index hint.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiNotePlaintext {
+    pub note: FfiNote,
+    pub leaf_index_hint: Option<u64>,
+}
+
+/// A payment to one recipient.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiRecipient {
+    /// 32 bytes
+    pub owner_pubkey: Vec<u8>,
+    pub amount: u64,
+}
+
+/// A prepared transaction and the wallet state to persist with it.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FfiPreparedTransaction {
+    /// Serialized `ProofRequest`
+    pub request_json: String,
+    /// Serialized `WalletState`, with the change blinding index advanced;
+    /// save it before submitting so the index is never reused
+    pub wallet_state_json: String,
+}
+
+/// The note commitment (32 bytes).
+#[uniffi::export]
+pub fn commit(note: FfiNote) -> Result<Vec<u8>, FfiError> {
+    Ok(note::commit(&note.try_into()?).to_vec())
+}
+
+/// The nullifier of a 65-byte nullifier signature (32 bytes).
+#[uniffi::export]
+pub fn compute_nullifier(signature: Vec<u8>) -> Result<Vec<u8>, FfiError> {
+    let signature: [u8; 65] = fixed("signature", &signature)?;
+    Ok(note::compute_nullifier(&signature).to_vec())
+}
+
+/// Check that `leaf` sits at `leaf_index` under `root`.
+#[uniffi::export]
+pub fn verify_merkle_proof(
+    leaf: Vec<u8>,
+    leaf_index: u64,
+    siblings: Vec<Vec<u8>>,
+    root: Vec<u8>,
+) -> Result<bool, FfiError> {
+    let siblings = siblings
+        .iter()
+        .map(|s| fixed("sibling", s))
+        .collect::<Result<Vec<[u8; 32]>, _>>()?;
+    Ok(MerkleTree::verify_proof(
+        fixed("leaf", &leaf)?,
+        &MerkleProof::new(leaf_index, siblings),
+        fixed("root", &root)?,
+    ))
+}
+
+/// Encrypt a note for a 33-byte compressed view key. Returns the blob
+/// published alongside the commitment.
+#[uniffi::export]
+pub fn encrypt_note(note: FfiNote, leaf_index_hint: Option<u64>, view_pubkey: Vec<u8>) -> Result<Vec<u8>, FfiError> {
+    let encrypted = NotePlaintext::new(note.try_into()?, leaf_index_hint)
+        .encrypt(&fixed("view_pubkey", &view_pubkey)?)
+        .map_err(failed)?;
+    bincode::serialize(&encrypted).map_err(|e| failed(format!("Serialize failed: {}", e)))
+}
+
+/// Try to decrypt a note blob with a 32-byte view secret key. Returns
+/// `None` if the note isn't addressed to this key.
+#[uniffi::export]
+pub fn decrypt_note(blob: Vec<u8>, view_secret: Vec<u8>) -> Result<Option<FfiNotePlaintext>, FfiError> {
+    let encrypted: EncryptedNote =
+        bincode::deserialize(&blob).map_err(|e| invalid(format!("Malformed note blob: {}", e)))?;
+    Ok(NotePlaintext::decrypt(&encrypted, &fixed("view_secret", &view_secret)?).map(|p| FfiNotePlaintext {
+        note: p.note.into(),
+        leaf_index_hint: p.leaf_index_hint,
+    }))
+}
+
+/// Owner pubkey (32 bytes) of the wallet derived from `seed`.
+#[uniffi::export]
+pub fn owner_pubkey_from_seed(seed: Vec<u8>) -> Result<Vec<u8>, FfiError> {
+    let spending_key = prepare::derive_spending_key(&seed).map_err(failed)?;
+    Ok(prepare::owner_pubkey(&spending_key).map_err(failed)?.to_vec())
+}
+
+/// Owner pubkey (32 bytes) for a 33-byte compressed or 65-byte uncompressed
+/// secp256k1 public key.
+#[uniffi::export]
+pub fn owner_pubkey_from_public_key(public_key: Vec<u8>) -> Result<Vec<u8>, FfiError> {
+    Ok(owner::owner_from_public_key(&public_key).map_err(invalid)?.to_vec())
+}
+
+/// Build a signed `ProofRequest` (JSON) paying `recipients`.
+///
+/// `wallet_state_json` is a serialized `WalletState`; `tree_leaves` are the
+/// ledger's commitments in insertion order, from which input proofs are
+/// built. `chain_id` is the chain of that ledger. The returned state has
+/// consumed a change blinding index and replaces the one passed in.
+#[uniffi::export]
+pub fn prepare_transaction(
+    chain_id: u64,
+    seed: Vec<u8>,
+    recipients: Vec<FfiRecipient>,
+    fee: u64,
+    wallet_state_json: String,
+    tree_leaves: Vec<Vec<u8>>,
+) -> Result<FfiPreparedTransaction, FfiError> {
+    let recipients = recipients
+        .iter()
+        .map(|r| {
+            Ok(Recipient {
+                owner_pubkey: fixed("owner_pubkey", &r.owner_pubkey)?,
+                amount: r.amount,
+            })
+        })
+        .collect::<Result<Vec<_>, FfiError>>()?;
+    let mut state: WalletState =
+        serde_json::from_str(&wallet_state_json).map_err(|e| invalid(format!("Malformed wallet state: {}", e)))?;
+    let leaves = tree_leaves
+        .iter()
+        .map(|leaf| fixed("leaf", leaf))
+        .collect::<Result<Vec<[u8; 32]>, _>>()?;
+
+    let request = prepare::prepare_transaction(chain_id, &seed, &recipients, fee, &mut state, &MerkleTree::with_leaves(leaves))
+        .map_err(failed)?;
+    let serialize_failed = |e: serde_json::Error| failed(format!("Serialize failed: {}", e));
+    Ok(FfiPreparedTransaction {
+        request_json: serde_json::to_string(&request).map_err(serialize_failed)?,
+        wallet_state_json: serde_json::to_string(&state).map_err(serialize_failed)?,
+    })
+}
+
+/// Build the guest's stdin for a `ProofRequest` (JSON): a bincode-encoded
+/// `GuestInput` envelope holding the request's public inputs and its witness
+/// with precomputed values.
+#[uniffi::export]
+pub fn build_witness(request_json: String) -> Result<Vec<u8>, FfiError> {
+    let mut request: ProofRequest =
+        serde_json::from_str(&request_json).map_err(|e| invalid(format!("Malformed request: {}", e)))?;
+    request.canonicalize_inputs().map_err(invalid)?;
+    let witness = request.to_witness().map_err(invalid)?;
+    witness.validate_structure().map_err(invalid)?;
+    let public_inputs = PublicInputs::new(request.old_root.0).with_chain_id(request.chain_id);
+    let input = GuestInput::new(public_inputs, witness.with_precomputed_values());
+    bincode::serialize(&input).map_err(|e| failed(format!("Serialize failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::generate_keypair;
+
+    fn sample_note() -> FfiNote {
+        Note::new(250, [4; 32], [5; 32]).into()
+    }
+
+    #[test]
+    fn test_ffi_matches_core() {
+        let note = sample_note();
+        assert_eq!(commit(note.clone()).unwrap(), note::commit(&note.clone().try_into().unwrap()).to_vec());
+
+        let mut short = note.clone();
+        short.blinding.pop();
+        assert!(matches!(commit(short), Err(FfiError::InvalidInput { .. })));
+
+        let tree = MerkleTree::with_leaves(vec![[1; 32], [2; 32]]);
+        let proof = tree.prove(1).unwrap();
+        let siblings: Vec<Vec<u8>> = proof.siblings.iter().map(|s| s.to_vec()).collect();
+        assert!(verify_merkle_proof(vec![2; 32], 1, siblings.clone(), tree.root().to_vec()).unwrap());
+        assert!(!verify_merkle_proof(vec![2; 32], 0, siblings, tree.root().to_vec()).unwrap());
+    }
+
+    #[test]
+    fn test_ffi_encryption_roundtrip() {
+        let (secret, public) = generate_keypair();
+        let (other_secret, _) = generate_keypair();
+        let blob = encrypt_note(sample_note(), Some(7), public.to_vec()).unwrap();
+
+        let plaintext = decrypt_note(blob.clone(), secret.to_vec()).unwrap().unwrap();
+        assert_eq!(plaintext.note, sample_note());
+        assert_eq!(plaintext.leaf_index_hint, Some(7));
+        assert!(decrypt_note(blob, other_secret.to_vec()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_built_witness_is_a_guest_input() {
+        let seed = b"ffi test seed".to_vec();
+        let owner = owner_pubkey_from_seed(seed.clone()).unwrap();
+        let notes = [Note::new(60, fixed("owner", &owner).unwrap(), [1; 32]), Note::new(50, [9; 32], [2; 32])];
+        let mut state = WalletState::new();
+        state.add_note(notes[0].clone(), 0);
+        let leaves: Vec<Vec<u8>> = notes.iter().map(|n| note::commit(n).to_vec()).collect();
+        let recipients = vec![FfiRecipient { owner_pubkey: owner.clone(), amount: 40 }];
+        let prepared =
+            prepare_transaction(5, seed, recipients, 1, serde_json::to_string(&state).unwrap(), leaves).unwrap();
+
+        let bytes = build_witness(prepared.request_json).unwrap();
+        let (public_inputs, witness) = bincode::deserialize::<GuestInput>(&bytes).unwrap().open().unwrap();
+        assert_eq!(public_inputs.chain_id, 5);
+        assert!(witness.has_precomputed_values());
+        assert!(matches!(build_witness("{}".to_string()), Err(FfiError::InvalidInput { .. })));
+    }
+}
@@ -5,14 +5,15 @@ use sp1_zkvm::io;
 
 use crate::ledger::{simulate_tx_and_build_public_outputs, simulate_tx_with_precomputed, Ledger};
 use crate::note::commit;
-use crate::sp1_types::{PublicInputs, Witness};
+use crate::sp1_types::GuestInput;
 
 entrypoint!(main);
 
 /// SP1 zkVM entry point for private UTXO transactions.
 ///
 /// # Proof Flow
-/// 1. Read public inputs (old_root) and private witness (notes, proofs)
+/// 1. Read public inputs (old_root) and private witness (notes, proofs),
+///    as one `GuestInput`
 /// 2. Validate witness structure and value conservation
 /// 3. Reconstruct ledger state from witness inputs
 /// 4. Verify old_root matches reconstructed state
@@ -37,8 +38,7 @@ pub fn main() {
     // STEP 1: Read inputs from host
     // ========================================================================
 
-    let public_inputs: PublicInputs = io::read();
-    let witness: Witness = io::read();
+    let (public_inputs, witness) = io::read::<GuestInput>().open().unwrap_or_else(|e| panic!("{}", e));
 
     // ========================================================================
     // STEP 2: Validate witness structure and constraints
@@ -86,7 +86,7 @@ pub fn main() {
     // 2. Use Merkle proofs to verify each input exists at its claimed index
     // 3. Validate proofs against old_root
     for (i, note) in witness.input_notes.iter().enumerate() {
-        let added_index = ledger.add_note(note.clone());
+        let added_index = ledger.add_note(note.clone()).index;
 
         // Verify the note was added at the expected index
         assert_eq!(
@@ -205,6 +205,7 @@ pub fn main() {
 mod tests {
     use super::*;
     use crate::note::Note;
+    use crate::sp1_types::{PublicInputs, Witness};
 
     // Note: These tests won't run in the zkVM, but help document expected behavior
 
@@ -0,0 +1,204 @@
+//! Content-addressed record of the guest ELF
+//!
+//! The guest links this crate, so any change under `core/src` needs a
+//! `cargo prove build` before the host's embedded ELF proves the new rules.
+//! `guest-elf.lock` records the sha256 of the committed ELF and a hash of
+//! the core sources it was built from; `build.rs` hashes the sources as
+//! they are now. The host compares the two (`check_elf`) at startup:
+//!
+//! - ELF hash differs from the record: the ELF was rebuilt (or swapped)
+//!   without regenerating the record.
+//! - Source hash differs from the record: core was edited since the ELF was
+//!   built, so the guest is stale.
+//!
+//! `record_for` renders a fresh record for the host's `elf-record` command.
+//!
+//! # Reproducible builds
+//! A plain `cargo prove build` uses whatever toolchain is installed, so its
+//! ELF can't be reproduced elsewhere. The committed ELF is built in SP1's
+//! docker image instead (`prover/program/build-reproducible.sh`), and the
+//! record pins the image by digest (`toolchain-sha256`). Anyone can then
+//! rebuild from the audited sources with the same image and compare hashes:
+//! the host's `verify-elf` command does that with `check_toolchain` and
+//! `check_rebuild`.
+
+use sha2::{Digest, Sha256};
+
+use crate::hex::Bytes32;
+
+/// Hash of this crate's sources, computed by `build.rs`.
+pub const CORE_SOURCE_SHA256: &str = env!("GHOSTCLAW_CORE_SOURCE_SHA256");
+
+/// Contents of `guest-elf.lock` at build time.
+pub const RECORD: &str = include_str!("../guest-elf.lock");
+
+/// Docker image reproducible guest builds use (SP1's, matching the guest's
+/// `sp1-zkvm`).
+pub const GUEST_TOOLCHAIN_IMAGE: &str = "ghcr.io/succinctlabs/sp1:v5.2.3";
+
+/// A parsed `guest-elf.lock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElfRecord {
+    pub elf_sha256: Bytes32,
+    pub core_source_sha256: Bytes32,
+    /// Digest of the `GUEST_TOOLCHAIN_IMAGE` the ELF was built in; `None`
+    /// for an ELF built outside docker, which can't be reproduced
+    pub toolchain_sha256: Option<Bytes32>,
+}
+
+impl ElfRecord {
+    /// Parse `key = 0x<hex>` lines; blank lines and `#` comments are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut elf_sha256 = None;
+        let mut core_source_sha256 = None;
+        let mut toolchain_sha256 = None;
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("Malformed ELF record line: {:?}", line))?;
+            let value: Bytes32 = value.trim().parse().map_err(|e| format!("Invalid {}: {}", key.trim(), e))?;
+            match key.trim() {
+                "elf-sha256" => elf_sha256 = Some(value),
+                "core-source-sha256" => core_source_sha256 = Some(value),
+                "toolchain-sha256" => toolchain_sha256 = Some(value),
+                other => return Err(format!("Unknown ELF record key: {}", other)),
+            }
+        }
+        Ok(ElfRecord {
+            elf_sha256: elf_sha256.ok_or("ELF record is missing elf-sha256")?,
+            core_source_sha256: core_source_sha256.ok_or("ELF record is missing core-source-sha256")?,
+            toolchain_sha256,
+        })
+    }
+
+    /// The record compiled into this crate.
+    pub fn committed() -> Result<Self, String> {
+        Self::parse(RECORD)
+    }
+
+    /// Check `elf` against this record and the current core sources.
+    pub fn check(&self, elf: &[u8]) -> Result<(), String> {
+        self.check_sources(elf, &current_source_hash()?)
+    }
+
+    fn check_sources(&self, elf: &[u8], sources: &Bytes32) -> Result<(), String> {
+        let actual = elf_sha256(elf);
+        if actual != self.elf_sha256 {
+            return Err(format!(
+                "Embedded guest ELF has sha256 {} but guest-elf.lock records {}; \
+                 regenerate the record with `elf-record --write` after rebuilding the guest",
+                actual, self.elf_sha256
+            ));
+        }
+        if *sources != self.core_source_sha256 {
+            return Err(format!(
+                "Core sources changed since the guest ELF was built (sources {}, ELF built from {}); \
+                 rebuild it with `cargo prove build` and regenerate guest-elf.lock",
+                sources, self.core_source_sha256
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check the toolchain image about to rebuild the ELF is the one it was
+    /// built in.
+    ///
+    /// # Errors
+    /// Fails if the digests differ, or if the record pins no toolchain.
+    pub fn check_toolchain(&self, image_digest: &Bytes32) -> Result<(), String> {
+        match self.toolchain_sha256 {
+            None => Err(format!(
+                "guest-elf.lock doesn't pin a toolchain; the ELF wasn't built in {} and can't be reproduced",
+                GUEST_TOOLCHAIN_IMAGE
+            )),
+            Some(pinned) if pinned != *image_digest => Err(format!(
+                "Toolchain image {} has digest {} but guest-elf.lock pins {}",
+                GUEST_TOOLCHAIN_IMAGE, image_digest, pinned
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Check an ELF rebuilt from the sources is byte-identical to the
+    /// recorded one.
+    pub fn check_rebuild(&self, rebuilt: &[u8]) -> Result<(), String> {
+        let actual = elf_sha256(rebuilt);
+        if actual != self.elf_sha256 {
+            return Err(format!(
+                "Rebuilt guest ELF has sha256 {} but guest-elf.lock records {}; \
+                 the committed ELF wasn't built from these sources with this toolchain",
+                actual, self.elf_sha256
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn elf_sha256(elf: &[u8]) -> Bytes32 {
+    Bytes32(Sha256::digest(elf).into())
+}
+
+fn current_source_hash() -> Result<Bytes32, String> {
+    CORE_SOURCE_SHA256.parse().map_err(|e| format!("Invalid core source hash: {}", e))
+}
+
+/// Check `elf` against the committed `guest-elf.lock`.
+pub fn check_elf(elf: &[u8]) -> Result<(), String> {
+    ElfRecord::committed()?.check(elf)
+}
+
+/// `guest-elf.lock` contents recording `elf` as built from the current
+/// sources, in the toolchain image with digest `toolchain` if it was.
+pub fn record_for(elf: &[u8], toolchain: Option<&Bytes32>) -> Result<String, String> {
+    let header: String = RECORD.lines().take_while(|l| l.starts_with('#')).map(|l| format!("{}\n", l)).collect();
+    let toolchain = toolchain.map(|digest| format!("toolchain-sha256 = {}\n", digest)).unwrap_or_default();
+    Ok(format!(
+        "{}elf-sha256 = {}\ncore-source-sha256 = {}\n{}",
+        header,
+        elf_sha256(elf),
+        current_source_hash()?,
+        toolchain
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_committed_record_parses() {
+        ElfRecord::committed().unwrap();
+    }
+
+    #[test]
+    fn test_fresh_record_accepts_its_elf() {
+        let elf = b"guest elf";
+        let record = ElfRecord::parse(&record_for(elf, None).unwrap()).unwrap();
+        assert_eq!(record.elf_sha256, elf_sha256(elf));
+        record.check(elf).unwrap();
+
+        let err = record.check(b"rebuilt guest elf").unwrap_err();
+        assert!(err.contains("elf-record"), "{}", err);
+    }
+
+    #[test]
+    fn test_core_edit_flags_stale_guest() {
+        let elf = b"guest elf";
+        let record = ElfRecord::parse(&record_for(elf, None).unwrap()).unwrap();
+        let err = record.check_sources(elf, &Bytes32([0x11; 32])).unwrap_err();
+        assert!(err.contains("cargo prove build"), "{}", err);
+    }
+
+    #[test]
+    fn test_reproduced_build_checks() {
+        let elf = b"guest elf";
+        let image = Bytes32([0x22; 32]);
+        let record = ElfRecord::parse(&record_for(elf, Some(&image)).unwrap()).unwrap();
+        assert_eq!(record.toolchain_sha256, Some(image));
+        record.check_toolchain(&image).unwrap();
+        record.check_rebuild(elf).unwrap();
+        assert!(record.check_toolchain(&Bytes32([0x33; 32])).unwrap_err().contains("pins"));
+        assert!(record.check_rebuild(b"other elf").is_err());
+
+        let unpinned = ElfRecord::parse(&record_for(elf, None).unwrap()).unwrap();
+        assert!(unpinned.check_toolchain(&image).unwrap_err().contains("doesn't pin"));
+    }
+}
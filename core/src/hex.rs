@@ -0,0 +1,313 @@
+//! Fixed-length byte strings with a 0x-hex text form
+//!
+//! `Bytes32` and `Bytes65` parse from and display as 0x-prefixed lowercase
+//! hex. They serialize as hex strings in human-readable formats (JSON) and
+//! as plain byte tuples in binary ones (bincode), so swapping a `[u8; N]`
+//! field for one doesn't change the bytes the guest reads.
+//!
+//! `bytes32` / `bytes32_vec` are `#[serde(with = ...)]` adapters for structs
+//! that keep raw arrays in memory but should read as hex in JSON.
+
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::str::FromStr;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Lowercase hex of `bytes`, without a prefix.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(DIGITS[(byte >> 4) as usize] as char);
+        out.push(DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Decode hex, with or without a `0x` prefix.
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    let digits = s.strip_prefix("0x").unwrap_or(s).as_bytes();
+    let pairs = digits.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(format!("Odd-length hex string ({} chars)", digits.len()));
+    }
+    pairs
+        .map(|pair| Ok((nibble(pair[0])? << 4) | nibble(pair[1])?))
+        .collect()
+}
+
+/// `bytes` as an `N`-byte array, or an error naming the argument (for the
+/// FFI and wasm bindings, which take byte strings of any length).
+#[cfg(any(feature = "ffi", feature = "wasm"))]
+pub(crate) fn fixed<const N: usize>(name: &str, bytes: &[u8]) -> Result<[u8; N], String> {
+    <[u8; N]>::try_from(bytes).map_err(|_| format!("{} must be {} bytes, got {}", name, N, bytes.len()))
+}
+
+fn nibble(c: u8) -> Result<u8, String> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(format!("Invalid hex character {:?}", c as char)),
+    }
+}
+
+macro_rules! fixed_bytes {
+    ($(#[$doc:meta])* $name:ident, $len:expr) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub [u8; $len]);
+
+        impl $name {
+            pub const LEN: usize = $len;
+
+            pub fn to_vec(&self) -> Vec<u8> {
+                self.0.to_vec()
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self([0u8; $len])
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl From<$name> for [u8; $len] {
+            fn from(bytes: $name) -> Self {
+                bytes.0
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = String;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, String> {
+                <[u8; $len]>::try_from(bytes)
+                    .map(Self)
+                    .map_err(|_| format!("Expected {} bytes, got {}", $len, bytes.len()))
+            }
+        }
+
+        impl Deref for $name {
+            type Target = [u8; $len];
+
+            fn deref(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl DerefMut for $name {
+            fn deref_mut(&mut self) -> &mut [u8; $len] {
+                &mut self.0
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl PartialEq<[u8; $len]> for $name {
+            fn eq(&self, other: &[u8; $len]) -> bool {
+                &self.0 == other
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, String> {
+                let digits = s.strip_prefix("0x").unwrap_or(s);
+                if digits.len() != $len * 2 {
+                    return Err(format!(
+                        "Expected {} bytes ({} hex chars), got {} chars",
+                        $len,
+                        $len * 2,
+                        digits.len()
+                    ));
+                }
+                Self::try_from(decode_hex(digits)?.as_slice())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "0x{}", encode_hex(&self.0))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    return serializer.collect_str(self);
+                }
+                let mut tuple = serializer.serialize_tuple($len)?;
+                for byte in &self.0 {
+                    tuple.serialize_element(byte)?;
+                }
+                tuple.end()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                if deserializer.is_human_readable() {
+                    let s = String::deserialize(deserializer)?;
+                    return s.parse().map_err(de::Error::custom);
+                }
+
+                struct TupleVisitor;
+
+                impl<'de> Visitor<'de> for TupleVisitor {
+                    type Value = [u8; $len];
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        write!(f, "{} bytes", $len)
+                    }
+
+                    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[u8; $len], A::Error> {
+                        let mut bytes = [0u8; $len];
+                        for (i, byte) in bytes.iter_mut().enumerate() {
+                            *byte = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                        }
+                        Ok(bytes)
+                    }
+                }
+
+                deserializer.deserialize_tuple($len, TupleVisitor).map(Self)
+            }
+        }
+    };
+}
+
+fixed_bytes!(
+    /// 20 bytes: Ethereum addresses.
+    Bytes20,
+    20
+);
+
+fixed_bytes!(
+    /// 32 bytes: commitments, nullifiers, roots, keys.
+    Bytes32,
+    32
+);
+
+fixed_bytes!(
+    /// 65 bytes: Ethereum-style signatures `[r (32), s (32), v (1)]`.
+    Bytes65,
+    65
+);
+
+/// `#[serde(with = "crate::hex::bytes32")]` for `[u8; 32]` fields.
+pub mod bytes32 {
+    use super::Bytes32;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        Bytes32(*bytes).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        Bytes32::deserialize(deserializer).map(|b| b.0)
+    }
+}
+
+/// `#[serde(with = "crate::hex::bytes32_vec")]` for `Vec<[u8; 32]>` fields.
+pub mod bytes32_vec {
+    use super::Bytes32;
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(items: &[[u8; 32]], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(items.iter().copied().map(Bytes32))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<[u8; 32]>, D::Error> {
+        Vec::<Bytes32>::deserialize(deserializer).map(|items| items.into_iter().map(|b| b.0).collect())
+    }
+}
+
+/// `#[serde(with = "crate::hex::bytes32_option")]` for `Option<[u8; 32]>`
+/// fields.
+pub mod bytes32_option {
+    use super::Bytes32;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<[u8; 32]>, serializer: S) -> Result<S::Ok, S::Error> {
+        bytes.map(Bytes32).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[u8; 32]>, D::Error> {
+        Option::<Bytes32>::deserialize(deserializer).map(|b| b.map(|b| b.0))
+    }
+}
+
+/// `#[serde(with = "crate::hex::bytes")]` for byte strings of any length
+/// (`Vec<u8>`, or arrays too long for serde's built-in impls).
+pub mod bytes {
+    use super::{decode_hex, encode_hex};
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, T: AsRef<[u8]>>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", encode_hex(bytes.as_ref())))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<Vec<u8>>>(deserializer: D) -> Result<T, D::Error> {
+        let bytes = decode_hex(&String::deserialize(deserializer)?).map_err(de::Error::custom)?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| de::Error::custom(format!("unexpected length {}", len)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let b: Bytes32 = "0xABcd000000000000000000000000000000000000000000000000000000000001".parse().unwrap();
+        assert_eq!(b.0[0], 0xab);
+        assert_eq!(b.0[31], 1);
+        assert_eq!(b.to_string(), "0xabcd000000000000000000000000000000000000000000000000000000000001");
+        assert_eq!(b.to_string()[2..].parse::<Bytes32>().unwrap(), b);
+
+        assert!("0x1234".parse::<Bytes32>().unwrap_err().contains("32 bytes"));
+        assert!("zz".repeat(65).parse::<Bytes65>().unwrap_err().contains("Invalid hex"));
+    }
+
+    #[test]
+    fn test_serde_is_hex_in_json_and_raw_in_bincode() {
+        let sig = Bytes65([7u8; 65]);
+
+        let json = serde_json::to_string(&sig).unwrap();
+        assert_eq!(json, format!("\"0x{}\"", "07".repeat(65)));
+        assert_eq!(serde_json::from_str::<Bytes65>(&json).unwrap(), sig);
+
+        let raw = bincode::serialize(&Bytes32([9u8; 32])).unwrap();
+        assert_eq!(raw, bincode::serialize(&[9u8; 32]).unwrap());
+        assert_eq!(bincode::deserialize::<Bytes32>(&raw).unwrap(), [9u8; 32]);
+    }
+}
@@ -0,0 +1,518 @@
+//! Query layer of the ledger indexer
+//!
+//! The indexer mirrors one deployment's `OutputCommitted` and
+//! `NullifierUsed` events into a `ChainState` and answers the lookups
+//! wallets and the prover-server would otherwise scrape events for: a leaf
+//! by index, a commitment's index and membership proof, a nullifier's
+//! status, and the root history. `StateSnapshot` is the whole state in the
+//! format `sp1-host reconcile` checks against the contract.
+//!
+//! Lists are paginated by position: a page holds up to `limit` entries
+//! (at most `MAX_PAGE_LIMIT`) from `cursor`, and `next` is the cursor of the
+//! following page, if any.
+//!
+//! Events are applied a transaction at a time (`ObservedTransaction`), each
+//! checked with `ChainState::verify_transition` first, so a transaction the
+//! ledger shouldn't have accepted, or whose `RootUpdated` disagrees with the
+//! indexer's tree, stops the indexer instead of being mirrored.
+//!
+//! A `Checkpoint` is a snapshot tagged with its deployment; the indexer
+//! writes one on shutdown and resumes from it instead of the deploy block.
+//!
+//! `ProofCache` keeps recently served membership proofs, keyed by root and
+//! commitment, so a wallet retrying the same spend (e.g. after fixing a
+//! signature) doesn't make the indexer walk the tree again. Entries only
+//! hold for the root they were built against: the first lookup after the
+//! root advances drops them all.
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chains::{ChainDeployment, ChainState};
+use crate::hex::{encode_hex, Bytes32};
+use crate::ledger::{PublicOutputs, TransitionReport};
+use crate::merkle::MerkleProof;
+use crate::note::Nullifier;
+
+/// Entries per page when the request doesn't say.
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+
+/// Most entries one page returns.
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<u64>,
+    pub total: u64,
+}
+
+fn page<S, T>(all: &[S], cursor: u64, limit: usize, entry: impl Fn(u64, &S) -> T) -> Page<T> {
+    let start = usize::try_from(cursor).unwrap_or(usize::MAX).min(all.len());
+    let end = start.saturating_add(limit.clamp(1, MAX_PAGE_LIMIT)).min(all.len());
+    Page {
+        items: all[start..end].iter().enumerate().map(|(i, s)| entry((start + i) as u64, s)).collect(),
+        next: (end < all.len()).then_some(end as u64),
+        total: all.len() as u64,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeafEntry {
+    pub index: u64,
+    pub commitment: Bytes32,
+}
+
+/// A leaf's membership proof against the indexer's current root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitmentProof {
+    pub index: u64,
+    pub commitment: Bytes32,
+    pub root: Bytes32,
+    pub proof: MerkleProof,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NullifierStatus {
+    pub nullifier: Bytes32,
+    pub spent: bool,
+}
+
+/// A root and its position in the history (0 is the empty tree).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootEntry {
+    pub position: u64,
+    pub root: Bytes32,
+}
+
+/// How far the indexer has synced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub chain_id: u64,
+    /// Last block whose events have been applied
+    pub block_number: u64,
+    pub leaf_count: u64,
+    pub root: Bytes32,
+}
+
+/// The indexer's whole state, as `sp1-host reconcile` reads it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSnapshot {
+    pub block_number: u64,
+    pub root: Bytes32,
+    pub leaf_count: u64,
+    pub nullifiers: Vec<Bytes32>,
+    pub leaves: Vec<Bytes32>,
+}
+
+/// A snapshot and the deployment it's of, to resume indexing from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub deployment: ChainDeployment,
+    pub state: StateSnapshot,
+}
+
+/// The ledger events of one transaction, in log order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObservedTransaction {
+    /// From `NullifierUsed`
+    pub nullifiers: Vec<Nullifier>,
+    /// Leaf index and commitment of each `OutputCommitted`
+    pub commitments: Vec<(u64, [u8; 32])>,
+    /// `newRoot` of the last `RootUpdated`, if the transaction inserted any leaf
+    pub new_root: Option<[u8; 32]>,
+}
+
+/// One deployment's events, indexed for lookup.
+#[derive(Debug, Clone)]
+pub struct IndexerState {
+    chain: ChainState,
+    /// First leaf holding each commitment
+    indices: HashMap<[u8; 32], u64>,
+    synced_block: u64,
+}
+
+impl IndexerState {
+    pub fn new(deployment: ChainDeployment) -> Self {
+        Self { chain: ChainState::new(deployment), indices: HashMap::new(), synced_block: 0 }
+    }
+
+    /// Rebuild the state a checkpoint was taken of.
+    ///
+    /// # Errors
+    /// Fails if the checkpoint is of another deployment, or its leaves don't
+    /// hash to its root.
+    pub fn resume(deployment: ChainDeployment, checkpoint: &Checkpoint) -> Result<Self, String> {
+        if checkpoint.deployment != deployment {
+            return Err(format!(
+                "Checkpoint is of ledger {} on chain {}, not {} on chain {}",
+                checkpoint.deployment.ledger, checkpoint.deployment.chain_id, deployment.ledger, deployment.chain_id
+            ));
+        }
+        let snapshot = &checkpoint.state;
+        let mut state = Self::new(deployment);
+        for (index, leaf) in snapshot.leaves.iter().enumerate() {
+            state.insert_commitment(index as u64, leaf.0)?;
+        }
+        for nullifier in &snapshot.nullifiers {
+            state.spend(nullifier.0)?;
+        }
+        if state.chain.root() != snapshot.root.0 || snapshot.leaf_count != snapshot.leaves.len() as u64 {
+            return Err(format!(
+                "Checkpoint at block {} is corrupt: {} leaves hash to 0x{}, not 0x{}",
+                snapshot.block_number,
+                snapshot.leaves.len(),
+                encode_hex(&state.chain.root()),
+                encode_hex(&snapshot.root.0)
+            ));
+        }
+        state.set_synced_block(snapshot.block_number);
+        Ok(state)
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint { deployment: self.chain.deployment.clone(), state: self.snapshot() }
+    }
+
+    /// The next block whose events haven't been applied.
+    pub fn next_block(&self) -> u64 {
+        self.synced_block + 1
+    }
+
+    pub fn chain(&self) -> &ChainState {
+        &self.chain
+    }
+
+    /// Record that every event up to `block` has been applied.
+    pub fn set_synced_block(&mut self, block: u64) {
+        self.synced_block = self.synced_block.max(block);
+    }
+
+    /// Apply an `OutputCommitted` event.
+    ///
+    /// # Errors
+    /// Fails if `leaf_index` isn't the next leaf (a missed or repeated event).
+    pub fn insert_commitment(&mut self, leaf_index: u64, commitment: [u8; 32]) -> Result<(), String> {
+        let expected = self.chain.tree().leaf_count() as u64;
+        if leaf_index != expected {
+            return Err(format!("OutputCommitted for leaf {} but the next leaf is {}", leaf_index, expected));
+        }
+        self.chain.insert_commitment(commitment);
+        self.indices.entry(commitment).or_insert(leaf_index);
+        Ok(())
+    }
+
+    /// Check a transaction's events with `ChainState::verify_transition`
+    /// and apply them.
+    ///
+    /// The proof's old root isn't in the events, so the transition is
+    /// checked from the current root; its nullifiers must be unspent, and
+    /// its commitments must land on the next leaves and give the root its
+    /// last `RootUpdated` reports.
+    ///
+    /// # Errors
+    /// Fails, without changing state, on any of those checks.
+    pub fn apply_transaction(&mut self, tx: &ObservedTransaction) -> Result<TransitionReport, String> {
+        let outputs = PublicOutputs {
+            chain_id: self.chain.chain_id(),
+            old_root: self.chain.root(),
+            nullifiers: tx.nullifiers.clone(),
+            output_commitments: tx.commitments.iter().map(|(_, commitment)| *commitment).collect(),
+            withdrawal: None,
+            external_nullifier: None,
+        };
+        let report = self.chain.verify_transition(&outputs)?;
+        for (i, (leaf_index, _)) in tx.commitments.iter().enumerate() {
+            let expected = report.first_leaf_index as u64 + i as u64;
+            if *leaf_index != expected {
+                return Err(format!("OutputCommitted for leaf {} but the next leaf is {}", leaf_index, expected));
+            }
+        }
+        if let Some(new_root) = tx.new_root.filter(|root| *root != report.new_root) {
+            return Err(format!(
+                "RootUpdated reports root 0x{} but the transaction's leaves give 0x{}",
+                encode_hex(&new_root),
+                encode_hex(&report.new_root)
+            ));
+        }
+
+        for nullifier in &tx.nullifiers {
+            self.spend(*nullifier)?;
+        }
+        for (leaf_index, commitment) in &tx.commitments {
+            self.insert_commitment(*leaf_index, *commitment)?;
+        }
+        Ok(report)
+    }
+
+    /// Apply a `NullifierUsed` event.
+    pub fn spend(&mut self, nullifier: Nullifier) -> Result<(), String> {
+        self.chain.spend(nullifier)
+    }
+
+    pub fn leaf(&self, index: u64) -> Option<LeafEntry> {
+        let commitment = self.chain.tree().get_leaf(usize::try_from(index).ok()?)?;
+        Some(LeafEntry { index, commitment: Bytes32(commitment) })
+    }
+
+    pub fn leaves(&self, cursor: u64, limit: usize) -> Page<LeafEntry> {
+        page(self.chain.tree().leaves(), cursor, limit, |index, c| LeafEntry { index, commitment: Bytes32(*c) })
+    }
+
+    /// The first leaf holding `commitment`.
+    pub fn index_of(&self, commitment: &[u8; 32]) -> Option<LeafEntry> {
+        let index = *self.indices.get(commitment)?;
+        Some(LeafEntry { index, commitment: Bytes32(*commitment) })
+    }
+
+    pub fn proof_of(&self, commitment: &[u8; 32]) -> Option<CommitmentProof> {
+        let LeafEntry { index, commitment } = self.index_of(commitment)?;
+        let proof = self.chain.tree().prove(index as usize)?;
+        Some(CommitmentProof { index, commitment, root: Bytes32(self.chain.root()), proof })
+    }
+
+    pub fn nullifier_status(&self, nullifier: &Nullifier) -> NullifierStatus {
+        NullifierStatus { nullifier: Bytes32(*nullifier), spent: self.chain.is_spent(nullifier) }
+    }
+
+    pub fn roots(&self, cursor: u64, limit: usize) -> Page<RootEntry> {
+        page(self.chain.root_history(), cursor, limit, |position, r| RootEntry { position, root: Bytes32(*r) })
+    }
+
+    /// The latest position `root` held in the history, if it ever did.
+    pub fn root_entry(&self, root: &[u8; 32]) -> Option<RootEntry> {
+        let position = self.chain.root_history().iter().rposition(|r| r == root)?;
+        Some(RootEntry { position: position as u64, root: Bytes32(*root) })
+    }
+
+    pub fn sync_status(&self) -> SyncStatus {
+        SyncStatus {
+            chain_id: self.chain.chain_id(),
+            block_number: self.synced_block,
+            leaf_count: self.chain.tree().leaf_count() as u64,
+            root: Bytes32(self.chain.root()),
+        }
+    }
+
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            block_number: self.synced_block,
+            root: Bytes32(self.chain.root()),
+            leaf_count: self.chain.tree().leaf_count() as u64,
+            nullifiers: self.chain.nullifiers().sorted().into_iter().map(Bytes32).collect(),
+            leaves: self.chain.tree().leaves().iter().copied().map(Bytes32).collect(),
+        }
+    }
+}
+
+/// Proofs served most recently.
+pub const DEFAULT_PROOF_CACHE_CAPACITY: usize = 1024;
+
+/// Membership proofs already built against the current root, oldest evicted
+/// first once `capacity` is reached.
+#[derive(Debug, Clone)]
+pub struct ProofCache {
+    capacity: usize,
+    proofs: HashMap<([u8; 32], [u8; 32]), CommitmentProof>,
+    order: VecDeque<([u8; 32], [u8; 32])>,
+    root: Option<[u8; 32]>,
+    hits: u64,
+}
+
+impl Default for ProofCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROOF_CACHE_CAPACITY)
+    }
+}
+
+impl ProofCache {
+    /// A cache of up to `capacity` proofs (0 disables it).
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, proofs: HashMap::new(), order: VecDeque::new(), root: None, hits: 0 }
+    }
+
+    /// `state.proof_of(commitment)`, from the cache when it was already
+    /// built against `state`'s current root.
+    pub fn proof_of(&mut self, state: &IndexerState, commitment: &[u8; 32]) -> Option<CommitmentProof> {
+        let root = state.chain().root();
+        if self.root != Some(root) {
+            self.invalidate();
+            self.root = Some(root);
+        }
+        let key = (root, *commitment);
+        if let Some(proof) = self.proofs.get(&key) {
+            self.hits += 1;
+            return Some(proof.clone());
+        }
+        let proof = state.proof_of(commitment)?;
+        if self.capacity > 0 {
+            if self.order.len() >= self.capacity {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.proofs.remove(&oldest);
+                }
+            }
+            self.order.push_back(key);
+            self.proofs.insert(key, proof.clone());
+        }
+        Some(proof)
+    }
+
+    /// Drop every cached proof, e.g. when the indexer applies new leaves.
+    pub fn invalidate(&mut self) {
+        self.proofs.clear();
+        self.order.clear();
+        self.root = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// Lookups answered from the cache so far.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+
+    fn indexer() -> IndexerState {
+        IndexerState::new(ChainDeployment { chain_id: 31337, ledger: "0x00".to_string() })
+    }
+
+    #[test]
+    fn test_lookups_and_pagination() {
+        let mut state = indexer();
+        for i in 0..5u8 {
+            state.insert_commitment(i as u64, [i + 1; 32]).unwrap();
+        }
+        assert!(state.insert_commitment(7, [9; 32]).is_err());
+        state.spend([0xaa; 32]).unwrap();
+        state.set_synced_block(42);
+
+        assert_eq!(state.leaf(2).unwrap().commitment, Bytes32([3; 32]));
+        assert!(state.leaf(5).is_none());
+        assert_eq!(state.index_of(&[4; 32]).unwrap().index, 3);
+
+        let proof = state.proof_of(&[4; 32]).unwrap();
+        assert_eq!(proof.root.0, state.chain().root());
+        assert!(MerkleTree::verify_proof([4; 32], &proof.proof, proof.root.0));
+        assert!(state.proof_of(&[9; 32]).is_none());
+
+        assert!(state.nullifier_status(&[0xaa; 32]).spent);
+        assert!(!state.nullifier_status(&[0xbb; 32]).spent);
+
+        let first = state.leaves(0, 2);
+        assert_eq!((first.items.len(), first.next, first.total), (2, Some(2), 5));
+        let last = state.leaves(4, 2);
+        assert_eq!((last.items[0].index, last.next), (4, None));
+        assert!(state.leaves(9, 2).items.is_empty());
+
+        let roots = state.roots(0, DEFAULT_PAGE_LIMIT);
+        assert_eq!(roots.total, 6);
+        assert_eq!(state.root_entry(&state.chain().root()).unwrap().position, 5);
+
+        let snapshot = state.snapshot();
+        assert_eq!((snapshot.block_number, snapshot.leaf_count, snapshot.nullifiers.len()), (42, 5, 1));
+        assert_eq!(state.sync_status().block_number, 42);
+    }
+
+    #[test]
+    fn test_transactions_are_verified_before_applying() {
+        let mut state = indexer();
+        let deposit = ObservedTransaction { commitments: vec![(0, [1; 32])], ..Default::default() };
+        let report = state.apply_transaction(&deposit).unwrap();
+        assert_eq!(report.new_root, state.chain().root());
+
+        let mut expected = state.chain().tree().clone();
+        expected.push_leaf([2; 32]);
+        let transfer = ObservedTransaction {
+            nullifiers: vec![[9; 32]],
+            commitments: vec![(1, [2; 32])],
+            new_root: Some(expected.root()),
+        };
+        state.apply_transaction(&transfer).unwrap();
+        assert!(state.nullifier_status(&[9; 32]).spent);
+
+        // Nothing is applied from a transaction that doesn't verify
+        let respend = ObservedTransaction { nullifiers: vec![[9; 32]], ..transfer.clone() };
+        assert!(state.apply_transaction(&respend).unwrap_err().contains("already spent"));
+        let skipped = ObservedTransaction { nullifiers: vec![[8; 32]], commitments: vec![(3, [3; 32])], new_root: None };
+        assert!(state.apply_transaction(&skipped).unwrap_err().contains("next leaf is 2"));
+        let wrong_root = ObservedTransaction { commitments: vec![(2, [3; 32])], new_root: Some([7; 32]), ..skipped };
+        assert!(state.apply_transaction(&wrong_root).unwrap_err().contains("RootUpdated"));
+        assert!(!state.nullifier_status(&[8; 32]).spent);
+        assert_eq!(state.sync_status().leaf_count, 2);
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let mut state = indexer();
+        for i in 0..3u8 {
+            state.insert_commitment(i as u64, [i + 1; 32]).unwrap();
+        }
+        state.spend([0xaa; 32]).unwrap();
+        state.set_synced_block(7);
+
+        let checkpoint = state.checkpoint();
+        let resumed = IndexerState::resume(checkpoint.deployment.clone(), &checkpoint).unwrap();
+        assert_eq!(resumed.snapshot(), state.snapshot());
+        assert_eq!(resumed.roots(0, DEFAULT_PAGE_LIMIT), state.roots(0, DEFAULT_PAGE_LIMIT));
+        assert_eq!(resumed.next_block(), 8);
+
+        let other = ChainDeployment { chain_id: 1, ledger: "0x00".to_string() };
+        assert!(IndexerState::resume(other, &checkpoint).unwrap_err().contains("chain 31337"));
+        let mut corrupt = checkpoint.clone();
+        corrupt.state.leaves.pop();
+        assert!(IndexerState::resume(checkpoint.deployment.clone(), &corrupt).unwrap_err().contains("corrupt"));
+    }
+
+    #[test]
+    fn test_proof_cache_follows_the_root() {
+        let mut state = indexer();
+        for i in 0..4u8 {
+            state.insert_commitment(i as u64, [i + 1; 32]).unwrap();
+        }
+        let mut cache = ProofCache::new(2);
+        let first = cache.proof_of(&state, &[2; 32]).unwrap();
+        let again = cache.proof_of(&state, &[2; 32]).unwrap();
+        assert_eq!((again.root, again.proof.siblings.clone()), (first.root, first.proof.siblings.clone()));
+        assert_eq!(cache.hits(), 1);
+        assert!(cache.proof_of(&state, &[9; 32]).is_none());
+
+        cache.proof_of(&state, &[3; 32]).unwrap();
+        cache.proof_of(&state, &[4; 32]).unwrap();
+        assert_eq!(cache.len(), 2);
+        cache.proof_of(&state, &[2; 32]).unwrap();
+        assert_eq!(cache.hits(), 1, "the oldest entry was evicted");
+
+        // A new leaf moves the root; the old proofs no longer verify against it
+        state.insert_commitment(4, [5; 32]).unwrap();
+        let fresh = cache.proof_of(&state, &[2; 32]).unwrap();
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(fresh.root.0, state.chain().root());
+        assert!(MerkleTree::verify_proof([2; 32], &fresh.proof, fresh.root.0));
+        assert_ne!(fresh.root, first.root);
+
+        let mut disabled = ProofCache::new(0);
+        disabled.proof_of(&state, &[2; 32]).unwrap();
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Canonical input ordering
+//!
+//! A spend's inputs could be listed in any order, and each order is a
+//! different request: a different `request_hash`, different public values
+//! (nullifiers are committed in input order) and signatures that sit at
+//! different positions. Inputs are instead sorted by leaf index, so the
+//! same logical spend always yields the same request, and a signature can't
+//! be moved to another input's slot by resubmitting the inputs reordered.
+//!
+//! Two inputs at one leaf index can't both be in the tree; ties are still
+//! broken by commitment so the order is total, and two inputs with the same
+//! index and commitment are the same note spent twice.
+//!
+//! `Witness::validate_structure` rejects inputs out of this order, so
+//! wallets sort before signing (`prepare` does); `canonicalize_inputs` on
+//! a `Witness` or `ProofRequest` reorders one built otherwise. Each input's
+//! signatures only cover its own nullifier, so reordering after signing
+//! keeps them valid, but a `NullifierTreeUpdate` is built in input order
+//! and has to be rebuilt.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Positions of the inputs in canonical order: `order[k]` is the input
+/// that goes `k`th. `commitment_of(i)` is only called on index ties.
+pub fn input_order(indices: &[usize], commitment_of: impl Fn(usize) -> [u8; 32]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..indices.len()).collect();
+    order.sort_by(|&a, &b| indices[a].cmp(&indices[b]).then_with(|| commitment_of(a).cmp(&commitment_of(b))));
+    order
+}
+
+/// Check inputs are in strictly ascending (leaf index, commitment) order.
+///
+/// # Errors
+/// Names the first input out of order, or the repeated one.
+pub fn check_input_order(indices: &[usize], commitment_of: impl Fn(usize) -> [u8; 32]) -> Result<(), String> {
+    for i in 1..indices.len() {
+        let (previous, index) = (indices[i - 1], indices[i]);
+        if index > previous {
+            continue;
+        }
+        if index < previous {
+            return Err(format!(
+                "Inputs must be in ascending leaf index order: input {} at leaf {} follows leaf {}",
+                i, index, previous
+            ));
+        }
+        let (previous, commitment) = (commitment_of(i - 1), commitment_of(i));
+        if commitment == previous {
+            return Err(format!("Input {} repeats the note at leaf {}", i, index));
+        }
+        if commitment < previous {
+            return Err(format!("Inputs {} and {} share leaf {} and must be ordered by commitment", i - 1, i, index));
+        }
+    }
+    Ok(())
+}
+
+/// Reorder `items` (one per input, or empty) by `order`.
+pub fn permute<T>(items: &mut Vec<T>, order: &[usize]) {
+    if items.len() != order.len() {
+        return;
+    }
+    let mut taken: Vec<Option<T>> = items.drain(..).map(Some).collect();
+    items.extend(order.iter().map(|&i| taken[i].take().expect("order is a permutation")));
+}
+
+/// Whether `order` leaves every input where it is.
+pub fn is_identity(order: &[usize]) -> bool {
+    order.iter().enumerate().all(|(k, &i)| k == i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inputs_sort_by_leaf_index_then_commitment() {
+        let indices = [9, 2, 5, 2];
+        let commitments = [[1u8; 32], [8; 32], [3; 32], [4; 32]];
+        let order = input_order(&indices, |i| commitments[i]);
+        assert_eq!(order, vec![3, 1, 2, 0]);
+
+        let mut sorted = indices.to_vec();
+        permute(&mut sorted, &order);
+        assert_eq!(sorted, vec![2, 2, 5, 9]);
+        let mut sorted_commitments = commitments.to_vec();
+        permute(&mut sorted_commitments, &order);
+        check_input_order(&sorted, |i| sorted_commitments[i]).unwrap();
+
+        let mut empty: Vec<u8> = Vec::new();
+        permute(&mut empty, &order);
+        assert!(empty.is_empty());
+        assert!(is_identity(&[0, 1, 2]) && !is_identity(&order));
+    }
+
+    #[test]
+    fn test_out_of_order_inputs_are_rejected() {
+        let commitment = |i: usize| [i as u8; 32];
+        assert!(check_input_order(&[3, 1], commitment).unwrap_err().contains("input 1 at leaf 1 follows leaf 3"));
+        assert!(check_input_order(&[4, 4], |i| [9 - i as u8; 32]).unwrap_err().contains("ordered by commitment"));
+        assert!(check_input_order(&[4, 4], |_| [1; 32]).unwrap_err().contains("repeats"));
+        check_input_order(&[4, 4], commitment).unwrap();
+        check_input_order(&[], commitment).unwrap();
+    }
+}
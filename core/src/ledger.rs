@@ -1,20 +1,54 @@
 use serde::{Serialize, Deserialize};
-use crate::merkle::MerkleTree;
+use crate::merkle::{Insertion, LeafIndex, MerkleTree};
+use crate::nullifier_set::NullifierSet;
 use crate::note::{commit, Note, Nullifier};
+use crate::sp1_types::{Withdrawal, Witness};
 
 /// Public outputs of a transaction that the chain / verifier can see.
 ///
 /// HIGH-LEVEL:
 /// - This is what SP1 will "commit" as public I/O.
 /// - The Solidity contract will receive something shaped like this.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// - In JSON the fields are camelCase 0x-hex strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PublicOutputs {
+    /// Chain the proof is bound to (from `PublicInputs`). Set by the guest;
+    /// simulations leave it 0.
+    #[serde(default)]
+    pub chain_id: u64,
     /// Merkle root before applying this transaction.
+    #[serde(with = "crate::hex::bytes32")]
     pub old_root: [u8; 32],
     /// Nullifiers for all notes spent in this tx.
+    #[serde(with = "crate::hex::bytes32_vec")]
     pub nullifiers: Vec<Nullifier>,
     /// Commitments of all newly created notes in this tx.
+    #[serde(with = "crate::hex::bytes32_vec")]
     pub output_commitments: Vec<[u8; 32]>,
+    /// Value paid out of the pool, for withdrawals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawal: Option<Withdrawal>,
+    /// Application context the inputs were used in, for scoped proofs
+    /// (see `Witness::external_nullifier`); the nullifiers are then that
+    /// context's, not spends.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::hex::bytes32_option")]
+    pub external_nullifier: Option<[u8; 32]>,
+}
+
+impl PublicOutputs {
+    /// Reject all-zero nullifiers and commitments. Both are hashes, so a
+    /// zero value means a bug or a forged witness; the contract also treats
+    /// a zero commitment as an empty leaf.
+    pub fn validate_nonzero(&self) -> Result<(), String> {
+        if let Some(i) = self.nullifiers.iter().position(|n| *n == [0u8; 32]) {
+            return Err(format!("Nullifier {} is zero", i));
+        }
+        if let Some(i) = self.output_commitments.iter().position(|c| *c == [0u8; 32]) {
+            return Err(format!("Output commitment {} is zero", i));
+        }
+        Ok(())
+    }
 }
 
 /// A very simple in-memory ledger for Phase 1.
@@ -35,15 +69,15 @@ pub struct Ledger {
     /// In the real protocol, these are the leaves of the global Merkle tree.
     utxos: Vec<Note>,
     /// Nullifiers of notes that have been spent.
-    /// Uses Vec for zkVM efficiency - linear search is O(n) but avoids
-    /// hash computation overhead. For typical transactions with 1-4 inputs,
-    /// this is faster than HashSet in the zkVM.
     ///
-    /// SECURITY: Double-spend check is performed via `is_nullifier_spent()`
-    /// which does a full linear scan to ensure no duplicates.
-    spent_nullifiers: Vec<Nullifier>,
+    /// SECURITY: Double-spend check is performed via `is_nullifier_spent()`.
+    spent_nullifiers: NullifierSet,
     /// The Merkle tree tracking all note commitments.
     tree: MerkleTree,
+    /// Roots the tree had before the current one, oldest first: the
+    /// contract keeps accepting them (`validRoots`).
+    #[serde(default)]
+    root_history: Vec<[u8; 32]>,
 }
 
 impl Ledger {
@@ -51,8 +85,9 @@ impl Ledger {
     pub fn new() -> Self {
         Self {
             utxos: Vec::new(),
-            spent_nullifiers: Vec::new(),
+            spent_nullifiers: NullifierSet::new(),
             tree: MerkleTree::new(),
+            root_history: Vec::new(),
         }
     }
 
@@ -62,34 +97,40 @@ impl Ledger {
     }
 
     /// Add a new note to the ledger (mint/create).
-    /// Returns the leaf index where the note was added.
-    pub fn add_note(&mut self, note: Note) -> u64 {
-        let index = self.tree.push_note(&note);
+    /// Returns where the note was added, with the new root and its proof.
+    pub fn add_note(&mut self, note: Note) -> Insertion {
+        self.root_history.push(self.tree.root());
+        let insertion = self.tree.push_note(&note);
         self.utxos.push(note);
-        index
+        insertion
+    }
+
+    /// Whether proofs against `root` are still accepted: the current root or
+    /// any earlier one, as with the contract's `validRoots`.
+    pub fn is_valid_root(&self, root: &[u8; 32]) -> bool {
+        *root == self.current_root() || self.root_history.contains(root)
     }
 
     /// Check if a nullifier has been spent.
     ///
     /// # Security
-    /// This performs a linear scan through all spent nullifiers to check
-    /// for duplicates. This is critical for double-spend prevention.
+    /// This is critical for double-spend prevention.
     pub fn is_nullifier_spent(&self, nullifier: &Nullifier) -> bool {
-        self.spent_nullifiers.iter().any(|n| n == nullifier)
+        self.spent_nullifiers.contains(nullifier)
     }
 
     /// Mark a nullifier as spent.
     ///
     /// # Security
-    /// First checks if nullifier already exists (double-spend attempt),
-    /// then adds to the spent set. The linear search ensures no duplicates
-    /// can be added.
+    /// Fails if the nullifier already exists (double-spend attempt), so no
+    /// duplicates can be added.
     pub fn spend_nullifier(&mut self, nullifier: Nullifier) -> Result<(), String> {
-        if self.is_nullifier_spent(&nullifier) {
-            return Err("Nullifier already spent".to_string());
-        }
-        self.spent_nullifiers.push(nullifier);
-        Ok(())
+        self.spent_nullifiers.insert(nullifier)
+    }
+
+    /// The spent nullifiers.
+    pub fn spent_nullifiers(&self) -> &NullifierSet {
+        &self.spent_nullifiers
     }
 
     /// Get a note by its index in the UTXO set.
@@ -112,6 +153,73 @@ impl Ledger {
     ) -> Result<PublicOutputs, String> {
         simulate_tx_and_build_public_outputs(self, input_indices, nullifier_signatures, tx_signatures, output_notes)
     }
+
+    /// Check that proven `outputs` apply to this ledger as the contract would
+    /// apply them, without changing it: the old root is one the ledger has
+    /// had (not necessarily the current one), and no nullifier is spent
+    /// (here or twice in the tx). The report carries the root after the
+    /// commitments are inserted into the current tree.
+    ///
+    /// The proof itself isn't checked; this is for outputs already proven
+    /// (observed on-chain, or about to be submitted).
+    ///
+    /// # Errors
+    /// Fails on the first of those checks that doesn't hold, on a tree too
+    /// full for the commitments, and on scoped outputs, which the ledger
+    /// doesn't accept.
+    pub fn verify_transition(&self, outputs: &PublicOutputs) -> Result<TransitionReport, String> {
+        verify_transition_on(&self.tree, |n| self.is_nullifier_spent(n), |root| self.is_valid_root(root), outputs)
+    }
+}
+
+/// `Ledger::verify_transition` against another copy of the ledger's state:
+/// its tree, spent nullifiers and valid roots (as `chains::ChainState`
+/// mirrors them from events).
+///
+/// Commitments are inserted the way the contract does
+/// (`MerkleTree::predict_contract_root`), so zero commitments, which the
+/// contract doesn't refuse, take a leaf without changing the root.
+pub fn verify_transition_on(
+    tree: &MerkleTree,
+    is_spent: impl Fn(&Nullifier) -> bool,
+    is_valid_root: impl Fn(&[u8; 32]) -> bool,
+    outputs: &PublicOutputs,
+) -> Result<TransitionReport, String> {
+    if outputs.external_nullifier.is_some() {
+        return Err("Scoped outputs (externalNullifier) aren't a ledger transition".to_string());
+    }
+    if !is_valid_root(&outputs.old_root) {
+        return Err(format!("Old root {} isn't a root the ledger has had", crate::hex::Bytes32(outputs.old_root)));
+    }
+    if let Some(i) = outputs.nullifiers.iter().position(is_spent) {
+        return Err(format!("Nullifier {} already spent", i));
+    }
+    if let Some(&i) = repeats(&outputs.nullifiers).first() {
+        return Err(format!("Nullifier {} repeats an earlier one in the tx", i));
+    }
+
+    Ok(TransitionReport {
+        old_root: outputs.old_root,
+        new_root: tree.predict_contract_root(&outputs.output_commitments)?,
+        nullifiers: outputs.nullifiers.len(),
+        first_leaf_index: tree.leaf_count() as LeafIndex,
+        commitments: outputs.output_commitments.len(),
+    })
+}
+
+/// A transition accepted by `Ledger::verify_transition`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransitionReport {
+    /// Root the outputs were proven against
+    pub old_root: [u8; 32],
+    /// Root once the output commitments are inserted into the current tree
+    pub new_root: [u8; 32],
+    /// Nullifiers the transition spends
+    pub nullifiers: usize,
+    /// Leaf index of the first output commitment
+    pub first_leaf_index: LeafIndex,
+    /// Output commitments the transition inserts
+    pub commitments: usize,
 }
 
 /// Simulate a transaction and build the public outputs.
@@ -134,6 +242,14 @@ pub fn simulate_tx_and_build_public_outputs(
         output_commitments.push(commit(note));
     }
 
+    // The tx signatures also cover the fee, so value conservation comes first
+    let input_notes = input_indices
+        .iter()
+        .map(|&idx| ledger.get_note(idx).cloned().ok_or_else(|| format!("Note at index {} not found", idx)))
+        .collect::<Result<Vec<Note>, String>>()?;
+    let fee = crate::signatures::tx_fee(&input_notes, &output_notes)
+        .ok_or("Outputs exceed inputs")?;
+
     // 2. Process Inputs
     let mut nullifiers = Vec::new();
 
@@ -149,6 +265,9 @@ pub fn simulate_tx_and_build_public_outputs(
         if nullifier_sig.len() != 65 || tx_sig.len() != 65 {
             return Err(format!("Invalid signature length at index {}", i));
         }
+        if !crate::signatures::is_canonical_nullifier_signature(nullifier_sig) {
+            return Err(format!("Nullifier signature at index {} is not canonical (low-s, v = 27 or 28)", i));
+        }
 
         // --- Verify Nullifier Signature ---
         // Message = Keccak256(Commitment)
@@ -160,7 +279,7 @@ pub fn simulate_tx_and_build_public_outputs(
         let nullifier_pubkey = recover_ethereum_key(&msg_hash, nullifier_sig)
             .map_err(|e| format!("Nullifier signature recovery failed: {}", e))?;
 
-        if nullifier_pubkey != note.owner_pubkey {
+        if !crate::ct::eq(&nullifier_pubkey, &note.owner_pubkey) {
              return Err(format!("Nullifier signature mismatch at index {}. Not owner.", i));
         }
 
@@ -168,18 +287,13 @@ pub fn simulate_tx_and_build_public_outputs(
         let nullifier = crate::note::compute_nullifier(nullifier_sig);
 
         // --- Verify Tx Signature ---
-        // Message = Keccak256(Nullifier || OutputCommitments...)
-        let mut tx_hasher = Keccak256::new();
-        tx_hasher.update(&nullifier);
-        for out_com in &output_commitments {
-            tx_hasher.update(out_com);
-        }
-        let tx_msg_hash = tx_hasher.finalize();
+        // Message = Keccak256(Nullifier || Fee || OutputCommitments...)
+        let tx_msg_hash = crate::signatures::tx_message(&nullifier, fee, &output_commitments);
 
         let tx_pubkey = recover_ethereum_key(&tx_msg_hash, tx_sig)
             .map_err(|e| format!("Tx signature recovery failed: {}", e))?;
 
-        if tx_pubkey != note.owner_pubkey {
+        if !crate::ct::eq(&tx_pubkey, &note.owner_pubkey) {
              return Err(format!("Tx signature mismatch at index {}. Not owner.", i));
         }
 
@@ -200,13 +314,16 @@ pub fn simulate_tx_and_build_public_outputs(
     // let new_root = ledger.current_root();
 
     Ok(PublicOutputs {
+        chain_id: 0,
         old_root,
         nullifiers,
         output_commitments,
+        withdrawal: None,
+        external_nullifier: None,
     })
 }
 
-fn recover_ethereum_key(msg_hash: &[u8], sig_bytes: &[u8]) -> Result<[u8; 32], &'static str> {
+pub(crate) fn recover_ethereum_key(msg_hash: &[u8], sig_bytes: &[u8]) -> Result<[u8; 32], &'static str> {
     use sha3::{Digest, Keccak256};
     use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
 
@@ -254,158 +371,451 @@ fn recover_ethereum_key(msg_hash: &[u8], sig_bytes: &[u8]) -> Result<[u8; 32], &
     Ok(pubkey)
 }
 
-/// Optimized transaction simulation using precomputed values.
-///
-/// This version is for use inside the zkVM where we want to avoid:
-/// - ECDSA operations (expensive in zkVM)
-/// - Redundant commitment calculations
+/// Verification result for one transaction input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputCheck {
+    /// Commitment recomputed from the note data
+    pub commitment: [u8; 32],
+    /// Precomputed commitment matches the note
+    pub commitment_ok: bool,
+    /// Merkle membership at the claimed root (`None` if not checked)
+    pub proof_ok: Option<bool>,
+    /// Nullifier derived from the nullifier signature (`None` if missing)
+    pub nullifier: Option<Nullifier>,
+    /// Nullifier signature recovers to the note owner
+    pub nullifier_sig_ok: bool,
+    /// Precomputed nullifier matches the derived one
+    pub nullifier_ok: bool,
+    /// Tx signature recovers to the note owner
+    pub tx_sig_ok: bool,
+    /// Nullifier is already spent in the ledger or earlier in this tx
+    pub already_spent: bool,
+    /// First failure for this input, if any
+    pub error: Option<String>,
+}
+
+impl InputCheck {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn fail(&mut self, reason: String) {
+        self.error.get_or_insert(reason);
+    }
+}
+
+/// Verification result for one transaction output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputCheck {
+    /// Commitment recomputed from the note data
+    pub commitment: [u8; 32],
+    /// Precomputed commitment matches the note
+    pub commitment_ok: bool,
+    pub error: Option<String>,
+}
+
+/// Outcome of checking a transaction against a ledger.
 ///
-/// The host precomputes nullifiers and commitments, and the zkVM only:
-/// 1. Verifies precomputed commitments match note data (via fast Blake3 hashing)
-/// 2. Verifies precomputed nullifiers match (privkey || commitment) hash
-/// 3. Updates ledger state
+/// Every input and output is checked even after a failure, so callers get
+/// all failure reasons at once. The ledger is only updated when the whole
+/// transaction is valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxSimulation {
+    pub inputs: Vec<InputCheck>,
+    pub outputs: Vec<OutputCheck>,
+    pub input_total: u64,
+    pub output_total: u64,
+    /// `input_total - output_total - public_amount`; negative means value
+    /// would be created
+    pub conservation_delta: i128,
+    /// Failures not tied to a single input or output
+    pub errors: Vec<String>,
+    /// Nullifiers and output commitments; only meaningful if `is_valid()`
+    pub public_outputs: PublicOutputs,
+}
+
+impl TxSimulation {
+    /// Value left over for the relayer (zero if the tx doesn't conserve value).
+    pub fn fee(&self) -> u64 {
+        u64::try_from(self.conservation_delta).unwrap_or(0)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+            && self.inputs.iter().all(InputCheck::is_ok)
+            && self.outputs.iter().all(|o| o.error.is_none())
+    }
+
+    /// All failure reasons, transaction-level first, then per input and output.
+    pub fn failure_reasons(&self) -> Vec<String> {
+        self.errors
+            .iter()
+            .chain(self.inputs.iter().filter_map(|i| i.error.as_ref()))
+            .chain(self.outputs.iter().filter_map(|o| o.error.as_ref()))
+            .cloned()
+            .collect()
+    }
+
+    /// Collapse into the public outputs, or every failure reason joined.
+    pub fn into_result(self) -> Result<PublicOutputs, String> {
+        if self.is_valid() {
+            Ok(self.public_outputs)
+        } else {
+            Err(self.failure_reasons().join("; "))
+        }
+    }
+}
+
+/// Check a transaction using host-precomputed values, without touching the
+/// ledger.
 ///
-/// # Parameters
-/// - `ledger`: The current ledger state
-/// - `input_keys`: Private keys for the input notes
-/// - `output_notes`: New notes being created
-/// - `precomputed_nullifiers`: Nullifiers computed by host
-/// - `precomputed_input_commitments`: Input commitments computed by host
-/// - `precomputed_output_commitments`: Output commitments computed by host
+/// The host precomputes nullifiers and commitments; this verifies they match
+/// the note data and signatures:
+/// 1. Precomputed input/output commitments match the notes (Blake3)
+/// 2. Each nullifier signature recovers to the note owner, and the
+///    precomputed nullifier equals Hash(signature)
+/// 3. Each tx signature over (nullifier || fee || output commitments)
+///    recovers to the note owner
+/// 4. No nullifier is already spent, in the ledger or within the tx
+/// 5. Inputs cover outputs
 ///
-/// # Returns
-/// `PublicOutputs` struct with verified nullifiers and commitments
-pub fn simulate_tx_with_precomputed(
-    ledger: &mut Ledger,
+/// Merkle membership is not checked here (see `simulate_witness`).
+#[allow(clippy::too_many_arguments)]
+pub fn check_tx_with_precomputed(
+    ledger: &Ledger,
     nullifier_signatures: &[Vec<u8>],
     tx_signatures: &[Vec<u8>],
     input_notes: &[Note],
-    output_notes: Vec<Note>,
+    output_notes: &[Note],
     precomputed_nullifiers: &[[u8; 32]],
-    _precomputed_input_commitments: &[[u8; 32]],
+    precomputed_input_commitments: &[[u8; 32]],
     precomputed_output_commitments: &[[u8; 32]],
-) -> Result<PublicOutputs, String> {
-
-
-    // Capture old root
-    let old_root = ledger.current_root();
-
-    // 1. Compute Output Commitments first (needed for Tx Signature Verification)
-    // Note: In the optimized path, we are given precomputed_output_commitments.
-    // However, we MUST verify they match the actual output_notes data.
-    // We do this verification loop below, but we need the verified commitments for the TxHash.
-    // Let's verify them first and collect them.
-    let mut output_commitments = Vec::new();
-    for (i, note) in output_notes.iter().enumerate() {
-        let precomputed_commitment = precomputed_output_commitments.get(i)
-            .ok_or_else(|| format!("Missing precomputed commitment for output {}", i))?;
+) -> TxSimulation {
+    check_tx(
+        ledger,
+        nullifier_signatures,
+        tx_signatures,
+        input_notes,
+        output_notes,
+        precomputed_nullifiers,
+        precomputed_input_commitments,
+        precomputed_output_commitments,
+        None,
+        None,
+        &recover_ethereum_key,
+    )
+}
 
-        // Verify commitment matches note data (Blake3 is fast in zkVM)
-        let recomputed_commitment = commit(note);
+/// Recovers the signer of a 65-byte signature over a message hash.
+pub(crate) type RecoverKey<'a> = dyn Fn(&[u8], &[u8]) -> Result<[u8; 32], &'static str> + 'a;
+
+/// `check_tx_with_precomputed`, paying out `withdrawal` if set: it counts
+/// towards the outputs, is excluded from the fee and is covered by the tx
+/// signatures (`withdrawal_tx_message`). With `external_nullifier`, the
+/// signatures are checked over the scoped messages instead (see
+/// `Witness::external_nullifier`). Signers are recovered with `recover`
+/// (see `signature_batch` for the host's parallel recovery).
+#[allow(clippy::too_many_arguments)]
+fn check_tx(
+    ledger: &Ledger,
+    nullifier_signatures: &[Vec<u8>],
+    tx_signatures: &[Vec<u8>],
+    input_notes: &[Note],
+    output_notes: &[Note],
+    precomputed_nullifiers: &[[u8; 32]],
+    precomputed_input_commitments: &[[u8; 32]],
+    precomputed_output_commitments: &[[u8; 32]],
+    withdrawal: Option<Withdrawal>,
+    external_nullifier: Option<[u8; 32]>,
+    recover: &RecoverKey,
+) -> TxSimulation {
+    use crate::signatures::{input_nullifier_message, scoped_tx_message, tx_message, withdrawal_tx_message};
+
+    let mut errors = Vec::new();
+    if precomputed_nullifiers.len() > input_notes.len() {
+        errors.push(format!(
+            "{} precomputed nullifiers for {} inputs",
+            precomputed_nullifiers.len(),
+            input_notes.len()
+        ));
+    }
+    if precomputed_output_commitments.len() > output_notes.len() {
+        errors.push(format!(
+            "{} precomputed output commitments for {} outputs",
+            precomputed_output_commitments.len(),
+            output_notes.len()
+        ));
+    }
 
-        if recomputed_commitment != *precomputed_commitment {
-            return Err(format!(
-                "Output commitment mismatch at index {}: precomputed doesn't match note",
+    // 1. Outputs first: the tx signature message covers their commitments
+    let outputs: Vec<OutputCheck> = output_notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| {
+            let commitment = commit(note);
+            let error = match precomputed_output_commitments.get(i) {
+                None => Some(format!("Missing precomputed commitment for output {}", i)),
+                Some(c) if *c != commitment => Some(format!(
+                    "Output commitment mismatch at index {}: precomputed doesn't match note",
+                    i
+                )),
+                Some(_) => None,
+            };
+            OutputCheck { commitment, commitment_ok: error.is_none(), error }
+        })
+        .collect();
+    let output_commitments: Vec<[u8; 32]> = outputs.iter().map(|o| o.commitment).collect();
+    // Also covered by the tx signatures; a tx without one fails conservation below
+    let public_amount = withdrawal.map_or(0, |w| w.public_amount);
+    let fee = crate::signatures::tx_fee(input_notes, output_notes)
+        .and_then(|fee| fee.checked_sub(public_amount))
+        .unwrap_or(0);
+
+    // 2. Inputs
+    let mut inputs: Vec<InputCheck> = Vec::with_capacity(input_notes.len());
+    for (i, note) in input_notes.iter().enumerate() {
+        let commitment = commit(note);
+        let mut check = InputCheck {
+            commitment,
+            commitment_ok: precomputed_input_commitments.get(i) == Some(&commitment),
+            proof_ok: None,
+            nullifier: None,
+            nullifier_sig_ok: false,
+            nullifier_ok: false,
+            tx_sig_ok: false,
+            already_spent: false,
+            error: None,
+        };
+        if !check.commitment_ok {
+            check.fail(format!(
+                "Input commitment mismatch at index {}: precomputed doesn't match note",
                 i
             ));
         }
-        output_commitments.push(*precomputed_commitment);
-    }
 
-    // 2. Process Inputs and Verify Signatures
-    let mut nullifiers = Vec::new();
-    // We need Keccak256 for Ethereum-style signature verification
-    use sha3::{Digest, Keccak256};
-
-    for (i, precomputed_nullifier) in precomputed_nullifiers.iter().enumerate() {
-        let note = input_notes.get(i)
-            .ok_or_else(|| format!("Missing input note at index {}", i))?;
-        
-        let nullifier_sig = nullifier_signatures.get(i)
-            .ok_or_else(|| format!("Missing nullifier signature for input {}", i))?;
-        let tx_sig = tx_signatures.get(i)
-            .ok_or_else(|| format!("Missing tx signature for input {}", i))?;
-
-        // --- Verify Nullifier Signature ---
-        // Message = Keccak256(Commitment)
-        // We must match Host/JS logic: Keccak(Prefix + Keccak(Commitment))
-        let input_commitment = commit(note);
-        let mut hasher = Keccak256::new();
-        hasher.update(&input_commitment);
-        let msg_hash = hasher.finalize();
-        
-        let nullifier_pubkey = recover_ethereum_key(&msg_hash, nullifier_sig)
-            .map_err(|e| format!("Nullifier signature recovery failed at index {}: {}", i, e))?;
-
-        if nullifier_pubkey != note.owner_pubkey {
-             let recovered_hex: String = nullifier_pubkey.iter().map(|b| format!("{:02x}", b)).collect();
-             let expected_hex: String = note.owner_pubkey.iter().map(|b| format!("{:02x}", b)).collect();
-             let sig_hex: String = nullifier_sig.iter().map(|b| format!("{:02x}", b)).collect();
-             let commitment_hex: String = input_commitment.iter().map(|b| format!("{:02x}", b)).collect();
-             let msg_hash_hex: String = msg_hash.iter().map(|b| format!("{:02x}", b)).collect();
-             
-             return Err(format!(
-                 "Nullifier signature mismatch at index {}. Not owner.\n  Recovered: {}\n  Expected:  {}\n  Sig: {}\n  Comm: {}\n  MsgHash: {}",
-                 i, recovered_hex, expected_hex, sig_hex, commitment_hex, msg_hash_hex
-             ));
+        let Some(nullifier_sig) = nullifier_signatures.get(i) else {
+            check.fail(format!("Missing nullifier signature for input {}", i));
+            inputs.push(check);
+            continue;
+        };
+
+        // --- Nullifier signature: Message = Keccak256(Commitment), or the scoped message ---
+        // Only the canonical encoding, or one note would have several nullifiers
+        let recovered = if crate::signatures::is_canonical_nullifier_signature(nullifier_sig) {
+            recover(&input_nullifier_message(&commitment, external_nullifier.as_ref()), nullifier_sig)
+        } else {
+            Err("not in canonical form (low-s, v = 27 or 28)")
+        };
+        match recovered {
+            Ok(pubkey) if crate::ct::eq(&pubkey, &note.owner_pubkey) => check.nullifier_sig_ok = true,
+            Ok(pubkey) => check.fail(format!(
+                "Nullifier signature mismatch at index {}. Not owner.\n  Recovered: {}\n  Expected:  {}",
+                i,
+                crate::hex::encode_hex(&pubkey),
+                crate::hex::encode_hex(&note.owner_pubkey)
+            )),
+            Err(e) => check.fail(format!("Nullifier signature recovery failed at index {}: {}", i, e)),
         }
 
-        // Recompute Nullifier from Signature (this is fast hashing)
-        let recomputed_nullifier = crate::note::compute_nullifier(nullifier_sig);
-        
-        if recomputed_nullifier != *precomputed_nullifier {
-            return Err(format!(
+        // Nullifier = Hash(NullifierSig), or keyed on the note in a scoped use
+        let nullifier = crate::note::input_nullifier(note, nullifier_sig, external_nullifier.as_ref());
+        check.nullifier = Some(nullifier);
+        check.nullifier_ok = precomputed_nullifiers.get(i).is_some_and(|n| crate::ct::eq(n, &nullifier));
+        if !check.nullifier_ok {
+            check.fail(format!(
                 "Nullifier mismatch at input {}: precomputed doesn't match recomputed",
                 i
             ));
         }
 
-        // --- Verify Tx Signature ---
-        // Message = Keccak256(Nullifier || OutputCommitments...)
-        let mut tx_hasher = Keccak256::new();
-        tx_hasher.update(&recomputed_nullifier);
-        for out_com in &output_commitments {
-            tx_hasher.update(out_com);
+        // --- Tx signature: Message = Keccak256(Nullifier || Fee || OutputCommitments... [|| Withdrawal]) ---
+        let tx_msg_hash = match (&external_nullifier, &withdrawal) {
+            (Some(external_nullifier), _) => scoped_tx_message(&nullifier, external_nullifier),
+            (None, Some(w)) => withdrawal_tx_message(&nullifier, fee, &output_commitments, w),
+            (None, None) => tx_message(&nullifier, fee, &output_commitments),
+        };
+        match tx_signatures.get(i) {
+            None => check.fail(format!("Missing tx signature for input {}", i)),
+            Some(tx_sig) => match recover(&tx_msg_hash, tx_sig) {
+                Ok(pubkey) if crate::ct::eq(&pubkey, &note.owner_pubkey) => check.tx_sig_ok = true,
+                Ok(_) => check.fail(format!("Tx signature mismatch at index {}. Not owner.", i)),
+                Err(e) => check.fail(format!("Tx signature recovery failed at index {}: {}", i, e)),
+            },
         }
-        let tx_msg_hash = tx_hasher.finalize();
 
-        let tx_pubkey = recover_ethereum_key(&tx_msg_hash, tx_sig)
-            .map_err(|e| format!("Tx signature recovery failed at index {}: {}", i, e))?;
+        // --- Double spend against the ledger (earlier inputs below) ---
+        check.already_spent = ledger.is_nullifier_spent(&nullifier);
+        if check.already_spent {
+            check.fail(format!("Nullifier at input {} already spent", i));
+        }
 
-        if tx_pubkey != note.owner_pubkey {
-             return Err(format!("Tx signature mismatch at index {}. Not owner.", i));
+        inputs.push(check);
+    }
+    // ... and against earlier inputs, sorting rather than comparing every pair
+    let nullifiers: Vec<Option<[u8; 32]>> = inputs.iter().map(|check| check.nullifier).collect();
+    for i in repeats(&nullifiers) {
+        if nullifiers[i].is_some() && !inputs[i].already_spent {
+            inputs[i].already_spent = true;
+            inputs[i].fail(format!("Nullifier at input {} already spent", i));
         }
+    }
+
+    // 3. Value conservation
+    let input_total: u128 = input_notes.iter().map(|n| n.amount as u128).sum();
+    let output_total: u128 = output_notes.iter().map(|n| n.amount as u128).sum();
+    let conservation_delta = input_total as i128 - output_total as i128 - public_amount as i128;
+    if conservation_delta < 0 {
+        errors.push(match withdrawal {
+            Some(_) => format!(
+                "Insufficient input value: {} < {} outputs + {} withdrawn",
+                input_total, output_total, public_amount
+            ),
+            None => format!("Insufficient input value: {} < {} outputs", input_total, output_total),
+        });
+    }
+    let input_total = u64::try_from(input_total).unwrap_or_else(|_| {
+        errors.push("Input value overflows u64".to_string());
+        u64::MAX
+    });
+    let output_total = u64::try_from(output_total).unwrap_or_else(|_| {
+        errors.push("Output value overflows u64".to_string());
+        u64::MAX
+    });
+
+    let public_outputs = PublicOutputs {
+        chain_id: 0,
+        old_root: ledger.current_root(),
+        nullifiers: inputs.iter().map(|c| c.nullifier.unwrap_or_default()).collect(),
+        output_commitments,
+        withdrawal,
+        external_nullifier,
+    };
 
-        // Check if nullifier is already spent in the ledger
-        if ledger.is_nullifier_spent(precomputed_nullifier) {
-            return Err(format!("Nullifier at input {} already spent", i));
+    TxSimulation {
+        inputs,
+        outputs,
+        input_total,
+        output_total,
+        conservation_delta,
+        errors,
+        public_outputs,
+    }
+}
+
+/// Check a transaction with precomputed values and, if valid, apply it.
+///
+/// Used inside the zkVM, where the host supplies precomputed nullifiers and
+/// commitments. See `check_tx_with_precomputed` for what is verified; the
+/// ledger is left untouched when any check fails.
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_tx_with_precomputed(
+    ledger: &mut Ledger,
+    nullifier_signatures: &[Vec<u8>],
+    tx_signatures: &[Vec<u8>],
+    input_notes: &[Note],
+    output_notes: Vec<Note>,
+    precomputed_nullifiers: &[[u8; 32]],
+    precomputed_input_commitments: &[[u8; 32]],
+    precomputed_output_commitments: &[[u8; 32]],
+) -> TxSimulation {
+    let simulation = check_tx_with_precomputed(
+        ledger,
+        nullifier_signatures,
+        tx_signatures,
+        input_notes,
+        &output_notes,
+        precomputed_nullifiers,
+        precomputed_input_commitments,
+        precomputed_output_commitments,
+    );
+    if simulation.is_valid() {
+        apply_simulation(ledger, &simulation, output_notes);
+    }
+    simulation
+}
+
+/// Check a full witness, including Merkle membership of every input at
+/// `old_root`, and apply it to `ledger` if valid.
+///
+/// This is the single verification path shared by the guest and the host's
+/// pre-flight check. `public_outputs.old_root` is set to `old_root`.
+pub fn simulate_witness(ledger: &mut Ledger, witness: &Witness, old_root: [u8; 32]) -> TxSimulation {
+    simulate_witness_with(ledger, witness, old_root, &recover_ethereum_key)
+}
+
+/// `simulate_witness`, recovering signers with `recover`.
+pub(crate) fn simulate_witness_with(
+    ledger: &mut Ledger,
+    witness: &Witness,
+    old_root: [u8; 32],
+    recover: &RecoverKey,
+) -> TxSimulation {
+    let mut simulation = check_tx(
+        ledger,
+        &witness.nullifier_signatures,
+        &witness.tx_signatures,
+        &witness.input_notes,
+        &witness.output_notes,
+        &witness.precomputed_nullifiers,
+        &witness.precomputed_input_commitments,
+        &witness.precomputed_output_commitments,
+        witness.withdrawal,
+        witness.external_nullifier,
+        recover,
+    );
+
+    // Membership binds the inputs to the contract's state; without it fake
+    // notes with arbitrary amounts could be spent.
+    for (i, check) in simulation.inputs.iter_mut().enumerate() {
+        let proof_ok = witness
+            .input_proofs
+            .get(i)
+            .is_some_and(|proof| MerkleTree::verify_proof(check.commitment, proof, old_root));
+        check.proof_ok = Some(proof_ok);
+        if !proof_ok {
+            check.fail(match witness.input_proofs.get(i) {
+                None => format!("Missing Merkle proof for input {}", i),
+                Some(_) => format!("Merkle proof failed for input {}: commitment not in tree at old_root", i),
+            });
         }
+    }
+    simulation.public_outputs.old_root = old_root;
+    if let Err(e) = witness.validate_old_root(&old_root) {
+        simulation.errors.push(e);
+    }
+    if let Err(e) = simulation.public_outputs.validate_nonzero() {
+        simulation.errors.push(e);
+    }
 
-        nullifiers.push(*precomputed_nullifier);
-        ledger.spend_nullifier(*precomputed_nullifier)?;
+    if simulation.is_valid() {
+        apply_simulation(ledger, &simulation, witness.output_notes.clone());
     }
+    simulation
+}
+
+/// Positions of the items equal to an earlier item, ascending. Sorts, so a
+/// consolidation spending many inputs doesn't compare every pair.
+fn repeats<T: Ord>(items: &[T]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&a, &b| items[a].cmp(&items[b]).then(a.cmp(&b)));
+    let mut repeated: Vec<usize> =
+        order.windows(2).filter(|pair| items[pair[0]] == items[pair[1]]).map(|pair| pair[1]).collect();
+    repeated.sort_unstable();
+    repeated
+}
 
-    // 3. Update Ledger with new outputs
+fn apply_simulation(ledger: &mut Ledger, simulation: &TxSimulation, output_notes: Vec<Note>) {
+    ledger.spent_nullifiers.extend(simulation.public_outputs.nullifiers.iter().copied());
     for note in output_notes {
         ledger.add_note(note);
     }
-
-    // Capture new root
-    // let new_root = ledger.current_root();
-
-    Ok(PublicOutputs {
-        old_root,
-        nullifiers,
-        output_commitments,
-    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::note::compute_nullifier;
-    use k256::ecdsa::{SigningKey, signature::Signer};
-    use sha3::{Keccak256, Digest};
+    use k256::ecdsa::SigningKey;
 
     #[test]
     fn test_ledger_creation() {
@@ -422,9 +832,10 @@ mod tests {
         let mut ledger = Ledger::new();
         let note = Note::new(100, [1; 32], [2; 32]);
         
-        let index = ledger.add_note(note.clone());
-        assert_eq!(index, 0);
+        let insertion = ledger.add_note(note.clone());
+        assert_eq!(insertion.index, 0);
         assert_eq!(ledger.note_count(), 1);
+        assert_eq!(insertion.new_root, ledger.current_root());
         assert_ne!(ledger.current_root(), [0u8; 32]);
     }
 
@@ -492,6 +903,7 @@ mod tests {
         
         let mut tx_hasher = Keccak256::new();
         tx_hasher.update(&nullifier);
+        tx_hasher.update(0u64.to_be_bytes()); // fee: 100 in, 60 + 40 out
         tx_hasher.update(&output_commitment1);
         tx_hasher.update(&output_commitment2);
         let tx_msg_hash = tx_hasher.finalize();
@@ -524,154 +936,233 @@ mod tests {
         // assert_ne!(outputs.old_root, outputs.new_root);
     }
 
-    #[test]
-    fn test_simulate_tx_with_precomputed() {
-        use k256::ecdsa::{SigningKey, signature::Signer};
-        use sha3::{Keccak256, Digest};
-
-        let mut ledger = Ledger::new();
+    /// Owner key, its note, and signatures spending it into `outputs`,
+    /// leaving the transaction's total `fee`.
+    fn signed_spend(amount: u64, fee: u64, outputs: &[Note]) -> (Note, Vec<u8>, Vec<u8>) {
+        use crate::signatures::{nullifier_message, sign_message, tx_message};
 
-        // Add initial note
         let signing_key = SigningKey::random(&mut rand::thread_rng());
-        let verify_key = signing_key.verifying_key();
-        let encoded_point = verify_key.to_encoded_point(true);
+        let secret: [u8; 32] = signing_key.to_bytes().into();
         let mut owner_pubkey = [0u8; 32];
-        owner_pubkey.copy_from_slice(&encoded_point.as_bytes()[1..]);
-
-        let input_note = Note::new(100, owner_pubkey, [2; 32]);
-        ledger.add_note(input_note.clone());
-
-        // Create output notes
-        let output1 = Note::new(60, [4; 32], [5; 32]);
-        let output2 = Note::new(40, [7; 32], [8; 32]);
+        owner_pubkey.copy_from_slice(&signing_key.verifying_key().to_encoded_point(true).as_bytes()[1..]);
 
-        // 1. Generate Nullifier Signature (needed for precomputed nullifier)
-        // Message = Ethereum prefix + commitment (no intermediate hash)
-        // recover_ethereum_key does: Keccak256(prefix + msg_hash)
-        // So we sign: Keccak256(prefix + commitment)
-        let input_commitment = commit(&input_note);
-
-        let mut eth_hasher = Keccak256::new();
-        eth_hasher.update(b"\x19Ethereum Signed Message:\n32");
-        eth_hasher.update(&input_commitment);
-        let eth_msg_hash = eth_hasher.finalize();
-
-        let (signature, rec_id) = signing_key.sign_prehash_recoverable(&eth_msg_hash).unwrap();
-        let mut nullifier_sig_bytes = Vec::new();
-        nullifier_sig_bytes.extend_from_slice(&signature.to_bytes());
-        nullifier_sig_bytes.push(rec_id.to_byte() + 27);
+        let input_note = Note::new(amount, owner_pubkey, [2; 32]);
+        let nullifier_sig = sign_message(&secret, &nullifier_message(&commit(&input_note))).unwrap();
+        let output_commitments: Vec<[u8; 32]> = outputs.iter().map(commit).collect();
+        let tx_sig = sign_message(&secret, &tx_message(&compute_nullifier(&nullifier_sig), fee, &output_commitments)).unwrap();
 
-        let nullifier = crate::note::compute_nullifier(&nullifier_sig_bytes);
-        
-        // Output Commitments
-        let output_commitment1 = commit(&output1);
-        let output_commitment2 = commit(&output2);
-
-        // 2. Generate Tx Signature
-        let mut tx_hasher = Keccak256::new();
-        tx_hasher.update(&nullifier);
-        tx_hasher.update(&output_commitment1);
-        tx_hasher.update(&output_commitment2);
-        let tx_msg_hash = tx_hasher.finalize();
-
-        let mut eth_tx_hasher = Keccak256::new();
-        eth_tx_hasher.update(b"\x19Ethereum Signed Message:\n32");
-        eth_tx_hasher.update(&tx_msg_hash);
-        let eth_tx_msg_hash = eth_tx_hasher.finalize();
+        (input_note, nullifier_sig.to_vec(), tx_sig.to_vec())
+    }
 
-        let (tx_signature, tx_rec_id) = signing_key.sign_prehash_recoverable(&eth_tx_msg_hash).unwrap();
-        let mut tx_sig_bytes = Vec::new();
-        tx_sig_bytes.extend_from_slice(&tx_signature.to_bytes());
-        tx_sig_bytes.push(tx_rec_id.to_byte() + 27);
+    #[test]
+    fn test_simulate_tx_with_precomputed() {
+        let mut ledger = Ledger::new();
+        let outputs = vec![Note::new(60, [4; 32], [5; 32]), Note::new(40, [7; 32], [8; 32])];
+        let (input_note, nullifier_sig, tx_sig) = signed_spend(100, 0, &outputs);
+        ledger.add_note(input_note.clone());
+        let nullifier = compute_nullifier(&nullifier_sig);
+        let output_commitments: Vec<[u8; 32]> = outputs.iter().map(commit).collect();
 
-        let result = simulate_tx_with_precomputed(
+        let simulation = simulate_tx_with_precomputed(
             &mut ledger,
-            &[nullifier_sig_bytes],
-            &[tx_sig_bytes], // Correct Tx Sig
-            &[input_note.clone()], // Input notes
-            vec![output1, output2],
+            &[nullifier_sig],
+            &[tx_sig],
+            std::slice::from_ref(&input_note),
+            outputs,
             &[nullifier],
-            &[input_commitment],
-            &[output_commitment1, output_commitment2],
+            &[commit(&input_note)],
+            &output_commitments,
         );
 
-        assert!(result.is_ok());
-        let outputs = result.unwrap();
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+        assert_eq!(simulation.fee(), 0);
+        assert!(ledger.is_nullifier_spent(&nullifier));
+        assert_eq!(ledger.note_count(), 3);
 
-        assert_eq!(outputs.nullifiers.len(), 1);
-        assert_eq!(outputs.nullifiers[0], nullifier);
-        assert_eq!(outputs.output_commitments.len(), 2);
+        let outputs = simulation.into_result().unwrap();
+        assert_eq!(outputs.nullifiers, vec![nullifier]);
+        assert_eq!(outputs.output_commitments, output_commitments);
     }
 
     #[test]
     fn test_precomputed_mismatch_rejected() {
         let mut ledger = Ledger::new();
-        // Setup similar to above...
-        let signing_key = SigningKey::random(&mut rand::thread_rng());
-        let verify_key = signing_key.verifying_key();
-        let encoded_point = verify_key.to_encoded_point(true);
-        let mut owner_pubkey = [0u8; 32];
-        owner_pubkey.copy_from_slice(&encoded_point.as_bytes()[1..]);
-        let input_note = Note::new(100, owner_pubkey, [2; 32]);
+        let output = Note::new(100, [4; 32], [5; 32]);
+        let (input_note, nullifier_sig, _) = signed_spend(100, 0, std::slice::from_ref(&output));
         ledger.add_note(input_note.clone());
-        let output1 = Note::new(100, [4; 32], [5; 32]);
+        let fake_nullifier = [99u8; 32];
 
-        // Valid signature for nullifier generation
-        // Message = Ethereum prefix + commitment (no intermediate hash)
-        let input_commitment = commit(&input_note);
-        let mut eth_hasher = Keccak256::new();
-        eth_hasher.update(b"\x19Ethereum Signed Message:\n32");
-        eth_hasher.update(&input_commitment);
-        let eth_msg_hash = eth_hasher.finalize();
-        let (signature, rec_id) = signing_key.sign_prehash_recoverable(&eth_msg_hash).unwrap();
-        let mut nullifier_sig_bytes = Vec::new();
-        nullifier_sig_bytes.extend_from_slice(&signature.to_bytes());
-        nullifier_sig_bytes.push(rec_id.to_byte() + 27);
-        
-        let output_commitment = commit(&output1);
-        let fake_nullifier = [99u8; 32]; // Wrong!
-
-        // Generate valid TX Sig for the FAKE nullifier? 
-        // Logic: Sig(Hash(Nullifier || OutputCommitments))
-        // If we want it to fail on "Nullifier mismatch", we should probably pass a Valid Signature over the EXPECTED nullifier?
-        // No, `simulate_tx_with_precomputed` recomputes the nullifier from the Sig first.
-        // `let recomputed_nullifier = compute_nullifier(nullifier_sig);`
-        // `if recomputed_nullifier != *precomputed_nullifier { ... }`
-        // 
-        // So we have a Valid Nullifier Sig. `recomputed_nullifier` will be correct (real nullifier).
-        // `precomputed_nullifier` is fake.
-        // So `recomputed != precomputed` check will fail.
-        // Use standard Tx Sig generation logic
-        // But wait, Tx Sig verification comes AFTER this check in my new logic!
-        // So I can pass a dummy Tx Sig and it won't be reached.
-        // But to be safe and clean, let's pass a dummy bytes that is NOT empty (65 bytes) just in case.
-        
-        // Actually, let's just use the dummy 65 bytes 0 signature, since we EXPECT it to fail before verifying Tx Sig.
-        // Verification steps:
-        // 1. Verify Nullifier Sig (Passed)
-        // 2. Recompute Nullifier (Passed, gets Real Nullifier)
-        // 3. Compare Recomputed vs Precomputed (Real != Fake) -> ERROR: "Nullifier mismatch"
-        // 4. Verify Tx Sig (Not reached)
-        
-        // So previous code was fine?
-        // "dummy Tx Sig (not checked in optimized path yet?)"
-        // It has 65 bytes of 0.
-        // It will fail `recover_ethereum_key` if reached.
-        // But it shouldn't be reached.
-        // Let's stick with the existing test code for now but I'll update the comment.
-        
-        let result = simulate_tx_with_precomputed(
+        let simulation = simulate_tx_with_precomputed(
             &mut ledger,
-            &[nullifier_sig_bytes], // Correct sig
-            &[vec![0u8; 65]], // Dummy Tx Sig (Should not be reached due to nullifier mismatch)
-            &[input_note.clone()],
-            vec![output1],
-            &[fake_nullifier], // Wrong precomputed value
-            &[input_commitment],
-            &[output_commitment],
+            &[nullifier_sig],
+            &[vec![0u8; 65]], // Invalid tx signature, reported alongside
+            std::slice::from_ref(&input_note),
+            vec![output.clone()],
+            &[fake_nullifier],
+            &[commit(&input_note)],
+            &[commit(&output)],
         );
 
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Nullifier mismatch"));
+        let input = &simulation.inputs[0];
+        assert!(input.nullifier_sig_ok);
+        assert!(!input.nullifier_ok);
+        assert!(!input.tx_sig_ok);
+        assert!(input.error.as_ref().unwrap().contains("Nullifier mismatch"));
+        assert_eq!(ledger.note_count(), 1, "ledger must not change on failure");
+        assert!(simulation.into_result().unwrap_err().contains("Nullifier mismatch"));
+    }
+
+    #[test]
+    fn test_simulation_reports_every_failure() {
+        let ledger = Ledger::new();
+        let output = Note::new(150, [4; 32], [5; 32]);
+        let (input_note, nullifier_sig, tx_sig) = signed_spend(100, 50, std::slice::from_ref(&output));
+        let nullifier = compute_nullifier(&nullifier_sig);
+
+        let simulation = check_tx_with_precomputed(
+            &ledger,
+            &[nullifier_sig.clone(), nullifier_sig],
+            &[tx_sig.clone(), tx_sig],
+            &[input_note.clone(), input_note.clone()],
+            std::slice::from_ref(&output),
+            &[nullifier, nullifier],
+            &[commit(&input_note), [0u8; 32]],
+            &[[1u8; 32]],
+        );
+
+        assert!(simulation.inputs[0].is_ok());
+        assert!(!simulation.inputs[1].commitment_ok);
+        assert!(simulation.inputs[1].already_spent);
+        assert!(!simulation.outputs[0].commitment_ok);
+        assert_eq!(simulation.conservation_delta, 200 - 150);
+        assert_eq!(simulation.fee(), 50);
+        assert_eq!(simulation.failure_reasons().len(), 2);
+    }
+
+    #[test]
+    fn test_tx_signature_covers_fee() {
+        let output = Note::new(90, [4; 32], [5; 32]);
+        let run = |signed_fee: u64| {
+            let (input_note, nullifier_sig, tx_sig) = signed_spend(100, signed_fee, std::slice::from_ref(&output));
+            check_tx_with_precomputed(
+                &Ledger::new(),
+                std::slice::from_ref(&nullifier_sig),
+                &[tx_sig],
+                std::slice::from_ref(&input_note),
+                std::slice::from_ref(&output),
+                &[compute_nullifier(&nullifier_sig)],
+                &[commit(&input_note)],
+                &[commit(&output)],
+            )
+        };
+
+        assert!(run(10).is_valid());
+        // Signed for no fee, but the outputs leave 10 to the relayer
+        let underpaid = run(0);
+        assert!(!underpaid.inputs[0].tx_sig_ok);
+        assert!(underpaid.into_result().unwrap_err().contains("Tx signature mismatch"));
+    }
+
+    #[test]
+    fn test_nullifier_signature_v_must_be_canonical() {
+        use crate::testing::{TestOwner, WitnessBuilder};
+
+        let alice = TestOwner::alice();
+        let (public_inputs, witness) = WitnessBuilder::new().input(&alice, 100).output(&alice, 100).build().unwrap();
+        assert!(simulate_witness(&mut Ledger::new(), &witness, public_inputs.old_root).is_valid());
+
+        // The same recovery id as raw (0/1) and EIP-155 (35/36) bytes
+        let v = witness.nullifier_signatures[0][64];
+        for flipped in [v - 27, v + 8] {
+            let mut malleated = witness.clone();
+            malleated.nullifier_signatures[0][64] = flipped;
+            let malleated = malleated.with_precomputed_values();
+
+            let simulation = simulate_witness(&mut Ledger::new(), &malleated, public_inputs.old_root);
+            assert!(!simulation.inputs[0].nullifier_sig_ok, "v = {} accepted", flipped);
+            assert!(simulation.into_result().unwrap_err().contains("canonical form"));
+        }
+    }
+
+    #[test]
+    fn test_simulate_witness_checks_membership() {
+        let output = Note::new(100, [4; 32], [5; 32]);
+        let (input_note, nullifier_sig, tx_sig) = signed_spend(100, 0, std::slice::from_ref(&output));
+        let mut tree = MerkleTree::new();
+        tree.push_note(&input_note);
+        let proof = tree.prove(0).unwrap();
+
+        let witness = Witness::new(
+            vec![input_note],
+            vec![0],
+            vec![proof],
+            vec![nullifier_sig],
+            vec![tx_sig],
+            vec![output],
+        )
+        .with_precomputed_values();
+
+        let valid = simulate_witness(&mut Ledger::new(), &witness, tree.root());
+        assert!(valid.is_valid(), "{:?}", valid.failure_reasons());
+        assert_eq!(valid.inputs[0].proof_ok, Some(true));
+        assert_eq!(valid.public_outputs.old_root, tree.root());
+
+        let invalid = simulate_witness(&mut Ledger::new(), &witness, [7u8; 32]);
+        assert_eq!(invalid.inputs[0].proof_ok, Some(false));
+        assert!(invalid.into_result().unwrap_err().contains("Merkle proof failed"));
+
+        let empty = simulate_witness(&mut Ledger::new(), &witness, MerkleTree::new().root());
+        assert!(empty.errors.iter().any(|e| e.contains("empty tree root")), "{:?}", empty.errors);
+    }
+
+    #[test]
+    fn test_zero_public_outputs_rejected() {
+        let mut outputs = PublicOutputs { chain_id: 1, old_root: [1; 32], nullifiers: vec![[2; 32]], output_commitments: vec![[3; 32]], withdrawal: None, external_nullifier: None };
+        assert!(outputs.validate_nonzero().is_ok());
+        outputs.output_commitments.push([0; 32]);
+        assert_eq!(outputs.validate_nonzero().unwrap_err(), "Output commitment 1 is zero");
+        outputs.nullifiers.insert(0, [0; 32]);
+        assert_eq!(outputs.validate_nonzero().unwrap_err(), "Nullifier 0 is zero");
+    }
+
+    #[test]
+    fn test_verify_transition() {
+        let mut ledger = Ledger::new();
+        ledger.add_note(Note::new(100, [1; 32], [2; 32]));
+        ledger.spend_nullifier([9; 32]).unwrap();
+        let outputs = PublicOutputs {
+            chain_id: 1,
+            old_root: ledger.current_root(),
+            nullifiers: vec![[2; 32]],
+            output_commitments: vec![[3; 32], [4; 32]],
+            withdrawal: None,
+            external_nullifier: None,
+        };
+
+        let report = ledger.verify_transition(&outputs).unwrap();
+        let mut expected = ledger.tree.clone();
+        expected.push_leaf([3; 32]);
+        expected.push_leaf([4; 32]);
+        assert_eq!(report.new_root, expected.root());
+        assert_eq!((report.first_leaf_index, report.nullifiers, report.commitments), (1, 1, 2));
+        assert_eq!(ledger.note_count(), 1);
+
+        // An earlier root is still valid, and the outputs go into the current tree
+        let mut earlier = outputs.clone();
+        earlier.old_root = MerkleTree::new().root();
+        assert_eq!(ledger.verify_transition(&earlier).unwrap().new_root, report.new_root);
+        let mut unknown = outputs.clone();
+        unknown.old_root = [7; 32];
+        assert!(ledger.verify_transition(&unknown).unwrap_err().contains("isn't a root the ledger has had"));
+        let mut spent = outputs.clone();
+        spent.nullifiers.push([9; 32]);
+        assert_eq!(ledger.verify_transition(&spent).unwrap_err(), "Nullifier 1 already spent");
+        let mut repeated = outputs.clone();
+        repeated.nullifiers.push([2; 32]);
+        assert!(ledger.verify_transition(&repeated).unwrap_err().contains("repeats"));
+        assert_eq!(repeats(&[[3u8; 32], [1; 32], [3; 32], [1; 32], [3; 32], [2; 32]]), vec![2, 3, 4]);
+        let mut scoped = outputs;
+        scoped.external_nullifier = Some([5; 32]);
+        assert!(ledger.verify_transition(&scoped).is_err());
     }
 }
\ No newline at end of file
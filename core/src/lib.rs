@@ -1,8 +1,100 @@
-pub mod ledger;
+//! Notes, the Merkle tree and witness validation build as `no_std + alloc`
+//! without the default `std` feature; everything else needs it.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+// `no_std + alloc`
+pub mod ct;
+pub mod hex;
+pub mod input_order;
 pub mod merkle;
 pub mod note;
+pub mod nullifier_tree;
 pub mod sp1_types;
 
+#[cfg(feature = "std")]
+pub mod amount_commitment;
+
+#[cfg(feature = "std")]
+pub mod balance_proof;
+
+#[cfg(feature = "std")]
+pub mod batch;
+
+#[cfg(feature = "std")]
+pub mod calldata;
+
+#[cfg(feature = "std")]
+pub mod chains;
+
+#[cfg(feature = "std")]
+pub mod cycle_limits;
+
+#[cfg(feature = "std")]
+pub mod denominations;
+
+#[cfg(feature = "std")]
+pub mod denylist;
+
+#[cfg(feature = "std")]
+pub mod differential;
+
+#[cfg(feature = "std")]
+pub mod guest_elf;
+
+#[cfg(feature = "std")]
+pub mod indexer;
+
+#[cfg(feature = "std")]
+pub mod ledger;
+
+#[cfg(feature = "std")]
+pub mod linkability;
+
+#[cfg(feature = "std")]
+pub mod migration;
+
+#[cfg(feature = "std")]
+pub mod minimize;
+
+#[cfg(feature = "std")]
+pub mod nullifier_set;
+
+#[cfg(feature = "std")]
+pub mod output_order;
+
+#[cfg(feature = "std")]
+pub mod owner;
+
+#[cfg(feature = "std")]
+pub mod proof_request;
+
+#[cfg(feature = "std")]
+pub mod prover_event;
+
+#[cfg(feature = "std")]
+pub mod response_signature;
+
+#[cfg(feature = "std")]
+pub mod revocation;
+
+#[cfg(feature = "std")]
+pub mod signatures;
+
+#[cfg(feature = "std")]
+pub mod signature_batch;
+
+#[cfg(feature = "std")]
+pub mod signer;
+
+#[cfg(feature = "std")]
+pub mod wallet;
+
+#[cfg(feature = "std")]
+pub mod witness_privacy;
+
 #[cfg(feature = "encryption")]
 pub mod transaction_builder;
 
@@ -18,11 +110,98 @@ pub mod deposit_withdraw;
 #[cfg(feature = "encryption")]
 pub mod tx_metadata;
 
+#[cfg(feature = "encryption")]
+pub mod witness_store;
+
+#[cfg(feature = "encryption")]
+pub mod memo;
+
+#[cfg(feature = "encryption")]
+pub mod wallet_backup;
+
+#[cfg(feature = "encryption")]
+pub mod prepare;
+
+#[cfg(feature = "encryption")]
+pub mod delegated;
+
+#[cfg(feature = "encryption")]
+pub mod recovery;
+
+#[cfg(feature = "encryption")]
+pub mod sealed_fields;
+
+#[cfg(feature = "encryption")]
+pub mod webhooks;
+
+#[cfg(any(feature = "testing", all(test, feature = "std")))]
+pub mod testing;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "ffi")]
+uniffi::setup_scaffolding!();
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "abi")]
+pub mod events;
+
+#[cfg(feature = "abi")]
+pub mod public_values;
+
+#[cfg(feature = "abi")]
+pub mod fee_market;
+
+#[cfg(all(test, feature = "std"))]
+mod commitment_vectors;
+
 // Re-exports for convenience
 pub use crate::note::{commit, compute_nullifier, Note, Nullifier};
-pub use merkle::MerkleTree;
-pub use ledger::{Ledger, PublicOutputs, simulate_tx_with_precomputed};
-pub use sp1_types::{PublicInputs, Witness};
+pub use hex::{Bytes20, Bytes32, Bytes65};
+pub use merkle::{Insertion, MerkleTree};
+pub use sp1_types::{GuestInput, PublicInputs, Withdrawal, Witness};
+
+#[cfg(feature = "std")]
+pub use batch::{plan_chained_batch, BatchPlan, BatchStep};
+
+#[cfg(feature = "std")]
+pub use cycle_limits::{check_cycles, CycleLimits, CycleModel, CycleSample};
+
+#[cfg(feature = "std")]
+pub use nullifier_set::{NullifierSet, NullifierSnapshot};
+
+#[cfg(feature = "std")]
+pub use proof_request::{NoteData, ProofRequest};
+
+#[cfg(feature = "std")]
+pub use ledger::{
+    check_tx_with_precomputed, simulate_tx_with_precomputed, simulate_witness, InputCheck, Ledger, OutputCheck,
+    PublicOutputs, TransitionReport, TxSimulation,
+};
+
+#[cfg(feature = "std")]
+pub use prover_event::{ProofResult, ProverEvent};
+
+#[cfg(feature = "std")]
+pub use response_signature::ResponseSignature;
+
+#[cfg(feature = "std")]
+pub use signature_batch::RecoveredKeys;
+
+#[cfg(feature = "std")]
+pub use signer::{KeySigner, LedgerSigner, Signer, TrezorSigner};
+
+#[cfg(feature = "std")]
+pub use wallet::{ExclusionReason, OwnedNote, ScanCursor, WalletState};
+
+#[cfg(feature = "std")]
+pub use chains::{ChainDeployment, ChainRegistry, ChainState};
+
+#[cfg(feature = "std")]
+pub use witness_privacy::{PrivacyAssessment, Sensitivity};
 
 #[cfg(feature = "encryption")]
 pub use encryption::{generate_keypair, encrypt_note, decrypt_note, EncryptedNote, ViewPublicKey, ViewSecretKey, KeyType};
@@ -35,3 +214,28 @@ pub use deposit_withdraw::{DepositData, WithdrawData};
 
 #[cfg(feature = "encryption")]
 pub use transaction_builder::TransactionBuilder;
+
+#[cfg(feature = "encryption")]
+pub use witness_store::{SealedWitness, StoredJob, WitnessStore};
+
+#[cfg(feature = "encryption")]
+pub use wallet_backup::{BackupManifest, WalletBackup};
+
+#[cfg(feature = "encryption")]
+pub use delegated::{EncryptedRequest, ProverKey, ProverKeyring};
+
+#[cfg(feature = "encryption")]
+pub use sealed_fields::SealedFieldsRequest;
+
+#[cfg(feature = "encryption")]
+pub use prepare::{
+    prepare_transaction, prepare_transaction_with_policy, prepare_transaction_with_signer, prepare_withdrawal,
+    prepare_withdrawal_with_policy, prepare_withdrawal_with_signer, sign_owned_inputs, sign_owned_inputs_with_signer,
+    ProofSource, Recipient,
+};
+
+#[cfg(feature = "encryption")]
+pub use recovery::{derive_note_blinding, recover_wallet, Recovery};
+
+#[cfg(feature = "abi")]
+pub use events::{decode_ledger_log, LedgerEvent};
@@ -0,0 +1,178 @@
+//! Linkability report for a proposed transaction
+//!
+//! The proof hides which notes a transaction spends and what it pays, but
+//! the transaction can still be tied to others by what surrounds it.
+//! `assess` looks for the patterns a chain observer or counterparty uses:
+//!
+//! - distinctive amounts: outputs that aren't a standard denomination (see
+//!   `denominations`), and a withdrawal, whose amount is public, equal to
+//!   one of the notes
+//! - immediate spends: an input among the last `fresh_leaves` leaves, so
+//!   only a handful of notes could be the one spent and the timing links
+//!   payer and payee
+//! - rare shapes: more than two inputs or outputs, which few transactions
+//!   share (denominated payments trade this for amount privacy)
+//!
+//! Each warning has a weight; `score` is their sum, capped at 100 (0 is
+//! nothing found). Wallets show the warnings before asking for a proof,
+//! and the host logs them and returns them with the proof.
+
+use serde::{Deserialize, Serialize};
+
+use crate::denominations::AmountExposure;
+use crate::sp1_types::Witness;
+
+/// Inputs this close to the end of the tree count as just received.
+pub const DEFAULT_FRESH_LEAVES: u64 = 64;
+
+const DISTINCTIVE_AMOUNT_WEIGHT: u8 = 20;
+const WITHDRAWAL_MATCH_WEIGHT: u8 = 30;
+const IMMEDIATE_SPEND_WEIGHT: u8 = 25;
+const RARE_SHAPE_WEIGHT: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkabilityKind {
+    DistinctiveAmount,
+    WithdrawalMatchesNote,
+    ImmediateSpend,
+    RareShape,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkabilityWarning {
+    pub kind: LinkabilityKind,
+    pub weight: u8,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkabilityReport {
+    /// 0 (nothing found) to 100
+    pub score: u8,
+    pub warnings: Vec<LinkabilityWarning>,
+}
+
+impl LinkabilityReport {
+    fn warn(&mut self, kind: LinkabilityKind, weight: u8, message: String) {
+        self.score = self.score.saturating_add(weight).min(100);
+        self.warnings.push(LinkabilityWarning { kind, weight, message });
+    }
+}
+
+/// What `assess` knows beyond the witness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkabilityContext {
+    /// Leaves in the tree now; immediate spends aren't detected without it
+    pub leaf_count: Option<u64>,
+    pub fresh_leaves: u64,
+}
+
+impl Default for LinkabilityContext {
+    fn default() -> Self {
+        Self { leaf_count: None, fresh_leaves: DEFAULT_FRESH_LEAVES }
+    }
+}
+
+/// Score how linkable `witness` is (see the module docs).
+pub fn assess(witness: &Witness, context: &LinkabilityContext) -> LinkabilityReport {
+    let mut report = LinkabilityReport::default();
+
+    for (i, note) in witness.output_notes.iter().enumerate().filter(|(_, note)| note.amount != 0) {
+        let exposure = AmountExposure::of(note.amount);
+        if !exposure.standard {
+            report.warn(
+                LinkabilityKind::DistinctiveAmount,
+                DISTINCTIVE_AMOUNT_WEIGHT,
+                format!(
+                    "Output {} amount {} isn't a standard denomination (~{:.0} identifying bits)",
+                    i, note.amount, exposure.identifying_bits
+                ),
+            );
+        }
+    }
+
+    if let Some(withdrawal) = &witness.withdrawal {
+        let amount = withdrawal.public_amount;
+        let matching = witness
+            .input_notes
+            .iter()
+            .position(|n| n.amount == amount)
+            .map(|i| format!("input {}", i))
+            .or_else(|| witness.output_notes.iter().position(|n| n.amount == amount).map(|i| format!("output {}", i)));
+        if let Some(note) = matching {
+            report.warn(
+                LinkabilityKind::WithdrawalMatchesNote,
+                WITHDRAWAL_MATCH_WEIGHT,
+                format!("Withdrawal of {} publishes the exact amount of {}", amount, note),
+            );
+        }
+    }
+
+    if let Some(leaf_count) = context.leaf_count {
+        for (i, &index) in witness.input_indices.iter().enumerate() {
+            let newer = leaf_count.saturating_sub(index as u64 + 1);
+            if newer < context.fresh_leaves {
+                report.warn(
+                    LinkabilityKind::ImmediateSpend,
+                    IMMEDIATE_SPEND_WEIGHT,
+                    format!("Input {} (leaf {}) is spent only {} leaves after it was created", i, index, newer),
+                );
+            }
+        }
+    }
+
+    let (inputs, outputs) = (witness.input_notes.len(), witness.output_notes.len());
+    if inputs > 2 || outputs > 2 {
+        report.warn(
+            LinkabilityKind::RareShape,
+            RARE_SHAPE_WEIGHT,
+            format!("{} inputs and {} outputs is a rare shape; most transactions have at most 2 of each", inputs, outputs),
+        );
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use crate::sp1_types::Withdrawal;
+
+    fn witness(inputs: &[(u64, usize)], outputs: &[u64]) -> Witness {
+        let notes = inputs.iter().map(|&(amount, _)| Note::new(amount, [1; 32], [2; 32])).collect();
+        let indices = inputs.iter().map(|&(_, index)| index).collect();
+        let sigs = vec![vec![0u8; 65]; inputs.len()];
+        let outputs = outputs.iter().map(|&amount| Note::new(amount, [3; 32], [4; 32])).collect();
+        Witness::new_without_proofs(notes, indices, sigs.clone(), sigs, outputs)
+    }
+
+    #[test]
+    fn test_clean_transaction_scores_zero() {
+        let report = assess(&witness(&[(1100, 3)], &[1000, 100]), &LinkabilityContext { leaf_count: Some(500), ..Default::default() });
+        assert_eq!(report, LinkabilityReport::default());
+    }
+
+    #[test]
+    fn test_linkable_patterns_are_reported() {
+        let context = LinkabilityContext { leaf_count: Some(100), fresh_leaves: 10 };
+        let report = assess(&witness(&[(1_234, 95), (100, 3), (100, 4)], &[1_334, 0, 0]), &context);
+        let kinds: Vec<LinkabilityKind> = report.warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![LinkabilityKind::DistinctiveAmount, LinkabilityKind::ImmediateSpend, LinkabilityKind::RareShape]
+        );
+        assert_eq!(report.score, 60);
+        assert!(report.warnings[1].message.contains("only 4 leaves"));
+
+        let withdrawal = witness(&[(500, 1)], &[]).with_withdrawal(Withdrawal { public_amount: 500, recipient: [0; 20].into() });
+        let report = assess(&withdrawal, &LinkabilityContext::default());
+        assert_eq!(report.warnings[0].kind, LinkabilityKind::WithdrawalMatchesNote);
+
+        let heavy = witness(&[(1, 98); 3], &[7, 7, 7]);
+        assert_eq!(assess(&heavy, &context).score, 100);
+    }
+}
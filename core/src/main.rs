@@ -1,7 +1,7 @@
 // src/main.rs
 
-use utxo_prototype::ledger::{simulate_tx_and_build_public_outputs, Ledger, PublicOutputs};
-use utxo_prototype::note::Note;
+use ghostclaw_core::ledger::{simulate_tx_and_build_public_outputs, Ledger, PublicOutputs};
+use ghostclaw_core::note::Note;
 
 fn main() {
     // HIGH-LEVEL: tiny "demo chain" to show how a transaction
@@ -12,7 +12,7 @@ fn main() {
 
     // Generate keys for our users
     #[cfg(feature = "encryption")]
-    use utxo_prototype::encryption::generate_keypair;
+    use ghostclaw_core::encryption::generate_keypair;
     #[cfg(not(feature = "encryption"))]
     fn generate_keypair() -> ([u8; 32], [u8; 33]) { ([1u8; 32], [2u8; 33]) }
 
@@ -30,27 +30,15 @@ fn main() {
     charlie_owner.copy_from_slice(&charlie_pub[1..]);
 
     // Add two notes (like an initial funding step).
-    let note1 = Note {
-        amount: 10,
-        owner_pubkey: alice_owner,
-        blinding: [2u8; 32],
-    };
-
-    let note2 = Note {
-        amount: 20,
-        owner_pubkey: bob_owner,
-        blinding: [4u8; 32],
-    };
+    let note1 = Note::new(10, alice_owner, [2u8; 32]);
+
+    let note2 = Note::new(20, bob_owner, [4u8; 32]);
 
     ledger.add_note(note1.clone());
     ledger.add_note(note2.clone());
 
     // Build a simple tx: spend note1 (index 0) into a new note with amount 10.
-    let out_note = Note {
-        amount: 10,
-        owner_pubkey: charlie_owner, // pretend this is someone else's key
-        blinding: [5u8; 32],
-    };
+    let out_note = Note::new(10, charlie_owner, [5u8; 32]); // pretend this is someone else's key
 
     // Sign the input note
     use k256::ecdsa::{SigningKey, signature::Signer};
@@ -63,7 +51,7 @@ fn main() {
     let signing_key = SigningKey::from_slice(&alice_key).expect("invalid private key");
 
     // 1. Generate Nullifier Signature
-    let input_commitment = utxo_prototype::commit(&note1);
+    let input_commitment = ghostclaw_core::commit(&note1);
     let mut hasher = Keccak256::new();
     hasher.update(&input_commitment);
     let msg_hash = hasher.finalize();
@@ -79,13 +67,14 @@ fn main() {
     nullifier_sig.push(rec_id.to_byte() + 27);
 
     // 2. Generate Tx Signature
-    // Message = Keccak(Nullifier || OutputCommitments)
+    // Message = Keccak(Nullifier || Fee || OutputCommitments)
     // Compute Nullifier first
-    let nullifier = utxo_prototype::note::compute_nullifier(&nullifier_sig);
+    let nullifier = ghostclaw_core::note::compute_nullifier(&nullifier_sig);
     
-    let output_commitment = utxo_prototype::commit(&out_note);
+    let output_commitment = ghostclaw_core::commit(&out_note);
     let mut tx_hasher = Keccak256::new();
     tx_hasher.update(&nullifier);
+    tx_hasher.update(0u64.to_be_bytes()); // fee: 10 in, 10 out
     tx_hasher.update(&output_commitment);
     let tx_msg_hash = tx_hasher.finalize();
 
@@ -168,27 +157,19 @@ mod hex {
 
 #[cfg(test)]
 mod tests {
-    use utxo_prototype::ledger::Ledger;
-    use utxo_prototype::merkle::MerkleTree;
-    use utxo_prototype::note::{commit, Note};
+    use ghostclaw_core::ledger::Ledger;
+    use ghostclaw_core::merkle::MerkleTree;
+    use ghostclaw_core::note::{commit, Note};
 
     #[cfg(feature = "encryption")]
-    use utxo_prototype::encryption::generate_keypair;
+    use ghostclaw_core::encryption::generate_keypair;
     #[cfg(not(feature = "encryption"))]
     fn generate_keypair() -> ([u8; 32], [u8; 33]) { ([1u8; 32], [2u8; 33]) }
 
     #[test]
     fn merkle_root_changes_when_leaves_change() {
-        let note1 = Note {
-            amount: 10,
-            owner_pubkey: [1u8; 32],
-            blinding: [2u8; 32],
-        };
-        let note2 = Note {
-            amount: 20,
-            owner_pubkey: [3u8; 32],
-            blinding: [4u8; 32],
-        };
+        let note1 = Note::new(10, [1u8; 32], [2u8; 32]);
+        let note2 = Note::new(20, [3u8; 32], [4u8; 32]);
 
         let c1 = commit(&note1);
         let c2 = commit(&note2);
@@ -214,11 +195,7 @@ mod tests {
         owner.copy_from_slice(&pubkey[1..]);
 
         // One initial note with amount 10 at index 0.
-        let note = Note {
-            amount: 10,
-            owner_pubkey: owner,
-            blinding: [2u8; 32],
-        };
+        let note = Note::new(10, owner, [2u8; 32]);
 
         ledger.add_note(note.clone());
 
@@ -227,7 +204,7 @@ mod tests {
             let signing_key = SigningKey::from_slice(&key).expect("invalid key");
             
             // Nullifier Sig
-            let commit = utxo_prototype::note::commit(&note);
+            let commit = ghostclaw_core::note::commit(&note);
             let mut hasher = Keccak256::new();
             hasher.update(&commit);
             let msg = hasher.finalize();
@@ -240,10 +217,11 @@ mod tests {
             null_sig.push(rid.to_byte() + 27);
 
             // Tx Sig
-            let nullifier = utxo_prototype::note::compute_nullifier(&null_sig);
-            let out_commit = utxo_prototype::note::commit(out_note);
+            let nullifier = ghostclaw_core::note::compute_nullifier(&null_sig);
+            let out_commit = ghostclaw_core::note::commit(out_note);
             let mut tx_hasher = Keccak256::new();
             tx_hasher.update(&nullifier);
+            tx_hasher.update(0u64.to_be_bytes()); // fee
             tx_hasher.update(&out_commit);
             let tx_msg = tx_hasher.finalize();
             let mut eth_tx_hasher = Keccak256::new();
@@ -258,11 +236,7 @@ mod tests {
         };
 
         // First tx: spend index 0 into a new note with amount 10.
-        let out_note = Note {
-            amount: 10,
-            owner_pubkey: [9u8; 32],
-            blinding: [5u8; 32],
-        };
+        let out_note = Note::new(10, [9u8; 32], [5u8; 32]);
         
         let (nsig1, tsig1) = sign_tx(&out_note);
 
@@ -270,11 +244,7 @@ mod tests {
         assert!(res1.is_ok(), "first spend should succeed");
 
         // Second tx: try to spend index 0 again (same original note).
-        let out_note2 = Note {
-            amount: 10,
-            owner_pubkey: [9u8; 32],
-            blinding: [6u8; 32],
-        };
+        let out_note2 = Note::new(10, [9u8; 32], [6u8; 32]);
 
         let (nsig2, tsig2) = sign_tx(&out_note2);
 
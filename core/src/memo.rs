@@ -0,0 +1,301 @@
+//! Memos larger than one ciphertext blob
+//!
+//! A memo travels inside the output's `CommitmentMetadata`, sealed as one
+//! blob. Richer payloads (invoices, refund addresses) don't fit the size an
+//! indexer or relayer will carry per blob, so a long memo is split into
+//! chunks, each sealed on its own with a header (`memo_id`, `index`,
+//! `count`, `total_len`), and the metadata is sealed without it. The
+//! output's metadata field then holds every blob, framed with `MAGIC`;
+//! metadata with a short memo stays a single, unframed blob as before.
+//!
+//! The scanner (`open_output_metadata`) puts the memo back with a
+//! `MemoAssembler`, which accepts the chunks in any order and checks that
+//! they agree on the memo's shape. `MemoLimits` bound both sides: the
+//! builder refuses a memo over `max_total_bytes` (see `TransactionBuilder`),
+//! and the assembler refuses headers that claim more.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encryption::{decrypt_note, encrypt_note, EncryptedNote, ViewPublicKey, ViewSecretKey};
+use crate::tx_metadata::CommitmentMetadata;
+
+/// Prefix of an output metadata field holding several blobs.
+pub const MAGIC: &[u8; 4] = b"GCM1";
+
+/// Size limits of memos, in plaintext bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoLimits {
+    /// Memo bytes sealed per blob
+    pub max_chunk_bytes: usize,
+    /// Largest memo accepted
+    pub max_total_bytes: usize,
+}
+
+impl Default for MemoLimits {
+    fn default() -> Self {
+        Self { max_chunk_bytes: 512, max_total_bytes: 16 * 1024 }
+    }
+}
+
+impl MemoLimits {
+    /// # Errors
+    /// Fails if a memo of `len` bytes is over the limit, or would need more
+    /// chunks than a header can count.
+    pub fn check(&self, len: usize) -> Result<(), String> {
+        if self.max_chunk_bytes == 0 {
+            return Err("Memo chunk size must be nonzero".to_string());
+        }
+        if len > self.max_total_bytes {
+            return Err(format!("Memo of {} bytes exceeds the {}-byte limit", len, self.max_total_bytes));
+        }
+        if len.div_ceil(self.max_chunk_bytes) > u16::MAX as usize {
+            return Err(format!("Memo of {} bytes needs more than {} chunks", len, u16::MAX));
+        }
+        Ok(())
+    }
+}
+
+/// Position of a chunk in its memo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoHeader {
+    /// Random, shared by the memo's chunks
+    pub memo_id: [u8; 16],
+    pub index: u16,
+    pub count: u16,
+    pub total_len: u32,
+}
+
+/// One sealed piece of a memo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoChunk {
+    pub header: MemoHeader,
+    pub data: Vec<u8>,
+}
+
+/// Split `memo` into chunks of at most `limits.max_chunk_bytes`.
+pub fn split_memo(memo: &[u8], limits: &MemoLimits) -> Result<Vec<MemoChunk>, String> {
+    limits.check(memo.len())?;
+    let memo_id: [u8; 16] = rand::random();
+    let count = memo.len().div_ceil(limits.max_chunk_bytes) as u16;
+    Ok(memo
+        .chunks(limits.max_chunk_bytes)
+        .enumerate()
+        .map(|(index, data)| MemoChunk {
+            header: MemoHeader { memo_id, index: index as u16, count, total_len: memo.len() as u32 },
+            data: data.to_vec(),
+        })
+        .collect())
+}
+
+/// Seal each chunk of `memo` to `recipient_pubkey` as its own blob.
+pub fn seal_memo(memo: &[u8], recipient_pubkey: &ViewPublicKey, limits: &MemoLimits) -> Result<Vec<Vec<u8>>, String> {
+    split_memo(memo, limits)?
+        .iter()
+        .map(|chunk| {
+            let plaintext = bincode::serialize(chunk).map_err(|e| format!("Serialize failed: {}", e))?;
+            bincode::serialize(&encrypt_note(&plaintext, recipient_pubkey)?)
+                .map_err(|e| format!("Failed to serialize encrypted memo chunk: {}", e))
+        })
+        .collect()
+}
+
+/// Open a blob sealed by `seal_memo`, or `None` if it isn't one for us.
+pub fn open_memo_chunk(blob: &[u8], secret_key: &ViewSecretKey) -> Option<MemoChunk> {
+    let encrypted: EncryptedNote = bincode::deserialize(blob).ok()?;
+    bincode::deserialize(&decrypt_note(&encrypted, secret_key)?).ok()
+}
+
+/// Chunks received so far of one memo.
+struct PartialMemo {
+    count: u16,
+    total_len: u32,
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+/// Reassembles memos from chunks arriving in any order.
+pub struct MemoAssembler {
+    limits: MemoLimits,
+    partial: HashMap<[u8; 16], PartialMemo>,
+}
+
+impl MemoAssembler {
+    pub fn new(limits: MemoLimits) -> Self {
+        Self { limits, partial: HashMap::new() }
+    }
+
+    /// Add a chunk; returns the memo once its last chunk arrives.
+    ///
+    /// # Errors
+    /// Fails on a header over the limits, out of range or disagreeing with
+    /// earlier chunks of the memo, and on chunks of the wrong size. A
+    /// repeated chunk with the same data is ignored.
+    pub fn add(&mut self, chunk: MemoChunk) -> Result<Option<Vec<u8>>, String> {
+        let MemoChunk { header, data } = chunk;
+        self.limits.check(header.total_len as usize)?;
+        let expected_count = (header.total_len as usize).div_ceil(self.limits.max_chunk_bytes);
+        if header.count as usize != expected_count || header.index >= header.count {
+            return Err(format!(
+                "Memo chunk {} of {} doesn't fit a {}-byte memo",
+                header.index, header.count, header.total_len
+            ));
+        }
+        let expected_len = if header.index + 1 < header.count {
+            self.limits.max_chunk_bytes
+        } else {
+            header.total_len as usize - (header.count as usize - 1) * self.limits.max_chunk_bytes
+        };
+        if data.len() != expected_len {
+            return Err(format!("Memo chunk {} is {} bytes, expected {}", header.index, data.len(), expected_len));
+        }
+
+        let partial = self.partial.entry(header.memo_id).or_insert_with(|| PartialMemo {
+            count: header.count,
+            total_len: header.total_len,
+            chunks: vec![None; header.count as usize],
+        });
+        if partial.count != header.count || partial.total_len != header.total_len {
+            return Err("Memo chunks disagree on the memo's size".to_string());
+        }
+        match &partial.chunks[header.index as usize] {
+            Some(existing) if *existing != data => {
+                return Err(format!("Memo chunk {} arrived twice with different data", header.index));
+            }
+            Some(_) => return Ok(None),
+            None => partial.chunks[header.index as usize] = Some(data),
+        }
+        if partial.chunks.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+
+        let partial = self.partial.remove(&header.memo_id).expect("memo is pending");
+        Ok(Some(partial.chunks.into_iter().flatten().flatten().collect()))
+    }
+
+    /// Memos with chunks still missing.
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+}
+
+/// Seal `metadata` for an output's metadata field, chunking a memo longer
+/// than `limits.max_chunk_bytes` (see the module docs).
+pub fn seal_output_metadata(
+    metadata: &CommitmentMetadata,
+    recipient_pubkey: &ViewPublicKey,
+    limits: &MemoLimits,
+) -> Result<Vec<u8>, String> {
+    let memo = metadata.memo().unwrap_or_default();
+    limits.check(memo.len())?;
+    if memo.len() <= limits.max_chunk_bytes {
+        return metadata.encrypt(recipient_pubkey);
+    }
+
+    let mut without_memo = metadata.clone();
+    without_memo.set_memo(None);
+    let mut blobs = vec![without_memo.encrypt(recipient_pubkey)?];
+    blobs.extend(seal_memo(memo.as_bytes(), recipient_pubkey, limits)?);
+
+    let mut field = MAGIC.to_vec();
+    bincode::serialize_into(&mut field, &blobs).map_err(|e| format!("Failed to frame metadata: {}", e))?;
+    Ok(field)
+}
+
+/// Open an output's metadata field, reassembling a chunked memo.
+///
+/// # Errors
+/// Fails if the metadata isn't for `secret_key`, or a chunked memo is
+/// incomplete, inconsistent or not UTF-8.
+pub fn open_output_metadata(field: &[u8], secret_key: &ViewSecretKey, limits: &MemoLimits) -> Result<CommitmentMetadata, String> {
+    let Some(framed) = field.strip_prefix(MAGIC.as_slice()) else {
+        return CommitmentMetadata::decrypt(field, secret_key);
+    };
+    let blobs: Vec<Vec<u8>> = bincode::deserialize(framed).map_err(|e| format!("Invalid metadata framing: {}", e))?;
+    let (first, chunks) = blobs.split_first().ok_or("Framed metadata holds no blobs")?;
+    let mut metadata = CommitmentMetadata::decrypt(first, secret_key)?;
+
+    let mut assembler = MemoAssembler::new(*limits);
+    let mut memo = None;
+    for blob in chunks {
+        let chunk = open_memo_chunk(blob, secret_key).ok_or("Failed to decrypt memo chunk")?;
+        if let Some(complete) = assembler.add(chunk)? {
+            memo = Some(complete);
+        }
+    }
+    if assembler.pending() > 0 || memo.is_none() {
+        return Err("Memo chunks are missing".to_string());
+    }
+    let memo = String::from_utf8(memo.unwrap_or_default()).map_err(|_| "Memo is not UTF-8".to_string())?;
+    metadata.set_memo(Some(memo));
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::generate_keypair;
+
+    fn limits() -> MemoLimits {
+        MemoLimits { max_chunk_bytes: 16, max_total_bytes: 100 }
+    }
+
+    #[test]
+    fn test_long_memo_round_trips_through_metadata() {
+        let (secret, public) = generate_keypair();
+        let memo = "Invoice #42: 3 widgets, refund to 0xabc".repeat(2);
+        let metadata = CommitmentMetadata::for_recipient(None, Some(memo.clone()), [7; 32]);
+
+        let field = seal_output_metadata(&metadata, &public, &limits()).unwrap();
+        assert!(field.starts_with(MAGIC));
+        assert_eq!(open_output_metadata(&field, &secret, &limits()).unwrap().memo(), Some(memo.as_str()));
+
+        // Short memos stay a single unframed blob
+        let short = CommitmentMetadata::for_recipient(None, Some("hi".to_string()), [7; 32]);
+        let field = seal_output_metadata(&short, &public, &limits()).unwrap();
+        assert_eq!(CommitmentMetadata::decrypt(&field, &secret).unwrap().memo(), Some("hi"));
+
+        let too_long = CommitmentMetadata::for_recipient(None, Some("x".repeat(101)), [7; 32]);
+        assert!(seal_output_metadata(&too_long, &public, &limits()).unwrap_err().contains("exceeds"));
+    }
+
+    #[test]
+    fn test_assembler_accepts_any_order() {
+        let memo: Vec<u8> = (0..40).collect();
+        let mut chunks = split_memo(&memo, &limits()).unwrap();
+        assert_eq!(chunks.len(), 3);
+        chunks.reverse();
+
+        let mut assembler = MemoAssembler::new(limits());
+        assert_eq!(assembler.add(chunks[0].clone()).unwrap(), None);
+        assert_eq!(assembler.add(chunks[0].clone()).unwrap(), None);
+        assert_eq!(assembler.add(chunks[1].clone()).unwrap(), None);
+        assert_eq!(assembler.pending(), 1);
+        assert_eq!(assembler.add(chunks[2].clone()).unwrap(), Some(memo));
+        assert_eq!(assembler.pending(), 0);
+    }
+
+    #[test]
+    fn test_assembler_rejects_inconsistent_chunks() {
+        let chunks = split_memo(&[1; 40], &limits()).unwrap();
+        let mut assembler = MemoAssembler::new(limits());
+        assembler.add(chunks[0].clone()).unwrap();
+
+        let mut altered = chunks[0].clone();
+        altered.data[0] = 2;
+        assert!(assembler.add(altered).unwrap_err().contains("twice"));
+
+        let mut short = chunks[1].clone();
+        short.data.pop();
+        assert!(assembler.add(short).is_err());
+
+        let mut oversized = chunks[1].clone();
+        oversized.header.total_len = 1_000;
+        assert!(assembler.add(oversized).unwrap_err().contains("exceeds"));
+
+        let mut out_of_range = chunks[2].clone();
+        out_of_range.header.index = 3;
+        assert!(assembler.add(out_of_range).is_err());
+    }
+}
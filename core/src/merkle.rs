@@ -1,3 +1,7 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use crate::note::commit;
@@ -9,17 +13,61 @@ pub const TREE_HEIGHT: usize = 32;
 /// Precomputed zero hashes for each level of the tree
 /// ZEROS[0] = hash of empty leaf
 /// ZEROS[i] = hash(ZEROS[i-1], ZEROS[i-1])
-lazy_static::lazy_static! {
-    pub static ref ZEROS: [[u8; 32]; TREE_HEIGHT] = {
-        let mut zeros = [[0u8; 32]; TREE_HEIGHT];
-        // Level 0: empty leaf is just zeros
-        zeros[0] = [0u8; 32];
-        // Each subsequent level is hash of two children from previous level
-        for i in 1..TREE_HEIGHT {
-            zeros[i] = hash_pair(zeros[i-1], zeros[i-1]);
+///
+/// A constant table (the same values `MerkleTree.sol` hardcodes) rather than
+/// a lazily computed one, so no runtime initialization is needed in the guest
+/// or on wasm32.
+pub const ZEROS: [[u8; 32]; TREE_HEIGHT] = [
+    hex32("0000000000000000000000000000000000000000000000000000000000000000"),
+    hex32("ad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"),
+    hex32("b4c11951957c6f8f642c4af61cd6b24640fec6dc7fc607ee8206a99e92410d30"),
+    hex32("21ddb9a356815c3fac1026b6dec5df3124afbadb485c9ba5a3e3398a04b7ba85"),
+    hex32("e58769b32a1beaf1ea27375a44095a0d1fb664ce2dd358e7fcbfb78c26a19344"),
+    hex32("0eb01ebfc9ed27500cd4dfc979272d1f0913cc9f66540d7e8005811109e1cf2d"),
+    hex32("887c22bd8750d34016ac3c66b5ff102dacdd73f6b014e710b51e8022af9a1968"),
+    hex32("ffd70157e48063fc33c97a050f7f640233bf646cc98d9524c6b92bcf3ab56f83"),
+    hex32("9867cc5f7f196b93bae1e27e6320742445d290f2263827498b54fec539f756af"),
+    hex32("cefad4e508c098b9a7e1d8feb19955fb02ba9675585078710969d3440f5054e0"),
+    hex32("f9dc3e7fe016e050eff260334f18a5d4fe391d82092319f5964f2e2eb7c1c3a5"),
+    hex32("f8b13a49e282f609c317a833fb8d976d11517c571d1221a265d25af778ecf892"),
+    hex32("3490c6ceeb450aecdc82e28293031d10c7d73bf85e57bf041a97360aa2c5d99c"),
+    hex32("c1df82d9c4b87413eae2ef048f94b4d3554cea73d92b0f7af96e0271c691e2bb"),
+    hex32("5c67add7c6caf302256adedf7ab114da0acfe870d449a3a489f781d659e8becc"),
+    hex32("da7bce9f4e8618b6bd2f4132ce798cdc7a60e7e1460a7299e3c6342a579626d2"),
+    hex32("2733e50f526ec2fa19a22b31e8ed50f23cd1fdf94c9154ed3a7609a2f1ff981f"),
+    hex32("e1d3b5c807b281e4683cc6d6315cf95b9ade8641defcb32372f1c126e398ef7a"),
+    hex32("5a2dce0a8a7f68bb74560f8f71837c2c2ebbcbf7fffb42ae1896f13f7c7479a0"),
+    hex32("b46a28b6f55540f89444f63de0378e3d121be09e06cc9ded1c20e65876d36aa0"),
+    hex32("c65e9645644786b620e2dd2ad648ddfcbf4a7e5b1a3a4ecfe7f64667a3f0b7e2"),
+    hex32("f4418588ed35a2458cffeb39b93d26f18d2ab13bdce6aee58e7b99359ec2dfd9"),
+    hex32("5a9c16dc00d6ef18b7933a6f8dc65ccb55667138776f7dea101070dc8796e377"),
+    hex32("4df84f40ae0c8229d0d6069e5c8f39a7c299677a09d367fc7b05e3bc380ee652"),
+    hex32("cdc72595f74c7b1043d0e1ffbab734648c838dfb0527d971b602bc216c9619ef"),
+    hex32("0abf5ac974a1ed57f4050aa510dd9c74f508277b39d7973bb2dfccc5eeb0618d"),
+    hex32("b8cd74046ff337f0a7bf2c8e03e10f642c1886798d71806ab1e888d9e5ee87d0"),
+    hex32("838c5655cb21c6cb83313b5a631175dff4963772cce9108188b34ac87c81c41e"),
+    hex32("662ee4dd2dd7b2bc707961b1e646c4047669dcb6584f0d8d770daf5d7e7deb2e"),
+    hex32("388ab20e2573d171a88108e79d820e98f26c0b84aa8b2f4aa4968dbb818ea322"),
+    hex32("93237c50ba75ee485f4c22adf2f741400bdf8d6a9cc7df7ecae576221665d735"),
+    hex32("8448818bb4ae4562849e949e17ac16e0be16688e156b5cf15e098c627c0056a9"),
+];
+
+const fn hex32(s: &str) -> [u8; 32] {
+    const fn nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            _ => panic!("invalid hex digit"),
         }
-        zeros
-    };
+    }
+    let s = s.as_bytes();
+    let mut out = [0u8; 32];
+    let mut i = 0;
+    while i < 32 {
+        out[i] = (nibble(s[2 * i]) << 4) | nibble(s[2 * i + 1]);
+        i += 1;
+    }
+    out
 }
 
 /// Hash two 32-byte values using Keccak256
@@ -34,34 +82,88 @@ pub fn hash_pair(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
     hash
 }
 
+/// Position of a leaf. `u128` so trees taller than 64 levels can be
+/// addressed; every conversion to a narrower type is checked.
+pub type LeafIndex = u128;
+
+/// Tallest supported tree: the largest height whose leaf count (2^height)
+/// still fits in a `LeafIndex`.
+pub const MAX_TREE_HEIGHT: usize = 127;
+
+/// Zero hash for an empty subtree of height `level` (`ZEROS[level]` for the
+/// first TREE_HEIGHT levels, extended by hashing for taller trees).
+pub fn zero_hash(level: usize) -> [u8; 32] {
+    if level < TREE_HEIGHT {
+        return ZEROS[level];
+    }
+    (TREE_HEIGHT..=level).fold(ZEROS[TREE_HEIGHT - 1], |zero, _| hash_pair(zero, zero))
+}
+
+/// Zero hashes for every level of a tree of `height`.
+pub fn zero_hashes(height: usize) -> Vec<[u8; 32]> {
+    let mut zeros = ZEROS[..height.min(TREE_HEIGHT)].to_vec();
+    while zeros.len() < height {
+        let top = zeros[zeros.len() - 1];
+        zeros.push(hash_pair(top, top));
+    }
+    zeros
+}
+
+/// Number of leaves a tree of `height` can hold.
+pub fn capacity(height: usize) -> LeafIndex {
+    assert!(height <= MAX_TREE_HEIGHT, "tree height {} exceeds {}", height, MAX_TREE_HEIGHT);
+    1 << height
+}
+
 /// A Merkle proof for a fixed-height tree.
 ///
 /// # Structure
-/// - `leaf_index`: Position of the leaf in the tree (0 to 2^TREE_HEIGHT - 1)
-/// - `siblings`: Array of TREE_HEIGHT sibling hashes from leaf to root
+/// - `leaf_index`: Position of the leaf in the tree (0 to 2^height - 1)
+/// - `siblings`: One sibling hash per level, from leaf to root (TREE_HEIGHT
+///   for the deployed ledger; the proof's height is its sibling count)
 ///
 /// # Verification
 /// Start with the leaf, hash with each sibling moving up the tree,
 /// final result should equal the root.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MerkleProof {
-    pub leaf_index: u64,
+    pub leaf_index: LeafIndex,
+    #[serde(with = "crate::hex::bytes32_vec")]
     pub siblings: Vec<[u8; 32]>,
 }
 
 impl MerkleProof {
     /// Create a new Merkle proof with the given index and siblings
-    pub fn new(leaf_index: u64, siblings: Vec<[u8; 32]>) -> Self {
-        Self { leaf_index, siblings }
+    pub fn new(leaf_index: impl Into<LeafIndex>, siblings: Vec<[u8; 32]>) -> Self {
+        Self { leaf_index: leaf_index.into(), siblings }
     }
+
+    /// Height of the tree this proof is for.
+    pub fn height(&self) -> usize {
+        self.siblings.len()
+    }
+}
+
+/// A leaf just added by `push_leaf`, as of its insertion.
+///
+/// `new_root` and `path` are taken while inserting, so callers don't need
+/// a separate `root()`/`prove()` that a later insertion could get ahead of.
+/// The path stays valid against `new_root` only; later insertions change
+/// the leaf's siblings on the right.
+#[derive(Debug, Clone)]
+pub struct Insertion {
+    pub index: LeafIndex,
+    pub new_root: [u8; 32],
+    pub path: MerkleProof,
 }
 
 /// Fixed-height Incremental Merkle Tree using Keccak256
 ///
 /// # Design
-/// - Fixed height of TREE_HEIGHT (32) levels
+/// - Fixed height, TREE_HEIGHT (32) levels unless built `with_height`
 /// - Uses precomputed zero hashes for empty subtrees
-/// - Efficient incremental updates: O(TREE_HEIGHT) hashes per insert
+/// - Efficient incremental updates: O(height) hashes per insert
 /// - EVM-compatible: uses Keccak256 matching Solidity
 ///
 /// # Security
@@ -69,13 +171,20 @@ impl MerkleProof {
 /// - Proof verification is independent of tree state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerkleTree {
+    /// Number of levels below the root
+    #[serde(default = "default_height")]
+    height: usize,
     /// All leaves in the tree (commitments)
     leaves: Vec<[u8; 32]>,
     /// Cached intermediate nodes for efficient updates
     /// filled_subtrees[i] = the leftmost filled node at level i
     filled_subtrees: Vec<[u8; 32]>,
     /// Current number of leaves
-    next_index: u64,
+    next_index: LeafIndex,
+}
+
+fn default_height() -> usize {
+    TREE_HEIGHT
 }
 
 impl Default for MerkleTree {
@@ -88,12 +197,27 @@ impl MerkleTree {
     /// Create a new empty Merkle tree
     pub fn new() -> Self {
         Self {
+            height: TREE_HEIGHT,
             leaves: Vec::new(),
             filled_subtrees: ZEROS.to_vec(),
             next_index: 0,
         }
     }
 
+    /// Create an empty tree of `height` levels (1..=MAX_TREE_HEIGHT), for
+    /// deployments configured with a taller (or shorter) tree.
+    pub fn with_height(height: usize) -> Result<Self, String> {
+        if height == 0 || height > MAX_TREE_HEIGHT {
+            return Err(format!("Tree height must be 1..={}, got {}", MAX_TREE_HEIGHT, height));
+        }
+        Ok(Self {
+            height,
+            leaves: Vec::new(),
+            filled_subtrees: zero_hashes(height),
+            next_index: 0,
+        })
+    }
+
     /// Create a Merkle tree with initial leaves
     pub fn with_leaves(initial_leaves: Vec<[u8; 32]>) -> Self {
         let mut tree = Self::new();
@@ -103,52 +227,78 @@ impl MerkleTree {
         tree
     }
 
+    /// Number of levels below the root.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     /// Add a new leaf to the tree
-    /// Returns the index where the leaf was inserted
-    pub fn push_leaf(&mut self, leaf: [u8; 32]) -> u64 {
+    /// Returns its index, the new root and its proof (see `Insertion`)
+    ///
+    /// # Panics
+    /// If the tree is full (see `try_push_leaf`).
+    pub fn push_leaf(&mut self, leaf: [u8; 32]) -> Insertion {
+        self.try_push_leaf(leaf).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Add a new leaf, failing instead of wrapping when all 2^height
+    /// positions are taken.
+    pub fn try_push_leaf(&mut self, leaf: [u8; 32]) -> Result<Insertion, String> {
         let index = self.next_index;
+        if index >= capacity(self.height) {
+            return Err(format!("Merkle tree of height {} is full", self.height));
+        }
         self.leaves.push(leaf);
 
-        // Update filled_subtrees for incremental root computation
+        // Update filled_subtrees for incremental root computation. The new
+        // leaf is the rightmost, so its siblings are the filled subtrees on
+        // its left and empty subtrees on its right.
         let mut current_hash = leaf;
         let mut current_index = index;
+        let mut siblings = Vec::with_capacity(self.height);
 
-        for level in 0..TREE_HEIGHT {
+        for level in 0..self.height {
             if current_index % 2 == 0 {
                 // We're on the left, update filled_subtrees
                 self.filled_subtrees[level] = current_hash;
                 // Hash with zero on the right (empty subtree)
-                current_hash = hash_pair(current_hash, ZEROS[level]);
+                siblings.push(zero_hash(level));
+                current_hash = hash_pair(current_hash, zero_hash(level));
             } else {
                 // We're on the right, hash with the filled subtree on the left
+                siblings.push(self.filled_subtrees[level]);
                 current_hash = hash_pair(self.filled_subtrees[level], current_hash);
             }
             current_index /= 2;
         }
 
         self.next_index += 1;
-        index
+        Ok(Insertion {
+            index,
+            new_root: current_hash,
+            path: MerkleProof::new(index, siblings),
+        })
     }
 
     /// Convenience helper: push a commitment for a note
-    pub fn push_note(&mut self, note: &crate::note::Note) -> u64 {
+    pub fn push_note(&mut self, note: &crate::note::Note) -> Insertion {
         self.push_leaf(commit(note))
     }
 
     /// Get the current Merkle root
     pub fn root(&self) -> [u8; 32] {
         if self.leaves.is_empty() {
-            return ZEROS[TREE_HEIGHT - 1];
+            return zero_hash(self.height - 1);
         }
 
         // Compute root by walking up from the last inserted leaf
         let mut current_hash = self.leaves[self.leaves.len() - 1];
         let mut current_index = self.next_index - 1;
 
-        for level in 0..TREE_HEIGHT {
+        for level in 0..self.height {
             if current_index % 2 == 0 {
                 // We're on the left, sibling is zero (empty)
-                current_hash = hash_pair(current_hash, ZEROS[level]);
+                current_hash = hash_pair(current_hash, zero_hash(level));
             } else {
                 // We're on the right, sibling is filled_subtrees
                 current_hash = hash_pair(self.filled_subtrees[level], current_hash);
@@ -184,12 +334,13 @@ impl MerkleTree {
             return None;
         }
 
-        let mut siblings = Vec::with_capacity(TREE_HEIGHT);
+        let mut siblings = Vec::with_capacity(self.height);
         let mut level_nodes = self.leaves.clone();
         let mut index = leaf_index;
 
         // Pad to next power of 2 with zeros for each level
-        for level in 0..TREE_HEIGHT {
+        for level in 0..self.height {
+            let zero = zero_hash(level);
             // Get sibling
             let sibling_index = if index % 2 == 0 {
                 index + 1
@@ -200,7 +351,7 @@ impl MerkleTree {
             let sibling = if sibling_index < level_nodes.len() {
                 level_nodes[sibling_index]
             } else {
-                ZEROS[level]
+                zero
             };
 
             siblings.push(sibling);
@@ -209,8 +360,8 @@ impl MerkleTree {
             let mut next_level = Vec::new();
             let mut i = 0;
             while i < level_nodes.len() {
-                let left = if i < level_nodes.len() { level_nodes[i] } else { ZEROS[level] };
-                let right = if i + 1 < level_nodes.len() { level_nodes[i + 1] } else { ZEROS[level] };
+                let left = if i < level_nodes.len() { level_nodes[i] } else { zero };
+                let right = if i + 1 < level_nodes.len() { level_nodes[i + 1] } else { zero };
                 next_level.push(hash_pair(left, right));
                 i += 2;
             }
@@ -223,13 +374,13 @@ impl MerkleTree {
             }
         }
 
-        // Ensure we have exactly TREE_HEIGHT siblings
-        while siblings.len() < TREE_HEIGHT {
-            siblings.push(ZEROS[siblings.len()]);
+        // Ensure we have exactly one sibling per level
+        while siblings.len() < self.height {
+            siblings.push(zero_hash(siblings.len()));
         }
 
         Some(MerkleProof {
-            leaf_index: leaf_index as u64,
+            leaf_index: leaf_index as LeafIndex,
             siblings,
         })
     }
@@ -245,16 +396,23 @@ impl MerkleTree {
     /// - `expected_root`: The root to verify against (from contract)
     ///
     /// # Returns
-    /// `true` if the proof is valid, `false` otherwise
+    /// `true` if the proof is valid, `false` otherwise. A proof whose
+    /// `leaf_index` doesn't fit in its height is invalid (its high bits
+    /// would otherwise be ignored, so two indices would share one path).
     pub fn verify_proof(
         leaf: [u8; 32],
         proof: &MerkleProof,
         expected_root: [u8; 32],
     ) -> bool {
+        let height = proof.height();
+        if height == 0 || height > MAX_TREE_HEIGHT || proof.leaf_index >= capacity(height) {
+            return false;
+        }
+
         let mut current = leaf;
         let mut index = proof.leaf_index;
 
-        for (level, sibling) in proof.siblings.iter().enumerate() {
+        for sibling in &proof.siblings {
             current = if index % 2 == 0 {
                 // We're on the left
                 hash_pair(current, *sibling)
@@ -263,17 +421,179 @@ impl MerkleTree {
                 hash_pair(*sibling, current)
             };
             index /= 2;
+        }
 
-            // Early exit if we've processed all meaningful levels
-            if level >= TREE_HEIGHT - 1 {
-                break;
+        current == expected_root
+    }
+
+    /// `verify_proof`, additionally requiring the proof to be for a tree of
+    /// exactly `height` levels.
+    pub fn verify_proof_at_height(
+        leaf: [u8; 32],
+        proof: &MerkleProof,
+        expected_root: [u8; 32],
+        height: usize,
+    ) -> bool {
+        proof.height() == height && Self::verify_proof(leaf, proof, expected_root)
+    }
+
+    /// Insert `commitments` exactly as `MerkleTree.insert` in MerkleTree.sol
+    /// does, one `_insertCommitment` per commitment in the order the ledger
+    /// calls it (a deposit's commitment, then each output in `PublicOutputs`
+    /// order; zero commitments aren't skipped).
+    ///
+    /// The replica follows the contract's loop and stored-root update line by
+    /// line, with its zero table, rather than reusing `push_leaf` and `root`.
+    /// Each insertion is returned with the roots the contract emits in
+    /// `RootUpdated`, so indexer or wallet state can be checked against the
+    /// chain event by event. A zero commitment is indistinguishable from an
+    /// empty leaf: it takes an index but leaves the root unchanged.
+    pub fn insert_like_contract(&mut self, commitments: &[[u8; 32]]) -> Result<Vec<ContractInsertion>, String> {
+        if self.height != TREE_HEIGHT {
+            return Err(format!(
+                "The ledger contract's tree has height {}, this tree has {}",
+                TREE_HEIGHT, self.height
+            ));
+        }
+        let mut root = self.root();
+        let mut insertions = Vec::with_capacity(commitments.len());
+        for &leaf in commitments {
+            // require(index < 2**TREE_HEIGHT, "Tree is full")
+            let index = self.next_index;
+            if index >= capacity(TREE_HEIGHT) {
+                return Err("Tree is full".to_string());
             }
+
+            let mut current_hash = leaf;
+            let mut current_index = index;
+            for (level, zero) in ZEROS.iter().enumerate() {
+                if current_index.is_multiple_of(2) {
+                    self.filled_subtrees[level] = current_hash;
+                    current_hash = hash_pair(current_hash, *zero);
+                } else {
+                    current_hash = hash_pair(self.filled_subtrees[level], current_hash);
+                }
+                current_index /= 2;
+            }
+            self.leaves.push(leaf);
+            self.next_index = index + 1;
+
+            insertions.push(ContractInsertion { leaf_index: index, old_root: root, new_root: current_hash });
+            root = current_hash;
         }
+        Ok(insertions)
+    }
 
-        current == expected_root
+    /// The root the ledger's tree will have after `insert_like_contract` of
+    /// `commitments`, leaving this tree as it is: what the last `RootUpdated`
+    /// of a transaction inserting them should show if nothing else is
+    /// inserted first. Only the frontier is copied, not the leaves.
+    pub fn predict_contract_root(&self, commitments: &[[u8; 32]]) -> Result<[u8; 32], String> {
+        let mut frontier = Self {
+            height: self.height,
+            leaves: Vec::new(),
+            filled_subtrees: self.filled_subtrees.clone(),
+            next_index: self.next_index,
+        };
+        let insertions = frontier.insert_like_contract(commitments)?;
+        Ok(insertions.last().map_or_else(|| self.root(), |insertion| insertion.new_root))
     }
 }
 
+/// Magic prefix of `MerkleTree::to_bytes` snapshots.
+pub const TREE_SNAPSHOT_MAGIC: [u8; 4] = *b"GCMT";
+
+/// Layout version of `MerkleTree::to_bytes` snapshots.
+pub const TREE_SNAPSHOT_VERSION: u8 = 1;
+
+const SNAPSHOT_HEADER_LEN: usize = 4 + 1 + 1 + 8;
+const SNAPSHOT_CHECKSUM_LEN: usize = 32;
+
+impl MerkleTree {
+    /// Compact binary snapshot of the tree.
+    ///
+    /// Layout: magic `GCMT`, version byte, height byte, leaf count (u64
+    /// LE), the leaves concatenated, the frontier (`height` filled-subtree
+    /// hashes), then a blake3 checksum of everything before it. 32 bytes
+    /// per leaf, against over 100 for the serde derive as JSON (a number
+    /// per byte), and loading doesn't rehash the tree.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            SNAPSHOT_HEADER_LEN + 32 * (self.leaves.len() + self.height) + SNAPSHOT_CHECKSUM_LEN,
+        );
+        out.extend_from_slice(&TREE_SNAPSHOT_MAGIC);
+        out.push(TREE_SNAPSHOT_VERSION);
+        // MAX_TREE_HEIGHT fits in a byte
+        out.push(self.height as u8);
+        out.extend_from_slice(&(self.leaves.len() as u64).to_le_bytes());
+        for leaf in self.leaves.iter().chain(&self.filled_subtrees) {
+            out.extend_from_slice(leaf);
+        }
+        let checksum = blake3::hash(&out);
+        out.extend_from_slice(checksum.as_bytes());
+        out
+    }
+
+    /// Load a snapshot written by `to_bytes`.
+    ///
+    /// The checksum covers the whole snapshot, so a truncated or corrupted
+    /// file is rejected rather than loaded with a wrong root.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < SNAPSHOT_HEADER_LEN + SNAPSHOT_CHECKSUM_LEN {
+            return Err(format!("Tree snapshot too short: {} bytes", bytes.len()));
+        }
+        let (body, checksum) = bytes.split_at(bytes.len() - SNAPSHOT_CHECKSUM_LEN);
+        if body[..4] != TREE_SNAPSHOT_MAGIC {
+            return Err("Not a tree snapshot (bad magic)".to_string());
+        }
+        if body[4] != TREE_SNAPSHOT_VERSION {
+            return Err(format!("Unsupported tree snapshot version {}", body[4]));
+        }
+        if blake3::hash(body).as_bytes() != checksum {
+            return Err("Tree snapshot checksum mismatch".to_string());
+        }
+        let height = body[5] as usize;
+        if height == 0 || height > MAX_TREE_HEIGHT {
+            return Err(format!("Tree height must be 1..={}, got {}", MAX_TREE_HEIGHT, height));
+        }
+        let leaf_count = u64::from_le_bytes(body[6..SNAPSHOT_HEADER_LEN].try_into().unwrap());
+        let hashes = &body[SNAPSHOT_HEADER_LEN..];
+        let expected = usize::try_from(leaf_count)
+            .ok()
+            .and_then(|n| n.checked_add(height))
+            .and_then(|n| n.checked_mul(32));
+        if expected != Some(hashes.len()) {
+            return Err(format!(
+                "Tree snapshot has {} bytes of hashes for {} leaves at height {}",
+                hashes.len(),
+                leaf_count,
+                height
+            ));
+        }
+        if LeafIndex::from(leaf_count) > capacity(height) {
+            return Err(format!("{} leaves overflow a tree of height {}", leaf_count, height));
+        }
+
+        let mut chunks = hashes.chunks_exact(32).map(|c| <[u8; 32]>::try_from(c).unwrap());
+        let leaves: Vec<[u8; 32]> = chunks.by_ref().take(leaf_count as usize).collect();
+        let filled_subtrees: Vec<[u8; 32]> = chunks.collect();
+        Ok(Self { height, leaves, filled_subtrees, next_index: LeafIndex::from(leaf_count) })
+    }
+}
+
+/// Empty-leaf value of the contract's tree (`zeros(0)` in MerkleTree.sol).
+/// It equals `ZEROS[0]`, so `ZEROS` is the contract's zero table too; the
+/// tests fail if either side changes its convention.
+pub const CONTRACT_ZERO_LEAF: [u8; 32] = [0u8; 32];
+
+/// One commitment inserted by the contract, with the `RootUpdated` roots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractInsertion {
+    pub leaf_index: LeafIndex,
+    pub old_root: [u8; 32],
+    pub new_root: [u8; 32],
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,7 +621,7 @@ mod tests {
     fn test_single_leaf() {
         let mut tree = MerkleTree::new();
         let leaf = [1u8; 32];
-        let index = tree.push_leaf(leaf);
+        let index = tree.push_leaf(leaf).index;
 
         assert_eq!(index, 0);
         assert_eq!(tree.leaf_count(), 1);
@@ -430,15 +750,59 @@ mod tests {
     fn test_leaf_index_tracking() {
         let mut tree = MerkleTree::new();
 
-        let index1 = tree.push_leaf([1u8; 32]);
-        let index2 = tree.push_leaf([2u8; 32]);
-        let index3 = tree.push_leaf([3u8; 32]);
+        let index1 = tree.push_leaf([1u8; 32]).index;
+        let index2 = tree.push_leaf([2u8; 32]).index;
+        let index3 = tree.push_leaf([3u8; 32]).index;
 
         assert_eq!(index1, 0);
         assert_eq!(index2, 1);
         assert_eq!(index3, 2);
     }
 
+    #[test]
+    fn test_insertion_matches_root_and_prove() {
+        let mut tree = MerkleTree::with_height(4).unwrap();
+        for i in 0..7u8 {
+            let leaf = [i + 1; 32];
+            let insertion = tree.push_leaf(leaf);
+            assert_eq!(insertion.new_root, tree.root());
+            assert_eq!(insertion.path.siblings, tree.prove(insertion.index as usize).unwrap().siblings);
+            assert!(MerkleTree::verify_proof(leaf, &insertion.path, insertion.new_root));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_bytes_roundtrip() {
+        let mut tree = MerkleTree::with_leaves((0..100u8).map(|i| hash_pair([i; 32], [0; 32])).collect());
+        let bytes = tree.to_bytes();
+        assert_eq!(bytes.len(), 4 + 1 + 1 + 8 + 32 * (100 + TREE_HEIGHT) + 32);
+        assert!(bytes.len() * 3 < serde_json::to_vec(&tree).unwrap().len());
+
+        let mut loaded = MerkleTree::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        assert_eq!(loaded.leaves(), tree.leaves());
+        assert_eq!(loaded.prove(42).unwrap().siblings, tree.prove(42).unwrap().siblings);
+        // The frontier carries over, so appends agree too
+        assert_eq!(loaded.push_leaf([0xaa; 32]).new_root, tree.push_leaf([0xaa; 32]).new_root);
+
+        let empty = MerkleTree::with_height(5).unwrap();
+        let loaded = MerkleTree::from_bytes(&empty.to_bytes()).unwrap();
+        assert_eq!((loaded.height(), loaded.root()), (5, empty.root()));
+    }
+
+    #[test]
+    fn test_snapshot_bytes_rejects_corruption() {
+        let bytes = MerkleTree::with_leaves(vec![[1; 32], [2; 32]]).to_bytes();
+        let mut flipped = bytes.clone();
+        flipped[20] ^= 1;
+        assert!(MerkleTree::from_bytes(&flipped).unwrap_err().contains("checksum"));
+        assert!(MerkleTree::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(MerkleTree::from_bytes(b"GCMT").unwrap_err().contains("too short"));
+        let mut wrong_magic = bytes;
+        wrong_magic[0] = b'X';
+        assert!(MerkleTree::from_bytes(&wrong_magic).unwrap_err().contains("magic"));
+    }
+
     /// Test that Keccak256 hash matches Solidity's keccak256(abi.encodePacked(...))
     /// This verifies cross-platform compatibility
     #[test]
@@ -560,6 +924,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_taller_tree_roots_and_proofs() {
+        assert!(MerkleTree::with_height(0).is_err());
+        assert!(MerkleTree::with_height(MAX_TREE_HEIGHT + 1).is_err());
+
+        let mut tree = MerkleTree::with_height(40).unwrap();
+        assert_eq!(tree.root(), zero_hash(39));
+        let leaves = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        for leaf in leaves {
+            tree.push_leaf(leaf);
+        }
+
+        // Same bottom 32 levels as the default tree, then 8 more zero levels
+        let mut expected = MerkleTree::with_leaves(leaves.to_vec()).root();
+        for level in TREE_HEIGHT..40 {
+            expected = hash_pair(expected, zero_hash(level));
+        }
+        assert_eq!(tree.root(), expected);
+
+        let proof = tree.prove(2).unwrap();
+        assert_eq!(proof.height(), 40);
+        assert!(MerkleTree::verify_proof(leaves[2], &proof, tree.root()));
+        assert!(MerkleTree::verify_proof_at_height(leaves[2], &proof, tree.root(), 40));
+        assert!(!MerkleTree::verify_proof_at_height(leaves[2], &proof, tree.root(), TREE_HEIGHT));
+    }
+
+    #[test]
+    fn test_full_tree_rejects_more_leaves() {
+        let mut tree = MerkleTree::with_height(2).unwrap();
+        for i in 0..4u8 {
+            assert_eq!(tree.try_push_leaf([i; 32]).map(|insertion| insertion.index), Ok(i as LeafIndex));
+        }
+        assert!(tree.try_push_leaf([4; 32]).unwrap_err().contains("full"));
+    }
+
+    #[test]
+    fn test_zero_hashes_extend_table() {
+        let zeros = zero_hashes(45);
+        assert_eq!(&zeros[..TREE_HEIGHT], &ZEROS[..]);
+        for level in 1..45 {
+            assert_eq!(zeros[level], hash_pair(zeros[level - 1], zeros[level - 1]));
+            assert_eq!(zero_hash(level), zeros[level]);
+        }
+    }
+
+    /// Root of a tree of `siblings.len()` levels with `leaf` at `index`.
+    fn root_from_path(leaf: [u8; 32], index: LeafIndex, siblings: &[[u8; 32]]) -> [u8; 32] {
+        siblings.iter().enumerate().fold(leaf, |node, (level, sibling)| {
+            if (index >> level) & 1 == 0 {
+                hash_pair(node, *sibling)
+            } else {
+                hash_pair(*sibling, node)
+            }
+        })
+    }
+
+    proptest::proptest! {
+        /// Indices straddling 2^32 take distinct paths in a 40-level tree
+        #[test]
+        fn prop_indices_around_2_pow_32(offset in -4i64..4, seed in proptest::prelude::any::<[u8; 32]>()) {
+            let index = ((1i128 << 32) + offset as i128) as LeafIndex;
+            let siblings: Vec<[u8; 32]> = (0..40u8).map(|level| hash_pair(seed, [level; 32])).collect();
+            let root = root_from_path(seed, index, &siblings);
+            let proof = MerkleProof::new(index, siblings.clone());
+            proptest::prop_assert!(MerkleTree::verify_proof(seed, &proof, root));
+
+            // Flipping bit 32 (which a u32 index would drop) changes the path
+            let aliased = MerkleProof::new(index ^ (1 << 32), siblings);
+            proptest::prop_assert!(!MerkleTree::verify_proof(seed, &aliased, root));
+        }
+
+        /// A 32-level proof can't carry an index with bits above bit 31
+        #[test]
+        fn prop_height_32_rejects_wide_indices(low in proptest::prelude::any::<u32>(), high in 1u64..u64::MAX) {
+            let siblings = ZEROS.to_vec();
+            let root = root_from_path([7; 32], low as LeafIndex, &siblings);
+            let valid = MerkleProof::new(low as LeafIndex, siblings.clone());
+            proptest::prop_assert!(MerkleTree::verify_proof([7; 32], &valid, root));
+
+            let wide = MerkleProof::new(((high as LeafIndex) << 32) | low as LeafIndex, siblings);
+            proptest::prop_assert!(!MerkleTree::verify_proof([7; 32], &wide, root));
+        }
+
+        /// Serialized proofs keep indices above u64::MAX intact
+        #[test]
+        fn prop_proof_index_round_trips(index in proptest::prelude::any::<u128>()) {
+            let proof = MerkleProof::new(index, vec![[1; 32]; 2]);
+            let json = serde_json::to_string(&proof).unwrap();
+            let parsed: MerkleProof = serde_json::from_str(&json).unwrap();
+            proptest::prop_assert_eq!(parsed.leaf_index, index);
+        }
+    }
+
     /// Test empty tree proof generation fails gracefully
     #[test]
     fn test_empty_tree_proof_fails() {
@@ -569,4 +1026,97 @@ mod tests {
         assert!(tree.prove(0).is_none(), "Should return None for empty tree");
         assert!(tree.prove(100).is_none(), "Should return None for any index in empty tree");
     }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ContractTreeFixture {
+        empty_root: crate::hex::Bytes32,
+        insertions: Vec<FixtureInsertion>,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FixtureInsertion {
+        commitment: crate::hex::Bytes32,
+        leaf_index: u64,
+        root_after: crate::hex::Bytes32,
+    }
+
+    /// Replays test-vectors/contract_tree_roots.json, the fixture
+    /// PrivateUTXOLedger_ContractTree.t.sol pushes through the ledger
+    #[test]
+    fn test_insert_like_contract_matches_contract_roots() {
+        let fixture: ContractTreeFixture =
+            serde_json::from_str(include_str!("../test-vectors/contract_tree_roots.json")).unwrap();
+        let commitments: Vec<[u8; 32]> = fixture.insertions.iter().map(|i| i.commitment.0).collect();
+
+        let mut tree = MerkleTree::new();
+        assert_eq!(tree.root(), fixture.empty_root.0);
+        let insertions = tree.insert_like_contract(&commitments).unwrap();
+
+        let mut old_root = fixture.empty_root.0;
+        for (got, expected) in insertions.iter().zip(&fixture.insertions) {
+            assert_eq!(got.leaf_index, LeafIndex::from(expected.leaf_index));
+            assert_eq!(got.old_root, old_root, "old root of leaf {}", expected.leaf_index);
+            assert_eq!(got.new_root, expected.root_after.0, "root after leaf {}", expected.leaf_index);
+            old_root = got.new_root;
+        }
+        assert_eq!(tree.root(), old_root);
+        assert_eq!(tree.leaves(), &commitments[..]);
+    }
+
+    #[test]
+    fn test_insert_like_contract_matches_push_leaf() {
+        let mut replica = MerkleTree::new();
+        let mut pushed = MerkleTree::new();
+        for batch in [vec![[1u8; 32]], vec![[2; 32], CONTRACT_ZERO_LEAF, [3; 32]], vec![], vec![[4; 32]; 5]] {
+            let insertions = replica.insert_like_contract(&batch).unwrap();
+            assert_eq!(insertions.len(), batch.len());
+            for (leaf, insertion) in batch.iter().zip(&insertions) {
+                assert_eq!(pushed.root(), insertion.old_root);
+                assert_eq!(pushed.push_leaf(*leaf).index, insertion.leaf_index);
+                assert_eq!(pushed.root(), insertion.new_root);
+            }
+            assert_eq!(replica.root(), pushed.root());
+        }
+        // The incremental state stays usable for proofs
+        let proof = replica.prove(3).unwrap();
+        assert!(MerkleTree::verify_proof([3; 32], &proof, replica.root()));
+    }
+
+    #[test]
+    fn test_predict_contract_root() {
+        let mut tree = MerkleTree::with_leaves(vec![[1; 32], [2; 32], [3; 32]]);
+        let before = tree.root();
+        let outputs = [[4; 32], [5; 32]];
+        let predicted = tree.predict_contract_root(&outputs).unwrap();
+        assert_eq!(tree.root(), before);
+        assert_eq!(tree.leaf_count(), 3);
+        assert_eq!(tree.predict_contract_root(&[]).unwrap(), before);
+
+        let insertions = tree.insert_like_contract(&outputs).unwrap();
+        assert_eq!(insertions[1].new_root, predicted);
+        assert_ne!(predicted, before);
+    }
+
+    #[test]
+    fn test_contract_zero_convention() {
+        assert_eq!(CONTRACT_ZERO_LEAF, ZEROS[0]);
+        let mut zero = CONTRACT_ZERO_LEAF;
+        for (level, expected) in ZEROS.iter().enumerate() {
+            assert_eq!(zero, *expected, "zeros({})", level);
+            zero = hash_pair(zero, zero);
+        }
+
+        // A zero commitment doesn't move the root
+        let mut tree = MerkleTree::new();
+        tree.insert_like_contract(&[[9; 32]]).unwrap();
+        let root = tree.root();
+        let insertion = tree.insert_like_contract(&[CONTRACT_ZERO_LEAF]).unwrap()[0];
+        assert_eq!((insertion.leaf_index, insertion.new_root), (1, root));
+
+        // Only the contract's height is replicated
+        let mut short = MerkleTree::with_height(20).unwrap();
+        assert!(short.insert_like_contract(&[[1; 32]]).is_err());
+    }
 }
@@ -0,0 +1,334 @@
+//! Tree migration to a new hash scheme
+//!
+//! A ledger whose tree hashes change (another node hash, a new leaf domain,
+//! eventually an arithmetization-friendly hash) can't rebuild its tree from
+//! the notes: only their owners know the openings. A migration wraps each
+//! existing leaf instead. Leaf `i` of the new tree is
+//! `scheme.leaf(old_leaf_i)`, a public function of the old commitment, and
+//! the new tree's nodes are hashed with `scheme.node`. Its root is the new
+//! ledger's genesis root, and a note stays spendable by proving membership
+//! of its wrapped commitment.
+//!
+//! Leaves are never removed, so spent notes are migrated too: their wrapped
+//! commitments are still members of the new tree, and only the spent set
+//! stops them being spent again. A migration therefore carries the source
+//! ledger's spent nullifiers (replayed from its `NullifierUsed` events), and
+//! the new deployment must start with every one of them marked spent:
+//! `nullifierUsed` seeded from the set, or in SMT mode the bundle's
+//! `nullifier_root` as its genesis nullifier root (see `nullifier_tree`).
+//! A deployment that accepts proofs before it is seeded lets every note
+//! spent on the old ledger be spent again.
+//!
+//! `migrate` produces the leaf mapping and a `MigrationBundle`: the source
+//! tree (deployment, block, root, leaf count), the scheme, the genesis root,
+//! a digest of the mapping and the spent set's count, hash and SMT root.
+//! The bundle is the evidence for the genesis state: `verify_migration`
+//! recomputes both roots from the mapping and the spent set's digests from
+//! its snapshot, so anyone holding the old leaves and nullifiers can check a
+//! migration before deploying or trusting it.
+//!
+//! `HashScheme::Keccak` is the current tree (identity leaves, Keccak
+//! nodes); migrating to it reproduces the source root.
+
+use serde::{Deserialize, Serialize};
+
+use crate::chains::ChainDeployment;
+use crate::hex::Bytes32;
+use crate::merkle::{hash_pair, MerkleTree, TREE_HEIGHT};
+use crate::nullifier_set::{NullifierSet, NullifierSnapshot};
+use crate::nullifier_tree::NullifierTree;
+
+/// Layout version of `MigrationBundle`; 2 added the spent set.
+pub const MIGRATION_BUNDLE_VERSION: u8 = 2;
+
+const MAPPING_DIGEST_DOMAIN: &str = "ghostclaw-tree-migration-v1";
+
+/// How a tree hashes its leaves and nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "hash", rename_all = "camelCase")]
+pub enum HashScheme {
+    /// The current tree: leaves are the commitments, nodes Keccak256
+    Keccak,
+    /// Blake3 under `domain`, for leaves and nodes alike
+    Blake3 { domain: String },
+}
+
+impl HashScheme {
+    /// Parse a `--scheme` name; `blake3` requires a domain.
+    pub fn parse(name: &str, domain: Option<&str>) -> Result<Self, String> {
+        match (name, domain) {
+            ("keccak", None) => Ok(HashScheme::Keccak),
+            ("keccak", Some(_)) => Err("The keccak scheme takes no domain".to_string()),
+            ("blake3", Some(domain)) if !domain.is_empty() => Ok(HashScheme::Blake3 { domain: domain.to_string() }),
+            ("blake3", _) => Err("The blake3 scheme needs a domain".to_string()),
+            _ => Err(format!("Unknown hash scheme {} (expected keccak or blake3)", name)),
+        }
+    }
+
+    fn blake3(domain: &str, kind: &[u8], parts: &[&[u8; 32]]) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new_derive_key(domain);
+        hasher.update(kind);
+        for part in parts {
+            hasher.update(*part);
+        }
+        hasher.finalize().into()
+    }
+
+    /// The new tree's leaf for an old commitment.
+    pub fn leaf(&self, commitment: &[u8; 32]) -> [u8; 32] {
+        match self {
+            HashScheme::Keccak => *commitment,
+            HashScheme::Blake3 { domain } => Self::blake3(domain, b"leaf", &[commitment]),
+        }
+    }
+
+    pub fn node(&self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        match self {
+            HashScheme::Keccak => hash_pair(*left, *right),
+            HashScheme::Blake3 { domain } => Self::blake3(domain, b"node", &[left, right]),
+        }
+    }
+
+    /// Root of a tree of `height` holding `leaves`; empty leaves are zero,
+    /// and the empty tree's root is the zero hash one level down, as in the
+    /// contract (and `MerkleTree::root`).
+    pub fn root(&self, leaves: &[[u8; 32]], height: usize) -> [u8; 32] {
+        let mut level = leaves.to_vec();
+        let mut zero = [0u8; 32];
+        if level.is_empty() {
+            for _ in 1..height {
+                zero = self.node(&zero, &zero);
+            }
+            return zero;
+        }
+        for _ in 0..height {
+            if level.len() % 2 == 1 {
+                level.push(zero);
+            }
+            level = level.chunks_exact(2).map(|pair| self.node(&pair[0], &pair[1])).collect();
+            zero = self.node(&zero, &zero);
+        }
+        level[0]
+    }
+}
+
+/// One leaf before and after migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeafMapping {
+    pub index: u64,
+    pub old: Bytes32,
+    pub new: Bytes32,
+}
+
+/// The tree a migration starts from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceTree {
+    pub deployment: ChainDeployment,
+    /// Block the leaves were read at
+    pub block_number: u64,
+    pub root: Bytes32,
+    pub leaf_count: u64,
+}
+
+/// What a new deployment's genesis root is derived from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationBundle {
+    pub version: u8,
+    pub scheme: HashScheme,
+    pub height: usize,
+    pub source: SourceTree,
+    pub genesis_root: Bytes32,
+    /// `mapping_digest` of the leaf mapping
+    pub mapping_digest: Bytes32,
+    /// Nullifiers spent on the source ledger, which the new one must be
+    /// seeded with
+    pub spent: SpentNullifiers,
+}
+
+/// Digests of the spent set a migration carries over.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpentNullifiers {
+    pub count: usize,
+    /// `NullifierSet::hash`
+    pub hash: Bytes32,
+    /// Root of a nullifier tree holding them, the genesis nullifier root in
+    /// SMT mode
+    pub nullifier_root: Bytes32,
+}
+
+impl SpentNullifiers {
+    fn of(spent: &NullifierSet) -> Self {
+        Self {
+            count: spent.len(),
+            hash: Bytes32(spent.hash()),
+            nullifier_root: Bytes32(NullifierTree::from_nullifiers(spent.iter().copied()).root()),
+        }
+    }
+}
+
+/// Digest binding a bundle to its mapping (blake3 over every entry).
+pub fn mapping_digest(mapping: &[LeafMapping]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new_derive_key(MAPPING_DIGEST_DOMAIN);
+    for entry in mapping {
+        hasher.update(&entry.index.to_le_bytes());
+        hasher.update(&entry.old.0);
+        hasher.update(&entry.new.0);
+    }
+    hasher.finalize().into()
+}
+
+/// Migrate `leaves` and the `spent` nullifiers, read from `deployment` at
+/// `block_number`, to `scheme`.
+///
+/// # Errors
+/// Fails if the leaves don't hash to `root` (an incomplete or reordered
+/// read of the source tree).
+pub fn migrate(
+    deployment: ChainDeployment,
+    block_number: u64,
+    root: [u8; 32],
+    leaves: &[[u8; 32]],
+    spent: &NullifierSet,
+    scheme: HashScheme,
+) -> Result<(Vec<LeafMapping>, MigrationBundle), String> {
+    let source_root = MerkleTree::with_leaves(leaves.to_vec()).root();
+    if source_root != root {
+        return Err(format!(
+            "{} leaves hash to {}, not the source root {}",
+            leaves.len(),
+            Bytes32(source_root),
+            Bytes32(root)
+        ));
+    }
+
+    let mapping: Vec<LeafMapping> = leaves
+        .iter()
+        .enumerate()
+        .map(|(i, old)| LeafMapping { index: i as u64, old: Bytes32(*old), new: Bytes32(scheme.leaf(old)) })
+        .collect();
+    let new_leaves: Vec<[u8; 32]> = mapping.iter().map(|entry| entry.new.0).collect();
+    let bundle = MigrationBundle {
+        version: MIGRATION_BUNDLE_VERSION,
+        genesis_root: Bytes32(scheme.root(&new_leaves, TREE_HEIGHT)),
+        scheme,
+        height: TREE_HEIGHT,
+        source: SourceTree { deployment, block_number, root: Bytes32(root), leaf_count: leaves.len() as u64 },
+        mapping_digest: Bytes32(mapping_digest(&mapping)),
+        spent: SpentNullifiers::of(spent),
+    };
+    Ok((mapping, bundle))
+}
+
+/// Check that `mapping` is a complete migration of the bundle's source tree
+/// and yields its genesis root, and that `spent` is the spent set it
+/// carries over.
+pub fn verify_migration(bundle: &MigrationBundle, mapping: &[LeafMapping], spent: &NullifierSnapshot) -> Result<(), String> {
+    if bundle.version != MIGRATION_BUNDLE_VERSION {
+        return Err(format!("Unsupported migration bundle version {}", bundle.version));
+    }
+    if bundle.height != TREE_HEIGHT {
+        return Err(format!("Migration to height {} isn't supported (the ledger's is {})", bundle.height, TREE_HEIGHT));
+    }
+    if mapping.len() as u64 != bundle.source.leaf_count {
+        return Err(format!("Mapping has {} leaves, the source tree {}", mapping.len(), bundle.source.leaf_count));
+    }
+    if mapping_digest(mapping) != bundle.mapping_digest.0 {
+        return Err("Mapping digest doesn't match the bundle".to_string());
+    }
+    for (i, entry) in mapping.iter().enumerate() {
+        if entry.index != i as u64 {
+            return Err(format!("Mapping entry {} is for leaf {}", i, entry.index));
+        }
+        if bundle.scheme.leaf(&entry.old.0) != entry.new.0 {
+            return Err(format!("Leaf {} isn't migrated under the bundle's scheme", i));
+        }
+    }
+
+    let old: Vec<[u8; 32]> = mapping.iter().map(|entry| entry.old.0).collect();
+    let old_root = MerkleTree::with_leaves(old).root();
+    if old_root != bundle.source.root.0 {
+        return Err(format!("Old leaves hash to {}, not the source root {}", Bytes32(old_root), bundle.source.root));
+    }
+    let new: Vec<[u8; 32]> = mapping.iter().map(|entry| entry.new.0).collect();
+    let genesis_root = bundle.scheme.root(&new, bundle.height);
+    if genesis_root != bundle.genesis_root.0 {
+        return Err(format!("New leaves hash to {}, not the genesis root {}", Bytes32(genesis_root), bundle.genesis_root));
+    }
+
+    let spent = NullifierSet::from_snapshot(spent)?;
+    if SpentNullifiers::of(&spent) != bundle.spent {
+        return Err(format!(
+            "Spent set ({} nullifiers, hash {}) doesn't match the bundle's ({}, hash {})",
+            spent.len(),
+            Bytes32(spent.hash()),
+            bundle.spent.count,
+            bundle.spent.hash
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment() -> ChainDeployment {
+        ChainDeployment { chain_id: 31337, ledger: "0x00".to_string() }
+    }
+
+    fn leaves() -> Vec<[u8; 32]> {
+        (1..=5u8).map(|i| [i; 32]).collect()
+    }
+
+    fn spent() -> NullifierSet {
+        [[0xa1; 32], [0xb2; 32]].into_iter().collect()
+    }
+
+    #[test]
+    fn test_keccak_scheme_is_the_current_tree() {
+        let leaves = leaves();
+        let root = MerkleTree::with_leaves(leaves.clone()).root();
+        let (_, bundle) = migrate(deployment(), 9, root, &leaves, &spent(), HashScheme::Keccak).unwrap();
+        assert_eq!(bundle.genesis_root.0, root);
+        assert_eq!(HashScheme::Keccak.root(&[], TREE_HEIGHT), MerkleTree::new().root());
+    }
+
+    #[test]
+    fn test_migration_verifies_and_detects_tampering() {
+        let leaves = leaves();
+        let root = MerkleTree::with_leaves(leaves.clone()).root();
+        let scheme = HashScheme::parse("blake3", Some("ghostclaw-v2")).unwrap();
+        assert!(migrate(deployment(), 9, [0; 32], &leaves, &spent(), scheme.clone()).is_err());
+
+        let (mapping, bundle) = migrate(deployment(), 9, root, &leaves, &spent(), scheme).unwrap();
+        let snapshot = spent().snapshot();
+        assert_ne!(bundle.genesis_root.0, root);
+        verify_migration(&bundle, &mapping, &snapshot).unwrap();
+
+        let mut dropped = mapping.clone();
+        dropped.pop();
+        assert!(verify_migration(&bundle, &dropped, &snapshot).unwrap_err().contains("4 leaves"));
+        let mut forged = mapping.clone();
+        forged[2].new = Bytes32([9; 32]);
+        assert!(verify_migration(&bundle, &forged, &snapshot).is_err());
+        let mut rerooted = bundle.clone();
+        rerooted.genesis_root = Bytes32(root);
+        assert!(verify_migration(&rerooted, &mapping, &snapshot).unwrap_err().contains("genesis root"));
+
+        // The spent set must be the bundle's exactly: one left out would be
+        // spendable again
+        let mut padded = spent();
+        padded.insert([0xc3; 32]).unwrap();
+        let unseeded = NullifierSet::new().snapshot();
+        assert!(verify_migration(&bundle, &mapping, &padded.snapshot()).unwrap_err().contains("Spent set"));
+        assert!(verify_migration(&bundle, &mapping, &unseeded).unwrap_err().contains("Spent set"));
+        assert_eq!(bundle.spent.nullifier_root.0, NullifierTree::from_nullifiers(spent().sorted()).root());
+
+        assert!(HashScheme::parse("blake3", None).is_err());
+        assert!(HashScheme::parse("poseidon", None).is_err());
+    }
+}
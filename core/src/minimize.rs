@@ -0,0 +1,177 @@
+//! Witness minimization for failure reproduction
+//!
+//! A rejected witness from a real wallet usually carries several inputs and
+//! outputs that have nothing to do with the failure. `minimize_witness`
+//! greedily drops inputs and outputs (keeping every per-input vector aligned
+//! and the structure valid) while the failure still reproduces, leaving the
+//! smallest witness worth attaching to a bug report against the circuit.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hex::Bytes32;
+use crate::ledger::{simulate_witness, Ledger};
+use crate::sp1_types::Witness;
+
+/// A minimized failing witness, shareable as a JSON fixture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureFixture {
+    /// The failure the witness reproduces
+    pub failure: String,
+    pub old_root: Bytes32,
+    /// Input/output counts before minimization
+    pub original_inputs: usize,
+    pub original_outputs: usize,
+    pub witness: Witness,
+}
+
+/// `reason` with every run of digits replaced by `#`, so the same failure
+/// matches after inputs are renumbered ("input 3" vs "input 0").
+pub fn failure_class(reason: &str) -> String {
+    let mut class = String::with_capacity(reason.len());
+    let mut in_digits = false;
+    for c in reason.chars() {
+        if c.is_ascii_digit() {
+            if !in_digits {
+                class.push('#');
+            }
+            in_digits = true;
+        } else {
+            class.push(c);
+            in_digits = false;
+        }
+    }
+    class
+}
+
+/// `witness` without input `i` (and its index, proof, signatures and
+/// precomputed values).
+pub fn without_input(witness: &Witness, i: usize) -> Witness {
+    fn drop_at<T: Clone>(items: &[T], i: usize) -> Vec<T> {
+        items.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, item)| item.clone()).collect()
+    }
+    let mut w = witness.clone();
+    w.input_notes = drop_at(&witness.input_notes, i);
+    w.input_indices = drop_at(&witness.input_indices, i);
+    w.input_proofs = drop_at(&witness.input_proofs, i);
+    w.nullifier_signatures = drop_at(&witness.nullifier_signatures, i);
+    w.tx_signatures = drop_at(&witness.tx_signatures, i);
+    w.precomputed_nullifiers = drop_at(&witness.precomputed_nullifiers, i);
+    w.precomputed_input_commitments = drop_at(&witness.precomputed_input_commitments, i);
+    w
+}
+
+/// `witness` without output `i` (and its precomputed commitment).
+pub fn without_output(witness: &Witness, i: usize) -> Witness {
+    let mut w = witness.clone();
+    if i < w.output_notes.len() {
+        w.output_notes.remove(i);
+    }
+    if i < w.precomputed_output_commitments.len() {
+        w.precomputed_output_commitments.remove(i);
+    }
+    w
+}
+
+/// Shrink `witness` to a local minimum: no single input or output can be
+/// removed without `reproduces` returning false.
+///
+/// `reproduces` is only called on structurally valid candidates; the
+/// original witness is assumed to reproduce.
+pub fn minimize_witness<F: FnMut(&Witness) -> bool>(witness: &Witness, mut reproduces: F) -> Witness {
+    let mut current = witness.clone();
+    loop {
+        let candidates = (0..current.input_count())
+            .map(|i| without_input(&current, i))
+            .chain((0..current.output_count()).map(|i| without_output(&current, i)));
+
+        let mut shrunk = None;
+        for candidate in candidates {
+            if candidate.validate_structure().is_ok() && reproduces(&candidate) {
+                shrunk = Some(candidate);
+                break;
+            }
+        }
+        match shrunk {
+            Some(smaller) => current = smaller,
+            None => return current,
+        }
+    }
+}
+
+/// Minimize a witness the simulator rejects at `old_root`, keeping the
+/// first failure reason (up to renumbering). `None` if it doesn't fail.
+pub fn minimize_simulation_failure(witness: &Witness, old_root: [u8; 32]) -> Option<FailureFixture> {
+    let failures = |w: &Witness| simulate_witness(&mut Ledger::new(), w, old_root).failure_reasons();
+    let failure = failures(witness).into_iter().next()?;
+    let class = failure_class(&failure);
+
+    let minimized = minimize_witness(witness, |candidate| {
+        failures(candidate).iter().any(|reason| failure_class(reason) == class)
+    });
+    Some(FailureFixture {
+        failure,
+        old_root: Bytes32(old_root),
+        original_inputs: witness.input_count(),
+        original_outputs: witness.output_count(),
+        witness: minimized,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+    use crate::note::Note;
+    use crate::testing::{populated_tree, TestOwner, WitnessBuilder};
+
+    /// Signed witness spending one note per amount (leaves 0..n, each with
+    /// its own owner) into `outputs`, and the tree holding them.
+    fn signed_witness(amounts: &[u64], outputs: Vec<Note>) -> (Witness, MerkleTree) {
+        let owners: Vec<TestOwner> = amounts.iter().map(|_| TestOwner::random()).collect();
+        let notes: Vec<Note> = owners.iter().zip(amounts).map(|(owner, &amount)| owner.random_note(amount)).collect();
+        let (tree, indices) = populated_tree(0, &notes);
+        let builder = owners.iter().zip(notes).fold(WitnessBuilder::new(), |b, (owner, note)| b.input_note(owner, note));
+        let builder = outputs.into_iter().fold(builder, WitnessBuilder::output_note);
+        let (_, witness) = builder.build_in(&tree, indices).unwrap();
+        (witness, tree)
+    }
+
+    #[test]
+    fn test_failure_class_ignores_numbers() {
+        assert_eq!(
+            failure_class("Merkle proof failed for input 12: not in tree"),
+            failure_class("Merkle proof failed for input 0: not in tree")
+        );
+        assert_ne!(failure_class("Missing Merkle proof for input 1"), failure_class("Merkle proof failed for input 1"));
+    }
+
+    #[test]
+    fn test_minimizes_to_failing_input() {
+        let outputs = vec![Note::new(100, [4; 32], [5; 32]), Note::new(50, [6; 32], [7; 32])];
+        let (mut witness, tree) = signed_witness(&[60, 50, 40], outputs);
+        // Input 1's proof is for the wrong leaf
+        witness.input_proofs[1] = tree.prove(2).unwrap();
+        assert_eq!(simulate_witness(&mut Ledger::new(), &witness, tree.root()).failure_reasons().len(), 1);
+
+        let fixture = minimize_simulation_failure(&witness, tree.root()).unwrap();
+        assert!(fixture.failure.contains("Merkle proof failed for input 1"));
+        assert_eq!((fixture.original_inputs, fixture.original_outputs), (3, 2));
+        assert_eq!(fixture.witness.input_count(), 1);
+        // Dropping an output breaks the tx signature, which masks the proof failure
+        assert_eq!(fixture.witness.output_count(), 2);
+        assert_eq!(fixture.witness.input_notes[0].amount, 50);
+        assert!(fixture.witness.validate_structure().is_ok());
+        assert!(fixture.witness.has_precomputed_values());
+
+        let json = serde_json::to_string(&fixture).unwrap();
+        let parsed: FailureFixture = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.witness.input_notes, fixture.witness.input_notes);
+    }
+
+    #[test]
+    fn test_valid_witness_has_nothing_to_minimize() {
+        let (witness, tree) = signed_witness(&[100], vec![Note::new(100, [4; 32], [5; 32])]);
+        assert!(minimize_simulation_failure(&witness, tree.root()).is_none());
+    }
+}
@@ -2,8 +2,13 @@ use blake3::Hasher;
 use serde::{Deserialize, Serialize};
 
 // Domain separators as constants for better maintainability
-const NOTE_COMMITMENT_DOMAIN: &[u8] = b"NOTE_COMMITMENT_v1";
+/// Commitment domain prefix; the note version follows in decimal, so
+/// version 1 hashes under exactly `NOTE_COMMITMENT_v1`
+const NOTE_COMMITMENT_DOMAIN_PREFIX: &[u8] = b"NOTE_COMMITMENT_v";
 const NULLIFIER_DOMAIN: &[u8] = b"NULLIFIER_v1";
+const NULLIFIER_KEY_DOMAIN: &[u8] = b"NULLIFIER_KEY_v2";
+const KEYED_NULLIFIER_DOMAIN: &[u8] = b"NULLIFIER_v2";
+const SCOPED_NULLIFIER_DOMAIN: &[u8] = b"SCOPED_NULLIFIER_v1";
 
 /// A simple UTXO note in our prototype.
 ///
@@ -20,6 +25,32 @@ pub struct Note {
     pub amount: u64,
     pub owner_pubkey: [u8; 32],
     pub blinding: [u8; 32],
+    /// Note format version, folded into the commitment
+    #[serde(default = "default_note_version")]
+    pub version: NoteVersion,
+}
+
+/// Version of a note's format.
+///
+/// # Format Migration
+/// The version is part of the commitment preimage, so notes of different
+/// formats can share one tree: a future format with new fields gets a new
+/// version, and the circuit accepts every version in
+/// `ACCEPTED_NOTE_VERSIONS` while wallets migrate. Dropping a version from
+/// the set (a new ELF and vkey) retires it.
+pub type NoteVersion = u8;
+
+/// The original format: amount, owner and blinding.
+pub const NOTE_VERSION_V1: NoteVersion = 1;
+
+/// Version of the notes this library creates.
+pub const CURRENT_NOTE_VERSION: NoteVersion = NOTE_VERSION_V1;
+
+/// Note versions the circuit accepts, for inputs and outputs alike.
+pub const ACCEPTED_NOTE_VERSIONS: &[NoteVersion] = &[NOTE_VERSION_V1];
+
+fn default_note_version() -> NoteVersion {
+    NOTE_VERSION_V1
 }
 
 impl Note {
@@ -33,9 +64,16 @@ impl Note {
             amount,
             owner_pubkey,
             blinding,
+            version: CURRENT_NOTE_VERSION,
         }
     }
 
+    /// The same note in format `version` (a different commitment).
+    pub fn with_version(mut self, version: NoteVersion) -> Self {
+        self.version = version;
+        self
+    }
+
     /// Compute the commitment for this note.
     ///
     /// This is a convenience method that calls the top-level `commit` function.
@@ -56,6 +94,8 @@ pub type Nullifier = [u8; 32];
 ///
 /// # Commitment Scheme
 /// The commitment binds to:
+/// - `version`: The note format, via the domain separator
+///   (`NOTE_COMMITMENT_v<version>`, so v1 commitments are unchanged)
 /// - `amount`: The value of the note
 /// - `owner_pubkey`: Who can spend it
 /// - `blinding`: Random entropy for hiding
@@ -70,7 +110,13 @@ pub fn commit(note: &Note) -> [u8; 32] {
     let mut hasher = Hasher::new();
 
     // Domain separator prevents hash collisions with other protocol components
-    hasher.update(NOTE_COMMITMENT_DOMAIN);
+    // and between note versions (the decimal suffix changes the length or
+    // the bytes of the preimage)
+    hasher.update(NOTE_COMMITMENT_DOMAIN_PREFIX);
+    let version = note.version;
+    let digits = [b'0' + version / 100, b'0' + version / 10 % 10, b'0' + version % 10];
+    let width = if version >= 100 { 3 } else if version >= 10 { 2 } else { 1 };
+    hasher.update(&digits[3 - width..]);
 
     // Hash all public and semi-public components
     hasher.update(&note.amount.to_le_bytes());
@@ -105,6 +151,65 @@ pub fn compute_nullifier(signature: &[u8]) -> Nullifier {
     *hash.as_bytes()
 }
 
+/// Derive a nullifier key from the owner's spending key (migration target).
+///
+/// # Nullifier-Key Migration
+/// `compute_nullifier` hashes a signature, so it is only stable when wallets
+/// sign deterministically (RFC 6979, low-s). The v2 scheme removes that
+/// assumption: nullifier = Hash(NULLIFIER_v2 || nullifier_key || commitment),
+/// with the circuit proving the nullifier key belongs to the note owner.
+///
+/// Migration is per-note: notes created before the cut-over keep v1
+/// nullifiers; wallets re-shield them (spend v1 → create v2-era notes) and the
+/// contract accepts both nullifier sets until the v1 set is retired.
+pub fn derive_nullifier_key(spending_key: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Hasher::new();
+    hasher.update(NULLIFIER_KEY_DOMAIN);
+    hasher.update(spending_key);
+    *hasher.finalize().as_bytes()
+}
+
+/// Compute a v2 nullifier from a nullifier key and note commitment.
+///
+/// Independent of signature encoding, so it cannot be malleated. See
+/// `derive_nullifier_key` for the migration path.
+pub fn compute_keyed_nullifier(nullifier_key: &[u8; 32], commitment: &[u8; 32]) -> Nullifier {
+    let mut hasher = Hasher::new();
+    hasher.update(KEYED_NULLIFIER_DOMAIN);
+    hasher.update(nullifier_key);
+    hasher.update(commitment);
+    *hasher.finalize().as_bytes()
+}
+
+/// Compute a note's nullifier in an application context (see
+/// `Witness::external_nullifier`).
+///
+/// A signature-derived nullifier would let the owner re-sign with another
+/// nonce and use the note in the same context twice, so this one is keyed
+/// on the note's blinding instead (a secret committed in the note):
+/// `compute_keyed_nullifier(derive_nullifier_key(blinding),
+/// Hash(SCOPED_NULLIFIER_v1 || external_nullifier || commitment))`. The
+/// circuit recomputes it from the witness.
+///
+/// Whoever created the note also knows its blinding and can recognise its
+/// scoped nullifiers; spend nullifiers stay unlinkable to them.
+pub fn compute_scoped_nullifier(note: &Note, external_nullifier: &[u8; 32]) -> Nullifier {
+    let mut hasher = Hasher::new();
+    hasher.update(SCOPED_NULLIFIER_DOMAIN);
+    hasher.update(external_nullifier);
+    hasher.update(&commit(note));
+    compute_keyed_nullifier(&derive_nullifier_key(&note.blinding), hasher.finalize().as_bytes())
+}
+
+/// The nullifier of an input: `compute_scoped_nullifier` in an application
+/// context, the hash of its nullifier signature otherwise.
+pub fn input_nullifier(note: &Note, signature: &[u8], external_nullifier: Option<&[u8; 32]>) -> Nullifier {
+    match external_nullifier {
+        Some(external_nullifier) => compute_scoped_nullifier(note, external_nullifier),
+        None => compute_nullifier(signature),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,6 +233,28 @@ mod tests {
         assert_ne!(nullifier1, nullifier2);
     }
 
+    #[test]
+    fn test_keyed_nullifier_is_independent_of_v1() {
+        let note = Note::new(100, [1; 32], [2; 32]);
+        let nk = derive_nullifier_key(&[3; 32]);
+        let keyed = compute_keyed_nullifier(&nk, &commit(&note));
+
+        assert_eq!(keyed, compute_keyed_nullifier(&nk, &commit(&note)));
+        assert_ne!(keyed, compute_keyed_nullifier(&derive_nullifier_key(&[4; 32]), &commit(&note)));
+        assert_ne!(nk, [3; 32]);
+    }
+
+    #[test]
+    fn test_scoped_nullifier_ignores_the_signature() {
+        let note = Note::new(100, [1; 32], [2; 32]);
+        let scoped = input_nullifier(&note, &[7; 65], Some(&[5; 32]));
+
+        assert_eq!(scoped, input_nullifier(&note, &[8; 65], Some(&[5; 32])));
+        assert_ne!(scoped, input_nullifier(&note, &[7; 65], Some(&[6; 32])));
+        assert_ne!(scoped, input_nullifier(&note.clone().with_version(2), &[7; 65], Some(&[5; 32])));
+        assert_eq!(input_nullifier(&note, &[7; 65], None), compute_nullifier(&[7; 65]));
+    }
+
     #[test]
     fn test_commitment_and_nullifier_are_different() {
         let note = Note::new(100, [1; 32], [2; 32]);
@@ -138,6 +265,40 @@ mod tests {
         assert_ne!(commitment, nullifier);
     }
 
+    #[test]
+    fn test_version_is_folded_into_commitment() {
+        let note = Note::new(100, [1; 32], [2; 32]);
+        assert_eq!(note.version, NOTE_VERSION_V1);
+
+        // v1 hashes exactly the original preimage
+        let mut legacy = Hasher::new();
+        legacy.update(b"NOTE_COMMITMENT_v1");
+        legacy.update(&100u64.to_le_bytes());
+        legacy.update(&[1; 32]);
+        legacy.update(&[2; 32]);
+        assert_eq!(commit(&note), *legacy.finalize().as_bytes());
+
+        let commitments: Vec<[u8; 32]> = (0..=255u8).map(|v| commit(&note.clone().with_version(v))).collect();
+        let mut unique = commitments.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), 256);
+
+        let mut v12 = Hasher::new();
+        v12.update(b"NOTE_COMMITMENT_v12");
+        v12.update(&100u64.to_le_bytes());
+        v12.update(&[1; 32]);
+        v12.update(&[2; 32]);
+        assert_eq!(commitments[12], *v12.finalize().as_bytes());
+    }
+
+    #[test]
+    fn test_version_defaults_to_v1_when_absent() {
+        let json = format!(r#"{{"amount":5,"owner_pubkey":{:?},"blinding":{:?}}}"#, [1u8; 32], [2u8; 32]);
+        let note: Note = serde_json::from_str(&json).unwrap();
+        assert_eq!(note, Note::new(5, [1; 32], [2; 32]));
+    }
+
     // ========================================================================
     // CROSS-LANGUAGE TEST VECTORS
     // These test vectors MUST produce identical results in:
@@ -0,0 +1,171 @@
+//! Spent-nullifier set
+//!
+//! One type for spent state wherever it's mirrored: the ledger model the
+//! guest and host pre-checks run, per-chain indexer state (`chains`), and
+//! event replays in the host. It keeps a `BTreeSet` rather than a
+//! `HashSet`: lookups stay cheap, iteration is already in the sorted order
+//! snapshots need, and the guest doesn't need a seeded hasher.
+//!
+//! Snapshots list the nullifiers in ascending order with their count and
+//! `spent_nullifiers_hash` (the same digest a balance proof commits to), so
+//! two parties can compare state by hash. `mapping_export` is the contract's
+//! view: the `nullifierUsed` entries that are `true`, keyed by nullifier.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::balance_proof::spent_nullifiers_hash;
+use crate::hex::Bytes32;
+use crate::note::Nullifier;
+
+/// Nullifiers that have been spent.
+///
+/// Serializes as the ascending list of nullifiers.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NullifierSet {
+    spent: BTreeSet<Nullifier>,
+}
+
+/// A point-in-time export of a `NullifierSet`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NullifierSnapshot {
+    pub count: usize,
+    /// `spent_nullifiers_hash` of `nullifiers`
+    pub hash: Bytes32,
+    /// Ascending
+    pub nullifiers: Vec<Bytes32>,
+}
+
+impl NullifierSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, nullifier: &Nullifier) -> bool {
+        self.spent.contains(nullifier)
+    }
+
+    /// Mark `nullifier` spent.
+    ///
+    /// # Errors
+    /// Fails, leaving the set unchanged, if it's already spent.
+    pub fn insert(&mut self, nullifier: Nullifier) -> Result<(), String> {
+        if !self.spent.insert(nullifier) {
+            return Err("Nullifier already spent".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.spent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spent.is_empty()
+    }
+
+    /// Ascending.
+    pub fn iter(&self) -> impl Iterator<Item = &Nullifier> {
+        self.spent.iter()
+    }
+
+    /// Ascending, as the balance proof's `spent_nullifiers` expects.
+    pub fn sorted(&self) -> Vec<Nullifier> {
+        self.spent.iter().copied().collect()
+    }
+
+    /// Nullifiers in this set but not in `other`, ascending.
+    pub fn difference(&self, other: &NullifierSet) -> Vec<Nullifier> {
+        self.spent.difference(&other.spent).copied().collect()
+    }
+
+    /// `spent_nullifiers_hash` over the set.
+    pub fn hash(&self) -> [u8; 32] {
+        spent_nullifiers_hash(&self.sorted())
+    }
+
+    pub fn snapshot(&self) -> NullifierSnapshot {
+        NullifierSnapshot {
+            count: self.len(),
+            hash: Bytes32(self.hash()),
+            nullifiers: self.spent.iter().copied().map(Bytes32).collect(),
+        }
+    }
+
+    /// Restore a snapshot, checking it's ascending, without repeats, and
+    /// matches its count and hash.
+    pub fn from_snapshot(snapshot: &NullifierSnapshot) -> Result<Self, String> {
+        if !snapshot.nullifiers.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Err("Snapshot nullifiers must be strictly ascending".to_string());
+        }
+        let set: Self = snapshot.nullifiers.iter().map(|n| n.0).collect();
+        if set.len() != snapshot.count {
+            return Err(format!("Snapshot lists {} nullifiers but claims {}", set.len(), snapshot.count));
+        }
+        if set.hash() != snapshot.hash.0 {
+            return Err(format!("Snapshot hash {} doesn't match its nullifiers ({})", snapshot.hash, Bytes32(set.hash())));
+        }
+        Ok(set)
+    }
+
+    /// The ledger's `nullifierUsed` mapping as this set implies it: every
+    /// spent nullifier maps to `true` (unlisted ones read as `false`).
+    pub fn mapping_export(&self) -> BTreeMap<Bytes32, bool> {
+        self.spent.iter().map(|&n| (Bytes32(n), true)).collect()
+    }
+}
+
+impl FromIterator<Nullifier> for NullifierSet {
+    fn from_iter<I: IntoIterator<Item = Nullifier>>(iter: I) -> Self {
+        Self { spent: iter.into_iter().collect() }
+    }
+}
+
+impl Extend<Nullifier> for NullifierSet {
+    fn extend<I: IntoIterator<Item = Nullifier>>(&mut self, iter: I) {
+        self.spent.extend(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_rejects_double_spend() {
+        let mut set = NullifierSet::new();
+        set.insert([2; 32]).unwrap();
+        assert!(set.insert([2; 32]).unwrap_err().contains("already spent"));
+        assert!(set.contains(&[2; 32]) && !set.contains(&[3; 32]));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let set: NullifierSet = [[3; 32], [1; 32], [2; 32]].into_iter().collect();
+        let snapshot = set.snapshot();
+        assert_eq!(snapshot.nullifiers, vec![Bytes32([1; 32]), Bytes32([2; 32]), Bytes32([3; 32])]);
+        assert_eq!(snapshot.hash.0, spent_nullifiers_hash(&set.sorted()));
+        assert_eq!(NullifierSet::from_snapshot(&snapshot).unwrap(), set);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<NullifierSnapshot>(&json).unwrap(), snapshot);
+
+        let mut tampered = snapshot.clone();
+        tampered.nullifiers.pop();
+        tampered.count = 2;
+        assert!(NullifierSet::from_snapshot(&tampered).unwrap_err().contains("hash"));
+        tampered.nullifiers.reverse();
+        assert!(NullifierSet::from_snapshot(&tampered).unwrap_err().contains("ascending"));
+    }
+
+    #[test]
+    fn test_mapping_export() {
+        let set: NullifierSet = [[1; 32]].into_iter().collect();
+        let json = serde_json::to_value(set.mapping_export()).unwrap();
+        assert_eq!(json, serde_json::json!({ format!("0x{}", "01".repeat(32)): true }));
+    }
+}
@@ -0,0 +1,248 @@
+//! Sparse Merkle tree of spent nullifiers (SMT mode)
+//!
+//! The ledger stores a slot per spent nullifier and checks each one on
+//! submission. In SMT mode it keeps a single root over every spent
+//! nullifier instead, and double-spend prevention moves into the proof: the
+//! witness carries a `NullifierTreeUpdate`, one non-membership proof per
+//! nullifier against the root so far, and the guest (`nullifier-tree`
+//! feature) checks them, inserts the nullifiers and commits the old and new
+//! roots in a trailer section after the other public values
+//! (`encode_nullifier_roots`, `public_values::SECTION_NULLIFIER_ROOTS`), so
+//! every layout, compressed included, keeps its shape. The ledger then only
+//! checks the old root is its current one and stores the new one.
+//!
+//! The tree has a leaf for every 256-bit nullifier, addressed by its bits
+//! from the most significant. An unspent leaf is zero and a spent one
+//! `leaf_hash(nullifier)`; a node over two zero children is zero, so empty
+//! subtrees need no table and proofs list only their non-zero siblings
+//! (`bitmap` marks which).
+//!
+//! The ledger contract doesn't keep a nullifier root yet. Until it does,
+//! updates are checked by the host's preflight and the guest feature only.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hex::Bytes32;
+use crate::note::Nullifier;
+
+/// Levels of the tree: one per bit of a nullifier.
+pub const NULLIFIER_TREE_DEPTH: usize = 256;
+
+/// Root of the tree with nothing spent.
+pub const EMPTY_NULLIFIER_ROOT: [u8; 32] = [0u8; 32];
+
+const LEAF_DOMAIN: &[u8] = b"NULLIFIER_TREE_LEAF_v1";
+const NODE_DOMAIN: &[u8] = b"NULLIFIER_TREE_NODE_v1";
+
+/// The leaf of a spent nullifier.
+pub fn leaf_hash(nullifier: &Nullifier) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(LEAF_DOMAIN);
+    hasher.update(nullifier);
+    hasher.finalize().into()
+}
+
+/// Parent of two nodes; zero over two empty subtrees.
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    if *left == [0u8; 32] && *right == [0u8; 32] {
+        return [0u8; 32];
+    }
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(NODE_DOMAIN);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Bit `depth` of `key`, most significant first (1 = right).
+fn bit(key: &[u8; 32], depth: usize) -> bool {
+    (key[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+/// The path to a nullifier's leaf, proving the leaf is zero.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonMembershipProof {
+    /// Bit `d` (most significant first) is set when the sibling at depth
+    /// `d` from the root is non-zero
+    pub bitmap: Bytes32,
+    /// The non-zero siblings, root side first
+    pub siblings: Vec<Bytes32>,
+}
+
+impl NonMembershipProof {
+    /// Root of the tree this path is in, with `leaf` at `nullifier`.
+    pub fn root_with(&self, nullifier: &Nullifier, leaf: [u8; 32]) -> Result<[u8; 32], String> {
+        let mut remaining = self.siblings.len();
+        let mut current = leaf;
+        for depth in (0..NULLIFIER_TREE_DEPTH).rev() {
+            let sibling = if bit(&self.bitmap.0, depth) {
+                remaining = remaining.checked_sub(1).ok_or("Nullifier proof has fewer siblings than its bitmap")?;
+                self.siblings[remaining].0
+            } else {
+                [0u8; 32]
+            };
+            current = if bit(nullifier, depth) { node_hash(&sibling, &current) } else { node_hash(&current, &sibling) };
+        }
+        if remaining != 0 {
+            return Err("Nullifier proof has more siblings than its bitmap".to_string());
+        }
+        Ok(current)
+    }
+}
+
+/// The nullifier tree before a transaction and the paths to insert its
+/// nullifiers, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NullifierTreeUpdate {
+    pub old_root: Bytes32,
+    /// `proofs[i]` is against the root after inserting `nullifiers[..i]`
+    pub proofs: Vec<NonMembershipProof>,
+}
+
+impl NullifierTreeUpdate {
+    /// Check each nullifier is unspent and insert it; the new root.
+    pub fn apply(&self, nullifiers: &[Nullifier]) -> Result<[u8; 32], String> {
+        if self.proofs.len() != nullifiers.len() {
+            return Err(format!("{} nullifier proofs for {} nullifiers", self.proofs.len(), nullifiers.len()));
+        }
+        let mut root = self.old_root.0;
+        for (i, (nullifier, proof)) in nullifiers.iter().zip(&self.proofs).enumerate() {
+            if proof.root_with(nullifier, [0u8; 32])? != root {
+                return Err(format!("Nullifier {} is spent, or its proof is against another root", i));
+            }
+            root = proof.root_with(nullifier, leaf_hash(nullifier))?;
+        }
+        Ok(root)
+    }
+}
+
+/// The trailer section the guest commits in SMT mode: the old root, then
+/// the new one.
+pub fn encode_nullifier_roots(old_root: &[u8; 32], new_root: &[u8; 32]) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    out[..32].copy_from_slice(old_root);
+    out[32..].copy_from_slice(new_root);
+    out
+}
+
+/// Decode `encode_nullifier_roots` output into the old and new roots.
+pub fn decode_nullifier_roots(section: &[u8]) -> Result<([u8; 32], [u8; 32]), String> {
+    if section.len() != 64 {
+        return Err(format!("Nullifier roots section is {} bytes, not 64", section.len()));
+    }
+    Ok((section[..32].try_into().unwrap(), section[32..].try_into().unwrap()))
+}
+
+/// Every spent nullifier, to compute roots and proofs from (host, indexer).
+#[derive(Debug, Clone, Default)]
+pub struct NullifierTree {
+    spent: BTreeSet<Nullifier>,
+}
+
+/// Root of the subtree at `depth` holding `keys` (sorted, all sharing the
+/// first `depth` bits).
+fn subtree_root(keys: &[Nullifier], depth: usize) -> [u8; 32] {
+    match keys {
+        [] => [0u8; 32],
+        [key] if depth == NULLIFIER_TREE_DEPTH => leaf_hash(key),
+        _ => {
+            let split = keys.partition_point(|key| !bit(key, depth));
+            node_hash(&subtree_root(&keys[..split], depth + 1), &subtree_root(&keys[split..], depth + 1))
+        }
+    }
+}
+
+impl NullifierTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_nullifiers(nullifiers: impl IntoIterator<Item = Nullifier>) -> Self {
+        Self { spent: nullifiers.into_iter().collect() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.spent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spent.is_empty()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        subtree_root(&self.spent.iter().copied().collect::<Vec<_>>(), 0)
+    }
+
+    /// The path to `nullifier`'s leaf, if it isn't spent.
+    pub fn prove_absent(&self, nullifier: &Nullifier) -> Result<NonMembershipProof, String> {
+        if self.spent.contains(nullifier) {
+            return Err(format!("Nullifier 0x{} is already spent", crate::hex::encode_hex(nullifier)));
+        }
+        let keys: Vec<Nullifier> = self.spent.iter().copied().collect();
+        let mut path = &keys[..];
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+        for depth in 0..NULLIFIER_TREE_DEPTH {
+            let split = path.partition_point(|key| !bit(key, depth));
+            let (left, right) = path.split_at(split);
+            let (sibling, next) = if bit(nullifier, depth) { (left, right) } else { (right, left) };
+            let sibling = subtree_root(sibling, depth + 1);
+            if sibling != [0u8; 32] {
+                bitmap[depth / 8] |= 0x80 >> (depth % 8);
+                siblings.push(Bytes32(sibling));
+            }
+            path = next;
+        }
+        Ok(NonMembershipProof { bitmap: Bytes32(bitmap), siblings })
+    }
+
+    /// Insert `nullifiers` in order, returning the update a witness carries.
+    pub fn insert_all(&mut self, nullifiers: &[Nullifier]) -> Result<NullifierTreeUpdate, String> {
+        let old_root = Bytes32(self.root());
+        let mut proofs = Vec::with_capacity(nullifiers.len());
+        for nullifier in nullifiers {
+            proofs.push(self.prove_absent(nullifier)?);
+            self.spent.insert(*nullifier);
+        }
+        Ok(NullifierTreeUpdate { old_root, proofs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_proves_non_membership_and_new_root() {
+        let mut tree = NullifierTree::from_nullifiers([[0x11; 32], [0x80; 32], [0x81; 32]]);
+        assert_eq!(NullifierTree::new().root(), EMPTY_NULLIFIER_ROOT);
+        let spent = [[0x7f; 32], [0x82; 32]];
+        let update = tree.insert_all(&spent).unwrap();
+
+        assert_eq!(update.apply(&spent).unwrap(), tree.root());
+        assert_eq!(tree.len(), 5);
+        // Same nullifiers in the other order don't match the proofs' roots
+        assert!(update.apply(&[spent[1], spent[0]]).is_err());
+        assert!(update.apply(&spent[..1]).is_err());
+    }
+
+    #[test]
+    fn test_spent_nullifier_cannot_be_inserted() {
+        let mut tree = NullifierTree::from_nullifiers([[0x11; 32]]);
+        assert!(tree.prove_absent(&[0x11; 32]).is_err());
+        assert!(tree.insert_all(&[[0x22; 32], [0x22; 32]]).is_err());
+
+        // A proof of absence can't be replayed once the nullifier is in
+        let mut tree = NullifierTree::new();
+        let update = tree.insert_all(&[[0x33; 32]]).unwrap();
+        let replay = NullifierTreeUpdate { old_root: Bytes32(tree.root()), proofs: update.proofs };
+        assert!(replay.apply(&[[0x33; 32]]).is_err());
+    }
+}
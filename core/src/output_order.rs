@@ -0,0 +1,96 @@
+//! Canonical output ordering
+//!
+//! Wallets that always put the payment first and the change second leak
+//! which output is which to anyone watching `OutputCommitted` events. Outputs
+//! are instead sorted by commitment before signing: the order is canonical
+//! (anyone can check it) and, because every commitment includes a random
+//! blinding, it is a uniformly random permutation of the outputs.
+//!
+//! The tx signatures cover the output commitments in order, so the sort has
+//! to happen before signing; the proof and `ProofResponse` then carry the
+//! same order. Recipients and senders no longer find their notes by
+//! position, but by commitment (`match_outputs`, `WalletState::claim_outputs`).
+
+use crate::note::{commit, Note};
+
+/// Sort `outputs` by commitment.
+pub fn canonicalize_outputs(outputs: &mut [Note]) {
+    outputs.sort_by_cached_key(commit);
+}
+
+/// Sort `outputs` by commitment, applying the same permutation to `paired`
+/// (e.g. per-output metadata).
+pub fn canonicalize_outputs_with<T>(outputs: Vec<Note>, paired: Vec<T>) -> (Vec<Note>, Vec<T>) {
+    assert_eq!(outputs.len(), paired.len(), "one paired item per output");
+    let mut zipped: Vec<([u8; 32], Note, T)> =
+        outputs.into_iter().zip(paired).map(|(note, item)| (commit(&note), note, item)).collect();
+    zipped.sort_by_key(|entry| entry.0);
+    zipped.into_iter().map(|(_, note, item)| (note, item)).unzip()
+}
+
+/// Whether `commitments` are in canonical (ascending) order.
+pub fn is_canonical_order(commitments: &[[u8; 32]]) -> bool {
+    commitments.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+/// Position of each note's commitment among a transaction's published
+/// `commitments` (`None` if it isn't one of them).
+pub fn match_outputs(notes: &[Note], commitments: &[[u8; 32]]) -> Vec<Option<usize>> {
+    notes
+        .iter()
+        .map(|note| {
+            let commitment = commit(note);
+            commitments.iter().position(|c| *c == commitment)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(amount: u64, blinding: u8) -> Note {
+        Note::new(amount, [7; 32], [blinding; 32])
+    }
+
+    #[test]
+    fn test_canonical_order_is_by_commitment() {
+        let mut outputs = vec![note(95, 1), note(13, 2), note(40, 3), note(8, 4)];
+        canonicalize_outputs(&mut outputs);
+        let commitments: Vec<[u8; 32]> = outputs.iter().map(commit).collect();
+        assert!(is_canonical_order(&commitments));
+
+        // The permutation depends on the blindings, not on the roles
+        let payment_first = (1..=20u8).filter(|&b| {
+            let mut pair = vec![note(95, b), note(13, b + 100)];
+            canonicalize_outputs(&mut pair);
+            pair[0].amount == 95
+        });
+        let count = payment_first.count();
+        assert!(count > 0 && count < 20, "payment first in {} of 20", count);
+    }
+
+    #[test]
+    fn test_paired_items_follow_their_outputs() {
+        let outputs = vec![note(95, 1), note(13, 2), note(40, 3)];
+        let labels = vec!["payment", "change", "tip"];
+        let (sorted, labels) = canonicalize_outputs_with(outputs.clone(), labels);
+        for (note, label) in sorted.iter().zip(&labels) {
+            let original = outputs.iter().position(|n| n == note).unwrap();
+            assert_eq!(*label, ["payment", "change", "tip"][original]);
+        }
+    }
+
+    #[test]
+    fn test_match_outputs_finds_notes_by_commitment() {
+        let mut outputs = vec![note(95, 1), note(13, 2)];
+        canonicalize_outputs(&mut outputs);
+        let commitments: Vec<[u8; 32]> = outputs.iter().map(commit).collect();
+
+        let change = note(13, 2);
+        let stranger = note(13, 9);
+        let positions = match_outputs(&[change.clone(), stranger], &commitments);
+        assert_eq!(positions[0], outputs.iter().position(|n| *n == change));
+        assert_eq!(positions[1], None);
+    }
+}
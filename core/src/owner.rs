@@ -0,0 +1,158 @@
+//! Conversions to the canonical owner representation
+//!
+//! A note's `owner_pubkey` is the 32-byte X coordinate of the owner's
+//! secp256k1 key; signatures are checked by recovering the key and comparing
+//! X only. Keys arrive in other forms (33-byte compressed or 65-byte
+//! uncompressed SEC1 from wallets, a spending key in the demo and tests, an
+//! Ethereum address from users), so everything that accepts an owner goes
+//! through here rather than slicing bytes by hand: a key that isn't on the
+//! curve would produce notes nobody can ever sign for.
+//!
+//! An Ethereum address is a hash of the key and can't be turned back into
+//! one. `owner_from_signed_address` recovers the key from a signature by
+//! that address instead; `eth_addresses` goes the other way.
+
+use k256::ecdsa::SigningKey;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::PublicKey;
+use sha3::{Digest, Keccak256};
+
+use crate::hex::decode_hex;
+use crate::ledger::recover_ethereum_key;
+
+/// Owner for a spending key.
+pub fn owner_from_spending_key(spending_key: &[u8; 32]) -> Result<[u8; 32], String> {
+    let signing_key = SigningKey::from_bytes(spending_key.into())
+        .map_err(|e| format!("Invalid spending key: {}", e))?;
+    Ok(x_coordinate(&PublicKey::from(signing_key.verifying_key())))
+}
+
+/// Owner for a SEC1 public key: 33 bytes compressed (`02`/`03` prefix) or
+/// 65 bytes uncompressed (`04` prefix).
+///
+/// # Errors
+/// Fails on any other length or if the point isn't on the curve.
+pub fn owner_from_public_key(public_key: &[u8]) -> Result<[u8; 32], String> {
+    // Checked explicitly: SEC1 also has 33-byte "compact" keys (`05`)
+    match (public_key.len(), public_key.first()) {
+        (33, Some(0x02 | 0x03)) | (65, Some(0x04)) => {}
+        (33 | 65, Some(prefix)) => return Err(format!("Unsupported public key prefix 0x{:02x}", prefix)),
+        (len, _) => {
+            return Err(format!("Public key must be 33 (compressed) or 65 (uncompressed) bytes, got {}", len));
+        }
+    }
+    let key = PublicKey::from_sec1_bytes(public_key).map_err(|_| "Public key is not a valid secp256k1 point".to_string())?;
+    Ok(x_coordinate(&key))
+}
+
+/// Check that `owner` is the X coordinate of some secp256k1 point.
+pub fn validate_owner(owner: &[u8; 32]) -> Result<(), String> {
+    public_key_even_y(owner).map(|_| ())
+}
+
+/// Owner for whoever controls `address`, recovered from their Ethereum
+/// personal-message signature over `msg_hash`.
+///
+/// # Errors
+/// Fails if the signature is malformed or was made by a different address.
+pub fn owner_from_signed_address(address: &[u8; 20], msg_hash: &[u8; 32], signature: &[u8]) -> Result<[u8; 32], String> {
+    let owner = recover_ethereum_key(msg_hash, signature).map_err(|e| e.to_string())?;
+    if !eth_addresses(&owner)?.contains(address) {
+        return Err(format!("Signature is not from 0x{}", crate::hex::encode_hex(address)));
+    }
+    Ok(owner)
+}
+
+/// The two Ethereum addresses an owner can correspond to (X alone doesn't
+/// fix the parity of Y): even Y first, then odd.
+pub fn eth_addresses(owner: &[u8; 32]) -> Result<[[u8; 20]; 2], String> {
+    let even = public_key_even_y(owner)?;
+    let odd = PublicKey::from_affine(-*even.as_affine()).map_err(|_| "Public key is the identity".to_string())?;
+    Ok([eth_address(&even), eth_address(&odd)])
+}
+
+/// Parse an owner from hex: a 32-byte owner (validated), or a 33/65-byte
+/// public key. Addresses are rejected with a pointer to
+/// `owner_from_signed_address`.
+pub fn parse_owner(s: &str) -> Result<[u8; 32], String> {
+    let bytes = decode_hex(s.trim())?;
+    match bytes.len() {
+        32 => {
+            let mut owner = [0u8; 32];
+            owner.copy_from_slice(&bytes);
+            validate_owner(&owner)?;
+            Ok(owner)
+        }
+        20 => Err("An Ethereum address doesn't determine the owner key; pass the public key, or recover it from a signature".to_string()),
+        _ => owner_from_public_key(&bytes),
+    }
+}
+
+fn x_coordinate(key: &PublicKey) -> [u8; 32] {
+    let mut owner = [0u8; 32];
+    owner.copy_from_slice(&key.to_encoded_point(true).as_bytes()[1..]);
+    owner
+}
+
+fn public_key_even_y(owner: &[u8; 32]) -> Result<PublicKey, String> {
+    let mut compressed = [0u8; 33];
+    compressed[0] = 0x02;
+    compressed[1..].copy_from_slice(owner);
+    PublicKey::from_sec1_bytes(&compressed).map_err(|_| "Owner is not the X coordinate of a secp256k1 point".to_string())
+}
+
+fn eth_address(key: &PublicKey) -> [u8; 20] {
+    let hash = Keccak256::digest(&key.to_encoded_point(false).as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::sign_message;
+
+    const SECRET: [u8; 32] = [0x11; 32];
+
+    #[test]
+    fn test_key_encodings_agree() {
+        let owner = owner_from_spending_key(&SECRET).unwrap();
+        let key = PublicKey::from(SigningKey::from_bytes((&SECRET).into()).unwrap().verifying_key());
+        let compressed = key.to_encoded_point(true);
+        let uncompressed = key.to_encoded_point(false);
+
+        assert_eq!(owner_from_public_key(compressed.as_bytes()).unwrap(), owner);
+        assert_eq!(owner_from_public_key(uncompressed.as_bytes()).unwrap(), owner);
+        assert_eq!(parse_owner(&crate::hex::encode_hex(uncompressed.as_bytes())).unwrap(), owner);
+        assert_eq!(parse_owner(&format!("0x{}", crate::hex::encode_hex(&owner))).unwrap(), owner);
+        assert!(eth_addresses(&owner).unwrap().contains(&eth_address(&key)));
+    }
+
+    #[test]
+    fn test_invalid_keys_rejected() {
+        let owner = owner_from_spending_key(&SECRET).unwrap();
+        let mut bad_prefix = [0u8; 33];
+        bad_prefix[0] = 0x05;
+        bad_prefix[1..].copy_from_slice(&owner);
+
+        assert!(owner_from_public_key(&bad_prefix).is_err());
+        assert!(owner_from_public_key(&owner).is_err());
+        // x = 5 has no square root of x^3 + 7 on secp256k1
+        let mut off_curve = [0u8; 32];
+        off_curve[31] = 5;
+        assert!(validate_owner(&off_curve).is_err());
+        assert!(parse_owner(&"ab".repeat(20)).unwrap_err().contains("address"));
+    }
+
+    #[test]
+    fn test_owner_from_signed_address() {
+        let owner = owner_from_spending_key(&SECRET).unwrap();
+        let key = PublicKey::from(SigningKey::from_bytes((&SECRET).into()).unwrap().verifying_key());
+        let msg = [0x42; 32];
+        let signature = sign_message(&SECRET, &msg).unwrap();
+
+        assert_eq!(owner_from_signed_address(&eth_address(&key), &msg, &signature).unwrap(), owner);
+        assert!(owner_from_signed_address(&[0x99; 20], &msg, &signature).is_err());
+    }
+}
@@ -0,0 +1,760 @@
+//! Client-side transaction preparation
+//!
+//! `prepare_transaction` turns a payment intent into a signed `ProofRequest`
+//! using only the wallet seed and local state: it derives the spending key,
+//! selects notes, fetches and checks their Merkle proofs, builds the
+//! recipient and change outputs, and signs. A native or mobile wallet can
+//! then hand the request to any prover without the prover learning the seed.
+//! `prepare_withdrawal` does the same for value leaving the pool, and
+//! `sign_owned_inputs` lets each owner of a jointly funded transaction sign
+//! their own inputs.
+//!
+//! The `_with_signer` variants take the key as a `signer::Signer` instead,
+//! so a hardware wallet signs the digests and only its signatures enter the
+//! request. The seed is then only used for change blindings.
+//!
+//! The `_with_policy` variants also take a `denominations::OutputPolicy`,
+//! which splits each payment and the change into several notes (e.g. powers
+//! of ten, so output amounts are less unique); the others keep one note per
+//! output.
+//!
+//! Recipient blindings come from the thread RNG; `prepare_transaction_with_rng`
+//! takes the RNG instead, so a test with a seeded one gets the same request,
+//! byte for byte, every run. Nothing else here is random: change blindings
+//! are seed-derived and outputs are sorted, not shuffled.
+
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+
+use crate::denominations::{ExactAmounts, OutputPolicy};
+use crate::merkle::{LeafIndex, MerkleProof, MerkleTree};
+use crate::note::{commit, compute_nullifier, input_nullifier, Note};
+use crate::output_order::canonicalize_outputs;
+use crate::hex::Bytes65;
+use crate::proof_request::{NoteData, ProofRequest};
+use crate::signatures::{
+    input_nullifier_message, nullifier_message, scoped_tx_message, tx_fee, tx_message, withdrawal_tx_message,
+};
+use crate::signer::{sign_checked, KeySigner, Signer};
+use crate::sp1_types::Withdrawal;
+use crate::wallet::{OwnedNote, WalletState};
+
+/// A payment to one recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recipient {
+    /// Recipient's owner pubkey (x-coordinate of their secp256k1 key)
+    pub owner_pubkey: [u8; 32],
+    pub amount: u64,
+}
+
+/// Where input Merkle proofs come from: a local tree mirror, or an
+/// indexer/RPC client implemented by the wallet.
+pub trait ProofSource {
+    /// Root the proofs are against (must be a root the contract knows).
+    fn root(&self) -> Result<[u8; 32], String>;
+
+    /// Inclusion proof for the leaf at `leaf_index`.
+    fn proof(&self, leaf_index: u64) -> Result<MerkleProof, String>;
+}
+
+impl ProofSource for MerkleTree {
+    fn root(&self) -> Result<[u8; 32], String> {
+        Ok(MerkleTree::root(self))
+    }
+
+    fn proof(&self, leaf_index: u64) -> Result<MerkleProof, String> {
+        usize::try_from(leaf_index)
+            .ok()
+            .and_then(|index| self.prove(index))
+            .ok_or_else(|| format!("Leaf {} is not in the tree ({} leaves)", leaf_index, self.leaf_count()))
+    }
+}
+
+/// Derive the wallet's spending key from its seed (HKDF-SHA256).
+pub fn derive_spending_key(seed: &[u8]) -> Result<[u8; 32], String> {
+    let hkdf = Hkdf::<Sha256>::new(Some(b"ghostclaw-spending-key"), seed);
+    let mut okm = [0u8; 32];
+    hkdf.expand(b"utxo-prototype-v1-spending-key", &mut okm)
+        .expect("HKDF expand failed");
+    // Rejects the (negligible) outputs that aren't valid scalars
+    owner_pubkey(&okm)?;
+    Ok(okm)
+}
+
+/// Owner pubkey for a spending key: the x-coordinate of its public key, as
+/// stored in `Note::owner_pubkey`.
+pub fn owner_pubkey(spending_key: &[u8; 32]) -> Result<[u8; 32], String> {
+    crate::owner::owner_from_spending_key(spending_key)
+}
+
+/// Pick spendable notes owned by `owner` covering `target`, largest first.
+///
+/// # Errors
+/// Returns an error if the owner's spendable balance is below `target`.
+pub fn select_notes<'a>(state: &'a WalletState, owner: &[u8; 32], target: u64) -> Result<Vec<&'a OwnedNote>, String> {
+    let mut candidates: Vec<&OwnedNote> = state.unspent().filter(|n| &n.note.owner_pubkey == owner).collect();
+    candidates.sort_by(|a, b| b.note.amount.cmp(&a.note.amount).then(a.leaf_index.cmp(&b.leaf_index)));
+
+    let mut selected = Vec::new();
+    let mut total: u128 = 0;
+    for owned in candidates {
+        if total >= target as u128 {
+            break;
+        }
+        total += owned.note.amount as u128;
+        selected.push(owned);
+    }
+    if total < target as u128 {
+        return Err(format!("Insufficient funds: {} spendable, {} needed", total, target));
+    }
+    Ok(selected)
+}
+
+/// Build a signed proof request paying `recipients` from the wallet's notes.
+///
+/// Inputs are chosen with `select_notes` to cover the payments plus `fee`
+/// (left unclaimed as the input/output difference). Any remainder goes to a
+/// change note owned by the wallet. Recipient blindings are random; the
+/// change blinding is derived from the seed (see `recovery`), advancing
+/// `state.next_blinding_index` even if the request is never submitted.
+/// Outputs are in canonical order (see `output_order`), so the change isn't
+/// always last, and inputs are by leaf index (see `input_order`).
+///
+/// # Errors
+/// Fails on an empty or zero-value payment, a recipient owner that isn't a
+/// curve point, insufficient funds, or a proof from `proofs` that doesn't
+/// place the selected note under its root.
+pub fn prepare_transaction(
+    chain_id: u64,
+    seed: &[u8],
+    recipients: &[Recipient],
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+) -> Result<ProofRequest, String> {
+    let signer = KeySigner::new(derive_spending_key(seed)?)?;
+    prepare_transaction_with_signer(chain_id, &signer, seed, recipients, fee, state, proofs)
+}
+
+/// `prepare_transaction`, spending `signer`'s notes; change blindings are
+/// still derived from `seed`.
+pub fn prepare_transaction_with_signer(
+    chain_id: u64,
+    signer: &impl Signer,
+    seed: &[u8],
+    recipients: &[Recipient],
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+) -> Result<ProofRequest, String> {
+    prepare_transaction_with_policy(chain_id, signer, seed, recipients, fee, state, proofs, &ExactAmounts)
+}
+
+/// `prepare_transaction_with_signer`, splitting each payment and the change
+/// into notes with `policy`. Every change note gets its own seed-derived
+/// blinding.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_transaction_with_policy(
+    chain_id: u64,
+    signer: &impl Signer,
+    seed: &[u8],
+    recipients: &[Recipient],
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+    policy: &impl OutputPolicy,
+) -> Result<ProofRequest, String> {
+    prepare_transaction_with_rng(chain_id, signer, seed, recipients, fee, state, proofs, policy, &mut rand::thread_rng())
+}
+
+/// `prepare_transaction_with_policy`, drawing recipient blindings from `rng`
+/// instead of the thread RNG. Only for tests and snapshots with a seeded
+/// RNG; real payments need unpredictable blindings.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_transaction_with_rng(
+    chain_id: u64,
+    signer: &impl Signer,
+    seed: &[u8],
+    recipients: &[Recipient],
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+    policy: &impl OutputPolicy,
+    rng: &mut impl RngCore,
+) -> Result<ProofRequest, String> {
+    if recipients.is_empty() {
+        return Err("No recipients".to_string());
+    }
+    if recipients.iter().any(|r| r.amount == 0) {
+        return Err("Recipient amounts must be non-zero".to_string());
+    }
+    for (i, recipient) in recipients.iter().enumerate() {
+        crate::owner::validate_owner(&recipient.owner_pubkey).map_err(|e| format!("Recipient {}: {}", i, e))?;
+    }
+    prepare(chain_id, signer, seed, recipients, None, fee, state, proofs, policy, rng)
+}
+
+/// Build a signed proof request paying `withdrawal` out of the pool.
+///
+/// Like `prepare_transaction` with no recipients: inputs cover the
+/// withdrawal plus `fee`, and any remainder goes to a change note. Spending
+/// notes that sum exactly to that leaves no outputs at all.
+///
+/// # Errors
+/// Fails on a zero amount or recipient, insufficient funds, or a bad proof
+/// from `proofs`.
+pub fn prepare_withdrawal(
+    chain_id: u64,
+    seed: &[u8],
+    withdrawal: Withdrawal,
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+) -> Result<ProofRequest, String> {
+    let signer = KeySigner::new(derive_spending_key(seed)?)?;
+    prepare_withdrawal_with_signer(chain_id, &signer, seed, withdrawal, fee, state, proofs)
+}
+
+/// `prepare_withdrawal`, spending `signer`'s notes; change blindings are
+/// still derived from `seed`.
+pub fn prepare_withdrawal_with_signer(
+    chain_id: u64,
+    signer: &impl Signer,
+    seed: &[u8],
+    withdrawal: Withdrawal,
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+) -> Result<ProofRequest, String> {
+    prepare_withdrawal_with_policy(chain_id, signer, seed, withdrawal, fee, state, proofs, &ExactAmounts)
+}
+
+/// `prepare_withdrawal_with_signer`, splitting the change into notes with
+/// `policy`.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_withdrawal_with_policy(
+    chain_id: u64,
+    signer: &impl Signer,
+    seed: &[u8],
+    withdrawal: Withdrawal,
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+    policy: &impl OutputPolicy,
+) -> Result<ProofRequest, String> {
+    if withdrawal.public_amount == 0 {
+        return Err("Withdrawal amount must be non-zero".to_string());
+    }
+    if withdrawal.recipient == [0u8; 20] {
+        return Err("Withdrawal recipient must be non-zero".to_string());
+    }
+    // No recipients, so the RNG goes unused and withdrawals are deterministic
+    prepare(chain_id, signer, seed, &[], Some(withdrawal), fee, state, proofs, policy, &mut rand::thread_rng())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare(
+    chain_id: u64,
+    signer: &impl Signer,
+    seed: &[u8],
+    recipients: &[Recipient],
+    withdrawal: Option<Withdrawal>,
+    fee: u64,
+    state: &mut WalletState,
+    proofs: &impl ProofSource,
+    policy: &impl OutputPolicy,
+    rng: &mut impl RngCore,
+) -> Result<ProofRequest, String> {
+    let public_amount = withdrawal.map_or(0, |w| w.public_amount);
+    let payment = fee
+        .checked_add(public_amount)
+        .and_then(|total| recipients.iter().try_fold(total, |total, r| total.checked_add(r.amount)))
+        .ok_or("Payment total overflows u64")?;
+
+    let owner = signer.owner()?;
+    let mut inputs: Vec<OwnedNote> = select_notes(state, &owner, payment)?.into_iter().cloned().collect();
+    // Canonical input order (see `input_order`), before anything is signed
+    inputs.sort_by_key(|owned| owned.leaf_index);
+
+    let old_root = proofs.root()?;
+    let mut input_proofs = Vec::with_capacity(inputs.len());
+    for owned in &inputs {
+        let proof = proofs.proof(owned.leaf_index)?;
+        if proof.leaf_index != LeafIndex::from(owned.leaf_index) || !MerkleTree::verify_proof(owned.commitment, &proof, old_root) {
+            return Err(format!("Proof for leaf {} does not match the note or root", owned.leaf_index));
+        }
+        input_proofs.push(proof.siblings.into_iter().map(Into::into).collect());
+    }
+
+    let mut outputs: Vec<Note> = Vec::new();
+    for recipient in recipients {
+        for amount in split_output(policy, recipient.amount)? {
+            let mut blinding = [0u8; 32];
+            rng.fill_bytes(&mut blinding);
+            outputs.push(Note::new(amount, recipient.owner_pubkey, blinding));
+        }
+    }
+    let input_total: u64 = inputs.iter().map(|n| n.note.amount).sum();
+    let change = input_total - payment;
+    if change > 0 {
+        for amount in split_output(policy, change)? {
+            outputs.push(Note::new(amount, owner, state.next_blinding(seed)));
+        }
+    }
+    canonicalize_outputs(&mut outputs);
+    let output_commitments: Vec<[u8; 32]> = outputs.iter().map(commit).collect();
+
+    let mut nullifier_signatures = Vec::with_capacity(inputs.len());
+    let mut tx_signatures = Vec::with_capacity(inputs.len());
+    for owned in &inputs {
+        let nullifier_sig = sign_checked(signer, &owner, &nullifier_message(&owned.commitment))?;
+        let nullifier = compute_nullifier(&nullifier_sig);
+        let tx_msg_hash = match &withdrawal {
+            Some(w) => withdrawal_tx_message(&nullifier, fee, &output_commitments, w),
+            None => tx_message(&nullifier, fee, &output_commitments),
+        };
+        tx_signatures.push(sign_checked(signer, &owner, &tx_msg_hash)?.into());
+        nullifier_signatures.push(nullifier_sig.into());
+    }
+
+    let input_indices = inputs
+        .iter()
+        .map(|n| usize::try_from(n.leaf_index).map_err(|_| format!("Leaf index {} overflows usize", n.leaf_index)))
+        .collect::<Result<Vec<usize>, String>>()?;
+
+    Ok(ProofRequest {
+        schema_version: crate::proof_request::REQUEST_SCHEMA_VERSION,
+        input_notes: inputs.iter().map(|n| NoteData::from(&n.note)).collect(),
+        output_notes: outputs.iter().map(NoteData::from).collect(),
+        nullifier_signatures,
+        tx_signatures,
+        input_indices,
+        input_proofs,
+        old_root: old_root.into(),
+        chain_id,
+        withdrawal,
+        external_nullifier: None,
+        nullifier_tree: None,
+        input_amount_blindings: Vec::new(),
+        output_amount_blindings: Vec::new(),
+        deadline_ms: None,
+        trace_id: None,
+    })
+}
+
+/// `policy`'s split of `amount`, checked to be non-zero notes summing to it.
+fn split_output(policy: &impl OutputPolicy, amount: u64) -> Result<Vec<u64>, String> {
+    let notes = policy.split(amount);
+    let total = notes.iter().try_fold(0u64, |total, &n| total.checked_add(n));
+    if notes.contains(&0) || total != Some(amount) {
+        return Err(format!("Output policy split {} into {:?}", amount, notes));
+    }
+    Ok(notes)
+}
+
+/// Sign every input of `request` owned by the wallet, leaving the other
+/// inputs' signatures as they are.
+///
+/// For transactions funded by several owners, e.g. a payjoin where the
+/// recipient adds a note of their own so the payment isn't simply the
+/// difference between inputs and change: one party builds the request
+/// with every input and output, then each owner signs in turn. Tx
+/// signatures cover all outputs and the fee, so those must be final before
+/// anyone signs. Inputs not yet signed hold zero placeholders (see
+/// `ProofRequest::unsigned_inputs`).
+///
+/// Also signs requests with an `external_nullifier`, using the notes in that
+/// application context instead of spending them.
+///
+/// # Returns
+/// Indices of the inputs signed.
+///
+/// # Errors
+/// Fails if the wallet owns none of the inputs or the outputs exceed them.
+pub fn sign_owned_inputs(seed: &[u8], request: &mut ProofRequest) -> Result<Vec<usize>, String> {
+    sign_owned_inputs_with_signer(&KeySigner::new(derive_spending_key(seed)?)?, request)
+}
+
+/// `sign_owned_inputs` for the inputs owned by `signer`.
+pub fn sign_owned_inputs_with_signer(signer: &impl Signer, request: &mut ProofRequest) -> Result<Vec<usize>, String> {
+    let owner = signer.owner()?;
+    let external_nullifier = request.external_nullifier.map(|e| e.0);
+
+    let input_notes: Vec<Note> = request.input_notes.iter().map(Note::from).collect();
+    let output_notes: Vec<Note> = request.output_notes.iter().map(Note::from).collect();
+    let public_amount = request.withdrawal.map_or(0, |w| w.public_amount);
+    let fee = tx_fee(&input_notes, &output_notes)
+        .and_then(|fee| fee.checked_sub(public_amount))
+        .ok_or("Outputs exceed inputs")?;
+    let output_commitments: Vec<[u8; 32]> = output_notes.iter().map(commit).collect();
+
+    request.nullifier_signatures.resize(input_notes.len(), Bytes65::default());
+    request.tx_signatures.resize(input_notes.len(), Bytes65::default());
+    let mut signed = Vec::new();
+    for (i, note) in input_notes.iter().enumerate().filter(|(_, n)| n.owner_pubkey == owner) {
+        let nullifier_msg = input_nullifier_message(&commit(note), external_nullifier.as_ref());
+        let nullifier_sig = sign_checked(signer, &owner, &nullifier_msg)?;
+        let nullifier = input_nullifier(note, &nullifier_sig, external_nullifier.as_ref());
+        let tx_msg_hash = match (&external_nullifier, &request.withdrawal) {
+            (Some(external_nullifier), _) => scoped_tx_message(&nullifier, external_nullifier),
+            (None, Some(w)) => withdrawal_tx_message(&nullifier, fee, &output_commitments, w),
+            (None, None) => tx_message(&nullifier, fee, &output_commitments),
+        };
+        request.tx_signatures[i] = sign_checked(signer, &owner, &tx_msg_hash)?.into();
+        request.nullifier_signatures[i] = nullifier_sig.into();
+        signed.push(i);
+    }
+    if signed.is_empty() {
+        return Err("The wallet owns none of the request's inputs".to_string());
+    }
+    Ok(signed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{simulate_witness, Ledger};
+
+    const SEED: &[u8] = b"prepare-transaction-test-seed";
+
+    #[test]
+    fn test_prepared_request_passes_guest_checks() {
+        let owner = owner_pubkey(&derive_spending_key(SEED).unwrap()).unwrap();
+        let mut tree = MerkleTree::new();
+        let mut state = WalletState::new();
+        tree.push_note(&Note::new(5, [0xee; 32], [0xee; 32]));
+        for (amount, blinding) in [(30, 1), (80, 2), (10, 3)] {
+            let note = Note::new(amount, owner, [blinding; 32]);
+            let index = tree.push_note(&note).index;
+            state.add_note(note, u64::try_from(index).unwrap());
+        }
+
+        let recipient = Recipient { owner_pubkey: [7; 32], amount: 95 };
+        let request = prepare_transaction(1, SEED, &[recipient], 2, &mut state, &tree).unwrap();
+
+        // Largest first: 80 + 30 covers 97, leaving 13 change; inputs by leaf index
+        assert_eq!(request.input_indices, vec![1, 2]);
+        assert_eq!(request.chain_id, 1);
+        let mut amounts: Vec<u64> = request.output_notes.iter().map(|n| n.amount).collect();
+        amounts.sort_unstable();
+        assert_eq!(amounts, vec![13, 95]);
+        let change = request.output_notes.iter().find(|n| n.amount == 13).unwrap();
+        assert_eq!(change.owner_pubkey, owner);
+        let commitments: Vec<[u8; 32]> = request.output_notes.iter().map(|n| commit(&Note::from(n))).collect();
+        assert!(crate::output_order::is_canonical_order(&commitments));
+
+        let witness = request.to_witness().unwrap().with_precomputed_values();
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, request.old_root.0);
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+        assert_eq!(simulation.fee(), 2);
+
+        // A client that lists its inputs in selection order is put back in
+        // leaf order, each input keeping its own proof and signatures
+        let mut reordered = request.clone();
+        reordered.input_notes.reverse();
+        reordered.input_indices.reverse();
+        reordered.input_proofs.reverse();
+        reordered.nullifier_signatures.reverse();
+        reordered.tx_signatures.reverse();
+        assert!(reordered.to_witness().unwrap().validate_structure().unwrap_err().contains("ascending leaf index"));
+        reordered.canonicalize_inputs().unwrap();
+        assert_eq!(serde_json::to_value(&reordered).unwrap(), serde_json::to_value(&request).unwrap());
+    }
+
+    #[test]
+    fn test_external_signer_spends_its_own_notes() {
+        // E.g. a hardware wallet: the seed only derives change blindings
+        let signer = KeySigner::new([0x24; 32]).unwrap();
+        let owner = signer.owner().unwrap();
+        let mut tree = MerkleTree::new();
+        let mut state = WalletState::new();
+        for (amount, blinding) in [(50, 1), (20, 2)] {
+            let note = Note::new(amount, owner, [blinding; 32]);
+            let index = tree.push_note(&note).index;
+            state.add_note(note, u64::try_from(index).unwrap());
+        }
+
+        let recipient = Recipient { owner_pubkey: [7; 32], amount: 60 };
+        let request = prepare_transaction_with_signer(1, &signer, SEED, &[recipient], 1, &mut state, &tree).unwrap();
+        let change = request.output_notes.iter().find(|n| n.amount == 9).unwrap();
+        assert_eq!(change.owner_pubkey, owner);
+        assert_eq!(change.blinding.0, crate::recovery::derive_note_blinding(SEED, 0));
+
+        let witness = request.to_witness().unwrap().with_precomputed_values();
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, request.old_root.0);
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+
+        // The seed's own key owns none of these notes
+        assert!(prepare_transaction(1, SEED, &[recipient], 1, &mut state, &tree).is_err());
+    }
+
+    #[test]
+    fn test_denominated_outputs() {
+        let signer = KeySigner::new(derive_spending_key(SEED).unwrap()).unwrap();
+        let owner = signer.owner().unwrap();
+        let mut tree = MerkleTree::new();
+        let mut state = WalletState::new();
+        let note = Note::new(1_000, owner, [1; 32]);
+        let index = tree.push_note(&note).index;
+        state.add_note(note, u64::try_from(index).unwrap());
+
+        let recipient = Recipient { owner_pubkey: [7; 32], amount: 320 };
+        let policy = crate::denominations::Denominations::default();
+        let request =
+            prepare_transaction_with_policy(1, &signer, SEED, &[recipient], 0, &mut state, &tree, &policy).unwrap();
+
+        // 320 to the recipient as 100 + 100 + 100 + 20, 680 change as 100 x 3 + 380
+        let amounts = |owner: [u8; 32]| {
+            let mut amounts: Vec<u64> =
+                request.output_notes.iter().filter(|n| n.owner_pubkey.0 == owner).map(|n| n.amount).collect();
+            amounts.sort_unstable();
+            amounts
+        };
+        assert_eq!(amounts([7; 32]), vec![20, 100, 100, 100]);
+        assert_eq!(amounts(owner), vec![100, 100, 100, 380]);
+        assert_eq!(state.next_blinding_index, 4);
+
+        let witness = request.to_witness().unwrap().with_precomputed_values();
+        assert!(simulate_witness(&mut Ledger::new(), &witness, request.old_root.0).is_valid());
+    }
+
+    #[test]
+    fn test_seeded_rng_gives_identical_requests() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let signer = KeySigner::new(derive_spending_key(SEED).unwrap()).unwrap();
+        let owner = signer.owner().unwrap();
+        let mut tree = MerkleTree::new();
+        let mut state = WalletState::new();
+        for (amount, blinding) in [(50, 1), (20, 2)] {
+            let note = Note::new(amount, owner, [blinding; 32]);
+            let index = tree.push_note(&note).index;
+            state.add_note(note, u64::try_from(index).unwrap());
+        }
+        let recipients = [Recipient { owner_pubkey: [7; 32], amount: 30 }, Recipient { owner_pubkey: [8; 32], amount: 25 }];
+
+        let snapshot = |rng_seed: u64| {
+            // A fresh copy of the state, so both runs derive the same change blinding
+            let mut state = state.clone();
+            let mut rng = StdRng::seed_from_u64(rng_seed);
+            let request = prepare_transaction_with_rng(
+                1, &signer, SEED, &recipients, 1, &mut state, &tree, &ExactAmounts, &mut rng,
+            )
+            .unwrap();
+            serde_json::to_string(&request).unwrap()
+        };
+        assert_eq!(snapshot(7), snapshot(7));
+        assert_ne!(snapshot(7), snapshot(8));
+    }
+
+    #[test]
+    fn test_full_withdrawal_has_no_outputs() {
+        let owner = owner_pubkey(&derive_spending_key(SEED).unwrap()).unwrap();
+        let mut tree = MerkleTree::new();
+        let mut state = WalletState::new();
+        for (amount, blinding) in [(60, 1), (40, 2)] {
+            let note = Note::new(amount, owner, [blinding; 32]);
+            let index = tree.push_note(&note).index;
+            state.add_note(note, u64::try_from(index).unwrap());
+        }
+
+        let withdrawal = Withdrawal { public_amount: 99, recipient: [0x0b; 20].into() };
+        let request = prepare_withdrawal(1, SEED, withdrawal, 1, &mut state, &tree).unwrap();
+        assert!(request.output_notes.is_empty());
+        assert_eq!(request.withdrawal, Some(withdrawal));
+
+        let witness = request.to_witness().unwrap().with_precomputed_values();
+        witness.validate_structure().unwrap();
+        witness.validate_value_conservation().unwrap();
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, request.old_root.0);
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+        assert_eq!(simulation.fee(), 1);
+        assert_eq!(simulation.public_outputs.withdrawal, Some(withdrawal));
+
+        // The signatures cover the recipient: redirecting the payout fails
+        let mut redirected = witness.clone();
+        redirected.withdrawal = Some(Withdrawal { recipient: [0x0c; 20].into(), ..withdrawal });
+        assert!(!simulate_witness(&mut Ledger::new(), &redirected, request.old_root.0).is_valid());
+
+        // Dropping the withdrawal leaves a transaction with no outputs
+        let mut burn = witness;
+        burn.withdrawal = None;
+        assert!(burn.validate_structure().unwrap_err().contains("must be a withdrawal"));
+    }
+
+    #[test]
+    fn test_payjoin_inputs_signed_by_each_owner() {
+        const RECIPIENT_SEED: &[u8] = b"prepare-transaction-test-recipient";
+        let sender = owner_pubkey(&derive_spending_key(SEED).unwrap()).unwrap();
+        let recipient = owner_pubkey(&derive_spending_key(RECIPIENT_SEED).unwrap()).unwrap();
+        let inputs = [Note::new(80, sender, [1; 32]), Note::new(20, recipient, [2; 32])];
+        let mut tree = MerkleTree::new();
+        for note in &inputs {
+            tree.push_note(note);
+        }
+
+        // Sender pays 50; the recipient's 20 comes back to them with it
+        let mut request = ProofRequest {
+            schema_version: crate::proof_request::REQUEST_SCHEMA_VERSION,
+            input_notes: inputs.iter().map(NoteData::from).collect(),
+            output_notes: vec![
+                NoteData::from(&Note::new(70, recipient, [3; 32])),
+                NoteData::from(&Note::new(29, sender, [4; 32])),
+            ],
+            nullifier_signatures: Vec::new(),
+            tx_signatures: Vec::new(),
+            input_indices: vec![0, 1],
+            input_proofs: (0..2).map(|i| tree.prove(i).unwrap().siblings.into_iter().map(Into::into).collect()).collect(),
+            old_root: tree.root().into(),
+            chain_id: 1,
+            withdrawal: None,
+            external_nullifier: None,
+            nullifier_tree: None,
+            input_amount_blindings: Vec::new(),
+            output_amount_blindings: Vec::new(),
+            deadline_ms: None,
+            trace_id: None,
+        };
+
+        assert_eq!(sign_owned_inputs(SEED, &mut request).unwrap(), vec![0]);
+        assert_eq!(request.unsigned_inputs(), vec![1]);
+        assert_eq!(sign_owned_inputs(RECIPIENT_SEED, &mut request).unwrap(), vec![1]);
+        assert!(request.unsigned_inputs().is_empty());
+        assert!(sign_owned_inputs(b"someone-else", &mut request).is_err());
+
+        let witness = request.to_witness().unwrap().with_precomputed_values();
+        assert_eq!(witness.input_owners(), vec![sender, recipient]);
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, tree.root());
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+        assert_eq!(simulation.fee(), 1);
+
+        // One owner's signature can't authorize the other's input
+        let mut swapped = witness;
+        swapped.tx_signatures.swap(0, 1);
+        let reasons = simulate_witness(&mut Ledger::new(), &swapped, tree.root()).failure_reasons();
+        assert_eq!(reasons.len(), 2, "{:?}", reasons);
+    }
+
+    #[test]
+    fn test_scoped_use_has_per_context_nullifiers() {
+        let owner = owner_pubkey(&derive_spending_key(SEED).unwrap()).unwrap();
+        let note = Note::new(40, owner, [1; 32]);
+        let mut tree = MerkleTree::new();
+        tree.push_note(&note);
+
+        let scoped_request = |external_nullifier: [u8; 32]| {
+            let mut request = ProofRequest {
+                schema_version: crate::proof_request::REQUEST_SCHEMA_VERSION,
+                input_notes: vec![NoteData::from(&note)],
+                output_notes: Vec::new(),
+                nullifier_signatures: Vec::new(),
+                tx_signatures: Vec::new(),
+                input_indices: vec![0],
+                input_proofs: vec![tree.prove(0).unwrap().siblings.into_iter().map(Into::into).collect()],
+                old_root: tree.root().into(),
+                chain_id: 1,
+                withdrawal: None,
+                external_nullifier: Some(external_nullifier.into()),
+                nullifier_tree: None,
+                input_amount_blindings: Vec::new(),
+                output_amount_blindings: Vec::new(),
+                deadline_ms: None,
+                trace_id: None,
+            };
+            sign_owned_inputs(SEED, &mut request).unwrap();
+            let witness = request.to_witness().unwrap().with_precomputed_values();
+            let simulation = simulate_witness(&mut Ledger::new(), &witness, request.old_root.0);
+            assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+            assert_eq!(simulation.public_outputs.external_nullifier, Some(external_nullifier));
+            (witness, simulation.public_outputs.nullifiers[0])
+        };
+
+        // Stable within a context, different across contexts and from the spend nullifier
+        let (witness, airdrop) = scoped_request([0xa1; 32]);
+        assert_eq!(scoped_request([0xa1; 32]).1, airdrop);
+        let (_, poll) = scoped_request([0xb2; 32]);
+        let spend_sig = crate::signatures::sign_message(&derive_spending_key(SEED).unwrap(), &nullifier_message(&commit(&note)));
+        assert_ne!(airdrop, poll);
+        assert_ne!(airdrop, compute_nullifier(&spend_sig.unwrap()));
+
+        // A scoped signature doesn't verify in another context or as a spend
+        let mut moved = witness.clone();
+        moved.external_nullifier = Some([0xb2; 32]);
+        assert!(!simulate_witness(&mut Ledger::new(), &moved, tree.root()).is_valid());
+        let mut spend = witness.clone().with_withdrawal(Withdrawal { public_amount: 40, recipient: [1; 20].into() });
+        assert!(spend.validate_structure().unwrap_err().contains("scoped"));
+        spend.external_nullifier = None;
+        assert!(!simulate_witness(&mut Ledger::new(), &spend, tree.root()).is_valid());
+    }
+
+    #[test]
+    fn test_scoped_nullifier_is_the_same_for_any_signature() {
+        use k256::ecdsa::hazmat::SignPrimitive;
+        use k256::Scalar;
+
+        let key = derive_spending_key(SEED).unwrap();
+        let owner = owner_pubkey(&key).unwrap();
+        let note = Note::new(40, owner, [1; 32]);
+        let mut tree = MerkleTree::new();
+        tree.push_note(&note);
+        let mut request = ProofRequest {
+            schema_version: crate::proof_request::REQUEST_SCHEMA_VERSION,
+            input_notes: vec![NoteData::from(&note)],
+            output_notes: Vec::new(),
+            nullifier_signatures: Vec::new(),
+            tx_signatures: Vec::new(),
+            input_indices: vec![0],
+            input_proofs: vec![tree.prove(0).unwrap().siblings.into_iter().map(Into::into).collect()],
+            old_root: tree.root().into(),
+            chain_id: 1,
+            withdrawal: None,
+            external_nullifier: Some([0xa1; 32].into()),
+            nullifier_tree: None,
+            input_amount_blindings: Vec::new(),
+            output_amount_blindings: Vec::new(),
+            deadline_ms: None,
+            trace_id: None,
+        };
+        sign_owned_inputs(SEED, &mut request).unwrap();
+        let witness = request.to_witness().unwrap().with_precomputed_values();
+
+        // A valid signature over the same message with a non-RFC 6979 nonce
+        let secret = *k256::ecdsa::SigningKey::from_bytes((&key).into()).unwrap().as_nonzero_scalar().as_ref();
+        let message = crate::signatures::scoped_nullifier_message(&[0xa1; 32], &commit(&note));
+        let digest = crate::signatures::eth_signed_hash(&message);
+        let (signature, rec_id) = secret.try_sign_prehashed(Scalar::from(12345u64), &digest.into()).unwrap();
+        let mut resigned = [0u8; 65];
+        resigned[..64].copy_from_slice(&signature.to_bytes());
+        resigned[64] = rec_id.unwrap().to_byte() + 27;
+        assert_ne!(resigned[..], witness.nullifier_signatures[0][..]);
+
+        let mut other = witness.clone();
+        other.nullifier_signatures[0] = resigned.to_vec();
+        let other = other.with_precomputed_values();
+        let first = simulate_witness(&mut Ledger::new(), &witness, tree.root());
+        let second = simulate_witness(&mut Ledger::new(), &other, tree.root());
+        assert!(second.is_valid(), "{:?}", second.failure_reasons());
+        assert_eq!(second.public_outputs.nullifiers, first.public_outputs.nullifiers);
+    }
+
+    #[test]
+    fn test_insufficient_funds_and_stale_tree_rejected() {
+        let owner = owner_pubkey(&derive_spending_key(SEED).unwrap()).unwrap();
+        let note = Note::new(50, owner, [1; 32]);
+        let mut state = WalletState::new();
+        state.add_note(note.clone(), 0);
+        let recipient = Recipient { owner_pubkey: [7; 32], amount: 40 };
+
+        let mut tree = MerkleTree::new();
+        tree.push_note(&note);
+        let err = prepare_transaction(1, SEED, &[recipient], 20, &mut state, &tree).unwrap_err();
+        assert!(err.contains("Insufficient funds"), "{}", err);
+
+        // The wallet thinks the note is at leaf 0, but the mirror disagrees
+        let stale = MerkleTree::with_leaves(vec![[0xaa; 32]]);
+        let err = prepare_transaction(1, SEED, &[recipient], 0, &mut state, &stale).unwrap_err();
+        assert!(err.contains("does not match"), "{}", err);
+    }
+}
@@ -0,0 +1,247 @@
+//! The JSON request the prover host accepts on stdin
+//!
+//! Defined here rather than in the host so wallets can build requests
+//! without depending on the prover (see `prepare::prepare_transaction`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::hex::{Bytes32, Bytes65};
+use crate::input_order::{input_order, is_identity, permute};
+use crate::merkle::MerkleProof;
+use crate::note::{Note, NoteVersion, NOTE_VERSION_V1};
+use crate::nullifier_tree::NullifierTreeUpdate;
+use crate::sp1_types::{Withdrawal, Witness};
+
+/// Request format this build understands. Bump when a field changes
+/// meaning, so old clients fail loudly instead of proving the wrong thing.
+pub const REQUEST_SCHEMA_VERSION: u32 = 1;
+
+/// Transaction request from the prover-server
+///
+/// Unknown fields are rejected (serde lists the accepted ones), so a typo
+/// like `ownerPubKey` fails to parse instead of silently defaulting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProofRequest {
+    /// Request format version; omitted by clients that predate it (v1)
+    #[serde(default = "request_schema_v1")]
+    pub schema_version: u32,
+    /// Input notes being spent (full note data)
+    pub input_notes: Vec<NoteData>,
+    /// Output notes being created
+    pub output_notes: Vec<NoteData>,
+    /// Nullifier signatures
+    pub nullifier_signatures: Vec<Bytes65>,
+    /// Transaction signatures
+    pub tx_signatures: Vec<Bytes65>,
+    /// Indices of input notes in the merkle tree
+    pub input_indices: Vec<usize>,
+    /// Merkle proofs for input notes (sibling hashes, leaf to root)
+    pub input_proofs: Vec<Vec<Bytes32>>,
+    /// Current merkle root from contract
+    pub old_root: Bytes32,
+    /// Chain whose ledger `old_root` is from; the proof is bound to it
+    pub chain_id: u64,
+    /// Value paid out of the pool; required when there are no output notes
+    #[serde(default)]
+    pub withdrawal: Option<Withdrawal>,
+    /// Application context to use the inputs in instead of spending them
+    /// (see `Witness::external_nullifier`); no outputs or withdrawal then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_nullifier: Option<Bytes32>,
+    /// Non-membership proofs of the inputs' nullifiers in the ledger's
+    /// nullifier tree, for guests built in SMT mode (see `nullifier_tree`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nullifier_tree: Option<NullifierTreeUpdate>,
+    /// Amount commitment blindings, per input and per output, for guests
+    /// built in hidden-amounts mode (see `amount_commitment`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_amount_blindings: Vec<Bytes32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_amount_blindings: Vec<Bytes32>,
+    /// Unix time (ms) after which the client no longer wants the proof; the
+    /// host bounds network proving by it and fails with `DEADLINE_EXCEEDED`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
+    /// Correlation ID from the prover-server, echoed in logs and responses
+    #[serde(default)]
+    pub trace_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NoteData {
+    pub amount: u64,
+    pub owner_pubkey: Bytes32,
+    pub blinding: Bytes32,
+    /// Omitted by clients that predate note versions (v1)
+    #[serde(default = "note_version_v1")]
+    pub version: NoteVersion,
+}
+
+fn request_schema_v1() -> u32 {
+    1
+}
+
+fn note_version_v1() -> NoteVersion {
+    NOTE_VERSION_V1
+}
+
+impl From<&Note> for NoteData {
+    fn from(note: &Note) -> Self {
+        Self {
+            amount: note.amount,
+            owner_pubkey: note.owner_pubkey.into(),
+            blinding: note.blinding.into(),
+            version: note.version,
+        }
+    }
+}
+
+impl From<&NoteData> for Note {
+    fn from(data: &NoteData) -> Self {
+        Note::new(data.amount, data.owner_pubkey.0, data.blinding.0).with_version(data.version)
+    }
+}
+
+impl ProofRequest {
+    /// Reject requests in a format this build doesn't understand.
+    pub fn check_schema_version(&self) -> Result<(), String> {
+        if self.schema_version != REQUEST_SCHEMA_VERSION {
+            return Err(format!(
+                "Unsupported request schemaVersion {} (this prover accepts {})",
+                self.schema_version, REQUEST_SCHEMA_VERSION
+            ));
+        }
+        Ok(())
+    }
+
+    /// Inputs still missing a nullifier or tx signature (absent, or the
+    /// all-zero placeholder left for another owner to fill in).
+    pub fn unsigned_inputs(&self) -> Vec<usize> {
+        let unsigned = |sigs: &[Bytes65], i: usize| sigs.get(i).is_none_or(|sig| *sig == Bytes65::default());
+        (0..self.input_notes.len())
+            .filter(|&i| unsigned(&self.nullifier_signatures, i) || unsigned(&self.tx_signatures, i))
+            .collect()
+    }
+
+    /// Put the inputs in canonical order (see `input_order`), moving each
+    /// one's proofs and signatures with it, so the same spend always hashes
+    /// to the same request. See `Witness::canonicalize_inputs`.
+    ///
+    /// # Errors
+    /// Fails if they need reordering and a `nullifier_tree` update is
+    /// attached, or on mismatched input lengths.
+    pub fn canonicalize_inputs(&mut self) -> Result<(), String> {
+        if self.input_indices.len() != self.input_notes.len() {
+            return Err(format!(
+                "Mismatch: {} notes vs {} indices",
+                self.input_notes.len(),
+                self.input_indices.len()
+            ));
+        }
+        let order = input_order(&self.input_indices, |i| crate::note::commit(&Note::from(&self.input_notes[i])));
+        if is_identity(&order) {
+            return Ok(());
+        }
+        if self.nullifier_tree.is_some() {
+            return Err("Inputs must be ordered before building the nullifier tree update".to_string());
+        }
+        permute(&mut self.input_notes, &order);
+        permute(&mut self.input_indices, &order);
+        permute(&mut self.input_proofs, &order);
+        permute(&mut self.nullifier_signatures, &order);
+        permute(&mut self.tx_signatures, &order);
+        permute(&mut self.input_amount_blindings, &order);
+        Ok(())
+    }
+
+    /// The witness this request describes, without precomputed values.
+    ///
+    /// # Errors
+    /// Returns an error if the proof list doesn't match the inputs.
+    pub fn to_witness(&self) -> Result<Witness, String> {
+        if self.input_proofs.len() != self.input_notes.len() {
+            return Err(format!(
+                "Mismatch: {} notes vs {} proofs",
+                self.input_notes.len(),
+                self.input_proofs.len()
+            ));
+        }
+        let input_proofs = self
+            .input_proofs
+            .iter()
+            .zip(&self.input_indices)
+            .map(|(proof, &index)| MerkleProof::new(index as u64, proof.iter().map(|s| s.0).collect()))
+            .collect();
+
+        let mut witness = Witness::new(
+            self.input_notes.iter().map(Note::from).collect(),
+            self.input_indices.clone(),
+            input_proofs,
+            self.nullifier_signatures.iter().map(Bytes65::to_vec).collect(),
+            self.tx_signatures.iter().map(Bytes65::to_vec).collect(),
+            self.output_notes.iter().map(Note::from).collect(),
+        );
+        witness.withdrawal = self.withdrawal;
+        witness.external_nullifier = self.external_nullifier.map(|e| e.0);
+        witness.nullifier_tree = self.nullifier_tree.clone();
+        witness.input_amount_blindings = self.input_amount_blindings.iter().map(|r| r.0).collect();
+        witness.output_amount_blindings = self.output_amount_blindings.iter().map(|r| r.0).collect();
+        Ok(witness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_json(note: &str) -> String {
+        format!(
+            r#"{{"inputNotes":[],"outputNotes":[{}],"nullifierSignatures":[],"txSignatures":[],"inputIndices":[],"inputProofs":[],"oldRoot":"0x{}","chainId":1}}"#,
+            note,
+            "11".repeat(32)
+        )
+    }
+
+    #[test]
+    fn test_unknown_fields_rejected() {
+        let key = "22".repeat(32);
+        let good = format!(r#"{{"amount":5,"ownerPubkey":"0x{}","blinding":"0x{}"}}"#, key, key);
+        let request: ProofRequest = serde_json::from_str(&request_json(&good)).unwrap();
+        assert_eq!(request.schema_version, REQUEST_SCHEMA_VERSION);
+        assert!(request.check_schema_version().is_ok());
+
+        let typo = format!(r#"{{"amount":5,"ownerPubKey":"0x{}","blinding":"0x{}"}}"#, key, key);
+        let err = serde_json::from_str::<ProofRequest>(&request_json(&typo)).unwrap_err().to_string();
+        assert!(err.contains("unknown field `ownerPubKey`") && err.contains("`ownerPubkey`"), "{}", err);
+
+        let extra = request_json(&good).replacen('{', r#"{"fee":1,"#, 1);
+        assert!(serde_json::from_str::<ProofRequest>(&extra).is_err());
+    }
+
+    #[test]
+    fn test_chain_id_required() {
+        let json = request_json("").replace(r#","chainId":1"#, "");
+        let err = serde_json::from_str::<ProofRequest>(&json).unwrap_err().to_string();
+        assert!(err.contains("missing field `chainId`"), "{}", err);
+    }
+
+    #[test]
+    fn test_deadline_is_optional() {
+        let request: ProofRequest = serde_json::from_str(&request_json("")).unwrap();
+        assert_eq!(request.deadline_ms, None);
+        assert!(!serde_json::to_string(&request).unwrap().contains("deadlineMs"));
+
+        let json = request_json("").replacen('{', r#"{"deadlineMs":1700000000000,"#, 1);
+        let request: ProofRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(request.deadline_ms, Some(1_700_000_000_000));
+    }
+
+    #[test]
+    fn test_unsupported_schema_version_rejected() {
+        let json = request_json("").replacen('{', r#"{"schemaVersion":2,"#, 1);
+        let request: ProofRequest = serde_json::from_str(&json).unwrap();
+        assert!(request.check_schema_version().unwrap_err().contains("schemaVersion 2"));
+    }
+}
@@ -0,0 +1,105 @@
+//! Progress events streamed by the prover CLI server
+//!
+//! The WebSocket server reports each proof's progress as a `ProverEvent`,
+//! tagged JSON with a snake_case `type` and camelCase fields:
+//!
+//! ```json
+//! {"type": "executing", "cycles": 1843211}
+//! {"type": "proving_shard", "shard": 3, "of": 8}
+//! {"type": "complete", "response": {"proof": "0x...", "publicValues": "0x..."}}
+//! ```
+//!
+//! Clients match on the variant rather than parse free-text messages; the
+//! stages a backend can't observe (shards and wrapping happen inside the
+//! SDK's `prove` on the local backends) are simply skipped. `percent` maps
+//! an event onto a progress bar for UIs that want one.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::PublicOutputs;
+
+/// One step of serving a proof request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum ProverEvent {
+    /// Accepted, waiting for a prover
+    Queued,
+    /// Loading the ELF and computing the proving key
+    SetupStarted,
+    /// The guest executed in `cycles` cycles; proving starts next
+    Executing { cycles: u64 },
+    /// Proving shard `shard` (from 0) of `of`
+    ProvingShard { shard: u32, of: u32 },
+    /// Wrapping the shard proofs into the on-chain (PLONK/Groth16) proof
+    Wrapping,
+    Complete { response: ProofResult },
+    Failed { error: String },
+}
+
+/// The proof a `Complete` event carries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofResult {
+    /// 0x-hex proof bytes, as the verifier takes them
+    pub proof: String,
+    /// 0x-hex public values
+    pub public_values: String,
+    /// The public values decoded, when the server decodes them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_outputs: Option<PublicOutputs>,
+}
+
+impl ProverEvent {
+    /// Whether no more events follow for this proof.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Complete { .. } | Self::Failed { .. })
+    }
+
+    /// Rough completion, 0 to 100.
+    pub fn percent(&self) -> u8 {
+        match self {
+            Self::Queued => 0,
+            Self::SetupStarted => 10,
+            Self::Executing { .. } => 20,
+            Self::ProvingShard { shard, of } => 20 + (70 * (*shard).min(*of) / (*of).max(1)) as u8,
+            Self::Wrapping => 90,
+            Self::Complete { .. } | Self::Failed { .. } => 100,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_tagged_json() {
+        let json = serde_json::to_string(&ProverEvent::ProvingShard { shard: 3, of: 8 }).unwrap();
+        assert_eq!(json, r#"{"type":"proving_shard","shard":3,"of":8}"#);
+        assert_eq!(serde_json::to_string(&ProverEvent::SetupStarted).unwrap(), r#"{"type":"setup_started"}"#);
+
+        let complete = ProverEvent::Complete {
+            response: ProofResult { proof: "0x01".into(), public_values: "0x02".into(), public_outputs: None },
+        };
+        let json = serde_json::to_string(&complete).unwrap();
+        assert!(json.contains(r#""publicValues":"0x02""#), "{}", json);
+        assert_eq!(serde_json::from_str::<ProverEvent>(&json).unwrap(), complete);
+        assert!(serde_json::from_str::<ProverEvent>(r#"{"type":"progress","percent":10}"#).is_err());
+    }
+
+    #[test]
+    fn test_percent_is_monotonic() {
+        let events = [
+            ProverEvent::Queued,
+            ProverEvent::SetupStarted,
+            ProverEvent::Executing { cycles: 10 },
+            ProverEvent::ProvingShard { shard: 0, of: 4 },
+            ProverEvent::ProvingShard { shard: 3, of: 4 },
+            ProverEvent::Wrapping,
+            ProverEvent::Failed { error: "out of memory".into() },
+        ];
+        assert!(events.windows(2).all(|w| w[0].percent() <= w[1].percent()));
+        assert!(events.iter().filter(|e| e.is_final()).count() == 1);
+        assert_eq!(ProverEvent::ProvingShard { shard: 9, of: 0 }.percent(), 20);
+    }
+}
@@ -0,0 +1,549 @@
+//! ABI encoding of the guest's public values
+//!
+//! Two formats are committed by the guest:
+//! - **Full**: `PublicOutputs(chainId, oldRoot, nullifiers[], outputCommitments[])`.
+//! - **Compressed** (more than `COMPRESSION_THRESHOLD` nullifiers and
+//!   commitments combined): the lists are replaced by their counts and a
+//!   Keccak Merkle root over each. The full lists are passed to the contract
+//!   as calldata, which re-hashes them against the proven roots.
+//!
+//! - **Withdrawal**: `WithdrawalOutputs(chainId, oldRoot, nullifiers[],
+//!   outputCommitments[], publicAmount, recipient)`, the full format with
+//!   the value paid out of the pool appended. Never compressed.
+//!
+//! - **Scoped**: `ScopedOutputs(tag, chainId, oldRoot, externalNullifier,
+//!   nullifiers[])`, for notes used in an application context rather than
+//!   spent (see `Witness::external_nullifier`). For application contracts,
+//!   not the ledger: the leading `SCOPED_OUTPUTS_TAG` sits where the ledger
+//!   decodes its `uint64 chainId`, and doesn't fit one, so the ledger's
+//!   decoder rejects the values outright.
+//!
+//! All others lead with the chain ID the proof is bound to; the ledger
+//! rejects values whose `chainId` isn't `block.chainid`.
+//!
+//! The compressed encoding is a fixed 192 bytes, which is never a valid full
+//! encoding (at least 224 bytes), so the two are unambiguous. Full and
+//! withdrawal encodings differ in the offset of the nullifier list (the
+//! fourth word): 0x80 after a four-field head, 0xc0 after a six-field one.
+//!
+//! # Trailer
+//!
+//! Guest modes that commit more than these layouts (hidden amounts, SMT
+//! nullifier roots) append
+//! a trailer after any of them: tagged sections, each `tag (1) || length
+//! (u32 BE) || body`, in ascending tag order, then a 32-byte footer,
+//! `TRAILER_MAGIC (28) || sections length (u32 BE)`. Decoders read the
+//! footer from the end and strip the trailer first (`split_trailer`, and
+//! `PublicValuesTrailer.base` in the ledger), so the length and offset
+//! checks above see the same values with or without one. A new section gets
+//! a new tag; a new trailer layout gets a new magic.
+//!
+//! Must match `contracts/src/PublicValuesCompression.sol`,
+//! `contracts/src/PublicValuesTrailer.sol` and
+//! `PrivateUTXOLedger.WithdrawalOutputs`.
+
+use alloy_sol_types::{sol, SolType};
+use sha3::{Digest, Keccak256};
+
+use crate::ledger::PublicOutputs;
+use crate::sp1_types::Withdrawal;
+
+/// Lists with more items than this (nullifiers + commitments) are compressed.
+pub const COMPRESSION_THRESHOLD: usize = 16;
+
+/// First word of the scoped encoding.
+pub const SCOPED_OUTPUTS_TAG: [u8; 32] = *b"ghostclaw-scoped-outputs-v1\0\0\0\0\0";
+
+/// ABI-encoded length of `CompressedPublicOutputsSol`.
+pub const COMPRESSED_LEN: usize = 6 * 32;
+
+/// Leads the trailer footer; the version is part of it.
+pub const TRAILER_MAGIC: [u8; 28] = *b"ghostclaw-pv-trailer-v1\0\0\0\0\0";
+
+/// Length of the trailer footer (magic and sections length).
+pub const TRAILER_FOOTER_LEN: usize = 32;
+
+/// Trailer section holding `amount_commitment::encode_hidden_amounts`.
+pub const SECTION_HIDDEN_AMOUNTS: u8 = 1;
+
+/// Trailer section holding `nullifier_tree::encode_nullifier_roots`.
+pub const SECTION_NULLIFIER_ROOTS: u8 = 2;
+
+sol! {
+    /// Must match the PublicOutputs struct in PrivateUTXOLedger.sol
+    struct PublicOutputsSol {
+        uint64 chainId;
+        bytes32 oldRoot;
+        bytes32[] nullifiers;
+        bytes32[] outputCommitments;
+    }
+
+    /// Must match the WithdrawalOutputs struct in PrivateUTXOLedger.sol
+    struct WithdrawalPublicOutputsSol {
+        uint64 chainId;
+        bytes32 oldRoot;
+        bytes32[] nullifiers;
+        bytes32[] outputCommitments;
+        uint64 publicAmount;
+        address recipient;
+    }
+
+    /// Decoded by application contracts verifying scoped proofs
+    struct ScopedPublicOutputsSol {
+        bytes32 tag;
+        uint64 chainId;
+        bytes32 oldRoot;
+        bytes32 externalNullifier;
+        bytes32[] nullifiers;
+    }
+
+    /// Must match PublicValuesCompression.CompressedPublicOutputs
+    struct CompressedPublicOutputsSol {
+        uint64 chainId;
+        bytes32 oldRoot;
+        uint32 nullifierCount;
+        bytes32 nullifiersRoot;
+        uint32 commitmentCount;
+        bytes32 commitmentsRoot;
+    }
+}
+
+/// Keccak Merkle root over a list, zero-padded to a power of two.
+///
+/// An empty list has root `0`; a single item is its own root. The list
+/// length is committed alongside, so padding can't be confused with items.
+pub fn list_root(items: &[[u8; 32]]) -> [u8; 32] {
+    if items.is_empty() {
+        return [0u8; 32];
+    }
+    let mut level = items.to_vec();
+    level.resize(items.len().next_power_of_two(), [0u8; 32]);
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let mut hasher = Keccak256::new();
+                hasher.update(pair[0]);
+                hasher.update(pair[1]);
+                hasher.finalize().into()
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// Offset of the nullifier list in a withdrawal encoding (see module docs).
+const WITHDRAWAL_LIST_OFFSET: u8 = 6 * 32;
+
+/// Whether the guest commits these outputs in compressed form.
+pub fn should_compress(outputs: &PublicOutputs) -> bool {
+    outputs.withdrawal.is_none()
+        && outputs.external_nullifier.is_none()
+        && outputs.nullifiers.len() + outputs.output_commitments.len() > COMPRESSION_THRESHOLD
+}
+
+/// ABI-encode the full public values.
+pub fn encode_full(outputs: &PublicOutputs) -> Vec<u8> {
+    let sol = PublicOutputsSol {
+        chainId: outputs.chain_id,
+        oldRoot: outputs.old_root.into(),
+        nullifiers: outputs.nullifiers.iter().map(|n| (*n).into()).collect(),
+        outputCommitments: outputs.output_commitments.iter().map(|c| (*c).into()).collect(),
+    };
+    PublicOutputsSol::abi_encode(&sol)
+}
+
+/// ABI-encode public values paying out `withdrawal`.
+pub fn encode_withdrawal(outputs: &PublicOutputs, withdrawal: &Withdrawal) -> Vec<u8> {
+    let sol = WithdrawalPublicOutputsSol {
+        chainId: outputs.chain_id,
+        oldRoot: outputs.old_root.into(),
+        nullifiers: outputs.nullifiers.iter().map(|n| (*n).into()).collect(),
+        outputCommitments: outputs.output_commitments.iter().map(|c| (*c).into()).collect(),
+        publicAmount: withdrawal.public_amount,
+        recipient: withdrawal.recipient.0.into(),
+    };
+    WithdrawalPublicOutputsSol::abi_encode(&sol)
+}
+
+/// ABI-encode the public values of a scoped proof in the
+/// `external_nullifier` context.
+pub fn encode_scoped(outputs: &PublicOutputs, external_nullifier: &[u8; 32]) -> Vec<u8> {
+    let sol = ScopedPublicOutputsSol {
+        tag: SCOPED_OUTPUTS_TAG.into(),
+        chainId: outputs.chain_id,
+        oldRoot: outputs.old_root.into(),
+        externalNullifier: (*external_nullifier).into(),
+        nullifiers: outputs.nullifiers.iter().map(|n| (*n).into()).collect(),
+    };
+    ScopedPublicOutputsSol::abi_encode(&sol)
+}
+
+/// ABI-encode the compressed public values.
+pub fn encode_compressed(outputs: &PublicOutputs) -> Vec<u8> {
+    let sol = CompressedPublicOutputsSol {
+        chainId: outputs.chain_id,
+        oldRoot: outputs.old_root.into(),
+        nullifierCount: outputs.nullifiers.len() as u32,
+        nullifiersRoot: list_root(&outputs.nullifiers).into(),
+        commitmentCount: outputs.output_commitments.len() as u32,
+        commitmentsRoot: list_root(&outputs.output_commitments).into(),
+    };
+    CompressedPublicOutputsSol::abi_encode(&sol)
+}
+
+/// Encode public values in the format the guest commits for these outputs.
+pub fn encode_public_values(outputs: &PublicOutputs) -> Vec<u8> {
+    if let Some(external_nullifier) = &outputs.external_nullifier {
+        encode_scoped(outputs, external_nullifier)
+    } else if let Some(withdrawal) = &outputs.withdrawal {
+        encode_withdrawal(outputs, withdrawal)
+    } else if should_compress(outputs) {
+        encode_compressed(outputs)
+    } else {
+        encode_full(outputs)
+    }
+}
+
+/// Encode trailer `sections` (tag and body, tags ascending) to commit after
+/// the ABI public values; empty if there are none.
+pub fn encode_trailer(sections: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    if sections.is_empty() {
+        return Vec::new();
+    }
+    debug_assert!(sections.windows(2).all(|pair| pair[0].0 < pair[1].0), "trailer tags must ascend");
+    let mut out = Vec::new();
+    for (tag, body) in sections {
+        out.push(*tag);
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        out.extend_from_slice(body);
+    }
+    let sections_len = out.len() as u32;
+    out.extend_from_slice(&TRAILER_MAGIC);
+    out.extend_from_slice(&sections_len.to_be_bytes());
+    out
+}
+
+/// Trailer sections as (tag, body), tags ascending.
+pub type TrailerSections<'a> = Vec<(u8, &'a [u8])>;
+
+/// Split committed public values into the ABI values and the trailer's
+/// sections (none if there is no trailer).
+///
+/// # Errors
+/// Fails on a malformed trailer: a length past the start of the values, a
+/// truncated section, or tags out of order.
+pub fn split_trailer(public_values: &[u8]) -> Result<(&[u8], TrailerSections<'_>), String> {
+    let Some(footer_at) = public_values.len().checked_sub(TRAILER_FOOTER_LEN) else {
+        return Ok((public_values, Vec::new()));
+    };
+    let footer = &public_values[footer_at..];
+    if footer[..28] != TRAILER_MAGIC {
+        return Ok((public_values, Vec::new()));
+    }
+    let sections_len = u32::from_be_bytes(footer[28..].try_into().unwrap()) as usize;
+    let base_len = footer_at
+        .checked_sub(sections_len)
+        .ok_or_else(|| format!("Trailer claims {} bytes of sections, more than the public values", sections_len))?;
+
+    let (base, mut rest) = public_values[..footer_at].split_at(base_len);
+    let mut sections = TrailerSections::new();
+    while !rest.is_empty() {
+        if rest.len() < 5 {
+            return Err("Truncated trailer section header".to_string());
+        }
+        let tag = rest[0];
+        let len = u32::from_be_bytes(rest[1..5].try_into().unwrap()) as usize;
+        let body = rest[5..].get(..len).ok_or_else(|| format!("Trailer section {} is truncated", tag))?;
+        if sections.last().is_some_and(|(last, _)| *last >= tag) {
+            return Err(format!("Trailer section {} is out of order", tag));
+        }
+        sections.push((tag, body));
+        rest = &rest[5 + len..];
+    }
+    Ok((base, sections))
+}
+
+/// The body of trailer section `tag`, if the values carry one.
+pub fn trailer_section(public_values: &[u8], tag: u8) -> Result<Option<&[u8]>, String> {
+    let (_, sections) = split_trailer(public_values)?;
+    Ok(sections.into_iter().find(|(t, _)| *t == tag).map(|(_, body)| body))
+}
+
+/// The ABI values, without a trailer.
+fn base(public_values: &[u8]) -> &[u8] {
+    split_trailer(public_values).map_or(public_values, |(base, _)| base)
+}
+
+/// Whether committed public values use the compressed format.
+pub fn is_compressed(public_values: &[u8]) -> bool {
+    base(public_values).len() == COMPRESSED_LEN
+}
+
+/// Whether committed public values are a scoped proof's.
+pub fn is_scoped(public_values: &[u8]) -> bool {
+    base(public_values).get(32..64) == Some(&SCOPED_OUTPUTS_TAG[..])
+}
+
+/// Whether committed public values pay a withdrawal.
+pub fn is_withdrawal(public_values: &[u8]) -> bool {
+    let public_values = base(public_values);
+    !is_compressed(public_values)
+        && !is_scoped(public_values)
+        && public_values.len() >= 128
+        && public_values[96..127].iter().all(|&b| b == 0)
+        && public_values[127] == WITHDRAWAL_LIST_OFFSET
+}
+
+/// Decode public values of any format, ignoring a trailer.
+///
+/// For compressed values, `nullifiers` and `output_commitments` are the full
+/// lists supplied alongside (as the contract receives them in calldata) and
+/// are checked against the committed counts and roots. They are ignored for
+/// the full format.
+pub fn decode_public_values(
+    public_values: &[u8],
+    nullifiers: &[[u8; 32]],
+    output_commitments: &[[u8; 32]],
+) -> Result<PublicOutputs, String> {
+    let (public_values, _) = split_trailer(public_values)?;
+    if is_scoped(public_values) {
+        let sol = ScopedPublicOutputsSol::abi_decode(public_values, true)
+            .map_err(|e| format!("Failed to decode scoped public values: {}", e))?;
+        return Ok(PublicOutputs {
+            chain_id: sol.chainId,
+            old_root: sol.oldRoot.0,
+            nullifiers: sol.nullifiers.iter().map(|n| n.0).collect(),
+            output_commitments: Vec::new(),
+            withdrawal: None,
+            external_nullifier: Some(sol.externalNullifier.0),
+        });
+    }
+    if is_withdrawal(public_values) {
+        let sol = WithdrawalPublicOutputsSol::abi_decode(public_values, true)
+            .map_err(|e| format!("Failed to decode withdrawal public values: {}", e))?;
+        return Ok(PublicOutputs {
+            chain_id: sol.chainId,
+            old_root: sol.oldRoot.0,
+            nullifiers: sol.nullifiers.iter().map(|n| n.0).collect(),
+            output_commitments: sol.outputCommitments.iter().map(|c| c.0).collect(),
+            withdrawal: Some(Withdrawal { public_amount: sol.publicAmount, recipient: sol.recipient.0 .0.into() }),
+            external_nullifier: None,
+        });
+    }
+    if !is_compressed(public_values) {
+        let sol = PublicOutputsSol::abi_decode(public_values, true)
+            .map_err(|e| format!("Failed to decode public values: {}", e))?;
+        return Ok(PublicOutputs {
+            chain_id: sol.chainId,
+            old_root: sol.oldRoot.0,
+            nullifiers: sol.nullifiers.iter().map(|n| n.0).collect(),
+            output_commitments: sol.outputCommitments.iter().map(|c| c.0).collect(),
+            withdrawal: None,
+            external_nullifier: None,
+        });
+    }
+
+    let sol = CompressedPublicOutputsSol::abi_decode(public_values, true)
+        .map_err(|e| format!("Failed to decode compressed public values: {}", e))?;
+    if sol.nullifierCount as usize != nullifiers.len() {
+        return Err(format!(
+            "Nullifier count mismatch: committed {}, supplied {}",
+            sol.nullifierCount,
+            nullifiers.len()
+        ));
+    }
+    if sol.commitmentCount as usize != output_commitments.len() {
+        return Err(format!(
+            "Commitment count mismatch: committed {}, supplied {}",
+            sol.commitmentCount,
+            output_commitments.len()
+        ));
+    }
+    if list_root(nullifiers) != sol.nullifiersRoot.0 {
+        return Err("Supplied nullifiers do not match the committed root".to_string());
+    }
+    if list_root(output_commitments) != sol.commitmentsRoot.0 {
+        return Err("Supplied output commitments do not match the committed root".to_string());
+    }
+
+    Ok(PublicOutputs {
+        chain_id: sol.chainId,
+        old_root: sol.oldRoot.0,
+        nullifiers: nullifiers.to_vec(),
+        output_commitments: output_commitments.to_vec(),
+        withdrawal: None,
+        external_nullifier: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(inputs: u8, outputs: u8) -> PublicOutputs {
+        PublicOutputs {
+            chain_id: 11155111,
+            old_root: [0xaa; 32],
+            nullifiers: (0..inputs).map(|i| [i; 32]).collect(),
+            output_commitments: (0..outputs).map(|i| [0x80 | i; 32]).collect(),
+            withdrawal: None,
+            external_nullifier: None,
+        }
+    }
+
+    #[test]
+    fn test_list_root_shapes() {
+        assert_eq!(list_root(&[]), [0u8; 32]);
+        assert_eq!(list_root(&[[7; 32]]), [7; 32]);
+
+        let pair: [u8; 32] = Keccak256::digest([[1u8; 32], [2u8; 32]].concat()).into();
+        let zero_pair: [u8; 32] = Keccak256::digest([[3u8; 32], [0u8; 32]].concat()).into();
+        let expected: [u8; 32] = Keccak256::digest([pair, zero_pair].concat()).into();
+        assert_eq!(list_root(&[[1; 32], [2; 32], [3; 32]]), expected);
+    }
+
+    #[test]
+    fn test_small_transactions_stay_full() {
+        let small = outputs(2, 2);
+        let encoded = encode_public_values(&small);
+
+        assert!(!is_compressed(&encoded));
+        assert_eq!(encoded, encode_full(&small));
+        assert_eq!(decode_public_values(&encoded, &[], &[]).unwrap(), small);
+    }
+
+    #[test]
+    fn test_compressed_roundtrip_with_calldata_lists() {
+        let large = outputs(4, 20);
+        let encoded = encode_public_values(&large);
+
+        assert_eq!(encoded.len(), COMPRESSED_LEN);
+        assert!(encoded.len() < encode_full(&large).len());
+        let decoded = decode_public_values(&encoded, &large.nullifiers, &large.output_commitments).unwrap();
+        assert_eq!(decoded, large);
+    }
+
+    #[test]
+    fn test_chain_id_leads_both_formats() {
+        let empty = outputs(0, 0);
+        let full = encode_full(&empty);
+        assert_eq!(full.len(), COMPRESSED_LEN + 32);
+        assert!(!is_compressed(&full));
+        // Tuple offset, then chainId as the first head word
+        assert_eq!(full[56..64], 11155111u64.to_be_bytes());
+
+        let compressed = encode_compressed(&outputs(4, 20));
+        assert_eq!(compressed[24..32], 11155111u64.to_be_bytes());
+
+        let mut other_chain = outputs(1, 1);
+        other_chain.chain_id = 1;
+        assert_ne!(encode_full(&other_chain), encode_full(&outputs(1, 1)));
+    }
+
+    #[test]
+    fn test_scoped_roundtrip_isnt_a_ledger_encoding() {
+        let mut scoped = outputs(20, 0);
+        scoped.external_nullifier = Some([0x80; 32]);
+        let encoded = encode_public_values(&scoped);
+
+        assert!(is_scoped(&encoded) && !is_compressed(&encoded) && !is_withdrawal(&encoded));
+        assert_eq!(decode_public_values(&encoded, &[], &[]).unwrap(), scoped);
+        // The tag is where the ledger reads chainId, and isn't a uint64
+        assert!(PublicOutputsSol::abi_decode(&encoded, true).is_err());
+        assert!(WithdrawalPublicOutputsSol::abi_decode(&encoded, true).is_err());
+        assert!(!is_scoped(&encode_full(&outputs(1, 1))));
+    }
+
+    #[test]
+    fn test_compressed_rejects_tampered_lists() {
+        let large = outputs(4, 20);
+        let encoded = encode_compressed(&large);
+
+        let mut swapped = large.output_commitments.clone();
+        swapped.swap(0, 1);
+        assert!(decode_public_values(&encoded, &large.nullifiers, &swapped).is_err());
+        assert!(decode_public_values(&encoded, &large.nullifiers[..3], &large.output_commitments).is_err());
+    }
+
+    #[test]
+    fn test_withdrawal_commits_amount_and_recipient() {
+        let mut burn = outputs(20, 0);
+        burn.withdrawal = Some(Withdrawal { public_amount: 500, recipient: [0xbb; 20].into() });
+        let encoded = encode_public_values(&burn);
+
+        // Never compressed, and told apart from transfers by the list offset
+        assert!(!is_compressed(&encoded));
+        assert!(is_withdrawal(&encoded));
+        assert!(!is_withdrawal(&encode_full(&outputs(1, 2))));
+        assert!(!is_withdrawal(&encode_compressed(&outputs(4, 20))));
+        assert_eq!(decode_public_values(&encoded, &[], &[]).unwrap(), burn);
+
+        let mut other_recipient = burn.clone();
+        other_recipient.withdrawal = Some(Withdrawal { public_amount: 500, recipient: [0xcc; 20].into() });
+        assert_ne!(encode_public_values(&other_recipient), encoded);
+    }
+
+    #[test]
+    fn test_trailer_keeps_the_base_layout() {
+        let large = outputs(4, 20);
+        let compressed = encode_public_values(&large);
+        let trailer = encode_trailer(&[(SECTION_HIDDEN_AMOUNTS, vec![7; 45])]);
+        let committed = [compressed.clone(), trailer].concat();
+
+        assert!(is_compressed(&committed) && !is_withdrawal(&committed) && !is_scoped(&committed));
+        let (base, sections) = split_trailer(&committed).unwrap();
+        assert_eq!(base, &compressed[..]);
+        assert_eq!(sections, vec![(SECTION_HIDDEN_AMOUNTS, &[7u8; 45][..])]);
+        assert_eq!(trailer_section(&committed, SECTION_HIDDEN_AMOUNTS).unwrap(), Some(&[7u8; 45][..]));
+        let decoded = decode_public_values(&committed, &large.nullifiers, &large.output_commitments).unwrap();
+        assert_eq!(decoded, large);
+
+        // No trailer: the values are their own base
+        assert_eq!(split_trailer(&compressed).unwrap(), (&compressed[..], Vec::new()));
+        assert!(encode_trailer(&[]).is_empty());
+
+        let mut overlong = committed.clone();
+        let at = overlong.len() - 4;
+        overlong[at..].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(split_trailer(&overlong).is_err());
+        assert!(decode_public_values(&overlong, &large.nullifiers, &large.output_commitments).is_err());
+    }
+
+    #[test]
+    fn test_compressed_smt_values_carry_both_roots() {
+        use crate::nullifier_tree::{decode_nullifier_roots, encode_nullifier_roots, NullifierTree};
+
+        let large = outputs(4, 20);
+        let mut tree = NullifierTree::from_nullifiers([[0xf0; 32]]);
+        let update = tree.insert_all(&large.nullifiers).unwrap();
+        let new_root = update.apply(&large.nullifiers).unwrap();
+        assert_eq!(new_root, tree.root());
+
+        // As the guest commits them with hidden amounts and SMT mode both on
+        let committed = [
+            encode_public_values(&large),
+            encode_trailer(&[
+                (SECTION_HIDDEN_AMOUNTS, vec![1; 12]),
+                (SECTION_NULLIFIER_ROOTS, encode_nullifier_roots(&update.old_root.0, &new_root).to_vec()),
+            ]),
+        ]
+        .concat();
+
+        assert!(is_compressed(&committed));
+        assert_eq!(decode_public_values(&committed, &large.nullifiers, &large.output_commitments).unwrap(), large);
+        let roots = trailer_section(&committed, SECTION_NULLIFIER_ROOTS).unwrap().unwrap();
+        assert_eq!(decode_nullifier_roots(roots).unwrap(), (update.old_root.0, new_root));
+        assert!(decode_nullifier_roots(&roots[1..]).is_err());
+
+        // Sections out of order are malformed
+        let mut sections = Vec::new();
+        for tag in [SECTION_NULLIFIER_ROOTS, SECTION_HIDDEN_AMOUNTS] {
+            sections.push(tag);
+            sections.extend_from_slice(&0u32.to_be_bytes());
+        }
+        let footer = [&TRAILER_MAGIC[..], &(sections.len() as u32).to_be_bytes()].concat();
+        let out_of_order = [encode_public_values(&large), sections, footer].concat();
+        assert!(split_trailer(&out_of_order).unwrap_err().contains("out of order"));
+    }
+}
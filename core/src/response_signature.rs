@@ -0,0 +1,172 @@
+//! Prover operator signatures over proof responses
+//!
+//! A `ProofResponse` crosses the prover-server, queues and relayers before
+//! it reaches whoever submits it. The proof itself can't be forged, but the
+//! response around it can be swapped for another valid proof (someone
+//! else's transaction, another guest program). An operator that signs its
+//! responses lets downstream services check that a response came from the
+//! prover they configured and answers the request they sent.
+//!
+//! The signed digest is `response_digest`: Keccak256 over a domain tag, the
+//! hash of the request as it was received (`request_hash`), the public
+//! outputs in a fixed layout, and the vkey hash. The signature is an
+//! Ethereum personal-message signature (`signatures::sign_message`), and the
+//! operator is identified like a note owner: by the X coordinate of its key.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::hex::{Bytes32, Bytes65};
+use crate::ledger::{recover_ethereum_key, PublicOutputs};
+use crate::signatures::sign_message;
+
+/// Domain tag of `response_digest`.
+pub const RESPONSE_SIGNATURE_DOMAIN: &[u8] = b"ghostclaw-proof-response-v1";
+
+/// The operator's signature carried in a `ProofResponse`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseSignature {
+    /// Operator key (X coordinate, as `owner::owner_from_spending_key`)
+    pub operator: Bytes32,
+    /// `request_hash` of the request this response answers
+    pub request_hash: Bytes32,
+    pub signature: Bytes65,
+}
+
+/// Hash of a request as received (the JSON line, sealed or not).
+pub fn request_hash(raw_request: &[u8]) -> [u8; 32] {
+    Keccak256::digest(raw_request).into()
+}
+
+/// The digest an operator signs for a response.
+pub fn response_digest(request_hash: &[u8; 32], outputs: &PublicOutputs, vkey_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(RESPONSE_SIGNATURE_DOMAIN);
+    hasher.update(request_hash);
+    hasher.update(outputs.chain_id.to_be_bytes());
+    hasher.update(outputs.old_root);
+    hasher.update((outputs.nullifiers.len() as u32).to_be_bytes());
+    for nullifier in &outputs.nullifiers {
+        hasher.update(nullifier);
+    }
+    hasher.update((outputs.output_commitments.len() as u32).to_be_bytes());
+    for commitment in &outputs.output_commitments {
+        hasher.update(commitment);
+    }
+    match &outputs.withdrawal {
+        Some(withdrawal) => {
+            hasher.update([1]);
+            hasher.update(withdrawal.public_amount.to_be_bytes());
+            hasher.update(withdrawal.recipient);
+        }
+        None => hasher.update([0]),
+    }
+    match &outputs.external_nullifier {
+        Some(external_nullifier) => {
+            hasher.update([1]);
+            hasher.update(external_nullifier);
+        }
+        None => hasher.update([0]),
+    }
+    hasher.update(vkey_hash);
+    hasher.finalize().into()
+}
+
+impl ResponseSignature {
+    /// Sign a response with the operator's secret key.
+    pub fn sign(
+        operator_key: &[u8; 32],
+        request_hash: [u8; 32],
+        outputs: &PublicOutputs,
+        vkey_hash: &[u8; 32],
+    ) -> Result<Self, String> {
+        let signature = sign_message(operator_key, &response_digest(&request_hash, outputs, vkey_hash))?;
+        Ok(Self {
+            operator: crate::owner::owner_from_spending_key(operator_key)?.into(),
+            request_hash: request_hash.into(),
+            signature: signature.into(),
+        })
+    }
+
+    /// Check the signature is by `operator` over this response to the
+    /// request hashing to `request_hash`.
+    ///
+    /// # Errors
+    /// Fails if the signature is malformed, by another key, or for other
+    /// outputs, vkey or request.
+    pub fn verify(
+        &self,
+        operator: &[u8; 32],
+        request_hash: &[u8; 32],
+        outputs: &PublicOutputs,
+        vkey_hash: &[u8; 32],
+    ) -> Result<(), String> {
+        if self.operator.0 != *operator {
+            return Err(format!("Response is signed by operator {}, not {}", self.operator, Bytes32(*operator)));
+        }
+        if self.request_hash.0 != *request_hash {
+            return Err("Response answers a different request".to_string());
+        }
+        let digest = response_digest(request_hash, outputs, vkey_hash);
+        let signer = recover_ethereum_key(&digest, &self.signature.0).map_err(|e| format!("Invalid response signature: {}", e))?;
+        if signer != *operator {
+            return Err("Response signature doesn't match its contents".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sp1_types::Withdrawal;
+
+    const OPERATOR_KEY: [u8; 32] = [0x11; 32];
+
+    fn outputs() -> PublicOutputs {
+        PublicOutputs {
+            chain_id: 1,
+            old_root: [3; 32],
+            nullifiers: vec![[4; 32]],
+            output_commitments: vec![[5; 32], [6; 32]],
+            withdrawal: None,
+            external_nullifier: None,
+        }
+    }
+
+    #[test]
+    fn test_signed_response_verifies() {
+        let request = request_hash(br#"{"chainId":1}"#);
+        let signed = ResponseSignature::sign(&OPERATOR_KEY, request, &outputs(), &[9; 32]).unwrap();
+        let operator = crate::owner::owner_from_spending_key(&OPERATOR_KEY).unwrap();
+        signed.verify(&operator, &request, &outputs(), &[9; 32]).unwrap();
+
+        let json = serde_json::to_string(&signed).unwrap();
+        assert!(json.contains("\"requestHash\":\"0x"));
+        assert_eq!(serde_json::from_str::<ResponseSignature>(&json).unwrap(), signed);
+    }
+
+    #[test]
+    fn test_tampered_response_fails() {
+        let request = request_hash(b"request");
+        let signed = ResponseSignature::sign(&OPERATOR_KEY, request, &outputs(), &[9; 32]).unwrap();
+        let operator = signed.operator.0;
+
+        let mut swapped = outputs();
+        swapped.output_commitments.swap(0, 1);
+        assert!(signed.verify(&operator, &request, &swapped, &[9; 32]).is_err());
+
+        let mut withdrawn = outputs();
+        withdrawn.withdrawal = Some(Withdrawal { public_amount: 1, recipient: [1; 20].into() });
+        assert!(signed.verify(&operator, &request, &withdrawn, &[9; 32]).is_err());
+
+        assert!(signed.verify(&operator, &request, &outputs(), &[8; 32]).is_err());
+        assert!(signed.verify(&operator, &request_hash(b"other"), &outputs(), &[9; 32]).is_err());
+        assert!(signed.verify(&[7; 32], &request, &outputs(), &[9; 32]).unwrap_err().contains("operator"));
+
+        let mut forged = signed;
+        forged.operator = Bytes32([7; 32]);
+        assert!(forged.verify(&[7; 32], &request, &outputs(), &[9; 32]).is_err());
+    }
+}
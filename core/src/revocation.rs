@@ -0,0 +1,129 @@
+//! Revoked guest programs
+//!
+//! Once several circuit versions circulate, some must stop being used: a
+//! soundness bug in an old guest, or a version retired after a migration.
+//! A revocation list names them by vkey hash (what verifiers check) or by
+//! ELF sha256 (what `guest-elf.lock` records, known before any proving key
+//! setup). Provers refuse to prove with a revoked program and verifiers
+//! refuse proofs under a revoked vkey, whatever the chain still accepts.
+//!
+//! Lists are JSON (`{"revoked": [{"vkeyHash", "reason"}, ...]}`) and merge,
+//! so an operator can layer a published list with local entries.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hex::Bytes32;
+
+/// One revoked program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vkey_hash: Option<Bytes32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elf_sha256: Option<Bytes32>,
+    /// Shown when a proof is refused ("soundness bug in v3 range check")
+    pub reason: String,
+    /// Unix seconds, informational
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<u64>,
+}
+
+/// A set of revoked programs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationList {
+    #[serde(default)]
+    pub revoked: Vec<Revocation>,
+}
+
+impl RevocationList {
+    /// Reject entries that name no program (call after deserializing).
+    pub fn validate(&self) -> Result<(), String> {
+        match self.revoked.iter().position(|r| r.vkey_hash.is_none() && r.elf_sha256.is_none()) {
+            Some(i) => Err(format!("Revocation {} names neither a vkeyHash nor an elfSha256", i)),
+            None => Ok(()),
+        }
+    }
+
+    /// Add `other`'s entries, skipping exact duplicates.
+    pub fn merge(&mut self, other: RevocationList) {
+        for revocation in other.revoked {
+            if !self.revoked.contains(&revocation) {
+                self.revoked.push(revocation);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.revoked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+
+    /// The revocation covering `vkey_hash`, if any.
+    pub fn vkey_revocation(&self, vkey_hash: &[u8; 32]) -> Option<&Revocation> {
+        self.revoked.iter().find(|r| r.vkey_hash.is_some_and(|v| v.0 == *vkey_hash))
+    }
+
+    /// The revocation covering an ELF with this sha256, if any.
+    pub fn elf_revocation(&self, elf_sha256: &[u8; 32]) -> Option<&Revocation> {
+        self.revoked.iter().find(|r| r.elf_sha256.is_some_and(|e| e.0 == *elf_sha256))
+    }
+
+    /// Fail if `vkey_hash` is revoked.
+    pub fn check_vkey(&self, vkey_hash: &[u8; 32]) -> Result<(), String> {
+        match self.vkey_revocation(vkey_hash) {
+            Some(r) => Err(format!("Program vkey {} is revoked: {}", Bytes32(*vkey_hash), r.reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail if an ELF with this sha256 is revoked.
+    pub fn check_elf(&self, elf_sha256: &[u8; 32]) -> Result<(), String> {
+        match self.elf_revocation(elf_sha256) {
+            Some(r) => Err(format!("Guest ELF {} is revoked: {}", Bytes32(*elf_sha256), r.reason)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<RevocationList, String> {
+        let list: RevocationList = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        list.validate()?;
+        Ok(list)
+    }
+
+    #[test]
+    fn test_revoked_vkeys_and_elfs_refused() {
+        let mut list = parse(&format!(
+            r#"{{"revoked": [{{"vkeyHash": "{}", "reason": "range check bug", "revokedAt": 1700000000}}]}}"#,
+            Bytes32([1; 32])
+        ))
+        .unwrap();
+        assert!(list.check_vkey(&[1; 32]).unwrap_err().contains("range check bug"));
+        assert!(list.check_vkey(&[2; 32]).is_ok());
+        assert!(list.check_elf(&[1; 32]).is_ok());
+
+        let local = RevocationList {
+            revoked: vec![
+                Revocation { vkey_hash: None, elf_sha256: Some(Bytes32([3; 32])), reason: "retired".into(), revoked_at: None },
+                list.revoked[0].clone(),
+            ],
+        };
+        list.merge(local);
+        assert_eq!(list.len(), 2);
+        assert!(list.check_elf(&[3; 32]).unwrap_err().contains("retired"));
+    }
+
+    #[test]
+    fn test_entry_must_name_a_program() {
+        assert!(parse(r#"{"revoked": [{"reason": "?"}]}"#).unwrap_err().contains("neither"));
+        assert!(parse("{}").unwrap().is_empty());
+    }
+}
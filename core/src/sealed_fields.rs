@@ -0,0 +1,267 @@
+//! Requests with their witness fields sealed to the prover
+//!
+//! `delegated` seals a whole request, so the prover-server sees only
+//! `chainId` and `traceId`. An intermediary that also routes on the old
+//! root, the withdrawal or the transaction's shape can instead be given a
+//! `SealedFieldsRequest`: the request with every field that identifies or
+//! spends a note (notes, signatures, leaf indices and proofs) sealed to one
+//! of the prover's published keys. Only the host holding the keyring can
+//! open it, and does so in memory.
+//!
+//! Sealing is the `delegated` scheme (ECIES over secp256k1 with AES-256-GCM)
+//! to the same rotating keys, so a prover publishes one set of keys for
+//! both. The clear fields the host takes on trust (chain id, old root and
+//! the input and output counts) are bound as associated data, with the key
+//! id and format version, under a domain tag of their own.
+
+use serde::{Deserialize, Serialize};
+
+use crate::delegated::{seal_to, ProverKey, ProverKeyring};
+use crate::encryption::ViewPublicKey;
+use crate::hex::{Bytes32, Bytes65};
+use crate::nullifier_tree::NullifierTreeUpdate;
+use crate::proof_request::{NoteData, ProofRequest};
+use crate::sp1_types::Withdrawal;
+
+/// Format version of `SealedFields`.
+///
+/// 2: sealed to the `delegated` prover keys instead of a static X25519 key.
+pub const SEALED_FIELDS_VERSION: u8 = 2;
+
+/// Domain tag of the associated data, keeping these ciphertexts apart from
+/// whole sealed requests under the same keys.
+const SEALED_FIELDS_DOMAIN: &[u8] = b"ghostclaw-sealed-fields";
+
+/// The fields of a `ProofRequest` that identify or spend notes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WitnessFields {
+    pub input_notes: Vec<NoteData>,
+    pub output_notes: Vec<NoteData>,
+    pub nullifier_signatures: Vec<Bytes65>,
+    pub tx_signatures: Vec<Bytes65>,
+    pub input_indices: Vec<usize>,
+    pub input_proofs: Vec<Vec<Bytes32>>,
+    /// Sealed too: the proof paths narrow down the nullifiers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nullifier_tree: Option<NullifierTreeUpdate>,
+    /// Sealed too: they open the amount commitments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_amount_blindings: Vec<Bytes32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_amount_blindings: Vec<Bytes32>,
+}
+
+/// `WitnessFields` sealed to a prover key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SealedFields {
+    pub version: u8,
+    /// The prover key sealed to (see `delegated::ProverKey`)
+    pub key_id: String,
+    #[serde(with = "crate::hex::bytes")]
+    pub ephemeral_pubkey: ViewPublicKey,
+    #[serde(with = "crate::hex::bytes")]
+    pub nonce: [u8; 12],
+    #[serde(with = "crate::hex::bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+/// A proof request whose witness fields are sealed (see the module docs).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SealedFieldsRequest {
+    pub schema_version: u32,
+    pub old_root: Bytes32,
+    pub chain_id: u64,
+    #[serde(default)]
+    pub withdrawal: Option<Withdrawal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_nullifier: Option<Bytes32>,
+    /// In the clear so an intermediary can drop requests nobody waits for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    /// Shape, for routing by proving cost (the proof makes it public anyway)
+    pub input_count: usize,
+    pub output_count: usize,
+    pub sealed_fields: SealedFields,
+}
+
+impl SealedFieldsRequest {
+    /// Seal `request`'s witness fields to `key` (wallet side).
+    ///
+    /// # Errors
+    /// Fails if the key has expired or isn't a valid public key.
+    pub fn seal(request: &ProofRequest, key: &ProverKey, now: u64) -> Result<Self, String> {
+        let fields = WitnessFields {
+            input_notes: request.input_notes.clone(),
+            output_notes: request.output_notes.clone(),
+            nullifier_signatures: request.nullifier_signatures.clone(),
+            tx_signatures: request.tx_signatures.clone(),
+            input_indices: request.input_indices.clone(),
+            input_proofs: request.input_proofs.clone(),
+            nullifier_tree: request.nullifier_tree.clone(),
+            input_amount_blindings: request.input_amount_blindings.clone(),
+            output_amount_blindings: request.output_amount_blindings.clone(),
+        };
+        let plaintext = serde_json::to_vec(&fields).map_err(|e| format!("Serialize failed: {}", e))?;
+        let (input_count, output_count) = (request.input_notes.len(), request.output_notes.len());
+        let aad = associated_data(SEALED_FIELDS_VERSION, &key.key_id, request.chain_id, &request.old_root.0, input_count, output_count);
+        let sealed = seal_to(key, now, &plaintext, &aad)?;
+
+        Ok(Self {
+            schema_version: request.schema_version,
+            old_root: request.old_root,
+            chain_id: request.chain_id,
+            withdrawal: request.withdrawal,
+            external_nullifier: request.external_nullifier,
+            deadline_ms: request.deadline_ms,
+            trace_id: request.trace_id.clone(),
+            input_count,
+            output_count,
+            sealed_fields: SealedFields {
+                version: SEALED_FIELDS_VERSION,
+                key_id: key.key_id.clone(),
+                ephemeral_pubkey: sealed.ephemeral_pubkey,
+                nonce: sealed.nonce,
+                ciphertext: sealed.ciphertext,
+            },
+        })
+    }
+
+    /// Open the sealed fields with the prover's keyring (host side).
+    ///
+    /// # Errors
+    /// Fails on an unknown or expired key, tampering (including a changed
+    /// `chainId`, `oldRoot` or count), or sealed fields that aren't valid.
+    pub fn open(&self, keyring: &ProverKeyring, now: u64) -> Result<ProofRequest, String> {
+        let sealed = &self.sealed_fields;
+        if sealed.version != SEALED_FIELDS_VERSION {
+            return Err(format!("Unsupported sealed fields version {}", sealed.version));
+        }
+        let aad = associated_data(
+            sealed.version,
+            &sealed.key_id,
+            self.chain_id,
+            &self.old_root.0,
+            self.input_count,
+            self.output_count,
+        );
+        let plaintext =
+            keyring.decrypt(&sealed.key_id, &sealed.ephemeral_pubkey, &sealed.nonce, &sealed.ciphertext, &aad, now)?;
+        // Serde errors can quote the input, so don't pass them on
+        let fields: WitnessFields =
+            serde_json::from_slice(&plaintext).map_err(|_| "Sealed fields are not valid witness fields".to_string())?;
+        if fields.input_notes.len() != self.input_count || fields.output_notes.len() != self.output_count {
+            return Err(format!(
+                "Sealed fields hold {} inputs and {} outputs, but the request claims {} and {}",
+                fields.input_notes.len(),
+                fields.output_notes.len(),
+                self.input_count,
+                self.output_count
+            ));
+        }
+
+        Ok(ProofRequest {
+            schema_version: self.schema_version,
+            input_notes: fields.input_notes,
+            output_notes: fields.output_notes,
+            nullifier_signatures: fields.nullifier_signatures,
+            tx_signatures: fields.tx_signatures,
+            input_indices: fields.input_indices,
+            input_proofs: fields.input_proofs,
+            old_root: self.old_root,
+            chain_id: self.chain_id,
+            withdrawal: self.withdrawal,
+            external_nullifier: self.external_nullifier,
+            nullifier_tree: fields.nullifier_tree,
+            input_amount_blindings: fields.input_amount_blindings,
+            output_amount_blindings: fields.output_amount_blindings,
+            deadline_ms: self.deadline_ms,
+            trace_id: self.trace_id.clone(),
+        })
+    }
+}
+
+fn associated_data(
+    version: u8,
+    key_id: &str,
+    chain_id: u64,
+    old_root: &[u8; 32],
+    input_count: usize,
+    output_count: usize,
+) -> Vec<u8> {
+    let mut aad = SEALED_FIELDS_DOMAIN.to_vec();
+    aad.push(version);
+    aad.extend_from_slice(&chain_id.to_be_bytes());
+    aad.extend_from_slice(old_root);
+    aad.extend_from_slice(&(input_count as u64).to_be_bytes());
+    aad.extend_from_slice(&(output_count as u64).to_be_bytes());
+    aad.extend_from_slice(key_id.as_bytes());
+    aad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> ProofRequest {
+        serde_json::from_value(serde_json::json!({
+            "inputNotes": [{"amount": 5, "ownerPubkey": crate::hex::encode_hex(&[1; 32]), "blinding": crate::hex::encode_hex(&[2; 32])}],
+            "outputNotes": [], "nullifierSignatures": [crate::hex::encode_hex(&[4; 65])], "txSignatures": [],
+            "inputIndices": [7], "inputProofs": [[]], "oldRoot": crate::hex::encode_hex(&[3; 32]),
+            "chainId": 1, "traceId": "t-1", "withdrawal": {"publicAmount": 5, "recipient": crate::hex::encode_hex(&[9; 20])},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_seal_and_open_fields() {
+        let keyring = ProverKeyring::generate(100);
+        let sealed = SealedFieldsRequest::seal(&request(), keyring.current().unwrap(), 100).unwrap();
+
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(json.contains("\"chainId\":1") && json.contains("\"inputCount\":1"));
+        assert!(!json.contains(&crate::hex::encode_hex(&[2; 32])[2..]), "blinding leaked");
+        let sealed: SealedFieldsRequest = serde_json::from_str(&json).unwrap();
+
+        let opened = sealed.open(&keyring, 100).unwrap();
+        assert_eq!(opened.input_notes[0].blinding, Bytes32([2; 32]));
+        assert_eq!(opened.input_indices, vec![7]);
+        assert_eq!(opened.trace_id.as_deref(), Some("t-1"));
+        assert!(sealed.open(&ProverKeyring::generate(100), 100).unwrap_err().contains("Unknown prover key"));
+    }
+
+    #[test]
+    fn test_clear_fields_are_bound() {
+        let keyring = ProverKeyring::generate(100);
+        let sealed = SealedFieldsRequest::seal(&request(), keyring.current().unwrap(), 100).unwrap();
+
+        let mut rerouted = sealed.clone();
+        rerouted.chain_id = 2;
+        assert!(rerouted.open(&keyring, 100).unwrap_err().contains("tampered"));
+
+        let mut reshaped = sealed.clone();
+        reshaped.output_count = 3;
+        assert!(reshaped.open(&keyring, 100).unwrap_err().contains("tampered"));
+
+        // A whole-request ciphertext under the same key doesn't open as fields
+        let whole = crate::delegated::EncryptedRequest::seal(&request(), keyring.current().unwrap(), 100).unwrap();
+        let mut swapped = sealed;
+        swapped.sealed_fields.ephemeral_pubkey = whole.ephemeral_pubkey;
+        swapped.sealed_fields.nonce = whole.nonce;
+        swapped.sealed_fields.ciphertext = whole.ciphertext;
+        assert!(swapped.open(&keyring, 100).is_err());
+    }
+
+    #[test]
+    fn test_rotated_keys_open_until_they_expire() {
+        let mut keyring = ProverKeyring::generate(100);
+        let sealed = SealedFieldsRequest::seal(&request(), keyring.current().unwrap(), 100).unwrap();
+        keyring.rotate(200, 60);
+        assert!(sealed.open(&keyring, 259).is_ok());
+        assert!(sealed.open(&keyring, 260).unwrap_err().contains("expired"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Host-side signature recovery for a whole transaction at once
+//!
+//! `check_tx` recovers two signers per input (nullifier and tx signature),
+//! one after the other, and the host's determinism check recovers the
+//! nullifier signatures again. For a
+//! 16-input transaction that is dozens of secp256k1 recoveries on the
+//! request path before proving starts.
+//!
+//! k256 has no batch API for public key recovery (batch verification needs
+//! the keys up front, and recovering them is the point), so `RecoveredKeys`
+//! collects every (message, signature) pair a transaction needs, computing
+//! each message once, and recovers them across threads. The checks then
+//! look their signers up instead of recovering inline, so their results and
+//! error messages are the same as the sequential path's.
+//!
+//! The guest keeps calling `simulate_witness`, which recovers sequentially.
+
+use std::collections::HashMap;
+
+use crate::ledger::{recover_ethereum_key, simulate_witness_with, Ledger, TxSimulation};
+use crate::note::{commit, input_nullifier, Note, Nullifier};
+use crate::signatures::{
+    check_nullifier_determinism_with, input_nullifier_message, scoped_tx_message, tx_fee, tx_message,
+    withdrawal_tx_message, DeterminismEvidence,
+};
+use crate::sp1_types::Witness;
+
+type Recovery = Result<[u8; 32], &'static str>;
+
+/// Signers recovered ahead of the checks that need them.
+#[derive(Debug, Default, Clone)]
+pub struct RecoveredKeys {
+    keys: HashMap<([u8; 32], Vec<u8>), Recovery>,
+}
+
+impl RecoveredKeys {
+    /// Recover the signer of every (message hash, signature) pair, in
+    /// parallel when more than one thread is available.
+    pub fn recover_all(pairs: Vec<([u8; 32], Vec<u8>)>) -> Self {
+        let mut pairs = pairs;
+        pairs.sort_unstable();
+        pairs.dedup();
+
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(pairs.len());
+        let recover = |chunk: &[([u8; 32], Vec<u8>)]| -> Vec<Recovery> {
+            chunk.iter().map(|(msg, sig)| recover_ethereum_key(msg, sig)).collect()
+        };
+        let results: Vec<Recovery> = if threads <= 1 {
+            recover(&pairs)
+        } else {
+            let chunk_len = pairs.len().div_ceil(threads);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> =
+                    pairs.chunks(chunk_len).map(|chunk| scope.spawn(move || recover(chunk))).collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().expect("Signature recovery thread panicked"))
+                    .collect()
+            })
+        };
+
+        Self { keys: pairs.into_iter().zip(results).collect() }
+    }
+
+    /// Recover every signature `witness` carries, with each input's
+    /// nullifier and tx messages computed once.
+    pub fn for_witness(witness: &Witness) -> Self {
+        let output_commitments: Vec<[u8; 32]> = witness.output_notes.iter().map(commit).collect();
+        let public_amount = witness.withdrawal.map_or(0, |w| w.public_amount);
+        let fee = tx_fee(&witness.input_notes, &witness.output_notes)
+            .and_then(|fee| fee.checked_sub(public_amount))
+            .unwrap_or(0);
+
+        let mut pairs = Vec::with_capacity(witness.input_notes.len() * 2);
+        for (i, note) in witness.input_notes.iter().enumerate() {
+            let msg = input_nullifier_message(&commit(note), witness.external_nullifier.as_ref());
+            let Some(nullifier_sig) = witness.nullifier_signatures.get(i) else {
+                continue;
+            };
+            pairs.push((msg, nullifier_sig.clone()));
+
+            if let Some(tx_sig) = witness.tx_signatures.get(i) {
+                let nullifier = input_nullifier(note, nullifier_sig, witness.external_nullifier.as_ref());
+                let tx_msg = match (&witness.external_nullifier, &witness.withdrawal) {
+                    (Some(external_nullifier), _) => scoped_tx_message(&nullifier, external_nullifier),
+                    (None, Some(w)) => withdrawal_tx_message(&nullifier, fee, &output_commitments, w),
+                    (None, None) => tx_message(&nullifier, fee, &output_commitments),
+                };
+                pairs.push((tx_msg, tx_sig.clone()));
+            }
+        }
+        Self::recover_all(pairs)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The signer of `sig` over `msg_hash`, recovering it now if it wasn't
+    /// part of the batch.
+    pub fn recover(&self, msg_hash: &[u8], sig: &[u8]) -> Result<[u8; 32], &'static str> {
+        let cached = <[u8; 32]>::try_from(msg_hash)
+            .ok()
+            .and_then(|msg| self.keys.get(&(msg, sig.to_vec())));
+        match cached {
+            Some(recovery) => *recovery,
+            None => recover_ethereum_key(msg_hash, sig),
+        }
+    }
+
+    /// `signatures::check_nullifier_determinism` with these signers, in
+    /// `external_nullifier`'s context if set (as the witness's).
+    pub fn check_nullifier_determinism(
+        &self,
+        note: &Note,
+        external_nullifier: Option<&[u8; 32]>,
+        signature: &[u8],
+        evidence: DeterminismEvidence<'_>,
+    ) -> Result<Nullifier, String> {
+        check_nullifier_determinism_with(note, external_nullifier, signature, evidence, &|msg, sig| {
+            self.recover(msg, sig)
+        })
+    }
+
+    /// `ledger::simulate_witness` with these signers.
+    pub fn simulate_witness(&self, ledger: &mut Ledger, witness: &Witness, old_root: [u8; 32]) -> TxSimulation {
+        simulate_witness_with(ledger, witness, old_root, &|msg, sig| self.recover(msg, sig))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::simulate_witness;
+    use crate::note::compute_nullifier;
+    use crate::signatures::sign_message;
+    use crate::testing::{TestOwner, WitnessBuilder};
+
+    /// Signed witness spending `inputs` notes of 10, each with its own
+    /// owner, for a fee of 1; and its root.
+    fn signed_witness(inputs: usize) -> (Witness, [u8; 32]) {
+        let builder = (0..inputs).fold(WitnessBuilder::new(), |b, _| b.input(&TestOwner::random(), 10));
+        let (public_inputs, witness) = builder.output(&TestOwner::random(), 10 * inputs as u64 - 1).build().unwrap();
+        (witness, public_inputs.old_root)
+    }
+
+    #[test]
+    fn test_batched_simulation_matches_sequential() {
+        let (witness, root) = signed_witness(16);
+        let keys = RecoveredKeys::for_witness(&witness);
+        assert_eq!(keys.len(), 32);
+
+        let batched = keys.simulate_witness(&mut Ledger::new(), &witness, root);
+        let sequential = simulate_witness(&mut Ledger::new(), &witness, root);
+        assert!(batched.errors.is_empty() && batched.inputs.iter().all(|i| i.error.is_none()));
+        assert_eq!(format!("{:?}", batched), format!("{:?}", sequential));
+
+        // A bad signature fails the same way on both paths
+        let mut forged = witness.clone();
+        forged.tx_signatures[3] = forged.tx_signatures[4].clone();
+        let keys = RecoveredKeys::for_witness(&forged);
+        let batched = keys.simulate_witness(&mut Ledger::new(), &forged, root);
+        let sequential = simulate_witness(&mut Ledger::new(), &forged, root);
+        assert!(batched.inputs[3].error.is_some());
+        assert_eq!(format!("{:?}", batched), format!("{:?}", sequential));
+    }
+
+    #[test]
+    fn test_batched_determinism_check() {
+        let (witness, _) = signed_witness(3);
+        let keys = RecoveredKeys::for_witness(&witness);
+        for (note, sig) in witness.input_notes.iter().zip(&witness.nullifier_signatures) {
+            let nullifier = keys.check_nullifier_determinism(note, None, sig, DeterminismEvidence::None);
+            assert_eq!(nullifier.unwrap(), compute_nullifier(sig));
+        }
+        // Pairs outside the batch are still recovered
+        let other = sign_message(&[5; 32], &[1; 32]).unwrap();
+        assert_eq!(keys.recover(&[1; 32], &other), recover_ethereum_key(&[1; 32], &other));
+    }
+}
@@ -0,0 +1,311 @@
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+
+use crate::ct;
+use crate::ledger::{recover_ethereum_key, RecoverKey};
+use crate::note::{commit, compute_nullifier, input_nullifier, Note, Nullifier};
+use crate::sp1_types::Withdrawal;
+
+/// Half the secp256k1 group order; signatures with `s` above this are malleable.
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d,
+    0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Message hash signed to derive a note's nullifier: Keccak256(commitment).
+pub fn nullifier_message(commitment: &[u8; 32]) -> [u8; 32] {
+    Keccak256::digest(commitment).into()
+}
+
+/// Domain of `scoped_nullifier_message`.
+const SCOPED_NULLIFIER_DOMAIN: &[u8] = b"ghostclaw-scoped-nullifier-v1";
+
+/// Domain of `scoped_tx_message`.
+const SCOPED_TX_DOMAIN: &[u8] = b"ghostclaw-scoped-tx-v1";
+
+/// Message hash the owner signs to use a note in an application context:
+/// Keccak256(domain || external_nullifier || commitment).
+///
+/// A different message from `nullifier_message`, so the signature can't be
+/// replayed as a spend. It only authorizes: the scoped nullifier is keyed on
+/// the note (see `note::compute_scoped_nullifier`), since re-signing with
+/// another nonce would give a new nullifier if it were the signature's hash.
+pub fn scoped_nullifier_message(external_nullifier: &[u8; 32], commitment: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(SCOPED_NULLIFIER_DOMAIN);
+    hasher.update(external_nullifier);
+    hasher.update(commitment);
+    hasher.finalize().into()
+}
+
+/// The nullifier message for an input: `scoped_nullifier_message` in an
+/// application context, `nullifier_message` otherwise.
+pub fn input_nullifier_message(commitment: &[u8; 32], external_nullifier: Option<&[u8; 32]>) -> [u8; 32] {
+    match external_nullifier {
+        Some(external_nullifier) => scoped_nullifier_message(external_nullifier, commitment),
+        None => nullifier_message(commitment),
+    }
+}
+
+/// Message hash signed to use a note in an application context:
+/// Keccak256(domain || external_nullifier || nullifier).
+pub fn scoped_tx_message(nullifier: &Nullifier, external_nullifier: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(SCOPED_TX_DOMAIN);
+    hasher.update(external_nullifier);
+    hasher.update(nullifier);
+    hasher.finalize().into()
+}
+
+/// Message hash signed to authorize a transaction:
+/// Keccak256(nullifier || fee (u64 BE) || output_commitments...).
+///
+/// Every input carries its own tx signature, in input order, by that
+/// input's owner, over its own nullifier. Each one covers every output and
+/// the fee (see `tx_fee`), so an owner's consent doesn't carry over to a
+/// transaction that pays anyone else or leaves the relayer more.
+pub fn tx_message(nullifier: &Nullifier, fee: u64, output_commitments: &[[u8; 32]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(nullifier);
+    hasher.update(fee.to_be_bytes());
+    for commitment in output_commitments {
+        hasher.update(commitment);
+    }
+    hasher.finalize().into()
+}
+
+/// Message hash signed to authorize a withdrawal:
+/// Keccak256(nullifier || fee (u64 BE) || output_commitments... ||
+/// public_amount (u64 BE) || recipient (20 bytes)).
+///
+/// The `tx_message` preimage with the withdrawal appended, so the owners
+/// sign where the value goes. `fee` here is what's left after the outputs
+/// and `public_amount`.
+pub fn withdrawal_tx_message(
+    nullifier: &Nullifier,
+    fee: u64,
+    output_commitments: &[[u8; 32]],
+    withdrawal: &Withdrawal,
+) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(nullifier);
+    hasher.update(fee.to_be_bytes());
+    for commitment in output_commitments {
+        hasher.update(commitment);
+    }
+    hasher.update(withdrawal.public_amount.to_be_bytes());
+    hasher.update(withdrawal.recipient);
+    hasher.finalize().into()
+}
+
+/// The fee a transaction's tx signatures cover: input total minus output
+/// total. `None` if the outputs exceed the inputs (or the fee overflows u64).
+pub fn tx_fee(input_notes: &[Note], output_notes: &[Note]) -> Option<u64> {
+    let input_total: u128 = input_notes.iter().map(|n| n.amount as u128).sum();
+    let output_total: u128 = output_notes.iter().map(|n| n.amount as u128).sum();
+    input_total.checked_sub(output_total).and_then(|fee| u64::try_from(fee).ok())
+}
+
+/// Apply the Ethereum personal-message prefix to a 32-byte message hash.
+pub fn eth_signed_hash(msg_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"\x19Ethereum Signed Message:\n32");
+    hasher.update(msg_hash);
+    hasher.finalize().into()
+}
+
+/// Sign a message hash Ethereum-style: `[r (32), s (32), v (1)]` with v = 27/28.
+///
+/// k256 uses RFC 6979 nonces and normalizes to low-s, so the same key and
+/// message always produce the same signature (and therefore nullifier).
+pub fn sign_message(secret_key: &[u8; 32], msg_hash: &[u8; 32]) -> Result<[u8; 65], String> {
+    let signing_key = SigningKey::from_bytes(secret_key.into())
+        .map_err(|e| format!("Invalid secret key: {}", e))?;
+    let (signature, rec_id) = signing_key
+        .sign_prehash_recoverable(&eth_signed_hash(msg_hash))
+        .map_err(|e| format!("Signing failed: {}", e))?;
+
+    let mut out = [0u8; 65];
+    out[..64].copy_from_slice(&signature.to_bytes());
+    out[64] = rec_id.to_byte() + 27;
+    Ok(out)
+}
+
+/// Whether a 65-byte signature is in canonical low-s form.
+///
+/// A high-s signature is the malleated twin of a valid low-s one: it verifies
+/// for the same key and message but hashes to a different nullifier.
+pub fn is_low_s(signature: &[u8]) -> bool {
+    signature.len() == 65 && signature[32..64] <= SECP256K1_HALF_ORDER[..]
+}
+
+/// Whether a 65-byte signature carries the Ethereum recovery byte (27 or 28).
+///
+/// `recover_ethereum_key` also accepts the raw (0/1) and EIP-155 (>= 35)
+/// encodings of the same recovery id, and each hashes to a different
+/// nullifier.
+pub fn has_canonical_v(signature: &[u8]) -> bool {
+    signature.len() == 65 && matches!(signature[64], 27 | 28)
+}
+
+/// Whether a nullifier signature is the one encoding of its (r, s, recovery
+/// id) that `compute_nullifier` accepts: low-s and v in {27, 28}.
+pub fn is_canonical_nullifier_signature(signature: &[u8]) -> bool {
+    is_low_s(signature) && has_canonical_v(signature)
+}
+
+/// Evidence that a nullifier signature was produced deterministically.
+///
+/// Only the signing key counts: a deterministic signer produces the same
+/// bytes every time, so a "second signature" from the client is either the
+/// first one resubmitted or proof of a randomized signer, and the client can
+/// always pick the former.
+pub enum DeterminismEvidence<'a> {
+    /// The owner's secret key: the signature is re-derived and compared.
+    SigningKey(&'a [u8; 32]),
+    /// No evidence available; only canonical (low-s) form is enforced.
+    None,
+}
+
+/// Check that a nullifier signature is deterministic (RFC 6979) and canonical.
+///
+/// The nullifier is `Hash(signature)`, so it is only stable if the wallet
+/// signs deterministically. A randomized-ECDSA wallet would produce a new
+/// nullifier every time it spends the same note, making the note
+/// double-spendable (or unspendable once the first nullifier is lost).
+///
+/// # Returns
+/// The nullifier derived from `signature` if all checks pass.
+pub fn check_nullifier_determinism(
+    note: &Note,
+    signature: &[u8],
+    evidence: DeterminismEvidence<'_>,
+) -> Result<Nullifier, String> {
+    check_nullifier_determinism_with(note, None, signature, evidence, &recover_ethereum_key)
+}
+
+/// `check_nullifier_determinism` for a nullifier signature in the
+/// `external_nullifier` context (see `scoped_nullifier_message`).
+pub fn check_scoped_nullifier_determinism(
+    note: &Note,
+    external_nullifier: &[u8; 32],
+    signature: &[u8],
+    evidence: DeterminismEvidence<'_>,
+) -> Result<Nullifier, String> {
+    check_nullifier_determinism_with(note, Some(external_nullifier), signature, evidence, &recover_ethereum_key)
+}
+
+/// `check_nullifier_determinism`, in `external_nullifier`'s context if set,
+/// recovering signers with `recover`.
+pub(crate) fn check_nullifier_determinism_with(
+    note: &Note,
+    external_nullifier: Option<&[u8; 32]>,
+    signature: &[u8],
+    evidence: DeterminismEvidence<'_>,
+    recover: &RecoverKey,
+) -> Result<Nullifier, String> {
+    if signature.len() != 65 {
+        return Err(format!("Nullifier signature must be 65 bytes, got {}", signature.len()));
+    }
+    if !is_low_s(signature) {
+        return Err("Nullifier signature is not in low-s form (malleable)".to_string());
+    }
+    if !has_canonical_v(signature) {
+        return Err(format!(
+            "Nullifier signature recovery byte must be 27 or 28, got {} (malleable)",
+            signature[64]
+        ));
+    }
+
+    let msg_hash = input_nullifier_message(&commit(note), external_nullifier);
+    let signer = recover(&msg_hash, signature)
+        .map_err(|e| format!("Nullifier signature recovery failed: {}", e))?;
+    if !ct::eq(&signer, &note.owner_pubkey) {
+        return Err("Nullifier signature is not from the note owner".to_string());
+    }
+
+    match evidence {
+        DeterminismEvidence::SigningKey(secret_key) => {
+            let rederived = sign_message(secret_key, &msg_hash)?;
+            if !ct::eq(&compute_nullifier(&rederived), &compute_nullifier(signature)) {
+                return Err(
+                    "Nullifier signature is not deterministic: re-signing with the owner key \
+                     produced a different nullifier"
+                        .to_string(),
+                );
+            }
+        }
+        DeterminismEvidence::None => {}
+    }
+
+    Ok(input_nullifier(note, signature, external_nullifier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner_note(secret: &[u8; 32]) -> Note {
+        let key = SigningKey::from_bytes(secret.into()).unwrap();
+        let encoded = key.verifying_key().to_encoded_point(true);
+        let mut owner = [0u8; 32];
+        owner.copy_from_slice(&encoded.as_bytes()[1..]);
+        Note::new(100, owner, [9; 32])
+    }
+
+    #[test]
+    fn test_signing_is_deterministic() {
+        let secret = [0x11u8; 32];
+        let msg = nullifier_message(&[3u8; 32]);
+        assert_eq!(sign_message(&secret, &msg).unwrap(), sign_message(&secret, &msg).unwrap());
+    }
+
+    #[test]
+    fn test_determinism_check_with_key() {
+        let secret = [0x22u8; 32];
+        let note = owner_note(&secret);
+        let sig = sign_message(&secret, &nullifier_message(&commit(&note))).unwrap();
+
+        let from_key = check_nullifier_determinism(&note, &sig, DeterminismEvidence::SigningKey(&secret));
+        assert_eq!(from_key.unwrap(), compute_nullifier(&sig));
+    }
+
+    #[test]
+    fn test_high_s_signature_rejected() {
+        let secret = [0x33u8; 32];
+        let note = owner_note(&secret);
+        let mut sig = sign_message(&secret, &nullifier_message(&commit(&note))).unwrap();
+        sig[32] = 0xff;
+
+        let result = check_nullifier_determinism(&note, &sig, DeterminismEvidence::None);
+        assert!(result.unwrap_err().contains("low-s"));
+    }
+
+    #[test]
+    fn test_non_canonical_v_rejected() {
+        let secret = [0x66u8; 32];
+        let note = owner_note(&secret);
+        let sig = sign_message(&secret, &nullifier_message(&commit(&note))).unwrap();
+
+        // Same recovery id, so the owner still recovers, but a new nullifier
+        for flipped in [sig[64] - 27, sig[64] + 8] {
+            let mut malleated = sig;
+            malleated[64] = flipped;
+            let result = check_nullifier_determinism(&note, &malleated, DeterminismEvidence::None);
+            assert!(result.unwrap_err().contains("27 or 28"));
+        }
+    }
+
+    #[test]
+    fn test_wrong_key_evidence_rejected() {
+        let secret = [0x44u8; 32];
+        let note = owner_note(&secret);
+        let sig = sign_message(&secret, &nullifier_message(&commit(&note))).unwrap();
+
+        let other_key = [0x55u8; 32];
+        let result = check_nullifier_determinism(&note, &sig, DeterminismEvidence::SigningKey(&other_key));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,438 @@
+//! Signing abstraction for witness preparation
+//!
+//! A spend needs two signatures per input (nullifier and tx, see
+//! `signatures`), both Ethereum personal-message signatures over a 32-byte
+//! digest. `Signer` is what `prepare` asks for them, so the key can stay in
+//! a hardware wallet: the digests go to the device and only the signatures
+//! come back into the request.
+//!
+//! - `KeySigner`: a raw spending key, signed with k256 (what the seed-based
+//!   `prepare_transaction` uses)
+//! - `LedgerSigner`: the Ledger Ethereum app, over an `ApduTransport`
+//! - `TrezorSigner`: Trezor's Ethereum messages, over a `TrezorTransport`
+//!
+//! The transports are implemented by the wallet (USB HID, WebHID, Bluetooth,
+//! a bridge daemon), like `prepare::ProofSource`. Both devices sign with
+//! RFC 6979 nonces and low-s, which the nullifier needs to be stable; the
+//! signatures are still checked (`sign_checked`) since a device on the
+//! wrong derivation path or firmware signs for a key that owns nothing.
+
+use crate::ledger::recover_ethereum_key;
+use crate::owner::{owner_from_public_key, owner_from_spending_key};
+use crate::signatures::{is_low_s, sign_message};
+
+/// Default Ethereum derivation path (first account).
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// Signs 32-byte digests for one owner.
+pub trait Signer {
+    /// Owner pubkey of the signing key (see `owner`).
+    fn owner(&self) -> Result<[u8; 32], String>;
+
+    /// Ethereum personal-message signature over `msg_hash`, as
+    /// `signatures::sign_message` produces it: `[r (32), s (32), v (1)]`.
+    fn sign_hash(&self, msg_hash: &[u8; 32]) -> Result<[u8; 65], String>;
+}
+
+/// Sign `msg_hash` and check the signature is canonical and from `owner`.
+pub fn sign_checked(signer: &impl Signer, owner: &[u8; 32], msg_hash: &[u8; 32]) -> Result<[u8; 65], String> {
+    let signature = signer.sign_hash(msg_hash)?;
+    if !is_low_s(&signature) {
+        return Err("Signer returned a high-s (malleable) signature".to_string());
+    }
+    let signer_owner =
+        recover_ethereum_key(msg_hash, &signature).map_err(|e| format!("Signer returned an invalid signature: {}", e))?;
+    if !crate::ct::eq(&signer_owner, owner) {
+        return Err(format!(
+            "Signer signed for owner {}, expected {}",
+            crate::hex::encode_hex(&signer_owner),
+            crate::hex::encode_hex(owner)
+        ));
+    }
+    Ok(signature)
+}
+
+/// Parse a BIP-32 path like `m/44'/60'/0'/0/0` (`'` or `h` marks hardened).
+pub fn parse_derivation_path(path: &str) -> Result<Vec<u32>, String> {
+    let rest = path.trim().strip_prefix("m/").ok_or_else(|| format!("Derivation path must start with m/: {}", path))?;
+    rest.split('/')
+        .map(|part| {
+            let (index, hardened) = match part.strip_suffix('\'').or_else(|| part.strip_suffix('h')) {
+                Some(index) => (index, true),
+                None => (part, false),
+            };
+            let index: u32 = index.parse().map_err(|_| format!("Invalid path component {:?} in {}", part, path))?;
+            if index >= HARDENED {
+                return Err(format!("Path component {} out of range in {}", index, path));
+            }
+            Ok(if hardened { index | HARDENED } else { index })
+        })
+        .collect()
+}
+
+/// Move a device's `v` to 27/28 and check the length.
+fn ethereum_signature(r_s: &[u8], v: u8) -> Result<[u8; 65], String> {
+    if r_s.len() != 64 {
+        return Err(format!("Device returned a {}-byte signature", r_s.len() + 1));
+    }
+    let mut signature = [0u8; 65];
+    signature[..64].copy_from_slice(r_s);
+    signature[64] = if v < 27 { v + 27 } else { v };
+    Ok(signature)
+}
+
+// =============================================================================
+// Raw key
+// =============================================================================
+
+/// A spending key held in memory.
+pub struct KeySigner {
+    spending_key: [u8; 32],
+}
+
+impl KeySigner {
+    /// # Errors
+    /// Fails if `spending_key` isn't a valid secp256k1 scalar.
+    pub fn new(spending_key: [u8; 32]) -> Result<Self, String> {
+        owner_from_spending_key(&spending_key)?;
+        Ok(Self { spending_key })
+    }
+}
+
+impl Signer for KeySigner {
+    fn owner(&self) -> Result<[u8; 32], String> {
+        owner_from_spending_key(&self.spending_key)
+    }
+
+    fn sign_hash(&self, msg_hash: &[u8; 32]) -> Result<[u8; 65], String> {
+        sign_message(&self.spending_key, msg_hash)
+    }
+}
+
+// =============================================================================
+// Ledger
+// =============================================================================
+
+/// Exchanges APDUs with a Ledger device.
+pub trait ApduTransport {
+    /// Send one command APDU; returns the response data followed by the
+    /// 2-byte status word.
+    fn exchange(&self, apdu: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+const LEDGER_CLA: u8 = 0xe0;
+const LEDGER_INS_GET_PUBLIC_KEY: u8 = 0x02;
+const LEDGER_INS_SIGN_PERSONAL_MESSAGE: u8 = 0x08;
+const LEDGER_SW_OK: u16 = 0x9000;
+const LEDGER_SW_DENIED: u16 = 0x6985;
+
+/// The Ledger Ethereum app at a derivation path.
+pub struct LedgerSigner<T> {
+    transport: T,
+    path: Vec<u32>,
+}
+
+impl<T: ApduTransport> LedgerSigner<T> {
+    pub fn new(transport: T, path: &str) -> Result<Self, String> {
+        let path = parse_derivation_path(path)?;
+        if path.len() > 10 {
+            return Err("Ledger derivation paths have at most 10 components".to_string());
+        }
+        Ok(Self { transport, path })
+    }
+
+    fn encoded_path(&self) -> Vec<u8> {
+        let mut data = vec![self.path.len() as u8];
+        for index in &self.path {
+            data.extend_from_slice(&index.to_be_bytes());
+        }
+        data
+    }
+
+    fn exchange(&self, ins: u8, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut apdu = vec![LEDGER_CLA, ins, 0x00, 0x00, data.len() as u8];
+        apdu.extend_from_slice(data);
+        let mut response = self.transport.exchange(&apdu)?;
+        if response.len() < 2 {
+            return Err("Ledger returned a truncated response".to_string());
+        }
+        let status = response.split_off(response.len() - 2);
+        match u16::from_be_bytes([status[0], status[1]]) {
+            LEDGER_SW_OK => Ok(response),
+            LEDGER_SW_DENIED => Err("Rejected on the Ledger".to_string()),
+            sw => Err(format!("Ledger error 0x{:04x} (is the Ethereum app open?)", sw)),
+        }
+    }
+}
+
+impl<T: ApduTransport> Signer for LedgerSigner<T> {
+    fn owner(&self) -> Result<[u8; 32], String> {
+        // pubkey length || uncompressed pubkey || address length || address
+        let response = self.exchange(LEDGER_INS_GET_PUBLIC_KEY, &self.encoded_path())?;
+        let len = *response.first().ok_or("Ledger returned no public key")? as usize;
+        let public_key = response.get(1..1 + len).ok_or("Ledger returned a truncated public key")?;
+        owner_from_public_key(public_key)
+    }
+
+    fn sign_hash(&self, msg_hash: &[u8; 32]) -> Result<[u8; 65], String> {
+        // The message fits one APDU: at most 1 + 40 + 4 + 32 bytes
+        let mut data = self.encoded_path();
+        data.extend_from_slice(&(msg_hash.len() as u32).to_be_bytes());
+        data.extend_from_slice(msg_hash);
+        // v || r || s
+        let response = self.exchange(LEDGER_INS_SIGN_PERSONAL_MESSAGE, &data)?;
+        match response.split_first() {
+            Some((&v, r_s)) => ethereum_signature(r_s, v),
+            None => Err("Ledger returned no signature".to_string()),
+        }
+    }
+}
+
+// =============================================================================
+// Trezor
+// =============================================================================
+
+/// Exchanges protobuf messages with a Trezor device.
+pub trait TrezorTransport {
+    /// Send one message; returns the reply's type and payload.
+    fn call(&self, message_type: u16, payload: &[u8]) -> Result<(u16, Vec<u8>), String>;
+}
+
+const TREZOR_FAILURE: u16 = 3;
+const TREZOR_PIN_MATRIX_REQUEST: u16 = 18;
+const TREZOR_BUTTON_REQUEST: u16 = 26;
+const TREZOR_BUTTON_ACK: u16 = 27;
+const TREZOR_PASSPHRASE_REQUEST: u16 = 41;
+const TREZOR_ETHEREUM_SIGN_MESSAGE: u16 = 64;
+const TREZOR_ETHEREUM_MESSAGE_SIGNATURE: u16 = 66;
+const TREZOR_ETHEREUM_GET_PUBLIC_KEY: u16 = 450;
+const TREZOR_ETHEREUM_PUBLIC_KEY: u16 = 451;
+
+/// Trezor's Ethereum support at a derivation path.
+pub struct TrezorSigner<T> {
+    transport: T,
+    path: Vec<u32>,
+}
+
+impl<T: TrezorTransport> TrezorSigner<T> {
+    pub fn new(transport: T, path: &str) -> Result<Self, String> {
+        Ok(Self { transport, path: parse_derivation_path(path)? })
+    }
+
+    /// Send `message_type`, confirming button requests, and expect `reply`.
+    fn call(&self, message_type: u16, payload: &[u8], reply: u16) -> Result<Vec<u8>, String> {
+        let (mut reply_type, mut reply_payload) = self.transport.call(message_type, payload)?;
+        while reply_type == TREZOR_BUTTON_REQUEST {
+            (reply_type, reply_payload) = self.transport.call(TREZOR_BUTTON_ACK, &[])?;
+        }
+        match reply_type {
+            t if t == reply => Ok(reply_payload),
+            TREZOR_FAILURE => {
+                let message = protobuf::field(&reply_payload, 2).map(String::from_utf8_lossy).unwrap_or_default();
+                Err(format!("Trezor failure: {}", message))
+            }
+            TREZOR_PIN_MATRIX_REQUEST | TREZOR_PASSPHRASE_REQUEST => {
+                Err("Trezor is locked; unlock it before signing".to_string())
+            }
+            other => Err(format!("Unexpected Trezor message type {}", other)),
+        }
+    }
+
+    fn encoded_path(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for index in &self.path {
+            protobuf::put_varint_field(&mut payload, 1, *index as u64);
+        }
+        payload
+    }
+}
+
+impl<T: TrezorTransport> Signer for TrezorSigner<T> {
+    fn owner(&self) -> Result<[u8; 32], String> {
+        // EthereumPublicKey { node: HDNodeType { public_key = 6 } = 1 }
+        let reply = self.call(TREZOR_ETHEREUM_GET_PUBLIC_KEY, &self.encoded_path(), TREZOR_ETHEREUM_PUBLIC_KEY)?;
+        let node = protobuf::field(&reply, 1).ok_or("Trezor returned no public key")?;
+        owner_from_public_key(protobuf::field(node, 6).ok_or("Trezor returned no public key")?)
+    }
+
+    fn sign_hash(&self, msg_hash: &[u8; 32]) -> Result<[u8; 65], String> {
+        let mut payload = self.encoded_path();
+        protobuf::put_bytes_field(&mut payload, 2, msg_hash);
+        // EthereumMessageSignature { signature = 2: r || s || v }
+        let reply = self.call(TREZOR_ETHEREUM_SIGN_MESSAGE, &payload, TREZOR_ETHEREUM_MESSAGE_SIGNATURE)?;
+        match protobuf::field(&reply, 2) {
+            Some([r_s @ .., v]) => ethereum_signature(r_s, *v),
+            _ => Err("Trezor returned no signature".to_string()),
+        }
+    }
+}
+
+/// The few protobuf encodings the Trezor messages above need.
+mod protobuf {
+    fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    pub fn put_varint_field(out: &mut Vec<u8>, field: u32, value: u64) {
+        put_varint(out, (field as u64) << 3);
+        put_varint(out, value);
+    }
+
+    pub fn put_bytes_field(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+        put_varint(out, ((field as u64) << 3) | 2);
+        put_varint(out, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+    }
+
+    fn varint(input: &mut &[u8]) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = input.split_first()?;
+            *input = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// The first length-delimited `field` in `message`.
+    pub fn field(message: &[u8], field: u32) -> Option<&[u8]> {
+        let mut input = message;
+        while !input.is_empty() {
+            let key = varint(&mut input)?;
+            let len = match key & 7 {
+                0 => {
+                    varint(&mut input)?;
+                    continue;
+                }
+                1 => 8,
+                2 => usize::try_from(varint(&mut input)?).ok()?,
+                5 => 4,
+                _ => return None,
+            };
+            let (value, rest) = (input.get(..len)?, input.get(len..)?);
+            if key >> 3 == field as u64 && key & 7 == 2 {
+                return Some(value);
+            }
+            input = rest;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    const KEY: [u8; 32] = [0x42; 32];
+
+    fn public_key(compressed: bool) -> Vec<u8> {
+        let key = SigningKey::from_bytes((&KEY).into()).unwrap();
+        key.verifying_key().to_encoded_point(compressed).as_bytes().to_vec()
+    }
+
+    /// A Ledger Ethereum app holding `KEY` at every path.
+    struct MockLedger;
+
+    impl ApduTransport for MockLedger {
+        fn exchange(&self, apdu: &[u8]) -> Result<Vec<u8>, String> {
+            let data = &apdu[5..];
+            assert_eq!(apdu[4] as usize, data.len());
+            let path_len = 1 + 4 * data[0] as usize;
+            let mut response = match apdu[1] {
+                LEDGER_INS_GET_PUBLIC_KEY => {
+                    let mut response = vec![65];
+                    response.extend(public_key(false));
+                    response.extend([40; 41]);
+                    response
+                }
+                LEDGER_INS_SIGN_PERSONAL_MESSAGE => {
+                    let message = &data[path_len + 4..];
+                    let signature = sign_message(&KEY, message.try_into().unwrap()).unwrap();
+                    let mut response = vec![signature[64]];
+                    response.extend_from_slice(&signature[..64]);
+                    response
+                }
+                _ => return Ok(vec![0x6d, 0x00]),
+            };
+            response.extend_from_slice(&LEDGER_SW_OK.to_be_bytes());
+            Ok(response)
+        }
+    }
+
+    /// A Trezor holding `KEY` that asks for a button press before signing.
+    struct MockTrezor {
+        pending: std::cell::RefCell<Option<Vec<u8>>>,
+    }
+
+    impl TrezorTransport for MockTrezor {
+        fn call(&self, message_type: u16, payload: &[u8]) -> Result<(u16, Vec<u8>), String> {
+            match message_type {
+                TREZOR_ETHEREUM_GET_PUBLIC_KEY => {
+                    let mut node = Vec::new();
+                    protobuf::put_varint_field(&mut node, 1, 5);
+                    protobuf::put_bytes_field(&mut node, 6, &public_key(true));
+                    let mut reply = Vec::new();
+                    protobuf::put_bytes_field(&mut reply, 1, &node);
+                    Ok((TREZOR_ETHEREUM_PUBLIC_KEY, reply))
+                }
+                TREZOR_ETHEREUM_SIGN_MESSAGE => {
+                    let message = protobuf::field(payload, 2).unwrap();
+                    let signature = sign_message(&KEY, message.try_into().unwrap()).unwrap();
+                    *self.pending.borrow_mut() = Some(signature.to_vec());
+                    Ok((TREZOR_BUTTON_REQUEST, Vec::new()))
+                }
+                TREZOR_BUTTON_ACK => {
+                    let mut reply = Vec::new();
+                    protobuf::put_bytes_field(&mut reply, 2, &self.pending.borrow_mut().take().unwrap());
+                    protobuf::put_bytes_field(&mut reply, 3, b"0x0000000000000000000000000000000000000000");
+                    Ok((TREZOR_ETHEREUM_MESSAGE_SIGNATURE, reply))
+                }
+                _ => {
+                    let mut reply = Vec::new();
+                    protobuf::put_bytes_field(&mut reply, 2, b"Unknown message");
+                    Ok((TREZOR_FAILURE, reply))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_devices_sign_like_the_raw_key() {
+        let raw = KeySigner::new(KEY).unwrap();
+        let ledger = LedgerSigner::new(MockLedger, DEFAULT_DERIVATION_PATH).unwrap();
+        let trezor = TrezorSigner::new(MockTrezor { pending: Default::default() }, DEFAULT_DERIVATION_PATH).unwrap();
+        let owner = raw.owner().unwrap();
+        let digest = [7u8; 32];
+
+        assert_eq!(ledger.owner().unwrap(), owner);
+        assert_eq!(trezor.owner().unwrap(), owner);
+        let expected = sign_checked(&raw, &owner, &digest).unwrap();
+        assert_eq!(sign_checked(&ledger, &owner, &digest).unwrap(), expected);
+        assert_eq!(sign_checked(&trezor, &owner, &digest).unwrap(), expected);
+
+        let other = KeySigner::new([0x43; 32]).unwrap();
+        assert!(sign_checked(&other, &owner, &digest).unwrap_err().contains("expected"));
+    }
+
+    #[test]
+    fn test_parse_derivation_path() {
+        assert_eq!(
+            parse_derivation_path(DEFAULT_DERIVATION_PATH).unwrap(),
+            vec![44 | HARDENED, 60 | HARDENED, HARDENED, 0, 0]
+        );
+        assert_eq!(parse_derivation_path("m/44h/60h/1h").unwrap(), vec![44 | HARDENED, 60 | HARDENED, 1 | HARDENED]);
+        assert!(parse_derivation_path("44'/60'").is_err());
+        assert!(parse_derivation_path("m/44'/x").is_err());
+        assert!(parse_derivation_path("m/2147483648").is_err());
+    }
+}
@@ -1,6 +1,13 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
-use crate::merkle::MerkleProof;
-use crate::note::Note;
+use crate::hex::Bytes20;
+use crate::input_order::{check_input_order, input_order, is_identity, permute};
+use crate::merkle::{MerkleProof, TREE_HEIGHT, ZEROS};
+use crate::note::{Note, NoteVersion, ACCEPTED_NOTE_VERSIONS};
+use crate::nullifier_tree::NullifierTreeUpdate;
 
 /// Public inputs that the chain/host provides to the SP1 program.
 ///
@@ -19,12 +26,24 @@ pub struct PublicInputs {
     /// This must match `currentRoot` on the Ethereum contract.
     /// Ensures the transaction is built against the correct state.
     pub old_root: [u8; 32],
+
+    /// Chain the proof is for; committed in the public values and checked
+    /// against `block.chainid` by the ledger, so a proof can't be replayed
+    /// on another deployment with the same root.
+    #[serde(default)]
+    pub chain_id: u64,
 }
 
 impl PublicInputs {
     /// Create new public inputs with the given old root.
     pub fn new(old_root: [u8; 32]) -> Self {
-        Self { old_root }
+        Self { old_root, chain_id: 0 }
+    }
+
+    /// Bind the proof to `chain_id`.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
     }
 
     /// Check if this represents an empty tree state.
@@ -33,6 +52,63 @@ impl PublicInputs {
     }
 }
 
+/// Value a transaction pays out of the shielded pool.
+///
+/// Committed in the public values (see `public_values`), and covered by
+/// every tx signature (see `signatures::withdrawal_tx_message`), so neither
+/// the amount nor the recipient can be changed after the owners sign. The
+/// ledger pays `public_amount` to `recipient` from its balance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    pub public_amount: u64,
+    pub recipient: Bytes20,
+}
+
+/// Magic prefix of `GuestInput`. A host still writing the old two-value
+/// layout (public inputs, then witness) puts the old root here, so the
+/// guest rejects it instead of misreading the witness.
+pub const GUEST_INPUT_MAGIC: [u8; 4] = *b"GCIN";
+
+/// Layout version of `GuestInput`. SP1 stdin is bincode, which encodes
+/// fields by position and ignores `#[serde(default)]`: bump this whenever
+/// `GuestInput`, `PublicInputs` or `Witness` gains, loses or reorders a
+/// field, so a host and guest built from different trees fail with a
+/// version mismatch rather than a garbled witness.
+pub const GUEST_INPUT_VERSION: u32 = 4;
+
+/// Everything the host gives the transaction guest, written and read as
+/// one value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestInput {
+    pub magic: [u8; 4],
+    pub version: u32,
+    pub public_inputs: PublicInputs,
+    pub witness: Witness,
+}
+
+impl GuestInput {
+    /// Envelope for the current layout version.
+    pub fn new(public_inputs: PublicInputs, witness: Witness) -> Self {
+        Self { magic: GUEST_INPUT_MAGIC, version: GUEST_INPUT_VERSION, public_inputs, witness }
+    }
+
+    /// The public inputs and witness, if the envelope is one this build
+    /// reads.
+    pub fn open(self) -> Result<(PublicInputs, Witness), String> {
+        if self.magic != GUEST_INPUT_MAGIC {
+            return Err("Guest input is not a GuestInput envelope (host and guest out of date?)".to_string());
+        }
+        if self.version != GUEST_INPUT_VERSION {
+            return Err(format!(
+                "Guest input version {} doesn't match this build's {}",
+                self.version, GUEST_INPUT_VERSION
+            ));
+        }
+        Ok((self.public_inputs, self.witness))
+    }
+}
+
 /// Private witness that only the prover (SP1) sees.
 ///
 /// # Privacy Model
@@ -69,6 +145,8 @@ pub struct Witness {
     /// Used to:
     /// - Compute nullifiers (nullifier = hash(spend_secret, index))
     /// - Verify Merkle proofs
+    ///
+    /// Ascending: inputs are in canonical order (see `input_order`).
     pub input_indices: Vec<usize>,
 
     /// Merkle proofs proving each input note exists in the tree.
@@ -85,7 +163,8 @@ pub struct Witness {
 
     /// Signatures used to authorize the transaction (Anti-Theft).
     ///
-    /// Signs the transaction hash (nullifier + outputs).
+    /// One per input, by that input's owner, over
+    /// `signatures::tx_message(nullifier, fee, output_commitments)`.
     /// Format: 65 bytes [r (32), s (32), v (1)]
     pub tx_signatures: Vec<Vec<u8>>,
 
@@ -95,6 +174,39 @@ pub struct Witness {
     /// (including blinding) remains private.
     pub output_notes: Vec<Note>,
 
+    /// Value leaving the pool, if this is a withdrawal. Required when there
+    /// are no outputs: a transaction that only burns would otherwise hand
+    /// the whole input value to the relayer as fee.
+    #[serde(default)]
+    pub withdrawal: Option<Withdrawal>,
+
+    /// Application context (an airdrop, a poll, a rate-limit epoch) the
+    /// inputs are used in instead of spent.
+    ///
+    /// When set, each nullifier signature is over
+    /// `signatures::scoped_nullifier_message(external_nullifier, commitment)`
+    /// and each nullifier is `note::compute_scoped_nullifier`, so a note gets
+    /// exactly one nullifier per context however it is signed, unlinkable to
+    /// its spend nullifier and to its nullifiers in other contexts. Tx signatures are
+    /// over `scoped_tx_message`. Nothing is spent, so the witness has no
+    /// outputs or withdrawal (see `validate_structure`).
+    #[serde(default)]
+    pub external_nullifier: Option<[u8; 32]>,
+
+    /// Non-membership proofs of the nullifiers in the ledger's nullifier
+    /// tree (SMT mode; see `nullifier_tree`). Only read by guests built with
+    /// the `nullifier-tree` feature, which require it.
+    #[serde(default)]
+    pub nullifier_tree: Option<NullifierTreeUpdate>,
+
+    /// Blinding of each input's and output's amount commitment, in input
+    /// and output order (hidden-amounts mode; see `amount_commitment`).
+    /// Independent of the notes' own blindings; empty otherwise.
+    #[serde(default)]
+    pub input_amount_blindings: Vec<[u8; 32]>,
+    #[serde(default)]
+    pub output_amount_blindings: Vec<[u8; 32]>,
+
     // =========================================================================
     // PRECOMPUTED VALUES (Performance Optimization)
     // These are computed on the host to avoid expensive operations inside zkVM.
@@ -147,6 +259,11 @@ impl Witness {
             nullifier_signatures,
             tx_signatures,
             output_notes,
+            withdrawal: None,
+            external_nullifier: None,
+            nullifier_tree: None,
+            input_amount_blindings: Vec::new(),
+            output_amount_blindings: Vec::new(),
             precomputed_nullifiers: Vec::new(),
             precomputed_input_commitments: Vec::new(),
             precomputed_output_commitments: Vec::new(),
@@ -168,6 +285,11 @@ impl Witness {
             nullifier_signatures,
             tx_signatures,
             output_notes,
+            withdrawal: None,
+            external_nullifier: None,
+            nullifier_tree: None,
+            input_amount_blindings: Vec::new(),
+            output_amount_blindings: Vec::new(),
             precomputed_nullifiers: Vec::new(),
             precomputed_input_commitments: Vec::new(),
             precomputed_output_commitments: Vec::new(),
@@ -194,12 +316,61 @@ impl Witness {
             nullifier_signatures,
             tx_signatures,
             output_notes,
+            withdrawal: None,
+            external_nullifier: None,
+            nullifier_tree: None,
+            input_amount_blindings: Vec::new(),
+            output_amount_blindings: Vec::new(),
             precomputed_nullifiers,
             precomputed_input_commitments,
             precomputed_output_commitments,
         }
     }
 
+    /// Pay `withdrawal` out of the pool.
+    pub fn with_withdrawal(mut self, withdrawal: Withdrawal) -> Self {
+        self.withdrawal = Some(withdrawal);
+        self
+    }
+
+    /// Use the inputs in the `external_nullifier` context instead of
+    /// spending them.
+    pub fn with_external_nullifier(mut self, external_nullifier: [u8; 32]) -> Self {
+        self.external_nullifier = Some(external_nullifier);
+        self
+    }
+
+    /// Put the inputs in canonical order (see `input_order`), moving each
+    /// one's proofs, signatures and precomputed values with it.
+    ///
+    /// # Errors
+    /// Fails if they need reordering and a `nullifier_tree` update (built in
+    /// input order) is attached, or on mismatched input lengths.
+    pub fn canonicalize_inputs(&mut self) -> Result<(), String> {
+        if self.input_indices.len() != self.input_notes.len() {
+            return Err(format!(
+                "Mismatched input lengths: {} notes vs {} indices",
+                self.input_notes.len(),
+                self.input_indices.len()
+            ));
+        }
+        let order = input_order(&self.input_indices, |i| crate::note::commit(&self.input_notes[i]));
+        if is_identity(&order) {
+            return Ok(());
+        }
+        if self.nullifier_tree.is_some() {
+            return Err("Inputs must be ordered before building the nullifier tree update".to_string());
+        }
+        permute(&mut self.input_notes, &order);
+        permute(&mut self.input_indices, &order);
+        permute(&mut self.input_proofs, &order);
+        permute(&mut self.nullifier_signatures, &order);
+        permute(&mut self.tx_signatures, &order);
+        permute(&mut self.precomputed_nullifiers, &order);
+        permute(&mut self.precomputed_input_commitments, &order);
+        Ok(())
+    }
+
     /// Check if this witness has precomputed values.
     ///
     /// Returns true if precomputed nullifiers and commitments are provided.
@@ -215,6 +386,12 @@ impl Witness {
     /// Checks:
     /// - Input notes, indices, and proofs have matching lengths (if proofs provided)
     /// - No empty inputs or outputs (unless explicitly allowed)
+    /// - A transaction without outputs is a withdrawal, and a withdrawal
+    ///   spends inputs and pays a nonzero amount to a nonzero recipient
+    /// - A scoped witness (`external_nullifier`) has inputs, and no outputs
+    ///   or withdrawal
+    /// - Inputs are in canonical order: ascending leaf index, ties by
+    ///   commitment (see `input_order`)
     ///
     /// # Returns
     /// `Ok(())` if structure is valid, `Err` with description otherwise.
@@ -246,6 +423,17 @@ impl Witness {
             ));
         }
 
+        if let Some((kind, i, len)) = self
+            .nullifier_signatures
+            .iter()
+            .enumerate()
+            .map(|(i, sig)| ("Nullifier", i, sig.len()))
+            .chain(self.tx_signatures.iter().enumerate().map(|(i, sig)| ("Tx", i, sig.len())))
+            .find(|&(_, _, len)| len != 65)
+        {
+            return Err(format!("{} signature {} must be 65 bytes, got {}", kind, i, len));
+        }
+
         // If proofs are provided, they must match input count
         if !self.input_proofs.is_empty() && self.input_proofs.len() != self.input_notes.len() {
             return Err(format!(
@@ -255,11 +443,61 @@ impl Witness {
             ));
         }
 
+        check_input_order(&self.input_indices, |i| crate::note::commit(&self.input_notes[i]))?;
+
         // Transactions should have at least one input or output
         if self.input_notes.is_empty() && self.output_notes.is_empty() {
             return Err("Transaction must have at least one input or output".to_string());
         }
 
+        if self.external_nullifier.is_some() {
+            if self.input_notes.is_empty() {
+                return Err("A scoped witness (externalNullifier) must use at least one input".to_string());
+            }
+            if !self.output_notes.is_empty() || self.withdrawal.is_some() {
+                return Err("A scoped witness (externalNullifier) spends nothing, so it can't have outputs or a withdrawal".to_string());
+            }
+            return self.validate_note_versions(ACCEPTED_NOTE_VERSIONS);
+        }
+
+        match &self.withdrawal {
+            Some(withdrawal) => {
+                if self.input_notes.is_empty() {
+                    return Err("Withdrawal must spend at least one input".to_string());
+                }
+                if withdrawal.public_amount == 0 {
+                    return Err("Withdrawal publicAmount must be nonzero".to_string());
+                }
+                if withdrawal.recipient == [0u8; 20] {
+                    return Err("Withdrawal recipient must be nonzero".to_string());
+                }
+            }
+            None if self.output_notes.is_empty() => {
+                return Err(
+                    "A transaction with no outputs must be a withdrawal (set publicAmount and recipient)".to_string(),
+                );
+            }
+            None => {}
+        }
+
+        self.validate_note_versions(ACCEPTED_NOTE_VERSIONS)
+    }
+
+    /// Every input and output note is in one of the `accepted` formats.
+    pub fn validate_note_versions(&self, accepted: &[NoteVersion]) -> Result<(), String> {
+        let check = |kind: &str, i: usize, note: &Note| {
+            if accepted.contains(&note.version) {
+                Ok(())
+            } else {
+                Err(format!("{} {} has note version {}, accepted: {:?}", kind, i, note.version, accepted))
+            }
+        };
+        for (i, note) in self.input_notes.iter().enumerate() {
+            check("Input", i, note)?;
+        }
+        for (i, note) in self.output_notes.iter().enumerate() {
+            check("Output", i, note)?;
+        }
         Ok(())
     }
 
@@ -273,14 +511,14 @@ impl Witness {
         self.output_notes.len()
     }
 
-    /// Calculate total input value.
+    /// Calculate total input value (saturating at `u64::MAX`).
     pub fn total_input_value(&self) -> u64 {
-        self.input_notes.iter().map(|n| n.amount).sum()
+        self.input_notes.iter().fold(0u64, |total, n| total.saturating_add(n.amount))
     }
 
-    /// Calculate total output value.
+    /// Calculate total output value (saturating at `u64::MAX`).
     pub fn total_output_value(&self) -> u64 {
-        self.output_notes.iter().map(|n| n.amount).sum()
+        self.output_notes.iter().fold(0u64, |total, n| total.saturating_add(n.amount))
     }
 
     /// Check if this is a mint transaction (no inputs).
@@ -293,24 +531,169 @@ impl Witness {
         self.output_notes.is_empty()
     }
 
+    /// Check if this transaction pays value out of the pool.
+    pub fn is_withdrawal(&self) -> bool {
+        self.withdrawal.is_some()
+    }
+
+    /// Distinct input owners, in input order.
+    ///
+    /// Inputs may belong to different keys, e.g. a payjoin where sender and
+    /// recipient both fund the transfer: each input carries its own
+    /// nullifier and tx signature, checked against that input's owner.
+    pub fn input_owners(&self) -> Vec<[u8; 32]> {
+        let mut owners: Vec<[u8; 32]> = Vec::new();
+        for note in &self.input_notes {
+            if !owners.contains(&note.owner_pubkey) {
+                owners.push(note.owner_pubkey);
+            }
+        }
+        owners
+    }
+
+    /// Value paid out of the pool (0 for transfers).
+    pub fn public_amount(&self) -> u64 {
+        self.withdrawal.map_or(0, |w| w.public_amount)
+    }
+
+    /// Reject an `old_root` no input could be a member of: all zeros, or
+    /// the empty tree's root. Only applies when there are inputs (a pure
+    /// mint doesn't depend on the root), and fails earlier and more clearly
+    /// than the Merkle proofs would.
+    pub fn validate_old_root(&self, old_root: &[u8; 32]) -> Result<(), String> {
+        if self.input_notes.is_empty() {
+            return Ok(());
+        }
+        if *old_root == [0u8; 32] {
+            return Err("old_root is zero but the transaction spends inputs".to_string());
+        }
+        if *old_root == ZEROS[TREE_HEIGHT - 1] {
+            return Err("old_root is the empty tree root but the transaction spends inputs".to_string());
+        }
+        Ok(())
+    }
+
     /// Validate value conservation.
     ///
     /// In a real system, you'd allow inputs > outputs (the difference is a fee).
     /// For Phase 1, we can enforce exact balance.
+    ///
+    /// Totals are summed without overflow: the guest is built without
+    /// overflow checks, so a wrapping sum would let `[u64::MAX, 2]` pass as
+    /// an output total of 1.
+    ///
+    /// A withdrawal's `public_amount` counts as an output.
     pub fn validate_value_conservation(&self) -> Result<(), String> {
-        let input_total = self.total_input_value();
-        let output_total = self.total_output_value();
+        let input_total: u128 = self.input_notes.iter().map(|n| n.amount as u128).sum();
+        let output_total: u128 = self.output_notes.iter().map(|n| n.amount as u128).sum();
 
-        if input_total < output_total {
+        if output_total > u64::MAX as u128 {
+            return Err(format!("Output value overflows u64: {}", output_total));
+        }
+
+        let public_amount = self.public_amount() as u128;
+        if input_total < output_total + public_amount {
             return Err(format!(
-                "Insufficient input value: {} < {} outputs",
-                input_total, output_total
+                "Insufficient input value: {} < {} outputs + {} withdrawn",
+                input_total, output_total, public_amount
             ));
         }
 
         Ok(())
     }
 
+    /// Enforce the dust policy: every output is either 0 (padding) or at
+    /// least `threshold`. A threshold of 0 disables the check.
+    ///
+    /// Without it, anyone can bloat the commitment tree (and every wallet's
+    /// scan set) with huge numbers of 1-wei notes for the price of a fee.
+    pub fn validate_dust(&self, threshold: u64) -> Result<(), String> {
+        for (i, note) in self.output_notes.iter().enumerate() {
+            if note.amount != 0 && note.amount < threshold {
+                return Err(format!(
+                    "Output {} amount {} is below the dust threshold {}",
+                    i, note.amount, threshold
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every output with value is owned by a secp256k1 key: its
+    /// `owner_pubkey` is non-zero and the X coordinate of a curve point.
+    ///
+    /// Any other owner can never sign, so the value is burned; that's what
+    /// a wallet encoding keys wrong (a hash, the Y coordinate, a truncated
+    /// address) produces. Zero-amount padding outputs aren't checked.
+    #[cfg(feature = "std")]
+    pub fn validate_output_owners(&self) -> Result<(), String> {
+        for (i, note) in self.output_notes.iter().enumerate().filter(|(_, note)| note.amount != 0) {
+            if note.owner_pubkey == [0u8; 32] {
+                return Err(format!("Output {} owner is zero", i));
+            }
+            crate::owner::validate_owner(&note.owner_pubkey)
+                .map_err(|e| format!("Output {} owner isn't a secp256k1 key ({}); its value would be unspendable", i, e))?;
+        }
+        Ok(())
+    }
+
+    /// Check no output commitment repeats another output, an input, or a
+    /// leaf already in the tree (`leaf_index` returns its index if so).
+    ///
+    /// The ledger appends duplicates without complaint, but two leaves with
+    /// one commitment link the transactions that created them, and scanners
+    /// can't tell which leaf a decrypted note belongs to. Only a reused
+    /// blinding or a replayed wallet transaction produces one.
+    #[cfg(feature = "std")]
+    pub fn validate_output_uniqueness(&self, leaf_index: impl Fn(&[u8; 32]) -> Option<u64>) -> Result<(), String> {
+        use crate::note::commit;
+
+        let outputs: Vec<[u8; 32]> = self.output_notes.iter().map(commit).collect();
+        for (i, commitment) in outputs.iter().enumerate() {
+            if let Some(j) = outputs[..i].iter().position(|other| other == commitment) {
+                return Err(format!("Output {} has the same commitment as output {} (blinding reused)", i, j));
+            }
+            if let Some(j) = self.input_notes.iter().position(|input| commit(input) == *commitment) {
+                return Err(format!("Output {} recreates input {}'s commitment (transaction replayed)", i, j));
+            }
+            if let Some(index) = leaf_index(commitment) {
+                return Err(format!(
+                    "Output {} commitment 0x{} is already leaf {} (blinding reused or transaction replayed)",
+                    i,
+                    crate::hex::encode_hex(commitment),
+                    index
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Output blindings that look like wallet bugs rather than randomness:
+    /// a single repeated byte (e.g. all zero), equal to the owner pubkey,
+    /// repeated across outputs, or reused from an input.
+    ///
+    /// Weak blindings break the commitment's hiding property (amount and
+    /// owner become guessable from the commitment) without any visible
+    /// symptom, so the host checks them before proving.
+    pub fn blinding_issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        for (i, note) in self.output_notes.iter().enumerate() {
+            let b = &note.blinding;
+            if b.iter().all(|&byte| byte == b[0]) {
+                issues.push(format!("Output {} blinding is the byte 0x{:02x} repeated", i, b[0]));
+            } else if crate::ct::eq(b, &note.owner_pubkey) {
+                issues.push(format!("Output {} blinding equals its owner pubkey", i));
+            }
+            if let Some(j) = self.output_notes[..i].iter().position(|other| crate::ct::eq(&other.blinding, b)) {
+                issues.push(format!("Output {} reuses the blinding of output {}", i, j));
+            }
+            if let Some(j) = self.input_notes.iter().position(|input| crate::ct::eq(&input.blinding, b)) {
+                issues.push(format!("Output {} reuses the blinding of input {}", i, j));
+            }
+        }
+        issues
+    }
+
     /// Compute and populate precomputed values for optimized proving.
     ///
     /// This method should be called on the HOST before passing the witness
@@ -334,12 +717,13 @@ impl Witness {
             .map(|note| commit(note))
             .collect();
 
-        // Compute nullifiers (Airtight: Hash(Sig))
-        // We use the provided nullifier signatures.
+        // Compute nullifiers (Airtight: Hash(Sig), or keyed on the note
+        // when scoped). We use the provided nullifier signatures.
         self.precomputed_nullifiers = self
             .nullifier_signatures
             .iter()
-            .map(|sig| crate::note::compute_nullifier(sig))
+            .zip(&self.input_notes)
+            .map(|(sig, note)| crate::note::input_nullifier(note, sig, self.external_nullifier.as_ref()))
             .collect();
 
         // Compute output commitments
@@ -380,6 +764,27 @@ mod tests {
         assert!(!inputs.is_empty_tree());
     }
 
+    #[test]
+    fn test_guest_input_envelope() {
+        let (note, _) = dummy_note(5);
+        let witness = Witness::new_without_proofs(vec![], vec![], vec![], vec![], vec![note]);
+        let public_inputs = PublicInputs::new([7; 32]).with_chain_id(10);
+
+        let bytes = bincode::serialize(&GuestInput::new(public_inputs.clone(), witness.clone())).unwrap();
+        let (read_inputs, read_witness) = bincode::deserialize::<GuestInput>(&bytes).unwrap().open().unwrap();
+        assert_eq!(read_inputs, public_inputs);
+        assert_eq!(read_witness.output_notes, witness.output_notes);
+
+        // The old positional layout isn't mistaken for an envelope
+        let mut old = bincode::serialize(&public_inputs).unwrap();
+        old.extend(bincode::serialize(&witness).unwrap());
+        assert!(bincode::deserialize::<GuestInput>(&old).map_or(true, |input| input.open().is_err()));
+
+        let mut future = GuestInput::new(public_inputs, witness);
+        future.version += 1;
+        assert!(future.open().unwrap_err().contains("version"));
+    }
+
     #[test]
     fn test_empty_tree_detection() {
         let empty = PublicInputs::new([0u8; 32]);
@@ -423,6 +828,34 @@ mod tests {
         
         assert!(witness.validate_structure().is_ok());
         assert!(witness.validate_value_conservation().is_ok());
+
+        let mut truncated = witness.clone();
+        truncated.tx_signatures[0].pop();
+        assert_eq!(truncated.validate_structure().unwrap_err(), "Tx signature 0 must be 65 bytes, got 64");
+    }
+
+    #[test]
+    fn test_inputs_out_of_leaf_order_rejected_until_canonicalized() {
+        let (a, _) = dummy_note(60);
+        let (b, _) = dummy_note(40);
+        let (out, _) = dummy_note(100);
+        let signature = |byte: u8| vec![byte; 65];
+
+        let mut witness = Witness::new_without_proofs(
+            vec![a.clone(), b.clone()],
+            vec![7, 3],
+            vec![signature(1), signature(2)],
+            vec![signature(3), signature(4)],
+            vec![out],
+        );
+        assert!(witness.validate_structure().unwrap_err().contains("input 1 at leaf 3 follows leaf 7"));
+
+        witness.canonicalize_inputs().unwrap();
+        witness.validate_structure().unwrap();
+        assert_eq!(witness.input_indices, vec![3, 7]);
+        assert_eq!(witness.input_notes, vec![b, a]);
+        assert_eq!(witness.nullifier_signatures, vec![signature(2), signature(1)]);
+        assert_eq!(witness.tx_signatures, vec![signature(4), signature(3)]);
     }
 
     #[test]
@@ -459,6 +892,144 @@ mod tests {
         assert!(witness.validate_value_conservation().is_err());
     }
 
+    #[test]
+    fn test_empty_or_zero_old_root_rejected_with_inputs() {
+        let (input, _key) = dummy_note(100);
+        let (out, _) = dummy_note(100);
+        let sigs = vec![vec![0u8; 65]];
+        let spend = Witness::new_without_proofs(vec![input], vec![0], sigs.clone(), sigs, vec![out.clone()]);
+
+        assert!(spend.validate_old_root(&[0u8; 32]).unwrap_err().contains("zero"));
+        assert!(spend.validate_old_root(&ZEROS[TREE_HEIGHT - 1]).unwrap_err().contains("empty tree"));
+        assert!(spend.validate_old_root(&[42u8; 32]).is_ok());
+
+        // A pure mint doesn't depend on the root
+        let mint = Witness::new_without_proofs(vec![], vec![], vec![], vec![], vec![out]);
+        assert!(mint.validate_old_root(&ZEROS[TREE_HEIGHT - 1]).is_ok());
+    }
+
+    #[test]
+    fn test_overflowing_outputs_rejected() {
+        let (input, _key) = dummy_note(1);
+        let (big, _) = dummy_note(u64::MAX);
+        let (small, _) = dummy_note(2);
+        let sigs = vec![vec![0u8; 65]];
+
+        let witness = Witness::new_without_proofs(vec![input], vec![0], sigs.clone(), sigs, vec![big, small]);
+
+        assert_eq!(witness.total_output_value(), u64::MAX);
+        assert!(witness.validate_value_conservation().unwrap_err().contains("overflows"));
+    }
+
+    #[test]
+    fn test_dust_outputs_rejected() {
+        let (input, _) = dummy_note(1000);
+        let (padding, _) = dummy_note(0);
+        let (payment, _) = dummy_note(500);
+        let (dust, _) = dummy_note(1);
+        let sigs = vec![vec![0u8; 65]];
+
+        let padded = vec![payment.clone(), padding];
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), padded);
+        assert!(witness.validate_dust(100).is_ok());
+
+        let dusty = vec![payment, dust];
+        let witness = Witness::new_without_proofs(vec![input], vec![0], sigs.clone(), sigs, dusty);
+        assert!(witness.validate_dust(100).unwrap_err().contains("Output 1"));
+        assert!(witness.validate_dust(0).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_output_owners_must_be_curve_points() {
+        let (input, _) = dummy_note(1000);
+        let owner = crate::owner::owner_from_spending_key(&[7; 32]).unwrap();
+        let sigs = vec![vec![0u8; 65]];
+        let outputs = vec![Note::new(900, owner, [1; 32]), Note::new(0, [0; 32], [2; 32])];
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), outputs);
+        assert!(witness.validate_output_owners().is_ok());
+
+        let mut off_curve = owner;
+        while crate::owner::validate_owner(&off_curve).is_ok() {
+            off_curve[31] = off_curve[31].wrapping_add(1);
+        }
+        let outputs = vec![Note::new(900, owner, [1; 32]), Note::new(50, off_curve, [2; 32])];
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), outputs);
+        assert!(witness.validate_output_owners().unwrap_err().contains("Output 1 owner isn't a secp256k1 key"));
+
+        let outputs = vec![Note::new(900, [0; 32], [1; 32])];
+        let witness = Witness::new_without_proofs(vec![input], vec![0], sigs.clone(), sigs, outputs);
+        assert_eq!(witness.validate_output_owners().unwrap_err(), "Output 0 owner is zero");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_output_commitments_must_be_new() {
+        let (input, _) = dummy_note(1000);
+        let sigs = vec![vec![0u8; 65]];
+        let outputs = vec![Note::new(500, [4; 32], [1; 32]), Note::new(500, [4; 32], [3; 32])];
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), outputs.clone());
+        assert!(witness.validate_output_uniqueness(|_| None).is_ok());
+
+        let existing = crate::note::commit(&outputs[1]);
+        let err = witness.validate_output_uniqueness(|c| (*c == existing).then_some(7)).unwrap_err();
+        assert!(err.starts_with("Output 1 commitment") && err.contains("already leaf 7"), "{}", err);
+
+        let repeated = vec![outputs[0].clone(), outputs[0].clone()];
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), repeated);
+        assert!(witness.validate_output_uniqueness(|_| None).unwrap_err().contains("same commitment as output 0"));
+
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs, vec![input]);
+        assert!(witness.validate_output_uniqueness(|_| None).unwrap_err().contains("recreates input 0"));
+    }
+
+    #[test]
+    fn test_unaccepted_note_versions_rejected() {
+        let (input, _) = dummy_note(1000);
+        let (output, _) = dummy_note(1000);
+        let sigs = vec![vec![0u8; 65]];
+
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), vec![output.clone()]);
+        assert!(witness.validate_structure().is_ok());
+
+        let future = output.with_version(2);
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), vec![future.clone()]);
+        assert!(witness.validate_structure().unwrap_err().contains("Output 0 has note version 2"));
+        // A migration ELF accepting both formats takes it
+        assert!(witness.validate_note_versions(&[1, 2]).is_ok());
+
+        let witness = Witness::new_without_proofs(vec![future], vec![0], sigs.clone(), sigs, vec![input]);
+        assert!(witness.validate_structure().unwrap_err().contains("Input 0 has note version 2"));
+    }
+
+    #[test]
+    fn test_blinding_issues() {
+        let (input, _) = dummy_note(1000);
+        let sigs = vec![vec![0u8; 65]];
+        let random = |seed: u8| -> [u8; 32] { core::array::from_fn(|i| seed.wrapping_mul(31).wrapping_add(i as u8 * 7)) };
+
+        let good = vec![Note::new(600, [4; 32], random(1)), Note::new(400, [5; 32], random(2))];
+        let witness = Witness::new_without_proofs(vec![input.clone()], vec![0], sigs.clone(), sigs.clone(), good);
+        assert!(witness.blinding_issues().is_empty());
+
+        let bad = vec![
+            Note::new(100, [4; 32], [0; 32]),
+            Note::new(100, [5; 32], [5; 32]),
+            Note::new(100, random(9), random(9)),
+            Note::new(100, [6; 32], random(3)),
+            Note::new(100, [7; 32], random(3)),
+            Note::new(100, [8; 32], input.blinding),
+        ];
+        let witness = Witness::new_without_proofs(vec![input], vec![0], sigs.clone(), sigs, bad);
+        let issues = witness.blinding_issues();
+        assert!(issues[0].contains("Output 0") && issues[0].contains("0x00 repeated"));
+        assert!(issues[1].contains("Output 1") && issues[1].contains("repeated"));
+        assert!(issues[2].contains("Output 2 blinding equals its owner pubkey"));
+        assert!(issues[3].contains("Output 4 reuses the blinding of output 3"));
+        // dummy_note blindings are [2; 32], so output 5 is also a repeated byte
+        assert!(issues.iter().any(|i| i == "Output 5 reuses the blinding of input 0"));
+    }
+
     #[test]
     fn test_mint_transaction() {
         let (out, _) = dummy_note(100);
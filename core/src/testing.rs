@@ -0,0 +1,335 @@
+//! Fixtures for tests, demos and downstream integrations (`testing` feature)
+//!
+//! Real k256 keys and signatures throughout, so everything built here
+//! passes `simulate_witness` and the guest unless a test breaks it on
+//! purpose:
+//!
+//! - `TestOwner`: a spending key and its note owner, fixed (`alice`, `bob`)
+//!   or random
+//! - `random_note`, `populated_tree`: notes and a tree holding them among
+//!   unrelated leaves
+//! - `WitnessBuilder`: a signed transaction from input and output amounts
+//! - `demo_transaction`: the Alice (100) -> Bob (50) + change (50) transfer
+//!   the host binaries prove when run without a request
+//!
+//! Randomness comes from the thread RNG unless a caller passes its own: the
+//! `_with` helpers take any `RngCore`, and `WitnessBuilder::seed` draws
+//! every blinding and filler leaf from a seeded one, so the same seed builds
+//! the same witness byte for byte.
+//!
+//! Nothing here is for real funds: the demo keys are public.
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+use crate::merkle::MerkleTree;
+use crate::note::{commit, compute_nullifier, Note};
+use crate::owner::owner_from_spending_key;
+use crate::signatures::{nullifier_message, sign_message, tx_fee, tx_message, withdrawal_tx_message};
+use crate::sp1_types::{PublicInputs, Withdrawal, Witness};
+
+/// Alice's demo spending key.
+pub const ALICE_KEY: [u8; 32] = [
+    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10,
+    0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f, 0x20,
+];
+
+/// Bob's demo spending key.
+pub const BOB_KEY: [u8; 32] = [
+    0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e, 0x2f, 0x30,
+    0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f, 0x40,
+];
+
+/// A spending key and the note owner it controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestOwner {
+    pub spending_key: [u8; 32],
+    pub owner: [u8; 32],
+}
+
+impl TestOwner {
+    /// # Panics
+    /// If `spending_key` isn't a valid secp256k1 secret.
+    pub fn from_key(spending_key: [u8; 32]) -> Self {
+        let owner = owner_from_spending_key(&spending_key).expect("Invalid test spending key");
+        Self { spending_key, owner }
+    }
+
+    pub fn alice() -> Self {
+        Self::from_key(ALICE_KEY)
+    }
+
+    pub fn bob() -> Self {
+        Self::from_key(BOB_KEY)
+    }
+
+    pub fn random() -> Self {
+        Self::random_with(&mut rand::thread_rng())
+    }
+
+    /// A random key drawn from `rng`.
+    pub fn random_with(rng: &mut impl RngCore) -> Self {
+        loop {
+            let spending_key: [u8; 32] = rng.gen();
+            if let Ok(owner) = owner_from_spending_key(&spending_key) {
+                return Self { spending_key, owner };
+            }
+        }
+    }
+
+    /// A note for `amount` owned by this key, with a fixed blinding.
+    pub fn note(&self, amount: u64, blinding: u8) -> Note {
+        Note::new(amount, self.owner, [blinding; 32])
+    }
+
+    /// A note for `amount` owned by this key, with a random blinding.
+    pub fn random_note(&self, amount: u64) -> Note {
+        self.random_note_with(amount, &mut rand::thread_rng())
+    }
+
+    /// A note for `amount` owned by this key, with a blinding from `rng`.
+    pub fn random_note_with(&self, amount: u64, rng: &mut impl RngCore) -> Note {
+        Note::new(amount, self.owner, rng.gen())
+    }
+}
+
+/// A note for `amount` with a random owner and blinding.
+pub fn random_note(amount: u64) -> Note {
+    TestOwner::random().random_note(amount)
+}
+
+/// A tree with `filler` random leaves, then `notes`; returns it with the
+/// notes' leaf indices.
+pub fn populated_tree(filler: usize, notes: &[Note]) -> (MerkleTree, Vec<usize>) {
+    populated_tree_with(filler, notes, &mut rand::thread_rng())
+}
+
+/// `populated_tree`, with filler leaves drawn from `rng`.
+pub fn populated_tree_with(filler: usize, notes: &[Note], rng: &mut impl RngCore) -> (MerkleTree, Vec<usize>) {
+    let mut tree = MerkleTree::new();
+    for _ in 0..filler {
+        tree.push_leaf(rng.gen());
+    }
+    let indices = notes.iter().map(|note| tree.push_note(note).index as usize).collect();
+    (tree, indices)
+}
+
+/// Builds a signed transaction: inputs are placed in a fresh tree (after
+/// `filler` unrelated leaves) or found in an existing one (`build_in`), and
+/// every input owner signs its nullifier and the transaction.
+#[derive(Debug, Clone, Default)]
+pub struct WitnessBuilder {
+    inputs: Vec<(TestOwner, Note)>,
+    outputs: Vec<Note>,
+    withdrawal: Option<Withdrawal>,
+    chain_id: u64,
+    filler: usize,
+    rng: Option<StdRng>,
+}
+
+impl WitnessBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw blindings and filler leaves from an RNG seeded with `seed`
+    /// instead of the thread RNG; call it before adding random notes.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = Some(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    fn random_note(&mut self, owner: &TestOwner, amount: u64) -> Note {
+        match &mut self.rng {
+            Some(rng) => owner.random_note_with(amount, rng),
+            None => owner.random_note(amount),
+        }
+    }
+
+    /// Spend a new random-blinding note of `amount` owned by `owner`.
+    pub fn input(mut self, owner: &TestOwner, amount: u64) -> Self {
+        let note = self.random_note(owner, amount);
+        self.input_note(owner, note)
+    }
+
+    /// Spend `note`, which `owner` must own for the signatures to verify.
+    pub fn input_note(mut self, owner: &TestOwner, note: Note) -> Self {
+        self.inputs.push((*owner, note));
+        self
+    }
+
+    /// Pay `amount` to `owner` in a new random-blinding note.
+    pub fn output(mut self, owner: &TestOwner, amount: u64) -> Self {
+        let note = self.random_note(owner, amount);
+        self.output_note(note)
+    }
+
+    pub fn output_note(mut self, note: Note) -> Self {
+        self.outputs.push(note);
+        self
+    }
+
+    pub fn withdrawal(mut self, public_amount: u64, recipient: [u8; 20]) -> Self {
+        self.withdrawal = Some(Withdrawal { public_amount, recipient: recipient.into() });
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Unrelated leaves inserted before the inputs.
+    pub fn filler(mut self, filler: usize) -> Self {
+        self.filler = filler;
+        self
+    }
+
+    /// The public inputs and signed witness, with Merkle proofs and
+    /// precomputed values.
+    ///
+    /// # Errors
+    /// Fails if the outputs and withdrawal exceed the inputs (there's no fee
+    /// to sign) or a key can't sign.
+    pub fn build(mut self) -> Result<(PublicInputs, Witness), String> {
+        let notes: Vec<Note> = self.inputs.iter().map(|(_, note)| note.clone()).collect();
+        let (tree, indices) = match &mut self.rng {
+            Some(rng) => populated_tree_with(self.filler, &notes, rng),
+            None => populated_tree(self.filler, &notes),
+        };
+        self.build_in(&tree, indices)
+    }
+
+    /// `build`, spending inputs already in `tree` at `indices` (in input
+    /// order) instead of a fresh tree; `filler` is ignored. The witness's
+    /// inputs are then sorted by leaf index, as `validate_structure` needs.
+    ///
+    /// # Errors
+    /// Also fails if an index isn't in the tree or holds another note.
+    pub fn build_in(self, tree: &MerkleTree, indices: Vec<usize>) -> Result<(PublicInputs, Witness), String> {
+        let notes: Vec<Note> = self.inputs.iter().map(|(_, note)| note.clone()).collect();
+        if indices.len() != notes.len() {
+            return Err(format!("{} inputs but {} indices", notes.len(), indices.len()));
+        }
+        for (i, (&index, note)) in indices.iter().zip(&notes).enumerate() {
+            if tree.get_leaf(index) != Some(commit(note)) {
+                return Err(format!("Input {} isn't at leaf {}", i, index));
+            }
+        }
+        let proofs = indices.iter().map(|&i| tree.prove(i).expect("leaf is in the tree")).collect();
+
+        let public_amount = self.withdrawal.map_or(0, |w| w.public_amount);
+        let fee = tx_fee(&notes, &self.outputs)
+            .and_then(|fee| fee.checked_sub(public_amount))
+            .ok_or("Outputs exceed inputs")?;
+        let output_commitments: Vec<[u8; 32]> = self.outputs.iter().map(commit).collect();
+
+        let mut nullifier_signatures = Vec::new();
+        let mut tx_signatures = Vec::new();
+        for (owner, note) in &self.inputs {
+            let nullifier_signature = sign_message(&owner.spending_key, &nullifier_message(&commit(note)))?;
+            let nullifier = compute_nullifier(&nullifier_signature);
+            let message = match &self.withdrawal {
+                Some(withdrawal) => withdrawal_tx_message(&nullifier, fee, &output_commitments, withdrawal),
+                None => tx_message(&nullifier, fee, &output_commitments),
+            };
+            tx_signatures.push(sign_message(&owner.spending_key, &message)?.to_vec());
+            nullifier_signatures.push(nullifier_signature.to_vec());
+        }
+
+        let mut witness = Witness::new(notes, indices, proofs, nullifier_signatures, tx_signatures, self.outputs);
+        if let Some(withdrawal) = self.withdrawal {
+            witness = witness.with_withdrawal(withdrawal);
+        }
+        witness.canonicalize_inputs()?;
+        let public_inputs = PublicInputs::new(tree.root()).with_chain_id(self.chain_id);
+        Ok((public_inputs, witness.with_precomputed_values()))
+    }
+}
+
+/// Alice spends a 100 note, paying Bob 50 and herself 50 in change, on
+/// `chain_id`. Blindings are fixed, so the commitments are the same every
+/// run.
+pub fn demo_transaction(chain_id: u64) -> (PublicInputs, Witness) {
+    let (alice, bob) = (TestOwner::alice(), TestOwner::bob());
+    WitnessBuilder::new()
+        .input_note(&alice, alice.note(100, 0x42))
+        .output_note(bob.note(50, 0x43))
+        .output_note(alice.note(50, 0x44))
+        .chain_id(chain_id)
+        .build()
+        .expect("The demo transaction balances")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{simulate_witness, Ledger};
+
+    #[test]
+    fn test_demo_transaction_verifies() {
+        let (public_inputs, witness) = demo_transaction(31337);
+        assert_eq!(public_inputs.chain_id, 31337);
+        assert_eq!(witness.output_notes[0].owner_pubkey, TestOwner::bob().owner);
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, public_inputs.old_root);
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+        assert_eq!(demo_transaction(31337).1.precomputed_output_commitments, witness.precomputed_output_commitments);
+    }
+
+    #[test]
+    fn test_built_witnesses_verify() {
+        let (alice, carol) = (TestOwner::alice(), TestOwner::random());
+        let (public_inputs, witness) = WitnessBuilder::new()
+            .input(&alice, 60)
+            .input(&carol, 40)
+            .output(&carol, 70)
+            .withdrawal(25, [7; 20])
+            .filler(5)
+            .build()
+            .unwrap();
+        assert_eq!(witness.input_indices, vec![5, 6]);
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, public_inputs.old_root);
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+        assert_eq!(simulation.fee(), 5);
+
+        assert!(WitnessBuilder::new().input(&alice, 1).output(&alice, 2).build().is_err());
+
+        let note = alice.note(10, 1);
+        let (tree, indices) = populated_tree(3, std::slice::from_ref(&note));
+        let (public_inputs, witness) = WitnessBuilder::new()
+            .input_note(&alice, note.clone())
+            .output(&carol, 10)
+            .build_in(&tree, indices)
+            .unwrap();
+        assert_eq!(public_inputs.old_root, tree.root());
+        assert!(simulate_witness(&mut Ledger::new(), &witness, tree.root()).is_valid());
+        assert!(WitnessBuilder::new().input_note(&alice, note).build_in(&tree, vec![0]).is_err());
+    }
+
+    #[test]
+    fn test_sixteen_input_consolidation_verifies() {
+        let (alice, bob) = (TestOwner::alice(), TestOwner::bob());
+        let builder = (0..16).fold(WitnessBuilder::new().seed(7), |builder, _| builder.input(&alice, 10));
+        let (public_inputs, witness) = builder.output(&bob, 160).build().unwrap();
+        witness.validate_structure().unwrap();
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, public_inputs.old_root);
+        assert!(simulation.is_valid(), "{:?}", simulation.failure_reasons());
+        assert_eq!(simulation.public_outputs.nullifiers.len(), 16);
+    }
+
+    #[test]
+    fn test_seeded_builds_are_identical() {
+        let (alice, bob) = (TestOwner::alice(), TestOwner::bob());
+        let build = |seed: u64| {
+            let (public_inputs, witness) =
+                WitnessBuilder::new().seed(seed).input(&alice, 30).output(&bob, 29).filler(4).build().unwrap();
+            (bincode::serialize(&public_inputs).unwrap(), bincode::serialize(&witness).unwrap())
+        };
+        assert_eq!(build(1), build(1));
+        assert_ne!(build(1), build(2));
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let owner = TestOwner::random_with(&mut rng);
+        assert_eq!(owner, TestOwner::random_with(&mut StdRng::seed_from_u64(3)));
+    }
+}
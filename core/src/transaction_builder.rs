@@ -1,12 +1,16 @@
 use crate::tx_metadata::CommitmentMetadata;
 use crate::note::Note;
 use crate::encryption::ViewPublicKey;
+use crate::output_order::canonicalize_outputs_with;
+use crate::memo::{seal_output_metadata, MemoLimits};
+use rand::RngCore;
 
 pub struct TransactionBuilder {
     pub inputs: Vec<Note>,
     pub input_indices: Vec<usize>,
     pub outputs: Vec<Note>,
     pub metadata: Vec<CommitmentMetadata>,
+    pub memo_limits: MemoLimits,
 }
 
 impl TransactionBuilder {
@@ -18,8 +22,60 @@ impl TransactionBuilder {
         amount: u64,
         memo: Option<String>,
         sender_pubkey: ViewPublicKey,
+    ) -> Result<Self, String> {
+        Self::build_transfer_with_limits(
+            sender_note,
+            sender_note_index,
+            recipient_pubkey,
+            amount,
+            memo,
+            sender_pubkey,
+            MemoLimits::default(),
+        )
+    }
+
+    /// `build_transfer` with memo size limits other than the defaults; a
+    /// memo over `memo_limits.max_total_bytes` is refused here, before any
+    /// signing or proving
+    pub fn build_transfer_with_limits(
+        sender_note: Note,
+        sender_note_index: usize,
+        recipient_pubkey: ViewPublicKey,
+        amount: u64,
+        memo: Option<String>,
+        sender_pubkey: ViewPublicKey,
+        memo_limits: MemoLimits,
+    ) -> Result<Self, String> {
+        Self::build_transfer_with_rng(
+            sender_note,
+            sender_note_index,
+            recipient_pubkey,
+            amount,
+            memo,
+            sender_pubkey,
+            memo_limits,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// `build_transfer_with_limits`, drawing both blindings from `rng`; a
+    /// seeded RNG gives the same outputs every run (tests and snapshots only)
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_transfer_with_rng(
+        sender_note: Note,
+        sender_note_index: usize,
+        recipient_pubkey: ViewPublicKey,
+        amount: u64,
+        memo: Option<String>,
+        sender_pubkey: ViewPublicKey,
+        memo_limits: MemoLimits,
+        rng: &mut impl RngCore,
     ) -> Result<Self, String> {
         let sender_value = sender_note.amount;
+
+        if let Some(memo) = &memo {
+            memo_limits.check(memo.len())?;
+        }
         
         if amount > sender_value {
             return Err("Insufficient funds".into());
@@ -33,7 +89,8 @@ impl TransactionBuilder {
         sender_owner.copy_from_slice(&sender_pubkey[1..]);
         
         // Create output for recipient
-        let recipient_blinding = rand::random();
+        let mut recipient_blinding = [0u8; 32];
+        rng.fill_bytes(&mut recipient_blinding);
         let recipient_note = Note::new(
             amount,
             recipient_owner,
@@ -42,7 +99,8 @@ impl TransactionBuilder {
         
         // Create change output for sender
         let change_amount = sender_value - amount;
-        let change_blinding = rand::random();
+        let mut change_blinding = [0u8; 32];
+        rng.fill_bytes(&mut change_blinding);
         let change_note = Note::new(
             change_amount,
             sender_owner,
@@ -64,15 +122,23 @@ impl TransactionBuilder {
             change_blinding,
         );
         
+        // Canonical order, so the change isn't always the second output
+        let (outputs, metadata) = canonicalize_outputs_with(
+            vec![recipient_note, change_note],
+            vec![recipient_metadata, sender_metadata],
+        );
+
         Ok(Self {
             inputs: vec![sender_note],
             input_indices: vec![sender_note_index],
-            outputs: vec![recipient_note, change_note],
-            metadata: vec![recipient_metadata, sender_metadata],
+            outputs,
+            metadata,
+            memo_limits,
         })
     }
     
-    /// Encrypt all metadata
+    /// Encrypt all metadata, one field per output (long memos chunked, see
+    /// `memo`)
     pub fn encrypt_metadata(&self) -> Result<Vec<Vec<u8>>, String> {
         let mut encrypted = Vec::new();
         
@@ -82,7 +148,7 @@ impl TransactionBuilder {
             view_pubkey[0] = 0x02; // Compressed public key prefix
             view_pubkey[1..].copy_from_slice(&self.outputs[i].owner_pubkey);
             
-            let encrypted_meta = metadata.encrypt(&view_pubkey)?;
+            let encrypted_meta = seal_output_metadata(metadata, &view_pubkey, &self.memo_limits)?;
             encrypted.push(encrypted_meta);
         }
         
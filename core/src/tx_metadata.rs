@@ -168,6 +168,22 @@ impl CommitmentMetadata {
         }
     }
 
+    /// The memo, if this kind of metadata carries one.
+    pub fn memo(&self) -> Option<&str> {
+        match self {
+            Self::SenderChange { memo, .. } | Self::ReceivedFunds { memo, .. } => memo.as_deref(),
+            Self::Deposit { .. } => None,
+        }
+    }
+
+    /// Replace the memo (no-op for deposits).
+    pub fn set_memo(&mut self, new_memo: Option<String>) {
+        match self {
+            Self::SenderChange { memo, .. } | Self::ReceivedFunds { memo, .. } => *memo = new_memo,
+            Self::Deposit { .. } => {}
+        }
+    }
+
     /// Create metadata for deposit
     pub fn for_deposit(amount: u64, blinding: [u8; 32]) -> Self {
         Self::Deposit {
@@ -0,0 +1,334 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::note::{commit, Note};
+
+/// A note the wallet owns, with where it lives in the commitment tree.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OwnedNote {
+    pub note: Note,
+    /// Commitment (`commit(note)`), cached for lookups against tree leaves
+    pub commitment: [u8; 32],
+    /// Leaf index in the ledger's Merkle tree
+    pub leaf_index: u64,
+    /// Set once the note's nullifier has been seen on-chain
+    pub spent: bool,
+    /// Set if the note is known to be unspendable; excluded notes don't
+    /// count towards the balance or coin selection
+    pub excluded: Option<ExclusionReason>,
+    /// Local-only labels (e.g. "payroll", "invoice-1042") for accounting;
+    /// never leave the wallet except in its own backups and exports
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+}
+
+/// Why a note was excluded from the spendable set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExclusionReason {
+    /// The owner key was rotated away; the wallet can no longer sign for it
+    KeyRotated,
+    /// Worth less than the fee floor at the time it was excluded
+    Dust { fee_floor: u64 },
+    /// Caller-defined reason
+    Other(String),
+}
+
+impl OwnedNote {
+    pub fn new(note: Note, leaf_index: u64) -> Self {
+        let commitment = commit(&note);
+        Self {
+            note,
+            commitment,
+            leaf_index,
+            spent: false,
+            excluded: None,
+            tags: BTreeSet::new(),
+        }
+    }
+}
+
+/// How far the wallet has scanned the ledger.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScanCursor {
+    /// Last block whose events have been processed
+    pub block_number: u64,
+    /// Number of tree leaves processed (next leaf index to scan)
+    pub leaf_count: u64,
+}
+
+/// Local wallet state: owned notes plus the scan cursor.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WalletState {
+    pub notes: Vec<OwnedNote>,
+    pub cursor: ScanCursor,
+    /// Next seed-derived blinding index (see `recovery`); only ever advances
+    #[serde(default)]
+    pub next_blinding_index: u64,
+}
+
+impl WalletState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blinding for the wallet's next self-owned note, advancing
+    /// `next_blinding_index` so the index is never reused.
+    #[cfg(feature = "encryption")]
+    pub fn next_blinding(&mut self, seed: &[u8]) -> [u8; 32] {
+        let blinding = crate::recovery::derive_note_blinding(seed, self.next_blinding_index);
+        self.next_blinding_index += 1;
+        blinding
+    }
+
+    /// Record a discovered note. Returns `false` if it was already known.
+    pub fn add_note(&mut self, note: Note, leaf_index: u64) -> bool {
+        let owned = OwnedNote::new(note, leaf_index);
+        if self.find(&owned.commitment).is_some() {
+            return false;
+        }
+        self.notes.push(owned);
+        true
+    }
+
+    /// Record whichever of `candidates` (decrypted outputs, or the change
+    /// notes of the wallet's own transaction) appear among a transaction's
+    /// `committed` leaves, as `(commitment, leaf_index)` pairs.
+    ///
+    /// Outputs are in canonical (commitment) order, so a note's position in
+    /// the transaction says nothing about whether it's the payment or the
+    /// change; matching is by commitment. Returns the number of notes added.
+    pub fn claim_outputs(&mut self, candidates: impl IntoIterator<Item = Note>, committed: &[([u8; 32], u64)]) -> usize {
+        let commitments: Vec<[u8; 32]> = committed.iter().map(|(c, _)| *c).collect();
+        let candidates: Vec<Note> = candidates.into_iter().collect();
+        let positions = crate::output_order::match_outputs(&candidates, &commitments);
+        candidates
+            .into_iter()
+            .zip(positions)
+            .filter_map(|(note, position)| Some((note, committed[position?].1)))
+            .filter(|(note, leaf_index)| self.add_note(note.clone(), *leaf_index))
+            .count()
+    }
+
+    /// Look up a note by commitment.
+    pub fn find(&self, commitment: &[u8; 32]) -> Option<&OwnedNote> {
+        self.notes.iter().find(|n| &n.commitment == commitment)
+    }
+
+    /// Mark a note spent. Returns `false` if the commitment is unknown.
+    pub fn mark_spent(&mut self, commitment: &[u8; 32]) -> bool {
+        match self.notes.iter_mut().find(|n| &n.commitment == commitment) {
+            Some(owned) => {
+                owned.spent = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Notes available for spending (unspent and not excluded).
+    pub fn unspent(&self) -> impl Iterator<Item = &OwnedNote> {
+        self.notes.iter().filter(|n| !n.spent && n.excluded.is_none())
+    }
+
+    /// Exclude a note from balance and selection. Returns `false` if the
+    /// commitment is unknown.
+    pub fn exclude(&mut self, commitment: &[u8; 32], reason: ExclusionReason) -> bool {
+        match self.notes.iter_mut().find(|n| &n.commitment == commitment) {
+            Some(owned) => {
+                owned.excluded = Some(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Undo an exclusion. Returns `false` if the note wasn't excluded.
+    pub fn include(&mut self, commitment: &[u8; 32]) -> bool {
+        self.notes
+            .iter_mut()
+            .find(|n| &n.commitment == commitment)
+            .and_then(|owned| owned.excluded.take())
+            .is_some()
+    }
+
+    /// Exclude every spendable note worth less than `fee_floor`.
+    /// Returns how many notes were excluded.
+    pub fn exclude_dust(&mut self, fee_floor: u64) -> usize {
+        self.exclude_where(|n| n.amount < fee_floor, ExclusionReason::Dust { fee_floor })
+    }
+
+    /// Exclude every spendable note owned by a rotated-away key.
+    /// Returns how many notes were excluded.
+    pub fn exclude_owner(&mut self, owner_pubkey: &[u8; 32]) -> usize {
+        self.exclude_where(|n| &n.owner_pubkey == owner_pubkey, ExclusionReason::KeyRotated)
+    }
+
+    fn exclude_where(&mut self, matches: impl Fn(&Note) -> bool, reason: ExclusionReason) -> usize {
+        let mut count = 0;
+        for owned in self.notes.iter_mut() {
+            if !owned.spent && owned.excluded.is_none() && matches(&owned.note) {
+                owned.excluded = Some(reason.clone());
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Excluded notes with their reasons, for auditing.
+    pub fn excluded(&self) -> impl Iterator<Item = (&OwnedNote, &ExclusionReason)> {
+        self.notes
+            .iter()
+            .filter(|n| !n.spent)
+            .filter_map(|n| n.excluded.as_ref().map(|reason| (n, reason)))
+    }
+
+    /// Drop spent notes, returning them (e.g. for archiving).
+    ///
+    /// Spent notes are kept by default so a rescan doesn't resurrect them;
+    /// only prune once the scan cursor is past the notes' leaves and won't be
+    /// rewound.
+    pub fn prune_spent(&mut self) -> Vec<OwnedNote> {
+        let (spent, live) = std::mem::take(&mut self.notes).into_iter().partition(|n| n.spent);
+        self.notes = live;
+        spent
+    }
+
+    /// Attach a tag to a note. Returns `false` if the commitment is unknown.
+    pub fn tag(&mut self, commitment: &[u8; 32], tag: &str) -> bool {
+        match self.notes.iter_mut().find(|n| &n.commitment == commitment) {
+            Some(owned) => {
+                owned.tags.insert(tag.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a tag from a note. Returns `false` if the note didn't have it.
+    pub fn untag(&mut self, commitment: &[u8; 32], tag: &str) -> bool {
+        self.notes
+            .iter_mut()
+            .find(|n| &n.commitment == commitment)
+            .is_some_and(|owned| owned.tags.remove(tag))
+    }
+
+    /// All notes carrying `tag`, spent or not.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a OwnedNote> {
+        self.notes.iter().filter(move |n| n.tags.contains(tag))
+    }
+
+    /// Spent notes carrying `tag`, in discovery order.
+    ///
+    /// Only covers notes still held; `prune_spent` drops their history.
+    pub fn spend_history<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a OwnedNote> {
+        self.tagged(tag).filter(|n| n.spent)
+    }
+
+    /// Total value spent from notes carrying `tag`.
+    pub fn spent_by_tag(&self, tag: &str) -> u64 {
+        self.spend_history(tag).map(|n| n.note.amount).sum()
+    }
+
+    /// Every tag in use.
+    pub fn tags(&self) -> BTreeSet<&str> {
+        self.notes.iter().flat_map(|n| n.tags.iter().map(String::as_str)).collect()
+    }
+
+    /// Total value of unspent notes.
+    pub fn balance(&self) -> u64 {
+        self.unspent().map(|n| n.note.amount).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_spend_and_balance() {
+        let mut state = WalletState::new();
+        let a = Note::new(70, [1; 32], [1; 32]);
+        let b = Note::new(30, [1; 32], [2; 32]);
+
+        assert!(state.add_note(a.clone(), 0));
+        assert!(state.add_note(b, 3));
+        assert!(!state.add_note(a.clone(), 0), "duplicate note should be ignored");
+        assert_eq!(state.balance(), 100);
+
+        assert!(state.mark_spent(&commit(&a)));
+        assert_eq!(state.balance(), 30);
+        assert!(!state.mark_spent(&[0xff; 32]));
+    }
+
+    #[test]
+    fn test_claim_outputs_matches_by_commitment() {
+        let mut outputs = vec![Note::new(95, [7; 32], [1; 32]), Note::new(13, [1; 32], [2; 32])];
+        crate::output_order::canonicalize_outputs(&mut outputs);
+        let committed: Vec<([u8; 32], u64)> = outputs.iter().zip(40..).map(|(n, i)| (commit(n), i)).collect();
+
+        let mut state = WalletState::new();
+        let change = Note::new(13, [1; 32], [2; 32]);
+        let unrelated = Note::new(13, [1; 32], [3; 32]);
+        assert_eq!(state.claim_outputs([change.clone(), unrelated], &committed), 1);
+        let leaf_index = 40 + outputs.iter().position(|n| *n == change).unwrap() as u64;
+        assert_eq!(state.find(&commit(&change)).unwrap().leaf_index, leaf_index);
+        assert_eq!(state.claim_outputs([change], &committed), 0);
+    }
+
+    #[test]
+    fn test_excluded_notes_leave_balance_and_are_audited() {
+        let mut state = WalletState::new();
+        let rotated = Note::new(500, [9; 32], [1; 32]);
+        let dust = Note::new(3, [1; 32], [2; 32]);
+        let spent = Note::new(40, [1; 32], [3; 32]);
+        state.add_note(rotated.clone(), 0);
+        state.add_note(dust.clone(), 1);
+        state.add_note(spent.clone(), 2);
+        state.add_note(Note::new(60, [1; 32], [4; 32]), 3);
+        state.mark_spent(&commit(&spent));
+
+        assert_eq!(state.exclude_owner(&[9; 32]), 1);
+        assert_eq!(state.exclude_dust(10), 1);
+        assert_eq!(state.balance(), 60);
+        assert_eq!(state.unspent().count(), 1);
+
+        let audit: Vec<_> = state.excluded().map(|(n, r)| (n.note.amount, r.clone())).collect();
+        assert_eq!(audit, vec![(500, ExclusionReason::KeyRotated), (3, ExclusionReason::Dust { fee_floor: 10 })]);
+
+        assert!(state.include(&commit(&rotated)));
+        assert_eq!(state.balance(), 560);
+
+        let pruned = state.prune_spent();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(state.notes.len(), 3);
+    }
+
+    #[test]
+    fn test_tags_and_spend_history() {
+        let mut state = WalletState::new();
+        let rent = Note::new(900, [1; 32], [1; 32]);
+        let payroll = Note::new(2000, [1; 32], [2; 32]);
+        let change = Note::new(100, [1; 32], [3; 32]);
+        for (i, note) in [&rent, &payroll, &change].into_iter().enumerate() {
+            state.add_note(note.clone(), i as u64);
+        }
+
+        assert!(state.tag(&commit(&rent), "housing"));
+        assert!(state.tag(&commit(&payroll), "income"));
+        assert!(state.tag(&commit(&change), "housing"));
+        assert!(!state.tag(&[0xff; 32], "housing"));
+        state.mark_spent(&commit(&rent));
+
+        assert_eq!(state.tagged("housing").count(), 2);
+        let history: Vec<u64> = state.spend_history("housing").map(|n| n.note.amount).collect();
+        assert_eq!(history, vec![900]);
+        assert_eq!(state.spent_by_tag("housing"), 900);
+        assert_eq!(state.spent_by_tag("income"), 0);
+        assert_eq!(state.tags().into_iter().collect::<Vec<_>>(), vec!["housing", "income"]);
+
+        assert!(state.untag(&commit(&change), "housing"));
+        assert!(!state.untag(&commit(&change), "housing"));
+        assert_eq!(state.tagged("housing").count(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+//! wasm-bindgen exports for browser wallets
+//!
+//! The web wallet calls these instead of its own TypeScript commitment,
+//! nullifier, Merkle and note-encryption code, so it computes exactly what
+//! the prover does. Byte strings are `Uint8Array`s, length-checked on the
+//! way in; amounts are `bigint`.
+//!
+//! Build with:
+//! wasm-pack build core --target web --no-default-features --features wasm
+
+use wasm_bindgen::prelude::*;
+
+use crate::encrypted_note::NotePlaintext;
+use crate::encryption::EncryptedNote;
+use crate::merkle::{MerkleProof, MerkleTree};
+use crate::note::{self, Note};
+use crate::owner;
+
+fn fixed<const N: usize>(name: &str, bytes: &[u8]) -> Result<[u8; N], JsError> {
+    crate::hex::fixed(name, bytes).map_err(|e| JsError::new(&e))
+}
+
+fn note(amount: u64, owner_pubkey: &[u8], blinding: &[u8]) -> Result<Note, JsError> {
+    Ok(Note::new(amount, fixed("ownerPubkey", owner_pubkey)?, fixed("blinding", blinding)?))
+}
+
+/// Note commitment (32 bytes).
+#[wasm_bindgen]
+pub fn commit(amount: u64, owner_pubkey: &[u8], blinding: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(note::commit(&note(amount, owner_pubkey, blinding)?).to_vec())
+}
+
+/// Owner pubkey (32 bytes) for a 33-byte compressed or 65-byte uncompressed
+/// public key; throws if it isn't a secp256k1 point.
+#[wasm_bindgen(js_name = ownerFromPublicKey)]
+pub fn owner_from_public_key(public_key: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(owner::owner_from_public_key(public_key).map_err(|e| JsError::new(&e))?.to_vec())
+}
+
+/// Nullifier of a 65-byte nullifier signature (32 bytes).
+#[wasm_bindgen(js_name = computeNullifier)]
+pub fn compute_nullifier(signature: &[u8]) -> Result<Vec<u8>, JsError> {
+    let signature: [u8; 65] = fixed("signature", signature)?;
+    Ok(note::compute_nullifier(&signature).to_vec())
+}
+
+/// Check that `leaf` sits at `leafIndex` under `root`. `siblings` is the
+/// concatenation of the 32-byte sibling hashes, leaf to root.
+#[wasm_bindgen(js_name = verifyMerkleProof)]
+pub fn verify_merkle_proof(leaf: &[u8], leaf_index: u64, siblings: &[u8], root: &[u8]) -> Result<bool, JsError> {
+    if !siblings.len().is_multiple_of(32) {
+        return Err(JsError::new(&format!("siblings length {} is not a multiple of 32", siblings.len())));
+    }
+    let siblings = siblings.chunks(32).map(|s| fixed("sibling", s)).collect::<Result<Vec<[u8; 32]>, _>>()?;
+    Ok(MerkleTree::verify_proof(
+        fixed("leaf", leaf)?,
+        &MerkleProof::new(leaf_index, siblings),
+        fixed("root", root)?,
+    ))
+}
+
+/// Encrypt a note for a 33-byte compressed view key. Returns the blob
+/// published alongside the commitment.
+#[wasm_bindgen(js_name = encryptNote)]
+pub fn encrypt_note(
+    amount: u64,
+    owner_pubkey: &[u8],
+    blinding: &[u8],
+    leaf_index_hint: Option<u64>,
+    view_pubkey: &[u8],
+) -> Result<Vec<u8>, JsError> {
+    let encrypted = NotePlaintext::new(note(amount, owner_pubkey, blinding)?, leaf_index_hint)
+        .encrypt(&fixed("viewPubkey", view_pubkey)?)
+        .map_err(|e| JsError::new(&e))?;
+    bincode::serialize(&encrypted).map_err(|e| JsError::new(&format!("Serialize failed: {}", e)))
+}
+
+/// A note decrypted by `decryptNote`.
+#[wasm_bindgen]
+pub struct DecryptedNote {
+    plaintext: NotePlaintext,
+}
+
+#[wasm_bindgen]
+impl DecryptedNote {
+    #[wasm_bindgen(getter)]
+    pub fn amount(&self) -> u64 {
+        self.plaintext.note.amount
+    }
+
+    #[wasm_bindgen(getter, js_name = ownerPubkey)]
+    pub fn owner_pubkey(&self) -> Vec<u8> {
+        self.plaintext.note.owner_pubkey.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn blinding(&self) -> Vec<u8> {
+        self.plaintext.note.blinding.to_vec()
+    }
+
+    #[wasm_bindgen(getter, js_name = leafIndexHint)]
+    pub fn leaf_index_hint(&self) -> Option<u64> {
+        self.plaintext.leaf_index_hint
+    }
+
+    /// Commitment of the decrypted note, to match against tree leaves.
+    pub fn commitment(&self) -> Vec<u8> {
+        note::commit(&self.plaintext.note).to_vec()
+    }
+}
+
+/// Try to decrypt a note blob with a 32-byte view secret key. Returns
+/// `undefined` if the note isn't addressed to this key.
+#[wasm_bindgen(js_name = decryptNote)]
+pub fn decrypt_note(blob: &[u8], view_secret: &[u8]) -> Result<Option<DecryptedNote>, JsError> {
+    let encrypted: EncryptedNote =
+        bincode::deserialize(blob).map_err(|e| JsError::new(&format!("Malformed note blob: {}", e)))?;
+    Ok(NotePlaintext::decrypt(&encrypted, &fixed("viewSecret", view_secret)?).map(|plaintext| DecryptedNote { plaintext }))
+}
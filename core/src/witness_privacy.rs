@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use crate::sp1_types::Witness;
+
+/// How sensitive a witness field is once it leaves the wallet's machine.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Sensitivity {
+    /// Enough on its own to link, value or steal notes (blindings, signatures).
+    Secret,
+    /// Reveals transaction structure or tree positions, but not spend authority.
+    Sensitive,
+    /// Pure function of other witness fields; can be recomputed in-circuit.
+    Derivable,
+}
+
+/// Exposure of a single witness field to the prover.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldExposure {
+    /// Field name as it appears in `Witness`.
+    pub field: String,
+    /// Number of elements present (notes, signatures, hashes).
+    pub count: usize,
+    /// Approximate number of payload bytes carried by the field.
+    pub bytes: usize,
+    pub sensitivity: Sensitivity,
+    /// Whether the field still leaves the machine (false once stripped).
+    pub transmitted: bool,
+}
+
+/// Report of exactly which private fields a witness would send to a prover.
+///
+/// # Purpose
+/// When using the network prover, the witness is handed to a third party.
+/// This report lets operators see what leaves the machine before submitting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyAssessment {
+    pub fields: Vec<FieldExposure>,
+}
+
+impl PrivacyAssessment {
+    /// Fields that are still transmitted.
+    pub fn transmitted(&self) -> impl Iterator<Item = &FieldExposure> {
+        self.fields.iter().filter(|f| f.transmitted)
+    }
+
+    /// Total bytes that leave the machine.
+    pub fn transmitted_bytes(&self) -> usize {
+        self.transmitted().map(|f| f.bytes).sum()
+    }
+
+    /// Whether any secret material (blindings, signatures) is transmitted.
+    pub fn exposes_secrets(&self) -> bool {
+        self.transmitted().any(|f| f.sensitivity == Sensitivity::Secret && f.count > 0)
+    }
+
+    /// Derivable fields that are still transmitted and could be stripped.
+    pub fn strippable(&self) -> impl Iterator<Item = &FieldExposure> {
+        self.transmitted()
+            .filter(|f| f.sensitivity == Sensitivity::Derivable && f.count > 0)
+    }
+}
+
+impl core::fmt::Display for PrivacyAssessment {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Witness privacy assessment:")?;
+        for field in &self.fields {
+            writeln!(
+                f,
+                "  {:<32} {:>3} item(s) {:>6} bytes  {:?}{}",
+                field.field,
+                field.count,
+                field.bytes,
+                field.sensitivity,
+                if field.transmitted { "" } else { " (stripped)" }
+            )?;
+        }
+        write!(f, "  total transmitted: {} bytes", self.transmitted_bytes())
+    }
+}
+
+/// Bytes carried by one serialized note (amount + owner_pubkey + blinding).
+const NOTE_BYTES: usize = 8 + 32 + 32;
+
+impl Witness {
+    /// Assess which private fields of this witness would leave the machine.
+    pub fn privacy_assessment(&self) -> PrivacyAssessment {
+        let sig_bytes = |sigs: &[Vec<u8>]| sigs.iter().map(|s| s.len()).sum::<usize>();
+        let proof_bytes: usize = self.input_proofs.iter().map(|p| 8 + p.siblings.len() * 32).sum();
+
+        let fields = vec![
+            FieldExposure {
+                field: "input_notes".to_string(),
+                count: self.input_notes.len(),
+                bytes: self.input_notes.len() * NOTE_BYTES,
+                sensitivity: Sensitivity::Secret,
+                transmitted: true,
+            },
+            FieldExposure {
+                field: "output_notes".to_string(),
+                count: self.output_notes.len(),
+                bytes: self.output_notes.len() * NOTE_BYTES,
+                sensitivity: Sensitivity::Secret,
+                transmitted: true,
+            },
+            FieldExposure {
+                field: "nullifier_signatures".to_string(),
+                count: self.nullifier_signatures.len(),
+                bytes: sig_bytes(&self.nullifier_signatures),
+                sensitivity: Sensitivity::Secret,
+                transmitted: true,
+            },
+            FieldExposure {
+                field: "tx_signatures".to_string(),
+                count: self.tx_signatures.len(),
+                bytes: sig_bytes(&self.tx_signatures),
+                sensitivity: Sensitivity::Secret,
+                transmitted: true,
+            },
+            FieldExposure {
+                field: "input_indices".to_string(),
+                count: self.input_indices.len(),
+                bytes: self.input_indices.len() * 8,
+                sensitivity: Sensitivity::Sensitive,
+                transmitted: true,
+            },
+            FieldExposure {
+                field: "input_proofs".to_string(),
+                count: self.input_proofs.len(),
+                bytes: proof_bytes,
+                sensitivity: Sensitivity::Sensitive,
+                transmitted: true,
+            },
+            FieldExposure {
+                field: "precomputed_nullifiers".to_string(),
+                count: self.precomputed_nullifiers.len(),
+                bytes: self.precomputed_nullifiers.len() * 32,
+                sensitivity: Sensitivity::Derivable,
+                transmitted: !self.precomputed_nullifiers.is_empty(),
+            },
+            FieldExposure {
+                field: "precomputed_input_commitments".to_string(),
+                count: self.precomputed_input_commitments.len(),
+                bytes: self.precomputed_input_commitments.len() * 32,
+                sensitivity: Sensitivity::Derivable,
+                transmitted: !self.precomputed_input_commitments.is_empty(),
+            },
+            FieldExposure {
+                field: "precomputed_output_commitments".to_string(),
+                count: self.precomputed_output_commitments.len(),
+                bytes: self.precomputed_output_commitments.len() * 32,
+                sensitivity: Sensitivity::Derivable,
+                transmitted: !self.precomputed_output_commitments.is_empty(),
+            },
+        ];
+
+        PrivacyAssessment { fields }
+    }
+
+    /// Strip every field that the guest can derive in-circuit.
+    ///
+    /// The precomputed nullifiers and commitments are pure functions of the
+    /// notes and nullifier signatures, so they carry no extra information for
+    /// a third-party prover and are recomputed by `with_derived_values`.
+    pub fn strip_derivable(mut self) -> Self {
+        self.precomputed_nullifiers.clear();
+        self.precomputed_input_commitments.clear();
+        self.precomputed_output_commitments.clear();
+        self
+    }
+
+    /// Recompute derivable fields stripped before network submission.
+    ///
+    /// Inside the zkVM this is cheap (Blake3 only). Values that are already
+    /// present are left untouched so they are still checked against the
+    /// recomputed ones by `simulate_tx_with_precomputed`.
+    pub fn with_derived_values(self) -> Self {
+        if self.has_precomputed_values() {
+            return self;
+        }
+        self.with_precomputed_values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::note::Note;
+    use crate::sp1_types::Witness;
+    use super::*;
+
+    fn sample_witness() -> Witness {
+        let input = Note::new(100, [1; 32], [2; 32]);
+        let out = Note::new(100, [3; 32], [4; 32]);
+        let sigs = vec![vec![7u8; 65]];
+        Witness::new_without_proofs(vec![input], vec![0], sigs.clone(), sigs, vec![out])
+            .with_precomputed_values()
+    }
+
+    #[test]
+    fn test_assessment_reports_secret_fields() {
+        let report = sample_witness().privacy_assessment();
+
+        assert!(report.exposes_secrets());
+        assert_eq!(report.strippable().count(), 3);
+
+        let sigs = report.fields.iter().find(|f| f.field == "nullifier_signatures").unwrap();
+        assert_eq!(sigs.bytes, 65);
+        assert_eq!(sigs.sensitivity, Sensitivity::Secret);
+    }
+
+    #[test]
+    fn test_strip_derivable_removes_precomputed_values() {
+        let witness = sample_witness();
+        let before = witness.privacy_assessment().transmitted_bytes();
+
+        let stripped = witness.strip_derivable();
+        assert!(!stripped.has_precomputed_values());
+
+        let report = stripped.privacy_assessment();
+        assert_eq!(report.strippable().count(), 0);
+        assert_eq!(before - report.transmitted_bytes(), 3 * 32);
+    }
+
+    #[test]
+    fn test_derived_values_match_host_precomputation() {
+        let witness = sample_witness();
+        let restored = witness.clone().strip_derivable().with_derived_values();
+
+        assert_eq!(restored.precomputed_nullifiers, witness.precomputed_nullifiers);
+        assert_eq!(restored.precomputed_input_commitments, witness.precomputed_input_commitments);
+        assert_eq!(restored.precomputed_output_commitments, witness.precomputed_output_commitments);
+    }
+}
@@ -0,0 +1,224 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::hex::Bytes32;
+use crate::sp1_types::{PublicInputs, Witness};
+
+/// Format version of sealed witness files.
+pub const SEALED_WITNESS_VERSION: u8 = 1;
+
+/// A proof job's inputs as persisted in the queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredJob {
+    pub public_inputs: PublicInputs,
+    pub witness: Witness,
+}
+
+/// A witness encrypted at rest with AES-256-GCM under an operator key.
+///
+/// # Security
+/// Persisted witnesses contain note blindings and spend signatures, i.e.
+/// everything needed to steal the associated notes. The job id is bound as
+/// associated data so a sealed file cannot be swapped between jobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedWitness {
+    pub version: u8,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedWitness {
+    /// Encrypt a job under `key`, binding it to `job_id`.
+    pub fn seal(job: &StoredJob, job_id: &str, key: &[u8; 32]) -> Result<Self, String> {
+        let plaintext = bincode::serialize(job)
+            .map_err(|e| format!("Serialize failed: {}", e))?;
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to create cipher: {}", e))?;
+
+        let nonce_bytes: [u8; 12] = rand::random();
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: &plaintext, aad: job_id.as_bytes() })
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+
+        Ok(Self {
+            version: SEALED_WITNESS_VERSION,
+            nonce: nonce_bytes,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt a job sealed for `job_id`.
+    pub fn open(&self, job_id: &str, key: &[u8; 32]) -> Result<StoredJob, String> {
+        if self.version != SEALED_WITNESS_VERSION {
+            return Err(format!("Unsupported sealed witness version {}", self.version));
+        }
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to create cipher: {}", e))?;
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&self.nonce), Payload { msg: &self.ciphertext, aad: job_id.as_bytes() })
+            .map_err(|_| "Failed to decrypt witness (wrong key or tampered file)".to_string())?;
+        bincode::deserialize(&plaintext).map_err(|e| format!("Deserialize failed: {}", e))
+    }
+}
+
+/// Directory of sealed proof jobs, one file per job id.
+pub struct WitnessStore {
+    dir: PathBuf,
+    key: [u8; 32],
+}
+
+impl WitnessStore {
+    /// Open (creating if needed) a store rooted at `dir`.
+    pub fn open(dir: impl AsRef<Path>, key: [u8; 32]) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create witness store {}: {}", dir.display(), e))?;
+        Ok(Self { dir, key })
+    }
+
+    /// Parse the operator key from a 0x-prefixed or bare 64-char hex string.
+    pub fn key_from_hex(hex_key: &str) -> Result<[u8; 32], String> {
+        hex_key
+            .trim()
+            .parse::<Bytes32>()
+            .map(|key| key.0)
+            .map_err(|e| format!("Witness store key must be 32 bytes of hex: {}", e))
+    }
+
+    fn path(&self, job_id: &str) -> Result<PathBuf, String> {
+        if job_id.is_empty() || !job_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid job id {:?}", job_id));
+        }
+        Ok(self.dir.join(format!("{}.witness", job_id)))
+    }
+
+    /// Seal and persist a job.
+    pub fn store(&self, job_id: &str, job: &StoredJob) -> Result<(), String> {
+        let sealed = SealedWitness::seal(job, job_id, &self.key)?;
+        let bytes = bincode::serialize(&sealed).map_err(|e| format!("Serialize failed: {}", e))?;
+        let path = self.path(job_id)?;
+        let mut file = std::fs::File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        file.write_all(&bytes)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Load and decrypt a persisted job.
+    pub fn load(&self, job_id: &str) -> Result<StoredJob, String> {
+        let path = self.path(job_id)?;
+        let bytes = std::fs::read(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let sealed: SealedWitness = bincode::deserialize(&bytes)
+            .map_err(|e| format!("Corrupt sealed witness {}: {}", path.display(), e))?;
+        sealed.open(job_id, &self.key)
+    }
+
+    /// Ids of all persisted jobs (for resuming after restart).
+    pub fn job_ids(&self) -> Result<Vec<String>, String> {
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to list {}: {}", self.dir.display(), e))?;
+        let mut ids: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().into_string().ok()?;
+                name.strip_suffix(".witness").map(str::to_string)
+            })
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Securely delete a completed job.
+    ///
+    /// The file is overwritten with zeros and synced before being unlinked,
+    /// so the ciphertext doesn't linger in freed blocks. (Copy-on-write
+    /// filesystems and SSD wear levelling may still retain old blocks; the
+    /// encryption at rest is the primary protection.)
+    pub fn remove(&self, job_id: &str) -> Result<(), String> {
+        let path = self.path(job_id)?;
+        let len = match std::fs::metadata(&path) {
+            Ok(meta) => meta.len() as usize,
+            Err(_) => return Ok(()),
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        file.write_all(&vec![0u8; len])
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("Failed to overwrite {}: {}", path.display(), e))?;
+        drop(file);
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+
+    fn sample_job() -> StoredJob {
+        let input = Note::new(100, [1; 32], [2; 32]);
+        let out = Note::new(100, [3; 32], [4; 32]);
+        let sigs = vec![vec![7u8; 65]];
+        StoredJob {
+            public_inputs: PublicInputs::new([9; 32]),
+            witness: Witness::new_without_proofs(vec![input], vec![0], sigs.clone(), sigs, vec![out]),
+        }
+    }
+
+    fn temp_store(name: &str, key: [u8; 32]) -> WitnessStore {
+        let dir = std::env::temp_dir().join(format!("ghostclaw-witness-store-{}-{}", name, std::process::id()));
+        WitnessStore::open(dir, key).unwrap()
+    }
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let key = [5u8; 32];
+        let sealed = SealedWitness::seal(&sample_job(), "job-1", &key).unwrap();
+        let opened = sealed.open("job-1", &key).unwrap();
+
+        assert_eq!(opened.public_inputs, sample_job().public_inputs);
+        assert_eq!(opened.witness.input_notes, sample_job().witness.input_notes);
+    }
+
+    #[test]
+    fn test_open_rejects_wrong_key_and_job_id() {
+        let sealed = SealedWitness::seal(&sample_job(), "job-1", &[5u8; 32]).unwrap();
+
+        assert!(sealed.open("job-1", &[6u8; 32]).is_err());
+        assert!(sealed.open("job-2", &[5u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_store_load_remove() {
+        let store = temp_store("roundtrip", [8u8; 32]);
+        store.store("abc", &sample_job()).unwrap();
+
+        assert_eq!(store.job_ids().unwrap(), vec!["abc".to_string()]);
+        assert_eq!(store.load("abc").unwrap().witness.output_notes, sample_job().witness.output_notes);
+
+        store.remove("abc").unwrap();
+        assert!(store.job_ids().unwrap().is_empty());
+        assert!(store.load("abc").is_err());
+    }
+
+    #[test]
+    fn test_rejects_path_traversal_job_id() {
+        let store = temp_store("traversal", [8u8; 32]);
+        assert!(store.store("../escape", &sample_job()).is_err());
+    }
+
+    #[test]
+    fn test_key_from_hex() {
+        let key = WitnessStore::key_from_hex(&format!("0x{}", "ab".repeat(32))).unwrap();
+        assert_eq!(key, [0xab; 32]);
+        assert!(WitnessStore::key_from_hex("1234").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+// ============================================
+// PERSISTENT JOB STORE
+// ============================================
+// One file per proof job under JOB_STORE_DIR, so a restart doesn't drop
+// queued or in-flight requests. Each file holds the job's status record
+// and, until the job finishes, its proof request.
+//
+// SECURITY: proof requests contain note blindings and spend signatures,
+// i.e. everything needed to steal the notes. They are sealed with
+// AES-256-GCM under JOB_STORE_KEY (32 bytes hex) with the job id as
+// associated data, matching core's WitnessStore; the key is required.
+
+const fs = require('fs');
+const path = require('path');
+const crypto = require('crypto');
+
+const SEALED_REQUEST_VERSION = 1;
+const JOB_FILE_SUFFIX = '.job.json';
+
+function parseKey(keyHex) {
+  const hex = (keyHex || '').trim().replace(/^0x/, '');
+  if (!/^[0-9a-fA-F]{64}$/.test(hex)) {
+    throw new Error('JOB_STORE_KEY must be 32 bytes of hex');
+  }
+  return Buffer.from(hex, 'hex');
+}
+
+class JobStore {
+  constructor(dir, keyHex) {
+    this.dir = dir;
+    this.key = parseKey(keyHex);
+    fs.mkdirSync(dir, { recursive: true });
+  }
+
+  path(jobId) {
+    if (!/^[A-Za-z0-9_-]+$/.test(jobId)) {
+      throw new Error(`Invalid job id ${JSON.stringify(jobId)}`);
+    }
+    return path.join(this.dir, `${jobId}${JOB_FILE_SUFFIX}`);
+  }
+
+  seal(jobId, proofRequest) {
+    const nonce = crypto.randomBytes(12);
+    const cipher = crypto.createCipheriv('aes-256-gcm', this.key, nonce);
+    cipher.setAAD(Buffer.from(jobId));
+    const ciphertext = Buffer.concat([cipher.update(JSON.stringify(proofRequest)), cipher.final()]);
+    return {
+      version: SEALED_REQUEST_VERSION,
+      nonce: nonce.toString('base64'),
+      ciphertext: ciphertext.toString('base64'),
+      tag: cipher.getAuthTag().toString('base64')
+    };
+  }
+
+  open(jobId, sealed) {
+    if (sealed.version !== SEALED_REQUEST_VERSION) {
+      throw new Error(`Unsupported sealed request version ${sealed.version}`);
+    }
+    const decipher = crypto.createDecipheriv('aes-256-gcm', this.key, Buffer.from(sealed.nonce, 'base64'));
+    decipher.setAAD(Buffer.from(jobId));
+    decipher.setAuthTag(Buffer.from(sealed.tag, 'base64'));
+    const plaintext = Buffer.concat([
+      decipher.update(Buffer.from(sealed.ciphertext, 'base64')),
+      decipher.final()
+    ]);
+    return JSON.parse(plaintext.toString());
+  }
+
+  // Persist a job; pass proofRequest only while the job still needs it
+  save(jobId, job, proofRequest) {
+    const record = {
+      jobId,
+      job,
+      request: proofRequest ? this.seal(jobId, proofRequest) : null
+    };
+    // Write-then-rename so a crash mid-write can't leave a torn file
+    const file = this.path(jobId);
+    const tmp = `${file}.tmp`;
+    fs.writeFileSync(tmp, JSON.stringify(record));
+    fs.renameSync(tmp, file);
+  }
+
+  remove(jobId) {
+    fs.rmSync(this.path(jobId), { force: true });
+  }
+
+  // Every persisted job as { jobId, job, proofRequest, error }; a request
+  // that can't be opened (wrong key, tampered file) is reported, not thrown
+  loadAll() {
+    return fs.readdirSync(this.dir)
+      .filter(name => name.endsWith(JOB_FILE_SUFFIX))
+      .map(name => {
+        const jobId = name.slice(0, -JOB_FILE_SUFFIX.length);
+        try {
+          const record = JSON.parse(fs.readFileSync(path.join(this.dir, name), 'utf8'));
+          const proofRequest = record.request ? this.open(jobId, record.request) : null;
+          return { jobId, job: record.job, proofRequest, error: null };
+        } catch (e) {
+          return { jobId, job: null, proofRequest: null, error: e.message };
+        }
+      });
+  }
+}
+
+module.exports = { JobStore };
@@ -0,0 +1,96 @@
+// ============================================
+// PRIORITY LANES
+// ============================================
+// A proof request may carry `priority`: "interactive" (the default, for
+// transfers a user is waiting on) or "background" (note consolidation and
+// other batch work). The scheduler always starts the oldest interactive
+// job before any background one, so a backlog of consolidations never
+// delays a payment; a job that is already proving is not interrupted.
+//
+// Tenants can cap how many jobs they have queued or running per lane
+// (`"lanes": { "interactive": 2 }` in TENANTS_FILE), so one API key can't
+// jump the queue by marking everything interactive.
+
+const LANES = ['interactive', 'background'];
+const DEFAULT_LANE = 'interactive';
+
+// Lane named by a request's `priority`, or null if it names none
+function parseLane(priority) {
+  if (priority === undefined || priority === null) return DEFAULT_LANE;
+  return LANES.includes(priority) ? priority : null;
+}
+
+class LaneQueue {
+  constructor() {
+    // lane -> [{ jobId, proofRequest, lane, tenantId, queuedAt }], oldest first
+    this.queues = new Map(LANES.map(lane => [lane, []]));
+    // jobId -> entry, for jobs taken off the queue and not yet finished
+    this.running = new Map();
+    this.counters = new Map(LANES.map(lane => [lane, { started: 0, finished: 0, totalWaitMs: 0, maxWaitMs: 0 }]));
+  }
+
+  get length() {
+    let total = 0;
+    for (const queue of this.queues.values()) total += queue.length;
+    return total;
+  }
+
+  push(entry) {
+    this.queues.get(entry.lane).push({ queuedAt: Date.now(), ...entry });
+  }
+
+  // Next job to start (highest-priority lane first), or undefined
+  shift() {
+    for (const lane of LANES) {
+      const entry = this.queues.get(lane).shift();
+      if (!entry) continue;
+      const wait = Date.now() - entry.queuedAt;
+      const counters = this.counters.get(lane);
+      counters.started++;
+      counters.totalWaitMs += wait;
+      counters.maxWaitMs = Math.max(counters.maxWaitMs, wait);
+      this.running.set(entry.jobId, entry);
+      return entry;
+    }
+    return undefined;
+  }
+
+  finish(jobId) {
+    const entry = this.running.get(jobId);
+    if (!entry) return;
+    this.running.delete(jobId);
+    this.counters.get(entry.lane).finished++;
+  }
+
+  // Queued jobs in the order they will start
+  entries() {
+    return LANES.flatMap(lane => this.queues.get(lane));
+  }
+
+  // Jobs `tenantId` has queued or running in `lane`
+  countFor(tenantId, lane) {
+    const queued = this.queues.get(lane).filter(e => e.tenantId === tenantId).length;
+    const running = [...this.running.values()].filter(e => e.lane === lane && e.tenantId === tenantId).length;
+    return queued + running;
+  }
+
+  // Per-lane queue metrics
+  stats() {
+    const now = Date.now();
+    return Object.fromEntries(LANES.map(lane => {
+      const queue = this.queues.get(lane);
+      const { started, finished, totalWaitMs, maxWaitMs } = this.counters.get(lane);
+      return [lane, {
+        queued: queue.length,
+        running: [...this.running.values()].filter(e => e.lane === lane).length,
+        started,
+        finished,
+        avgWaitMs: started ? Math.round(totalWaitMs / started) : 0,
+        maxWaitMs,
+        oldestQueuedMs: queue.length ? now - queue[0].queuedAt : 0
+      }];
+    }));
+  }
+}
+
+module.exports = { LaneQueue, LANES, DEFAULT_LANE, parseLane };
@@ -0,0 +1,104 @@
+// ============================================
+// PROOF REQUEST FORWARDING
+// ============================================
+// The body of /api/generate-proof is handed to the host as a ProofRequest
+// (core/src/proof_request.rs), which rejects unknown fields. The server
+// forwards every field listed here and rejects a body with any other up
+// front, listing the accepted ones, so a typo fails loudly instead of
+// being dropped on the way: keep the list in step with ProofRequest.
+
+// Forwarded as-is when present
+const REQUEST_FIELDS = [
+  'inputNotes',
+  'outputNotes',
+  'nullifierSignatures',
+  'txSignatures',
+  'inputIndices',
+  'inputProofs',
+  'oldRoot',
+  'chainId',
+  'withdrawal', // { publicAmount, recipient }: the value paid out of the pool
+  'externalNullifier',
+  'nullifierTree',
+  'inputAmountBlindings',
+  'outputAmountBlindings',
+  'deadlineMs'
+];
+
+// Accepted besides the forwarded fields: read by the server itself
+// (`schemaVersion` and `traceId` are forwarded too, with defaults)
+const SERVER_FIELDS = ['schemaVersion', 'traceId', 'priority'];
+
+const ACCEPTED_FIELDS = [...REQUEST_FIELDS, ...SERVER_FIELDS];
+
+// Fields of an unsealed request body that neither the server nor the host
+// understands
+function unknownFields(body) {
+  return Object.keys(body).filter(field => !ACCEPTED_FIELDS.includes(field));
+}
+
+// The ProofRequest to queue for an unsealed request body
+function buildProofRequest(body, traceId) {
+  const proofRequest = { schemaVersion: body.schemaVersion ?? 1 };
+  for (const field of REQUEST_FIELDS) {
+    if (body[field] !== undefined) proofRequest[field] = body[field];
+  }
+  proofRequest.traceId = traceId;
+  return proofRequest;
+}
+
+// A request with only its witness fields sealed (`sealedFields`, see
+// core/src/sealed_fields.rs): the host opens them with its keyring, and the
+// fields in the clear are bound to them, so they're forwarded unchanged
+const SEALED_FIELDS_REQUEST_FIELDS = [
+  'schemaVersion',
+  'oldRoot',
+  'chainId',
+  'withdrawal',
+  'externalNullifier',
+  'deadlineMs',
+  'traceId',
+  'inputCount',
+  'outputCount',
+  'sealedFields' // { version, keyId, ephemeralPubkey, nonce, ciphertext }
+];
+
+// Why a `sealedFields` body can't be queued, or null if it can
+function sealedFieldsProblem(body) {
+  const extra = Object.keys(body).filter(field => field !== 'priority' && !SEALED_FIELDS_REQUEST_FIELDS.includes(field));
+  if (extra.length > 0) {
+    return `Unknown sealed fields request field(s): ${extra.join(', ')}`;
+  }
+  const sealed = body.sealedFields;
+  const validSealed = typeof sealed === 'object' && sealed !== null &&
+    Number.isSafeInteger(sealed.version) &&
+    ['keyId', 'ephemeralPubkey', 'nonce', 'ciphertext'].every(field => typeof sealed[field] === 'string');
+  const isCount = n => Number.isSafeInteger(n) && n >= 0;
+  if (!validSealed || typeof body.oldRoot !== 'string' ||
+      !Number.isSafeInteger(body.chainId) || body.chainId <= 0 ||
+      !isCount(body.inputCount) || !isCount(body.outputCount)) {
+    return 'sealedFields requests need oldRoot, chainId, inputCount, outputCount and ' +
+      'sealedFields { version, keyId, ephemeralPubkey, nonce, ciphertext }';
+  }
+  return null;
+}
+
+// The SealedFieldsRequest to queue for a `sealedFields` body
+function buildSealedFieldsRequest(body, traceId) {
+  const request = { schemaVersion: body.schemaVersion ?? 1 };
+  for (const field of SEALED_FIELDS_REQUEST_FIELDS) {
+    if (body[field] !== undefined) request[field] = body[field];
+  }
+  request.traceId = traceId;
+  return request;
+}
+
+module.exports = {
+  buildProofRequest,
+  unknownFields,
+  ACCEPTED_FIELDS,
+  REQUEST_FIELDS,
+  buildSealedFieldsRequest,
+  sealedFieldsProblem,
+  SEALED_FIELDS_REQUEST_FIELDS
+};
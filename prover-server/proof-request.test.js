@@ -0,0 +1,65 @@
+const test = require('node:test');
+const assert = require('node:assert');
+const { buildProofRequest, unknownFields, buildSealedFieldsRequest, sealedFieldsProblem } = require('./proof-request');
+
+const transfer = {
+  inputNotes: [{ amount: 100, ownerPubkey: '0x01', blinding: '0x02' }],
+  outputNotes: [{ amount: 100, ownerPubkey: '0x03', blinding: '0x04' }],
+  nullifierSignatures: ['0xaa'],
+  txSignatures: ['0xbb'],
+  inputIndices: [0],
+  inputProofs: [[]],
+  oldRoot: '0x05',
+  chainId: 11155111
+};
+
+test('forwards the transfer fields with a schema version and trace ID', () => {
+  const proofRequest = buildProofRequest(transfer, 'trace-1');
+  assert.deepStrictEqual(proofRequest, { schemaVersion: 1, ...transfer, traceId: 'trace-1' });
+});
+
+test('forwards the withdrawal of a zero-output request', () => {
+  const withdrawal = { publicAmount: 100, recipient: '0x00000000000000000000000000000000000000aa' };
+  const proofRequest = buildProofRequest({ ...transfer, outputNotes: [], withdrawal }, 'trace-2');
+  assert.deepStrictEqual(proofRequest.outputNotes, []);
+  assert.deepStrictEqual(proofRequest.withdrawal, withdrawal);
+});
+
+test('forwards the optional witness fields', () => {
+  const optional = {
+    externalNullifier: '0x06',
+    nullifierTree: { oldRoot: '0x07' },
+    inputAmountBlindings: ['0x08'],
+    outputAmountBlindings: ['0x09'],
+    deadlineMs: 1_900_000_000_000
+  };
+  const proofRequest = buildProofRequest({ ...transfer, ...optional, priority: 'background' }, 'trace-3');
+  assert.deepStrictEqual(proofRequest, { schemaVersion: 1, ...transfer, ...optional, traceId: 'trace-3' });
+});
+
+test('reports fields neither the server nor the host accepts', () => {
+  assert.deepStrictEqual(unknownFields({ ...transfer, priority: 'interactive', traceId: 't' }), []);
+  assert.deepStrictEqual(unknownFields({ ...transfer, ownerPubKey: '0x01', inputNote: [] }), ['ownerPubKey', 'inputNote']);
+});
+
+const sealedFieldsRequest = {
+  oldRoot: '0x05',
+  chainId: 11155111,
+  inputCount: 1,
+  outputCount: 1,
+  sealedFields: { version: 2, keyId: 'k1', ephemeralPubkey: '0x02', nonce: '0x03', ciphertext: '0x04' }
+};
+
+test('forwards a sealed fields request unchanged with a trace ID', () => {
+  assert.strictEqual(sealedFieldsProblem({ ...sealedFieldsRequest, priority: 'background' }), null);
+  const request = buildSealedFieldsRequest({ ...sealedFieldsRequest, priority: 'background' }, 'trace-4');
+  assert.deepStrictEqual(request, { schemaVersion: 1, ...sealedFieldsRequest, traceId: 'trace-4' });
+});
+
+test('rejects sealed fields requests that carry witness fields or miss clear ones', () => {
+  assert.match(sealedFieldsProblem({ ...sealedFieldsRequest, inputNotes: [] }), /inputNotes/);
+  const { inputCount, ...shapeless } = sealedFieldsRequest;
+  assert.match(sealedFieldsProblem(shapeless), /need/);
+  const { keyId, ...sealed } = sealedFieldsRequest.sealedFields;
+  assert.match(sealedFieldsProblem({ ...sealedFieldsRequest, sealedFields: sealed }), /need/);
+});
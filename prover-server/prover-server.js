@@ -1,10 +1,22 @@
 const express = require('express');
 const { spawn } = require('child_process');
 const cors = require('cors');
+const fs = require('fs');
 const path = require('path');
 const { createPublicClient, http, parseAbiItem } = require('viem');
 const { sepolia } = require('viem/chains');
 const { blake3 } = require('@noble/hashes/blake3');
+const { JobStore } = require('./job-store');
+const { TenantRegistry, apiKeyFrom } = require('./tenants');
+const { LaneQueue, parseLane } = require('./lanes');
+const { ReservationBook, publicReservation } = require('./reservations');
+const {
+  buildProofRequest,
+  unknownFields,
+  ACCEPTED_FIELDS,
+  buildSealedFieldsRequest,
+  sealedFieldsProblem
+} = require('./proof-request');
 require('dotenv').config({ path: path.join(__dirname, '.env') });
 
 const app = express();
@@ -20,23 +32,41 @@ const PROVER_NETWORK_RPC = process.env.PROVER_NETWORK_RPC || 'https://rpc.mainne
 
 // Proof-required contract - all state changes require valid SP1 proofs
 // SECURITY FIX: Contract now decodes outputs from publicValues (proof binding bypass fix)
-const LEDGER_CONTRACT = process.env.LEDGER_CONTRACT || '0xF3Ac04b13dfb9D879c00Bd9F5924f80C7DB58AD0';
+// `sp1-host deploy` writes DEPLOYMENT_MANIFEST ({ ledger, deployBlock, ... })
+const DEPLOYMENT = process.env.DEPLOYMENT_MANIFEST
+  ? JSON.parse(fs.readFileSync(process.env.DEPLOYMENT_MANIFEST, 'utf8'))
+  : {};
+const LEDGER_CONTRACT = process.env.LEDGER_CONTRACT || DEPLOYMENT.ledger || '0xF3Ac04b13dfb9D879c00Bd9F5924f80C7DB58AD0';
 
 // RPC URL for blockchain queries
 const RPC_URL = process.env.RPC_URL || 'https://eth-sepolia.g.alchemy.com/v2/YOUR_API_KEY';
-const DEPLOYMENT_BLOCK = BigInt(process.env.DEPLOYMENT_BLOCK || '7662871');
+const DEPLOYMENT_BLOCK = BigInt(process.env.DEPLOYMENT_BLOCK || DEPLOYMENT.deployBlock || '7662871');
+
+// Ledger indexer (`sp1-host indexer`); inputs are checked against it instead
+// of scraping the ledger's events here
+const INDEXER_URL = process.env.INDEXER_URL;
 
 // Detect localhost mode
 const IS_LOCALHOST = RPC_URL.includes('localhost') || RPC_URL.includes('127.0.0.1');
 
-// Blake3 domain separator (must match Rust core/src/note.rs)
-const NOTE_COMMITMENT_DOMAIN = new TextEncoder().encode('NOTE_COMMITMENT_v1');
+// Offline/dev only: prove even when the host can't check a request's old
+// root against the ledger. Such responses carry `unverifiedRoot: true` and
+// relayers refuse them.
+const HOST_ARGS = process.env.ALLOW_UNVERIFIED_ROOT === '1' ? ['--allow-unverified-root'] : [];
+
+// Blake3 domain separator per note version (must match Rust core/src/note.rs):
+// "NOTE_COMMITMENT_v" followed by the version in decimal
+function noteCommitmentDomain(version = 1) {
+  return new TextEncoder().encode(`NOTE_COMMITMENT_v${version}`);
+}
 
 /**
  * SECURITY CRITICAL: Compute note commitment using Blake3
  * Must exactly match the Rust implementation in core/src/note.rs
  */
-function computeCommitment(amount, ownerPubkey, blinding) {
+function computeCommitment(amount, ownerPubkey, blinding, version = 1) {
+  const domain = noteCommitmentDomain(version);
+
   // amount: u64 as little-endian bytes
   const amountLE = new Uint8Array(8);
   let amt = BigInt(amount);
@@ -52,30 +82,20 @@ function computeCommitment(amount, ownerPubkey, blinding) {
   const blindingBytes = Buffer.from(blinding.replace('0x', '').padStart(64, '0'), 'hex');
 
   // Hash: domain || amount || owner || blinding
-  const preimage = new Uint8Array(NOTE_COMMITMENT_DOMAIN.length + 8 + 32 + 32);
-  preimage.set(NOTE_COMMITMENT_DOMAIN, 0);
-  preimage.set(amountLE, NOTE_COMMITMENT_DOMAIN.length);
-  preimage.set(ownerBytes, NOTE_COMMITMENT_DOMAIN.length + 8);
-  preimage.set(blindingBytes, NOTE_COMMITMENT_DOMAIN.length + 8 + 32);
+  const preimage = new Uint8Array(domain.length + 8 + 32 + 32);
+  preimage.set(domain, 0);
+  preimage.set(amountLE, domain.length);
+  preimage.set(ownerBytes, domain.length + 8);
+  preimage.set(blindingBytes, domain.length + 8 + 32);
 
   return '0x' + Buffer.from(blake3(preimage)).toString('hex');
 }
 
 /**
- * SECURITY CRITICAL: Verify that input note commitments exist on-chain
- * This prevents the "infinite mint" attack where attackers create fake notes
- *
- * Note: In mock mode (SP1_PROVER=mock), verification is skipped for localhost testing.
+ * Every OutputCommitted event since DEPLOYMENT_BLOCK, with a map from
+ * commitment to leaf index.
  */
-async function verifyInputCommitmentsExist(inputNotes, inputIndices) {
-  // Skip verification in mock mode for localhost testing
-  if (SP1_PROVER === 'mock' && IS_LOCALHOST) {
-    console.log('[Security] Mock mode on localhost - skipping on-chain commitment verification');
-    return { events: [], onChainCommitments: new Map() };
-  }
-
-  console.log('[Security] Verifying input commitments exist on-chain...');
-
+async function scrapeCommitments() {
   // Define chain for localhost or Sepolia
   const chain = IS_LOCALHOST
     ? { ...sepolia, id: 31337, name: 'Localhost', rpcUrls: { default: { http: [RPC_URL] } } }
@@ -102,6 +122,44 @@ async function verifyInputCommitmentsExist(inputNotes, inputIndices) {
     const commitment = event.args.commitment.toLowerCase();
     onChainCommitments.set(commitment, index);
   });
+  return { events, onChainCommitments };
+}
+
+/**
+ * Leaf indices of the inputs' commitments from the indexer (`sp1-host
+ * indexer`), so the ledger's events are scraped in one place.
+ */
+async function indexedCommitments(inputNotes) {
+  const onChainCommitments = new Map();
+  for (const note of inputNotes) {
+    const commitment = computeCommitment(note.amount, note.ownerPubkey, note.blinding, note.version).toLowerCase();
+    const response = await fetch(`${INDEXER_URL}/commitments/${commitment}`);
+    if (response.ok) {
+      onChainCommitments.set(commitment, (await response.json()).index);
+    } else if (response.status !== 404) {
+      throw new Error(`Indexer lookup failed (${response.status}): ${await response.text()}`);
+    }
+  }
+  return { events: [], onChainCommitments };
+}
+
+/**
+ * SECURITY CRITICAL: Verify that input note commitments exist on-chain
+ * This prevents the "infinite mint" attack where attackers create fake notes
+ *
+ * Note: In mock mode (SP1_PROVER=mock), verification is skipped for localhost testing.
+ */
+async function verifyInputCommitmentsExist(inputNotes, inputIndices) {
+  // Skip verification in mock mode for localhost testing
+  if (SP1_PROVER === 'mock' && IS_LOCALHOST) {
+    console.log('[Security] Mock mode on localhost - skipping on-chain commitment verification');
+    return { events: [], onChainCommitments: new Map() };
+  }
+
+  console.log('[Security] Verifying input commitments exist on-chain...');
+  const { events, onChainCommitments } = INDEXER_URL
+    ? await indexedCommitments(inputNotes)
+    : await scrapeCommitments();
 
   // Verify each input note
   for (let i = 0; i < inputNotes.length; i++) {
@@ -112,7 +170,8 @@ async function verifyInputCommitmentsExist(inputNotes, inputIndices) {
     const commitment = computeCommitment(
       note.amount,
       note.ownerPubkey,
-      note.blinding
+      note.blinding,
+      note.version
     ).toLowerCase();
 
     console.log(`[Security] Input ${i}: commitment=${commitment.slice(0, 18)}..., claimedIndex=${expectedIndex}`);
@@ -143,13 +202,112 @@ async function verifyInputCommitmentsExist(inputNotes, inputIndices) {
 
 // Track ongoing proof jobs
 const proofJobs = new Map();
+// Requests of jobs that haven't finished, for persistence
+const pendingRequests = new Map();
+// Running sp1-host processes, stopped on shutdown
+const activeProvers = new Set();
+
+// Persist jobs across restarts when JOB_STORE_DIR is set (see job-store.js)
+const JOB_STORE_DIR = process.env.JOB_STORE_DIR;
+const COMPLETED_JOB_TTL_MS = 10 * 60 * 1000;
+let jobStore = null;
+if (JOB_STORE_DIR) {
+  try {
+    jobStore = new JobStore(JOB_STORE_DIR, process.env.JOB_STORE_KEY);
+  } catch (e) {
+    console.error(`[JobStore] ${e.message}`);
+    process.exit(1);
+  }
+}
+
+function isFinished(job) {
+  return job.status === STAGES.SUCCESS || job.status === STAGES.ERROR;
+}
+
+function persistJob(jobId) {
+  if (!jobStore) return;
+  const job = proofJobs.get(jobId);
+  if (!job) return;
+  try {
+    jobStore.save(jobId, job, isFinished(job) ? null : pendingRequests.get(jobId));
+  } catch (e) {
+    console.error(`[JobStore] Failed to persist ${jobId}: ${e.message}`);
+  }
+}
+
+function forgetJob(jobId) {
+  proofJobs.delete(jobId);
+  pendingRequests.delete(jobId);
+  if (jobStore) jobStore.remove(jobId);
+}
+
+// Per-tenant API keys, quotas and metering when TENANTS_FILE is set (see tenants.js)
+const TENANTS_FILE = process.env.TENANTS_FILE;
+// Estimated network cost per million cycles (PROVE base units), for metering
+const NETWORK_COST_PER_MCYCLE = BigInt(process.env.NETWORK_COST_PER_MCYCLE || '0');
+let tenants = null;
+if (TENANTS_FILE) {
+  try {
+    tenants = new TenantRegistry(TENANTS_FILE, process.env.TENANT_USAGE_FILE);
+  } catch (e) {
+    console.error(`[Tenants] ${e.message}`);
+    process.exit(1);
+  }
+}
+
+// Resolve the caller's tenant; open access when no tenants are configured
+function requireTenant(req, res, next) {
+  if (!tenants) return next();
+  const tenant = tenants.authenticate(apiKeyFrom(req));
+  if (!tenant) {
+    return res.status(401).json({ error: 'Missing or invalid API key' });
+  }
+  req.tenant = tenant;
+  next();
+}
+
+// Jobs are only visible to the tenant that submitted them
+function visibleTo(job, req) {
+  return !tenants || req.tenant.admin || job.tenantId === req.tenant.id;
+}
+
+// Meter a finished job against its tenant
+function meterJob(job, response) {
+  if (!tenants || !job?.tenantId) return;
+  if (!response) {
+    tenants.meter(job.tenantId, { failures: 1 });
+    return;
+  }
+  const cycles = response.cycles || 0;
+  const onNetwork = response.routing ? response.routing.backend === 'network' : SP1_PROVER === 'network';
+  const networkSpend = onNetwork ? (BigInt(cycles) * NETWORK_COST_PER_MCYCLE) / 1000000n : 0n;
+  tenants.meter(job.tenantId, { proofs: 1, cycles, networkSpend: networkSpend.toString() });
+}
+
+// Nullifier reservations for two-phase submit (see reservations.js)
+const reservations = new ReservationBook({
+  ttlMs: Number(process.env.RESERVATION_TTL_MS || 5 * 60 * 1000),
+  maxRenewals: Number(process.env.RESERVATION_MAX_RENEWALS || 3),
+  perTenant: Number(process.env.RESERVATIONS_PER_TENANT || 20)
+});
+setInterval(() => reservations.prune(), 60 * 1000).unref();
+
+// Keep a finished job's copy of its reservation in step, so a restart
+// doesn't hand back renewals already used
+function persistReservation(reservation) {
+  const job = proofJobs.get(reservation.jobId);
+  if (!job?.reservation) return;
+  job.reservation = { ...job.reservation, expiresAt: reservation.expiresAt, renewals: reservation.renewals };
+  persistJob(reservation.jobId);
+}
 
 // ============================================
 // JOB QUEUE SYSTEM
 // ============================================
 // Process proofs sequentially to prevent race conditions and ensure
-// merkle tree state consistency between proofs
-const jobQueue = [];
+// merkle tree state consistency between proofs; interactive requests are
+// started before background ones (see lanes.js)
+const jobQueue = new LaneQueue();
 let isProcessing = false;
 const MAX_CONCURRENT = 1; // Process one at a time for safety
 let activeJobs = 0;
@@ -178,7 +336,7 @@ function processQueue() {
   if (!nextJob) return;
 
   activeJobs++;
-  console.log(`[Queue] Starting job ${nextJob.jobId}. Active: ${activeJobs}, Queued: ${jobQueue.length}`);
+  console.log(`[Queue] Starting ${nextJob.lane} job ${nextJob.jobId}. Active: ${activeJobs}, Queued: ${jobQueue.length}`);
 
   // Update queue positions for remaining jobs
   updateQueuePositions();
@@ -187,6 +345,11 @@ function processQueue() {
   executeProofGeneration(nextJob.jobId, nextJob.proofRequest)
     .finally(() => {
       activeJobs--;
+      jobQueue.finish(nextJob.jobId);
+      const finishedJob = proofJobs.get(nextJob.jobId);
+      if (finishedJob) finishedJob.completedAt = Date.now();
+      pendingRequests.delete(nextJob.jobId);
+      persistJob(nextJob.jobId);
       console.log(`[Queue] Job ${nextJob.jobId} finished. Active: ${activeJobs}, Queued: ${jobQueue.length}`);
       // Process next job in queue
       processQueue();
@@ -194,11 +357,12 @@ function processQueue() {
 }
 
 function updateQueuePositions() {
-  jobQueue.forEach((queuedJob, index) => {
+  const queued = jobQueue.entries();
+  queued.forEach((queuedJob, index) => {
     const job = proofJobs.get(queuedJob.jobId);
     if (job && job.status === STAGES.QUEUED) {
       job.queuePosition = index + 1;
-      job.stageDescription = `Queued (position ${index + 1} of ${jobQueue.length})`;
+      job.stageDescription = `Queued (position ${index + 1} of ${queued.length})`;
       proofJobs.set(queuedJob.jobId, job);
     }
   });
@@ -213,6 +377,22 @@ function executeProofGeneration(jobId, proofRequest) {
       return;
     }
 
+    // Don't start a prover for a client that stopped waiting in the queue
+    if (proofRequest.deadlineMs !== undefined && Date.now() >= proofRequest.deadlineMs) {
+      console.log(`[${jobId}] Deadline passed while queued; not proving`);
+      proofJobs.set(jobId, {
+        ...job,
+        status: STAGES.ERROR,
+        stage: STAGES.ERROR,
+        stageDescription: 'Deadline exceeded',
+        progress: 0,
+        error: 'Deadline exceeded: the request was still queued',
+        code: 'DEADLINE_EXCEEDED'
+      });
+      resolve();
+      return;
+    }
+
     // Update status to preparing
     updateJobStatus(jobId, STAGES.PREPARING, 'Initializing prover...', 10);
 
@@ -225,6 +405,10 @@ function executeProofGeneration(jobId, proofRequest) {
       SP1_PROVER: SP1_PROVER,
       RUST_LOG: 'info'
     };
+    // Tenants are metered on the cycles the host reports
+    if (tenants) {
+      proverEnv.METER_CYCLES = '1';
+    }
 
     // Add network credentials if using network prover
     if (SP1_PROVER === 'network' && NETWORK_PRIVATE_KEY) {
@@ -245,15 +429,18 @@ function executeProofGeneration(jobId, proofRequest) {
     let prover;
     if (SP1_HOST_BINARY) {
       console.log(`[${jobId}] Using prebuilt binary: ${SP1_HOST_BINARY}`);
-      prover = spawn(SP1_HOST_BINARY, [], { env: proverEnv });
+      prover = spawn(SP1_HOST_BINARY, HOST_ARGS, { env: proverEnv });
     } else {
       console.log(`[${jobId}] Using cargo run`);
-      prover = spawn('cargo', ['run', '--release', '--bin', 'sp1-host'], {
+      prover = spawn('cargo', ['run', '--release', '--bin', 'sp1-host', '--', ...HOST_ARGS], {
         cwd: proverPath,
         env: proverEnv
       });
     }
 
+    activeProvers.add(prover);
+    prover.on('close', () => activeProvers.delete(prover));
+
     // Write JSON input to stdin
     prover.stdin.write(jsonInput);
     prover.stdin.end();
@@ -325,6 +512,27 @@ function executeProofGeneration(jobId, proofRequest) {
           const response = JSON.parse(stdoutOutput.trim());
           console.log(`[${jobId}] Parsed proof response: vkey=${response.vkeyHash?.slice(0, 18)}...`);
 
+          let reservation, claimToken;
+          try {
+            ({ reservation, claimToken } = reservations.create(
+              jobId, finalJob?.tenantId, response.publicOutputs?.nullifiers || []));
+          } catch (reservationError) {
+            console.error(`[${jobId}] ${reservationError.message}`);
+            proofJobs.set(jobId, {
+              ...finalJob,
+              status: STAGES.ERROR,
+              stage: STAGES.ERROR,
+              stageDescription: reservationError.status === 429
+                ? 'Too many reservations held; submit or let earlier proofs expire'
+                : 'Inputs are already being submitted by another proof',
+              progress: 0,
+              error: reservationError.message
+            });
+            return resolve();
+          }
+          console.log(`[${jobId}] Reserved ${reservation.nullifiers.length} nullifiers as ${reservation.id}`);
+          meterJob(finalJob, response);
+
           proofJobs.set(jobId, {
             ...finalJob,
             status: STAGES.SUCCESS,
@@ -336,7 +544,16 @@ function executeProofGeneration(jobId, proofRequest) {
             publicValuesRaw: response.publicValuesRaw,
             publicOutputs: response.publicOutputs,
             vkeyHash: response.vkeyHash,
-            contractAddress: LEDGER_CONTRACT
+            cycles: response.cycles,
+            // Network requests the host retried before this proof
+            ...(response.networkAttempts && { networkAttempts: response.networkAttempts }),
+            // Out-of-band copy of the proof, when the host has ARTIFACT_STORE set
+            ...(response.artifacts && { artifacts: response.artifacts }),
+            // Ledger root once the outputs are inserted, when the host has INDEXER_STATE_URL set
+            ...(response.predictedNewRoot && { predictedNewRoot: response.predictedNewRoot }),
+            contractAddress: LEDGER_CONTRACT,
+            // The claim token is only shown to this job's tenant; relayers need it
+            reservation: { id: reservation.id, expiresAt: reservation.expiresAt, claimToken, renewals: 0 }
           });
         } catch (parseError) {
           console.error(`[${jobId}] Failed to parse proof JSON: ${parseError.message}`);
@@ -353,13 +570,20 @@ function executeProofGeneration(jobId, proofRequest) {
         }
       } else {
         console.error(`[${jobId}] Proof generation failed with code ${code}`);
+        meterJob(finalJob, null);
+        // On failure the prover writes {"error", "code", "traceId"} to stdout
+        const failure = parseErrorPayload(stdoutOutput);
         proofJobs.set(jobId, {
           ...finalJob,
           status: STAGES.ERROR,
           stage: STAGES.ERROR,
           stageDescription: 'Proof generation failed',
           progress: 0,
-          error: `Prover exited with code ${code}`,
+          error: failure?.error || `Prover exited with code ${code}`,
+          // e.g. DEADLINE_EXCEEDED, with the network attempts made so far
+          ...(failure?.code && { code: failure.code }),
+          ...(failure?.networkAttempts && { networkAttempts: failure.networkAttempts }),
+          traceId: failure?.traceId || finalJob?.traceId,
           output: stderrOutput.slice(-2000)
         });
       }
@@ -383,9 +607,23 @@ function executeProofGeneration(jobId, proofRequest) {
   });
 }
 
+// Last JSON error payload the prover wrote to stdout, if any
+function parseErrorPayload(stdout) {
+  const lines = stdout.trim().split('\n').reverse();
+  for (const line of lines) {
+    try {
+      const payload = JSON.parse(line);
+      if (payload && payload.error) return payload;
+    } catch (_) {
+      // not JSON
+    }
+  }
+  return null;
+}
+
 // Generate proof using SP1 (network or CPU)
 // Requests are queued and processed sequentially to prevent race conditions
-app.post('/api/generate-proof', async (req, res) => {
+app.post('/api/generate-proof', requireTenant, async (req, res) => {
   const {
     inputNotes,      // Array of { amount, ownerPubkey, blinding }
     outputNotes,     // Array of { amount, ownerPubkey, blinding }
@@ -393,20 +631,62 @@ app.post('/api/generate-proof', async (req, res) => {
     txSignatures,        // Array of signatures (hex strings)
     inputIndices,    // Array of merkle tree indices
     inputProofs,     // Array of merkle proofs (string[])
-    oldRoot          // Current merkle root from contract (hex string)
+    oldRoot,         // Current merkle root from contract (hex string)
+    chainId,         // Chain of the ledger oldRoot is from; the proof is bound to it
+    deadlineMs,      // Optional Unix time (ms) after which the proof isn't wanted
+    encryptedRequest, // Or: the whole request sealed to a published prover key
+    sealedFields     // Or: only the witness fields sealed to one (with oldRoot, chainId and the counts in the clear)
   } = req.body;
+  const sealed = Boolean(encryptedRequest || sealedFields);
 
   const jobId = Math.random().toString(36).substring(7);
+  // Correlates these logs with the Rust prover's (it prefixes every line)
+  const traceId = req.body.traceId || encryptedRequest?.traceId || req.get('x-trace-id') || jobId;
 
-  console.log(`[${jobId}] Received proof request`);
+  console.log(`[${jobId}] Received ${sealed ? 'sealed ' : ''}proof request (trace ${traceId})`);
   console.log(`[${jobId}] Mode: ${SP1_PROVER}`);
-  console.log(`[${jobId}] Inputs: ${inputNotes?.length || 0}, Outputs: ${outputNotes?.length || 0}`);
+
+  // Sealed requests are opened by the host only; just check the envelope
+  if (encryptedRequest) {
+    const validEnvelope = typeof encryptedRequest === 'object' &&
+      typeof encryptedRequest.keyId === 'string' &&
+      Number.isSafeInteger(encryptedRequest.chainId) && encryptedRequest.chainId > 0 &&
+      typeof encryptedRequest.ciphertext === 'string';
+    const extraFields = Object.keys(req.body).filter(k => !['encryptedRequest', 'priority'].includes(k));
+    if (!validEnvelope || extraFields.length > 0) {
+      return res.status(400).json({
+        error: 'Invalid sealed request',
+        traceId,
+        message: extraFields.length > 0
+          ? `Sealed requests carry no other fields (got ${extraFields.join(', ')})`
+          : 'encryptedRequest needs keyId, chainId and ciphertext'
+      });
+    }
+  } else if (sealedFields) {
+    const problem = sealedFieldsProblem(req.body);
+    if (problem) {
+      return res.status(400).json({ error: 'Invalid sealed request', traceId, message: problem });
+    }
+    console.log(`[${jobId}] Inputs: ${req.body.inputCount}, Outputs: ${req.body.outputCount} (sealed)`);
+  } else {
+    console.log(`[${jobId}] Inputs: ${inputNotes?.length || 0}, Outputs: ${outputNotes?.length || 0}`);
+    const unknown = unknownFields(req.body);
+    if (unknown.length > 0) {
+      return res.status(400).json({
+        error: 'Unknown fields',
+        traceId,
+        message: `Unknown request field(s): ${unknown.join(', ')}`,
+        accepted: ACCEPTED_FIELDS
+      });
+    }
+  }
 
   // Validate required fields
-  if (!inputNotes || !outputNotes || !nullifierSignatures || !txSignatures || !inputIndices || !inputProofs || !oldRoot) {
+  if (!sealed && (!inputNotes || !outputNotes || !nullifierSignatures || !txSignatures || !inputIndices || !inputProofs || !oldRoot || !Number.isSafeInteger(chainId) || chainId <= 0)) {
     return res.status(400).json({
       error: 'Missing required fields',
-      required: ['inputNotes', 'outputNotes', 'nullifierSignatures', 'txSignatures', 'inputIndices', 'inputProofs', 'oldRoot'],
+      traceId,
+      required: ['inputNotes', 'outputNotes', 'nullifierSignatures', 'txSignatures', 'inputIndices', 'inputProofs', 'oldRoot', 'chainId'],
       received: {
         inputNotes: !!inputNotes,
         outputNotes: !!outputNotes,
@@ -414,21 +694,56 @@ app.post('/api/generate-proof', async (req, res) => {
         txSignatures: !!txSignatures,
         inputIndices: !!inputIndices,
         inputProofs: !!inputProofs,
-        oldRoot: !!oldRoot
+        oldRoot: !!oldRoot,
+        chainId: Number.isSafeInteger(chainId) && chainId > 0
       }
     });
   }
 
   // Validate proofs match inputs
-  if (inputProofs.length !== inputNotes.length) {
+  if (!sealed && inputProofs.length !== inputNotes.length) {
     return res.status(400).json({
       error: 'Input mismatch',
+      traceId,
       message: `Received ${inputNotes.length} inputs but ${inputProofs.length} proofs`
     });
   }
 
-  // Calculate queue position
-  const queuePosition = jobQueue.length + 1;
+  if (deadlineMs !== undefined && (!Number.isSafeInteger(deadlineMs) || deadlineMs <= Date.now())) {
+    return res.status(400).json({ error: 'Invalid deadline', traceId, message: 'deadlineMs must be a Unix time in ms in the future' });
+  }
+
+  const lane = parseLane(req.body.priority);
+  if (!lane) {
+    return res.status(400).json({ error: 'Invalid priority', traceId, message: 'priority must be "interactive" or "background"' });
+  }
+
+  // A proof is useless without a reservation, so don't start one the
+  // tenant can't hold
+  const reservationLimit = reservations.checkCapacity(req.tenant?.id);
+  if (reservationLimit) {
+    return res.status(429).json({ error: 'Reservation limit reached', traceId, message: reservationLimit });
+  }
+
+  if (tenants) {
+    const exceeded = tenants.checkQuota(req.tenant);
+    if (exceeded) {
+      return res.status(429).json({ error: 'Quota exceeded', traceId, message: exceeded });
+    }
+    const laneLimit = req.tenant.lanes[lane];
+    if (laneLimit !== undefined && jobQueue.countFor(req.tenant.id, lane) >= laneLimit) {
+      return res.status(429).json({
+        error: 'Lane limit reached',
+        traceId,
+        message: `At most ${laneLimit} ${lane} jobs may be queued or running at once`
+      });
+    }
+    tenants.meter(req.tenant.id, { requests: 1 });
+  }
+
+  // Calculate queue position: interactive jobs only wait behind interactive ones
+  const ahead = jobQueue.entries().filter(e => lane === 'background' || e.lane === 'interactive').length;
+  const queuePosition = ahead + 1;
   const willStartImmediately = activeJobs < MAX_CONCURRENT;
 
   // Set initial job status
@@ -440,27 +755,31 @@ app.post('/api/generate-proof', async (req, res) => {
     startTime: Date.now(),
     proverMode: SP1_PROVER,
     queuePosition: willStartImmediately ? 0 : queuePosition,
-    queuedAt: Date.now()
+    queuedAt: Date.now(),
+    traceId,
+    tenantId: req.tenant?.id,
+    priority: lane
   });
 
   // Prepare proof request data
-  const proofRequest = {
-    inputNotes,
-    outputNotes,
-    nullifierSignatures,
-    txSignatures,
-    inputIndices,
-    inputProofs, // Added
-    oldRoot
-  };
+  const proofRequest = encryptedRequest
+    ? { encryptedRequest }
+    : sealedFields
+      ? buildSealedFieldsRequest(req.body, traceId)
+      : buildProofRequest(req.body, traceId);
 
   // Add to queue
-  jobQueue.push({ jobId, proofRequest });
+  jobQueue.push({ jobId, proofRequest, lane, tenantId: req.tenant?.id });
+  updateQueuePositions();
+  pendingRequests.set(jobId, proofRequest);
+  persistJob(jobId);
   console.log(`[Queue] Job ${jobId} added. Active: ${activeJobs}, Queued: ${jobQueue.length}`);
 
   // Return job ID immediately with queue info
   res.json({
     jobId,
+    traceId,
+    priority: lane,
     proverMode: SP1_PROVER,
     queuePosition: willStartImmediately ? 0 : queuePosition,
     activeJobs,
@@ -480,23 +799,195 @@ function updateJobStatus(jobId, stage, description, progress) {
     job.stageDescription = description;
     job.progress = progress;
     proofJobs.set(jobId, job);
+    persistJob(jobId);
   }
 }
 
 // Poll for proof status
-app.get('/api/proof-status/:jobId', (req, res) => {
+app.get('/api/proof-status/:jobId', requireTenant, (req, res) => {
   const { jobId } = req.params;
   const job = proofJobs.get(jobId);
 
-  if (!job) {
+  if (!job || !visibleTo(job, req)) {
     return res.status(404).json({ error: 'Job not found' });
   }
 
   res.json(job);
 
   // Clean up completed jobs after 10 minutes
-  if (job.status === STAGES.SUCCESS || job.status === STAGES.ERROR) {
-    setTimeout(() => proofJobs.delete(jobId), 10 * 60 * 1000);
+  if (isFinished(job)) {
+    setTimeout(() => forgetJob(jobId), COMPLETED_JOB_TTL_MS);
+  }
+});
+
+// Two-phase submit: a relayer claims a proof's reservation before sending
+// it, with the claim token the proof's tenant passed along
+function reservationAction(action) {
+  return (req, res) => {
+    const { relayer, claimToken, txHash } = req.body || {};
+    if (!relayer) {
+      return res.status(400).json({ error: 'Missing relayer' });
+    }
+    try {
+      const reservation = action === 'confirm'
+        ? reservations.confirm(req.params.id, claimToken, relayer, txHash)
+        : reservations[action](req.params.id, claimToken, relayer);
+      persistReservation(reservation);
+      console.log(`[Reservation] ${reservation.id} ${reservation.status} by ${relayer} (${action})`);
+      res.json(publicReservation(reservation));
+    } catch (error) {
+      res.status(error.status || 500).json({ error: error.message });
+    }
+  };
+}
+
+app.post('/api/reservations/:id/claim', reservationAction('claim'));
+// The claiming relayer confirms once the tx is mined...
+app.post('/api/reservations/:id/confirm', reservationAction('confirm'));
+// ...or releases it if submission failed, so another relayer may try
+app.post('/api/reservations/:id/release', reservationAction('release'));
+
+// Reservations are only visible to the tenant whose proof holds them
+app.get('/api/reservations/:id', requireTenant, (req, res) => {
+  const reservation = reservations.get(req.params.id);
+  if (!reservation || !visibleTo(reservation, req)) {
+    return res.status(404).json({ error: 'Reservation not found' });
+  }
+  res.json(publicReservation(reservation));
+});
+
+// Usage and quota of the caller's tenant; admins may pass ?tenant=<id|all>
+app.get('/api/usage', requireTenant, (req, res) => {
+  if (!tenants) {
+    return res.status(404).json({ error: 'Metering is disabled (TENANTS_FILE not set)' });
+  }
+  const requested = req.query.tenant;
+  if (requested && requested !== req.tenant.id && !req.tenant.admin) {
+    return res.status(403).json({ error: 'Only admin tenants can read other tenants\' usage' });
+  }
+  if (requested === 'all') {
+    return res.json({ tenants: [...tenants.tenants.keys()].map(id => tenants.usageOf(id)) });
+  }
+  res.json(tenants.usageOf(requested || req.tenant.id));
+});
+
+// Keys wallets seal requests to for delegated proving, current first.
+// The keyring (PROVER_KEYRING) is shared with the host, which holds the
+// secrets; only the public halves are served.
+const PROVER_KEYRING = process.env.PROVER_KEYRING;
+
+function publishedProverKeys() {
+  const { keys } = JSON.parse(fs.readFileSync(PROVER_KEYRING, 'utf8'));
+  const now = Math.floor(Date.now() / 1000);
+  return keys
+    .filter(k => k.expiresAt === undefined || now < k.expiresAt)
+    .map(({ keyId, publicKey, expiresAt }) => ({ keyId, publicKey, expiresAt }));
+}
+
+// Run a host subcommand, resolving with its stdout. `demo` runs it on the
+// host built with the `demo` feature (SP1_DEMO_HOST_BINARY), for commands
+// that sign sample transactions with the public test keys.
+function runHostCommand(args, { demo = false } = {}) {
+  return new Promise((resolve, reject) => {
+    const binary = demo ? process.env.SP1_DEMO_HOST_BINARY : process.env.SP1_HOST_BINARY;
+    const features = demo ? ['--features', 'demo'] : [];
+    const child = binary
+      ? spawn(binary, args, { env: process.env })
+      : spawn('cargo', ['run', '--release', ...features, '--bin', 'sp1-host', '--', ...args], {
+          cwd: process.env.PROVER_PATH || path.join(__dirname, '../prover/host'),
+          env: process.env
+        });
+    let stdout = '';
+    let stderr = '';
+    child.stdout.on('data', d => { stdout += d.toString(); });
+    child.stderr.on('data', d => { stderr += d.toString(); });
+    child.on('close', code => (code === 0 ? resolve(stdout) : reject(new Error(stderr.trim().split('\n').pop()))));
+  });
+}
+
+app.get('/api/prover-keys', (req, res) => {
+  if (!PROVER_KEYRING) {
+    return res.status(404).json({ error: 'Sealed requests are not enabled (PROVER_KEYRING unset)' });
+  }
+  try {
+    res.json({ keys: publishedProverKeys() });
+  } catch (e) {
+    res.status(500).json({ error: `Failed to read prover keyring: ${e.message}` });
+  }
+});
+
+// Rotate to a new current key; previous keys stay valid for ROTATION_GRACE_SECS
+app.post('/api/prover-keys/rotate', requireTenant, async (req, res) => {
+  if (!PROVER_KEYRING) {
+    return res.status(404).json({ error: 'Sealed requests are not enabled (PROVER_KEYRING unset)' });
+  }
+  if (!tenants || !req.tenant.admin) {
+    return res.status(403).json({ error: 'Key rotation requires an admin tenant' });
+  }
+  try {
+    await runHostCommand(['prover-keys', 'rotate', '--keyring', PROVER_KEYRING]);
+    const keys = publishedProverKeys();
+    console.log(`[ProverKeys] Rotated; current key ${keys[0]?.keyId}`);
+    res.json({ keys });
+  } catch (e) {
+    res.status(500).json({ error: `Key rotation failed: ${e.message}` });
+  }
+});
+
+// What relaying a transaction costs at the current gas price, in the
+// shielded token (see the host's `quote` subcommand), so wallets can show it
+// before proving. The ledger pays no fee out to relayers, so this is not a
+// fee to leave in the pool. Query: inputs, outputs, withdrawal, proofType.
+app.get('/api/quote', async (req, res) => {
+  const inputs = Number(req.query.inputs);
+  const outputs = Number(req.query.outputs || 0);
+  const withdrawal = req.query.withdrawal === 'true' || req.query.withdrawal === '1';
+  const proofType = req.query.proofType || 'groth16';
+  if (!Number.isInteger(inputs) || inputs < 1 || !Number.isInteger(outputs) || outputs < 0) {
+    return res.status(400).json({ error: 'inputs must be a positive integer and outputs a non-negative one' });
+  }
+  if (!['groth16', 'plonk'].includes(proofType)) {
+    return res.status(400).json({ error: 'proofType must be groth16 or plonk' });
+  }
+
+  let baseFee;
+  let priorityFee;
+  try {
+    const client = createPublicClient({ transport: http(RPC_URL) });
+    const block = await client.getBlock();
+    baseFee = block.baseFeePerGas;
+    priorityFee = await client.estimateMaxPriorityFeePerGas();
+  } catch (e) {
+    return res.status(502).json({ error: `Failed to read gas price: ${e.message}` });
+  }
+
+  const args = [
+    'quote', '--inputs', String(inputs), '--outputs', String(outputs), '--proof', proofType,
+    '--base-fee', baseFee.toString(), '--priority-fee', priorityFee.toString()
+  ];
+  if (withdrawal) args.push('--withdrawal');
+  try {
+    res.json(JSON.parse(await runHostCommand(args)));
+  } catch (e) {
+    res.status(500).json({ error: `Quote failed: ${e.message}` });
+  }
+});
+
+// Largest transactions this prover accepts under MAX_TX_CYCLES, measured on
+// its guest build (see the host's `limits` subcommand, which needs a `demo`
+// build). Measuring executes several sample transactions, so the first
+// result is kept.
+let limitsPromise = null;
+app.get('/api/limits', async (req, res) => {
+  if (!process.env.MAX_TX_CYCLES) {
+    return res.status(404).json({ error: 'No cycle ceiling configured (MAX_TX_CYCLES unset)' });
+  }
+  limitsPromise = limitsPromise || runHostCommand(['limits'], { demo: true }).then(JSON.parse);
+  try {
+    res.json(await limitsPromise);
+  } catch (e) {
+    limitsPromise = null;
+    res.status(500).json({ error: `Measuring limits failed: ${e.message}` });
   }
 });
 
@@ -508,18 +999,20 @@ app.get('/api/health', (req, res) => {
     networkConfigured: !!(SP1_PROVER === 'network' && NETWORK_PRIVATE_KEY),
     rpcUrl: SP1_PROVER === 'network' ? PROVER_NETWORK_RPC : null,
     ledgerContract: LEDGER_CONTRACT,
+    tenants: tenants ? tenants.size : null,
     queue: {
       activeJobs,
       queuedJobs: jobQueue.length,
       maxConcurrent: MAX_CONCURRENT,
-      totalTracked: proofJobs.size
+      totalTracked: proofJobs.size,
+      lanes: jobQueue.stats()
     }
   });
 });
 
 // Queue status endpoint
 app.get('/api/queue-status', (req, res) => {
-  const queuedJobIds = jobQueue.map(j => j.jobId);
+  const queuedJobIds = jobQueue.entries().map(j => j.jobId);
   const activeJobIds = [...proofJobs.entries()]
     .filter(([_, job]) => job.status !== STAGES.QUEUED && job.status !== STAGES.SUCCESS && job.status !== STAGES.ERROR)
     .map(([id, _]) => id);
@@ -529,7 +1022,8 @@ app.get('/api/queue-status', (req, res) => {
     queuedJobs: jobQueue.length,
     maxConcurrent: MAX_CONCURRENT,
     queuedJobIds,
-    activeJobIds
+    activeJobIds,
+    lanes: jobQueue.stats()
   });
 });
 
@@ -543,6 +1037,102 @@ app.get('/api/contract-info', (req, res) => {
   });
 });
 
+// Reload persisted jobs: finished ones stay pollable, queued and in-flight
+// ones are re-proven from their stored requests (in-flight first)
+function restoreJobs() {
+  if (!jobStore) return;
+  const requeue = [];
+  for (const { jobId, job, proofRequest, error } of jobStore.loadAll()) {
+    if (error || !job) {
+      console.error(`[JobStore] Dropping unreadable job ${jobId}: ${error}`);
+      jobStore.remove(jobId);
+      continue;
+    }
+    if (isFinished(job)) {
+      proofJobs.set(jobId, job);
+      if (job.reservation && job.publicOutputs) {
+        reservations.restore(jobId, job.tenantId, job.reservation, job.publicOutputs.nullifiers || []);
+      }
+      const age = Date.now() - (job.completedAt || 0);
+      setTimeout(() => forgetJob(jobId), Math.max(COMPLETED_JOB_TTL_MS - age, 0)).unref();
+      continue;
+    }
+    if (!proofRequest) {
+      proofJobs.set(jobId, {
+        ...job,
+        status: STAGES.ERROR,
+        stage: STAGES.ERROR,
+        stageDescription: 'Proof request lost in restart',
+        progress: 0,
+        error: 'Proof request was not persisted; resubmit',
+        completedAt: Date.now()
+      });
+      persistJob(jobId);
+      continue;
+    }
+    requeue.push({ jobId, job, proofRequest, wasRunning: job.status !== STAGES.QUEUED });
+  }
+
+  requeue.sort((a, b) => (b.wasRunning - a.wasRunning) || (a.job.queuedAt - b.job.queuedAt));
+  for (const { jobId, job, proofRequest, wasRunning } of requeue) {
+    proofJobs.set(jobId, {
+      ...job,
+      status: STAGES.QUEUED,
+      stage: STAGES.QUEUED,
+      stageDescription: wasRunning ? 'Restarting after server restart' : 'Queued',
+      progress: 0,
+      restarted: true
+    });
+    pendingRequests.set(jobId, proofRequest);
+    jobQueue.push({ jobId, proofRequest, lane: parseLane(job.priority) || 'interactive', tenantId: job.tenantId, queuedAt: job.queuedAt });
+    persistJob(jobId);
+  }
+  updateQueuePositions();
+
+  console.log(`[JobStore] Restored ${proofJobs.size} jobs (${requeue.length} requeued) from ${JOB_STORE_DIR}`);
+  processQueue();
+}
+
+restoreJobs();
+
+// On SIGTERM/SIGINT, persist every job as it stands (unfinished ones with
+// their requests, so restoreJobs re-proves them on the next start), stop
+// the provers and exit 0. Exits 1 only if a job couldn't be written; a
+// second signal exits at once. Without JOB_STORE_DIR unfinished jobs are
+// lost, which is logged.
+let shuttingDown = false;
+function shutdown(signal) {
+  if (shuttingDown) {
+    console.log(`[Shutdown] ${signal} received again; exiting without saving`);
+    process.exit(130);
+  }
+  shuttingDown = true;
+  const unfinished = [...proofJobs.values()].filter(job => !isFinished(job)).length;
+  console.log(`[Shutdown] ${signal} received; ${unfinished} unfinished jobs, ${activeProvers.size} provers running`);
+
+  let failed = false;
+  if (jobStore) {
+    for (const [jobId, job] of proofJobs) {
+      try {
+        jobStore.save(jobId, job, isFinished(job) ? null : pendingRequests.get(jobId));
+      } catch (e) {
+        console.error(`[Shutdown] Failed to persist ${jobId}: ${e.message}`);
+        failed = true;
+      }
+    }
+    console.log(`[Shutdown] Saved ${proofJobs.size} jobs to ${JOB_STORE_DIR}`);
+  } else if (unfinished > 0) {
+    console.warn(`[Shutdown] JOB_STORE_DIR is not set; ${unfinished} unfinished jobs are lost`);
+  }
+
+  // Exiting synchronously keeps the provers' close handlers from marking
+  // their jobs failed in the store
+  for (const prover of activeProvers) prover.kill('SIGTERM');
+  process.exit(failed ? 1 : 0);
+}
+process.on('SIGTERM', () => shutdown('SIGTERM'));
+process.on('SIGINT', () => shutdown('SIGINT'));
+
 app.listen(PORT, () => {
   console.log(`\n========================================`);
   console.log(`SP1 Prover Server v2.0`);
@@ -550,6 +1140,7 @@ app.listen(PORT, () => {
   console.log(`Port: ${PORT}`);
   console.log(`Prover Mode: ${SP1_PROVER}`);
   console.log(`Ledger Contract: ${LEDGER_CONTRACT}`);
+  console.log(`Tenants: ${tenants ? `${tenants.size} (API keys required)` : 'disabled (open access)'}`);
 
   if (SP1_PROVER === 'network' && NETWORK_PRIVATE_KEY) {
     console.log(`Network RPC: ${PROVER_NETWORK_RPC}`);
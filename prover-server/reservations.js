@@ -0,0 +1,200 @@
+// ============================================
+// NULLIFIER RESERVATIONS (two-phase submit)
+// ============================================
+// A finished proof comes with a short-lived reservation of its nullifiers.
+// A relayer claims the reservation before submitting, then confirms it once
+// the tx is mined or releases it if submission failed. Only one relayer can
+// hold a claim, so two relayers never race the same proof and the loser
+// doesn't pay gas for a guaranteed revert.
+//
+// Reservations are bounded so nobody can hold nullifiers indefinitely:
+// - each tenant may hold at most `perTenant` live reservations (without
+//   TENANTS_FILE every caller shares one bucket);
+// - claim, confirm and release need the reservation's claim token, which
+//   is only handed out in the proof result of the tenant that requested it
+//   (only its sha256 is kept);
+// - a claim renews the reservation for `ttlMs`, at most `maxRenewals`
+//   times, after which it expires like any other;
+// - a confirmed reservation is kept for `ttlMs` more, until the indexer
+//   has seen the nullifiers spent, and then dropped.
+
+const crypto = require('crypto');
+
+const RESERVATION = {
+  PENDING: 'pending',     // issued with the proof, claimable
+  CLAIMED: 'claimed',     // a relayer is submitting
+  CONFIRMED: 'confirmed'  // submitted on-chain; nullifiers are spent
+};
+
+function sha256(value) {
+  return crypto.createHash('sha256').update(value).digest();
+}
+
+// Error carrying the HTTP status to answer with
+function reservationError(status, message) {
+  const error = new Error(message);
+  error.status = status;
+  return error;
+}
+
+class ReservationBook {
+  constructor({ ttlMs, maxRenewals, perTenant }) {
+    this.ttlMs = ttlMs;
+    this.maxRenewals = maxRenewals;
+    this.perTenant = perTenant;
+    this.reservations = new Map(); // reservationId -> reservation
+    this.nullifiers = new Map();   // nullifier (lowercase hex) -> reservationId
+  }
+
+  isLive(reservation, now = Date.now()) {
+    return !!reservation && reservation.expiresAt > now;
+  }
+
+  // Live reservations held by a tenant
+  countFor(tenantId, now = Date.now()) {
+    let count = 0;
+    for (const reservation of this.reservations.values()) {
+      if (reservation.tenantId === tenantId && this.isLive(reservation, now)) count++;
+    }
+    return count;
+  }
+
+  // Reason the tenant can't take another reservation, or null
+  checkCapacity(tenantId, now = Date.now()) {
+    if (this.countFor(tenantId, now) < this.perTenant) return null;
+    return `At most ${this.perTenant} reservations may be held at once`;
+  }
+
+  // Reserve a proof's nullifiers for a tenant. Throws if the tenant is at
+  // its limit or another live reservation holds one of them. Returns the
+  // reservation and its claim token, which isn't stored.
+  create(jobId, tenantId, nullifiers, now = Date.now()) {
+    const capacity = this.checkCapacity(tenantId, now);
+    if (capacity) throw reservationError(429, capacity);
+    const keys = nullifiers.map(n => n.toLowerCase());
+    for (const key of keys) {
+      const holderId = this.nullifiers.get(key);
+      if (this.isLive(this.reservations.get(holderId), now)) {
+        throw reservationError(409, `Nullifier ${key.slice(0, 18)}... is reserved by ${holderId}`);
+      }
+    }
+
+    const claimToken = crypto.randomBytes(32).toString('hex');
+    const reservation = this.insert({
+      id: `res_${crypto.randomBytes(8).toString('hex')}`,
+      jobId,
+      tenantId,
+      nullifiers: keys,
+      expiresAt: now + this.ttlMs
+    }, claimToken);
+    return { reservation, claimToken };
+  }
+
+  // Re-register a finished job's reservation after a restart. Claims don't
+  // survive a restart, so it comes back claimable, with its renewals used.
+  restore(jobId, tenantId, { id, expiresAt, claimToken, renewals }, nullifiers, now = Date.now()) {
+    if (expiresAt <= now || !claimToken) return;
+    this.insert({
+      id,
+      jobId,
+      tenantId,
+      nullifiers: nullifiers.map(n => n.toLowerCase()),
+      expiresAt,
+      renewals: renewals || 0
+    }, claimToken);
+  }
+
+  insert(fields, claimToken) {
+    const reservation = {
+      status: RESERVATION.PENDING,
+      claimedBy: null,
+      txHash: null,
+      renewals: 0,
+      ...fields,
+      tokenHash: sha256(claimToken)
+    };
+    this.reservations.set(reservation.id, reservation);
+    reservation.nullifiers.forEach(key => this.nullifiers.set(key, reservation.id));
+    return reservation;
+  }
+
+  // The live reservation `claimToken` opens; compares hashes in constant time
+  authorize(id, claimToken, now = Date.now()) {
+    const reservation = this.reservations.get(id);
+    if (!this.isLive(reservation, now)) {
+      throw reservationError(404, 'Reservation not found or expired');
+    }
+    if (typeof claimToken !== 'string' || !crypto.timingSafeEqual(sha256(claimToken), reservation.tokenHash)) {
+      throw reservationError(403, 'Invalid claim token');
+    }
+    return reservation;
+  }
+
+  // A relayer claims a reservation before sending its proof; claiming again
+  // (or after a release) renews it, up to maxRenewals times
+  claim(id, claimToken, relayer, now = Date.now()) {
+    const reservation = this.authorize(id, claimToken, now);
+    if (reservation.status === RESERVATION.CONFIRMED) {
+      throw reservationError(409, 'Reservation already submitted');
+    }
+    if (reservation.status === RESERVATION.CLAIMED && reservation.claimedBy !== relayer) {
+      throw reservationError(409, 'Reservation claimed by another relayer');
+    }
+    if (reservation.renewals >= this.maxRenewals) {
+      throw reservationError(409, `Reservation was already renewed ${this.maxRenewals} times`);
+    }
+    reservation.status = RESERVATION.CLAIMED;
+    reservation.claimedBy = relayer;
+    reservation.renewals++;
+    reservation.expiresAt = now + this.ttlMs;
+    return reservation;
+  }
+
+  // The claiming relayer confirms once the tx is mined
+  confirm(id, claimToken, relayer, txHash, now = Date.now()) {
+    const reservation = this.claimedBy(id, claimToken, relayer, now);
+    reservation.status = RESERVATION.CONFIRMED;
+    reservation.txHash = txHash || null;
+    reservation.expiresAt = now + this.ttlMs;
+    return reservation;
+  }
+
+  // ...or releases it if submission failed, so another relayer may try
+  release(id, claimToken, relayer, now = Date.now()) {
+    const reservation = this.claimedBy(id, claimToken, relayer, now);
+    reservation.status = RESERVATION.PENDING;
+    reservation.claimedBy = null;
+    return reservation;
+  }
+
+  claimedBy(id, claimToken, relayer, now) {
+    const reservation = this.authorize(id, claimToken, now);
+    if (reservation.status !== RESERVATION.CLAIMED || reservation.claimedBy !== relayer) {
+      throw reservationError(409, 'Reservation is not claimed by this relayer');
+    }
+    return reservation;
+  }
+
+  get(id) {
+    return this.reservations.get(id);
+  }
+
+  // Drop expired reservations so their nullifiers can be proven again
+  prune(now = Date.now()) {
+    for (const [id, reservation] of this.reservations) {
+      if (this.isLive(reservation, now)) continue;
+      this.reservations.delete(id);
+      reservation.nullifiers.forEach(key => {
+        if (this.nullifiers.get(key) === id) this.nullifiers.delete(key);
+      });
+    }
+  }
+}
+
+// A reservation as shown to clients, without its token hash
+function publicReservation(reservation) {
+  const { tokenHash, ...rest } = reservation;
+  return rest;
+}
+
+module.exports = { ReservationBook, RESERVATION, publicReservation };
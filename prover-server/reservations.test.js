@@ -0,0 +1,65 @@
+const test = require('node:test');
+const assert = require('node:assert');
+const { ReservationBook, RESERVATION } = require('./reservations');
+
+const TTL = 1000;
+
+function book() {
+  return new ReservationBook({ ttlMs: TTL, maxRenewals: 2, perTenant: 2 });
+}
+
+test('claims need the token handed out with the proof', () => {
+  const reservations = book();
+  const { reservation, claimToken } = reservations.create('job1', 'wallet-a', ['0xAA'], 0);
+  assert.throws(() => reservations.claim(reservation.id, 'guess', 'relayer-1', 1), { status: 403 });
+  assert.throws(() => reservations.claim(reservation.id, undefined, 'relayer-1', 1), { status: 403 });
+  assert.strictEqual(reservations.claim(reservation.id, claimToken, 'relayer-1', 1).status, RESERVATION.CLAIMED);
+  assert.throws(() => reservations.claim(reservation.id, claimToken, 'relayer-2', 2), /another relayer/);
+  assert.throws(() => reservations.confirm(reservation.id, 'guess', 'relayer-1', '0x01', 2), { status: 403 });
+  assert.strictEqual(reservations.confirm(reservation.id, claimToken, 'relayer-1', '0x01', 2).status, RESERVATION.CONFIRMED);
+});
+
+test('a tenant holds at most perTenant live reservations', () => {
+  const reservations = book();
+  reservations.create('job1', 'wallet-a', ['0x01'], 0);
+  reservations.create('job2', 'wallet-a', ['0x02'], 0);
+  assert.throws(() => reservations.create('job3', 'wallet-a', ['0x03'], 0), { status: 429 });
+  // Other tenants have their own bound
+  reservations.create('job4', 'wallet-b', ['0x04'], 0);
+  // Expired reservations don't count
+  reservations.create('job5', 'wallet-a', ['0x05'], TTL);
+});
+
+test('renewals are capped and the reservation then expires', () => {
+  const reservations = book();
+  const { reservation, claimToken } = reservations.create('job1', 'wallet-a', ['0x01'], 0);
+  reservations.claim(reservation.id, claimToken, 'relayer-1', 500);
+  reservations.claim(reservation.id, claimToken, 'relayer-1', 1400);
+  assert.throws(() => reservations.claim(reservation.id, claimToken, 'relayer-1', 2300), /renewed 2 times/);
+  reservations.prune(2400);
+  assert.strictEqual(reservations.get(reservation.id), undefined);
+  // The nullifier is free again
+  reservations.create('job2', 'wallet-a', ['0x01'], 2400);
+});
+
+test('confirmed reservations expire too', () => {
+  const reservations = book();
+  const { reservation, claimToken } = reservations.create('job1', 'wallet-a', ['0x01'], 0);
+  reservations.claim(reservation.id, claimToken, 'relayer-1', 0);
+  reservations.confirm(reservation.id, claimToken, 'relayer-1', '0x02', 100);
+  assert.throws(() => reservations.create('job2', 'wallet-b', ['0x01'], 200), { status: 409 });
+  reservations.prune(100 + TTL);
+  assert.strictEqual(reservations.countFor('wallet-a', 100 + TTL), 0);
+});
+
+test('restored reservations keep their token and renewals', () => {
+  const reservations = book();
+  const { reservation, claimToken } = reservations.create('job1', 'wallet-a', ['0x01'], 0);
+  reservations.claim(reservation.id, claimToken, 'relayer-1', 0);
+  reservations.claim(reservation.id, claimToken, 'relayer-1', 0);
+
+  const restarted = book();
+  restarted.restore('job1', 'wallet-a', { id: reservation.id, expiresAt: TTL, claimToken, renewals: 2 }, ['0x01'], 0);
+  assert.strictEqual(restarted.get(reservation.id).status, RESERVATION.PENDING);
+  assert.throws(() => restarted.claim(reservation.id, claimToken, 'relayer-1', 1), /renewed 2 times/);
+});
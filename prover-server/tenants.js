@@ -0,0 +1,159 @@
+// ============================================
+// MULTI-TENANT API KEYS AND METERING
+// ============================================
+// When TENANTS_FILE is set, every proof request must carry an API key
+// (`x-api-key` header or `Authorization: Bearer <key>`) belonging to a
+// tenant, so one hosted prover can serve several wallet frontends:
+//
+// {
+//   "tenants": [
+//     {
+//       "id": "wallet-a",
+//       "keySha256": "<hex sha256 of the API key>",
+//       "admin": false,
+//       "quota": { "proofsPerDay": 500, "cyclesPerDay": 5000000000, "networkSpendPerDay": "1000000" },
+//       "lanes": { "interactive": 2, "background": 20 }
+//     }
+//   ]
+// }
+//
+// Only key hashes are stored (`printf %s "$KEY" | sha256sum`). Quotas are
+// per UTC day and optional. Usage (requests, proofs, failures, cycles and
+// estimated network spend) is kept per tenant per day and, with
+// TENANT_USAGE_FILE, persisted across restarts. `lanes` caps the jobs a
+// tenant may have queued or running per priority lane (see lanes.js).
+
+const fs = require('fs');
+const crypto = require('crypto');
+
+function sha256(value) {
+  return crypto.createHash('sha256').update(value).digest();
+}
+
+function today() {
+  return new Date().toISOString().slice(0, 10);
+}
+
+function emptyUsage() {
+  return { requests: 0, proofs: 0, failures: 0, cycles: 0, networkSpend: '0' };
+}
+
+function addUsage(usage, delta) {
+  usage.requests += delta.requests || 0;
+  usage.proofs += delta.proofs || 0;
+  usage.failures += delta.failures || 0;
+  usage.cycles += delta.cycles || 0;
+  usage.networkSpend = (BigInt(usage.networkSpend) + BigInt(delta.networkSpend || 0)).toString();
+}
+
+class TenantRegistry {
+  constructor(tenantsFile, usageFile) {
+    const config = JSON.parse(fs.readFileSync(tenantsFile, 'utf8'));
+    this.tenants = new Map();
+    for (const tenant of config.tenants || []) {
+      if (!tenant.id || !/^[0-9a-fA-F]{64}$/.test(tenant.keySha256 || '')) {
+        throw new Error(`Tenant ${JSON.stringify(tenant.id)} needs an id and a 32-byte hex keySha256`);
+      }
+      if (this.tenants.has(tenant.id)) {
+        throw new Error(`Duplicate tenant id ${tenant.id}`);
+      }
+      this.tenants.set(tenant.id, {
+        id: tenant.id,
+        keyHash: Buffer.from(tenant.keySha256, 'hex'),
+        admin: !!tenant.admin,
+        quota: tenant.quota || {},
+        lanes: tenant.lanes || {}
+      });
+    }
+
+    this.usageFile = usageFile;
+    // tenantId -> { days: { 'YYYY-MM-DD': usage }, total: usage }
+    this.usage = new Map();
+    if (usageFile && fs.existsSync(usageFile)) {
+      for (const [id, record] of Object.entries(JSON.parse(fs.readFileSync(usageFile, 'utf8')))) {
+        this.usage.set(id, record);
+      }
+    }
+  }
+
+  get size() {
+    return this.tenants.size;
+  }
+
+  // Tenant owning `apiKey`, or null; compares hashes in constant time
+  authenticate(apiKey) {
+    if (!apiKey) return null;
+    const hash = sha256(apiKey);
+    for (const tenant of this.tenants.values()) {
+      if (crypto.timingSafeEqual(hash, tenant.keyHash)) return tenant;
+    }
+    return null;
+  }
+
+  record(tenantId) {
+    if (!this.usage.has(tenantId)) {
+      this.usage.set(tenantId, { days: {}, total: emptyUsage() });
+    }
+    return this.usage.get(tenantId);
+  }
+
+  // Reason the tenant can't submit another proof today, or null
+  checkQuota(tenant) {
+    const day = this.record(tenant.id).days[today()] || emptyUsage();
+    const { proofsPerDay, cyclesPerDay, networkSpendPerDay } = tenant.quota;
+    if (proofsPerDay !== undefined && day.requests >= proofsPerDay) {
+      return `Daily proof quota of ${proofsPerDay} reached`;
+    }
+    if (cyclesPerDay !== undefined && day.cycles >= cyclesPerDay) {
+      return `Daily cycle quota of ${cyclesPerDay} reached`;
+    }
+    if (networkSpendPerDay !== undefined && BigInt(day.networkSpend) >= BigInt(networkSpendPerDay)) {
+      return `Daily network spend quota of ${networkSpendPerDay} reached`;
+    }
+    return null;
+  }
+
+  // Add to a tenant's usage for today, e.g. { requests: 1 } on submission
+  // and { proofs: 1, cycles, networkSpend } on completion
+  meter(tenantId, delta) {
+    const record = this.record(tenantId);
+    const day = today();
+    record.days[day] = record.days[day] || emptyUsage();
+    addUsage(record.days[day], delta);
+    addUsage(record.total, delta);
+    this.save();
+  }
+
+  usageOf(tenantId) {
+    const tenant = this.tenants.get(tenantId);
+    const record = this.record(tenantId);
+    return {
+      tenant: tenantId,
+      quota: tenant ? tenant.quota : {},
+      today: record.days[today()] || emptyUsage(),
+      days: record.days,
+      total: record.total
+    };
+  }
+
+  save() {
+    if (!this.usageFile) return;
+    try {
+      const tmp = `${this.usageFile}.tmp`;
+      fs.writeFileSync(tmp, JSON.stringify(Object.fromEntries(this.usage)));
+      fs.renameSync(tmp, this.usageFile);
+    } catch (e) {
+      console.error(`[Tenants] Failed to persist usage: ${e.message}`);
+    }
+  }
+}
+
+// API key from `x-api-key` or `Authorization: Bearer <key>`
+function apiKeyFrom(req) {
+  const header = req.get('x-api-key');
+  if (header) return header;
+  const auth = req.get('authorization') || '';
+  return auth.startsWith('Bearer ') ? auth.slice(7).trim() : null;
+}
+
+module.exports = { TenantRegistry, apiKeyFrom };
@@ -0,0 +1,126 @@
+//! `prove_batch` WebSocket messages
+//!
+//! A frontend submitting several user actions at once sends them in one
+//! message instead of opening a socket per proof:
+//!
+//! ```json
+//! {"type": "prove_batch", "batchId": "b1", "items": [
+//!   {"id": "send-1", "publicInputs": {...}, "witness": {...}}, ...]}
+//! ```
+//!
+//! The server replies `batch_accepted`, then each item's final
+//! `ProverEvent` (`complete` or `failed`) as it completes, with `batchId`,
+//! `index` and `id` added to correlate it (results arrive in completion
+//! order, not submission order), then `batch_done`.
+//! Up to BATCH_CONCURRENCY (default 2) items prove at once, and a batch
+//! holds at most BATCH_MAX_ITEMS (default 16).
+
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use serde::Deserialize;
+use sp1_sdk::{Prover, SP1ProvingKey, SP1Stdin};
+use tokio::sync::{mpsc, Semaphore};
+use ghostclaw_core::{GuestInput, ProofResult, ProverEvent, PublicInputs, Witness};
+
+use crate::{build_client, ELF};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchItem {
+    /// Caller's correlation ID, echoed in the item's result
+    #[serde(default)]
+    pub id: Option<String>,
+    pub public_inputs: PublicInputs,
+    pub witness: Witness,
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).filter(|&n| n > 0).unwrap_or(default)
+}
+
+async fn send(socket: &mut WebSocket, message: serde_json::Value) {
+    let _ = socket.send(Message::Text(message.to_string())).await;
+}
+
+/// Prove one item (blocking).
+fn prove_item(client: &sp1_sdk::EnvProver, pk: &SP1ProvingKey, item: BatchItem) -> ProverEvent {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(item.public_inputs, item.witness));
+    match client.prove(pk, &stdin).plonk().run() {
+        Ok(proof) => ProverEvent::Complete {
+            response: ProofResult {
+                proof: format!("0x{}", hex::encode(proof.bytes())),
+                public_values: format!("0x{}", hex::encode(proof.public_values.as_slice())),
+                public_outputs: None,
+            },
+        },
+        Err(e) => ProverEvent::Failed { error: e.to_string() },
+    }
+}
+
+/// Handle a `prove_batch` message, streaming results over `socket`.
+pub async fn prove_batch(mut socket: WebSocket, request: serde_json::Value) {
+    let batch_id = request["batchId"].clone();
+    let items: Vec<BatchItem> = match serde_json::from_value(request["items"].clone()) {
+        Ok(items) => items,
+        Err(e) => {
+            let message = format!("Invalid batch items: {}", e);
+            return send(&mut socket, serde_json::json!({ "type": "error", "batchId": batch_id, "message": message })).await;
+        }
+    };
+    let max_items = env_usize("BATCH_MAX_ITEMS", 16);
+    if items.is_empty() || items.len() > max_items {
+        let message = format!("A batch holds 1 to {} items, got {}", max_items, items.len());
+        return send(&mut socket, serde_json::json!({ "type": "error", "batchId": batch_id, "message": message })).await;
+    }
+    let count = items.len();
+    send(&mut socket, serde_json::json!({ "type": "batch_accepted", "batchId": batch_id, "count": count })).await;
+
+    let setup = tokio::task::spawn_blocking(|| {
+        let client = build_client();
+        let (pk, _vk) = client.setup(ELF);
+        (client, pk)
+    })
+    .await;
+    let (client, pk) = match setup {
+        Ok(setup) => setup,
+        Err(e) => {
+            let message = format!("Prover setup failed: {}", e);
+            return send(&mut socket, serde_json::json!({ "type": "error", "batchId": batch_id, "message": message })).await;
+        }
+    };
+    let prover = Arc::new((client, pk));
+    let permits = Arc::new(Semaphore::new(env_usize("BATCH_CONCURRENCY", 2)));
+
+    let (results, mut completed) = mpsc::channel(count);
+    for (index, mut item) in items.into_iter().enumerate() {
+        let (prover, permits, results) = (prover.clone(), permits.clone(), results.clone());
+        tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let id = item.id.take();
+            let result = tokio::task::spawn_blocking(move || prove_item(&prover.0, &prover.1, item))
+                .await
+                .unwrap_or_else(|e| ProverEvent::Failed { error: format!("Prover panicked: {}", e) });
+            let _ = results.send((index, id, result)).await;
+        });
+    }
+    drop(results);
+
+    let mut failed = 0;
+    while let Some((index, id, event)) = completed.recv().await {
+        if matches!(event, ProverEvent::Failed { .. }) {
+            failed += 1;
+        }
+        let mut result = serde_json::to_value(&event).expect("ProverEvent serializes");
+        result["batchId"] = batch_id.clone();
+        result["index"] = index.into();
+        result["id"] = id.into();
+        send(&mut socket, result).await;
+    }
+    send(
+        &mut socket,
+        serde_json::json!({ "type": "batch_done", "batchId": batch_id, "proved": count - failed, "failed": failed }),
+    )
+    .await;
+}
@@ -0,0 +1,212 @@
+//! `/health` endpoint for load-balancer checks
+//!
+//! Reports each dependency the prover needs to serve requests:
+//! - `elf`: guest ELF loaded and vkey computed at startup
+//! - `backend`: network RPC reachable, or a CPU smoke execution succeeds
+//!   (run at most once per `HEALTH_SMOKE_TTL_SECS`, default 300, and cached
+//!   in between, so load-balancer polling doesn't keep a core busy)
+//! - `indexer`: indexer sync height within `HEALTH_MAX_SYNC_LAG` of chain head
+//! - `credits`: network balance above `HEALTH_MIN_BALANCE`
+//!
+//! Returns 200 when every check passes or is skipped, 503 otherwise.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+use sp1_sdk::{ProverClient, SP1Stdin};
+use ghostclaw_core::{GuestInput, Note, PublicInputs, Witness};
+
+use crate::{AppState, ELF};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    /// Not applicable to this deployment (e.g. credits on the CPU backend)
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn ok(detail: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Ok, detail: detail.into() }
+    }
+
+    fn failed(detail: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Failed, detail: detail.into() }
+    }
+
+    fn skipped(detail: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Skipped, detail: detail.into() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub backend: String,
+    pub elf: Check,
+    pub backend_reachable: Check,
+    pub indexer: Check,
+    pub credits: Check,
+}
+
+pub async fn health_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let backend = std::env::var("SP1_PROVER").unwrap_or_else(|_| "cpu".to_string());
+    let is_network = backend == "network";
+
+    let elf = match &state.vkey_hash {
+        Some(hash) => Check::ok(format!("vkey {}", hash)),
+        None if ELF.is_empty() => Check::failed("ELF not loaded"),
+        None => Check::failed("vkey not computed"),
+    };
+
+    let backend_reachable = if is_network {
+        ping_network_rpc().await
+    } else {
+        tokio::task::spawn_blocking(cached_smoke_execute)
+            .await
+            .unwrap_or_else(|e| Check::failed(format!("smoke execution panicked: {}", e)))
+    };
+
+    let indexer = check_indexer_sync().await;
+
+    let credits = if is_network {
+        check_credits().await
+    } else {
+        Check::skipped("local backend")
+    };
+
+    let healthy = [&elf, &backend_reachable, &indexer, &credits]
+        .iter()
+        .all(|c| c.status != CheckStatus::Failed);
+
+    let report = HealthReport { healthy, backend, elf, backend_reachable, indexer, credits };
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report))
+}
+
+async fn ping_network_rpc() -> Check {
+    let rpc_url = std::env::var("PROVER_NETWORK_RPC")
+        .unwrap_or_else(|_| "https://rpc.mainnet.succinct.xyz".to_string());
+    match reqwest::get(&rpc_url).await {
+        Ok(_) => Check::ok(format!("{} reachable", rpc_url)),
+        Err(e) => Check::failed(format!("{} unreachable: {}", rpc_url, e)),
+    }
+}
+
+/// Last smoke execution and when it ran.
+static SMOKE_RESULT: Mutex<Option<(Instant, Check)>> = Mutex::new(None);
+
+/// `smoke_execute`, reusing its result for `HEALTH_SMOKE_TTL_SECS`.
+fn cached_smoke_execute() -> Check {
+    let ttl = Duration::from_secs(
+        std::env::var("HEALTH_SMOKE_TTL_SECS")
+            .ok()
+            .map_or(300, |v| v.parse().unwrap_or_else(|_| panic!("Invalid HEALTH_SMOKE_TTL_SECS: {}", v))),
+    );
+    // Held across the execution, so concurrent checks wait for one run
+    let mut cached = SMOKE_RESULT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((at, check)) = cached.as_ref() {
+        if at.elapsed() < ttl {
+            return check.clone();
+        }
+    }
+    let check = smoke_execute();
+    *cached = Some((Instant::now(), check.clone()));
+    check
+}
+
+/// Execute the guest on a minimal mint witness (no inputs, one output) on
+/// the local dev chain.
+fn smoke_execute() -> Check {
+    let witness = Witness::new_without_proofs(
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![Note::new(1, [1u8; 32], [2u8; 32])],
+    );
+    let public_inputs = PublicInputs::new(ghostclaw_core::MerkleTree::new().root()).with_chain_id(31337);
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(public_inputs, witness));
+
+    let client = ProverClient::builder().cpu().build();
+    match client.execute(ELF, &stdin).run() {
+        Ok((_, report)) => Check::ok(format!("smoke execute {} cycles", report.total_instruction_count())),
+        Err(e) => Check::failed(format!("smoke execute failed: {}", e)),
+    }
+}
+
+/// Compare the indexer's synced block (`INDEXER_SYNC_URL`, JSON `{"blockNumber": n}`)
+/// with the chain head at `RPC_URL`.
+async fn check_indexer_sync() -> Check {
+    let Ok(indexer_url) = std::env::var("INDEXER_SYNC_URL") else {
+        return Check::skipped("INDEXER_SYNC_URL not set");
+    };
+    let rpc_url = std::env::var("RPC_URL").unwrap_or_else(|_| "http://localhost:8545".to_string());
+    let max_lag: u64 = std::env::var("HEALTH_MAX_SYNC_LAG")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10);
+
+    let synced = match fetch_json(reqwest::Client::new().get(&indexer_url)).await {
+        Ok(body) => body["blockNumber"].as_u64(),
+        Err(e) => return Check::failed(format!("indexer unreachable: {}", e)),
+    };
+    let head = match fetch_json(reqwest::Client::new().post(&rpc_url).json(&serde_json::json!({
+        "jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": []
+    })))
+    .await
+    {
+        Ok(body) => body["result"]
+            .as_str()
+            .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()),
+        Err(e) => return Check::failed(format!("chain RPC unreachable: {}", e)),
+    };
+
+    match (synced, head) {
+        (Some(synced), Some(head)) if head.saturating_sub(synced) <= max_lag => {
+            Check::ok(format!("synced {} / head {}", synced, head))
+        }
+        (Some(synced), Some(head)) => Check::failed(format!(
+            "indexer {} blocks behind (synced {} / head {}, max lag {})",
+            head - synced, synced, head, max_lag
+        )),
+        _ => Check::failed("malformed indexer or RPC response"),
+    }
+}
+
+async fn check_credits() -> Check {
+    let min_balance: u128 = std::env::var("HEALTH_MIN_BALANCE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+
+    let client = ProverClient::builder().network().build();
+    match client.get_balance().await {
+        Ok(balance) => {
+            let balance: u128 = balance.to_string().parse().unwrap_or(u128::MAX);
+            if balance > min_balance {
+                Check::ok(format!("balance {}", balance))
+            } else {
+                Check::failed(format!("balance {} at or below threshold {}", balance, min_balance))
+            }
+        }
+        Err(e) => Check::failed(format!("balance query failed: {}", e)),
+    }
+}
+
+async fn fetch_json(request: reqwest::RequestBuilder) -> Result<serde_json::Value, reqwest::Error> {
+    request.send().await?.json().await
+}
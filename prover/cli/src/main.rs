@@ -1,26 +1,56 @@
+use std::sync::Arc;
+
 use axum::{
     extract::ws::{WebSocket, WebSocketUpgrade},
     response::IntoResponse,
     routing::get,
     Router,
 };
-use sp1_sdk::{ProverClient, SP1Stdin};
+use sp1_sdk::{HashableKey, Prover, ProverClient, SP1Stdin};
 use tokio::sync::mpsc;
+use ghostclaw_core::{ProofResult, ProverEvent};
+
+mod batch;
+mod health;
 
-const ELF: &[u8] = include_bytes!("../../sp1-program/target/riscv32im-succinct-zkvm-elf/release/sp1-program");
+/// State shared by all request handlers
+pub struct AppState {
+    /// vkey hash of the embedded ELF, computed once at startup
+    pub vkey_hash: Option<String>,
+}
+
+pub const ELF: &[u8] = include_bytes!("../../sp1-program/target/riscv32im-succinct-zkvm-elf/release/sp1-program");
 
 #[tokio::main]
 async fn main() {
+    // `--backend gpu|cpu|network|mock` selects the SP1 prover (`from_env` reads SP1_PROVER)
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(backend) = args.iter().position(|a| a == "--backend").and_then(|i| args.get(i + 1)) {
+        let prover = if backend == "gpu" { "cuda" } else { backend.as_str() };
+        std::env::set_var("SP1_PROVER", prover);
+    }
+    if let Some(device) = args.iter().position(|a| a == "--gpu-device").and_then(|i| args.get(i + 1)) {
+        std::env::set_var("CUDA_VISIBLE_DEVICES", device);
+    }
+
     println!("🚀 Starting Local SP1 Prover CLI");
     println!("📡 WebSocket server on ws://localhost:3001");
     
+    let vkey_hash = tokio::task::spawn_blocking(|| {
+        let (_, vk) = ProverClient::builder().cpu().build().setup(ELF);
+        format!("0x{}", vk.bytes32())
+    })
+    .await
+    .ok();
+    let state = Arc::new(AppState { vkey_hash });
+
     let app = Router::new()
-        .route("/", get(ws_handler));
+        .route("/", get(ws_handler))
+        .route("/health", get(health::health_handler))
+        .with_state(state);
 
-    axum::Server::bind(&"0.0.0.0:3001".parse().unwrap())
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3001").await.unwrap();
+    axum::serve(listener, app).await.unwrap();
 }
 
 async fn ws_handler(ws: WebSocketUpgrade) -> impl IntoResponse {
@@ -46,6 +76,10 @@ async fn handle_socket(mut socket: WebSocket) {
                 });
                 return;
             }
+            if request["type"] == "prove_batch" {
+                tokio::spawn(batch::prove_batch(socket, request));
+                return;
+            }
         }
     }
 }
@@ -54,47 +88,51 @@ async fn generate_proof_with_progress(
     mut socket: WebSocket,
     witness_json: serde_json::Value,
 ) {
-    // Send progress updates
-    let _ = socket.send(axum::extract::ws::Message::Text(
-        serde_json::json!({
-            "type": "progress",
-            "percent": 10,
-            "message": "Setting up prover..."
-        }).to_string()
-    )).await;
-
-    let client = ProverClient::from_env();
-    let (pk, _vk) = client.setup(ELF);
+    send_event(&mut socket, &ProverEvent::SetupStarted).await;
 
-    let _ = socket.send(axum::extract::ws::Message::Text(
-        serde_json::json!({
-            "type": "progress",
-            "percent": 20,
-            "message": "Generating proof..."
-        }).to_string()
-    )).await;
+    let client = build_client();
+    let (pk, _vk) = client.setup(ELF);
 
     // Deserialize witness
     let mut stdin = SP1Stdin::new();
     // ... serialize witness into stdin ...
 
-    match client.prove(&pk, &stdin).plonk().run() {
-        Ok(proof) => {
-            let _ = socket.send(axum::extract::ws::Message::Text(
-                serde_json::json!({
-                    "type": "proof",
-                    "proof": hex::encode(proof.bytes()),
-                    "publicOutputs": {} // Extract from proof
-                }).to_string()
-            )).await;
+    match client.execute(ELF, &stdin).run() {
+        Ok((_, report)) => {
+            send_event(&mut socket, &ProverEvent::Executing { cycles: report.total_instruction_count() }).await;
         }
-        Err(e) => {
-            let _ = socket.send(axum::extract::ws::Message::Text(
-                serde_json::json!({
-                    "type": "error",
-                    "message": e.to_string()
-                }).to_string()
-            )).await;
+        Err(e) => return send_event(&mut socket, &ProverEvent::Failed { error: e.to_string() }).await,
+    }
+
+    let event = match client.prove(&pk, &stdin).plonk().run() {
+        Ok(proof) => ProverEvent::Complete {
+            response: ProofResult {
+                proof: format!("0x{}", hex::encode(proof.bytes())),
+                public_values: format!("0x{}", hex::encode(proof.public_values.as_slice())),
+                public_outputs: None,
+            },
+        },
+        Err(e) => ProverEvent::Failed { error: e.to_string() },
+    };
+    send_event(&mut socket, &event).await;
+}
+
+/// Send a progress event as tagged JSON.
+async fn send_event(socket: &mut WebSocket, event: &ProverEvent) {
+    let json = serde_json::to_string(event).expect("ProverEvent serializes");
+    let _ = socket.send(axum::extract::ws::Message::Text(json)).await;
+}
+
+/// Build the prover selected by SP1_PROVER, falling back to CPU when CUDA
+/// initialization fails (no device, driver mismatch).
+fn build_client() -> sp1_sdk::EnvProver {
+    match std::panic::catch_unwind(ProverClient::from_env) {
+        Ok(client) => client,
+        Err(_) if std::env::var("SP1_PROVER").as_deref() == Ok("cuda") => {
+            eprintln!("CUDA prover initialization failed, falling back to CPU");
+            std::env::set_var("SP1_PROVER", "cpu");
+            ProverClient::from_env()
         }
+        Err(e) => std::panic::resume_unwind(e),
     }
-}
\ No newline at end of file
+}
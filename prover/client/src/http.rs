@@ -0,0 +1,147 @@
+//! HTTP transport: the prover-server's job API
+//!
+//! `POST /api/generate-proof` queues the request and returns a job ID;
+//! `GET /api/proof-status/:jobId` reports the job until it ends in `success`
+//! (with the proof response's fields) or `error` (with the host's error
+//! payload, `code` included). With tenants configured, the API key goes in
+//! `x-api-key`.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{ClientError, ErrorResponse, ProofRequest, ProofResponse};
+
+/// Default wait between status polls.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct HttpProver {
+    /// e.g. `http://localhost:3000`, without a trailing `/api`
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub poll_interval: Duration,
+    client: reqwest::blocking::Client,
+}
+
+/// Where a job is, as `/api/proof-status` reports it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    /// `queued`, `preparing`, `computing`, `proving`, `submitting`,
+    /// `success` or `error`
+    pub status: String,
+    #[serde(default)]
+    pub stage_description: Option<String>,
+    #[serde(default)]
+    pub progress: u8,
+}
+
+/// A rejected submission: `{"error", "traceId", "message"}`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rejection {
+    error: String,
+    #[serde(default)]
+    trace_id: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Submitted {
+    job_id: String,
+}
+
+impl HttpProver {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    fn authorized(&self, request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.header("x-api-key", key),
+            None => request,
+        }
+    }
+
+    /// Queue `request`; its job ID.
+    pub fn submit(&self, request: &ProofRequest) -> Result<String, ClientError> {
+        let url = format!("{}/api/generate-proof", self.base_url);
+        let response = self.authorized(self.client.post(&url).json(request)).send().map_err(ClientError::transport)?;
+        let status = response.status();
+        let body: Value = response.json().map_err(ClientError::transport)?;
+        if !status.is_success() {
+            return Err(rejected(body, status.as_u16()));
+        }
+        let submitted: Submitted = serde_json::from_value(body).map_err(ClientError::transport)?;
+        Ok(submitted.job_id)
+    }
+
+    /// The job's status, and its proof once it has succeeded.
+    pub fn poll(&self, job_id: &str) -> Result<(JobStatus, Option<ProofResponse>), ClientError> {
+        let url = format!("{}/api/proof-status/{}", self.base_url, job_id);
+        let response = self.authorized(self.client.get(&url)).send().map_err(ClientError::transport)?;
+        let status = response.status();
+        let body: Value = response.json().map_err(ClientError::transport)?;
+        if !status.is_success() {
+            return Err(rejected(body, status.as_u16()));
+        }
+        let job: JobStatus = serde_json::from_value(body.clone()).map_err(ClientError::transport)?;
+        match job.status.as_str() {
+            "success" => {
+                let proof = serde_json::from_value(body)
+                    .map_err(|e| ClientError::transport(format!("Invalid proof response: {}", e)))?;
+                Ok((job, Some(proof)))
+            }
+            "error" => {
+                let error = serde_json::from_value(body).map_err(ClientError::transport)?;
+                Err(ClientError::Prover(error))
+            }
+            _ => Ok((job, None)),
+        }
+    }
+
+    /// Submit `request` and poll until its job ends.
+    pub fn prove(&self, request: &ProofRequest) -> Result<ProofResponse, ClientError> {
+        let job_id = self.submit(request)?;
+        loop {
+            if let (_, Some(proof)) = self.poll(&job_id)? {
+                return Ok(proof);
+            }
+            sleep(self.poll_interval);
+        }
+    }
+}
+
+/// A non-2xx answer as a prover error.
+fn rejected(body: Value, status: u16) -> ClientError {
+    match serde_json::from_value::<Rejection>(body) {
+        Ok(rejection) => {
+            let error = match rejection.message {
+                Some(message) => format!("{}: {}", rejection.error, message),
+                None => rejection.error,
+            };
+            ClientError::Prover(ErrorResponse::new(error, rejection.trace_id))
+        }
+        Err(_) => ClientError::transport(format!("Prover-server answered HTTP {}", status)),
+    }
+}
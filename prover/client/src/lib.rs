@@ -0,0 +1,66 @@
+//! Client for Ghostclaw provers
+//!
+//! Everything a service needs to request proofs, without building the
+//! prover: the request and response types, the canonical request and
+//! response hashes operators sign (`request_hash`, `response_digest`), and
+//! transports to a prover:
+//!
+//! - `StdinProver`: runs the `sp1-host` binary, one request per process
+//! - `HttpProver` (`http` feature): the prover-server's job API
+//! - `WsProver` (`ws` feature): the prover CLI's WebSocket server, streaming
+//!   `ProverEvent`s
+//!
+//! Only `ghostclaw-core` (with its `std` feature) and serde are required;
+//! sp1-sdk stays behind in `sp1-host`.
+
+use std::fmt;
+
+pub mod response;
+pub mod stdin;
+
+#[cfg(feature = "http")]
+pub mod http;
+
+#[cfg(feature = "ws")]
+pub mod ws;
+
+pub use ghostclaw_core::proof_request::REQUEST_SCHEMA_VERSION;
+pub use ghostclaw_core::response_signature::{request_hash, response_digest};
+pub use ghostclaw_core::{NoteData, ProofRequest, ProofResult, ProverEvent, PublicOutputs, ResponseSignature};
+pub use response::{ErrorResponse, ProofResponse, DEADLINE_EXCEEDED};
+pub use stdin::StdinProver;
+
+#[cfg(feature = "http")]
+pub use http::HttpProver;
+
+#[cfg(feature = "ws")]
+pub use ws::WsProver;
+
+/// Why a proof couldn't be obtained.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// The prover answered with an error payload (see `ErrorResponse::code`)
+    Prover(ErrorResponse),
+    /// The prover couldn't be reached, or its answer couldn't be read
+    Transport { reason: String },
+}
+
+impl ClientError {
+    pub(crate) fn transport(reason: impl fmt::Display) -> Self {
+        ClientError::Transport { reason: reason.to_string() }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Prover(error) => match &error.code {
+                Some(code) => write!(f, "{} ({})", error.error, code),
+                None => write!(f, "{}", error.error),
+            },
+            ClientError::Transport { reason } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
@@ -0,0 +1,261 @@
+//! What the prover host writes to stdout
+//!
+//! A served request ends in one JSON line: a `ProofResponse`, or an
+//! `ErrorResponse` when proving failed. The prover-server keeps either in
+//! its job, so the HTTP transport reads the same fields back.
+
+use ghostclaw_core::linkability::LinkabilityReport;
+use ghostclaw_core::{Bytes32, PublicOutputs, ResponseSignature};
+use serde::{Deserialize, Serialize};
+
+/// Error code of a request whose `deadlineMs` passed before its proof
+pub const DEADLINE_EXCEEDED: &str = "DEADLINE_EXCEEDED";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofResponse {
+    pub proof: String,
+    pub public_values_raw: String,
+    pub public_outputs: PublicOutputs,
+    pub vkey_hash: String,
+    /// Public values commit list roots only; submit `publicOutputs` lists
+    /// as calldata via `submitTxCompressed`
+    #[serde(default)]
+    pub compressed: bool,
+    /// Backend routing decision (only set when SP1_PROVER=auto)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingDecision>,
+    /// Trace ID of the request this proof answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Guest cycles, when measured (SP1_PROVER=auto, METER_CYCLES or
+    /// MAX_TX_CYCLES set);
+    /// the prover-server meters tenants on this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u64>,
+    /// Where proof.bin, public_values.bin and manifest.json were uploaded
+    /// (only set when ARTIFACT_STORE is configured)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifacts: Option<ArtifactLocation>,
+    /// When `oldRoot` is expected to leave the ledger's root history (only
+    /// set for ledgers with a bounded history)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<ProofExpiry>,
+    /// Time spent in each stage of serving the request
+    #[serde(default)]
+    pub timings: StageTimings,
+    /// Network requests that failed before this proof
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_attempts: Vec<NetworkAttempt>,
+    /// Operator signature over the request, public outputs and vkey hash
+    /// (only set when the host has OPERATOR_KEY configured)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_signature: Option<ResponseSignature>,
+    /// `oldRoot` wasn't checked against the ledger (`--allow-unverified-root`
+    /// or demo mode); relayers refuse these proofs
+    #[serde(default)]
+    pub unverified_root: bool,
+    /// Patterns that could link this transaction to others (see core's
+    /// `linkability`); wallets can show these to the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linkability: Option<LinkabilityReport>,
+    /// Ledger root after this proof's outputs are inserted, if its
+    /// transaction is the next to insert any (only set when the host has
+    /// INDEXER_STATE_URL configured)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicted_new_root: Option<Bytes32>,
+}
+
+/// Error payload written to stdout when serving a request fails.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    pub error: String,
+    /// Machine-readable reason, for failures clients act on (e.g.
+    /// `DEADLINE_EXCEEDED`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Network requests that failed before giving up
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_attempts: Vec<NetworkAttempt>,
+}
+
+impl ErrorResponse {
+    pub fn new(error: impl Into<String>, trace_id: Option<String>) -> Self {
+        Self { error: error.into(), code: None, trace_id, network_attempts: Vec::new() }
+    }
+
+    /// The last error payload in a prover's stdout, if any.
+    pub fn from_stdout(stdout: &str) -> Option<Self> {
+        stdout.lines().rev().find_map(|line| serde_json::from_str(line.trim()).ok())
+    }
+
+    pub fn is_deadline_exceeded(&self) -> bool {
+        self.code.as_deref() == Some(DEADLINE_EXCEEDED)
+    }
+}
+
+/// Backend a proof was (or will be) generated on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Cpu,
+    Network,
+}
+
+/// Routing decision recorded in `ProofResponse`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingDecision {
+    /// Backend that generated the proof
+    pub backend: Backend,
+    /// Cycles measured by executing the guest before proving
+    pub cycles: u64,
+    /// Threshold in effect when the decision was made
+    pub local_max_cycles: u64,
+}
+
+/// Where a proof's artifacts were stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactLocation {
+    /// `s3://bucket/prefix/<id>/` or `ipfs://<cid>/`; files sit beneath it
+    pub uri: String,
+    /// Directory CID (IPFS only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+    /// SHA-256 of the uploaded `manifest.json`
+    pub manifest_sha256: String,
+}
+
+/// When a proof's old root is expected to leave the root history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofExpiry {
+    /// Insertions the ledger can still take before the old root is evicted
+    pub remaining_roots: u64,
+    /// Estimated block of the evicting insertion
+    pub block: u64,
+    /// Estimated unix time of that block
+    pub timestamp: u64,
+}
+
+/// Milliseconds per stage; stages that didn't run are absent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTimings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precompute_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setup_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub core_prove_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrap_ms: Option<u64>,
+    /// Core proof plus wrap, however the backend ran them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prove_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encode_ms: Option<u64>,
+    /// Since the host started serving the request
+    pub total_ms: u64,
+}
+
+impl StageTimings {
+    pub const EMPTY: Self = Self {
+        parse_ms: None,
+        precompute_ms: None,
+        execute_ms: None,
+        setup_ms: None,
+        core_prove_ms: None,
+        wrap_ms: None,
+        prove_ms: None,
+        encode_ms: None,
+        total_ms: 0,
+    };
+}
+
+/// Why a network proof request failed, as far as retrying goes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FailureKind {
+    AuctionNotFilled,
+    Timeout,
+    RateLimited,
+    Unreachable,
+    /// Not worth retrying
+    Permanent,
+}
+
+impl FailureKind {
+    /// Classify an SDK error by its message (the SDK doesn't expose typed
+    /// network errors).
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_ascii_lowercase();
+        let any = |needles: &[&str]| needles.iter().any(|n| error.contains(n));
+        if any(&["unfulfillable", "not fulfilled", "no bids", "auction"]) {
+            FailureKind::AuctionNotFilled
+        } else if any(&["rate limit", "too many requests", "429", "resource exhausted"]) {
+            FailureKind::RateLimited
+        } else if any(&["timed out", "timeout", "deadline exceeded"]) {
+            FailureKind::Timeout
+        } else if any(&["connection", "unavailable", "503", "502", "broken pipe", "dns"]) {
+            FailureKind::Unreachable
+        } else {
+            FailureKind::Permanent
+        }
+    }
+
+    pub fn is_transient(self) -> bool {
+        self != FailureKind::Permanent
+    }
+}
+
+/// One failed network request, reported in `ProofResponse.networkAttempts`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkAttempt {
+    pub attempt: u32,
+    pub kind: FailureKind,
+    pub error: String,
+    /// Backoff before the next attempt; absent when giving up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_in_ms: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_payload_is_last_json_line() {
+        let stdout = concat!(
+            "not json\n",
+            r#"{"error":"Deadline exceeded: gave up","code":"DEADLINE_EXCEEDED","traceId":"t1","#,
+            r#""networkAttempts":[{"attempt":1,"kind":"auctionNotFilled","error":"no bids"}]}"#,
+            "\n"
+        );
+        let error = ErrorResponse::from_stdout(stdout).unwrap();
+        assert!(error.is_deadline_exceeded());
+        assert_eq!(error.trace_id.as_deref(), Some("t1"));
+        assert_eq!(error.network_attempts[0].kind, FailureKind::AuctionNotFilled);
+        assert_eq!(ErrorResponse::from_stdout("Proof generated\n"), None);
+
+        let json = serde_json::to_string(&ErrorResponse::new("Invalid request", None)).unwrap();
+        assert_eq!(json, r#"{"error":"Invalid request"}"#);
+    }
+
+    #[test]
+    fn test_failure_classification() {
+        assert_eq!(FailureKind::classify("Request unfulfillable: no bids"), FailureKind::AuctionNotFilled);
+        assert_eq!(FailureKind::classify("HTTP 429 Too Many Requests"), FailureKind::RateLimited);
+        assert_eq!(FailureKind::classify("proof request timed out"), FailureKind::Timeout);
+        assert!(!FailureKind::classify("insufficient balance").is_transient());
+    }
+}
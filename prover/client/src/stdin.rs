@@ -0,0 +1,85 @@
+//! Stdin transport: the prover host as a child process
+//!
+//! The host reads one request line on stdin and writes a `ProofResponse`
+//! (or, on failure, an `ErrorResponse`) as the last line of its stdout; its
+//! logs go to stderr, which is passed through.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::{ClientError, ErrorResponse, ProofRequest, ProofResponse};
+
+/// Runs `sp1-host` (or a compatible binary) once per request.
+#[derive(Debug, Clone)]
+pub struct StdinProver {
+    pub binary: PathBuf,
+    /// Extra arguments, e.g. `--backend network`
+    pub args: Vec<String>,
+    /// Extra environment, e.g. `SP1_PROVER` or `NETWORK_PRIVATE_KEY`
+    pub envs: Vec<(String, String)>,
+}
+
+impl StdinProver {
+    pub fn new(binary: impl Into<PathBuf>) -> Self {
+        Self { binary: binary.into(), args: Vec::new(), envs: Vec::new() }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Prove `request`, waiting for the host to exit.
+    pub fn prove(&self, request: &ProofRequest) -> Result<ProofResponse, ClientError> {
+        let line = serde_json::to_string(request).map_err(ClientError::transport)?;
+        let mut child = Command::new(&self.binary)
+            .args(&self.args)
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| ClientError::transport(format!("Failed to start {}: {}", self.binary.display(), e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{}", line).map_err(ClientError::transport)?;
+        }
+        let output = child.wait_with_output().map_err(ClientError::transport)?;
+        parse_output(output.status.success(), &String::from_utf8_lossy(&output.stdout), request.trace_id.clone())
+    }
+}
+
+/// The host's answer from its exit status and stdout.
+fn parse_output(success: bool, stdout: &str, trace_id: Option<String>) -> Result<ProofResponse, ClientError> {
+    if success {
+        let last = stdout.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or_default();
+        return serde_json::from_str(last).map_err(|e| ClientError::transport(format!("Invalid proof response: {}", e)));
+    }
+    let error = ErrorResponse::from_stdout(stdout)
+        .unwrap_or_else(|| ErrorResponse::new("Prover exited without an error payload", trace_id));
+    Err(ClientError::Prover(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_host_reports_its_error_payload() {
+        let stdout = "{\"error\":\"Invalid request: Unsupported request schemaVersion 2\",\"traceId\":\"t1\"}\n";
+        match parse_output(false, stdout, None) {
+            Err(ClientError::Prover(error)) => assert_eq!(error.trace_id.as_deref(), Some("t1")),
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_output(false, "", Some("t2".to_string())) {
+            Err(ClientError::Prover(error)) => assert_eq!(error.trace_id.as_deref(), Some("t2")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(parse_output(true, "garbage\n", None), Err(ClientError::Transport { .. })));
+    }
+}
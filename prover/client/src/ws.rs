@@ -0,0 +1,55 @@
+//! WebSocket transport: the prover CLI's local server
+//!
+//! One connection per proof: the client sends `{"type": "prove",
+//! "witness": ...}` and the server streams `ProverEvent`s until `complete`
+//! or `failed`. Plain `ws://` only; the server listens on localhost.
+
+use serde_json::json;
+use tungstenite::Message;
+
+use crate::{ClientError, ErrorResponse, ProofResult, ProverEvent};
+use ghostclaw_core::Witness;
+
+/// Where the prover CLI listens by default.
+pub const DEFAULT_WS_URL: &str = "ws://localhost:3001";
+
+#[derive(Debug, Clone)]
+pub struct WsProver {
+    pub url: String,
+}
+
+impl Default for WsProver {
+    fn default() -> Self {
+        Self::new(DEFAULT_WS_URL)
+    }
+}
+
+impl WsProver {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+
+    /// Prove `witness`, passing each progress event to `on_event`.
+    pub fn prove(&self, witness: &Witness, mut on_event: impl FnMut(&ProverEvent)) -> Result<ProofResult, ClientError> {
+        let (mut socket, _) = tungstenite::connect(self.url.as_str())
+            .map_err(|e| ClientError::transport(format!("Failed to connect to {}: {}", self.url, e)))?;
+        let message = json!({ "type": "prove", "witness": witness });
+        socket.send(Message::Text(message.to_string())).map_err(ClientError::transport)?;
+
+        loop {
+            let text = match socket.read().map_err(ClientError::transport)? {
+                Message::Text(text) => text,
+                Message::Close(_) => return Err(ClientError::transport("Prover closed the connection before finishing")),
+                _ => continue,
+            };
+            let event: ProverEvent =
+                serde_json::from_str(&text).map_err(|e| ClientError::transport(format!("Invalid prover event: {}", e)))?;
+            on_event(&event);
+            match event {
+                ProverEvent::Complete { response } => return Ok(response),
+                ProverEvent::Failed { error } => return Err(ClientError::Prover(ErrorResponse::new(error, None))),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! Optional out-of-band storage for proof artifacts
+//!
+//! When `ARTIFACT_STORE` is set, every proof's `proof.bin`,
+//! `public_values.bin` and a `manifest.json` (sizes and SHA-256 digests) are
+//! uploaded after proving, and the location is returned in
+//! `ProofResponse.artifacts`. Relayers and auditors fetch them from there
+//! instead of relying on the JSON response.
+//!
+//! - `ARTIFACT_STORE=s3`: any S3-compatible store, path-style PUTs signed
+//!   with SigV4. `ARTIFACT_S3_ENDPOINT`, `ARTIFACT_S3_BUCKET`,
+//!   `ARTIFACT_S3_REGION` (default `us-east-1`), `ARTIFACT_S3_PREFIX`
+//!   (default `proofs`), `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+//!   optionally `AWS_SESSION_TOKEN`.
+//! - `ARTIFACT_STORE=ipfs`: an IPFS node's HTTP API at `ARTIFACT_IPFS_API`
+//!   (default `http://127.0.0.1:5001`); the files are added as one
+//!   directory and its CID returned.
+//!
+//! Upload failures are logged and leave `artifacts` unset; the proof itself
+//! is still returned.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub use ghostclaw_prover_client::response::ArtifactLocation;
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactFile {
+    pub name: &'static str,
+    pub size: usize,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u32,
+    pub vkey_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    pub compressed: bool,
+    pub created_at: u64,
+    pub files: Vec<ArtifactFile>,
+}
+
+pub enum ArtifactStore {
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: String,
+        prefix: String,
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+    },
+    Ipfs {
+        api: String,
+    },
+}
+
+fn required(name: &str) -> Result<String, String> {
+    std::env::var(name).map_err(|_| format!("{} is required for ARTIFACT_STORE=s3", name))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl ArtifactStore {
+    /// The configured store, `Ok(None)` if uploads are disabled.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match std::env::var("ARTIFACT_STORE").ok().as_deref() {
+            None | Some("") | Some("none") => Ok(None),
+            Some("s3") => Ok(Some(ArtifactStore::S3 {
+                endpoint: required("ARTIFACT_S3_ENDPOINT")?.trim_end_matches('/').to_string(),
+                bucket: required("ARTIFACT_S3_BUCKET")?,
+                region: std::env::var("ARTIFACT_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                prefix: std::env::var("ARTIFACT_S3_PREFIX")
+                    .unwrap_or_else(|_| "proofs".to_string())
+                    .trim_matches('/')
+                    .to_string(),
+                access_key: required("AWS_ACCESS_KEY_ID")?,
+                secret_key: required("AWS_SECRET_ACCESS_KEY")?,
+                session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            })),
+            Some("ipfs") => Ok(Some(ArtifactStore::Ipfs {
+                api: std::env::var("ARTIFACT_IPFS_API")
+                    .unwrap_or_else(|_| "http://127.0.0.1:5001".to_string())
+                    .trim_end_matches('/')
+                    .to_string(),
+            })),
+            Some(other) => Err(format!("Unknown ARTIFACT_STORE {:?} (s3, ipfs)", other)),
+        }
+    }
+
+    /// Upload a proof's artifacts.
+    pub fn upload(
+        &self,
+        proof: &[u8],
+        public_values: &[u8],
+        vkey_hash: &str,
+        trace_id: Option<String>,
+        compressed: bool,
+    ) -> Result<ArtifactLocation, String> {
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            vkey_hash: vkey_hash.to_string(),
+            trace_id,
+            compressed,
+            created_at: unix_now(),
+            files: vec![
+                ArtifactFile { name: "proof.bin", size: proof.len(), sha256: sha256_hex(proof) },
+                ArtifactFile { name: "public_values.bin", size: public_values.len(), sha256: sha256_hex(public_values) },
+            ],
+        };
+        let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| format!("Manifest: {}", e))?;
+        let files: [(&str, &[u8]); 3] = [
+            ("proof.bin", proof),
+            ("public_values.bin", public_values),
+            ("manifest.json", &manifest_json),
+        ];
+        let manifest_sha256 = sha256_hex(&manifest_json);
+
+        match self {
+            ArtifactStore::S3 { bucket, prefix, .. } => {
+                // Keyed by the public values digest: one directory per statement proven
+                let dir = match prefix.as_str() {
+                    "" => manifest.files[1].sha256.clone(),
+                    prefix => format!("{}/{}", prefix, manifest.files[1].sha256),
+                };
+                for (name, body) in files {
+                    self.s3_put(&format!("{}/{}", dir, name), body)?;
+                }
+                Ok(ArtifactLocation { uri: format!("s3://{}/{}/", bucket, dir), cid: None, manifest_sha256 })
+            }
+            ArtifactStore::Ipfs { api } => {
+                let cid = ipfs_add_directory(api, &files)?;
+                Ok(ArtifactLocation { uri: format!("ipfs://{}/", cid), cid: Some(cid), manifest_sha256 })
+            }
+        }
+    }
+
+    fn s3_put(&self, key: &str, body: &[u8]) -> Result<(), String> {
+        let ArtifactStore::S3 { endpoint, bucket, region, access_key, secret_key, session_token, .. } = self else {
+            unreachable!("s3_put on a non-S3 store");
+        };
+        let url = reqwest::Url::parse(&format!("{}/{}/{}", endpoint, bucket, key))
+            .map_err(|e| format!("Invalid ARTIFACT_S3_ENDPOINT: {}", e))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err("ARTIFACT_S3_ENDPOINT has no host".to_string()),
+        };
+
+        let (amz_date, date) = amz_timestamps(unix_now());
+        let payload_hash = sha256_hex(body);
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+        let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+        let canonical_request =
+            format!("PUT\n{}\n\n{}\n{}\n{}", url.path(), canonical_headers, signed_headers, payload_hash);
+
+        let scope = format!("{}/{}/s3/aws4_request", date, region);
+        let string_to_sign =
+            format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, sha256_hex(canonical_request.as_bytes()));
+        let signing_key = [region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes()), |key, part| {
+                hmac_sha256(&key, part.as_bytes())
+            });
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        let mut request = reqwest::blocking::Client::new().put(url.clone()).header(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                access_key, scope, signed_headers, signature
+            ),
+        );
+        for (name, value) in headers.into_iter().filter(|(k, _)| *k != "host") {
+            request = request.header(name, value);
+        }
+        let response = request.body(body.to_vec()).send().map_err(|e| format!("PUT {} failed: {}", url, e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(format!("PUT {} returned {}: {}", url, status, response.text().unwrap_or_default()));
+        }
+        Ok(())
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// `(YYYYMMDDTHHMMSSZ, YYYYMMDD)` for a Unix timestamp, as SigV4 wants.
+fn amz_timestamps(unix: u64) -> (String, String) {
+    let days = (unix / 86_400) as i64;
+    let secs = unix % 86_400;
+    // Civil-from-days (proleptic Gregorian)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let time = format!("{:02}{:02}{:02}", secs / 3_600, (secs % 3_600) / 60, secs % 60);
+    (format!("{}T{}Z", date, time), date)
+}
+
+/// Add `files` to IPFS wrapped in a directory and return the directory CID.
+fn ipfs_add_directory(api: &str, files: &[(&str, &[u8])]) -> Result<String, String> {
+    let mut form = reqwest::blocking::multipart::Form::new();
+    for (name, body) in files {
+        form = form.part("file", reqwest::blocking::multipart::Part::bytes(body.to_vec()).file_name(name.to_string()));
+    }
+    let url = format!("{}/api/v0/add?wrap-with-directory=true&cid-version=1&pin=true", api);
+    let response = reqwest::blocking::Client::new()
+        .post(&url)
+        .multipart(form)
+        .send()
+        .map_err(|e| format!("IPFS add failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("IPFS add returned {}", response.status()));
+    }
+    let body = response.text().map_err(|e| format!("IPFS add: {}", e))?;
+
+    // One JSON object per added entry; the wrapping directory has an empty name
+    #[derive(Deserialize)]
+    struct Added {
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(rename = "Hash")]
+        hash: String,
+    }
+    body.lines()
+        .filter_map(|line| serde_json::from_str::<Added>(line).ok())
+        .find(|added| added.name.is_empty())
+        .map(|added| added.hash)
+        .ok_or_else(|| "IPFS add returned no directory CID".to_string())
+}
+
+/// Upload with the configured store, if any. Errors are logged, not raised.
+pub fn upload_from_env(
+    proof: &[u8],
+    public_values: &[u8],
+    vkey_hash: &str,
+    trace_id: Option<String>,
+    compressed: bool,
+) -> Option<ArtifactLocation> {
+    let store = match ArtifactStore::from_env() {
+        Ok(store) => store?,
+        Err(e) => {
+            log!("Artifact upload skipped: {}", e);
+            return None;
+        }
+    };
+    match store.upload(proof, public_values, vkey_hash, trace_id, compressed) {
+        Ok(location) => {
+            log!("Artifacts uploaded to {}", location.uri);
+            Some(location)
+        }
+        Err(e) => {
+            log!("Artifact upload failed: {}", e);
+            None
+        }
+    }
+}
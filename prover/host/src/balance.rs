@@ -0,0 +1,132 @@
+//! `prove-balance` subcommand: prove the wallet holds at least a threshold
+//!
+//! # Usage
+//! WALLET_SEED=<hex> sp1-host prove-balance --wallet <state.json> --threshold <n>
+//!     --challenge <32-byte hex> [--contract <address>] [--rpc-url <url>]
+//!     [--block <n>] [--from-block <n>] [--log-chunk <blocks>]
+//!     [--elf <balance-proof-elf>] [--execute]
+//!
+//! The ledger's events are replayed up to `--block` (default: head) to
+//! rebuild the tree and the spent-nullifier set; the replayed root must equal
+//! the contract's `currentRoot` there. Notes are then selected from the
+//! wallet state, largest first, skipping any the chain has seen spent, and
+//! the `balance-proof` guest proves they cover `--threshold` (see core
+//! `balance_proof`). The partner supplies `--challenge` and checks the
+//! printed statement's root and `spentNullifiersHash` against the chain.
+//!
+//! `--execute` runs the guest without proving. The seed is read from
+//! WALLET_SEED rather than argv so it doesn't show up in process listings.
+
+use serde::Serialize;
+use sp1_sdk::{HashableKey, Prover, ProverClient, SP1Stdin};
+use ghostclaw_core::balance_proof::{decode_balance_statement, prepare_balance_witness};
+use ghostclaw_core::{Bytes32, MerkleTree, WalletState};
+
+use crate::reconcile::{call, currentRootCall, replay_events};
+use crate::rpc;
+
+const DEFAULT_ELF: &str = "../program/elf/balance-proof";
+
+/// Printed to stdout as JSON.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceProofResponse {
+    pub block_number: u64,
+    pub root: Bytes32,
+    pub threshold: String,
+    pub challenge: Bytes32,
+    pub owner: Bytes32,
+    pub spent_nullifiers_hash: Bytes32,
+    pub spent_nullifier_count: usize,
+    /// Absent with `--execute`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<String>,
+    pub public_values_raw: String,
+    pub vkey_hash: String,
+}
+
+pub fn run(args: &[String]) {
+    let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+    let contract = crate::flag_value(args, "--contract")
+        .or_else(rpc::ledger_contract_from_env)
+        .expect("Pass --contract <address> or set LEDGER_CONTRACT");
+    let from_block: u64 = crate::flag_value(args, "--from-block")
+        .map(|v| v.parse().expect("Invalid --from-block"))
+        .unwrap_or(0);
+    let chunk: u64 = crate::flag_value(args, "--log-chunk")
+        .map(|v| v.parse().expect("Invalid --log-chunk"))
+        .unwrap_or(10_000)
+        .max(1);
+    let threshold: u128 = crate::flag_value(args, "--threshold")
+        .expect("Pass --threshold <amount>")
+        .parse()
+        .expect("Invalid --threshold");
+    let challenge: Bytes32 = crate::flag_value(args, "--challenge")
+        .expect("Pass --challenge <32-byte hex from the verifier>")
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid --challenge: {}", e));
+    let seed = ghostclaw_core::hex::decode_hex(&std::env::var("WALLET_SEED").expect("Set WALLET_SEED to the wallet seed (hex)"))
+        .unwrap_or_else(|e| panic!("Invalid WALLET_SEED: {}", e));
+    let wallet_path = crate::flag_value(args, "--wallet").expect("Pass --wallet <state.json>");
+    let state: WalletState = std::fs::read_to_string(&wallet_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| panic!("Failed to load wallet state {}: {}", wallet_path, e));
+
+    let block = match crate::flag_value(args, "--block") {
+        Some(block) => block.parse().expect("Invalid --block"),
+        None => rpc::block_number(&rpc_url).expect("Failed to query chain head"),
+    };
+    log!("Replaying ledger events {}..={}...", from_block, block);
+    let replay = replay_events(&rpc_url, &contract, from_block, block, chunk).unwrap_or_else(|e| panic!("{}", e));
+    let tree = MerkleTree::with_leaves(replay.leaves);
+    let onchain_root = call(&rpc_url, &contract, &currentRootCall {}, block)
+        .unwrap_or_else(|e| panic!("{}", e))
+        ._0
+        .0;
+    if tree.root() != onchain_root {
+        panic!(
+            "Replayed events give root {} but the contract has {} at block {}; replay from an earlier --from-block",
+            Bytes32(tree.root()),
+            Bytes32(onchain_root),
+            block
+        );
+    }
+
+    let spent_nullifier_count = replay.nullifiers.len();
+    let witness = prepare_balance_witness(&seed, threshold, challenge.0, &state, &tree, replay.nullifiers.sorted())
+        .unwrap_or_else(|e| panic!("{}", e));
+    log!("Proving {} notes against root {}", witness.notes.len(), Bytes32(witness.root));
+
+    let elf_path = crate::flag_value(args, "--elf").unwrap_or_else(|| DEFAULT_ELF.to_string());
+    let elf = std::fs::read(&elf_path).unwrap_or_else(|e| panic!("Failed to read {}: {}", elf_path, e));
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&witness);
+
+    let client = ProverClient::builder().cpu().build();
+    let (pk, vk) = client.setup(&elf);
+    let (public_values, proof) = if args.contains(&"--execute".to_string()) {
+        let (public_values, report) = client.execute(&elf, &stdin).run().expect("Guest execution failed");
+        log!("Executed in {} cycles", report.total_instruction_count());
+        (public_values.to_vec(), None)
+    } else {
+        let proof = client.prove(&pk, &stdin).groth16().run().expect("Failed to generate proof");
+        client.verify(&proof, &vk).expect("Proof failed to verify");
+        (proof.public_values.to_vec(), Some(format!("0x{}", hex::encode(proof.bytes()))))
+    };
+
+    let statement = decode_balance_statement(&public_values).unwrap_or_else(|e| panic!("{}", e));
+    let response = BalanceProofResponse {
+        block_number: block,
+        root: Bytes32(statement.root),
+        threshold: statement.threshold.to_string(),
+        challenge: Bytes32(statement.challenge),
+        owner: Bytes32(statement.owner),
+        spent_nullifiers_hash: Bytes32(statement.spent_nullifiers_hash),
+        spent_nullifier_count,
+        proof,
+        public_values_raw: format!("0x{}", hex::encode(&public_values)),
+        vkey_hash: format!("0x{}", vk.bytes32()),
+    };
+    println!("{}", serde_json::to_string_pretty(&response).unwrap());
+}
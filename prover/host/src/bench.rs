@@ -0,0 +1,352 @@
+//! `bench-proofs` subcommand: compare proof systems for capacity planning
+//!
+//! # Usage
+//! sp1-host bench-proofs [--shapes 1x2,2x2,4x4] [--systems groth16,plonk,compressed,mock]
+//!     [--backend cpu|network] [--runs <n>] [--artifacts <contracts/out>]
+//!     [--format json|csv] [--out <file>]
+//!
+//! Each shape `<inputs>x<outputs>` is a correctly signed transaction built
+//! with `prepare_transaction` against a local tree, so the guest does the
+//! same work as for a real request. For every shape, system and run the
+//! report records guest cycles and prover gas (from executing the guest),
+//! proving time, proof size, the most the network may charge for it
+//! (prover gas × SP1_MAX_PRICE_PER_PGU, when set), and the gas the v5
+//! Solidity verifier uses on the proof (Groth16 and PLONK only; needs the
+//! forge artifacts in `--artifacts`).
+//!
+//! Proof size is the on-chain proof bytes for Groth16 and PLONK, and the
+//! serialized proof for compressed. Mock proofs are timed only.
+//!
+//! A failed run is recorded with its error rather than aborting the bench.
+
+use std::path::Path;
+use std::time::Instant;
+
+use serde::Serialize;
+use sp1_sdk::{HashableKey, Prover, ProverClient, SP1ProofMode, SP1ProofWithPublicValues, SP1Stdin};
+use ghostclaw_core::owner::owner_from_spending_key;
+use ghostclaw_core::prepare::{derive_spending_key, owner_pubkey};
+use ghostclaw_core::{prepare_transaction, GuestInput, MerkleTree, Note, ProofRequest, PublicInputs, Recipient, WalletState};
+
+use crate::network::NetworkConfig;
+use crate::{build_witness_from_request, deploy, verify_evm, ELF};
+
+const BENCH_SEED: &[u8] = b"bench-proofs-seed";
+
+/// Amount of each input note.
+const INPUT_AMOUNT: u64 = 1_000;
+
+/// A transaction shape: number of inputs and outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shape {
+    pub inputs: usize,
+    pub outputs: usize,
+}
+
+impl std::str::FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (inputs, outputs) = s.split_once('x').ok_or_else(|| format!("Shape {:?} is not <inputs>x<outputs>", s))?;
+        let shape = Shape {
+            inputs: inputs.trim().parse().map_err(|e| format!("Invalid shape {:?}: {}", s, e))?,
+            outputs: outputs.trim().parse().map_err(|e| format!("Invalid shape {:?}: {}", s, e))?,
+        };
+        if shape.inputs == 0 || shape.outputs == 0 {
+            return Err(format!("Shape {:?} needs at least one input and one output", s));
+        }
+        Ok(shape)
+    }
+}
+
+impl std::fmt::Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.inputs, self.outputs)
+    }
+}
+
+/// A proof system under test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum System {
+    Groth16,
+    Plonk,
+    Compressed,
+    Mock,
+}
+
+impl std::str::FromStr for System {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "groth16" => Ok(System::Groth16),
+            "plonk" => Ok(System::Plonk),
+            "compressed" => Ok(System::Compressed),
+            "mock" => Ok(System::Mock),
+            other => Err(format!("Unknown proof system {:?} (groth16, plonk, compressed, mock)", other)),
+        }
+    }
+}
+
+impl System {
+    fn name(self) -> &'static str {
+        match self {
+            System::Groth16 => "groth16",
+            System::Plonk => "plonk",
+            System::Compressed => "compressed",
+            System::Mock => "mock",
+        }
+    }
+
+    fn mode(self) -> SP1ProofMode {
+        match self {
+            System::Groth16 | System::Mock => SP1ProofMode::Groth16,
+            System::Plonk => SP1ProofMode::Plonk,
+            System::Compressed => SP1ProofMode::Compressed,
+        }
+    }
+
+    /// Forge artifact of the v5 Solidity verifier for this system.
+    fn verifier_artifact(self) -> Option<&'static str> {
+        match self {
+            System::Groth16 => Some("SP1VerifierGroth16.sol"),
+            System::Plonk => Some("SP1VerifierPlonk.sol"),
+            System::Compressed | System::Mock => None,
+        }
+    }
+}
+
+/// One measured proof.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchRow {
+    pub shape: String,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub system: System,
+    pub backend: String,
+    pub run: usize,
+    pub cycles: Option<u64>,
+    pub prover_gas: Option<u64>,
+    pub proving_ms: Option<u128>,
+    pub proof_bytes: Option<usize>,
+    /// Prover gas × SP1_MAX_PRICE_PER_PGU (network backend only)
+    pub max_network_cost: Option<u128>,
+    pub verify_gas: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Printed (or written to `--out`) as JSON.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+    pub vkey_hash: String,
+    pub rows: Vec<BenchRow>,
+}
+
+/// A signed request spending `shape.inputs` notes into `shape.outputs`
+/// outputs, with no fee or change.
+fn shape_request(shape: Shape, chain_id: u64) -> Result<ProofRequest, String> {
+    let owner = owner_pubkey(&derive_spending_key(BENCH_SEED)?)?;
+    let recipient = owner_from_spending_key(&[0x22; 32])?;
+
+    let mut tree = MerkleTree::new();
+    let mut state = WalletState::new();
+    for i in 0..shape.inputs {
+        let mut blinding = [0x42; 32];
+        blinding[..8].copy_from_slice(&(i as u64).to_be_bytes());
+        let note = Note::new(INPUT_AMOUNT, owner, blinding);
+        let index = tree.push_note(&note).index;
+        state.add_note(note, index as u64);
+    }
+
+    let total = INPUT_AMOUNT * shape.inputs as u64;
+    let share = total / shape.outputs as u64;
+    if share == 0 {
+        return Err(format!("Shape {} has more outputs than value to split", shape));
+    }
+    let recipients: Vec<Recipient> = (0..shape.outputs)
+        .map(|i| Recipient {
+            owner_pubkey: recipient,
+            amount: if i == 0 { total - share * (shape.outputs as u64 - 1) } else { share },
+        })
+        .collect();
+    prepare_transaction(chain_id, BENCH_SEED, &recipients, 0, &mut state, &tree)
+}
+
+fn shape_stdin(shape: Shape, strip_derivable: bool) -> Result<SP1Stdin, String> {
+    let request = shape_request(shape, 31337)?;
+    let witness = build_witness_from_request(&request);
+    let witness = if strip_derivable { witness.strip_derivable() } else { witness };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(PublicInputs::new(request.old_root.0).with_chain_id(request.chain_id), witness));
+    Ok(stdin)
+}
+
+fn proof_size(proof: &SP1ProofWithPublicValues, system: System) -> Result<Option<usize>, String> {
+    match system {
+        System::Groth16 | System::Plonk => Ok(Some(proof.bytes().len())),
+        System::Compressed => {
+            let path = std::env::temp_dir().join(format!("bench-proof-{}.bin", std::process::id()));
+            proof.save(&path).map_err(|e| format!("Failed to serialize proof: {}", e))?;
+            let size = std::fs::metadata(&path).map(|m| m.len() as usize);
+            let _ = std::fs::remove_file(&path);
+            size.map(Some).map_err(|e| format!("Failed to measure proof: {}", e))
+        }
+        System::Mock => Ok(None),
+    }
+}
+
+fn verify_gas(proof: &SP1ProofWithPublicValues, system: System, vkey: [u8; 32], artifacts: &Path) -> Result<Option<u64>, String> {
+    let Some(artifact) = system.verifier_artifact() else {
+        return Ok(None);
+    };
+    // Without forge artifacts verify gas just isn't measured (logged up front)
+    let Ok(code) = deploy::creation_code(artifacts, artifact, "SP1Verifier") else {
+        return Ok(None);
+    };
+    let verification = verify_evm::verify_in_evm(&code, vkey, &proof.public_values.to_vec(), &proof.bytes())?;
+    match verification.error {
+        None => Ok(Some(verification.gas_used)),
+        Some(e) => Err(format!("Verifier rejected the proof: {}", e)),
+    }
+}
+
+fn to_csv(rows: &[BenchRow]) -> String {
+    fn cell<T: ToString>(value: &Option<T>) -> String {
+        value.as_ref().map(ToString::to_string).unwrap_or_default()
+    }
+
+    let mut csv = String::from(
+        "shape,inputs,outputs,system,backend,run,cycles,proverGas,provingMs,proofBytes,maxNetworkCost,verifyGas,error\n",
+    );
+    for row in rows {
+        let error = row.error.as_deref().map(|e| format!("\"{}\"", e.replace('"', "\"\""))).unwrap_or_default();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            row.shape,
+            row.inputs,
+            row.outputs,
+            row.system.name(),
+            row.backend,
+            row.run,
+            cell(&row.cycles),
+            cell(&row.prover_gas),
+            cell(&row.proving_ms),
+            cell(&row.proof_bytes),
+            cell(&row.max_network_cost),
+            cell(&row.verify_gas),
+            error
+        ));
+    }
+    csv
+}
+
+fn parse_list<T: std::str::FromStr<Err = String>>(value: &str) -> Vec<T> {
+    value
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse().unwrap_or_else(|e| panic!("{}", e)))
+        .collect()
+}
+
+/// Entry point for the `bench-proofs` subcommand.
+pub fn run(args: &[String]) {
+    let shapes: Vec<Shape> = parse_list(&crate::flag_value(args, "--shapes").unwrap_or_else(|| "1x2,2x2,4x4".to_string()));
+    let systems: Vec<System> =
+        parse_list(&crate::flag_value(args, "--systems").unwrap_or_else(|| "groth16,plonk,compressed,mock".to_string()));
+    let backend = crate::flag_value(args, "--backend").unwrap_or_else(|| "cpu".to_string());
+    let runs: usize = crate::flag_value(args, "--runs").map(|v| v.parse().expect("Invalid --runs")).unwrap_or(1).max(1);
+    let artifacts = crate::flag_value(args, "--artifacts").unwrap_or_else(|| "contracts/out".to_string());
+    let format = crate::flag_value(args, "--format").unwrap_or_else(|| "json".to_string());
+    if format != "json" && format != "csv" {
+        panic!("Unknown --format {:?} (json, csv)", format);
+    }
+    let network = match backend.as_str() {
+        "network" => Some(NetworkConfig::from_env_or_exit()),
+        "cpu" => None,
+        other => panic!("Unknown --backend {:?} (cpu, network)", other),
+    };
+
+    if !Path::new(&artifacts).is_dir() {
+        log!("No forge artifacts in {}; verify gas won't be measured (run `forge build` in contracts/)", artifacts);
+    }
+
+    let cpu = ProverClient::builder().cpu().build();
+    let mock = ProverClient::builder().mock().build();
+    let network_client = network.as_ref().map(NetworkConfig::client);
+    let (pk, vk) = cpu.setup(ELF);
+    let vkey: [u8; 32] = hex::decode(vk.bytes32().trim_start_matches("0x"))
+        .expect("Invalid vkey hash")
+        .try_into()
+        .expect("vkey hash is 32 bytes");
+
+    let mut rows = Vec::new();
+    for &shape in &shapes {
+        let stdin = shape_stdin(shape, network.is_some()).unwrap_or_else(|e| panic!("{}", e));
+        let (cycles, prover_gas) = match cpu.execute(ELF, &stdin).run() {
+            Ok((_, report)) => (Some(report.total_instruction_count()), report.gas),
+            Err(e) => panic!("Shape {} fails in the guest: {}", shape, e),
+        };
+        log!("Shape {}: {} cycles", shape, cycles.unwrap_or_default());
+
+        for &system in &systems {
+            for run in 1..=runs {
+                log!("  {} run {}/{}...", system.name(), run, runs);
+                let backend_name = if system == System::Mock { "mock" } else { backend.as_str() };
+                let start = Instant::now();
+                let proof = match (system, &network, &network_client) {
+                    (System::Mock, _, _) => mock.prove(&pk, &stdin).mode(system.mode()).run().map_err(|e| e.to_string()),
+                    (_, Some(config), Some(client)) => config.prove(client, &pk, &stdin, system.mode()),
+                    _ => cpu.prove(&pk, &stdin).mode(system.mode()).run().map_err(|e| e.to_string()),
+                };
+                let proving_ms = start.elapsed().as_millis();
+
+                let mut row = BenchRow {
+                    shape: shape.to_string(),
+                    inputs: shape.inputs,
+                    outputs: shape.outputs,
+                    system,
+                    backend: backend_name.to_string(),
+                    run,
+                    cycles,
+                    prover_gas,
+                    proving_ms: None,
+                    proof_bytes: None,
+                    max_network_cost: None,
+                    verify_gas: None,
+                    error: None,
+                };
+                if backend_name == "network" {
+                    let price = network.as_ref().and_then(|n| n.max_price_per_pgu);
+                    row.max_network_cost = prover_gas.zip(price).map(|(gas, price)| gas as u128 * price as u128);
+                }
+                let measured = proof.and_then(|proof| {
+                    row.proving_ms = Some(proving_ms);
+                    row.proof_bytes = proof_size(&proof, system)?;
+                    row.verify_gas = verify_gas(&proof, system, vkey, Path::new(&artifacts))?;
+                    Ok(())
+                });
+                if let Err(e) = measured {
+                    log!("  {} run {} failed: {}", system.name(), run, e);
+                    row.error = Some(e);
+                }
+                rows.push(row);
+            }
+        }
+    }
+
+    let output = match format.as_str() {
+        "csv" => to_csv(&rows),
+        _ => serde_json::to_string_pretty(&BenchReport { vkey_hash: vk.bytes32(), rows }).unwrap(),
+    };
+    match crate::flag_value(args, "--out") {
+        Some(path) => {
+            std::fs::write(&path, output).unwrap_or_else(|e| panic!("Failed to write {}: {}", path, e));
+            log!("Wrote {}", path);
+        }
+        None => println!("{}", output),
+    }
+}
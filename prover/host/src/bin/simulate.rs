@@ -0,0 +1,327 @@
+//! Protocol simulation for parameter decisions
+//!
+//! # Usage
+//! simulate [--wallets <n>] [--txs <n>] [--max-inputs <n>] [--max-outputs <n>]
+//!     [--execute-sample <n>] [--seed <n>] [--out <report.json>]
+//!
+//! Creates `--wallets` random wallets (real keys, see core `testing`), each
+//! funded with one deposit, then runs `--txs` random transfers between them
+//! against an in-memory `Ledger`: every transaction is a correctly signed
+//! witness checked by `simulate_witness`, and every output is encrypted to
+//! its recipient as a wallet would receive it. `--seed` fixes who pays whom
+//! and how much (keys and blindings stay random).
+//!
+//! The report covers what tree height and circuit shapes have to absorb:
+//! - tree growth: leaves, share of the tree's capacity and snapshot size,
+//!   at ten checkpoints
+//! - scan time: one wallet trial-decrypting every output so far, at the
+//!   same checkpoints (the cost a new wallet pays to sync)
+//! - witness size: serialized guest input per transaction, by shape
+//! - guest cycles: with `--execute-sample`, that many transactions spread
+//!   over the run are also executed in the guest (no proof), which must
+//!   accept them
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use serde::Serialize;
+use sp1_sdk::{ProverClient, SP1Stdin};
+use ghostclaw_core::encrypted_note::NotePlaintext;
+use ghostclaw_core::merkle::{capacity, TREE_HEIGHT};
+use ghostclaw_core::testing::{TestOwner, WitnessBuilder};
+use ghostclaw_core::{
+    generate_keypair, simulate_witness, EncryptedNote, GuestInput, Ledger, MerkleTree, Note, PublicInputs,
+    ViewPublicKey, ViewSecretKey, Witness,
+};
+
+pub const ELF: &[u8] = include_bytes!("../../../program/elf/sp1-program");
+
+/// Amount of each wallet's initial deposit.
+const DEPOSIT_AMOUNT: u64 = 1_000_000;
+
+struct Wallet {
+    owner: TestOwner,
+    view_secret: ViewSecretKey,
+    view_public: ViewPublicKey,
+    /// Unspent notes and their leaf indices
+    notes: Vec<(Note, usize)>,
+}
+
+/// xorshift64*: reproducible choices without another dependency.
+struct Choices(u64);
+
+impl Choices {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) % n as u64) as usize
+    }
+
+    fn between(&mut self, low: usize, high: usize) -> usize {
+        low + self.below(high - low + 1)
+    }
+}
+
+struct Options {
+    wallets: usize,
+    txs: usize,
+    max_inputs: usize,
+    max_outputs: usize,
+    execute_sample: usize,
+    seed: u64,
+    out: Option<String>,
+}
+
+impl Options {
+    fn from_args(args: &[String]) -> Self {
+        let flag = |name: &str, default: u64| -> u64 {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+                .map(|v| v.parse().unwrap_or_else(|e| panic!("Invalid {} {}: {}", name, v, e)))
+                .unwrap_or(default)
+        };
+        let options = Self {
+            wallets: flag("--wallets", 100) as usize,
+            txs: flag("--txs", 1_000) as usize,
+            max_inputs: flag("--max-inputs", 4) as usize,
+            max_outputs: flag("--max-outputs", 4) as usize,
+            execute_sample: flag("--execute-sample", 0) as usize,
+            seed: flag("--seed", 1).max(1),
+            out: args.iter().position(|a| a == "--out").and_then(|i| args.get(i + 1)).cloned(),
+        };
+        assert!(options.wallets >= 2, "--wallets must be at least 2");
+        assert!(options.max_inputs >= 1, "--max-inputs must be at least 1");
+        assert!(options.max_outputs >= 2, "--max-outputs must be at least 2 (payment and change)");
+        options
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Checkpoint {
+    txs: usize,
+    leaves: usize,
+    /// Leaves over the tree's capacity at TREE_HEIGHT
+    tree_fill: f64,
+    snapshot_bytes: usize,
+    /// One wallet trial-decrypting every encrypted output so far
+    scan_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShapeStats {
+    count: usize,
+    min_witness_bytes: usize,
+    max_witness_bytes: usize,
+    total_witness_bytes: usize,
+    /// Guest cycles of the executed sample of this shape
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cycles: Vec<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    wallets: usize,
+    txs: usize,
+    leaves_per_tx: f64,
+    /// Transactions until the tree is full at this rate
+    txs_until_full: f64,
+    checkpoints: Vec<Checkpoint>,
+    /// Keyed by `<inputs>x<outputs>`
+    shapes: BTreeMap<String, ShapeStats>,
+    elapsed_ms: f64,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let options = Options::from_args(&args);
+    let mut choices = Choices(options.seed);
+    let start = Instant::now();
+
+    let mut ledger = Ledger::new();
+    let mut tree = MerkleTree::new();
+    let mut encrypted: Vec<EncryptedNote> = Vec::new();
+    let mut wallets: Vec<Wallet> = (0..options.wallets)
+        .map(|_| {
+            let owner = TestOwner::random();
+            let (view_secret, view_public) = generate_keypair();
+            let deposit = owner.random_note(DEPOSIT_AMOUNT);
+            ledger.add_note(deposit.clone());
+            let index = tree.push_note(&deposit).index as usize;
+            encrypted.push(NotePlaintext::new(deposit.clone(), Some(index as u64)).encrypt(&view_public).unwrap());
+            Wallet { owner, view_secret, view_public, notes: vec![(deposit, index)] }
+        })
+        .collect();
+    println!("Funded {} wallets", wallets.len());
+
+    let sample_every = match options.execute_sample {
+        0 => usize::MAX,
+        n => (options.txs / n).max(1),
+    };
+    let checkpoint_every = (options.txs / 10).max(1);
+    let mut checkpoints = Vec::new();
+    let mut shapes: BTreeMap<String, ShapeStats> = BTreeMap::new();
+
+    for tx in 1..=options.txs {
+        let (public_inputs, witness, sender) = random_transfer(&mut wallets, &tree, &options, &mut choices);
+
+        let simulation = simulate_witness(&mut ledger, &witness, public_inputs.old_root);
+        assert!(simulation.is_valid(), "Transaction {} rejected: {:?}", tx, simulation.failure_reasons());
+
+        let shape = format!("{}x{}", witness.input_notes.len(), witness.output_notes.len());
+        let guest_input = GuestInput::new(public_inputs, witness);
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&guest_input);
+        let witness_bytes: usize = stdin.buffer.iter().map(Vec::len).sum();
+        let stats = shapes.entry(shape).or_default();
+        stats.min_witness_bytes = if stats.count == 0 { witness_bytes } else { stats.min_witness_bytes.min(witness_bytes) };
+        stats.max_witness_bytes = stats.max_witness_bytes.max(witness_bytes);
+        stats.total_witness_bytes += witness_bytes;
+        stats.count += 1;
+
+        if tx % sample_every == 0 {
+            stats.cycles.push(execute(&stdin, tx));
+        }
+
+        let witness = guest_input.witness;
+        let spent: Vec<[u8; 32]> = witness.precomputed_input_commitments.clone();
+        wallets[sender].notes.retain(|(note, _)| !spent.contains(&ghostclaw_core::commit(note)));
+        deliver_outputs(&mut wallets, &mut tree, &mut encrypted, witness);
+        assert_eq!(tree.root(), ledger.current_root(), "Mirror tree diverged from the ledger");
+
+        if tx % checkpoint_every == 0 || tx == options.txs {
+            let checkpoint = checkpoint(tx, &tree, &encrypted, &wallets[0]);
+            println!(
+                "  {:>7} txs: {:>8} leaves, snapshot {} KiB, scan {:.1} ms",
+                checkpoint.txs,
+                checkpoint.leaves,
+                checkpoint.snapshot_bytes / 1024,
+                checkpoint.scan_ms
+            );
+            checkpoints.push(checkpoint);
+        }
+    }
+
+    let leaves_per_tx = (tree.leaf_count() - options.wallets) as f64 / options.txs.max(1) as f64;
+    let report = Report {
+        wallets: options.wallets,
+        txs: options.txs,
+        leaves_per_tx,
+        txs_until_full: capacity(TREE_HEIGHT) as f64 / leaves_per_tx,
+        checkpoints,
+        shapes,
+        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+    };
+    print_report(&report);
+    if let Some(path) = &options.out {
+        let json = serde_json::to_string_pretty(&report).expect("Report serializes");
+        std::fs::write(path, json).unwrap_or_else(|e| panic!("Failed to write {}: {}", path, e));
+        println!("\nWrote {}", path);
+    }
+}
+
+/// A signed transfer from a random funded wallet to random recipients, with
+/// change back to the sender; returns the sender's index too.
+fn random_transfer(
+    wallets: &mut [Wallet],
+    tree: &MerkleTree,
+    options: &Options,
+    choices: &mut Choices,
+) -> (PublicInputs, Witness, usize) {
+    let funded: Vec<usize> = (0..wallets.len()).filter(|&i| !wallets[i].notes.is_empty()).collect();
+    let sender = funded[choices.below(funded.len())];
+    let wallet = &wallets[sender];
+
+    let input_count = choices.between(1, options.max_inputs.min(wallet.notes.len()));
+    let mut available: Vec<usize> = (0..wallet.notes.len()).collect();
+    let inputs: Vec<usize> = (0..input_count).map(|_| available.swap_remove(choices.below(available.len()))).collect();
+    let total: u64 = inputs.iter().map(|&i| wallet.notes[i].0.amount).sum();
+
+    // Payments of at most half of what's left each, then the change
+    let payments = choices.between(1, options.max_outputs - 1);
+    let mut builder = WitnessBuilder::new();
+    for &i in &inputs {
+        builder = builder.input_note(&wallet.owner, wallet.notes[i].0.clone());
+    }
+    let mut left = total;
+    for _ in 0..payments {
+        let recipient = (sender + 1 + choices.below(wallets.len() - 1)) % wallets.len();
+        let amount = (choices.below((left / 2).max(1) as usize) as u64).max(1).min(left);
+        left -= amount;
+        builder = builder.output(&wallets[recipient].owner, amount);
+    }
+    builder = builder.output(&wallet.owner, left);
+
+    let indices = inputs.iter().map(|&i| wallet.notes[i].1).collect();
+    let (public_inputs, witness) = builder.build_in(tree, indices).unwrap_or_else(|e| panic!("{}", e));
+    (public_inputs, witness, sender)
+}
+
+/// Insert the outputs as the ledger did, encrypting each to its owner and
+/// adding it to their wallet.
+fn deliver_outputs(wallets: &mut [Wallet], tree: &mut MerkleTree, encrypted: &mut Vec<EncryptedNote>, witness: Witness) {
+    for note in witness.output_notes {
+        let index = tree.push_note(&note).index as usize;
+        let recipient = wallets
+            .iter_mut()
+            .find(|w| w.owner.owner == note.owner_pubkey)
+            .expect("Outputs only pay simulated wallets");
+        encrypted.push(NotePlaintext::new(note.clone(), Some(index as u64)).encrypt(&recipient.view_public).unwrap());
+        recipient.notes.push((note, index));
+    }
+}
+
+fn checkpoint(txs: usize, tree: &MerkleTree, encrypted: &[EncryptedNote], scanner: &Wallet) -> Checkpoint {
+    let start = Instant::now();
+    let found = encrypted.iter().filter(|e| NotePlaintext::decrypt(e, &scanner.view_secret).is_some()).count();
+    let scan_ms = start.elapsed().as_secs_f64() * 1000.0;
+    assert!(found >= scanner.notes.len(), "Scan missed some of the wallet's notes");
+    Checkpoint {
+        txs,
+        leaves: tree.leaf_count(),
+        tree_fill: tree.leaf_count() as f64 / capacity(TREE_HEIGHT) as f64,
+        snapshot_bytes: tree.to_bytes().len(),
+        scan_ms,
+    }
+}
+
+/// Execute the guest on `stdin` (no proof); it must accept.
+fn execute(stdin: &SP1Stdin, tx: usize) -> u64 {
+    let client = ProverClient::builder().cpu().build();
+    match client.execute(ELF, stdin).run() {
+        Ok((_, report)) => report.total_instruction_count(),
+        Err(e) => panic!("Guest rejected transaction {}: {}", tx, e),
+    }
+}
+
+fn print_report(report: &Report) {
+    println!("\n=== Simulation ({} wallets, {} txs, {:.1} s) ===", report.wallets, report.txs, report.elapsed_ms / 1000.0);
+    println!(
+        "Tree: {:.2} leaves per tx; full after ~{:.3e} txs at height {}",
+        report.leaves_per_tx, report.txs_until_full, TREE_HEIGHT
+    );
+    if let Some(last) = report.checkpoints.last() {
+        let per_note_us = last.scan_ms * 1000.0 / last.leaves.max(1) as f64;
+        println!("Scan: {:.1} ms for {} outputs ({:.1} µs each)", last.scan_ms, last.leaves, per_note_us);
+    }
+    println!("Shapes:");
+    for (shape, stats) in &report.shapes {
+        let cycles = match stats.cycles.iter().max() {
+            Some(max) => format!(", up to {} cycles ({} executed)", max, stats.cycles.len()),
+            None => String::new(),
+        };
+        println!(
+            "  {:>5}: {:>6} txs, witness {}-{} bytes (avg {}){}",
+            shape,
+            stats.count,
+            stats.min_witness_bytes,
+            stats.max_witness_bytes,
+            stats.total_witness_bytes / stats.count.max(1),
+            cycles
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! `prove-chained` subcommand: prove, one after another, transactions that
+//! spend each other's outputs
+//!
+//! Transactions are ordered so producers come before consumers, the tree is
+//! projected locally after each one, and chained inputs are proven against
+//! the projected root (see `ghostclaw_core::batch`).
+//!
+//! This is sequential proving, not aggregation: every transaction gets its
+//! own proof and is submitted as its own ledger call, in the reported
+//! `order`. Nothing makes the set atomic. A transaction proven against a
+//! projected root only lands once the transactions before it have, and if
+//! another transaction inserts leaves in between, the projected roots never
+//! occur and the rest must be proven again. The ledger's `currentRoot` is
+//! `finalRoot` once all have landed with nothing in between.
+//!
+//! # Usage
+//! sp1-host prove-chained <chain.json> [--backend cpu|mock|network] [--allow-unverified-root]
+//!
+//! The file holds `{"leaves": [...], "transactions": [ProofRequest...]}`,
+//! where `leaves` are the ledger's current commitments in insertion order.
+//! `inputIndices`, `inputProofs` and `oldRoot` in the transactions are
+//! ignored and recomputed. All transactions must share a `chainId`.
+
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{Prover, ProverClient, SP1ProofWithPublicValues, SP1Stdin};
+use ghostclaw_core::{plan_chained_batch, BatchPlan, Bytes32, GuestInput, MerkleTree};
+
+use crate::network::NetworkConfig;
+use crate::timings::{self, Stage};
+use crate::{
+    build_proof_response, build_witness_from_request, preflight_witness, ExpectedOutputs, ProofRequest, ProofResponse, ELF,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainedRequest {
+    /// Current tree leaves (commitments, insertion order)
+    pub leaves: Vec<Bytes32>,
+    pub transactions: Vec<ProofRequest>,
+    /// Correlation ID from the caller, echoed in logs and responses
+    #[serde(default)]
+    pub trace_id: Option<String>,
+}
+
+impl crate::trace::Traced for ChainedRequest {
+    fn trace_id(&self) -> Option<&str> {
+        self.trace_id.as_deref()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.transactions.iter().try_for_each(ProofRequest::check_schema_version)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainedTxResponse {
+    /// Position of the transaction in the request
+    pub tx_index: usize,
+    /// Root after this transaction's outputs are appended
+    pub new_root: Bytes32,
+    pub response: ProofResponse,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainedResponse {
+    /// Submission order (request positions)
+    pub order: Vec<usize>,
+    pub final_root: Bytes32,
+    /// Proofs in submission order, one per transaction
+    pub transactions: Vec<ChainedTxResponse>,
+}
+
+/// Entry point for the `prove-chained` subcommand.
+pub fn run(args: &[String]) {
+    let path = args.get(2).expect("Usage: sp1-host prove-chained <chain.json> [--backend cpu|mock|network]");
+    let backend = crate::flag_value(args, "--backend")
+        .unwrap_or_else(|| std::env::var("SP1_PROVER").unwrap_or_default());
+
+    let data = std::fs::read_to_string(path).expect("Failed to read transactions file");
+    crate::operator::record_request(&data);
+    let chain: ChainedRequest = crate::trace::parse_request(&data);
+
+    // Transactions spend from one ledger, so they share its chain
+    let chain_id = chain.transactions.first().map_or(0, |tx| tx.chain_id);
+    if let Some(tx) = chain.transactions.iter().position(|tx| tx.chain_id != chain_id) {
+        panic!("Invalid transactions: transaction {} is for chain {}, not {}", tx, chain.transactions[tx].chain_id, chain_id);
+    }
+    let deployment = crate::chains::deployment_or_refuse(chain_id);
+
+    let base = MerkleTree::with_leaves(chain.leaves.iter().map(|l| l.0).collect());
+    let base_root = Bytes32(base.root());
+    // Chained steps prove against roots projected from this one
+    crate::root_check::configure(args);
+    crate::root_check::verify(deployment.as_ref(), base_root.0);
+
+    let witnesses = chain
+        .transactions
+        .iter()
+        .map(|request| {
+            // Proofs are recomputed by the planner; fill placeholders so the
+            // witness builder's shape checks pass.
+            let mut request = request.clone();
+            let inputs = request.input_notes.len();
+            request.input_indices = vec![0; inputs];
+            request.input_proofs = vec![Vec::new(); inputs];
+            request.old_root = base_root;
+            build_witness_from_request(&request)
+        })
+        .collect();
+
+    let plan = plan_chained_batch(&base, witnesses).unwrap_or_else(|e| panic!("Invalid transactions: {}", e));
+    log!("{} chained transactions, submission order {:?}", plan.steps.len(), plan.order());
+
+    let transactions = match backend.as_str() {
+        "network" => {
+            let network = NetworkConfig::from_env_or_exit();
+            let client = network.client();
+            let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+            prove_plan(&plan, chain_id, true, crate::checked_vkey_hash(&vk), false, |stdin| {
+                timings::time(Stage::Prove, || network.prove_groth16(&client, &pk, stdin)).unwrap_or_else(|e| panic!("{}", e))
+            })
+        }
+        "mock" => {
+            log!("Using Mock Prover (Fast)");
+            let client = ProverClient::builder().mock().build();
+            let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+            prove_plan(&plan, chain_id, false, crate::checked_vkey_hash(&vk), true, |stdin| {
+                timings::time(Stage::CoreProve, || client.prove(&pk, stdin).run()).expect("Failed to generate proof")
+            })
+        }
+        _ => {
+            log!("Using CPU Prover (Local)");
+            let client = ProverClient::builder().cpu().build();
+            let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+            prove_plan(&plan, chain_id, false, crate::checked_vkey_hash(&vk), false, |stdin| {
+                timings::time(Stage::CoreProve, || client.prove(&pk, stdin).run()).expect("Failed to generate proof")
+            })
+        }
+    };
+
+    let response = ChainedResponse {
+        order: plan.order(),
+        final_root: Bytes32(plan.final_root),
+        transactions,
+    };
+    println!("{}", serde_json::to_string(&response).unwrap());
+}
+
+/// Prove each planned step in submission order.
+fn prove_plan(
+    plan: &BatchPlan,
+    chain_id: u64,
+    strip_derivable: bool,
+    vkey_hash: String,
+    is_mock: bool,
+    prove: impl Fn(&SP1Stdin) -> SP1ProofWithPublicValues,
+) -> Vec<ChainedTxResponse> {
+    plan.steps
+        .iter()
+        .map(|step| {
+            log!(
+                "\nProving chained transaction {} against root 0x{}",
+                step.tx_index,
+                hex::encode(&step.public_inputs.old_root[..8])
+            );
+            let witness = if strip_derivable {
+                step.witness.clone().strip_derivable()
+            } else {
+                step.witness.clone()
+            };
+            let expected = timings::time(Stage::Precompute, || {
+                preflight_witness(&step.witness, step.public_inputs.old_root);
+                ExpectedOutputs::from_witness(&step.witness, chain_id)
+            });
+
+            let mut stdin = SP1Stdin::new();
+            stdin.write(&GuestInput::new(step.public_inputs.clone().with_chain_id(chain_id), witness));
+
+            let start = std::time::Instant::now();
+            let proof = prove(&stdin);
+            ChainedTxResponse {
+                tx_index: step.tx_index,
+                new_root: Bytes32(step.new_root),
+                response: build_proof_response(proof, start, &expected, vkey_hash.clone(), is_mock, None),
+            }
+        })
+        .collect()
+}
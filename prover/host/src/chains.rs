@@ -0,0 +1,114 @@
+//! Per-chain deployments
+//!
+//! Every `ProofRequest` names its `chainId`, which the proof is bound to.
+//! One host can serve ledgers on several chains: set DEPLOYMENTS to a JSON
+//! array of `deploy` manifests (or just `{"chainId", "ledger", "rpcUrl",
+//! "deployBlock"}`) and each request's pre-proving checks and expiry
+//! estimate use the entry for its chain. A chain missing from DEPLOYMENTS is
+//! refused.
+//!
+//! Without DEPLOYMENTS the single-chain settings apply (LEDGER_CONTRACT or
+//! DEPLOYMENT_MANIFEST, RPC_URL); if the manifest names a chain, requests for
+//! any other chain are refused.
+//!
+//! A deployment may set `maxCalldataBytes` and `maxProofBytes`; proofs whose
+//! ledger call would exceed them are refused (see core `calldata`). Chains
+//! that don't set them fall back to MAX_CALLDATA_BYTES / MAX_PROOF_BYTES,
+//! then 128 KiB of calldata.
+
+use serde::Deserialize;
+use ghostclaw_core::calldata::CalldataLimits;
+
+use crate::rpc;
+
+/// The ledger serving one chain.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    pub chain_id: u64,
+    pub ledger: String,
+    /// Falls back to RPC_URL_<chainId>, then RPC_URL
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    #[serde(default)]
+    pub deploy_block: Option<u64>,
+    #[serde(default)]
+    pub max_calldata_bytes: Option<usize>,
+    #[serde(default)]
+    pub max_proof_bytes: Option<usize>,
+}
+
+impl Deployment {
+    pub fn rpc_url(&self) -> String {
+        self.rpc_url
+            .clone()
+            .or_else(|| std::env::var(format!("RPC_URL_{}", self.chain_id)).ok())
+            .unwrap_or_else(rpc::rpc_url_from_env)
+    }
+}
+
+fn deployments_from_env() -> Option<Result<Vec<Deployment>, String>> {
+    let path = std::env::var("DEPLOYMENTS").ok()?;
+    Some(
+        std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| format!("Invalid DEPLOYMENTS {}: {}", path, e))),
+    )
+}
+
+/// The deployment requests for `chain_id` are checked against; `Ok(None)`
+/// when no ledger is configured at all.
+///
+/// # Errors
+/// Fails if the configuration doesn't serve `chain_id`.
+pub fn deployment_for(chain_id: u64) -> Result<Option<Deployment>, String> {
+    if let Some(deployments) = deployments_from_env() {
+        return deployments?
+            .into_iter()
+            .find(|d| d.chain_id == chain_id)
+            .map(Some)
+            .ok_or_else(|| format!("Chain {} is not configured in DEPLOYMENTS", chain_id));
+    }
+
+    let manifest = crate::deploy::manifest_from_env().and_then(Result::ok);
+    if let Some(manifest) = &manifest {
+        if manifest.chain_id != chain_id {
+            return Err(format!("Request is for chain {}, but this host serves chain {}", chain_id, manifest.chain_id));
+        }
+    }
+    Ok(rpc::ledger_contract_from_env().map(|ledger| Deployment {
+        chain_id,
+        ledger,
+        rpc_url: None,
+        deploy_block: manifest.and_then(|m| m.deploy_block),
+        max_calldata_bytes: None,
+        max_proof_bytes: None,
+    }))
+}
+
+fn usize_from_env(name: &str) -> Option<usize> {
+    std::env::var(name).ok().map(|v| v.parse().unwrap_or_else(|_| panic!("Invalid {}", name)))
+}
+
+/// Size limits for submitting proofs on `chain_id`.
+pub fn calldata_limits(chain_id: u64) -> CalldataLimits {
+    let deployment = deployment_for(chain_id).ok().flatten();
+    let defaults = CalldataLimits::default();
+    CalldataLimits {
+        max_calldata_bytes: deployment
+            .as_ref()
+            .and_then(|d| d.max_calldata_bytes)
+            .or_else(|| usize_from_env("MAX_CALLDATA_BYTES"))
+            .unwrap_or(defaults.max_calldata_bytes),
+        max_proof_bytes: deployment
+            .as_ref()
+            .and_then(|d| d.max_proof_bytes)
+            .or_else(|| usize_from_env("MAX_PROOF_BYTES")),
+    }
+}
+
+/// `deployment_for`, panicking (so the prover-server sees a JSON error) if
+/// the chain isn't served.
+pub fn deployment_or_refuse(chain_id: u64) -> Option<Deployment> {
+    deployment_for(chain_id).unwrap_or_else(|e| panic!("Refusing to prove: {}", e))
+}
@@ -0,0 +1,558 @@
+//! `daemon` subcommand: prove requests from a directory or message queue
+//!
+//! # Usage
+//! sp1-host daemon --watch <dir> [options]
+//! sp1-host daemon --redis <url> [--queue <key>] [--responses <key>] [options]
+//! sp1-host daemon --nats <url> [--subject <subject>] [--responses <subject>] [options]
+//!
+//! Options: `--backend <name>` and `--allow-unverified-root` (passed to each
+//! proof), `--concurrency <n>` (default 1), `--timeout-secs <n>` per proof,
+//! `--max-cycles-per-hour <n>`.
+//!
+//! Lets the host sit behind standard queue infrastructure instead of the
+//! prover-server's HTTP/WebSocket protocol. Each message is a `ProofRequest`
+//! (plain, `encryptedRequest` or `sealedFields`, as on stdin) or an envelope
+//! `{"priority": "interactive"|"background", "replyTo": ..., "request": {...}}`.
+//! Each request is proven by a child `sp1-host` process, as the
+//! prover-server does, and its stdout (a `ProofResponse`, or the
+//! `{"error", "traceId"}` payload) is published back:
+//!
+//! - `--watch`: `<dir>/*.json` is claimed into `processing/`, answered in
+//!   `out/` (or `failed/`) under the same name. Several daemons can share a
+//!   directory. Producers should write under another extension and rename,
+//!   so a half-written file is never claimed.
+//! - `--redis`: requests are popped from the `--queue` list (default
+//!   `proof-requests`), responses pushed to `replyTo` or `--responses`
+//!   (default `proof-responses`).
+//! - `--nats`: requests arrive on `--subject` (default `proofs.requests`) in
+//!   the `sp1-host` queue group; responses go to the message's reply
+//!   subject, `replyTo`, or `--responses` (default `proofs.responses`).
+//!
+//! As in the prover-server, interactive jobs always start before background
+//! ones. With `--max-cycles-per-hour`, proofs are metered (METER_CYCLES) and
+//! background jobs wait while the last hour's cycles are over the budget;
+//! interactive ones still run.
+//!
+//! On SIGTERM/SIGINT (see `shutdown.rs`) the daemon stops taking messages,
+//! hands jobs it hasn't started back to their queue (the file moves from
+//! `processing/` back to the directory; the message is pushed back to the
+//! front of the Redis list, or republished to the NATS subject), waits for
+//! the running proofs and publishes them, then exits 0; 1 if a job couldn't
+//! be handed back. Bound the wait with `--timeout-secs`.
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// How long a source blocks waiting for a message when the daemon is idle.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Lane {
+    Interactive,
+    Background,
+}
+
+impl Lane {
+    fn parse(priority: Option<&Value>) -> Result<Self, String> {
+        match priority {
+            None | Some(Value::Null) => Ok(Lane::Interactive),
+            Some(Value::String(lane)) if lane == "interactive" => Ok(Lane::Interactive),
+            Some(Value::String(lane)) if lane == "background" => Ok(Lane::Background),
+            _ => Err("priority must be \"interactive\" or \"background\"".to_string()),
+        }
+    }
+}
+
+/// A request waiting for or being proven.
+struct Job {
+    id: String,
+    lane: Lane,
+    /// The request as the child reads it on stdin
+    request: String,
+    trace_id: Option<String>,
+    reply_to: Option<String>,
+    queued_at: Instant,
+    /// Why the message can't be proven (it's answered without proving)
+    rejection: Option<String>,
+    /// The message as received, to hand back on shutdown
+    message: Vec<u8>,
+}
+
+impl Job {
+    /// Unwrap an envelope, or take the message as a bare request.
+    fn parse(id: String, message: &[u8], reply_to: Option<String>) -> Result<Self, String> {
+        let value: Value = serde_json::from_slice(message).map_err(|e| format!("Invalid JSON: {}", e))?;
+        let (request, lane, envelope_reply) = match value.get("request") {
+            Some(request) => (
+                request.clone(),
+                Lane::parse(value.get("priority"))?,
+                value.get("replyTo").and_then(Value::as_str).map(String::from),
+            ),
+            None => (value, Lane::Interactive, None),
+        };
+        Ok(Self {
+            id,
+            lane,
+            trace_id: request.get("traceId").and_then(Value::as_str).map(String::from),
+            request: request.to_string(),
+            reply_to: reply_to.or(envelope_reply),
+            queued_at: Instant::now(),
+            rejection: None,
+            message: message.to_vec(),
+        })
+    }
+
+    fn error_payload(&self, error: &str) -> String {
+        let payload = crate::trace::ErrorResponse::new(error, self.trace_id.clone());
+        serde_json::to_string(&payload).unwrap()
+    }
+}
+
+/// A finished job's published output.
+struct Outcome {
+    job: Job,
+    ok: bool,
+    output: String,
+    cycles: u64,
+}
+
+/// Where requests come from and responses go.
+trait Source {
+    /// The next message, waiting up to `wait`.
+    fn poll(&mut self, wait: Duration) -> Result<Option<Job>, String>;
+
+    fn publish(&mut self, outcome: &Outcome) -> Result<(), String>;
+
+    /// Return a job that won't be started to the queue.
+    fn release(&mut self, job: &Job) -> Result<(), String>;
+}
+
+// =============================================================================
+// Directory
+// =============================================================================
+
+struct DirSource {
+    inbox: PathBuf,
+}
+
+impl DirSource {
+    fn new(inbox: &str) -> Result<Self, String> {
+        let inbox = PathBuf::from(inbox);
+        for sub in ["processing", "out", "failed"] {
+            std::fs::create_dir_all(inbox.join(sub)).map_err(|e| format!("Failed to create {}/{}: {}", inbox.display(), sub, e))?;
+        }
+        Ok(Self { inbox })
+    }
+
+    /// Claim the oldest request file by moving it into `processing/`.
+    fn claim(&self) -> Result<Option<(String, Vec<u8>)>, String> {
+        let entries = std::fs::read_dir(&self.inbox).map_err(|e| format!("Failed to read {}: {}", self.inbox.display(), e))?;
+        let mut files: Vec<(std::time::SystemTime, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+            .collect();
+        files.sort();
+
+        for (_, path) in files {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let claimed = self.inbox.join("processing").join(&name);
+            // Another daemon may have claimed it first
+            if std::fs::rename(&path, &claimed).is_err() {
+                continue;
+            }
+            let data = std::fs::read(&claimed).map_err(|e| format!("Failed to read {}: {}", claimed.display(), e))?;
+            return Ok(Some((name, data)));
+        }
+        Ok(None)
+    }
+}
+
+impl Source for DirSource {
+    fn poll(&mut self, wait: Duration) -> Result<Option<Job>, String> {
+        match self.claim()? {
+            Some((name, data)) => Ok(Some(
+                Job::parse(name.clone(), &data, None).unwrap_or_else(|e| rejected(name, &data, &e)),
+            )),
+            None => {
+                std::thread::sleep(wait);
+                Ok(None)
+            }
+        }
+    }
+
+    fn publish(&mut self, outcome: &Outcome) -> Result<(), String> {
+        let dir = self.inbox.join(if outcome.ok { "out" } else { "failed" });
+        write_atomic(&dir.join(&outcome.job.id), outcome.output.as_bytes())?;
+        let _ = std::fs::remove_file(self.inbox.join("processing").join(&outcome.job.id));
+        Ok(())
+    }
+
+    fn release(&mut self, job: &Job) -> Result<(), String> {
+        let claimed = self.inbox.join("processing").join(&job.id);
+        std::fs::rename(&claimed, self.inbox.join(&job.id))
+            .map_err(|e| format!("Failed to return {} to {}: {}", claimed.display(), self.inbox.display(), e))
+    }
+}
+
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, data).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// =============================================================================
+// Redis
+// =============================================================================
+
+struct RedisSource {
+    connection: redis::Connection,
+    queue: String,
+    responses: String,
+    received: u64,
+}
+
+impl RedisSource {
+    fn new(url: &str, queue: String, responses: String) -> Result<Self, String> {
+        let connection = redis::Client::open(url)
+            .and_then(|client| client.get_connection())
+            .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+        Ok(Self { connection, queue, responses, received: 0 })
+    }
+}
+
+impl Source for RedisSource {
+    fn poll(&mut self, wait: Duration) -> Result<Option<Job>, String> {
+        let popped: Option<(String, Vec<u8>)> = redis::cmd("BLPOP")
+            .arg(&self.queue)
+            .arg(wait.as_secs_f64())
+            .query(&mut self.connection)
+            .map_err(|e| format!("Redis BLPOP failed: {}", e))?;
+        Ok(popped.map(|(_, message)| {
+            self.received += 1;
+            let id = format!("redis-{}", self.received);
+            Job::parse(id.clone(), &message, None).unwrap_or_else(|e| rejected(id, &message, &e))
+        }))
+    }
+
+    fn publish(&mut self, outcome: &Outcome) -> Result<(), String> {
+        let key = outcome.job.reply_to.as_deref().unwrap_or(&self.responses);
+        redis::cmd("RPUSH")
+            .arg(key)
+            .arg(&outcome.output)
+            .query::<()>(&mut self.connection)
+            .map_err(|e| format!("Redis RPUSH failed: {}", e))
+    }
+
+    fn release(&mut self, job: &Job) -> Result<(), String> {
+        // Back to the front, where BLPOP took it from
+        redis::cmd("LPUSH")
+            .arg(&self.queue)
+            .arg(&job.message)
+            .query::<()>(&mut self.connection)
+            .map_err(|e| format!("Redis LPUSH failed: {}", e))
+    }
+}
+
+// =============================================================================
+// NATS
+// =============================================================================
+
+struct NatsSource {
+    connection: nats::Connection,
+    subscription: nats::Subscription,
+    subject: String,
+    responses: String,
+    received: u64,
+}
+
+impl NatsSource {
+    fn new(url: &str, subject: &str, responses: String) -> Result<Self, String> {
+        let connection = nats::connect(url).map_err(|e| format!("Failed to connect to NATS: {}", e))?;
+        let subscription = connection
+            .queue_subscribe(subject, "sp1-host")
+            .map_err(|e| format!("Failed to subscribe to {}: {}", subject, e))?;
+        Ok(Self { connection, subscription, subject: subject.to_string(), responses, received: 0 })
+    }
+}
+
+impl Source for NatsSource {
+    fn poll(&mut self, wait: Duration) -> Result<Option<Job>, String> {
+        match self.subscription.next_timeout(wait) {
+            Ok(message) => {
+                self.received += 1;
+                let id = format!("nats-{}", self.received);
+                let job = Job::parse(id.clone(), &message.data, message.reply.clone())
+                    .unwrap_or_else(|e| rejected(id, &message.data, &e));
+                Ok(Some(job))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(format!("NATS subscription failed: {}", e)),
+        }
+    }
+
+    fn publish(&mut self, outcome: &Outcome) -> Result<(), String> {
+        let subject = outcome.job.reply_to.as_deref().unwrap_or(&self.responses);
+        self.connection
+            .publish(subject, &outcome.output)
+            .map_err(|e| format!("NATS publish to {} failed: {}", subject, e))
+    }
+
+    fn release(&mut self, job: &Job) -> Result<(), String> {
+        // The reply subject goes with it, so the response still reaches the requester
+        let published = match &job.reply_to {
+            Some(reply) => self.connection.publish_request(&self.subject, reply, &job.message),
+            None => self.connection.publish(&self.subject, &job.message),
+        };
+        published.map_err(|e| format!("NATS publish to {} failed: {}", self.subject, e))
+    }
+}
+
+/// A job for a message that couldn't be parsed; it fails without proving.
+fn rejected(id: String, message: &[u8], error: &str) -> Job {
+    Job {
+        id,
+        lane: Lane::Interactive,
+        request: String::new(),
+        trace_id: None,
+        reply_to: None,
+        queued_at: Instant::now(),
+        rejection: Some(format!("Invalid request message: {}", error)),
+        message: message.to_vec(),
+    }
+}
+
+// =============================================================================
+// Scheduling
+// =============================================================================
+
+struct Options {
+    backend: Option<String>,
+    concurrency: usize,
+    timeout: Option<Duration>,
+    max_cycles_per_hour: Option<u64>,
+    allow_unverified_root: bool,
+}
+
+/// Prove `job` in a child host process; returns (ok, stdout, cycles).
+fn prove(job: &Job, options: &Options) -> (bool, String, u64) {
+    let exe = std::env::current_exe().expect("Failed to locate the sp1-host binary");
+    let mut command = Command::new(exe);
+    if let Some(backend) = &options.backend {
+        command.args(["--backend", backend]);
+    }
+    if options.allow_unverified_root {
+        command.arg("--allow-unverified-root");
+    }
+    if options.max_cycles_per_hour.is_some() {
+        command.env("METER_CYCLES", "1");
+    }
+    let child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::inherit()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return (false, job.error_payload(&format!("Failed to start prover: {}", e)), 0),
+    };
+
+    let mut stdin = child.stdin.take().unwrap();
+    let request = job.request.clone();
+    std::thread::spawn(move || {
+        let _ = stdin.write_all(request.as_bytes()).and_then(|_| stdin.write_all(b"\n"));
+    });
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+
+    let deadline = options.timeout.map(|t| Instant::now() + t);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if deadline.is_some_and(|d| Instant::now() >= d) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(200)),
+            Err(e) => return (false, job.error_payload(&format!("Failed to wait for prover: {}", e)), 0),
+        }
+    };
+    let output = reader.join().unwrap_or_default();
+
+    let Some(status) = status else {
+        let timeout = options.timeout.unwrap_or_default().as_secs();
+        return (false, job.error_payload(&format!("Proof timed out after {}s", timeout)), 0);
+    };
+    // The response (or error payload) is the last line of stdout
+    let last = output.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("").to_string();
+    let parsed: Option<Value> = serde_json::from_str(&last).ok();
+    let cycles = parsed.as_ref().and_then(|v| v.get("cycles")).and_then(Value::as_u64).unwrap_or(0);
+    match parsed {
+        Some(value) if status.success() && value.get("error").is_none() => (true, last, cycles),
+        Some(_) => (false, last, cycles),
+        None => (false, job.error_payload(&format!("Prover exited with {} and no response", status)), cycles),
+    }
+}
+
+/// Cycles proven over the last hour.
+struct CycleBudget {
+    limit: u64,
+    recent: VecDeque<(Instant, u64)>,
+}
+
+impl CycleBudget {
+    fn spent(&mut self) -> u64 {
+        while self.recent.front().is_some_and(|(at, _)| at.elapsed() > HOUR) {
+            self.recent.pop_front();
+        }
+        self.recent.iter().map(|(_, cycles)| cycles).sum()
+    }
+
+    fn exhausted(&mut self) -> bool {
+        self.spent() >= self.limit
+    }
+}
+
+/// Run until a shutdown signal, then drain; returns whether every unstarted
+/// job was handed back.
+fn run_loop(source: &mut dyn Source, options: Options) -> bool {
+    let mut queued: Vec<Job> = Vec::new();
+    let mut released = true;
+    let mut running = 0usize;
+    let mut budget = options.max_cycles_per_hour.map(|limit| CycleBudget { limit, recent: VecDeque::new() });
+    let options = std::sync::Arc::new(options);
+    let (done_tx, done_rx) = mpsc::channel::<Outcome>();
+
+    loop {
+        while let Ok(outcome) = done_rx.try_recv() {
+            running -= 1;
+            if let Some(budget) = budget.as_mut() {
+                budget.recent.push_back((Instant::now(), outcome.cycles));
+            }
+            log!(
+                "Job {} {} after {:?}",
+                outcome.job.id,
+                if outcome.ok { "proven" } else { "failed" },
+                outcome.job.queued_at.elapsed()
+            );
+            if let Err(e) = source.publish(&outcome) {
+                log!("Failed to publish the response to job {}: {}", outcome.job.id, e);
+            }
+        }
+
+        if crate::shutdown::requested() {
+            for job in queued.drain(..) {
+                match source.release(&job) {
+                    Ok(()) => log!("Returned job {} to the queue", job.id),
+                    Err(e) => {
+                        log!("Failed to return job {}: {}", job.id, e);
+                        released = false;
+                    }
+                }
+            }
+            if running == 0 {
+                return released;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+            continue;
+        }
+
+        // Fetch only what can start soon, so other daemons on the queue get the rest
+        let wait = if running == 0 && queued.is_empty() { POLL_INTERVAL } else { Duration::from_millis(100) };
+        if queued.len() < options.concurrency {
+            match source.poll(wait) {
+                Ok(Some(job)) => {
+                    log!("Queued job {} ({:?})", job.id, job.lane);
+                    queued.push(job);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log!("{}", e);
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+            }
+        } else {
+            std::thread::sleep(wait);
+        }
+
+        while running < options.concurrency {
+            let over_budget = budget.as_mut().is_some_and(CycleBudget::exhausted);
+            // Oldest job of the highest-priority lane that may run
+            let next = queued
+                .iter()
+                .enumerate()
+                .filter(|(_, job)| !(over_budget && job.lane == Lane::Background))
+                .min_by_key(|(_, job)| (job.lane, job.queued_at))
+                .map(|(i, _)| i);
+            let Some(next) = next else { break };
+            let job = queued.remove(next);
+
+            running += 1;
+            if let Some(error) = &job.rejection {
+                let output = job.error_payload(error);
+                let _ = done_tx.send(Outcome { job, ok: false, output, cycles: 0 });
+                continue;
+            }
+            log!("Starting job {} ({:?}, waited {:?})", job.id, job.lane, job.queued_at.elapsed());
+            let (done_tx, options) = (done_tx.clone(), options.clone());
+            std::thread::spawn(move || {
+                let (ok, output, cycles) = prove(&job, &options);
+                let _ = done_tx.send(Outcome { job, ok, output, cycles });
+            });
+        }
+    }
+}
+
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    crate::flag_value(args, flag).map(|v| v.parse().unwrap_or_else(|_| panic!("Invalid {}: {}", flag, v)))
+}
+
+/// Entry point for the `daemon` subcommand.
+pub fn run(args: &[String]) {
+    let options = Options {
+        backend: crate::flag_value(args, "--backend"),
+        concurrency: parse_flag(args, "--concurrency").unwrap_or(1).max(1),
+        timeout: parse_flag(args, "--timeout-secs").map(Duration::from_secs),
+        max_cycles_per_hour: parse_flag(args, "--max-cycles-per-hour"),
+        allow_unverified_root: args.iter().any(|a| a == "--allow-unverified-root"),
+    };
+
+    let mut source: Box<dyn Source> = if let Some(dir) = crate::flag_value(args, "--watch") {
+        log!("Watching {} for proof requests", dir);
+        Box::new(DirSource::new(&dir).unwrap_or_else(|e| panic!("{}", e)))
+    } else if let Some(url) = crate::flag_value(args, "--redis") {
+        let queue = crate::flag_value(args, "--queue").unwrap_or_else(|| "proof-requests".to_string());
+        let responses = crate::flag_value(args, "--responses").unwrap_or_else(|| "proof-responses".to_string());
+        log!("Taking proof requests from Redis list {}", queue);
+        Box::new(RedisSource::new(&url, queue, responses).unwrap_or_else(|e| panic!("{}", e)))
+    } else if let Some(url) = crate::flag_value(args, "--nats") {
+        let subject = crate::flag_value(args, "--subject").unwrap_or_else(|| "proofs.requests".to_string());
+        let responses = crate::flag_value(args, "--responses").unwrap_or_else(|| "proofs.responses".to_string());
+        log!("Taking proof requests from NATS subject {}", subject);
+        Box::new(NatsSource::new(&url, &subject, responses).unwrap_or_else(|e| panic!("{}", e)))
+    } else {
+        panic!("Usage: sp1-host daemon --watch <dir> | --redis <url> | --nats <url> [--concurrency <n>]");
+    };
+
+    log!(
+        "Proving up to {} at once{}{}",
+        options.concurrency,
+        options.timeout.map_or(String::new(), |t| format!(", {}s per proof", t.as_secs())),
+        options.max_cycles_per_hour.map_or(String::new(), |c| format!(", background work up to {} cycles/hour", c))
+    );
+    crate::shutdown::install();
+    if !run_loop(source.as_mut(), options) {
+        std::process::exit(1);
+    }
+    log!("Daemon stopped");
+}
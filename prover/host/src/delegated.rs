@@ -0,0 +1,169 @@
+//! Sealed requests for delegated proving
+//!
+//! A wallet that doesn't trust the prover-server with its notes seals the
+//! `ProofRequest` to this host's published key (see core's `delegated`) and
+//! submits `{"encryptedRequest": {...}}`. The host opens it in memory with
+//! the keyring at PROVER_KEYRING; the plaintext is never logged or written
+//! out (FAILURE_FIXTURE_DIR is skipped for sealed requests), and it isn't
+//! sent to the prover network unless SEALED_ALLOW_NETWORK=1.
+//!
+//! A request routed through an intermediary that needs its root, shape and
+//! withdrawal may instead arrive with only its witness fields sealed, as a
+//! `SealedFieldsRequest` (`{"sealedFields": ..., ...}`), sealed to the same
+//! keyring (see core's `sealed_fields`). It's treated as sealed in the same
+//! way.
+//!
+//! While serving a sealed request, log lines that would identify notes
+//! (leaf indices, blinding warnings) are redacted.
+//!
+//! # Usage
+//! sp1-host prover-keys rotate [--keyring <file>] [--grace-secs <n>]
+//! sp1-host prover-keys list [--keyring <file>]
+//!
+//! `rotate` creates the keyring if missing; previous keys keep opening
+//! requests for `--grace-secs` (ROTATION_GRACE_SECS, default 3600). `list`
+//! prints the published keys (no secrets) as JSON for the prover-server.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use ghostclaw_core::{EncryptedRequest, ProofRequest, ProverKeyring, SealedFieldsRequest};
+
+use crate::trace;
+
+static SEALED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SealedEnvelope {
+    encrypted_request: EncryptedRequest,
+}
+
+/// Whether the request being served arrived sealed.
+pub fn is_sealed() -> bool {
+    SEALED.load(Ordering::Relaxed)
+}
+
+/// Redact `value` from logs while serving a sealed request.
+pub fn redact(value: impl std::fmt::Display) -> String {
+    if is_sealed() {
+        "<redacted>".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// `trace::parse_request`, opening `{"encryptedRequest": ...}` envelopes
+/// and `SealedFieldsRequest`s with the host keyring. Timed
+/// as the parse stage. The request is recorded as received for the
+/// operator signature.
+pub fn parse_request(json: &str) -> ProofRequest {
+    crate::operator::record_request(json);
+    crate::timings::time(crate::timings::Stage::Parse, || parse_or_open(json))
+}
+
+fn parse_or_open(json: &str) -> ProofRequest {
+    if let Ok(sealed) = serde_json::from_str::<SealedFieldsRequest>(json) {
+        return open_fields(sealed);
+    }
+    let Ok(envelope) = serde_json::from_str::<SealedEnvelope>(json) else {
+        return trace::parse_request(json);
+    };
+    trace::install_error_payload_hook();
+    let sealed = envelope.encrypted_request;
+    if let Some(id) = &sealed.trace_id {
+        trace::set_trace_id(id);
+    }
+    SEALED.store(true, Ordering::Relaxed);
+
+    let keyring = load_keyring(&keyring_path(None)).unwrap_or_else(|e| panic!("Cannot open sealed request: {}", e));
+    let request = keyring.open(&sealed, now()).unwrap_or_else(|e| panic!("Cannot open sealed request: {}", e));
+    log!("Opened sealed request (key {})", sealed.key_id);
+    trace::accept(request)
+}
+
+fn open_fields(sealed: SealedFieldsRequest) -> ProofRequest {
+    trace::install_error_payload_hook();
+    if let Some(id) = &sealed.trace_id {
+        trace::set_trace_id(id);
+    }
+    SEALED.store(true, Ordering::Relaxed);
+
+    let keyring = load_keyring(&keyring_path(None)).unwrap_or_else(|e| panic!("Cannot open sealed fields: {}", e));
+    let request = sealed.open(&keyring, now()).unwrap_or_else(|e| panic!("Cannot open sealed fields: {}", e));
+    log!(
+        "Opened sealed fields (key {}, {} inputs, {} outputs)",
+        sealed.sealed_fields.key_id,
+        sealed.input_count,
+        sealed.output_count
+    );
+    trace::accept(request)
+}
+
+/// Panic unless sealed witnesses may go to the prover network.
+pub fn refuse_network_if_sealed() {
+    if is_sealed() && std::env::var("SEALED_ALLOW_NETWORK").as_deref() != Ok("1") {
+        panic!("Refusing to send a sealed request's witness to the prover network (set SEALED_ALLOW_NETWORK=1 to allow)");
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("clock before 1970").as_secs()
+}
+
+fn keyring_path(flag: Option<String>) -> Result<String, String> {
+    flag.or_else(|| std::env::var("PROVER_KEYRING").ok())
+        .ok_or_else(|| "PROVER_KEYRING is not set".to_string())
+}
+
+fn load_keyring(path: &Result<String, String>) -> Result<ProverKeyring, String> {
+    let path = path.as_ref()?;
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read keyring {}: {}", path, e))?;
+    serde_json::from_str(&json).map_err(|_| format!("Invalid keyring {}", path))
+}
+
+fn save_keyring(path: &str, keyring: &ProverKeyring) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(keyring).map_err(|e| format!("Serialize failed: {}", e))?;
+    write_secret(path, &json).map_err(|e| format!("Failed to write keyring {}: {}", path, e))
+}
+
+/// Write an owner-only file atomically.
+fn write_secret(path: &str, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let tmp = format!("{}.tmp", path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&tmp)
+        .and_then(|mut file| file.write_all(contents.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| std::fs::rename(&tmp, path))
+}
+
+pub fn run(args: &[String]) {
+    let path = keyring_path(crate::flag_value(args, "--keyring"));
+    match args.get(2).map(String::as_str) {
+        Some("rotate") => {
+            let grace_secs: u64 = crate::flag_value(args, "--grace-secs")
+                .or_else(|| std::env::var("ROTATION_GRACE_SECS").ok())
+                .map(|s| s.parse().expect("Invalid grace period"))
+                .unwrap_or(3600);
+            let file = path.clone().unwrap_or_else(|e| panic!("{} (or pass --keyring)", e));
+            let mut keyring = match load_keyring(&path) {
+                Ok(keyring) => keyring,
+                Err(_) if !std::path::Path::new(&file).exists() => ProverKeyring::default(),
+                Err(e) => panic!("{}", e),
+            };
+            let key_id = keyring.rotate(now(), grace_secs).key_id.clone();
+            save_keyring(&file, &keyring).unwrap_or_else(|e| panic!("{}", e));
+            log!("Current prover key is now {}", key_id);
+        }
+        Some("list") => {}
+        _ => panic!("Usage: prover-keys <rotate|list> [--keyring <file>]"),
+    }
+    let keyring = load_keyring(&path).unwrap_or_else(|e| panic!("{}", e));
+    println!("{}", serde_json::to_string_pretty(&keyring.published(now())).unwrap());
+}
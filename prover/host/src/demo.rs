@@ -0,0 +1,110 @@
+//! Demo mode (`sp1-host --demo`): proves core's `testing::demo_transaction`
+//! (Alice (100) -> Bob (50) + change (50)) without a frontend.
+//!
+//! The demo signs with core's public test keys, so it's only built with the
+//! `demo` feature (`cargo run --release --features demo -- --demo`); a prover
+//! serving requests is built without it.
+
+#[cfg(feature = "demo")]
+use alloy_sol_types::SolType;
+#[cfg(feature = "demo")]
+use ghostclaw_core::public_values::PublicOutputsSol;
+#[cfg(feature = "demo")]
+use ghostclaw_core::testing::demo_transaction;
+#[cfg(feature = "demo")]
+use ghostclaw_core::GuestInput;
+#[cfg(feature = "demo")]
+use sp1_sdk::{Prover, SP1ProofWithPublicValues, SP1Stdin};
+
+use crate::network::NetworkConfig;
+#[cfg(feature = "demo")]
+use crate::{checked_vkey_hash, ELF};
+
+#[cfg(not(feature = "demo"))]
+pub fn run_cpu(_client: sp1_sdk::CpuProver) {
+    unavailable()
+}
+
+#[cfg(not(feature = "demo"))]
+pub fn run_network(_network: &NetworkConfig, _client: sp1_sdk::NetworkProver) {
+    unavailable()
+}
+
+#[cfg(not(feature = "demo"))]
+fn unavailable() {
+    panic!("--demo needs a build with the `demo` feature (cargo run --release --features demo -- --demo)");
+}
+
+#[cfg(feature = "demo")]
+pub fn run_cpu(client: sp1_sdk::CpuProver) {
+    let (stdin, start, expected_output_count) = setup();
+    let (pk, vk) = client.setup(ELF);
+    let vkey_hash = checked_vkey_hash(&vk);
+    let proof = client.prove(&pk, &stdin).run().expect("Failed to generate proof");
+    finish(proof, start, expected_output_count);
+}
+
+#[cfg(feature = "demo")]
+pub fn run_network(network: &NetworkConfig, client: sp1_sdk::NetworkProver) {
+    let (stdin, start, expected_output_count) = setup();
+    let (pk, vk) = client.setup(ELF);
+    let vkey_hash = checked_vkey_hash(&vk);
+    log!("Requesting Groth16 proof from mainnet (for on-chain verification)...");
+    let proof = network.prove_groth16(&client, &pk, &stdin).unwrap_or_else(|e| panic!("{}", e));
+    finish(proof, start, expected_output_count);
+}
+
+/// Set up the demo transaction (see core `testing::demo_transaction`)
+#[cfg(feature = "demo")]
+fn setup() -> (SP1Stdin, std::time::Instant, usize) {
+    let (public_inputs, witness) = demo_transaction(0);
+
+    log!("Transaction: Alice (100) -> Bob (50) + Change (50)");
+    log!("Input note index: {}", witness.input_indices[0]);
+    log!("Old root: 0x{}", hex::encode(&public_inputs.old_root[..8]));
+    log!("  Precomputed {} nullifiers", witness.precomputed_nullifiers.len());
+    log!("  Precomputed {} input commitments", witness.precomputed_input_commitments.len());
+    log!("  Precomputed {} output commitments", witness.precomputed_output_commitments.len());
+
+    let expected_output_count = witness.output_notes.len();
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(public_inputs, witness));
+
+    log!("\nGenerating ZK proof (optimized path)...");
+    (stdin, std::time::Instant::now(), expected_output_count)
+}
+
+#[cfg(feature = "demo")]
+fn finish(proof: SP1ProofWithPublicValues, start: std::time::Instant, expected_output_count: usize) {
+    let duration = start.elapsed();
+    log!("Proof generated in {:?}!", duration);
+
+    // ABI-decode the public outputs (program commits ABI-encoded data)
+    let public_values_raw = proof.public_values.to_vec();
+    let public_outputs = PublicOutputsSol::abi_decode(&public_values_raw, true)
+        .expect("Failed to ABI-decode public outputs");
+
+    log!("\n=== Public Outputs ===");
+    log!("Old root: 0x{}", hex::encode(&public_outputs.oldRoot.as_slice()[..8]));
+    log!("Nullifiers: {}", public_outputs.nullifiers.len());
+    for (i, nullifier) in public_outputs.nullifiers.iter().enumerate() {
+        log!("  [{}]: 0x{}", i, hex::encode(&nullifier.as_slice()[..8]));
+    }
+    log!("Output commitments: {}", public_outputs.outputCommitments.len());
+    for (i, commitment) in public_outputs.outputCommitments.iter().enumerate() {
+        log!("  [{}]: 0x{}", i, hex::encode(&commitment.as_slice()[..8]));
+    }
+
+    let proof_bytes = proof.bytes();
+    log!("\nProof hex: 0x{}", hex::encode(&proof_bytes[..64.min(proof_bytes.len())]));
+    log!("Proof length: {} bytes", proof_bytes.len());
+
+    assert_eq!(
+        public_outputs.outputCommitments.len(),
+        expected_output_count,
+        "Output commitment count mismatch"
+    );
+
+    log!("\nSUCCESS! Proof verified with {} outputs.", expected_output_count);
+}
@@ -0,0 +1,195 @@
+//! `deploy` subcommand: deploy the SP1 verifier gateway and PrivateUTXOLedger
+//!
+//! # Usage
+//! sp1-host deploy [--rpc-url <url>] [--artifacts <contracts/out>] [--verifier <gateway>]
+//!                 [--token <erc20>] [--out <deployment.json>]
+//!
+//! Signs with PRIVATE_KEY. Unless `--verifier` names an existing gateway, an
+//! SP1VerifierGateway owned by the deployer is deployed and routed to a fresh
+//! Groth16 verifier. The ledger is deployed with the embedded ELF's vkey hash
+//! (no more copying it into Solidity), and its vkey and initial root are read
+//! back before the manifest is written.
+//!
+//! The manifest (`--out`, default `deployment.json`) records the chain id,
+//! addresses and vkey; point DEPLOYMENT_MANIFEST at it instead of setting
+//! LEDGER_CONTRACT for the host, prover-server and relayer.
+//!
+//! Bytecode comes from the forge artifacts (`forge build` in contracts/).
+
+use std::path::{Path, PathBuf};
+
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, Bytes, B256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use alloy_sol_types::{sol, SolCall, SolValue};
+use serde::{Deserialize, Serialize};
+use ghostclaw_core::merkle::MerkleTree;
+
+use crate::{rpc, vkey};
+
+sol! {
+    /// Route a verifier under its VERIFIER_HASH selector (gateway owner only)
+    function addRoute(address verifier) external;
+
+    function currentRoot() external view returns (bytes32);
+}
+
+/// Addresses and parameters of one ledger deployment.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentManifest {
+    pub chain_id: u64,
+    pub ledger: String,
+    /// The ISP1Verifier the ledger calls (a gateway unless `--verifier` said otherwise)
+    pub verifier_gateway: String,
+    /// Groth16 verifier routed by a gateway deployed alongside the ledger
+    pub groth16_verifier: Option<String>,
+    /// `0x0` for native ETH
+    pub token: String,
+    pub program_vkey: String,
+    pub initial_root: String,
+    pub deployer: String,
+    pub deploy_block: Option<u64>,
+}
+
+/// Read the manifest at DEPLOYMENT_MANIFEST, if set.
+pub fn manifest_from_env() -> Option<Result<DeploymentManifest, String>> {
+    let path = std::env::var("DEPLOYMENT_MANIFEST").ok()?;
+    Some(
+        std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| format!("Invalid manifest {}: {}", path, e))),
+    )
+}
+
+/// Creation bytecode from a forge artifact (`out/<File>.sol/<Contract>.json`).
+pub fn creation_code(artifacts: &Path, file: &str, contract: &str) -> Result<Vec<u8>, String> {
+    let path: PathBuf = artifacts.join(file).join(format!("{}.json", contract));
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {} (run `forge build` in contracts/): {}", path.display(), e))?;
+    let artifact: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid artifact {}: {}", path.display(), e))?;
+    let object = artifact["bytecode"]["object"]
+        .as_str()
+        .ok_or_else(|| format!("{} has no bytecode.object", path.display()))?;
+    hex::decode(object.trim_start_matches("0x")).map_err(|e| format!("Invalid bytecode in {}: {}", path.display(), e))
+}
+
+struct Deployed {
+    address: Address,
+    block: Option<u64>,
+}
+
+async fn send<P: Provider>(provider: &P, tx: TransactionRequest, what: &str) -> Result<Deployed, String> {
+    let receipt = provider
+        .send_transaction(tx)
+        .await
+        .map_err(|e| format!("Failed to send {}: {}", what, e))?
+        .get_receipt()
+        .await
+        .map_err(|e| format!("No receipt for {}: {}", what, e))?;
+    if !receipt.status() {
+        return Err(format!("{} reverted (tx {})", what, receipt.transaction_hash));
+    }
+    log!("{} mined in tx {}", what, receipt.transaction_hash);
+    Ok(Deployed {
+        address: receipt.contract_address.unwrap_or_default(),
+        block: receipt.block_number,
+    })
+}
+
+async fn deploy<P: Provider>(provider: &P, code: Vec<u8>, what: &str) -> Result<Deployed, String> {
+    let deployed = send(provider, TransactionRequest::default().with_deploy_code(Bytes::from(code)), what).await?;
+    if deployed.address == Address::ZERO {
+        return Err(format!("{} receipt has no contract address", what));
+    }
+    log!("{} deployed at {}", what, deployed.address);
+    Ok(deployed)
+}
+
+fn parse_address(value: &str, flag: &str) -> Address {
+    value.parse().unwrap_or_else(|e| panic!("Invalid {} {}: {}", flag, value, e))
+}
+
+/// Entry point for the `deploy` subcommand.
+pub fn run(args: &[String]) {
+    let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+    let artifacts = PathBuf::from(crate::flag_value(args, "--artifacts").unwrap_or_else(|| "contracts/out".to_string()));
+    let out = crate::flag_value(args, "--out").unwrap_or_else(|| "deployment.json".to_string());
+    let existing_verifier = crate::flag_value(args, "--verifier").map(|v| parse_address(&v, "--verifier"));
+    let token = crate::flag_value(args, "--token").map_or(Address::ZERO, |t| parse_address(&t, "--token"));
+
+    let signer: PrivateKeySigner = std::env::var("PRIVATE_KEY")
+        .expect("PRIVATE_KEY is required to deploy")
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid PRIVATE_KEY: {}", e));
+    let deployer = signer.address();
+
+    let program_vkey = vkey::local_vkey_hash();
+    let initial_root = MerkleTree::new().root();
+    log!("Deploying from {} with program vkey 0x{}", deployer, hex::encode(program_vkey));
+
+    let ledger_code = creation_code(&artifacts, "PrivateUTXOLedger.sol", "PrivateUTXOLedger").unwrap_or_else(|e| panic!("{}", e));
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start tokio runtime");
+    let manifest = runtime
+        .block_on(async {
+            let provider = ProviderBuilder::new()
+                .with_recommended_fillers()
+                .wallet(EthereumWallet::from(signer))
+                .on_http(rpc_url.parse().map_err(|e| format!("Invalid RPC URL {}: {}", rpc_url, e))?);
+            let chain_id = provider.get_chain_id().await.map_err(|e| format!("eth_chainId failed: {}", e))?;
+
+            let (gateway, groth16_verifier) = match existing_verifier {
+                Some(verifier) => {
+                    log!("Using existing verifier {}", verifier);
+                    (verifier, None)
+                }
+                None => {
+                    let gateway_code = creation_code(&artifacts, "SP1VerifierGateway.sol", "SP1VerifierGateway")?;
+                    let verifier_code = creation_code(&artifacts, "SP1VerifierGroth16.sol", "SP1Verifier")?;
+                    let gateway = deploy(&provider, [gateway_code, deployer.abi_encode()].concat(), "SP1VerifierGateway").await?;
+                    let verifier = deploy(&provider, verifier_code, "SP1Verifier (Groth16)").await?;
+                    let route = TransactionRequest::default()
+                        .with_to(gateway.address)
+                        .with_input(addRouteCall { verifier: verifier.address }.abi_encode());
+                    send(&provider, route, "addRoute").await?;
+                    (gateway.address, Some(verifier.address))
+                }
+            };
+
+            let constructor_args = (Address::ZERO, gateway, token, B256::from(program_vkey)).abi_encode_params();
+            let ledger = deploy(&provider, [ledger_code, constructor_args].concat(), "PrivateUTXOLedger").await?;
+
+            Ok::<_, String>(DeploymentManifest {
+                chain_id,
+                ledger: ledger.address.to_string(),
+                verifier_gateway: gateway.to_string(),
+                groth16_verifier: groth16_verifier.map(|v| v.to_string()),
+                token: token.to_string(),
+                program_vkey: format!("0x{}", hex::encode(program_vkey)),
+                initial_root: format!("0x{}", hex::encode(initial_root)),
+                deployer: deployer.to_string(),
+                deploy_block: ledger.block,
+            })
+        })
+        .unwrap_or_else(|e| panic!("Deployment failed: {}", e));
+
+    // Read back what the ledger was actually deployed with
+    let onchain_vkey = vkey::onchain_vkey_hash(&rpc_url, &manifest.ledger).expect("Failed to query on-chain vkey");
+    if onchain_vkey != program_vkey {
+        panic!("Deployed ledger reports vkey 0x{}, expected {}", hex::encode(onchain_vkey), manifest.program_vkey);
+    }
+    let ret = rpc::eth_call(&rpc_url, &manifest.ledger, &currentRootCall {}.abi_encode()).expect("Failed to query currentRoot");
+    let onchain_root = currentRootCall::abi_decode_returns(&ret, true).expect("Failed to decode currentRoot")._0.0;
+    if onchain_root != initial_root {
+        panic!("Deployed ledger reports root 0x{}, expected {}", hex::encode(onchain_root), manifest.initial_root);
+    }
+
+    let json = serde_json::to_string_pretty(&manifest).unwrap();
+    std::fs::write(&out, &json).unwrap_or_else(|e| panic!("Failed to write {}: {}", out, e));
+    log!("Deployment manifest written to {}", out);
+    println!("{}", json);
+}
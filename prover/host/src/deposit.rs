@@ -0,0 +1,99 @@
+//! `register-deposit` subcommand: add a shielded deposit to the wallet state
+//!
+//! # Usage
+//! sp1-host register-deposit --tx <hash> --amount <n> --owner <pubkey>
+//!     --blinding <hex> [--wallet <state.json>] [--contract <address>] [--rpc-url <url>]
+//!
+//! The depositor chose the note's opening (amount, owner, blinding) when
+//! building the deposit; the receipt's `Deposited` event says which leaf the
+//! commitment landed in. The resulting `OwnedNote` is printed and, with
+//! `--wallet`, added to that `WalletState` file (created if missing).
+//! `--owner` takes the 32-byte owner or a 33/65-byte public key.
+//!
+//! Logs are taken from the ledger only (`--contract`, LEDGER_CONTRACT or
+//! DEPLOYMENT_MANIFEST) when one is configured.
+
+use alloy::primitives::{Address, B256};
+use alloy::providers::{Provider, ProviderBuilder};
+use ghostclaw_core::events::owned_note_from_deposit;
+use ghostclaw_core::owner::parse_owner;
+use ghostclaw_core::{Bytes32, Note, OwnedNote, WalletState};
+
+use crate::rpc;
+
+/// Fetch the receipt of `tx_hash` and locate `note` among its deposits.
+pub fn owned_note_from_receipt(
+    rpc_url: &str,
+    tx_hash: B256,
+    contract: Option<Address>,
+    note: Note,
+) -> Result<OwnedNote, String> {
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| format!("Failed to start tokio runtime: {}", e))?;
+    let receipt = runtime.block_on(async {
+        let provider = ProviderBuilder::new()
+            .on_http(rpc_url.parse().map_err(|e| format!("Invalid RPC URL {}: {}", rpc_url, e))?);
+        provider
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|e| format!("eth_getTransactionReceipt failed: {}", e))?
+            .ok_or_else(|| format!("No receipt for {} (not mined yet?)", tx_hash))
+    })?;
+    if !receipt.status() {
+        return Err(format!("Deposit transaction {} reverted", tx_hash));
+    }
+
+    let logs: Vec<(Vec<[u8; 32]>, Vec<u8>)> = receipt
+        .inner
+        .logs()
+        .iter()
+        .filter(|log| contract.map_or(true, |c| log.address() == c))
+        .map(|log| (log.topics().iter().map(|t| t.0).collect(), log.data().data.to_vec()))
+        .collect();
+    owned_note_from_deposit(note, logs.iter().map(|(topics, data)| (topics.as_slice(), data.as_slice())))
+}
+
+pub fn run(args: &[String]) {
+    let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+    let tx_hash: B256 = crate::flag_value(args, "--tx")
+        .expect("Pass --tx <deposit transaction hash>")
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid --tx: {}", e));
+    let contract: Option<Address> = crate::flag_value(args, "--contract")
+        .or_else(rpc::ledger_contract_from_env)
+        .map(|c| c.parse().unwrap_or_else(|e| panic!("Invalid ledger address {}: {}", c, e)));
+
+    let amount: u64 = crate::flag_value(args, "--amount")
+        .expect("Pass --amount <deposited amount>")
+        .parse()
+        .expect("Invalid --amount");
+    let bytes32_flag = |flag: &str| -> Bytes32 {
+        crate::flag_value(args, flag)
+            .unwrap_or_else(|| panic!("Pass {} <32-byte hex>", flag))
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid {}: {}", flag, e))
+    };
+    let owner = crate::flag_value(args, "--owner")
+        .map(|o| parse_owner(&o).unwrap_or_else(|e| panic!("Invalid --owner: {}", e)))
+        .expect("Pass --owner <owner or public key>");
+    let note = Note::new(amount, owner, bytes32_flag("--blinding").0);
+
+    let owned = owned_note_from_receipt(&rpc_url, tx_hash, contract, note).unwrap_or_else(|e| panic!("{}", e));
+    log!("Deposit {} is leaf {}", tx_hash, owned.leaf_index);
+
+    if let Some(path) = crate::flag_value(args, "--wallet") {
+        let mut state: WalletState = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| panic!("Invalid wallet state {}: {}", path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WalletState::new(),
+            Err(e) => panic!("Failed to read {}: {}", path, e),
+        };
+        if state.add_note(owned.note.clone(), owned.leaf_index) {
+            std::fs::write(&path, serde_json::to_string_pretty(&state).unwrap())
+                .unwrap_or_else(|e| panic!("Failed to write {}: {}", path, e));
+            log!("Added to {}", path);
+        } else {
+            log!("Already in {}", path);
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&owned).unwrap());
+}
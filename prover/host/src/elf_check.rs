@@ -0,0 +1,114 @@
+//! Embedded guest ELF freshness check and the `elf-record` and `verify-elf`
+//! subcommands
+//!
+//! # Usage
+//! sp1-host elf-record [--write <core/guest-elf.lock>] [--toolchain <digest>]
+//! sp1-host verify-elf [--program <prover/program>]
+//!
+//! On startup the embedded ELF is checked against the `guest-elf.lock`
+//! committed with core (see core `guest_elf`): a mismatch means either core
+//! was edited without rebuilding the guest, or the guest was rebuilt without
+//! regenerating the record. Either way the embedded ELF may not prove the
+//! rules the host pre-checks, so by default (`ELF_CHECK=strict`) the host
+//! refuses to run; `warn` logs it instead (local development against a
+//! stale guest), `off` skips the check.
+//!
+//! `elf-record` prints the record for the embedded ELF and the current core
+//! sources, or writes it with `--write`. Run it after
+//! `build-reproducible.sh` (the host must be rebuilt first so it embeds the
+//! new ELF), passing the toolchain digest the script printed.
+//!
+//! `verify-elf` is for third parties: it rebuilds the guest from the
+//! sources in SP1's docker image, checks the image is the one the record
+//! pins, and compares the result with the record and the embedded ELF.
+//! Nothing runs unless all three match.
+
+use std::path::Path;
+use std::process::Command;
+
+use ghostclaw_core::guest_elf::{self, ElfRecord, GUEST_TOOLCHAIN_IMAGE};
+use ghostclaw_core::Bytes32;
+
+use crate::ELF;
+
+/// Check the embedded ELF as configured by ELF_CHECK.
+pub fn check() {
+    let mode = std::env::var("ELF_CHECK").unwrap_or_default();
+    match mode.as_str() {
+        "off" => {}
+        "" | "warn" | "strict" => {
+            if let Err(e) = guest_elf::check_elf(ELF) {
+                if mode != "warn" {
+                    panic!("{} (set ELF_CHECK=warn to run anyway)", e);
+                }
+                log!("WARNING: {}", e);
+            }
+        }
+        other => panic!("Unknown ELF_CHECK {:?} (strict, warn, off)", other),
+    }
+}
+
+/// Entry point for the `elf-record` subcommand.
+pub fn run(args: &[String]) {
+    let toolchain = crate::flag_value(args, "--toolchain")
+        .map(|digest| digest.parse::<Bytes32>().unwrap_or_else(|e| panic!("Invalid --toolchain {}: {}", digest, e)));
+    let record = guest_elf::record_for(ELF, toolchain.as_ref()).unwrap_or_else(|e| panic!("{}", e));
+    match crate::flag_value(args, "--write") {
+        Some(path) => {
+            std::fs::write(&path, record).unwrap_or_else(|e| panic!("Failed to write {}: {}", path, e));
+            log!("Wrote {}", path);
+        }
+        None => print!("{}", record),
+    }
+}
+
+/// Digest of the local copy of `GUEST_TOOLCHAIN_IMAGE`, pulling it first.
+fn toolchain_digest() -> Result<Bytes32, String> {
+    let pulled = Command::new("docker").args(["pull", GUEST_TOOLCHAIN_IMAGE]).output();
+    match pulled {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => return Err(format!("docker pull failed: {}", String::from_utf8_lossy(&output.stderr).trim())),
+        Err(e) => return Err(format!("Failed to run docker: {}", e)),
+    }
+    let output = Command::new("docker")
+        .args(["image", "inspect", "--format", "{{index .RepoDigests 0}}", GUEST_TOOLCHAIN_IMAGE])
+        .output()
+        .map_err(|e| format!("Failed to run docker: {}", e))?;
+    let reference = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let digest = reference
+        .split_once("@sha256:")
+        .map(|(_, digest)| digest)
+        .ok_or_else(|| format!("No digest for {} (got {:?})", GUEST_TOOLCHAIN_IMAGE, reference))?;
+    digest.parse().map_err(|e| format!("Invalid digest for {}: {}", GUEST_TOOLCHAIN_IMAGE, e))
+}
+
+/// Rebuild the guest in `program` with the reproducible build script.
+fn rebuild(program: &Path) -> Result<Vec<u8>, String> {
+    let out = std::env::temp_dir().join(format!("ghostclaw-verify-elf-{}", std::process::id()));
+    let status = Command::new(program.join("build-reproducible.sh"))
+        .arg(&out)
+        .status()
+        .map_err(|e| format!("Failed to run build-reproducible.sh: {}", e))?;
+    if !status.success() {
+        return Err(format!("Reproducible guest build failed ({})", status));
+    }
+    let elf = std::fs::read(out.join("sp1-program")).map_err(|e| format!("Failed to read rebuilt ELF: {}", e));
+    let _ = std::fs::remove_dir_all(&out);
+    elf
+}
+
+/// Entry point for the `verify-elf` subcommand.
+pub fn verify(args: &[String]) {
+    let program = crate::flag_value(args, "--program").unwrap_or_else(|| "../program".to_string());
+    let record = ElfRecord::committed().unwrap_or_else(|e| panic!("{}", e));
+
+    let digest = toolchain_digest().unwrap_or_else(|e| panic!("{}", e));
+    record.check_toolchain(&digest).unwrap_or_else(|e| panic!("{}", e));
+    log!("Toolchain {} matches the pinned digest {}", GUEST_TOOLCHAIN_IMAGE, digest);
+
+    log!("Rebuilding the guest in {}...", program);
+    let rebuilt = rebuild(Path::new(&program)).unwrap_or_else(|e| panic!("{}", e));
+    record.check_rebuild(&rebuilt).unwrap_or_else(|e| panic!("{}", e));
+    guest_elf::check_elf(ELF).unwrap_or_else(|e| panic!("{}", e));
+    println!("Verified: guest ELF {} is reproducible from these sources", record.elf_sha256);
+}
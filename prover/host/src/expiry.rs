@@ -0,0 +1,133 @@
+//! Proof expiry estimates (`expiresAt` in `ProofResponse`)
+//!
+//! A proof stays submittable while its `oldRoot` is in the ledger's root
+//! history. Ledgers with a bounded history expose its depth as
+//! `ROOT_HISTORY_SIZE()` (or set ROOT_HISTORY_SIZE for one that doesn't);
+//! every commitment inserted pushes one root, so the proof expires after
+//! `depth - insertions since oldRoot` more insertions. That count is turned
+//! into a block and a time from the chain head, using the insertion rate and
+//! block time over the last EXPIRY_RATE_WINDOW blocks (default 1000).
+//!
+//! `oldRoot`'s `RootUpdated` event is searched from the deployment's deploy
+//! block (see `chains.rs`), or the last EXPIRY_LOOKBACK_BLOCKS (default 100000).
+//!
+//! Ledgers that keep every root (as PrivateUTXOLedger does today) never
+//! expire a proof, and `expiresAt` is omitted. It is also omitted when the
+//! ledger can't be read or nothing was inserted in the rate window.
+//!
+//! It is only an estimate for wallets to plan by: the relayer checks
+//! `validRoots(oldRoot)` on the ledger before submitting.
+
+use alloy::primitives::U256;
+use alloy_sol_types::{sol, SolCall, SolEvent};
+use ghostclaw_core::merkle::MerkleTree;
+
+use crate::ledger_status::optional_call;
+use crate::rpc;
+
+pub use ghostclaw_prover_client::response::ProofExpiry;
+
+sol! {
+    function ROOT_HISTORY_SIZE() external view returns (uint256);
+    function nextLeafIndex() external view returns (uint256);
+
+    event RootUpdated(bytes32 indexed oldRoot, bytes32 indexed newRoot);
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.parse().unwrap_or_else(|e| panic!("Invalid {}: {}", name, e)))
+}
+
+fn u64_of(value: U256, what: &str) -> Result<u64, String> {
+    u64::try_from(value).map_err(|_| format!("{} {} overflows u64", what, value))
+}
+
+fn next_leaf_index(rpc_url: &str, contract: &str, block: u64) -> Result<u64, String> {
+    let ret = rpc::eth_call_at(rpc_url, contract, &nextLeafIndexCall {}.abi_encode(), Some(block))?;
+    let count = nextLeafIndexCall::abi_decode_returns(&ret, true)
+        .map_err(|e| format!("Failed to decode nextLeafIndex: {}", e))?
+        ._0;
+    u64_of(count, "nextLeafIndex")
+}
+
+/// Number of leaves in the tree when `root` was its root, or `None` if no
+/// `RootUpdated` to `root` was emitted in `[from_block, head]`.
+fn leaves_at_root(rpc_url: &str, contract: &str, root: [u8; 32], from_block: u64, head: u64) -> Result<Option<u64>, String> {
+    if root == MerkleTree::new().root() {
+        return Ok(Some(0));
+    }
+    let topic = RootUpdated::SIGNATURE_HASH.0;
+    let Some(created) = rpc::get_logs_by_topics(rpc_url, contract, from_block, head, &[Some(topic), None, Some(root)])?.pop()
+    else {
+        return Ok(None);
+    };
+
+    // nextLeafIndex is only readable at block granularity: subtract the
+    // insertions later in the same block
+    let block = created.block_number;
+    let in_block = rpc::get_logs_by_topics(rpc_url, contract, block, block, &[Some(topic)])?;
+    let position = in_block
+        .iter()
+        .rposition(|log| log.topics.get(2) == Some(&root))
+        .ok_or("RootUpdated log missing from its own block")?;
+    let later = (in_block.len() - position - 1) as u64;
+    Ok(Some(next_leaf_index(rpc_url, contract, block)? - later))
+}
+
+/// Estimate when a proof against `old_root` stops being submittable on
+/// `contract` (deployed at `deploy_block`, if known); `None` when the ledger
+/// keeps every root or there's no basis for an estimate.
+pub fn estimate(rpc_url: &str, contract: &str, deploy_block: Option<u64>, old_root: [u8; 32]) -> Result<Option<ProofExpiry>, String> {
+    let history = match env_u64("ROOT_HISTORY_SIZE") {
+        Some(size) => size,
+        None => match optional_call(rpc_url, contract, &ROOT_HISTORY_SIZECall {})? {
+            Some(size) => u64_of(size._0, "ROOT_HISTORY_SIZE")?,
+            None => return Ok(None),
+        },
+    };
+
+    let head = rpc::block_number(rpc_url)?;
+    let from_block = deploy_block.unwrap_or_else(|| head.saturating_sub(env_u64("EXPIRY_LOOKBACK_BLOCKS").unwrap_or(100_000)));
+    let Some(leaves_then) = leaves_at_root(rpc_url, contract, old_root, from_block, head)? else {
+        log!("Old root 0x{} not found since block {}; no expiry estimate", hex::encode(old_root), from_block);
+        return Ok(None);
+    };
+    let leaves_now = next_leaf_index(rpc_url, contract, head)?;
+    let remaining_roots = history.saturating_sub(leaves_now.saturating_sub(leaves_then));
+    let head_timestamp = rpc::block_timestamp(rpc_url, head)?;
+    if remaining_roots == 0 {
+        return Ok(Some(ProofExpiry { remaining_roots, block: head, timestamp: head_timestamp }));
+    }
+
+    let window_start = head.saturating_sub(env_u64("EXPIRY_RATE_WINDOW").unwrap_or(1000));
+    let inserted = leaves_now.saturating_sub(next_leaf_index(rpc_url, contract, window_start)?);
+    if inserted == 0 || window_start == head {
+        return Ok(None);
+    }
+    let window_blocks = head - window_start;
+    let window_seconds = head_timestamp.saturating_sub(rpc::block_timestamp(rpc_url, window_start)?);
+
+    // The evicting insertion is the `remaining_roots`-th from now
+    let blocks = (remaining_roots as u128 * window_blocks as u128).div_ceil(inserted as u128) as u64;
+    let seconds = (blocks as u128 * window_seconds as u128 / window_blocks as u128) as u64;
+    Ok(Some(ProofExpiry {
+        remaining_roots,
+        block: head.saturating_add(blocks),
+        timestamp: head_timestamp.saturating_add(seconds),
+    }))
+}
+
+/// `estimate` against the ledger serving `chain_id`; failures are logged and
+/// yield `None`.
+pub fn estimate_for_chain(chain_id: u64, old_root: [u8; 32]) -> Option<ProofExpiry> {
+    let deployment = crate::chains::deployment_for(chain_id).ok()??;
+    match estimate(&deployment.rpc_url(), &deployment.ledger, deployment.deploy_block, old_root) {
+        Ok(expiry) => expiry,
+        Err(e) => {
+            log!("Could not estimate proof expiry: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Foundry fixtures from real prover output
+//!
+//! With `--emit-foundry-fixture <dir>`, every proof the host returns is also
+//! written to `<dir>/<name>.json`: the proof and public values exactly as
+//! they'd be submitted, the vkey they verify under, and the state transition
+//! the ledger should make (nullifiers marked used, output commitments
+//! appended, a withdrawal paid). `contracts/test/PrivateUTXOLedger_ProverFixtures.t.sol`
+//! replays every fixture in `contracts/test/fixtures/prover/` against the
+//! SP1 Groth16 verifier, so contract changes can be regression-tested
+//! against real proofs without a Rust toolchain.
+//!
+//! The name is the request's trace ID, or the first nullifier. Mock proofs
+//! are written too, flagged `mock`; the forge test replays them against the
+//! mock verifier. Fixtures hold only what goes on-chain anyway.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use ghostclaw_core::hex::{Bytes20, Bytes32};
+use ghostclaw_core::PublicOutputs;
+
+static FIXTURE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// One proof and the ledger transition it should produce. Flat, with every
+/// field present, so forge can read it key by key.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoundryFixture {
+    pub name: String,
+    pub vkey_hash: String,
+    /// Proof bytes (0x-hex), as passed to the ledger
+    pub proof: String,
+    /// Raw public values (0x-hex), as passed to the ledger
+    pub public_values: String,
+    /// Replay against the mock verifier (the proof is a placeholder)
+    pub mock: bool,
+    /// Submit with `submitTxCompressed`, passing the lists below
+    pub compressed: bool,
+    pub chain_id: u64,
+    pub old_root: Bytes32,
+    /// Must be unused before and used after
+    pub nullifiers: Vec<Bytes32>,
+    /// Appended to the tree in this order
+    pub output_commitments: Vec<Bytes32>,
+    /// 0 for transfers
+    pub withdrawal_amount: u64,
+    pub withdrawal_recipient: Bytes20,
+}
+
+/// Read `--emit-foundry-fixture <dir>`, creating the directory.
+pub fn configure(args: &[String]) {
+    let Some(dir) = crate::flag_value(args, "--emit-foundry-fixture") else {
+        return;
+    };
+    std::fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("Failed to create fixture directory {}: {}", dir, e));
+    let _ = FIXTURE_DIR.set(PathBuf::from(dir));
+}
+
+/// Write the fixture for a proof, if a fixture directory was given.
+///
+/// Failures are logged; the proof is still returned.
+pub fn emit(
+    outputs: &PublicOutputs,
+    proof: &str,
+    public_values: &str,
+    vkey_hash: &str,
+    compressed: bool,
+    mock: bool,
+) {
+    let Some(dir) = FIXTURE_DIR.get() else {
+        return;
+    };
+    let name = crate::trace::trace_id().unwrap_or_else(|| match outputs.nullifiers.first() {
+        Some(nullifier) => format!("tx-{}", hex::encode(&nullifier[..8])),
+        None => format!("root-{}", hex::encode(&outputs.old_root[..8])),
+    });
+    let fixture = FoundryFixture {
+        name: name.clone(),
+        vkey_hash: vkey_hash.to_string(),
+        proof: proof.to_string(),
+        public_values: public_values.to_string(),
+        mock,
+        compressed,
+        chain_id: outputs.chain_id,
+        old_root: Bytes32(outputs.old_root),
+        nullifiers: outputs.nullifiers.iter().copied().map(Bytes32).collect(),
+        output_commitments: outputs.output_commitments.iter().copied().map(Bytes32).collect(),
+        withdrawal_amount: outputs.withdrawal.map_or(0, |w| w.public_amount),
+        withdrawal_recipient: outputs.withdrawal.map_or_else(Bytes20::default, |w| w.recipient),
+    };
+    match write(dir, &name, &fixture) {
+        Ok(path) => log!("Wrote Foundry fixture {}", path.display()),
+        Err(e) => log!("WARNING: failed to write Foundry fixture: {}", e),
+    }
+}
+
+fn write(dir: &Path, name: &str, fixture: &FoundryFixture) -> Result<PathBuf, String> {
+    // Trace IDs come from the caller; keep the file inside `dir`
+    let file_name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let path = dir.join(format!("{}.json", file_name));
+    let json = serde_json::to_string_pretty(fixture).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
@@ -0,0 +1,526 @@
+//! `indexer` subcommand: serve the ledger's state over HTTP
+//!
+//! # Usage
+//! sp1-host indexer [--contract <address>] [--rpc-url <url>] [--chain-id <n>]
+//!     [--from-block <n>] [--listen <addr>] [--poll-secs <n>] [--confirmations <n>]
+//!     [--log-chunk <blocks>] [--checkpoint <file>] [--proof-cache <n>]
+//!     [--max-webhooks <n>]
+//!
+//! Follows the ledger's `OutputCommitted` and `NullifierUsed` events (from
+//! `--from-block`, default the deployment block or 0, up to `--confirmations`
+//! blocks behind the head, default 2) and answers, as JSON (see core's
+//! `indexer`):
+//!
+//! - `GET /leaves?cursor=&limit=`, `GET /leaves/{index}`: leaves by index
+//! - `GET /commitments/{commitment}`: the leaf holding a commitment
+//! - `GET /commitments/{commitment}/proof`: its membership proof against the
+//!   current root. The last `--proof-cache` proofs (default 1024, 0 to turn
+//!   off) are kept until the root advances, so retries of the same spend
+//!   don't rebuild the path
+//! - `GET /nullifiers/{nullifier}`: whether it's spent
+//! - `GET /roots?cursor=&limit=`, `GET /roots/{root}`: the root history
+//! - `GET /sync`: synced block, leaf count and root (the CLI's
+//!   INDEXER_SYNC_URL)
+//! - `GET /state`: everything, as `reconcile` reads it (INDEXER_STATE_URL)
+//!
+//! Lists are paginated (`limit` default 100, at most 1000; a page's `next`
+//! is the following cursor). Unknown entries are 404s. The prover-server
+//! checks request inputs here when INDEXER_URL is set.
+//!
+//! With OPERATOR_KEY set (see `operator.rs`) and WEBHOOK_TOKEN set to a
+//! secret the operator hands to the wallets it serves, those wallets can
+//! also subscribe to push notifications instead of polling (see core's
+//! `webhooks`). Each call carries `Authorization: Bearer <WEBHOOK_TOKEN>`:
+//!
+//! - `POST /webhooks` with `{"url", "viewKey", "nullifiers"}`: returns
+//!   `{"id"}`; outputs that decrypt with the view key, and spends of the
+//!   listed nullifiers, are POSTed to `url`, signed by the operator key.
+//!   At most `--max-webhooks` (default 1000) are registered at once (429
+//!   beyond), and the URL must name a public host
+//! - `POST /webhooks/{id}/nullifiers` with a list: watch more nullifiers
+//! - `DELETE /webhooks/{id}`: unsubscribe
+//!
+//! Outputs are trial-decrypted after their events are applied, without
+//! holding the state, so API reads don't wait on it. Notifications go
+//! through a bounded queue (`WEBHOOK_QUEUE_CAPACITY`) to a single sender;
+//! when it's full, new ones are dropped and logged. The sender resolves
+//! each host, refuses non-public addresses, connects to the address it
+//! checked and doesn't follow redirects.
+//!
+//! Registrations live in memory only (wallets re-register after a restart)
+//! and each notification is sent at most once; a wallet should still poll
+//! now and then to catch anything its endpoint missed.
+//!
+//! Each transaction's events are checked before they're applied (core's
+//! `IndexerState::apply_transaction`, built on `verify_transition`): a
+//! spent nullifier, a `RootUpdated` the indexer's tree doesn't reproduce, or
+//! a missed or repeated leaf stops the indexer, and `reconcile` shows where
+//! it diverged. It doesn't follow reorgs deeper than `--confirmations`.
+//!
+//! With `--checkpoint`, the state is written to the file (a core `indexer`
+//! `Checkpoint`: the snapshot and its last applied block) on SIGTERM/SIGINT,
+//! and a later run resumes from it instead of `--from-block`. A checkpoint of
+//! another deployment, or whose leaves don't hash to its root, is refused.
+//! The file isn't written after a divergence, so the restart re-syncs from
+//! the last good checkpoint.
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use axum::extract::{FromRef, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use ghostclaw_core::chains::ChainDeployment;
+use ghostclaw_core::encryption::{EncryptedNote, KeyType};
+use ghostclaw_core::events::{decode_ledger_log, LedgerEvent, OutputCommitted};
+use ghostclaw_core::indexer::{
+    Checkpoint, CommitmentProof, IndexerState, LeafEntry, NullifierStatus, ObservedTransaction, Page, ProofCache,
+    RootEntry, StateSnapshot, SyncStatus, DEFAULT_PAGE_LIMIT, DEFAULT_PROOF_CACHE_CAPACITY,
+};
+use ghostclaw_core::webhooks::{
+    self, Delivery, NotificationSignature, WebhookRegistration, WebhookRegistry, DEFAULT_MAX_WEBHOOKS,
+    OPERATOR_HEADER, SIGNATURE_HEADER,
+};
+use ghostclaw_core::Bytes32;
+
+use crate::rpc::{self, RawLog};
+
+type Shared = Arc<RwLock<IndexerState>>;
+type Proofs = Arc<Mutex<ProofCache>>;
+type Webhooks = Arc<RwLock<WebhookRegistry>>;
+
+/// How long a webhook has to answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Notifications waiting for the sender; more are dropped.
+const WEBHOOK_QUEUE_CAPACITY: usize = 1024;
+
+#[derive(Clone)]
+struct Api {
+    state: Shared,
+    proofs: Proofs,
+    webhooks: Webhooks,
+    /// SHA-256 of WEBHOOK_TOKEN, which webhook calls must present
+    webhook_token: Option<[u8; 32]>,
+}
+
+impl FromRef<Api> for Shared {
+    fn from_ref(api: &Api) -> Self {
+        api.state.clone()
+    }
+}
+type ApiResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+struct Follow {
+    rpc_url: String,
+    contract: String,
+    confirmations: u64,
+    chunk: u64,
+    poll: Duration,
+    /// Queue of the webhook sender; no notifications without it
+    webhook_queue: Option<SyncSender<Delivery>>,
+}
+
+/// The event's ciphertext, if it's in a format `decrypt_note` reads.
+fn encrypted_note(event: &OutputCommitted) -> Option<EncryptedNote> {
+    let key_type = match event.keyType {
+        0 => KeyType::Secp256k1,
+        1 => KeyType::Secp256r1,
+        _ => return None,
+    };
+    Some(EncryptedNote {
+        key_type,
+        ephemeral_pubkey: event.ephemeralPubkey.as_ref().try_into().ok()?,
+        nonce: event.nonce.0,
+        ciphertext: event.ciphertext.to_vec(),
+    })
+}
+
+/// Apply the ledger's events up to block `to`, dropping cached proofs if
+/// the root moved; the webhook notifications they trigger.
+///
+/// The state is only locked while the events are applied: trial decryption
+/// of the outputs runs afterwards, on a copy of the registered view keys.
+fn apply_logs(
+    state: &Shared,
+    proofs: &Proofs,
+    webhooks: &Webhooks,
+    logs: Vec<RawLog>,
+    to: u64,
+) -> Result<Vec<Delivery>, String> {
+    let watching = !webhooks.read().unwrap().is_empty();
+    let mut outputs = Vec::new();
+    let mut spent = Vec::new();
+    {
+        let mut state = state.write().unwrap();
+        let root = state.chain().root();
+        for tx_logs in logs.chunk_by(|a, b| a.transaction_hash == b.transaction_hash) {
+            let mut tx = ObservedTransaction::default();
+            for log in tx_logs {
+                match decode_ledger_log(&log.topics, &log.data)? {
+                    Some(LedgerEvent::OutputCommitted(e)) => {
+                        let index = u64::try_from(e.leafIndex).map_err(|_| "leafIndex overflows u64".to_string())?;
+                        tx.commitments.push((index, e.commitment.0));
+                        if let Some(encrypted) = encrypted_note(&e).filter(|_| watching) {
+                            outputs.push((log.block_number, index, e.commitment.0, encrypted));
+                        }
+                    }
+                    Some(LedgerEvent::NullifierUsed(e)) => {
+                        tx.nullifiers.push(e.nullifier.0);
+                        spent.push((log.block_number, e.nullifier.0));
+                    }
+                    Some(LedgerEvent::RootUpdated(e)) => tx.new_root = Some(e.newRoot.0),
+                    _ => {}
+                }
+            }
+            state
+                .apply_transaction(&tx)
+                .map_err(|e| format!("Transaction 0x{}: {}", hex::encode(tx_logs[0].transaction_hash), e))?;
+        }
+        state.set_synced_block(to);
+        if state.chain().root() != root {
+            proofs.lock().unwrap().invalidate();
+        }
+    }
+
+    let watches = webhooks.read().unwrap().output_watches();
+    let mut deliveries: Vec<Delivery> = outputs
+        .iter()
+        .flat_map(|(block, index, commitment, encrypted)| {
+            webhooks::on_output(&watches, *block, *index, *commitment, encrypted)
+        })
+        .collect();
+    let mut webhooks = webhooks.write().unwrap();
+    for (block, nullifier) in spent {
+        deliveries.extend(webhooks.on_nullifier(block, nullifier));
+    }
+    Ok(deliveries)
+}
+
+/// Start the webhook sender: notifications queued on the returned sender
+/// are POSTed one at a time, signed with `key`.
+fn start_webhook_sender(key: [u8; 32]) -> SyncSender<Delivery> {
+    let (queue, deliveries) = mpsc::sync_channel::<Delivery>(WEBHOOK_QUEUE_CAPACITY);
+    std::thread::spawn(move || {
+        for delivery in deliveries {
+            if let Err(e) = deliver(&delivery, &key) {
+                log!("Webhook {} failed: {}", delivery.url, e);
+            }
+        }
+    });
+    queue
+}
+
+/// POST one notification. Failures aren't retried.
+///
+/// The host is resolved here and every address checked, and the request is
+/// pinned to the checked address, so a name that resolves (or later
+/// rebinds) to an internal address is never reached; redirects aren't
+/// followed for the same reason.
+fn deliver(delivery: &Delivery, key: &[u8; 32]) -> Result<(), String> {
+    let (host, port) = webhooks::webhook_target(&delivery.url)?;
+    let addrs: Vec<SocketAddr> =
+        (host.as_str(), port).to_socket_addrs().map_err(|e| format!("Failed to resolve {}: {}", host, e))?.collect();
+    if addrs.is_empty() || addrs.iter().any(|addr| !webhooks::is_public_ip(addr.ip())) {
+        return Err(format!("{} doesn't resolve to public addresses only", host));
+    }
+    let client = reqwest::blocking::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(&host, addrs[0])
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let body = serde_json::to_vec(&delivery.notification).expect("Notifications serialize");
+    let signature = NotificationSignature::sign(key, &body).map_err(|e| format!("Failed to sign: {}", e))?;
+    client
+        .post(&delivery.url)
+        .header("content-type", "application/json")
+        .header(OPERATOR_HEADER, signature.operator.to_string())
+        .header(SIGNATURE_HEADER, signature.signature.to_string())
+        .body(body)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Queue notifications for the sender, dropping (and logging) those that
+/// don't fit, so a slow endpoint never holds up indexing.
+fn enqueue(queue: &SyncSender<Delivery>, deliveries: Vec<Delivery>) {
+    for delivery in deliveries {
+        match queue.try_send(delivery) {
+            Ok(()) => {}
+            Err(TrySendError::Full(delivery)) => log!("Webhook queue full; dropped notification for {}", delivery.url),
+            Err(TrySendError::Disconnected(_)) => log!("Webhook sender stopped; dropped notification"),
+        }
+    }
+}
+
+/// Poll for new blocks forever. RPC failures are retried; events the state
+/// can't apply stop the process, since every later answer would be wrong.
+fn follow(state: Shared, proofs: Proofs, webhooks: Webhooks, follow: Follow, from_block: u64) {
+    let mut next = from_block;
+    loop {
+        match rpc::block_number(&follow.rpc_url) {
+            Ok(head) => {
+                let safe = head.saturating_sub(follow.confirmations);
+                while next <= safe {
+                    let end = safe.min(next.saturating_add(follow.chunk - 1));
+                    let logs = match rpc::get_logs(&follow.rpc_url, &follow.contract, next, end) {
+                        Ok(logs) => logs,
+                        Err(e) => {
+                            log!("Failed to fetch logs {}..={}: {}", next, end, e);
+                            break;
+                        }
+                    };
+                    match apply_logs(&state, &proofs, &webhooks, logs, end) {
+                        Ok(deliveries) => {
+                            if let Some(queue) = &follow.webhook_queue {
+                                enqueue(queue, deliveries);
+                            }
+                        }
+                        Err(e) => {
+                            log!("Indexer state diverged in blocks {}..={}: {}", next, end, e);
+                            std::process::exit(1);
+                        }
+                    }
+                    next = end + 1;
+                }
+            }
+            Err(e) => log!("Failed to query chain head: {}", e),
+        }
+        std::thread::sleep(follow.poll);
+    }
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    cursor: Option<u64>,
+    limit: Option<usize>,
+}
+
+fn found<T>(entry: Option<T>, what: &str) -> ApiResult<T> {
+    entry.map(Json).ok_or_else(|| (StatusCode::NOT_FOUND, format!("Unknown {}", what)))
+}
+
+async fn leaves(State(state): State<Shared>, Query(q): Query<PageQuery>) -> Json<Page<LeafEntry>> {
+    Json(state.read().unwrap().leaves(q.cursor.unwrap_or(0), q.limit.unwrap_or(DEFAULT_PAGE_LIMIT)))
+}
+
+async fn leaf(State(state): State<Shared>, Path(index): Path<u64>) -> ApiResult<LeafEntry> {
+    found(state.read().unwrap().leaf(index), "leaf")
+}
+
+async fn commitment(State(state): State<Shared>, Path(commitment): Path<Bytes32>) -> ApiResult<LeafEntry> {
+    found(state.read().unwrap().index_of(&commitment.0), "commitment")
+}
+
+async fn commitment_proof(State(api): State<Api>, Path(commitment): Path<Bytes32>) -> ApiResult<CommitmentProof> {
+    let state = api.state.read().unwrap();
+    found(api.proofs.lock().unwrap().proof_of(&state, &commitment.0), "commitment")
+}
+
+/// Check a webhook call carries `Authorization: Bearer <WEBHOOK_TOKEN>`.
+fn authorize_webhooks(api: &Api, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let presented = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let matches = match (presented, &api.webhook_token) {
+        (Some(token), Some(expected)) => {
+            let digest: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+            ghostclaw_core::ct::eq(&digest, expected)
+        }
+        _ => false,
+    };
+    if !matches {
+        return Err((StatusCode::UNAUTHORIZED, "Missing or invalid webhook token".to_string()));
+    }
+    Ok(())
+}
+
+async fn register_webhook(
+    State(api): State<Api>,
+    headers: HeaderMap,
+    Json(registration): Json<WebhookRegistration>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    authorize_webhooks(&api, &headers)?;
+    let mut hooks = api.webhooks.write().unwrap();
+    if hooks.is_full_for(&registration) {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "Webhook limit reached".to_string()));
+    }
+    let id = hooks.register(registration).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(serde_json::json!({ "id": id })))
+}
+
+async fn watch_nullifiers(
+    State(api): State<Api>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(nullifiers): Json<Vec<Bytes32>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize_webhooks(&api, &headers)?;
+    match api.webhooks.write().unwrap().watch_nullifiers(&id, nullifiers) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Ok(StatusCode::NOT_FOUND),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e)),
+    }
+}
+
+async fn unregister_webhook(
+    State(api): State<Api>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize_webhooks(&api, &headers)?;
+    match api.webhooks.write().unwrap().unregister(&id) {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+async fn nullifier(State(state): State<Shared>, Path(nullifier): Path<Bytes32>) -> Json<NullifierStatus> {
+    Json(state.read().unwrap().nullifier_status(&nullifier.0))
+}
+
+async fn roots(State(state): State<Shared>, Query(q): Query<PageQuery>) -> Json<Page<RootEntry>> {
+    Json(state.read().unwrap().roots(q.cursor.unwrap_or(0), q.limit.unwrap_or(DEFAULT_PAGE_LIMIT)))
+}
+
+async fn root(State(state): State<Shared>, Path(root): Path<Bytes32>) -> ApiResult<RootEntry> {
+    found(state.read().unwrap().root_entry(&root.0), "root")
+}
+
+async fn sync(State(state): State<Shared>) -> Json<SyncStatus> {
+    Json(state.read().unwrap().sync_status())
+}
+
+async fn snapshot(State(state): State<Shared>) -> Json<StateSnapshot> {
+    Json(state.read().unwrap().snapshot())
+}
+
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    crate::flag_value(args, flag).map(|v| v.parse().unwrap_or_else(|_| panic!("Invalid {}: {}", flag, v)))
+}
+
+/// Resume from the checkpoint at `path`, if one has been written.
+fn load_checkpoint(path: &str, deployment: &ChainDeployment) -> Option<IndexerState> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => panic!("Failed to read checkpoint {}: {}", path, e),
+    };
+    let checkpoint: Checkpoint =
+        serde_json::from_slice(&data).unwrap_or_else(|e| panic!("Invalid checkpoint {}: {}", path, e));
+    let state = IndexerState::resume(deployment.clone(), &checkpoint).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    Some(state)
+}
+
+fn save_checkpoint(path: &str, state: &Shared) -> Result<(), String> {
+    let checkpoint = state.read().unwrap().checkpoint();
+    crate::daemon::write_atomic(std::path::Path::new(path), &serde_json::to_vec(&checkpoint).unwrap())?;
+    log!(
+        "Saved checkpoint at block {} ({} leaves) to {}",
+        checkpoint.state.block_number,
+        checkpoint.state.leaf_count,
+        path
+    );
+    Ok(())
+}
+
+pub fn run(args: &[String]) {
+    let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+    let contract = crate::flag_value(args, "--contract")
+        .or_else(rpc::ledger_contract_from_env)
+        .expect("Pass --contract <address> or set LEDGER_CONTRACT");
+    let manifest = crate::deploy::manifest_from_env().and_then(Result::ok);
+    let chain_id = parse_flag(args, "--chain-id")
+        .or_else(|| manifest.as_ref().map(|m| m.chain_id))
+        .unwrap_or_else(|| rpc::chain_id(&rpc_url).expect("Failed to query chain ID"));
+    let from_block = parse_flag(args, "--from-block")
+        .or_else(|| manifest.as_ref().and_then(|m| m.deploy_block))
+        .unwrap_or(0);
+    let listen = crate::flag_value(args, "--listen").unwrap_or_else(|| "0.0.0.0:8090".to_string());
+    let mut follow_config = Follow {
+        rpc_url,
+        contract: contract.clone(),
+        confirmations: parse_flag(args, "--confirmations").unwrap_or(2),
+        chunk: parse_flag(args, "--log-chunk").unwrap_or(10_000u64).max(1),
+        poll: Duration::from_secs(parse_flag(args, "--poll-secs").unwrap_or(5)),
+        webhook_queue: None,
+    };
+    let webhook_token = std::env::var("WEBHOOK_TOKEN").ok().filter(|token| !token.is_empty());
+    let webhooks_enabled = match (crate::operator::operator_key(), &webhook_token) {
+        (Some(key), Some(_)) => {
+            follow_config.webhook_queue = Some(start_webhook_sender(key));
+            true
+        }
+        _ => false,
+    };
+
+    let checkpoint_path = crate::flag_value(args, "--checkpoint");
+    let deployment = ChainDeployment { chain_id, ledger: contract.clone() };
+    let (state, from_block) = match checkpoint_path.as_deref().and_then(|path| load_checkpoint(path, &deployment)) {
+        Some(state) => {
+            let next = state.next_block();
+            log!("Resuming from checkpoint: {} leaves", state.sync_status().leaf_count);
+            (state, next)
+        }
+        None => (IndexerState::new(deployment), from_block),
+    };
+    let state: Shared = Arc::new(RwLock::new(state));
+    let capacity = parse_flag(args, "--proof-cache").unwrap_or(DEFAULT_PROOF_CACHE_CAPACITY);
+    let proofs: Proofs = Arc::new(Mutex::new(ProofCache::new(capacity)));
+    log!("Indexing ledger {} on chain {} from block {}", contract, chain_id, from_block);
+    crate::shutdown::install();
+    let max_webhooks = parse_flag(args, "--max-webhooks").unwrap_or(DEFAULT_MAX_WEBHOOKS);
+    let webhooks: Webhooks = Arc::new(RwLock::new(WebhookRegistry::with_capacity(max_webhooks)));
+    let (follower, follower_proofs, follower_webhooks) = (state.clone(), proofs.clone(), webhooks.clone());
+    std::thread::spawn(move || follow(follower, follower_proofs, follower_webhooks, follow_config, from_block));
+
+    let mut app = Router::new()
+        .route("/leaves", get(leaves))
+        .route("/leaves/:index", get(leaf))
+        .route("/commitments/:commitment", get(commitment))
+        .route("/commitments/:commitment/proof", get(commitment_proof))
+        .route("/nullifiers/:nullifier", get(nullifier))
+        .route("/roots", get(roots))
+        .route("/roots/:root", get(root))
+        .route("/sync", get(sync))
+        .route("/state", get(snapshot));
+    if webhooks_enabled {
+        app = app
+            .route("/webhooks", post(register_webhook))
+            .route("/webhooks/:id", delete(unregister_webhook))
+            .route("/webhooks/:id/nullifiers", post(watch_nullifiers));
+    } else {
+        log!("OPERATOR_KEY or WEBHOOK_TOKEN not set: webhooks are disabled");
+    }
+    let webhook_token = webhook_token.map(|token| Sha256::digest(token.as_bytes()).into());
+    let app = app.with_state(Api { state: state.clone(), proofs, webhooks, webhook_token });
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start tokio runtime");
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(&listen)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to listen on {}: {}", listen, e));
+        log!("Serving indexer API on {}", listen);
+        axum::serve(listener, app)
+            .with_graceful_shutdown(crate::shutdown::wait())
+            .await
+            .expect("Indexer server failed");
+    });
+
+    if let Some(path) = checkpoint_path {
+        if let Err(e) = save_checkpoint(&path, &state) {
+            log!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Pre-proving check against the ledger's pause flag, denylist and leaves
+//!
+//! When a ledger is configured for the request's chain (see `chains.rs`), the
+//! host asks it whether the transaction could land before spending proving
+//! time on it:
+//!
+//! - `paused()`: a paused ledger rejects every `submitTx`, so proving is
+//!   refused with the ledger's `pauseReason()` when it exposes one.
+//! - `denylistRoot()`: with DENYLIST_FILE (a JSON array of hex commitments)
+//!   matching that root, spends of denylisted notes are refused.
+//!
+//! - `OutputCommitted` logs: an output whose commitment the ledger already
+//!   holds is refused (see `Witness::validate_output_uniqueness`). Logs are
+//!   searched from the deployment block; deposit commitments aren't indexed
+//!   topics, so only outputs of earlier transactions are found.
+//!
+//! Ledgers without these getters are treated as never paused and without a
+//! denylist. RPC failures are logged and don't block proving.
+
+use std::collections::HashMap;
+
+use alloy_sol_types::{sol, SolCall, SolEvent};
+use ghostclaw_core::denylist::Denylist;
+use ghostclaw_core::events::{decode_ledger_log, LedgerEvent, OutputCommitted};
+use ghostclaw_core::hex::Bytes32;
+use ghostclaw_core::Witness;
+
+use crate::chains::Deployment;
+use crate::rpc;
+
+sol! {
+    function paused() external view returns (bool);
+    function pauseReason() external view returns (string);
+    function denylistRoot() external view returns (bytes32);
+    function nextLeafIndex() external view returns (uint256);
+}
+
+/// `Some(return value)`, or `None` when the ledger doesn't implement `C`.
+pub fn optional_call<C: SolCall>(rpc_url: &str, contract: &str, call: &C) -> Result<Option<C::Return>, String> {
+    match rpc::eth_call(rpc_url, contract, &call.abi_encode()) {
+        Ok(ret) if ret.is_empty() => Ok(None),
+        Ok(ret) => C::abi_decode_returns(&ret, true)
+            .map(Some)
+            .map_err(|e| format!("Failed to decode {}: {}", C::SIGNATURE, e)),
+        // Missing functions revert (no fallback); transport errors don't
+        Err(e) if e.contains("revert") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn load_denylist(path: &str) -> Result<Denylist, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let entries: Vec<Bytes32> = serde_json::from_str(&json).map_err(|e| format!("Invalid denylist {}: {}", path, e))?;
+    Ok(Denylist::new(entries.into_iter().map(|e| e.0)))
+}
+
+/// Why `witness` would revert on `contract` (paused ledger or denylisted
+/// input), or `None` if it wouldn't. `Err` only when the ledger can't be read.
+pub fn check(rpc_url: &str, contract: &str, witness: &Witness) -> Result<Option<String>, String> {
+    if optional_call(rpc_url, contract, &pausedCall {})?.is_some_and(|r| r._0) {
+        let reason = optional_call(rpc_url, contract, &pauseReasonCall {})
+            .ok()
+            .flatten()
+            .map(|r| r._0)
+            .filter(|r| !r.is_empty())
+            .unwrap_or_else(|| "no reason given".to_string());
+        return Ok(Some(format!("Ledger {} is paused ({}); submitTx would revert", contract, reason)));
+    }
+
+    let Some(root) = optional_call(rpc_url, contract, &denylistRootCall {})?.map(|r| r._0.0) else {
+        return Ok(None);
+    };
+    if root == [0u8; 32] {
+        return Ok(None);
+    }
+    let Ok(path) = std::env::var("DENYLIST_FILE") else {
+        log!("Ledger {} has denylist root 0x{}; set DENYLIST_FILE to check inputs against it", contract, hex::encode(root));
+        return Ok(None);
+    };
+    let denylist = match load_denylist(&path) {
+        Ok(denylist) => denylist,
+        Err(e) => return Ok(Some(e)),
+    };
+    if denylist.root() != root {
+        return Ok(Some(format!(
+            "DENYLIST_FILE {} has root 0x{}, ledger has 0x{}",
+            path,
+            hex::encode(denylist.root()),
+            hex::encode(root)
+        )));
+    }
+    for (i, note) in witness.input_notes.iter().enumerate() {
+        let commitment = ghostclaw_core::commit(note);
+        if denylist.contains(&commitment) {
+            return Ok(Some(format!(
+                "Input {} (0x{}) is denylisted by ledger {}; submitTx would revert",
+                i,
+                hex::encode(commitment),
+                contract
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// The ledger's leaf count (`nextLeafIndex`), if it can be read.
+pub fn leaf_count(deployment: &Deployment) -> Option<u64> {
+    let count = optional_call(&deployment.rpc_url(), &deployment.ledger, &nextLeafIndexCall {}).ok().flatten()?;
+    u64::try_from(count._0).ok()
+}
+
+/// Leaf index of each of `witness`'s outputs that `contract` has already
+/// committed, from `OutputCommitted` logs since `from_block`.
+fn committed_outputs(rpc_url: &str, contract: &str, from_block: u64, witness: &Witness) -> Result<HashMap<[u8; 32], u64>, String> {
+    let head = rpc::block_number(rpc_url)?;
+    let mut committed = HashMap::new();
+    for note in &witness.output_notes {
+        let commitment = ghostclaw_core::commit(note);
+        let topics = [Some(OutputCommitted::SIGNATURE_HASH.0), Some(commitment)];
+        for log in rpc::get_logs_by_topics(rpc_url, contract, from_block, head, &topics)? {
+            if let Some(LedgerEvent::OutputCommitted(event)) = decode_ledger_log(&log.topics, &log.data)? {
+                committed.insert(commitment, u64::try_from(event.leafIndex).unwrap_or(u64::MAX));
+            }
+        }
+    }
+    Ok(committed)
+}
+
+/// Run `check` and the output commitment check against the request chain's
+/// ledger, panicking if the proof would revert or duplicate a leaf.
+pub fn check_deployment(deployment: Option<&Deployment>, witness: &Witness) {
+    let Some(deployment) = deployment else {
+        return;
+    };
+    let rpc_url = deployment.rpc_url();
+    match check(&rpc_url, &deployment.ledger, witness) {
+        Ok(None) => {}
+        Ok(Some(reason)) => panic!("Refusing to prove: {}", reason),
+        Err(e) => log!("Ledger status check skipped: {}", e),
+    }
+    match committed_outputs(&rpc_url, &deployment.ledger, deployment.deploy_block.unwrap_or(0), witness) {
+        Ok(committed) => witness
+            .validate_output_uniqueness(|c| committed.get(c).copied())
+            .unwrap_or_else(|e| panic!("Refusing to prove: {}", e)),
+        Err(e) => log!("Output commitment check skipped: {}", e),
+    }
+}
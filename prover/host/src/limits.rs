@@ -0,0 +1,99 @@
+//! Cycle ceiling per transaction, and the `limits` subcommand
+//!
+//! # Usage
+//! MAX_TX_CYCLES=<n> sp1-host limits [--max-inputs <n>] [--max-outputs <n>]
+//!
+//! With MAX_TX_CYCLES set, every request is executed (no proof) before
+//! proving, and refused if its cycles exceed the ceiling, with a suggestion
+//! of how many inputs per transaction would fit. How many inputs that is
+//! depends on the guest build, so there's no fixed input limit: `limits`
+//! executes demo transactions of sample shapes (1, 2 and `--max-inputs`
+//! inputs, 16 by default so a full consolidation is measured rather than
+//! extrapolated; 1, 2 and `--max-outputs` outputs) against the embedded ELF
+//! and prints a `CycleLimits` as JSON (see core's `cycle_limits`), with the
+//! most inputs that fit for each output count. The prover-server serves it as
+//! `GET /api/limits`.
+//!
+//! The samples are signed with core's test keys, so `limits` is only built
+//! with the `demo` feature; the ceiling itself is enforced in every build.
+
+#[cfg(feature = "demo")]
+use sp1_sdk::{ProverClient, SP1Stdin};
+#[cfg(feature = "demo")]
+use ghostclaw_core::testing::{TestOwner, WitnessBuilder};
+#[cfg(feature = "demo")]
+use ghostclaw_core::{CycleLimits, GuestInput};
+use ghostclaw_core::{check_cycles, CycleSample};
+
+#[cfg(feature = "demo")]
+use crate::ELF;
+
+/// The configured ceiling (`MAX_TX_CYCLES`), if any.
+pub fn max_tx_cycles() -> Option<u64> {
+    std::env::var("MAX_TX_CYCLES").ok().map(|v| v.parse().expect("Invalid MAX_TX_CYCLES"))
+}
+
+/// Refuse a request measured at `sample` if it's over MAX_TX_CYCLES.
+pub fn enforce(sample: &CycleSample) {
+    if let Some(max_cycles) = max_tx_cycles() {
+        check_cycles(sample, max_cycles).unwrap_or_else(|e| panic!("Refusing to prove: {}", e));
+    }
+}
+
+/// Execute a demo transaction of this shape: Alice spends `inputs` notes
+/// and pays Bob `outputs` notes.
+#[cfg(feature = "demo")]
+fn measure(inputs: usize, outputs: usize) -> CycleSample {
+    let (alice, bob) = (TestOwner::alice(), TestOwner::bob());
+    let mut builder = WitnessBuilder::new().chain_id(31337);
+    for _ in 0..inputs {
+        builder = builder.input(&alice, outputs as u64);
+    }
+    for _ in 0..outputs {
+        builder = builder.output(&bob, 1);
+    }
+    let (public_inputs, witness) = builder.build().unwrap_or_else(|e| panic!("Failed to build {}x{}: {}", inputs, outputs, e));
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(public_inputs, witness));
+    let (_, report) = ProverClient::builder()
+        .cpu()
+        .build()
+        .execute(ELF, &stdin)
+        .run()
+        .unwrap_or_else(|e| panic!("Guest rejected the {}x{} sample: {}", inputs, outputs, e));
+    let cycles = report.total_instruction_count();
+    log!("{}x{}: {} cycles", inputs, outputs, cycles);
+    CycleSample { inputs, outputs, cycles }
+}
+
+#[cfg(feature = "demo")]
+pub fn run(args: &[String]) {
+    let max_cycles = max_tx_cycles().expect("MAX_TX_CYCLES is not set");
+    let parse = |flag: &str, default: usize| {
+        crate::flag_value(args, flag).map_or(default, |v| v.parse().unwrap_or_else(|_| panic!("Invalid {}: {}", flag, v)))
+    };
+    let max_inputs = parse("--max-inputs", 16).max(2);
+    let max_outputs = parse("--max-outputs", 4).max(2);
+
+    let mut shapes = vec![(1, 1), (2, 1), (1, 2)];
+    shapes.extend([(max_inputs, 1), (1, max_outputs)]);
+    shapes.sort_unstable();
+    shapes.dedup();
+    let samples = shapes.into_iter().map(|(inputs, outputs)| measure(inputs, outputs)).collect();
+
+    let limits = CycleLimits::new(max_cycles, samples, max_outputs).unwrap_or_else(|e| panic!("{}", e));
+    log!(
+        "Ceiling {} cycles: {} + {}/input + {}/output",
+        limits.max_cycles,
+        limits.model.base,
+        limits.model.per_input,
+        limits.model.per_output
+    );
+    println!("{}", serde_json::to_string_pretty(&limits).unwrap());
+}
+
+#[cfg(not(feature = "demo"))]
+pub fn run(_args: &[String]) {
+    panic!("`limits` needs a build with the `demo` feature (cargo run --release --features demo -- limits)");
+}
@@ -7,136 +7,388 @@
 //! # Usage
 //! echo '{"inputNotes":[...],"outputNotes":[...],...}' | cargo run --release
 //!
-//! Or for demo mode (no stdin):
-//! cargo run --release -- --demo
-
-use sp1_sdk::{ProverClient, SP1Stdin, SP1ProofWithPublicValues, Prover, HashableKey};
-use sp1_sdk::network::FulfillmentStrategy;
-use utxo_prototype::{Ledger, Note, PublicInputs, Witness};
-use utxo_prototype::merkle::MerkleProof;
-use serde::{Deserialize, Serialize};
+//! Pass `--backend gpu` (requires the `cuda` feature) to prove on a local
+//! GPU, optionally with `--gpu-device <id>`; falls back to CPU if CUDA
+//! initialization fails. `--backend` takes precedence over SP1_PROVER.
+//!
+//! Set SP1_PROVER=auto to prove small witnesses locally and escalate larger
+//! ones (above SP1_LOCAL_MAX_CYCLES) to the prover network.
+//!
+//! To compare the embedded ELF's vkey with the deployed ledger:
+//! cargo run --release -- vkey --contract <address>
+//!
+//! To prove transactions that spend each other's outputs, one proof each,
+//! for submission in order (see `chained.rs`; nothing is aggregated):
+//! cargo run --release -- prove-chained <chain.json>
+//!
+//! To compare Groth16, PLONK and compressed proving time, proof size, network
+//! cost and verify gas over transaction shapes (see `bench.rs`):
+//! cargo run --release -- bench-proofs --shapes 1x2,4x4,16x1 --format csv --out bench.csv
+//!
+//! After `build-reproducible.sh`, to record the rebuilt ELF's hash so the
+//! host no longer refuses to run because it doesn't match core (ELF_CHECK;
+//! see `elf_check.rs`):
+//! cargo run --release -- elf-record --write ../../core/guest-elf.lock --toolchain <digest>
+//!
+//! To confirm the embedded ELF is the one the guest sources build to:
+//! cargo run --release -- verify-elf
+//!
+//! To deploy the verifier gateway and ledger with the embedded ELF's vkey:
+//! PRIVATE_KEY=... cargo run --release -- deploy --out deployment.json
+//!
+//! To check a proof against the Solidity verifier in revm before submitting:
+//! cargo run --release -- verify-evm <response.json> [--artifacts contracts/out]
+//!
+//! To index the ledger's events and serve leaves, membership proofs,
+//! nullifier status and root history over HTTP (see `indexer.rs`):
+//! cargo run --release -- indexer --contract <address> --listen 0.0.0.0:8090
+//!
+//! `indexer` and `daemon` save their in-memory state on SIGTERM/SIGINT and
+//! exit 0 (see `shutdown.rs`); with `--checkpoint <file>` the indexer
+//! resumes from where it stopped.
+//!
+//! To compare the indexer's root, leaf count and nullifiers with the contract:
+//! cargo run --release -- reconcile --indexer <url> --contract <address>
+//!
+//! To migrate the ledger's tree to a new hash scheme, writing the leaf
+//! mapping and a bundle with the new genesis root (see `migrate_tree.rs`):
+//! cargo run --release -- migrate-tree --scheme blake3 --domain <string> --out <dir>
+//!
+//! To add a shielded deposit to a wallet state file from its receipt:
+//! cargo run --release -- register-deposit --tx <hash> --amount <n> --owner <pubkey> --blinding <hex> --wallet <state.json>
+//!
+//! To create or rotate the keys wallets seal requests to for delegated
+//! proving (PROVER_KEYRING; see `delegated.rs`):
+//! cargo run --release -- prover-keys rotate --keyring <keys.json>
+//!
+//! To prove to a lender that the wallet holds at least a threshold at the
+//! current root, without revealing which notes (see `balance.rs`):
+//! WALLET_SEED=... cargo run --release -- prove-balance --wallet <state.json> --threshold <n> --challenge <hex>
+//!
+//! To quote what relaying a transaction shape costs at the current gas
+//! price (FEE_TOKEN_PER_ETH; see `quote.rs`):
+//! cargo run --release -- quote --inputs 2 --outputs 2 --base-fee <wei>
+//!
+//! To prove requests dropped into a directory or pushed to a Redis list or
+//! NATS subject, publishing the responses back (see `daemon.rs`):
+//! cargo run --release -- daemon --watch <dir> --concurrency 2
+//!
+//! To publish the most inputs a transaction can spend under MAX_TX_CYCLES,
+//! measured on the embedded ELF (see `limits.rs`):
+//! MAX_TX_CYCLES=... cargo run --release -- limits
+//!
+//! To shrink a rejected request to a minimal fixture for a bug report:
+//! cargo run --release -- minimize <request.json> --out <fixture.json>
+//!
+//! To explore the protocol by hand (notes, tree, nullifiers, witnesses and
+//! mock proofs in an in-memory session; see `repl.rs`):
+//! cargo run --release -- repl
+//!
+//! To replay archived proofs against an upgraded guest ELF:
+//! cargo run --release -- replay <archive-dir> --elf <new-elf>
+//!
+//! Or for demo mode (no stdin; see `demo.rs`):
+//! cargo run --release --features demo -- --demo
+//!
+//! Network proofs honour SP1_FULFILLMENT_STRATEGY, SP1_MAX_PRICE_PER_PGU,
+//! SP1_AUCTION_TIMEOUT_SECS, SP1_PROOF_TIMEOUT_SECS and SP1_PROVER_WHITELIST
+//! (see `network.rs`). A request's `deadlineMs` caps those timeouts; past
+//! it, proving fails with code `DEADLINE_EXCEEDED`.
+//!
+//! Set ARTIFACT_STORE=s3|ipfs to upload each proof's artifacts and return
+//! their location as `artifacts` (see `artifacts.rs`).
+//!
+//! Set METER_CYCLES=1 to execute the guest before proving and report its
+//! cycle count as `cycles` (used for per-tenant metering).
+//!
+//! Set MAX_TX_CYCLES to refuse transactions whose measured cycles exceed it,
+//! with a suggestion of how many inputs per transaction would fit (see
+//! `limits.rs`).
+//!
+//! Every request names its `chainId` and the proof is bound to it. With a
+//! ledger configured for that chain (DEPLOYMENTS, or LEDGER_CONTRACT /
+//! DEPLOYMENT_MANIFEST for a single chain; see `chains.rs`), proving is
+//! refused if the ledger is paused, an input is denylisted or an output's
+//! commitment is already a leaf (see `ledger_status.rs`).
+//!
+//! Every response carries a `linkability` report: distinctive amounts,
+//! spends of just-received notes and rare shapes that could tie the
+//! transaction to others (see core's `linkability`).
+//!
+//! With a ledger configured, `expiresAt` estimates when the proof's old root
+//! leaves a bounded root history, so relayers can ask for a fresh proof.
+//!
+//! Proofs whose ledger call would exceed the chain's calldata budget
+//! (MAX_CALLDATA_BYTES, default 128 KiB, or per chain in DEPLOYMENTS) are
+//! refused with a suggestion to split the transaction into chained ones.
+//!
+//! A request's old root must be a valid root on its chain's ledger; with no
+//! ledger or RPC to check it, proving is refused unless
+//! `--allow-unverified-root` is passed, and the response is then marked
+//! `unverifiedRoot` (see `root_check.rs`).
+//!
+//! Programs listed in REVOCATION_LIST or REVOKED_VKEYS (see `revocation.rs`)
+//! are refused, by ELF hash at startup and by vkey after key setup.
+//!
+//! Set DUST_THRESHOLD to reject outputs that are non-zero but below it.
+//!
+//! Output blindings that look like wallet bugs (repeated byte, equal to the
+//! owner, reused) are logged as warnings, or rejected with BLINDING_POLICY=reject.
+//!
+//! For delegated proving the request may arrive sealed to this host's key,
+//! as `{"encryptedRequest": ...}`; it's opened in memory with PROVER_KEYRING.
+//! Or only its witness fields may be sealed (`{"sealedFields": ...}`), to
+//! the same keys, so an intermediary can route it without reading it. Logs
+//! are redacted for both.
+//!
+//! A `traceId` in the request is prefixed to every log line and echoed in
+//! the response; on failure a `{"error", "traceId"}` payload goes to stdout.
+//!
+//! A request with an `externalNullifier` uses its notes in that application
+//! context (an airdrop claim, a vote) instead of spending them: the proof
+//! carries per-context nullifiers in the scoped public values layout, for
+//! the application's contract rather than the ledger.
+//!
+//! Set OPERATOR_KEY to a file holding the operator's key to sign each
+//! response (`operatorSignature`) over the request, public outputs and vkey
+//! hash (see `operator.rs`).
+//!
+//! Set INDEXER_STATE_URL to the indexer's `/state` to also return the
+//! ledger root after this proof's outputs are inserted (`predictedNewRoot`,
+//! see `predicted_root.rs`).
+//!
+//! Pass `--emit-foundry-fixture <dir>` to also write each proof as a fixture
+//! for the contracts' forge tests (see `foundry_fixture.rs`).
+//!
+//! To see which private witness fields would be sent to the prover network:
+//! echo '{...}' | SP1_PROVER=network cargo run --release -- --privacy-report
+
+use sp1_sdk::{ProverClient, SP1Stdin, SP1ProofWithPublicValues, SP1VerifyingKey, Prover, HashableKey};
+use ghostclaw_core::{Bytes65, CycleSample, GuestInput, Ledger, Note, PublicInputs, PublicOutputs, RecoveredKeys, Withdrawal, Witness};
+pub use ghostclaw_core::ProofRequest;
+pub use ghostclaw_prover_client::ProofResponse;
+use ghostclaw_core::merkle::MerkleProof;
+use ghostclaw_core::output_order::is_canonical_order;
+use ghostclaw_core::signatures::DeterminismEvidence;
 use std::io::{self, BufRead};
-use alloy_sol_types::{sol, SolType};
-
-// Define Solidity-compatible struct for ABI decoding (must match program/src/main.rs and contract)
-sol! {
-    struct PublicOutputsSol {
-        bytes32 oldRoot;
-        bytes32[] nullifiers;
-        bytes32[] outputCommitments;
-    }
-}
+use ghostclaw_core::calldata::check_calldata_size;
+use ghostclaw_core::linkability::{self, LinkabilityContext, LinkabilityReport};
+use ghostclaw_core::public_values::{decode_public_values, is_compressed};
+
+#[macro_use]
+mod trace;
+mod artifacts;
+mod balance;
+mod bench;
+mod chained;
+mod chains;
+mod daemon;
+mod delegated;
+mod deploy;
+mod demo;
+mod deposit;
+mod elf_check;
+mod expiry;
+mod foundry_fixture;
+mod indexer;
+mod ledger_status;
+mod limits;
+mod migrate_tree;
+mod minimize;
+mod network;
+mod operator;
+mod predicted_root;
+mod quote;
+mod reconcile;
+mod revocation;
+mod root_check;
+mod repl;
+mod replay;
+mod routing;
+mod rpc;
+mod shutdown;
+mod timings;
+mod verify_evm;
+mod vkey;
+
+use network::NetworkConfig;
+use routing::{Backend, RoutingDecision, RoutingPolicy};
+use timings::Stage;
 
 pub const ELF: &[u8] = include_bytes!("../../program/elf/sp1-program");
 
-/// Transaction request from the prover-server
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ProofRequest {
-    /// Input notes being spent (full note data)
-    pub input_notes: Vec<NoteData>,
-    /// Output notes being created
-    pub output_notes: Vec<NoteData>,
-    /// Nullifier signatures (hex strings: 65 bytes)
-    pub nullifier_signatures: Vec<String>,
-    /// Transaction signatures (hex strings: 65 bytes)
-    pub tx_signatures: Vec<String>,
-    /// Indices of input notes in the merkle tree
-    pub input_indices: Vec<usize>,
-    /// Merkle proofs for input notes (array of hex strings)
-    pub input_proofs: Vec<Vec<String>>,
-    /// Current merkle root from contract (hex string)
-    pub old_root: String,
-}
+/// Cycles measured for the request being served (one per process)
+static METERED_CYCLES: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+
+/// Linkability of the request being served (one per process)
+static LINKABILITY: std::sync::OnceLock<LinkabilityReport> = std::sync::OnceLock::new();
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct NoteData {
-    pub amount: u64,
-    pub owner_pubkey: String,
-    pub blinding: String,
+impl trace::Traced for ProofRequest {
+    fn trace_id(&self) -> Option<&str> {
+        self.trace_id.as_deref()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.check_schema_version()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ProofResponse {
-    pub proof: String,
-    pub public_values_raw: String,
-    pub public_outputs: PublicOutputsJson,
-    pub vkey_hash: String,
+/// Chain, nullifiers, output commitments and withdrawal a proof is expected
+/// to commit to.
+///
+/// Compressed public values carry only list roots; the full lists come from
+/// the witness and are checked against them.
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedOutputs {
+    pub chain_id: u64,
+    pub nullifiers: Vec<[u8; 32]>,
+    pub output_commitments: Vec<[u8; 32]>,
+    pub withdrawal: Option<Withdrawal>,
+    pub external_nullifier: Option<[u8; 32]>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PublicOutputsJson {
-    pub old_root: String,
-    pub nullifiers: Vec<String>,
-    pub output_commitments: Vec<String>,
+impl ExpectedOutputs {
+    fn from_witness(witness: &Witness, chain_id: u64) -> Self {
+        let witness = witness.clone().with_derived_values();
+        Self {
+            chain_id,
+            nullifiers: witness.precomputed_nullifiers,
+            output_commitments: witness.precomputed_output_commitments,
+            withdrawal: witness.withdrawal,
+            external_nullifier: witness.external_nullifier,
+        }
+    }
 }
 
 fn main() {
     // Check args
     let args: Vec<String> = std::env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("elf-record") => return elf_check::run(&args),
+        Some("verify-elf") => return elf_check::verify(&args),
+        _ => {}
+    }
+    elf_check::check();
+
+    match args.get(1).map(String::as_str) {
+        Some("prove-chained") => return chained::run(&args),
+        Some("bench-proofs") => return bench::run(&args),
+        Some("daemon") => return daemon::run(&args),
+        Some("deploy") => return deploy::run(&args),
+        Some("indexer") => return indexer::run(&args),
+        Some("limits") => return limits::run(&args),
+        Some("migrate-tree") => return migrate_tree::run(&args),
+        Some("minimize") => return minimize::run(&args),
+        Some("prove-balance") => return balance::run(&args),
+        Some("prover-keys") => return delegated::run(&args),
+        Some("quote") => return quote::run(&args),
+        Some("reconcile") => return reconcile::run(&args),
+        Some("repl") => return repl::run(&args),
+        Some("register-deposit") => return deposit::run(&args),
+        Some("replay") => return replay::run(&args),
+        Some("verify-evm") => return verify_evm::run(&args),
+        Some("vkey") => return vkey::run(&args),
+        _ => {}
+    }
+    timings::start();
+    revocation::refuse_revoked_elf(ELF);
+    foundry_fixture::configure(&args);
+    root_check::configure(&args);
+
     let is_demo = args.contains(&"--demo".to_string());
+    let privacy_report_only = args.contains(&"--privacy-report".to_string());
     
-    // Check if we should use network or CPU
-    let use_network = std::env::var("SP1_PROVER").unwrap_or_default() == "network";
+    // `--backend <name>` overrides SP1_PROVER
+    let backend = flag_value(&args, "--backend")
+        .unwrap_or_else(|| std::env::var("SP1_PROVER").unwrap_or_default());
 
-    if use_network {
-        let rpc_url = std::env::var("PROVER_NETWORK_RPC")
-            .unwrap_or_else(|_| "https://rpc.mainnet.succinct.xyz".to_string());
-        eprintln!("Using Network Prover (RPC: {})", rpc_url);
-        
-        // Build NetworkProver
-        let client = ProverClient::builder().network().rpc_url(&rpc_url).build();
+    // Check if we should use network or CPU
+    let use_network = backend == "network";
+
+    if backend == "gpu" {
+        let client = build_gpu_client(flag_value(&args, "--gpu-device"));
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        match (client, lines.next()) {
+            (Some(client), Some(Ok(line))) => {
+                let request = delegated::parse_request(&line);
+                run_proof_from_request_gpu(client, request);
+            }
+            (None, Some(Ok(line))) => {
+                log!("CUDA prover unavailable, falling back to CPU Prover (Local)");
+                let request = delegated::parse_request(&line);
+                run_proof_from_request_cpu(ProverClient::builder().cpu().build(), request);
+            }
+            _ => log!("No input provided"),
+        }
+    } else if backend == "auto" {
+        let policy = RoutingPolicy::from_env();
+        log!("Using Auto Prover (local up to {} cycles, network above)", policy.local_max_cycles);
+
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        if let Some(Ok(line)) = lines.next() {
+            let request = delegated::parse_request(&line);
+            run_proof_from_request_auto(policy, request);
+        } else {
+            log!("No input provided");
+        }
+    } else if use_network {
+        let network = NetworkConfig::from_env_or_exit();
+        let client = network.client();
 
          if is_demo {
-             run_demo_network(client);
+             demo::run_network(&network, client);
          } else {
              // Read from stdin
              let stdin = io::stdin();
              let mut lines = stdin.lock().lines();
              if let Some(Ok(line)) = lines.next() {
-                 let request: ProofRequest = serde_json::from_str(&line).expect("Failed to parse request");
-                 run_proof_from_request_network(client, request);
+                 let request = delegated::parse_request(&line);
+                 if privacy_report_only {
+                     print_privacy_report(&request);
+                     return;
+                 }
+                 run_proof_from_request_network(&network, client, request);
              } else {
-                 eprintln!("No input provided");
+                 log!("No input provided");
              }
          }
-    } else if std::env::var("SP1_PROVER").unwrap_or_default() == "mock" {
-        eprintln!("Using Mock Prover (Fast)");
+    } else if backend == "mock" {
+        log!("Using Mock Prover (Fast)");
         // Build MockProver
         let client = ProverClient::builder().mock().build();
         
         if is_demo {
-             run_demo_cpu(client);
+             demo::run_cpu(client);
         } else {
              // Read from stdin
              let stdin = io::stdin();
              let mut lines = stdin.lock().lines();
              if let Some(Ok(line)) = lines.next() {
-                 let request: ProofRequest = serde_json::from_str(&line).expect("Failed to parse request");
+                 let request = delegated::parse_request(&line);
                  run_proof_from_request_mock(client, request);
              } else {
-                 eprintln!("No input provided");
+                 log!("No input provided");
              }
         }
     } else {
-        eprintln!("Using CPU Prover (Local)");
+        log!("Using CPU Prover (Local)");
         // Build CpuProver
         let client = ProverClient::builder().cpu().build();
         
         if is_demo {
-             run_demo_cpu(client);
+             demo::run_cpu(client);
         } else {
              // Read from stdin
              let stdin = io::stdin();
              let mut lines = stdin.lock().lines();
              if let Some(Ok(line)) = lines.next() {
-                 let request: ProofRequest = serde_json::from_str(&line).expect("Failed to parse request");
+                 let request = delegated::parse_request(&line);
                  run_proof_from_request_cpu(client, request);
              } else {
-                 eprintln!("No input provided");
+                 log!("No input provided");
              }
         }
     }
@@ -144,45 +396,196 @@ fn main() {
 
 
 /// Build witness and public inputs from request
-fn build_inputs_from_request(request: &ProofRequest) -> (SP1Stdin, std::time::Instant, usize) {
-    eprintln!("Building inputs from request...");
+///
+/// When `strip_derivable` is set (network proving), precomputed values are
+/// removed before the witness leaves the machine; the guest recomputes them.
+fn build_inputs_from_request(request: &ProofRequest, strip_derivable: bool) -> (SP1Stdin, std::time::Instant, ExpectedOutputs) {
+    if strip_derivable {
+        delegated::refuse_network_if_sealed();
+    }
+    let old_root = request.old_root.0;
+    let (witness, expected) = timings::time(Stage::Precompute, || {
+        let witness = build_witness_from_request(request);
+        preflight_witness(&witness, old_root);
+        let deployment = chains::deployment_or_refuse(request.chain_id);
+        root_check::verify(deployment.as_ref(), old_root);
+        ledger_status::check_deployment(deployment.as_ref(), &witness);
+        report_linkability(&witness, deployment.as_ref());
+        let expected = ExpectedOutputs::from_witness(&witness, request.chain_id);
+        (witness, expected)
+    });
+
+    let witness = if strip_derivable {
+        let witness = witness.strip_derivable();
+        log!("{}", witness.privacy_assessment());
+        witness
+    } else {
+        witness
+    };
+
+    let public_inputs = PublicInputs::new(old_root).with_chain_id(request.chain_id);
+    let (inputs, outputs) = (witness.input_notes.len(), witness.output_notes.len());
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(public_inputs, witness));
+
+    let meter = std::env::var("METER_CYCLES").is_ok_and(|v| v == "1" || v == "true");
+    if (meter || limits::max_tx_cycles().is_some()) && METERED_CYCLES.get().is_none() {
+        let (_, report) = timings::time(Stage::Execute, || {
+            ProverClient::builder().cpu().build().execute(ELF, &stdin).run().expect("Failed to execute guest")
+        });
+        let cycles = report.total_instruction_count();
+        log!("Metered {} cycles", cycles);
+        limits::enforce(&CycleSample { inputs, outputs, cycles });
+        let _ = METERED_CYCLES.set(cycles);
+    }
+
+    log!("\nGenerating ZK proof (optimized path)...");
+    (stdin, std::time::Instant::now(), expected)
+}
+
+/// Log how linkable the transaction is and keep the report for the
+/// response; immediate spends are only detected with a ledger to read the
+/// leaf count from.
+fn report_linkability(witness: &Witness, deployment: Option<&chains::Deployment>) {
+    let leaf_count = deployment.and_then(ledger_status::leaf_count);
+    let report = linkability::assess(witness, &LinkabilityContext { leaf_count, ..Default::default() });
+    for warning in &report.warnings {
+        log!("  PRIVACY: {}", warning.message);
+    }
+    let _ = LINKABILITY.set(report);
+}
+
+/// Minimum non-zero output amount (`DUST_THRESHOLD`, 0 = no policy).
+///
+/// Enforced here for every ELF; guests built with `dust-policy` also enforce
+/// their own compiled-in threshold.
+fn dust_threshold_from_env() -> u64 {
+    // An unparsable value must not silently disable the policy
+    std::env::var("DUST_THRESHOLD")
+        .ok()
+        .map_or(0, |v| v.parse().unwrap_or_else(|_| panic!("Invalid DUST_THRESHOLD: {}", v)))
+}
+
+/// Run the guest's verification (`simulate_witness`) on the host, so an
+/// invalid request fails with every reason before any proving time is spent.
+/// Signers are recovered in parallel up front (see `signature_batch`).
+fn preflight_witness(witness: &Witness, old_root: [u8; 32]) {
+    if let Err(e) = witness.validate_dust(dust_threshold_from_env()) {
+        panic!("Transaction rejected by dust policy: {}", e);
+    }
+    if let Err(e) = witness.validate_output_owners() {
+        panic!("Transaction rejected: {}", e);
+    }
+    if let Err(e) = witness.validate_output_uniqueness(|_| None) {
+        panic!("Transaction rejected: {}", e);
+    }
+
+    let blinding_issues = witness.blinding_issues();
+    if !blinding_issues.is_empty() {
+        if std::env::var("BLINDING_POLICY").is_ok_and(|v| v == "reject") {
+            let issues: Vec<String> = blinding_issues.iter().map(delegated::redact).collect();
+            panic!("Transaction rejected for weak blindings:\n  {}", issues.join("\n  "));
+        }
+        for issue in &blinding_issues {
+            log!("  WARNING: {} (weak blindings make the commitment guessable)", delegated::redact(issue));
+        }
+    }
+
+    if let Some(update) = &witness.nullifier_tree {
+        let new_root = update
+            .apply(&witness.precomputed_nullifiers)
+            .unwrap_or_else(|e| panic!("Transaction rejected: {}", e));
+        log!("  Nullifier root: 0x{} -> 0x{}", hex::encode(&update.old_root.0[..8]), hex::encode(&new_root[..8]));
+    }
+
+    let output_commitments: Vec<[u8; 32]> = witness.output_notes.iter().map(ghostclaw_core::commit).collect();
+    if !is_canonical_order(&output_commitments) {
+        log!("  WARNING: outputs aren't in canonical order; their positions can reveal payment vs change");
+    }
+
+    let simulation = RecoveredKeys::for_witness(witness).simulate_witness(&mut Ledger::new(), witness, old_root);
+    for (i, input) in simulation.inputs.iter().enumerate() {
+        log!(
+            "  Input [{}] 0x{}: {}",
+            i,
+            hex::encode(&input.commitment[..8]),
+            input.error.as_deref().unwrap_or("ok")
+        );
+    }
+    log!(
+        "  Value: {} in, {} out, fee {}",
+        simulation.input_total,
+        simulation.output_total,
+        simulation.fee()
+    );
+    if !simulation.is_valid() {
+        minimize::write_fixture_from_env(witness, old_root);
+        panic!("Transaction would be rejected by the guest:\n  {}", simulation.failure_reasons().join("\n  "));
+    }
+}
+
+/// Print the privacy assessment for the witness a request would produce,
+/// as submitted to the prover network (derivable fields stripped).
+fn print_privacy_report(request: &ProofRequest) {
+    let witness = build_witness_from_request(request);
+    let full = witness.privacy_assessment();
+    let stripped = witness.strip_derivable().privacy_assessment();
+
+    log!("{}", full);
+    log!("After stripping derivable fields:");
+    log!("{}", stripped);
+    if stripped.exposes_secrets() {
+        log!("WARNING: note blindings and signatures are sent to the prover network.");
+    }
+
+    println!("{}", serde_json::to_string(&stripped).unwrap());
+}
+
+/// Build the witness (with precomputed values) from a request, with its
+/// inputs in canonical order (see core `input_order`)
+fn build_witness_from_request(request: &ProofRequest) -> Witness {
+    log!("Building inputs from request...");
+
+    // The guest only accepts inputs in leaf index order; clients may list
+    // them in selection order, so sort before anything is precomputed
+    let mut request = request.clone();
+    request.canonicalize_inputs().unwrap_or_else(|e| panic!("Invalid request: {}", e));
+    let request = &request;
 
     // Convert input notes
-    let input_notes: Vec<Note> = request.input_notes.iter().map(note_from_data).collect();
+    let input_notes: Vec<Note> = request.input_notes.iter().map(Note::from).collect();
 
     // Convert output notes
-    let output_notes: Vec<Note> = request.output_notes.iter().map(note_from_data).collect();
+    let output_notes: Vec<Note> = request.output_notes.iter().map(Note::from).collect();
 
     // Convert signatures
-    let nullifier_signatures: Vec<Vec<u8>> = request.nullifier_signatures.iter()
-        .map(|k| hex_to_bytes65(k).to_vec())
-        .collect();
-
-    let tx_signatures: Vec<Vec<u8>> = request.tx_signatures.iter()
-        .map(|k| hex_to_bytes65(k).to_vec())
-        .collect();
+    let nullifier_signatures: Vec<Vec<u8>> = request.nullifier_signatures.iter().map(Bytes65::to_vec).collect();
+    let tx_signatures: Vec<Vec<u8>> = request.tx_signatures.iter().map(Bytes65::to_vec).collect();
 
     // DEBUG: Log signature v values
     for (i, sig) in nullifier_signatures.iter().enumerate() {
-        eprintln!("  NullifierSig[{}] v value: {} (raw byte at index 64)", i, sig[64]);
+        log!("  NullifierSig[{}] v value: {} (raw byte at index 64)", i, sig[64]);
     }
     for (i, sig) in tx_signatures.iter().enumerate() {
-        eprintln!("  TxSig[{}] v value: {} (raw byte at index 64)", i, sig[64]);
+        log!("  TxSig[{}] v value: {} (raw byte at index 64)", i, sig[64]);
     }
 
-    // Parse old_root
-    let old_root = hex_to_bytes32(&request.old_root);
+    let old_root = request.old_root.0;
 
-    eprintln!("Transaction: {} inputs -> {} outputs", input_notes.len(), output_notes.len());
-    eprintln!("Old root: 0x{}", hex::encode(&old_root[..8]));
+    log!("Transaction: {} inputs -> {} outputs", input_notes.len(), output_notes.len());
+    if let Some(withdrawal) = &request.withdrawal {
+        log!("Withdrawal: {} to {}", withdrawal.public_amount, withdrawal.recipient);
+    }
+    log!("Old root: 0x{}", hex::encode(&old_root[..8]));
 
     // Build ledger to reconstruct state
     let mut ledger = Ledger::new();
 
     // Add input notes at their specified indices
     for (i, note) in input_notes.iter().enumerate() {
-        let idx = ledger.add_note(note.clone());
-        eprintln!("Added input note {} at index {}", i, idx);
+        let idx = ledger.add_note(note.clone()).index;
+        log!("Added input note {} at index {}", i, delegated::redact(idx));
         // Note: we trust input_indices from request match the newly added notes if the state is consistent.
         // In a real generic prover, we might need to sparsely verify branches, but here we rebuild the tree locally
         // or just supply the indices. The merkle proof verification inside zkVM checks consistency.
@@ -197,14 +600,9 @@ fn build_inputs_from_request(request: &ProofRequest) -> (SP1Stdin, std::time::In
     // Parse Merkle Proofs
     let input_proofs: Vec<MerkleProof> = request.input_proofs.iter()
         .zip(request.input_indices.iter())
-        .map(|(proof_hex, &index)| {
-            let siblings: Vec<[u8; 32]> = proof_hex.iter()
-                .map(|s| hex_to_bytes32(s))
-                .collect();
-            MerkleProof {
-                leaf_index: index as u64,
-                siblings,
-            }
+        .map(|(proof, &index)| {
+            let siblings: Vec<[u8; 32]> = proof.iter().map(|s| s.0).collect();
+            MerkleProof::new(index as u64, siblings)
         })
         .collect();
 
@@ -214,7 +612,7 @@ fn build_inputs_from_request(request: &ProofRequest) -> (SP1Stdin, std::time::In
     }
 
     // Create witness with precomputed values
-    let witness = Witness::new(
+    let mut witness = Witness::new(
         input_notes,
         request.input_indices.clone(),
         input_proofs,
@@ -222,188 +620,204 @@ fn build_inputs_from_request(request: &ProofRequest) -> (SP1Stdin, std::time::In
         tx_signatures.clone(),
         output_notes,
     );
+    witness.withdrawal = request.withdrawal;
+    witness.external_nullifier = request.external_nullifier.map(|e| e.0);
+    witness.nullifier_tree = request.nullifier_tree.clone();
+    if let Some(external_nullifier) = &request.external_nullifier {
+        log!("Scoped use in context {} (nothing is spent)", external_nullifier);
+    }
 
     // OPTIMIZATION: Compute expensive values on host (no ECDSA in zkVM)
-    eprintln!("Precomputing nullifiers and commitments on host...");
-
-    // DEBUG: Log input note details and verify signatures before precomputing
-    for (i, note) in witness.input_notes.iter().enumerate() {
-        eprintln!("  Input note [{}]:", i);
-        eprintln!("    amount: {}", note.amount);
-        eprintln!("    owner_pubkey: 0x{}", hex::encode(&note.owner_pubkey));
-        eprintln!("    blinding: 0x{}", hex::encode(&note.blinding));
-        // Compute commitment to show
-        let commitment = utxo_prototype::commit(note);
-        eprintln!("    commitment: 0x{}", hex::encode(&commitment));
-        // Compute nullifier to show (using sig)
-        if i < witness.nullifier_signatures.len() {
-             let nullifier = utxo_prototype::note::compute_nullifier(&witness.nullifier_signatures[i]);
-             eprintln!("    nullifier: 0x{}", hex::encode(&nullifier));
-
-             // DEBUG: Verify signature on host before sending to zkVM
-             let sig = &witness.nullifier_signatures[i];
-             eprintln!("    nullifier_sig (full): 0x{}", hex::encode(&sig));
-             eprintln!("    sig[0..32] (r): 0x{}", hex::encode(&sig[0..32]));
-             eprintln!("    sig[32..64] (s): 0x{}", hex::encode(&sig[32..64]));
-             eprintln!("    sig[64] (v): {}", sig[64]);
-
-             // Try to recover the public key from the signature
-             use sha3::{Digest, Keccak256};
-             use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
-
-             // Message = Keccak256(Commitment)
-             let mut hasher = Keccak256::new();
-             hasher.update(&commitment);
-             let msg_hash = hasher.finalize();
-             eprintln!("    msg_hash (Keccak256(commitment)): 0x{}", hex::encode(&msg_hash));
-
-             // Ethereum prefix
-             let mut eth_hasher = Keccak256::new();
-             eth_hasher.update(b"\x19Ethereum Signed Message:\n32");
-             eth_hasher.update(&msg_hash);
-             let eth_msg_hash = eth_hasher.finalize();
-             eprintln!("    eth_msg_hash: 0x{}", hex::encode(&eth_msg_hash));
-
-             // Parse signature
-             let r_s_bytes = &sig[0..64];
-             let v = sig[64];
-             let rec_id = if v == 0 || v == 1 { v } else if v == 27 || v == 28 { v - 27 } else { ((v - 35) % 2) as u8 };
-             eprintln!("    v={}, normalized rec_id={}", v, rec_id);
-
-             match Signature::try_from(r_s_bytes) {
-                 Ok(signature) => {
-                     // Try BOTH recovery IDs to see what we get
-                     for try_rec_id in [0u8, 1u8] {
-                         if let Some(recovery_id) = RecoveryId::from_byte(try_rec_id) {
-                             match VerifyingKey::recover_from_prehash(&eth_msg_hash, &signature, recovery_id) {
-                                 Ok(recovered_key) => {
-                                     let encoded = recovered_key.to_encoded_point(true);
-                                     let recovered_x: Vec<u8> = encoded.as_bytes()[1..].to_vec();
-                                     let prefix = encoded.as_bytes()[0];
-                                     let is_match = recovered_x.as_slice() == note.owner_pubkey;
-                                     let marker = if try_rec_id == rec_id { "<<< USING THIS" } else { "" };
-                                     eprintln!("    rec_id={}: prefix=0x{:02x}, X=0x{}... match={} {}",
-                                         try_rec_id, prefix, hex::encode(&recovered_x[0..8]), is_match, marker);
-                                 }
-                                 Err(e) => eprintln!("    rec_id={}: recovery failed: {:?}", try_rec_id, e),
-                             }
-                         }
-                     }
-
-                     // Now do the actual check with the provided recovery ID
-                     if let Some(recovery_id) = RecoveryId::from_byte(rec_id) {
-                         match VerifyingKey::recover_from_prehash(&eth_msg_hash, &signature, recovery_id) {
-                             Ok(recovered_key) => {
-                                 let encoded = recovered_key.to_encoded_point(true);
-                                 let recovered_x: Vec<u8> = encoded.as_bytes()[1..].to_vec();
-                                 eprintln!("    RECOVERED pubkey X: 0x{}", hex::encode(&recovered_x));
-                                 eprintln!("    EXPECTED pubkey X:  0x{}", hex::encode(&note.owner_pubkey));
-                                 if recovered_x.as_slice() == note.owner_pubkey {
-                                     eprintln!("    ✅ Signature verification PASSED on host");
-                                 } else {
-                                     eprintln!("    ❌ Signature verification FAILED on host - pubkey mismatch!");
-                                 }
-                             }
-                             Err(e) => eprintln!("    ❌ Signature recovery failed: {:?}", e),
-                         }
-                     } else {
-                         eprintln!("    ❌ Invalid recovery ID: {}", rec_id);
-                     }
-                 }
-                 Err(e) => eprintln!("    ❌ Invalid signature bytes: {:?}", e),
-             }
+    log!("Precomputing nullifiers and commitments on host...");
+
+    // Nullifier = Hash(signature) is only stable for deterministic, low-s signatures
+    // (the host has no signing keys, so only the low-s form is checked here)
+    let signers = RecoveredKeys::for_witness(&witness);
+    for (i, (note, sig)) in witness.input_notes.iter().zip(&witness.nullifier_signatures).enumerate() {
+        let evidence = DeterminismEvidence::None;
+        if let Err(e) = signers.check_nullifier_determinism(note, witness.external_nullifier.as_ref(), sig, evidence) {
+            panic!("Input {}: {}", i, e);
         }
     }
 
     let witness = witness.with_precomputed_values();
 
-    eprintln!("  Precomputed {} nullifiers", witness.precomputed_nullifiers.len());
+    log!("  Precomputed {} nullifiers", witness.precomputed_nullifiers.len());
     for (i, n) in witness.precomputed_nullifiers.iter().enumerate() {
-        eprintln!("    [{}] 0x{}", i, hex::encode(n));
+        log!("    [{}] 0x{}", i, hex::encode(n));
     }
-    eprintln!("  Precomputed {} input commitments", witness.precomputed_input_commitments.len());
+    log!("  Precomputed {} input commitments", witness.precomputed_input_commitments.len());
     for (i, c) in witness.precomputed_input_commitments.iter().enumerate() {
-        eprintln!("    [{}] 0x{}", i, hex::encode(c));
+        log!("    [{}] 0x{}", i, hex::encode(c));
     }
-    eprintln!("  Precomputed {} output commitments", witness.precomputed_output_commitments.len());
+    log!("  Precomputed {} output commitments", witness.precomputed_output_commitments.len());
 
-    let public_inputs = PublicInputs { old_root };
+    witness
+}
 
-    let expected_output_count = witness.output_notes.len();
+fn run_proof_from_request_cpu(client: sp1_sdk::CpuProver, request: ProofRequest) {
+    let (stdin, start, expected) = build_inputs_from_request(&request, false);
+    let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+    let vkey_hash = checked_vkey_hash(&vk);
+    let proof = timings::time(Stage::CoreProve, || client.prove(&pk, &stdin).run().expect("Failed to generate proof"));
+    output_proof_response(proof, start, &expected, vkey_hash, false, None);
+}
 
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&public_inputs);
-    stdin.write(&witness);
+fn run_proof_from_request_mock(client: sp1_sdk::CpuProver, request: ProofRequest) {
+    let (stdin, start, expected) = build_inputs_from_request(&request, false);
+    let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+    let vkey_hash = checked_vkey_hash(&vk);
+    let proof = timings::time(Stage::CoreProve, || client.prove(&pk, &stdin).run().expect("Failed to generate proof"));
+    output_proof_response(proof, start, &expected, vkey_hash, true, None);
+}
 
-    eprintln!("\nGenerating ZK proof (optimized path)...");
-    (stdin, std::time::Instant::now(), expected_output_count)
+fn run_proof_from_request_network(network: &NetworkConfig, client: sp1_sdk::NetworkProver, request: ProofRequest) {
+    // Third-party provers only receive what the guest cannot derive itself
+    let (stdin, start, expected) = build_inputs_from_request(&request, true);
+    let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+    let vkey_hash = checked_vkey_hash(&vk);
+    let network = network.clone().with_deadline(request.deadline_ms);
+    log!("Requesting Groth16 proof from mainnet (for on-chain verification)...");
+    let proof = timings::time(Stage::Prove, || network.prove_groth16(&client, &pk, &stdin)).unwrap_or_else(|e| panic!("{}", e));
+    output_proof_response(proof, start, &expected, vkey_hash, false, None);
 }
 
-fn run_proof_from_request_cpu(client: sp1_sdk::CpuProver, request: ProofRequest) {
-    let (stdin, start, expected_output_count) = build_inputs_from_request(&request);
-    let (pk, vk) = client.setup(ELF);
-    let vkey_hash = format!("0x{}", vk.bytes32());
-    eprintln!("Verification Key Hash: {}", vkey_hash);
-    let proof = client.prove(&pk, &stdin).run().expect("Failed to generate proof");
-    output_proof_response(proof, start, expected_output_count, vkey_hash, false);
+/// Build the CUDA prover, pinning it to `device` when given.
+///
+/// Returns `None` when the binary was built without the `cuda` feature or
+/// CUDA initialization fails, so the caller can fall back to the CPU prover.
+#[cfg(feature = "cuda")]
+fn build_gpu_client(device: Option<String>) -> Option<sp1_sdk::CudaProver> {
+    if let Some(device) = device {
+        log!("Using CUDA device {}", device);
+        std::env::set_var("CUDA_VISIBLE_DEVICES", device);
+    }
+    log!("Using CUDA Prover (Local GPU)");
+    match std::panic::catch_unwind(|| ProverClient::builder().cuda().build()) {
+        Ok(client) => Some(client),
+        Err(_) => {
+            log!("CUDA initialization failed");
+            None
+        }
+    }
 }
 
-fn run_proof_from_request_mock(client: sp1_sdk::CpuProver, request: ProofRequest) {
-    let (stdin, start, expected_output_count) = build_inputs_from_request(&request);
-    let (pk, vk) = client.setup(ELF);
-    let vkey_hash = format!("0x{}", vk.bytes32());
-    eprintln!("Verification Key Hash: {}", vkey_hash);
-    let proof = client.prove(&pk, &stdin).run().expect("Failed to generate proof");
-    output_proof_response(proof, start, expected_output_count, vkey_hash, true);
+#[cfg(not(feature = "cuda"))]
+fn build_gpu_client(_device: Option<String>) -> Option<std::convert::Infallible> {
+    log!("GPU backend requested but sp1-host was built without the `cuda` feature");
+    None
 }
 
-fn run_proof_from_request_network(client: sp1_sdk::NetworkProver, request: ProofRequest) {
-    let (stdin, start, expected_output_count) = build_inputs_from_request(&request);
-    let (pk, vk) = client.setup(ELF);
-    let vkey_hash = format!("0x{}", vk.bytes32());
-    eprintln!("Verification Key Hash: {}", vkey_hash);
-    eprintln!("Requesting Groth16 proof from mainnet (for on-chain verification)...");
-    let proof = client.prove(&pk, &stdin)
-        .strategy(FulfillmentStrategy::Auction)
-        .groth16()
-        .run()
-        .expect("Failed to generate proof");
-    output_proof_response(proof, start, expected_output_count, vkey_hash, false);
+#[cfg(feature = "cuda")]
+fn run_proof_from_request_gpu(client: sp1_sdk::CudaProver, request: ProofRequest) {
+    let (stdin, start, expected) = build_inputs_from_request(&request, false);
+    let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+    let vkey_hash = checked_vkey_hash(&vk);
+    let proof = timings::time(Stage::Prove, || client.prove(&pk, &stdin).groth16().run()).expect("Failed to generate proof");
+    output_proof_response(proof, start, &expected, vkey_hash, false, None);
+}
+
+#[cfg(not(feature = "cuda"))]
+fn run_proof_from_request_gpu(client: std::convert::Infallible, _request: ProofRequest) {
+    match client {}
+}
+
+/// Execute the guest to measure cycles, then prove locally or on the network
+fn run_proof_from_request_auto(policy: RoutingPolicy, request: ProofRequest) {
+    let (stdin, start, expected) = build_inputs_from_request(&request, false);
+
+    let cpu = ProverClient::builder().cpu().build();
+    let cycles = METERED_CYCLES.get().copied().unwrap_or_else(|| {
+        let (_, report) = timings::time(Stage::Execute, || cpu.execute(ELF, &stdin).run().expect("Failed to execute guest"));
+        report.total_instruction_count()
+    });
+    let decision = policy.decide(cycles);
+    log!(
+        "Routing: {} cycles (local max {}) -> {:?}",
+        decision.cycles, decision.local_max_cycles, decision.backend
+    );
+
+    match decision.backend {
+        Backend::Cpu => {
+            let (pk, vk) = timings::time(Stage::Setup, || cpu.setup(ELF));
+            let vkey_hash = checked_vkey_hash(&vk);
+            let proof = timings::time(Stage::CoreProve, || cpu.prove(&pk, &stdin).run().expect("Failed to generate proof"));
+            output_proof_response(proof, start, &expected, vkey_hash, false, Some(decision));
+        }
+        Backend::Network => {
+            let network = NetworkConfig::from_env_or_exit().with_deadline(request.deadline_ms);
+            let client = network.client();
+            // Rebuild stdin so derivable fields don't leave the machine
+            let (stdin, _, _) = build_inputs_from_request(&request, true);
+            let (pk, vk) = timings::time(Stage::Setup, || client.setup(ELF));
+            let vkey_hash = checked_vkey_hash(&vk);
+            let proof = timings::time(Stage::Prove, || network.prove_groth16(&client, &pk, &stdin)).unwrap_or_else(|e| panic!("{}", e));
+            output_proof_response(proof, start, &expected, vkey_hash, false, Some(decision));
+        }
+    }
 }
 
 /// Output proof as JSON to stdout (for prover-server to parse)
-fn output_proof_response(proof: SP1ProofWithPublicValues, start: std::time::Instant, expected_output_count: usize, vkey_hash: String, is_mock: bool) {
+fn output_proof_response(proof: SP1ProofWithPublicValues, start: std::time::Instant, expected: &ExpectedOutputs, vkey_hash: String, is_mock: bool, routing: Option<RoutingDecision>) {
+    let response = build_proof_response(proof, start, expected, vkey_hash, is_mock, routing);
+
+    // Output JSON to stdout (prover-server will parse this)
+    println!("{}", serde_json::to_string(&response).unwrap());
+}
+
+/// Decode and check a proof's public outputs and build the JSON response
+fn build_proof_response(proof: SP1ProofWithPublicValues, start: std::time::Instant, expected: &ExpectedOutputs, vkey_hash: String, is_mock: bool, routing: Option<RoutingDecision>) -> ProofResponse {
     let duration = start.elapsed();
-    eprintln!("Proof generated in {:?}!", duration);
+    log!("Proof generated in {:?}!", duration);
+    let encode_start = std::time::Instant::now();
 
     // IMPORTANT: Get raw public values bytes FIRST (for on-chain verification)
     // The SP1 verifier expects these exact bytes, not re-encoded!
     let public_values_raw = proof.public_values.to_vec();
     let public_values_hex = format!("0x{}", hex::encode(&public_values_raw));
 
-    // ABI-decode the public outputs (program commits ABI-encoded data)
-    let public_outputs = PublicOutputsSol::abi_decode(&public_values_raw, true)
+    // ABI-decode the public outputs (program commits ABI-encoded data).
+    // Compressed values are checked against the lists from the witness.
+    let compressed = is_compressed(&public_values_raw);
+    let public_outputs = decode_public_values(&public_values_raw, &expected.nullifiers, &expected.output_commitments)
         .expect("Failed to ABI-decode public outputs");
 
-    eprintln!("\n=== Public Outputs ===");
-    eprintln!("Old root: 0x{}", hex::encode(public_outputs.oldRoot.as_slice()));
-    eprintln!("Nullifiers: {}", public_outputs.nullifiers.len());
+    log!("\n=== Public Outputs{} ===", if compressed { " (compressed)" } else { "" });
+    log!("Chain: {}", public_outputs.chain_id);
+    log!("Old root: 0x{}", hex::encode(public_outputs.old_root));
+    log!("Nullifiers: {}", public_outputs.nullifiers.len());
     for (i, nullifier) in public_outputs.nullifiers.iter().enumerate() {
-        eprintln!("  [{}]: 0x{}", i, hex::encode(nullifier.as_slice()));
+        log!("  [{}]: 0x{}", i, hex::encode(nullifier));
     }
-    eprintln!("Output commitments: {}", public_outputs.outputCommitments.len());
-    for (i, commitment) in public_outputs.outputCommitments.iter().enumerate() {
-        eprintln!("  [{}]: 0x{}", i, hex::encode(commitment.as_slice()));
+    log!("Output commitments: {}", public_outputs.output_commitments.len());
+    for (i, commitment) in public_outputs.output_commitments.iter().enumerate() {
+        log!("  [{}]: 0x{}", i, hex::encode(commitment));
+    }
+    if let Some(withdrawal) = &public_outputs.withdrawal {
+        log!("Withdrawal: {} to {}", withdrawal.public_amount, withdrawal.recipient);
+    }
+    if let Some(external_nullifier) = &public_outputs.external_nullifier {
+        log!("External nullifier: 0x{} (scoped; not for the ledger)", hex::encode(external_nullifier));
     }
 
     // Verify expected outputs
+    assert_eq!(public_outputs.chain_id, expected.chain_id, "Chain binding mismatch");
     assert_eq!(
-        public_outputs.outputCommitments.len(),
-        expected_output_count,
-        "Output commitment count mismatch"
+        public_outputs.output_commitments,
+        expected.output_commitments,
+        "Output commitment mismatch"
     );
-
-    eprintln!("\nSUCCESS! Proof verified with {} outputs.", expected_output_count);
+    assert_eq!(public_outputs.withdrawal, expected.withdrawal, "Withdrawal mismatch");
+    assert_eq!(public_outputs.external_nullifier, expected.external_nullifier, "External nullifier mismatch");
+
+    match &expected.withdrawal {
+        Some(withdrawal) => log!(
+            "\nSUCCESS! Proof verified with {} outputs, withdrawing {}.",
+            expected.output_commitments.len(),
+            withdrawal.public_amount
+        ),
+        None => log!("\nSUCCESS! Proof verified with {} outputs.", expected.output_commitments.len()),
+    }
 
     // Get proof bytes
     let proof_bytes = if is_mock {
@@ -411,195 +825,55 @@ fn output_proof_response(proof: SP1ProofWithPublicValues, start: std::time::Inst
     } else {
         proof.bytes()
     };
+    let limits = chains::calldata_limits(public_outputs.chain_id);
+    check_calldata_size(&public_outputs, compressed, proof_bytes.len(), public_values_raw.len(), &limits)
+        .unwrap_or_else(|e| panic!("Refusing to return proof: {}", e));
     let proof_hex = format!("0x{}", hex::encode(&proof_bytes));
-
-    // Build response JSON and output to stdout
-    let response = ProofResponse {
+    foundry_fixture::emit(&public_outputs, &proof_hex, &public_values_hex, &vkey_hash, compressed, is_mock);
+    let artifacts = artifacts::upload_from_env(&proof_bytes, &public_values_raw, &vkey_hash, trace::trace_id(), compressed);
+    let expires_at = expiry::estimate_for_chain(public_outputs.chain_id, public_outputs.old_root);
+    let operator_signature = operator::sign_response(&public_outputs, &vkey_hash);
+    let predicted_new_root = predicted_root::predict_from_env(&public_outputs);
+    timings::record(Stage::Encode, encode_start.elapsed());
+    let timings = timings::take();
+    log!("Timings: {}", serde_json::to_string(&timings).unwrap());
+
+    ProofResponse {
         proof: proof_hex,
         public_values_raw: public_values_hex,  // Raw bytes for on-chain verification
-        public_outputs: PublicOutputsJson {
-            old_root: format!("0x{}", hex::encode(public_outputs.oldRoot.as_slice())),
-            nullifiers: public_outputs.nullifiers.iter()
-                .map(|n| format!("0x{}", hex::encode(n.as_slice())))
-                .collect(),
-            output_commitments: public_outputs.outputCommitments.iter()
-                .map(|c| format!("0x{}", hex::encode(c.as_slice())))
-                .collect(),
-        },
+        public_outputs,
         vkey_hash,
-    };
-
-    // Output JSON to stdout (prover-server will parse this)
-    println!("{}", serde_json::to_string(&response).unwrap());
-}
-
-// ============================================================================
-// DEMO MODE (for testing without frontend)
-// ============================================================================
-
-fn run_demo_cpu(client: sp1_sdk::CpuProver) {
-    let (stdin, start, expected_output_count) = setup_demo_transaction();
-    let (pk, vk) = client.setup(ELF);
-    let vkey_hash = format!("0x{}", vk.bytes32());
-    eprintln!("Verification Key Hash: {}", vkey_hash);
-    let proof = client.prove(&pk, &stdin).run().expect("Failed to generate proof");
-    finish_demo_proof(proof, start, expected_output_count);
-}
-
-fn run_demo_network(client: sp1_sdk::NetworkProver) {
-    let (stdin, start, expected_output_count) = setup_demo_transaction();
-    let (pk, vk) = client.setup(ELF);
-    let vkey_hash = format!("0x{}", vk.bytes32());
-    eprintln!("Verification Key Hash: {}", vkey_hash);
-    eprintln!("Requesting Groth16 proof from mainnet (for on-chain verification)...");
-    let proof = client.prove(&pk, &stdin)
-        .strategy(FulfillmentStrategy::Auction)
-        .groth16()
-        .run()
-        .expect("Failed to generate proof");
-    finish_demo_proof(proof, start, expected_output_count);
-}
-
-/// Set up a demo transaction with precomputed values
-fn setup_demo_transaction() -> (SP1Stdin, std::time::Instant, usize) {
-    // Create a demo private key (32 bytes)
-    let alice_privkey: [u8; 32] = [
-        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
-        0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10,
-        0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18,
-        0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f, 0x20,
-    ];
-
-    let bob_privkey: [u8; 32] = [
-        0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28,
-        0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e, 0x2f, 0x30,
-        0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38,
-        0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f, 0x40,
-    ];
-
-    let alice_owner: [u8; 32] = {
-        let mut owner = [0u8; 32];
-        owner.copy_from_slice(&alice_privkey);
-        owner
-    };
-
-    let bob_owner: [u8; 32] = {
-        let mut owner = [0u8; 32];
-        owner.copy_from_slice(&bob_privkey);
-        owner
-    };
-
-    let alice_input_note = Note {
-        amount: 100,
-        owner_pubkey: alice_owner,
-        blinding: [0x42; 32],
-    };
-
-    let bob_output_note = Note {
-        amount: 50,
-        owner_pubkey: bob_owner,
-        blinding: [0x43; 32],
-    };
-
-    let alice_change_note = Note {
-        amount: 50,
-        owner_pubkey: alice_owner,
-        blinding: [0x44; 32],
-    };
-
-    let mut ledger = Ledger::new();
-    let alice_index = ledger.add_note(alice_input_note.clone());
-    let old_root = ledger.current_root();
-
-    eprintln!("Transaction: Alice (100) -> Bob (50) + Change (50)");
-    eprintln!("Input note index: {}", alice_index);
-    eprintln!("Old root: 0x{}", hex::encode(&old_root[..8]));
-
-    let dummy_sig = [0u8; 65];
-
-    let witness = Witness::new_without_proofs(
-        vec![alice_input_note],
-        vec![alice_index as usize],
-        vec![dummy_sig.to_vec()], // Dummy NullifierSig
-        vec![dummy_sig.to_vec()], // Dummy TxSig
-        vec![bob_output_note, alice_change_note],
-    );
-
-    eprintln!("Precomputing nullifiers and commitments on host...");
-    let witness = witness.with_precomputed_values();
-
-    eprintln!("  Precomputed {} nullifiers", witness.precomputed_nullifiers.len());
-    eprintln!("  Precomputed {} input commitments", witness.precomputed_input_commitments.len());
-    eprintln!("  Precomputed {} output commitments", witness.precomputed_output_commitments.len());
-
-    let public_inputs = PublicInputs { old_root };
-    let expected_output_count = witness.output_notes.len();
-
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&public_inputs);
-    stdin.write(&witness);
-
-    eprintln!("\nGenerating ZK proof (optimized path)...");
-    (stdin, std::time::Instant::now(), expected_output_count)
-}
-
-fn finish_demo_proof(proof: SP1ProofWithPublicValues, start: std::time::Instant, expected_output_count: usize) {
-    let duration = start.elapsed();
-    eprintln!("Proof generated in {:?}!", duration);
-
-    // ABI-decode the public outputs (program commits ABI-encoded data)
-    let public_values_raw = proof.public_values.to_vec();
-    let public_outputs = PublicOutputsSol::abi_decode(&public_values_raw, true)
-        .expect("Failed to ABI-decode public outputs");
-
-    eprintln!("\n=== Public Outputs ===");
-    eprintln!("Old root: 0x{}", hex::encode(&public_outputs.oldRoot.as_slice()[..8]));
-    eprintln!("Nullifiers: {}", public_outputs.nullifiers.len());
-    for (i, nullifier) in public_outputs.nullifiers.iter().enumerate() {
-        eprintln!("  [{}]: 0x{}", i, hex::encode(&nullifier.as_slice()[..8]));
+        compressed,
+        cycles: routing.as_ref().map(|r| r.cycles).or_else(|| METERED_CYCLES.get().copied()),
+        routing,
+        trace_id: trace::trace_id(),
+        artifacts,
+        expires_at,
+        timings,
+        network_attempts: network::take_attempts(),
+        operator_signature,
+        unverified_root: root_check::unverified(),
+        linkability: LINKABILITY.get().cloned(),
+        predicted_new_root,
     }
-    eprintln!("Output commitments: {}", public_outputs.outputCommitments.len());
-    for (i, commitment) in public_outputs.outputCommitments.iter().enumerate() {
-        eprintln!("  [{}]: 0x{}", i, hex::encode(&commitment.as_slice()[..8]));
-    }
-
-    let proof_bytes = proof.bytes();
-    eprintln!("\nProof hex: 0x{}", hex::encode(&proof_bytes[..64.min(proof_bytes.len())]));
-    eprintln!("Proof length: {} bytes", proof_bytes.len());
-
-    assert_eq!(
-        public_outputs.outputCommitments.len(),
-        expected_output_count,
-        "Output commitment count mismatch"
-    );
-
-    eprintln!("\nSUCCESS! Proof verified with {} outputs.", expected_output_count);
 }
 
 // Helpers
 
-fn hex_to_bytes65(hex_str: &str) -> [u8; 65] {
-    let clean = if hex_str.starts_with("0x") { &hex_str[2..] } else { hex_str };
-    let bytes = hex::decode(clean).expect("Invalid hex for signature");
-    let mut arr = [0u8; 65];
-    arr.copy_from_slice(&bytes);
-    arr
+/// Value following `flag` on the command line (`--flag value`)
+/// The vkey's hash as returned in `ProofResponse`, logged; refuses revoked
+/// programs (see `revocation.rs`).
+fn checked_vkey_hash(vk: &SP1VerifyingKey) -> String {
+    let vkey_hash = format!("0x{}", vk.bytes32());
+    log!("Verification Key Hash: {}", vkey_hash);
+    revocation::refuse_revoked_vkey(&vkey_hash);
+    vkey_hash
 }
 
-fn hex_to_bytes32(hex_str: &str) -> [u8; 32] {
-    let clean = if hex_str.starts_with("0x") { &hex_str[2..] } else { hex_str };
-    let bytes = hex::decode(clean).expect("Invalid hex for root");
-    let mut arr = [0u8; 32];
-    arr.copy_from_slice(&bytes);
-    arr
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
 }
 
-fn note_from_data(data: &NoteData) -> Note {
-    let owner = hex_to_bytes32(&data.owner_pubkey);
-    let blinding = hex_to_bytes32(&data.blinding);
-    Note {
-        amount: data.amount,
-        owner_pubkey: owner,
-        blinding,
-    }
-}
@@ -0,0 +1,112 @@
+//! `migrate-tree` subcommand: migrate the ledger's tree to a new hash scheme
+//!
+//! # Usage
+//! sp1-host migrate-tree --scheme <keccak|blake3> [--domain <string>] --out <dir>
+//!     [--contract <address>] [--rpc-url <url>] [--from-block <n>] [--block <n>]
+//!     [--log-chunk <blocks>]
+//! sp1-host migrate-tree --verify <dir>
+//!
+//! Replays the ledger's `OutputCommitted` and `NullifierUsed` events up to
+//! `--block` (default the chain head), checks that they rebuild the
+//! contract's `currentRoot` at that block, and migrates the leaves and spent
+//! set (see core's `migration`). Writes `<dir>/mapping.json`, each leaf's
+//! old and new value, `<dir>/spent.json`, the spent nullifiers, and
+//! `<dir>/bundle.json`, the source tree, scheme, mapping digest, genesis
+//! root and spent-set digests, and prints the bundle. `--verify` re-checks a
+//! written migration offline, recomputing both roots from the mapping and
+//! the spent-set digests from `spent.json`.
+//!
+//! The new deployment is initialized with the bundle's genesis root and
+//! must be seeded with every nullifier in `spent.json` (or, in SMT mode, the
+//! bundle's `spent.nullifierRoot`) before it accepts proofs; otherwise notes
+//! spent on the old ledger can be spent again. Proving against it needs a
+//! guest built for the same scheme.
+
+use std::path::Path;
+
+use alloy_sol_types::sol;
+use ghostclaw_core::chains::ChainDeployment;
+use ghostclaw_core::migration::{migrate, verify_migration, HashScheme, LeafMapping, MigrationBundle};
+use ghostclaw_core::NullifierSnapshot;
+
+use crate::daemon::write_atomic;
+use crate::reconcile::{call, replay_events};
+use crate::rpc;
+
+sol! {
+    function currentRoot() external view returns (bytes32);
+}
+
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    crate::flag_value(args, flag).map(|v| v.parse().unwrap_or_else(|_| panic!("Invalid {}: {}", flag, v)))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> T {
+    let data = std::fs::read(path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+    serde_json::from_slice(&data).unwrap_or_else(|e| panic!("Invalid {}: {}", path.display(), e))
+}
+
+fn verify(dir: &Path) {
+    let bundle: MigrationBundle = read_json(&dir.join("bundle.json"));
+    let mapping: Vec<LeafMapping> = read_json(&dir.join("mapping.json"));
+    let spent: NullifierSnapshot = read_json(&dir.join("spent.json"));
+    verify_migration(&bundle, &mapping, &spent).unwrap_or_else(|e| panic!("Migration is invalid: {}", e));
+    log!(
+        "Migration of {} leaves and {} spent nullifiers from root {} to genesis root {} verified",
+        mapping.len(),
+        spent.count,
+        bundle.source.root,
+        bundle.genesis_root
+    );
+}
+
+pub fn run(args: &[String]) {
+    if let Some(dir) = crate::flag_value(args, "--verify") {
+        return verify(Path::new(&dir));
+    }
+
+    let scheme_name = crate::flag_value(args, "--scheme").expect("Pass --scheme <keccak|blake3>");
+    let scheme = HashScheme::parse(&scheme_name, crate::flag_value(args, "--domain").as_deref())
+        .unwrap_or_else(|e| panic!("{}", e));
+    let out = crate::flag_value(args, "--out").expect("Pass --out <dir>");
+    let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+    let contract = crate::flag_value(args, "--contract")
+        .or_else(rpc::ledger_contract_from_env)
+        .expect("Pass --contract <address> or set LEDGER_CONTRACT");
+    let manifest = crate::deploy::manifest_from_env().and_then(Result::ok);
+    let from_block = parse_flag(args, "--from-block")
+        .or_else(|| manifest.as_ref().and_then(|m| m.deploy_block))
+        .unwrap_or(0);
+    let chunk = parse_flag(args, "--log-chunk").unwrap_or(10_000u64).max(1);
+    let block = parse_flag(args, "--block").unwrap_or_else(|| rpc::block_number(&rpc_url).expect("Failed to query chain head"));
+    let chain_id = rpc::chain_id(&rpc_url).expect("Failed to query chain ID");
+
+    log!("Reading ledger {} at block {}...", contract, block);
+    let root = call(&rpc_url, &contract, &currentRootCall {}, block)
+        .unwrap_or_else(|e| panic!("Failed to read currentRoot: {}", e))
+        ._0
+        .0;
+    let replay = replay_events(&rpc_url, &contract, from_block, block, chunk).unwrap_or_else(|e| panic!("{}", e));
+
+    let deployment = ChainDeployment { chain_id, ledger: contract };
+    let (mapping, bundle) = migrate(deployment, block, root, &replay.leaves, &replay.nullifiers, scheme)
+        .unwrap_or_else(|e| panic!("Refusing to migrate: {}; replay from an earlier --from-block", e));
+
+    let dir = Path::new(&out);
+    std::fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Failed to create {}: {}", dir.display(), e));
+    for (name, json) in [
+        ("mapping.json", serde_json::to_vec(&mapping).unwrap()),
+        ("spent.json", serde_json::to_vec(&replay.nullifiers.snapshot()).unwrap()),
+        ("bundle.json", serde_json::to_vec_pretty(&bundle).unwrap()),
+    ] {
+        write_atomic(&dir.join(name), &json).unwrap_or_else(|e| panic!("{}", e));
+    }
+    log!(
+        "Migrated {} leaves and {} spent nullifiers; genesis root {}",
+        mapping.len(),
+        bundle.spent.count,
+        bundle.genesis_root
+    );
+    log!("Seed the new deployment with spent.json before it accepts proofs");
+    println!("{}", serde_json::to_string_pretty(&bundle).unwrap());
+}
@@ -0,0 +1,112 @@
+//! `minimize` subcommand: shrink a failing request to a shareable fixture
+//!
+//! # Usage
+//! sp1-host minimize <request.json> [--out <fixture.json>] [--execute]
+//!
+//! If the shared checks (`simulate_witness`) reject the request, inputs and
+//! outputs are dropped while the same failure reproduces. With `--execute`,
+//! a request the checks accept but the guest rejects is minimized against
+//! guest execution instead. The `FailureFixture` JSON goes to `--out` (or
+//! stdout); it's the witness to attach to a bug report.
+//!
+//! Setting FAILURE_FIXTURE_DIR makes the prover write the same fixture
+//! automatically whenever pre-flight rejects a request.
+
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+use sp1_sdk::{ProverClient, SP1Stdin};
+use ghostclaw_core::minimize::{failure_class, minimize_simulation_failure, minimize_witness, FailureFixture};
+use ghostclaw_core::{Bytes32, GuestInput, PublicInputs, Witness};
+
+use crate::{build_witness_from_request, ProofRequest, ELF};
+
+/// Execute the guest on `witness`, returning its failure (if any).
+fn guest_failure(witness: &Witness, old_root: [u8; 32]) -> Option<String> {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(PublicInputs::new(old_root), witness.clone()));
+    ProverClient::builder().cpu().build().execute(ELF, &stdin).run().err().map(|e| e.to_string())
+}
+
+/// Minimize a witness the guest rejects, keeping its failure reason.
+fn minimize_guest_failure(witness: &Witness, old_root: [u8; 32]) -> Option<FailureFixture> {
+    let failure = guest_failure(witness, old_root)?;
+    let class = failure_class(&failure);
+    let minimized = minimize_witness(witness, |candidate| {
+        log!("  Trying {} inputs, {} outputs...", candidate.input_count(), candidate.output_count());
+        guest_failure(candidate, old_root).is_some_and(|e| failure_class(&e) == class)
+    });
+    Some(FailureFixture {
+        failure,
+        old_root: Bytes32(old_root),
+        original_inputs: witness.input_count(),
+        original_outputs: witness.output_count(),
+        witness: minimized,
+    })
+}
+
+/// Write the minimized fixture to FAILURE_FIXTURE_DIR (if set), named by
+/// trace ID or content hash. Called from pre-flight before it rejects a request.
+pub fn write_fixture_from_env(witness: &Witness, old_root: [u8; 32]) {
+    let Ok(dir) = std::env::var("FAILURE_FIXTURE_DIR") else {
+        return;
+    };
+    if crate::delegated::is_sealed() {
+        log!("Not writing a failure fixture for a sealed request");
+        return;
+    }
+    let Some(fixture) = minimize_simulation_failure(witness, old_root) else {
+        return;
+    };
+    let json = serde_json::to_string_pretty(&fixture).unwrap();
+    let name = crate::trace::trace_id()
+        .unwrap_or_else(|| hex::encode(&Sha256::digest(json.as_bytes())[..8]));
+    let path = Path::new(&dir).join(format!("failure-{}.json", name));
+    match std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, json)) {
+        Ok(()) => log!(
+            "Minimized failure fixture ({} -> {} inputs, {} -> {} outputs) written to {}",
+            fixture.original_inputs,
+            fixture.witness.input_count(),
+            fixture.original_outputs,
+            fixture.witness.output_count(),
+            path.display()
+        ),
+        Err(e) => log!("Failed to write failure fixture to {}: {}", path.display(), e),
+    }
+}
+
+/// Entry point for the `minimize` subcommand.
+pub fn run(args: &[String]) {
+    let path = args.get(2).expect("Usage: sp1-host minimize <request.json> [--out <fixture.json>] [--execute]");
+    let json = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+    let request: ProofRequest = serde_json::from_str(&json).unwrap_or_else(|e| panic!("Invalid request {}: {}", path, e));
+
+    let witness = build_witness_from_request(&request);
+    witness.validate_structure().unwrap_or_else(|e| panic!("Malformed witness: {}", e));
+    let old_root = request.old_root.0;
+
+    log!("Minimizing {} inputs, {} outputs...", witness.input_count(), witness.output_count());
+    let fixture = match minimize_simulation_failure(&witness, old_root) {
+        Some(fixture) => fixture,
+        None if args.contains(&"--execute".to_string()) => {
+            log!("Shared checks accept the request; minimizing against guest execution");
+            minimize_guest_failure(&witness, old_root).unwrap_or_else(|| panic!("The guest accepts this request"))
+        }
+        None => panic!("Shared checks accept this request (pass --execute to minimize a guest-only failure)"),
+    };
+    log!("Failure: {}", fixture.failure);
+    log!(
+        "Reduced to {} inputs, {} outputs",
+        fixture.witness.input_count(),
+        fixture.witness.output_count()
+    );
+
+    let json = serde_json::to_string_pretty(&fixture).unwrap();
+    match crate::flag_value(args, "--out") {
+        Some(out) => {
+            std::fs::write(&out, json).unwrap_or_else(|e| panic!("Failed to write {}: {}", out, e));
+            log!("Fixture written to {}", out);
+        }
+        None => println!("{}", json),
+    }
+}
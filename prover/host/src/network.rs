@@ -0,0 +1,314 @@
+//! Prover network request configuration
+//!
+//! Every network proof (single, batch, auto-escalated) is requested through
+//! `NetworkConfig`, so operators tune cost and latency per deployment:
+//!
+//! - `PROVER_NETWORK_RPC`: network RPC endpoint
+//! - `SP1_FULFILLMENT_STRATEGY`: `auction` (default), `hosted` or `reserved`
+//! - `SP1_MAX_PRICE_PER_PGU`: highest bid accepted, per prover gas unit
+//! - `SP1_AUCTION_TIMEOUT_SECS`: give up if no prover wins the auction
+//! - `SP1_PROOF_TIMEOUT_SECS`: give up if the proof isn't fulfilled in time
+//! - `SP1_PROVER_WHITELIST`: comma-separated prover addresses allowed to bid
+//! - `SP1_RETRY_MAX_ATTEMPTS`: tries per proof, including the first (default 3)
+//! - `SP1_RETRY_BASE_MS` / `SP1_RETRY_MAX_MS`: first and largest backoff
+//!   (default 5000 / 60000)
+//!
+//! Transient failures (auction not filled, timeouts, rate limiting, the RPC
+//! being unreachable) are retried with exponential backoff and jitter;
+//! anything else (e.g. an unexecutable program or insufficient balance)
+//! fails at once. Every failed attempt is logged and reported in the
+//! response's `networkAttempts`, which the prover-server keeps in the job.
+//!
+//! A request's `deadlineMs` (Unix time) caps the auction and proof timeouts
+//! at the time left, and no retry starts that couldn't finish before it.
+//! Running out of time fails with code `DEADLINE_EXCEEDED`, noting how far
+//! proving got, instead of waiting for a proof nobody will submit.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sp1_sdk::network::FulfillmentStrategy;
+use sp1_sdk::{NetworkProver, Prover, ProverClient, SP1ProofMode, SP1ProofWithPublicValues, SP1ProvingKey, SP1Stdin};
+
+pub use ghostclaw_prover_client::response::{FailureKind, NetworkAttempt, DEADLINE_EXCEEDED};
+
+pub const DEFAULT_NETWORK_RPC: &str = "https://rpc.mainnet.succinct.xyz";
+
+/// Failed attempts of the proofs requested by this process (one request,
+/// or a batch's transactions in order)
+static ATTEMPTS: Mutex<Vec<NetworkAttempt>> = Mutex::new(Vec::new());
+
+/// Failed attempts recorded so far, clearing the record.
+pub fn take_attempts() -> Vec<NetworkAttempt> {
+    std::mem::take(&mut *ATTEMPTS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// How often and how patiently transient failures are retried.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Tries per proof, including the first
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, base_delay: Duration::from_secs(5), max_delay: Duration::from_secs(60) }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff after failed attempt `attempt` (1-based): the base delay
+    /// doubled per attempt, capped, then scaled by a random 50-100% so
+    /// hosts that failed together don't retry together.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(1u32 << attempt.saturating_sub(1).min(16));
+        let capped = exponential.min(self.max_delay);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+        capped.mul_f64(0.5 + f64::from(nanos % 1000) / 2000.0)
+    }
+}
+
+/// How the network fulfills a request.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Provers bid; cheapest eligible bid wins
+    Auction,
+    /// Succinct-hosted provers
+    Hosted,
+    /// Capacity reserved for this requester
+    Reserved,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "auction" => Ok(Strategy::Auction),
+            "hosted" => Ok(Strategy::Hosted),
+            "reserved" => Ok(Strategy::Reserved),
+            other => Err(format!("Unknown fulfillment strategy {:?} (auction, hosted, reserved)", other)),
+        }
+    }
+}
+
+impl From<Strategy> for FulfillmentStrategy {
+    fn from(strategy: Strategy) -> Self {
+        match strategy {
+            Strategy::Auction => FulfillmentStrategy::Auction,
+            Strategy::Hosted => FulfillmentStrategy::Hosted,
+            Strategy::Reserved => FulfillmentStrategy::Reserved,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkConfig {
+    pub rpc_url: String,
+    pub strategy: Strategy,
+    pub max_price_per_pgu: Option<u64>,
+    pub auction_timeout: Option<Duration>,
+    pub proof_timeout: Option<Duration>,
+    /// Prover addresses (0x-hex); empty means any prover may bid
+    pub whitelist: Vec<String>,
+    pub retry: RetryPolicy,
+    /// Unix time (ms) the request must be proven by (`deadlineMs`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: DEFAULT_NETWORK_RPC.to_string(),
+            strategy: Strategy::Auction,
+            max_price_per_pgu: None,
+            auction_timeout: None,
+            proof_timeout: None,
+            whitelist: Vec::new(),
+            retry: RetryPolicy::default(),
+            deadline_ms: None,
+        }
+    }
+}
+
+fn env_parsed<T: FromStr>(name: &str) -> Result<Option<T>, String>
+where
+    T::Err: Display,
+{
+    match std::env::var(name) {
+        Ok(v) if !v.trim().is_empty() => v.trim().parse().map(Some).map_err(|e| format!("Invalid {}: {}", name, e)),
+        _ => Ok(None),
+    }
+}
+
+fn parse_all<T: FromStr>(items: &[String]) -> Result<Vec<T>, String>
+where
+    T::Err: Display,
+{
+    items
+        .iter()
+        .map(|s| s.parse().map_err(|e| format!("Invalid prover address {:?}: {}", s, e)))
+        .collect()
+}
+
+impl NetworkConfig {
+    /// Read the configuration from the environment.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            rpc_url: env_parsed("PROVER_NETWORK_RPC")?.unwrap_or(defaults.rpc_url),
+            strategy: env_parsed("SP1_FULFILLMENT_STRATEGY")?.unwrap_or(defaults.strategy),
+            max_price_per_pgu: env_parsed("SP1_MAX_PRICE_PER_PGU")?,
+            auction_timeout: env_parsed("SP1_AUCTION_TIMEOUT_SECS")?.map(Duration::from_secs),
+            proof_timeout: env_parsed("SP1_PROOF_TIMEOUT_SECS")?.map(Duration::from_secs),
+            whitelist: std::env::var("SP1_PROVER_WHITELIST")
+                .map(|v| v.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default(),
+            retry: RetryPolicy {
+                max_attempts: env_parsed::<u32>("SP1_RETRY_MAX_ATTEMPTS")?.unwrap_or(defaults.retry.max_attempts).max(1),
+                base_delay: env_parsed("SP1_RETRY_BASE_MS")?.map(Duration::from_millis).unwrap_or(defaults.retry.base_delay),
+                max_delay: env_parsed("SP1_RETRY_MAX_MS")?.map(Duration::from_millis).unwrap_or(defaults.retry.max_delay),
+            },
+            deadline_ms: None,
+        })
+    }
+
+    /// `from_env`, panicking with the error if the configuration is invalid.
+    pub fn from_env_or_exit() -> Self {
+        Self::from_env().unwrap_or_else(|e| panic!("Invalid network prover configuration: {}", e))
+    }
+
+    /// This config bounded by a request's `deadlineMs`.
+    pub fn with_deadline(mut self, deadline_ms: Option<u64>) -> Self {
+        self.deadline_ms = deadline_ms;
+        self
+    }
+
+    /// Time left before the deadline (zero once it has passed); `None`
+    /// without one.
+    fn time_left(&self) -> Option<Duration> {
+        let deadline = UNIX_EPOCH + Duration::from_millis(self.deadline_ms?);
+        Some(deadline.duration_since(SystemTime::now()).unwrap_or_default())
+    }
+
+    /// The `DEADLINE_EXCEEDED` failure, noting how far proving got.
+    fn deadline_exceeded(&self, attempts: u32, started: Instant) -> String {
+        crate::trace::set_error_code(DEADLINE_EXCEEDED);
+        let progress = match attempts {
+            0 => "before requesting a proof (witness built, keys set up)".to_string(),
+            n => format!("after {} network attempt(s) over {:?}", n, started.elapsed()),
+        };
+        format!("Deadline exceeded: gave up on the network proof {}", progress)
+    }
+
+    pub fn client(&self) -> NetworkProver {
+        log!("Using Network Prover (RPC: {}, strategy {:?})", self.rpc_url, self.strategy);
+        ProverClient::builder().network().rpc_url(&self.rpc_url).build()
+    }
+
+    /// Request a Groth16 proof (for on-chain verification) under this config.
+    pub fn prove_groth16(
+        &self,
+        client: &NetworkProver,
+        pk: &SP1ProvingKey,
+        stdin: &SP1Stdin,
+    ) -> Result<SP1ProofWithPublicValues, String> {
+        self.prove(client, pk, stdin, SP1ProofMode::Groth16)
+    }
+
+    /// Request a proof of any kind under this config, retrying transient
+    /// failures per `retry` until the deadline, if any.
+    pub fn prove(
+        &self,
+        client: &NetworkProver,
+        pk: &SP1ProvingKey,
+        stdin: &SP1Stdin,
+        mode: SP1ProofMode,
+    ) -> Result<SP1ProofWithPublicValues, String> {
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            if self.time_left() == Some(Duration::ZERO) {
+                return Err(self.deadline_exceeded(attempt - 1, started));
+            }
+            let error = match self.prove_once(client, pk, stdin, mode) {
+                Ok(proof) => return Ok(proof),
+                Err(e) => e,
+            };
+            let kind = FailureKind::classify(&error);
+            let retry_in = (kind.is_transient() && attempt < self.retry.max_attempts).then(|| self.retry.delay(attempt));
+            // A transient failure is the deadline's doing once no time is
+            // left, or too little to wait out the backoff
+            let past_deadline = kind.is_transient()
+                && self.time_left().is_some_and(|left| retry_in.map_or(left.is_zero(), |delay| delay >= left));
+            let retry_in = retry_in.filter(|_| !past_deadline);
+            log!(
+                "Network proof attempt {}/{} failed ({:?}): {}",
+                attempt,
+                self.retry.max_attempts,
+                kind,
+                error
+            );
+            ATTEMPTS.lock().unwrap_or_else(|e| e.into_inner()).push(NetworkAttempt {
+                attempt,
+                kind,
+                error: error.clone(),
+                retry_in_ms: retry_in.map(|d| d.as_millis() as u64),
+            });
+            match retry_in {
+                Some(delay) => {
+                    log!("Retrying in {:?}", delay);
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                None if past_deadline => return Err(self.deadline_exceeded(attempt, started)),
+                None if kind.is_transient() => {
+                    return Err(format!("{} (gave up after {} attempts)", error, attempt));
+                }
+                None => return Err(error),
+            }
+        }
+    }
+
+    /// A single proof request under this config.
+    fn prove_once(
+        &self,
+        client: &NetworkProver,
+        pk: &SP1ProvingKey,
+        stdin: &SP1Stdin,
+        mode: SP1ProofMode,
+    ) -> Result<SP1ProofWithPublicValues, String> {
+        let mut request = client.prove(pk, stdin).strategy(self.strategy.into()).mode(mode);
+        if let Some(price) = self.max_price_per_pgu {
+            request = request.max_price_per_pgu(price);
+        }
+        let left = self.time_left();
+        if let Some(timeout) = capped(self.auction_timeout, left) {
+            request = request.auction_timeout(timeout);
+        }
+        if let Some(timeout) = capped(self.proof_timeout, left) {
+            request = request.timeout(timeout);
+        }
+        if !self.whitelist.is_empty() {
+            request = request.whitelist(Some(parse_all(&self.whitelist)?));
+        }
+        request.run().map_err(|e| format!("Network proof failed: {}", e))
+    }
+}
+
+/// The shorter of a configured timeout and the time left before the deadline.
+fn capped(timeout: Option<Duration>, left: Option<Duration>) -> Option<Duration> {
+    match (timeout, left) {
+        (Some(timeout), Some(left)) => Some(timeout.min(left)),
+        (timeout, left) => timeout.or(left),
+    }
+}
@@ -0,0 +1,47 @@
+//! Operator signatures on proof responses (`operatorSignature`)
+//!
+//! With OPERATOR_KEY set to a file holding the operator's secp256k1 key
+//! (hex), every `ProofResponse` is signed over the
+//! hash of the request as it arrived, its public outputs and the vkey hash
+//! (see core's `response_signature`). Relayers and wallets that pin the
+//! operator key can then reject responses that were swapped or replayed
+//! between the host and them.
+//!
+//! The request is hashed exactly as received: the stdin line (sealed or
+//! not) or the batch file, so a client can hash what it sent.
+
+use std::sync::OnceLock;
+
+use ghostclaw_core::response_signature::request_hash;
+use ghostclaw_core::{Bytes32, PublicOutputs, ResponseSignature};
+
+/// Hash of the request being served (one per process)
+static REQUEST_HASH: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Record the request being served, as received.
+pub fn record_request(raw: &str) {
+    let _ = REQUEST_HASH.set(request_hash(raw.as_bytes()));
+}
+
+fn load_operator_key(path: &str) -> Result<[u8; 32], String> {
+    let hex = std::fs::read_to_string(path).map_err(|e| format!("Failed to read operator key {}: {}", path, e))?;
+    let secret: Bytes32 = hex.trim().parse().map_err(|_| format!("Invalid operator key {}", path))?;
+    Ok(secret.0)
+}
+
+/// The key in OPERATOR_KEY; `None` when it isn't set.
+pub fn operator_key() -> Option<[u8; 32]> {
+    let path = std::env::var("OPERATOR_KEY").ok()?;
+    Some(load_operator_key(&path).unwrap_or_else(|e| panic!("{}", e)))
+}
+
+/// Sign a response with OPERATOR_KEY; `None` when it isn't set.
+pub fn sign_response(outputs: &PublicOutputs, vkey_hash: &str) -> Option<ResponseSignature> {
+    let key = operator_key()?;
+    let request_hash = *REQUEST_HASH.get().expect("No request recorded to sign a response for");
+    let vkey_hash: Bytes32 = vkey_hash.parse().unwrap_or_else(|e| panic!("Invalid vkey hash {}: {}", vkey_hash, e));
+    let signature = ResponseSignature::sign(&key, request_hash, outputs, &vkey_hash.0)
+        .unwrap_or_else(|e| panic!("Failed to sign response: {}", e));
+    log!("Signed response as operator {} (request {})", signature.operator, signature.request_hash);
+    Some(signature)
+}
@@ -0,0 +1,64 @@
+//! Predicted post-submission root (`predictedNewRoot` in `ProofResponse`)
+//!
+//! With INDEXER_STATE_URL set (the indexer's `/state`, as `reconcile` reads
+//! it), the host rebuilds the ledger's state from the indexer's leaves and
+//! nullifiers and checks the proof's outputs against it with core's
+//! `ChainState::verify_transition`, the same check the indexer applies to
+//! transactions it observes: the old root is one the ledger has had, no
+//! nullifier is spent, and the output commitments are inserted the way the
+//! contract does (`MerkleTree::predict_contract_root`: one
+//! `_insertCommitment` per output, in `PublicOutputs` order, with
+//! MerkleTree.sol's zero table). The result is the root the ledger will have
+//! if this transaction is the next one to insert leaves, so a relayer can
+//! compare it with the last `RootUpdated` its transaction emitted. A mismatch means another transaction landed
+//! first, the indexer lagged, or the contract inserted something it
+//! shouldn't have; the relayer should re-derive the root from events before
+//! trusting either.
+//!
+//! Omitted for scoped proofs (`externalNullifier`), which never touch the
+//! ledger's tree, when the state can't be read, has no leaf list, or its
+//! leaves don't hash to its root, and when the transition wouldn't apply
+//! (logged with the reason, e.g. a nullifier the indexer has seen spent).
+
+use ghostclaw_core::chains::{ChainDeployment, ChainState};
+use ghostclaw_core::{Bytes32, PublicOutputs};
+
+use crate::reconcile::IndexerSnapshot;
+
+/// The ledger root after `outputs` are inserted, from INDEXER_STATE_URL.
+pub fn predict_from_env(outputs: &PublicOutputs) -> Option<Bytes32> {
+    if outputs.external_nullifier.is_some() {
+        return None;
+    }
+    let url = std::env::var("INDEXER_STATE_URL").ok()?;
+    match predict(&url, outputs) {
+        Ok(root) => {
+            log!("Predicted new root: {}", root);
+            Some(root)
+        }
+        Err(e) => {
+            log!("No predicted root: {}", e);
+            None
+        }
+    }
+}
+
+fn predict(url: &str, outputs: &PublicOutputs) -> Result<Bytes32, String> {
+    let snapshot: IndexerSnapshot = reqwest::blocking::get(url)
+        .and_then(|r| r.json())
+        .map_err(|e| format!("Failed to fetch indexer state from {}: {}", url, e))?;
+    let leaves = snapshot.leaves.ok_or("Indexer state has no leaf list")?;
+    // The address only labels the state; the indexer serves one deployment
+    let ledger = crate::chains::deployment_for(outputs.chain_id)?.map_or_else(String::new, |d| d.ledger);
+    let mut state = ChainState::new(ChainDeployment { chain_id: outputs.chain_id, ledger });
+    for leaf in &leaves {
+        state.insert_commitment(leaf.0);
+    }
+    for nullifier in &snapshot.nullifiers {
+        state.spend(nullifier.0)?;
+    }
+    if state.root() != snapshot.root.0 {
+        return Err(format!("Indexer leaves hash to {}, not its root {}", Bytes32(state.root()), snapshot.root));
+    }
+    Ok(Bytes32(state.verify_transition(outputs)?.new_root))
+}
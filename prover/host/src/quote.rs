@@ -0,0 +1,89 @@
+//! `quote` subcommand: relaying cost of a transaction shape
+//!
+//! # Usage
+//! sp1-host quote --inputs <n> --outputs <n> [--withdrawal] [--proof groth16|plonk]
+//!     --base-fee <wei> [--priority-fee <wei>]
+//!
+//! Prints a `FeeQuote` as JSON (see core's `fee_market`); the prover-server
+//! serves it as `GET /api/quote` with the chain's current fees.
+//!
+//! - `FEE_TOKEN_PER_ETH`: shielded token base units worth 1 ETH (required)
+//! - `FEE_MARGIN_BPS`: relayer margin over the gas cost (default 1000 = 10%)
+//! - `FEE_PRIORITY_WEI`: priority fee when `--priority-fee` isn't given
+//!   (default 1 gwei)
+//! - `VERIFY_GAS_BENCH`: a `bench-proofs` JSON report; its measured verify
+//!   gas replaces the defaults per proof type
+//! - `LEDGER_GAS`: JSON `LedgerGas` overriding the execution estimates
+
+use serde::Deserialize;
+use ghostclaw_core::fee_market::{FeeMarket, LedgerGas, ProofType, VerifyGas};
+
+/// The fields of a `bench-proofs` row the quote needs.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BenchRow {
+    system: String,
+    verify_gas: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct BenchReport {
+    rows: Vec<BenchRow>,
+}
+
+fn verify_gas_from_env() -> Result<VerifyGas, String> {
+    let Ok(path) = std::env::var("VERIFY_GAS_BENCH") else {
+        return Ok(VerifyGas::default());
+    };
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let report: BenchReport = serde_json::from_str(&json).map_err(|e| format!("Invalid bench report {}: {}", path, e))?;
+    Ok(VerifyGas::from_benchmarks(
+        report.rows.iter().filter_map(|row| Some((row.system.parse::<ProofType>().ok()?, row.verify_gas?))),
+    ))
+}
+
+fn ledger_gas_from_env() -> Result<LedgerGas, String> {
+    match std::env::var("LEDGER_GAS") {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid LEDGER_GAS: {}", e)),
+        Err(_) => Ok(LedgerGas::default()),
+    }
+}
+
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    crate::flag_value(args, flag).map(|v| v.parse().unwrap_or_else(|_| panic!("Invalid {}: {}", flag, v)))
+}
+
+pub fn run(args: &[String]) {
+    let inputs: usize = parse_flag(args, "--inputs").expect("--inputs is required");
+    let outputs: usize = parse_flag(args, "--outputs").expect("--outputs is required");
+    let withdrawal = args.iter().any(|a| a == "--withdrawal");
+    let proof_type: ProofType = crate::flag_value(args, "--proof")
+        .map_or(Ok(ProofType::Groth16), |v| v.parse())
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    let market = FeeMarket {
+        base_fee_wei: parse_flag(args, "--base-fee").expect("--base-fee is required"),
+        priority_fee_wei: parse_flag(args, "--priority-fee").unwrap_or_else(|| {
+            std::env::var("FEE_PRIORITY_WEI").map_or(1_000_000_000, |v| v.parse().expect("Invalid FEE_PRIORITY_WEI"))
+        }),
+        token_per_eth: std::env::var("FEE_TOKEN_PER_ETH")
+            .expect("FEE_TOKEN_PER_ETH is not set")
+            .parse()
+            .expect("Invalid FEE_TOKEN_PER_ETH"),
+        margin_bps: std::env::var("FEE_MARGIN_BPS").map_or(1_000, |v| v.parse().expect("Invalid FEE_MARGIN_BPS")),
+        verify_gas: verify_gas_from_env().unwrap_or_else(|e| panic!("{}", e)),
+        ledger_gas: ledger_gas_from_env().unwrap_or_else(|e| panic!("{}", e)),
+    };
+
+    let quote = market.quote(proof_type, inputs, outputs, withdrawal).unwrap_or_else(|e| panic!("{}", e));
+    log!(
+        "{} gas at {} wei: relay cost {} ({}x{}, {:?})",
+        quote.total_gas,
+        quote.gas_price_wei,
+        quote.relay_cost,
+        inputs,
+        outputs,
+        proof_type
+    );
+    println!("{}", serde_json::to_string_pretty(&quote).unwrap());
+}
@@ -0,0 +1,273 @@
+//! `reconcile` subcommand: compare the indexer's state with the ledger contract
+//!
+//! # Usage
+//! sp1-host reconcile (--indexer <url> | --snapshot <file>) [--contract <address>]
+//!     [--rpc-url <url>] [--from-block <n>] [--log-chunk <blocks>]
+//!
+//! The indexer snapshot is `{"blockNumber", "root", "leafCount",
+//! "nullifiers": [...], "leaves": [...]}` (`blockNumber` and `leaves`
+//! optional). It is checked against contract storage (`currentRoot`,
+//! `nextLeafIndex`, `nullifierUsed`) at the snapshot's block, and against a
+//! replay of the ledger's `OutputCommitted` / `NullifierUsed` events.
+//!
+//! Events are replayed from `--from-block` (default 0), which must be at or
+//! before the ledger's deployment: the tree is rebuilt from leaf 0, so a
+//! later block is refused up front rather than failing on a missing leaf.
+//!
+//! Prints a JSON divergence report and exits 1 if anything differs, so drift
+//! surfaces here rather than as proof failures.
+
+
+use alloy_sol_types::{sol, SolCall};
+use serde::{Deserialize, Serialize};
+use ghostclaw_core::events::{decode_ledger_log, LedgerEvent};
+use ghostclaw_core::{Bytes32, MerkleTree, NullifierSet};
+
+use crate::rpc;
+
+sol! {
+    function currentRoot() external view returns (bytes32);
+    function nextLeafIndex() external view returns (uint256);
+    function nullifierUsed(bytes32 nullifier) external view returns (bool);
+}
+
+/// The indexer's view of the ledger.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexerSnapshot {
+    /// Block the snapshot reflects; chain state is read at this block
+    pub block_number: Option<u64>,
+    pub root: Bytes32,
+    pub leaf_count: u64,
+    pub nullifiers: Vec<Bytes32>,
+    /// Full leaf list, if the indexer exposes it
+    #[serde(default)]
+    pub leaves: Option<Vec<Bytes32>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerView {
+    pub root: Bytes32,
+    pub leaf_count: u64,
+    pub nullifier_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileReport {
+    pub contract: String,
+    pub block_number: u64,
+    pub indexer: LedgerView,
+    /// Contract storage
+    pub onchain: LedgerView,
+    /// Root rebuilt from replayed `OutputCommitted` events
+    pub replayed_root: Bytes32,
+    /// Nullifiers spent on-chain that the indexer doesn't have
+    pub missing_nullifiers: Vec<Bytes32>,
+    /// Nullifiers the indexer has that aren't spent on-chain
+    pub unknown_nullifiers: Vec<Bytes32>,
+    /// First leaf index where the indexer's leaves differ from the events
+    pub first_divergent_leaf: Option<u64>,
+    /// Human-readable list of every divergence found
+    pub divergences: Vec<String>,
+    pub in_sync: bool,
+}
+
+/// Ledger state reconstructed from events.
+pub(crate) struct Replay {
+    pub leaves: Vec<[u8; 32]>,
+    pub nullifiers: NullifierSet,
+}
+
+/// Replay the ledger's events in `from_block..=to_block`.
+///
+/// # Errors
+/// Fails if the ledger already had leaves before `from_block` (there's no
+/// checkpoint to seed the tree from, so replays start at deployment), or the
+/// events skip or repeat a leaf.
+pub(crate) fn replay_events(rpc_url: &str, contract: &str, from_block: u64, to_block: u64, chunk: u64) -> Result<Replay, String> {
+    if let Some(before) = from_block.checked_sub(1) {
+        // Before deployment there's no code and the call fails; that's fine
+        if let Ok(leaves) = call(rpc_url, contract, &nextLeafIndexCall {}, before) {
+            if !leaves._0.is_zero() {
+                return Err(format!(
+                    "The ledger already had {} leaves at block {}; --from-block must be at or before its deployment block",
+                    leaves._0, before
+                ));
+            }
+        }
+    }
+
+    let mut indexed_leaves = Vec::new();
+    let mut nullifiers = NullifierSet::new();
+
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start.saturating_add(chunk - 1));
+        log!("  Replaying logs {}..={}", start, end);
+        for log in rpc::get_logs(rpc_url, contract, start, end)? {
+            match decode_ledger_log(&log.topics, &log.data)? {
+                Some(LedgerEvent::OutputCommitted(e)) => {
+                    let index = u64::try_from(e.leafIndex).map_err(|_| "leafIndex overflows u64".to_string())?;
+                    indexed_leaves.push((index, e.commitment.0));
+                }
+                Some(LedgerEvent::NullifierUsed(e)) => {
+                    nullifiers
+                        .insert(e.nullifier.0)
+                        .map_err(|_| format!("NullifierUsed emitted twice for {}", Bytes32(e.nullifier.0)))?;
+                }
+                _ => {}
+            }
+        }
+        start = end + 1;
+    }
+
+    indexed_leaves.sort_by_key(|(index, _)| *index);
+    let mut leaves = Vec::with_capacity(indexed_leaves.len());
+    for (expected, (index, commitment)) in indexed_leaves.into_iter().enumerate() {
+        if index != expected as u64 {
+            return Err(format!("OutputCommitted events skip or repeat leaf {} (got {})", expected, index));
+        }
+        leaves.push(commitment);
+    }
+    Ok(Replay { leaves, nullifiers })
+}
+
+pub(crate) fn call<C: SolCall>(rpc_url: &str, contract: &str, call: &C, block: u64) -> Result<C::Return, String> {
+    let ret = rpc::eth_call_at(rpc_url, contract, &call.abi_encode(), Some(block))?;
+    C::abi_decode_returns(&ret, true).map_err(|e| format!("Failed to decode {}: {}", C::SIGNATURE, e))
+}
+
+fn load_snapshot(args: &[String]) -> Result<IndexerSnapshot, String> {
+    if let Some(path) = crate::flag_value(args, "--snapshot") {
+        let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        return serde_json::from_str(&json).map_err(|e| format!("Invalid snapshot {}: {}", path, e));
+    }
+    let url = crate::flag_value(args, "--indexer")
+        .or_else(|| std::env::var("INDEXER_STATE_URL").ok())
+        .ok_or("Pass --indexer <url> or --snapshot <file> (or set INDEXER_STATE_URL)")?;
+    reqwest::blocking::get(&url)
+        .and_then(|r| r.json())
+        .map_err(|e| format!("Failed to fetch indexer state from {}: {}", url, e))
+}
+
+/// Compare a snapshot with the contract at `block`.
+pub fn reconcile(
+    snapshot: &IndexerSnapshot,
+    rpc_url: &str,
+    contract: &str,
+    block: u64,
+    from_block: u64,
+    chunk: u64,
+) -> Result<ReconcileReport, String> {
+    log!("Reading contract state at block {}...", block);
+    let onchain_root = call(rpc_url, contract, &currentRootCall {}, block)?._0.0;
+    let onchain_leaf_count = u64::try_from(call(rpc_url, contract, &nextLeafIndexCall {}, block)?._0)
+        .map_err(|_| "nextLeafIndex overflows u64".to_string())?;
+
+    log!("Replaying ledger events from block {}...", from_block);
+    let replay = replay_events(rpc_url, contract, from_block, block, chunk)?;
+    let replayed_root = MerkleTree::with_leaves(replay.leaves.clone()).root();
+
+    let local: NullifierSet = snapshot.nullifiers.iter().map(|n| n.0).collect();
+    let missing_nullifiers: Vec<Bytes32> = replay.nullifiers.difference(&local).into_iter().map(Bytes32).collect();
+    // Events can be missed by a partial replay, so confirm against storage
+    let mut unknown_nullifiers = Vec::new();
+    for nullifier in local.difference(&replay.nullifiers) {
+        if !call(rpc_url, contract, &nullifierUsedCall { nullifier: nullifier.into() }, block)?._0 {
+            unknown_nullifiers.push(Bytes32(nullifier));
+        }
+    }
+
+    let first_divergent_leaf = snapshot.leaves.as_ref().and_then(|leaves| {
+        let diverges_at = leaves.iter().zip(&replay.leaves).position(|(a, b)| a.0 != *b);
+        match diverges_at {
+            Some(i) => Some(i as u64),
+            None if leaves.len() != replay.leaves.len() => Some(leaves.len().min(replay.leaves.len()) as u64),
+            None => None,
+        }
+    });
+
+    let mut divergences = Vec::new();
+    if snapshot.root != onchain_root {
+        divergences.push(format!("root: indexer {} vs contract {}", snapshot.root, Bytes32(onchain_root)));
+    }
+    if snapshot.leaf_count != onchain_leaf_count {
+        divergences.push(format!("leaf count: indexer {} vs contract {}", snapshot.leaf_count, onchain_leaf_count));
+    }
+    if replayed_root != onchain_root {
+        divergences.push(format!(
+            "replayed events give root {} ({} leaves) but contract has {}; replay from an earlier --from-block",
+            Bytes32(replayed_root),
+            replay.leaves.len(),
+            Bytes32(onchain_root)
+        ));
+    }
+    if !missing_nullifiers.is_empty() {
+        divergences.push(format!("{} spent nullifiers missing from the indexer", missing_nullifiers.len()));
+    }
+    if !unknown_nullifiers.is_empty() {
+        divergences.push(format!("{} indexer nullifiers not spent on-chain", unknown_nullifiers.len()));
+    }
+    if let Some(leaf) = first_divergent_leaf {
+        divergences.push(format!("indexer leaves diverge from events at leaf {}", leaf));
+    }
+
+    Ok(ReconcileReport {
+        contract: contract.to_string(),
+        block_number: block,
+        indexer: LedgerView {
+            root: snapshot.root,
+            leaf_count: snapshot.leaf_count,
+            nullifier_count: snapshot.nullifiers.len(),
+        },
+        onchain: LedgerView {
+            root: Bytes32(onchain_root),
+            leaf_count: onchain_leaf_count,
+            nullifier_count: replay.nullifiers.len(),
+        },
+        replayed_root: Bytes32(replayed_root),
+        missing_nullifiers,
+        unknown_nullifiers,
+        first_divergent_leaf,
+        in_sync: divergences.is_empty(),
+        divergences,
+    })
+}
+
+/// Entry point for the `reconcile` subcommand.
+pub fn run(args: &[String]) {
+    let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+    let contract = crate::flag_value(args, "--contract")
+        .or_else(rpc::ledger_contract_from_env)
+        .expect("Pass --contract <address> or set LEDGER_CONTRACT");
+    let from_block: u64 = crate::flag_value(args, "--from-block")
+        .map(|v| v.parse().expect("Invalid --from-block"))
+        .unwrap_or(0);
+    let chunk: u64 = crate::flag_value(args, "--log-chunk")
+        .map(|v| v.parse().expect("Invalid --log-chunk"))
+        .unwrap_or(10_000)
+        .max(1);
+
+    let snapshot = load_snapshot(args).unwrap_or_else(|e| panic!("{}", e));
+    let block = match snapshot.block_number {
+        Some(block) => block,
+        None => rpc::block_number(&rpc_url).expect("Failed to query chain head"),
+    };
+
+    let report = reconcile(&snapshot, &rpc_url, &contract, block, from_block, chunk)
+        .unwrap_or_else(|e| panic!("Reconciliation failed: {}", e));
+    for divergence in &report.divergences {
+        log!("DIVERGENCE: {}", divergence);
+    }
+    if report.in_sync {
+        log!("Indexer matches contract at block {}", block);
+    }
+
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    if !report.in_sync {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,226 @@
+//! `repl` subcommand: explore the protocol by hand
+//!
+//! # Usage
+//! sp1-host repl [--chain-id <id>]
+//!
+//! An in-memory session holding named keys, notes, a Merkle tree and the
+//! last witness built. Keys are named; `alice` is the spending key derived
+//! from the seed `alice`, created on first use. Notes are referred to as
+//! `n0`, `n1`, ... in creation order. Blindings are derived from the key's
+//! seed and the note number, so replaying a session gives the same
+//! commitments, nullifiers and roots.
+//!
+//! - `note new <amount> <key>`: a note owned by `key`
+//! - `note list`
+//! - `tree push <note>`: append the note's commitment
+//! - `tree root`
+//! - `tree prove <note>`: the note's inclusion proof, checked against the root
+//! - `nullifier <note>`: sign and derive the note's nullifier
+//! - `witness build <in,...> <out,...>`: signed witness spending the inputs
+//!   (which must be in the tree) into the outputs, fee being the difference;
+//!   runs the guest's checks and reports every failure
+//! - `witness show`: the witness as JSON
+//! - `prove mock`: prove the witness with the mock prover and decode the
+//!   public values
+//!
+//! Nothing is persisted; the session ends with `quit` or end of input.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+use sp1_sdk::{Prover, ProverClient, SP1Stdin};
+use ghostclaw_core::merkle::MerkleTree;
+use ghostclaw_core::output_order::canonicalize_outputs;
+use ghostclaw_core::prepare::{derive_spending_key, owner_pubkey};
+use ghostclaw_core::public_values::decode_public_values;
+use ghostclaw_core::recovery::derive_note_blinding;
+use ghostclaw_core::signatures::{nullifier_message, sign_message, tx_fee, tx_message};
+use ghostclaw_core::{commit, compute_nullifier, simulate_witness, GuestInput, Ledger, Note, PublicInputs, Witness};
+
+use crate::ELF;
+
+const HELP: &str = "\
+note new <amount> <key>         create a note owned by <key>
+note list                       list notes
+tree push <note>                append a note's commitment
+tree root                       current root and leaf count
+tree prove <note>               inclusion proof for a pushed note
+nullifier <note>                the note's nullifier
+witness build <in,..> <out,..>  signed witness spending inputs into outputs
+witness show                    the last witness as JSON
+prove mock                      prove the last witness with the mock prover
+help | quit";
+
+struct SessionNote {
+    note: Note,
+    key: String,
+    leaf_index: Option<usize>,
+}
+
+struct Session {
+    chain_id: u64,
+    keys: BTreeMap<String, [u8; 32]>,
+    notes: Vec<SessionNote>,
+    tree: MerkleTree,
+    /// Last witness built, with the root it was built against
+    witness: Option<(Witness, [u8; 32])>,
+}
+
+impl Session {
+    fn new(chain_id: u64) -> Self {
+        Self { chain_id, keys: BTreeMap::new(), notes: Vec::new(), tree: MerkleTree::new(), witness: None }
+    }
+
+    fn key(&mut self, name: &str) -> Result<[u8; 32], String> {
+        if let Some(key) = self.keys.get(name) {
+            return Ok(*key);
+        }
+        let key = derive_spending_key(name.as_bytes())?;
+        self.keys.insert(name.to_string(), key);
+        Ok(key)
+    }
+
+    fn note(&self, id: &str) -> Result<usize, String> {
+        id.strip_prefix('n')
+            .and_then(|i| i.parse::<usize>().ok())
+            .filter(|i| *i < self.notes.len())
+            .ok_or_else(|| format!("No note {} ({} notes)", id, self.notes.len()))
+    }
+
+    fn run(&mut self, words: &[&str]) -> Result<String, String> {
+        match words {
+            ["help"] => Ok(HELP.to_string()),
+            ["note", "new", amount, key] => {
+                let amount: u64 = amount.parse().map_err(|_| format!("Invalid amount: {}", amount))?;
+                let owner = owner_pubkey(&self.key(key)?)?;
+                let note = Note::new(amount, owner, derive_note_blinding(key.as_bytes(), self.notes.len() as u64));
+                let commitment = commit(&note);
+                self.notes.push(SessionNote { note, key: key.to_string(), leaf_index: None });
+                Ok(format!("n{}: {} to {} (commitment 0x{})", self.notes.len() - 1, amount, key, hex::encode(commitment)))
+            }
+            ["note", "list"] => Ok(self
+                .notes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| {
+                    let leaf = n.leaf_index.map_or("not in tree".to_string(), |l| format!("leaf {}", l));
+                    format!("n{}: {} to {}, {}", i, n.note.amount, n.key, leaf)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ["tree", "push", id] => {
+                let i = self.note(id)?;
+                if let Some(leaf) = self.notes[i].leaf_index {
+                    return Err(format!("{} is already at leaf {}", id, leaf));
+                }
+                let insertion = self.tree.try_push_leaf(commit(&self.notes[i].note))?;
+                self.notes[i].leaf_index = Some(insertion.index as usize);
+                Ok(format!("{} at leaf {}, root 0x{}", id, insertion.index, hex::encode(insertion.new_root)))
+            }
+            ["tree", "root"] => Ok(format!("0x{} ({} leaves)", hex::encode(self.tree.root()), self.tree.leaf_count())),
+            ["tree", "prove", id] => {
+                let i = self.note(id)?;
+                let leaf = self.notes[i].leaf_index.ok_or_else(|| format!("{} is not in the tree", id))?;
+                let proof = self.tree.prove(leaf).ok_or("Leaf missing from the tree")?;
+                let valid = MerkleTree::verify_proof(commit(&self.notes[i].note), &proof, self.tree.root());
+                let mut out = format!("leaf {}, valid against current root: {}", leaf, valid);
+                for (level, sibling) in proof.siblings.iter().enumerate() {
+                    out.push_str(&format!("\n  [{:2}] 0x{}", level, hex::encode(sibling)));
+                }
+                Ok(out)
+            }
+            ["nullifier", id] => {
+                let i = self.note(id)?;
+                let key = self.key(&self.notes[i].key.clone())?;
+                let signature = sign_message(&key, &nullifier_message(&commit(&self.notes[i].note)))?;
+                Ok(format!("0x{}", hex::encode(compute_nullifier(&signature))))
+            }
+            ["witness", "build", inputs, outputs] => self.build_witness(inputs, outputs),
+            ["witness", "show"] => {
+                let (witness, _) = self.witness.as_ref().ok_or("No witness built yet")?;
+                serde_json::to_string_pretty(witness).map_err(|e| e.to_string())
+            }
+            ["prove", "mock"] => self.prove_mock(),
+            _ => Err(format!("Unknown command: {} (try `help`)", words.join(" "))),
+        }
+    }
+
+    fn build_witness(&mut self, inputs: &str, outputs: &str) -> Result<String, String> {
+        let inputs = inputs.split(',').map(|id| self.note(id)).collect::<Result<Vec<usize>, String>>()?;
+        let outputs = outputs.split(',').map(|id| self.note(id)).collect::<Result<Vec<usize>, String>>()?;
+
+        let input_notes: Vec<Note> = inputs.iter().map(|&i| self.notes[i].note.clone()).collect();
+        let mut output_notes: Vec<Note> = outputs.iter().map(|&i| self.notes[i].note.clone()).collect();
+        canonicalize_outputs(&mut output_notes);
+        let fee = tx_fee(&input_notes, &output_notes).ok_or("Outputs exceed inputs")?;
+        let output_commitments: Vec<[u8; 32]> = output_notes.iter().map(commit).collect();
+
+        let mut witness = Witness::new(vec![], vec![], vec![], vec![], vec![], output_notes);
+        for &i in &inputs {
+            let leaf = self.notes[i].leaf_index.ok_or_else(|| format!("n{} is not in the tree", i))?;
+            let key = self.key(&self.notes[i].key.clone())?;
+            let commitment = commit(&self.notes[i].note);
+            let nullifier_sig = sign_message(&key, &nullifier_message(&commitment))?;
+            let tx_sig = sign_message(&key, &tx_message(&compute_nullifier(&nullifier_sig), fee, &output_commitments))?;
+            witness.input_notes.push(self.notes[i].note.clone());
+            witness.input_indices.push(leaf);
+            witness.input_proofs.push(self.tree.prove(leaf).ok_or("Leaf missing from the tree")?);
+            witness.nullifier_signatures.push(nullifier_sig.to_vec());
+            witness.tx_signatures.push(tx_sig.to_vec());
+        }
+        let witness = witness.with_precomputed_values();
+        let old_root = self.tree.root();
+
+        let simulation = simulate_witness(&mut Ledger::new(), &witness, old_root);
+        let summary = if simulation.is_valid() {
+            format!(
+                "Witness with {} inputs, {} outputs, fee {}: passes the guest's checks",
+                witness.input_count(),
+                witness.output_count(),
+                fee
+            )
+        } else {
+            format!("Witness rejected:\n  {}", simulation.failure_reasons().join("\n  "))
+        };
+        self.witness = Some((witness, old_root));
+        Ok(summary)
+    }
+
+    fn prove_mock(&self) -> Result<String, String> {
+        let (witness, old_root) = self.witness.as_ref().ok_or("No witness built yet")?;
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&GuestInput::new(PublicInputs::new(*old_root).with_chain_id(self.chain_id), witness.clone()));
+
+        let client = ProverClient::builder().mock().build();
+        let (pk, _) = client.setup(ELF);
+        let proof = client.prove(&pk, &stdin).run().map_err(|e| format!("Guest rejected the witness: {}", e))?;
+        let output_commitments: Vec<[u8; 32]> = witness.output_notes.iter().map(commit).collect();
+        let outputs = decode_public_values(&proof.public_values.to_vec(), &witness.precomputed_nullifiers, &output_commitments)?;
+        serde_json::to_string_pretty(&outputs).map_err(|e| e.to_string())
+    }
+}
+
+pub fn run(args: &[String]) {
+    let chain_id = crate::flag_value(args, "--chain-id").map_or(31337, |v| v.parse().expect("Invalid --chain-id"));
+    let mut session = Session::new(chain_id);
+    println!("Ghostclaw protocol REPL (chain {}); `help` for commands", chain_id);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => continue,
+            ["quit"] | ["exit"] => break,
+            words => match session.run(words) {
+                Ok(out) => println!("{}", out),
+                Err(e) => println!("error: {}", e),
+            },
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Replay archived proofs against a new guest ELF
+//!
+//! Before rotating the contract's vkey after a circuit upgrade, every archived
+//! `ProofRequest` is re-run on the new ELF and its public values are compared
+//! byte-for-byte with the archived `ProofResponse`. Any difference means the
+//! upgrade is not semantics-preserving.
+//!
+//! # Usage
+//! sp1-host replay <archive-dir> --elf <new-elf> [--prove]
+//!
+//! Each `*.json` file in the archive holds `{"request": ..., "response": ...}`.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{HashableKey, Prover, ProverClient};
+
+use crate::{build_inputs_from_request, ProofRequest, ProofResponse};
+
+/// An archived request together with the response it produced.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArchivedProof {
+    pub request: ProofRequest,
+    pub response: ProofResponse,
+}
+
+/// Result of replaying one archived proof.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResult {
+    pub file: String,
+    pub matches: bool,
+    pub archived_public_values: String,
+    /// Public values from the new ELF (absent if execution failed)
+    pub replayed_public_values: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Summary printed to stdout as JSON.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    pub old_vkey_hashes: Vec<String>,
+    pub new_vkey_hash: String,
+    pub total: usize,
+    pub mismatches: usize,
+    pub results: Vec<ReplayResult>,
+}
+
+/// Entry point for the `replay` subcommand.
+pub fn run(args: &[String]) {
+    let archive_dir = args.get(2).expect("Usage: sp1-host replay <archive-dir> --elf <new-elf> [--prove]");
+    let elf_path = crate::flag_value(args, "--elf").expect("--elf <path> is required");
+    let prove = args.contains(&"--prove".to_string());
+    // Replayed proofs are compared, never submitted
+    crate::root_check::allow_unverified();
+
+    let elf = std::fs::read(&elf_path).expect("Failed to read new ELF");
+    let archive = load_archive(Path::new(archive_dir));
+    eprintln!("Replaying {} archived proofs against {}", archive.len(), elf_path);
+
+    let client = ProverClient::builder().cpu().build();
+    let (pk, vk) = client.setup(&elf);
+    let new_vkey_hash = format!("0x{}", vk.bytes32());
+    eprintln!("New Verification Key Hash: {}", new_vkey_hash);
+
+    let mut old_vkey_hashes: Vec<String> = Vec::new();
+    let mut results = Vec::new();
+
+    for (file, archived) in archive {
+        if !old_vkey_hashes.contains(&archived.response.vkey_hash) {
+            old_vkey_hashes.push(archived.response.vkey_hash.clone());
+        }
+
+        let (stdin, _, _) = build_inputs_from_request(&archived.request, false);
+        let replayed = if prove {
+            client
+                .prove(&pk, &stdin)
+                .run()
+                .map(|proof| proof.public_values.to_vec())
+                .map_err(|e| e.to_string())
+        } else {
+            client
+                .execute(&elf, &stdin)
+                .run()
+                .map(|(public_values, _)| public_values.to_vec())
+                .map_err(|e| e.to_string())
+        };
+
+        let archived_hex = archived.response.public_values_raw.to_lowercase();
+        let result = match replayed {
+            Ok(bytes) => {
+                let replayed_hex = format!("0x{}", hex::encode(&bytes));
+                ReplayResult {
+                    file,
+                    matches: replayed_hex == archived_hex,
+                    archived_public_values: archived_hex,
+                    replayed_public_values: Some(replayed_hex),
+                    error: None,
+                }
+            }
+            Err(e) => ReplayResult {
+                file,
+                matches: false,
+                archived_public_values: archived_hex,
+                replayed_public_values: None,
+                error: Some(e),
+            },
+        };
+
+        eprintln!("  {} {}", if result.matches { "MATCH   " } else { "MISMATCH" }, result.file);
+        results.push(result);
+    }
+
+    let mismatches = results.iter().filter(|r| !r.matches).count();
+    let report = ReplayReport {
+        old_vkey_hashes,
+        new_vkey_hash,
+        total: results.len(),
+        mismatches,
+        results,
+    };
+
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    if mismatches > 0 {
+        eprintln!("\n{} of {} replays diverged: the upgrade is NOT semantics-preserving", mismatches, report.total);
+        std::process::exit(1);
+    }
+    eprintln!("\nAll {} replays match byte-for-byte", report.total);
+}
+
+/// Load every `*.json` archive entry, sorted by file name for stable output.
+fn load_archive(dir: &Path) -> Vec<(String, ArchivedProof)> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .expect("Failed to read archive directory")
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let data = std::fs::read_to_string(&path).expect("Failed to read archive entry");
+            let archived: ArchivedProof = serde_json::from_str(&data)
+                .unwrap_or_else(|e| panic!("Invalid archive entry {}: {}", path.display(), e));
+            (path.display().to_string(), archived)
+        })
+        .collect()
+}
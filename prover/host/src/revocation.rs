@@ -0,0 +1,78 @@
+//! Local revocation list of guest programs (see `ghostclaw_core::revocation`)
+//!
+//! - `REVOCATION_LIST`: comma-separated paths or http(s) URLs of
+//!   `RevocationList` JSON files, merged in order. Operators point this at
+//!   a published list and update it without rebuilding the host.
+//! - `REVOKED_VKEYS`: comma-separated vkey hashes, for an urgent local
+//!   revocation before a list is published.
+//!
+//! The host refuses to prove with a revoked ELF (checked at startup) or
+//! vkey (checked after key setup), and `verify-evm` refuses proofs under a
+//! revoked vkey. A configured list that can't be loaded is an error, not
+//! an empty list.
+
+use std::sync::OnceLock;
+
+use ghostclaw_core::revocation::{Revocation, RevocationList};
+use ghostclaw_core::Bytes32;
+
+static LIST: OnceLock<RevocationList> = OnceLock::new();
+
+fn fetch(source: &str) -> Result<String, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        reqwest::blocking::get(source)
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|e| format!("Failed to fetch revocation list {}: {}", source, e))
+    } else {
+        std::fs::read_to_string(source).map_err(|e| format!("Failed to read revocation list {}: {}", source, e))
+    }
+}
+
+fn load() -> Result<RevocationList, String> {
+    let mut list = RevocationList::default();
+    let sources = std::env::var("REVOCATION_LIST").unwrap_or_default();
+    for source in sources.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let parsed: RevocationList =
+            serde_json::from_str(&fetch(source)?).map_err(|e| format!("Invalid revocation list {}: {}", source, e))?;
+        parsed.validate().map_err(|e| format!("{}: {}", source, e))?;
+        list.merge(parsed);
+    }
+    let vkeys = std::env::var("REVOKED_VKEYS").unwrap_or_default();
+    for vkey in vkeys.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let vkey_hash: Bytes32 = vkey.parse().map_err(|e| format!("Invalid REVOKED_VKEYS entry {}: {}", vkey, e))?;
+        list.merge(RevocationList {
+            revoked: vec![Revocation {
+                vkey_hash: Some(vkey_hash),
+                elf_sha256: None,
+                reason: "listed in REVOKED_VKEYS".to_string(),
+                revoked_at: None,
+            }],
+        });
+    }
+    if !list.is_empty() {
+        log!("Loaded {} program revocations", list.len());
+    }
+    Ok(list)
+}
+
+/// The configured list, loaded once per process.
+pub fn list() -> &'static RevocationList {
+    LIST.get_or_init(|| load().unwrap_or_else(|e| panic!("{}", e)))
+}
+
+/// Refuse to continue with a revoked ELF.
+pub fn refuse_revoked_elf(elf: &[u8]) {
+    let digest = ghostclaw_core::guest_elf::elf_sha256(elf);
+    if let Err(e) = list().check_elf(&digest.0) {
+        panic!("Refusing to prove: {}", e);
+    }
+}
+
+/// Refuse to continue with a revoked vkey (0x-hex, as in `ProofResponse`).
+pub fn refuse_revoked_vkey(vkey_hash: &str) {
+    let vkey: Bytes32 = vkey_hash.parse().unwrap_or_else(|e| panic!("Invalid vkey hash {}: {}", vkey_hash, e));
+    if let Err(e) = list().check_vkey(&vkey.0) {
+        panic!("Refusing to prove: {}", e);
+    }
+}
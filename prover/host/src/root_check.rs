@@ -0,0 +1,77 @@
+//! Old-root verification against the ledger
+//!
+//! A proof only lands if its `oldRoot` is one the ledger has accepted
+//! (`validRoots`), and a request's root comes from the client. Before
+//! proving, the host asks the request chain's ledger (see `chains.rs`)
+//! whether the root is valid, and refuses if it isn't or if it can't ask:
+//! no ledger configured, an RPC failure, or a ledger without `validRoots`.
+//!
+//! `--allow-unverified-root` (off by default; for offline and dev use)
+//! proves anyway when the root can't be checked; a root the ledger rejects
+//! is still refused. The response then has `unverifiedRoot: true` and the
+//! relayer refuses to submit it. Demo proofs never check their root, so
+//! they're always marked unverified.
+
+use std::sync::OnceLock;
+
+use alloy_sol_types::sol;
+
+use crate::chains::Deployment;
+use crate::ledger_status::optional_call;
+
+sol! {
+    function validRoots(bytes32 root) external view returns (bool);
+}
+
+static ALLOW_UNVERIFIED: OnceLock<bool> = OnceLock::new();
+
+/// Whether the root of the request being served was checked (one per process)
+static VERIFIED: OnceLock<bool> = OnceLock::new();
+
+/// Read `--allow-unverified-root` from the command line.
+pub fn configure(args: &[String]) {
+    let _ = ALLOW_UNVERIFIED.set(args.iter().any(|a| a == "--allow-unverified-root"));
+}
+
+/// Prove against unverifiable roots without the flag, for subcommands whose
+/// proofs are never submitted.
+pub fn allow_unverified() {
+    let _ = ALLOW_UNVERIFIED.set(true);
+}
+
+/// Whether `root` is a valid root on `deployment`'s ledger; `Err` if the
+/// ledger can't say.
+fn is_valid_root(deployment: Option<&Deployment>, root: [u8; 32]) -> Result<bool, String> {
+    let deployment = deployment.ok_or("no ledger is configured for the chain")?;
+    optional_call(&deployment.rpc_url(), &deployment.ledger, &validRootsCall { root: root.into() })?
+        .map(|r| r._0)
+        .ok_or_else(|| format!("ledger {} has no validRoots", deployment.ledger))
+}
+
+/// Check `root` before proving against it, panicking if the ledger rejects
+/// it, or can't be asked without `--allow-unverified-root`.
+pub fn verify(deployment: Option<&Deployment>, root: [u8; 32]) {
+    let verified = match is_valid_root(deployment, root) {
+        Ok(true) => true,
+        Ok(false) => panic!(
+            "Refusing to prove: old root 0x{} isn't a valid root on ledger {}",
+            hex::encode(root),
+            deployment.map_or("", |d| d.ledger.as_str())
+        ),
+        Err(e) if ALLOW_UNVERIFIED.get().copied().unwrap_or(false) => {
+            log!("  WARNING: old root 0x{} is unverified ({}); relayers will refuse this proof", hex::encode(root), e);
+            false
+        }
+        Err(e) => panic!(
+            "Refusing to prove: can't verify old root 0x{} ({}); pass --allow-unverified-root for offline use",
+            hex::encode(root),
+            e
+        ),
+    };
+    let _ = VERIFIED.set(verified);
+}
+
+/// Whether proofs from this process are against an unchecked root.
+pub fn unverified() -> bool {
+    !VERIFIED.get().copied().unwrap_or(false)
+}
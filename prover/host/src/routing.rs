@@ -0,0 +1,42 @@
+//! Local-first proving policy
+//!
+//! Small witnesses are proven locally on CPU; anything above the configured
+//! cycle threshold is escalated to the prover network. The decision is
+//! recorded in `ProofResponse` so operators can audit which backend ran.
+
+pub use ghostclaw_prover_client::response::{Backend, RoutingDecision};
+
+/// Default local ceiling: a 1-2 input transfer stays comfortably below this.
+pub const DEFAULT_LOCAL_MAX_CYCLES: u64 = 20_000_000;
+
+/// Thresholds for `SP1_PROVER=auto`.
+#[derive(Debug, Clone, Copy)]
+pub struct RoutingPolicy {
+    /// Witnesses executing in at most this many cycles are proven locally.
+    pub local_max_cycles: u64,
+}
+
+impl RoutingPolicy {
+    /// Read thresholds from the environment (`SP1_LOCAL_MAX_CYCLES`).
+    pub fn from_env() -> Self {
+        let local_max_cycles = std::env::var("SP1_LOCAL_MAX_CYCLES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_LOCAL_MAX_CYCLES);
+        Self { local_max_cycles }
+    }
+
+    /// Pick a backend for a witness that executed in `cycles` cycles.
+    pub fn decide(&self, cycles: u64) -> RoutingDecision {
+        let backend = if cycles <= self.local_max_cycles {
+            Backend::Cpu
+        } else {
+            Backend::Network
+        };
+        RoutingDecision {
+            backend,
+            cycles,
+            local_max_cycles: self.local_max_cycles,
+        }
+    }
+}
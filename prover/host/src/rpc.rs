@@ -0,0 +1,159 @@
+//! Minimal Ethereum JSON-RPC client for host-side contract queries
+//!
+//! Only the handful of read calls the host needs (eth_call, eth_blockNumber,
+//! eth_getBlockByNumber, eth_getLogs, eth_getTransactionReceipt); transactions
+//! are never sent.
+
+use serde_json::{json, Value};
+
+/// Default RPC endpoint, matching the prover-server's `RPC_URL`.
+pub fn rpc_url_from_env() -> String {
+    std::env::var("RPC_URL").unwrap_or_else(|_| "http://localhost:8545".to_string())
+}
+
+/// Default ledger address, matching the prover-server's `LEDGER_CONTRACT`,
+/// falling back to the DEPLOYMENT_MANIFEST written by `deploy`.
+pub fn ledger_contract_from_env() -> Option<String> {
+    std::env::var("LEDGER_CONTRACT").ok().or_else(|| match crate::deploy::manifest_from_env()? {
+        Ok(manifest) => Some(manifest.ledger),
+        Err(e) => {
+            log!("Ignoring DEPLOYMENT_MANIFEST: {}", e);
+            None
+        }
+    })
+}
+
+/// Issue a raw JSON-RPC request and return its `result`.
+pub fn request(rpc_url: &str, method: &str, params: Value) -> Result<Value, String> {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+
+    let response: Value = reqwest::blocking::Client::new()
+        .post(rpc_url)
+        .json(&body)
+        .send()
+        .map_err(|e| format!("{} request failed: {}", method, e))?
+        .json()
+        .map_err(|e| format!("{} returned invalid JSON: {}", method, e))?;
+
+    if let Some(error) = response.get("error") {
+        return Err(format!("{} failed: {}", method, error));
+    }
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| format!("{} returned no result", method))
+}
+
+/// `eth_call` against the latest block, returning the raw return data.
+pub fn eth_call(rpc_url: &str, to: &str, calldata: &[u8]) -> Result<Vec<u8>, String> {
+    eth_call_at(rpc_url, to, calldata, None)
+}
+
+/// `eth_call` against a specific block (`None` for latest).
+pub fn eth_call_at(rpc_url: &str, to: &str, calldata: &[u8], block: Option<u64>) -> Result<Vec<u8>, String> {
+    let result = request(
+        rpc_url,
+        "eth_call",
+        json!([{ "to": to, "data": format!("0x{}", hex::encode(calldata)) }, block_tag(block)]),
+    )?;
+    decode_hex_value(&result)
+}
+
+/// A log's block, transaction, topics and data.
+pub struct RawLog {
+    pub block_number: u64,
+    pub transaction_hash: [u8; 32],
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
+
+/// `eth_getLogs` for every event `address` emitted in `[from_block, to_block]`.
+pub fn get_logs(rpc_url: &str, address: &str, from_block: u64, to_block: u64) -> Result<Vec<RawLog>, String> {
+    get_logs_by_topics(rpc_url, address, from_block, to_block, &[])
+}
+
+/// `eth_getLogs` restricted to `topics` (positional; `None` matches anything).
+pub fn get_logs_by_topics(
+    rpc_url: &str,
+    address: &str,
+    from_block: u64,
+    to_block: u64,
+    topics: &[Option<[u8; 32]>],
+) -> Result<Vec<RawLog>, String> {
+    let topics: Vec<Value> = topics
+        .iter()
+        .map(|t| t.map_or(Value::Null, |t| json!(format!("0x{}", hex::encode(t)))))
+        .collect();
+    let result = request(
+        rpc_url,
+        "eth_getLogs",
+        json!([{
+            "address": address,
+            "fromBlock": block_tag(Some(from_block)),
+            "toBlock": block_tag(Some(to_block)),
+            "topics": topics,
+        }]),
+    )?;
+    let logs = result.as_array().ok_or("eth_getLogs returned a non-array result")?;
+    logs.iter()
+        .map(|log| {
+            let topics = log["topics"]
+                .as_array()
+                .ok_or("Log without topics")?
+                .iter()
+                .map(|t| {
+                    <[u8; 32]>::try_from(decode_hex_value(t)?.as_slice()).map_err(|_| "Topic is not 32 bytes".to_string())
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(RawLog {
+                block_number: decode_quantity(&log["blockNumber"])?,
+                transaction_hash: <[u8; 32]>::try_from(decode_hex_value(&log["transactionHash"])?.as_slice())
+                    .map_err(|_| "Transaction hash is not 32 bytes".to_string())?,
+                topics,
+                data: decode_hex_value(&log["data"])?,
+            })
+        })
+        .collect()
+}
+
+fn block_tag(block: Option<u64>) -> String {
+    match block {
+        Some(n) => format!("0x{:x}", n),
+        None => "latest".to_string(),
+    }
+}
+
+/// Current chain head block number.
+pub fn block_number(rpc_url: &str) -> Result<u64, String> {
+    let result = request(rpc_url, "eth_blockNumber", json!([]))?;
+    decode_quantity(&result)
+}
+
+/// Chain ID the RPC endpoint serves.
+pub fn chain_id(rpc_url: &str) -> Result<u64, String> {
+    let result = request(rpc_url, "eth_chainId", json!([]))?;
+    decode_quantity(&result)
+}
+
+/// Timestamp (unix seconds) of block `block`.
+pub fn block_timestamp(rpc_url: &str, block: u64) -> Result<u64, String> {
+    let result = request(rpc_url, "eth_getBlockByNumber", json!([block_tag(Some(block)), false]))?;
+    decode_quantity(&result["timestamp"]).map_err(|e| format!("Block {} timestamp: {}", block, e))
+}
+
+/// Decode a 0x-prefixed hex data string.
+pub fn decode_hex_value(value: &Value) -> Result<Vec<u8>, String> {
+    let s = value.as_str().ok_or("Expected hex string")?;
+    hex::decode(s.trim_start_matches("0x")).map_err(|e| format!("Invalid hex: {}", e))
+}
+
+/// Decode a 0x-prefixed hex quantity.
+pub fn decode_quantity(value: &Value) -> Result<u64, String> {
+    let s = value.as_str().ok_or("Expected hex quantity")?;
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|e| format!("Invalid quantity: {}", e))
+}
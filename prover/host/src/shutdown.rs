@@ -0,0 +1,52 @@
+//! SIGTERM/SIGINT handling for the long-running subcommands
+//!
+//! `indexer` and `daemon` run until they're stopped. After `install`, the
+//! first SIGTERM or SIGINT sets `requested`: the subcommand stops taking
+//! work, writes what it holds in memory to disk (the indexer its
+//! checkpoint, the daemon its unstarted jobs back to their queue) and exits
+//! 0. It exits non-zero only if that fails, or on a genuine error. A second
+//! signal exits at once with 130, losing whatever wasn't saved.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::signal::unix::{signal, SignalKind};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+async fn next_signal() -> &'static str {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Failed to listen for SIGINT");
+    tokio::select! {
+        _ = terminate.recv() => "SIGTERM",
+        _ = interrupt.recv() => "SIGINT",
+    }
+}
+
+/// Listen for shutdown signals on a background thread.
+pub fn install() {
+    std::thread::spawn(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start the signal handler");
+        runtime.block_on(async {
+            log!("{} received; shutting down (signal again to exit without saving)", next_signal().await);
+            REQUESTED.store(true, Ordering::SeqCst);
+            log!("{} received; exiting without saving", next_signal().await);
+            std::process::exit(130);
+        });
+    });
+}
+
+/// Whether a shutdown signal has arrived.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Resolve once a shutdown signal arrives (for `with_graceful_shutdown`).
+pub async fn wait() {
+    while !requested() {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
@@ -0,0 +1,88 @@
+//! Per-stage timings reported in `ProofResponse.timings`
+//!
+//! The host serves one request per process, so stage durations are kept in
+//! a process-wide record (like the trace ID) rather than threaded through
+//! every backend's call chain. Stages that run more than once (e.g. setup
+//! on the `auto` backend's network fallback) accumulate.
+//!
+//! Backends that prove and wrap in one call (network and GPU Groth16) only
+//! report `proveMs`; `coreProveMs` and `wrapMs` are set when the stages run
+//! separately. Production telemetry reads these instead of stderr timestamps.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+pub use ghostclaw_prover_client::response::StageTimings;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static STAGES: Mutex<StageTimings> = Mutex::new(StageTimings::EMPTY);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading (and, for sealed requests, opening) the request
+    Parse,
+    /// Building the witness, host-side checks and precomputed values
+    Precompute,
+    /// Guest execution to count cycles (metering or `auto` routing)
+    Execute,
+    /// Proving key setup
+    Setup,
+    /// Core (STARK) proof
+    CoreProve,
+    /// Wrapping a core proof for on-chain verification
+    Wrap,
+    /// Core proof and wrap in a single backend call
+    Prove,
+    /// Decoding public values, checks and building the response
+    Encode,
+}
+
+/// Add `elapsed` to `stage` (and to `proveMs` for the split stages).
+fn add(timings: &mut StageTimings, stage: Stage, elapsed: Duration) {
+    let ms = elapsed.as_millis() as u64;
+    let mut bump = |field: &mut Option<u64>| *field = Some(field.unwrap_or(0) + ms);
+    match stage {
+        Stage::Parse => bump(&mut timings.parse_ms),
+        Stage::Precompute => bump(&mut timings.precompute_ms),
+        Stage::Execute => bump(&mut timings.execute_ms),
+        Stage::Setup => bump(&mut timings.setup_ms),
+        Stage::CoreProve => {
+            bump(&mut timings.core_prove_ms);
+            bump(&mut timings.prove_ms);
+        }
+        Stage::Wrap => {
+            bump(&mut timings.wrap_ms);
+            bump(&mut timings.prove_ms);
+        }
+        Stage::Prove => bump(&mut timings.prove_ms),
+        Stage::Encode => bump(&mut timings.encode_ms),
+    }
+}
+
+/// Mark the start of serving a request (the `totalMs` origin).
+pub fn start() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Run `f`, adding its duration to `stage`.
+pub fn time<T>(stage: Stage, f: impl FnOnce() -> T) -> T {
+    start();
+    let begun = Instant::now();
+    let out = f();
+    record(stage, begun.elapsed());
+    out
+}
+
+pub fn record(stage: Stage, elapsed: Duration) {
+    add(&mut STAGES.lock().unwrap_or_else(|e| e.into_inner()), stage, elapsed);
+}
+
+/// Timings so far, clearing the per-proof stages so the next proof in the
+/// same process (see `batch.rs`) starts fresh. Parse time is kept.
+pub fn take() -> StageTimings {
+    let mut stages = STAGES.lock().unwrap_or_else(|e| e.into_inner());
+    let parse_ms = stages.parse_ms;
+    let mut timings = std::mem::replace(&mut *stages, StageTimings { parse_ms, ..StageTimings::EMPTY });
+    timings.total_ms = STARTED.get().map_or(0, |t| t.elapsed().as_millis() as u64);
+    timings
+}
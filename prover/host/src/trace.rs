@@ -0,0 +1,101 @@
+//! Request trace IDs
+//!
+//! The prover-server tags each `ProofRequest` with a `traceId`. The host
+//! serves one request per process, so the ID is stored once and prefixed to
+//! every log line (`log!`), echoed in the `ProofResponse`, and included in
+//! the JSON error payload written to stdout if proving fails. Failures
+//! clients act on also carry a `code` (e.g. `DEADLINE_EXCEEDED`).
+
+use serde::de::DeserializeOwned;
+use std::sync::OnceLock;
+
+pub use ghostclaw_prover_client::ErrorResponse;
+
+static TRACE_ID: OnceLock<String> = OnceLock::new();
+
+/// Error code of the request's failure, if it has one
+static ERROR_CODE: OnceLock<&'static str> = OnceLock::new();
+
+/// `eprintln!` prefixed with the request's trace ID, if one was supplied.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        match $crate::trace::trace_id() {
+            Some(id) => eprintln!("[trace {}] {}", id, format_args!($($arg)*)),
+            None => eprintln!($($arg)*),
+        }
+    };
+}
+
+/// Trace ID of the request being served.
+pub fn trace_id() -> Option<String> {
+    TRACE_ID.get().cloned()
+}
+
+/// Requests that carry a trace ID.
+pub trait Traced {
+    fn trace_id(&self) -> Option<&str>;
+
+    /// Checks beyond the serde schema (e.g. the request format version).
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Parse a request, recording its `traceId` and switching failures to JSON
+/// error payloads on stdout.
+pub fn parse_request<T: DeserializeOwned + Traced>(json: &str) -> T {
+    install_error_payload_hook();
+    match serde_json::from_str::<T>(json) {
+        Ok(request) => accept(request),
+        Err(e) => {
+            // Still correlate the error if the ID itself is readable
+            let value = serde_json::from_str::<serde_json::Value>(json).ok();
+            if let Some(id) = value.as_ref().and_then(|v| v.get("traceId")).and_then(|v| v.as_str()) {
+                set_trace_id(id);
+            }
+            panic!("Failed to parse request: {}", e);
+        }
+    }
+}
+
+/// Record an already-parsed request's `traceId` and run its checks (for
+/// requests that don't arrive as plain JSON; see `delegated.rs`).
+pub fn accept<T: Traced>(request: T) -> T {
+    if let Some(id) = request.trace_id() {
+        set_trace_id(id);
+    }
+    if let Err(e) = request.validate() {
+        panic!("Invalid request: {}", e);
+    }
+    request
+}
+
+/// Tag the failure about to be reported with `code`, if none is set yet.
+pub fn set_error_code(code: &'static str) {
+    let _ = ERROR_CODE.set(code);
+}
+
+/// Set the trace ID, if none is recorded yet.
+pub fn set_trace_id(id: &str) {
+    let _ = TRACE_ID.set(id.to_string());
+}
+
+pub fn install_error_payload_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let error = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Prover panicked".to_string());
+        let response = ErrorResponse {
+            error,
+            code: ERROR_CODE.get().map(|code| code.to_string()),
+            trace_id: trace_id(),
+            network_attempts: crate::network::take_attempts(),
+        };
+        println!("{}", serde_json::to_string(&response).unwrap());
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,169 @@
+//! `verify-evm` subcommand: run a proof through the Solidity verifier in revm
+//!
+//! # Usage
+//! sp1-host verify-evm <response.json> [--artifacts <contracts/out>] [--vkey <hash>]
+//!                     [--contract <address>] [--rpc-url <url>]
+//!
+//! The v5 Groth16 `SP1Verifier` (the same forge artifact `deploy` routes the
+//! gateway to) is deployed into an in-memory EVM and called with
+//! `verifyProof(programVKey, publicValues, proofBytes)`, ABI-encoded from the
+//! `ProofResponse` exactly as the ledger forwards them. A proof that fails
+//! here would revert on-chain, so selector, byte-order and encoding mistakes
+//! are caught before any gas is spent.
+//!
+//! The program vkey is `--vkey`, else the ledger's `UTXO_PROGRAM_VKEY` when a
+//! contract is given (or LEDGER_CONTRACT / DEPLOYMENT_MANIFEST is set), else
+//! the response's `vkeyHash`. Proofs under a revoked vkey (REVOCATION_LIST,
+//! REVOKED_VKEYS; see `revocation.rs`) are refused.
+
+use std::path::Path;
+
+use alloy_sol_types::{sol, SolCall, SolError};
+use revm::db::{CacheDB, EmptyDB};
+use revm::primitives::{Address, Bytes, ExecutionResult, Output, TxKind};
+use revm::Evm;
+use serde::Serialize;
+
+use crate::{deploy, rpc, vkey, ProofResponse};
+
+sol! {
+    function verifyProof(bytes32 programVKey, bytes calldata publicValues, bytes calldata proofBytes) external view;
+
+    error WrongVerifierSelector(bytes4 received, bytes4 expected);
+    error InvalidProof();
+}
+
+/// Gas limit for the deployment and the verification call
+const GAS_LIMIT: u64 = 30_000_000;
+
+/// Caller of both transactions (any address works: gas is free in the sandbox)
+const CALLER: Address = Address::repeat_byte(0x11);
+
+/// Outcome of one in-EVM verification.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvmVerification {
+    pub verified: bool,
+    /// Gas used by the `verifyProof` call
+    pub gas_used: u64,
+    /// Decoded revert or halt reason when not verified
+    pub error: Option<String>,
+}
+
+/// Deploy `verifier_code` (creation bytecode) into a fresh in-memory EVM and
+/// call `verifyProof` on it.
+///
+/// # Errors
+/// Fails only if the verifier can't be deployed; a rejected proof is an
+/// `Ok` with `verified: false`.
+pub fn verify_in_evm(
+    verifier_code: &[u8],
+    program_vkey: [u8; 32],
+    public_values: &[u8],
+    proof: &[u8],
+) -> Result<EvmVerification, String> {
+    let mut evm = Evm::builder()
+        .with_db(CacheDB::new(EmptyDB::default()))
+        .modify_tx_env(|tx| {
+            tx.caller = CALLER;
+            tx.gas_limit = GAS_LIMIT;
+            tx.transact_to = TxKind::Create;
+            tx.data = Bytes::copy_from_slice(verifier_code);
+        })
+        .build();
+
+    let verifier = match evm.transact_commit().map_err(|e| format!("Verifier deployment failed: {:?}", e))? {
+        ExecutionResult::Success { output: Output::Create(_, Some(address)), .. } => address,
+        other => return Err(format!("Verifier deployment failed: {:?}", other)),
+    };
+
+    let calldata = verifyProofCall {
+        programVKey: program_vkey.into(),
+        publicValues: public_values.to_vec().into(),
+        proofBytes: proof.to_vec().into(),
+    }
+    .abi_encode();
+    evm.tx_mut().transact_to = TxKind::Call(verifier);
+    evm.tx_mut().data = calldata.into();
+
+    let result = evm.transact_commit().map_err(|e| format!("verifyProof call failed: {:?}", e))?;
+    Ok(match result {
+        ExecutionResult::Success { gas_used, .. } => EvmVerification { verified: true, gas_used, error: None },
+        ExecutionResult::Revert { gas_used, output } => EvmVerification {
+            verified: false,
+            gas_used,
+            error: Some(describe_revert(&output)),
+        },
+        ExecutionResult::Halt { reason, gas_used } => EvmVerification {
+            verified: false,
+            gas_used,
+            error: Some(format!("Halted: {:?}", reason)),
+        },
+    })
+}
+
+fn describe_revert(output: &[u8]) -> String {
+    if let Ok(e) = WrongVerifierSelector::abi_decode(output, true) {
+        return format!(
+            "WrongVerifierSelector: proof starts with 0x{}, verifier expects 0x{}",
+            hex::encode(e.received),
+            hex::encode(e.expected)
+        );
+    }
+    if InvalidProof::abi_decode(output, true).is_ok() {
+        return "InvalidProof: the proof does not verify against this vkey and these public values".to_string();
+    }
+    format!("Reverted with 0x{}", hex::encode(output))
+}
+
+fn decode_hex_field(value: &str, field: &str) -> Vec<u8> {
+    hex::decode(value.trim_start_matches("0x")).unwrap_or_else(|e| panic!("Invalid {} hex: {}", field, e))
+}
+
+/// Entry point for the `verify-evm` subcommand.
+pub fn run(args: &[String]) {
+    let response_path = args.get(2).expect("Usage: sp1-host verify-evm <response.json> [--artifacts <dir>] [--vkey <hash>]");
+    let artifacts = crate::flag_value(args, "--artifacts").unwrap_or_else(|| "contracts/out".to_string());
+
+    let json = std::fs::read_to_string(response_path).unwrap_or_else(|e| panic!("Failed to read {}: {}", response_path, e));
+    let response: ProofResponse =
+        serde_json::from_str(&json).unwrap_or_else(|e| panic!("Invalid ProofResponse {}: {}", response_path, e));
+
+    let program_vkey: [u8; 32] = match crate::flag_value(args, "--vkey") {
+        Some(hash) => decode_hex_field(&hash, "--vkey"),
+        None => match crate::flag_value(args, "--contract").or_else(rpc::ledger_contract_from_env) {
+            Some(contract) => {
+                let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+                vkey::onchain_vkey_hash(&rpc_url, &contract)
+                    .unwrap_or_else(|e| panic!("Failed to query vkey of {}: {}", contract, e))
+                    .to_vec()
+            }
+            None => decode_hex_field(&response.vkey_hash, "vkeyHash"),
+        },
+    }
+    .try_into()
+    .unwrap_or_else(|bytes: Vec<u8>| panic!("Program vkey must be 32 bytes, got {}", bytes.len()));
+    if let Err(e) = crate::revocation::list().check_vkey(&program_vkey) {
+        panic!("Refusing to verify: {}", e);
+    }
+    if format!("0x{}", hex::encode(program_vkey)) != response.vkey_hash.to_lowercase() {
+        log!("Warning: verifying against vkey 0x{}, but the proof reports {}", hex::encode(program_vkey), response.vkey_hash);
+    }
+
+    let verifier_code = deploy::creation_code(Path::new(&artifacts), "SP1VerifierGroth16.sol", "SP1Verifier")
+        .unwrap_or_else(|e| panic!("{}", e));
+    let proof = decode_hex_field(&response.proof, "proof");
+    let public_values = decode_hex_field(&response.public_values_raw, "publicValuesRaw");
+
+    let verification = verify_in_evm(&verifier_code, program_vkey, &public_values, &proof)
+        .unwrap_or_else(|e| panic!("{}", e));
+    println!("{}", serde_json::to_string_pretty(&verification).unwrap());
+
+    match &verification.error {
+        None => log!("Proof verifies in the EVM ({} gas)", verification.gas_used),
+        Some(e) => {
+            log!("Proof would revert on-chain: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
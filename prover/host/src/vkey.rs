@@ -0,0 +1,83 @@
+//! `vkey` subcommand: compare the embedded ELF's vkey with the deployed ledger
+//!
+//! # Usage
+//! sp1-host vkey [--rpc-url <url>] [--contract <address>]
+//!
+//! Without a contract address this only prints the local vkey hash. The
+//! ledger's vkey is an immutable constructor argument, so a mismatch is fixed
+//! by deploying a new ledger with the local vkey (see `deploy`), not rotated.
+
+use alloy_sol_types::{sol, SolCall};
+use serde::Serialize;
+use sp1_sdk::{HashableKey, Prover, ProverClient};
+
+use crate::{rpc, ELF};
+
+sol! {
+    /// Verification key getter exposed by PrivateUTXOLedger
+    function UTXO_PROGRAM_VKEY() external view returns (bytes32);
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VkeyReport {
+    pub local_vkey_hash: String,
+    pub contract: Option<String>,
+    pub onchain_vkey_hash: Option<String>,
+    /// `None` when no contract was queried
+    pub matches: Option<bool>,
+}
+
+/// Compute the vkey hash (bytes32) of the embedded guest ELF.
+pub fn local_vkey_hash() -> [u8; 32] {
+    let client = ProverClient::builder().cpu().build();
+    let (_, vk) = client.setup(ELF);
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hex::decode(vk.bytes32().trim_start_matches("0x")).expect("Invalid vkey hex"));
+    hash
+}
+
+/// Query the ledger's configured program vkey.
+pub fn onchain_vkey_hash(rpc_url: &str, contract: &str) -> Result<[u8; 32], String> {
+    let calldata = UTXO_PROGRAM_VKEYCall {}.abi_encode();
+    let ret = rpc::eth_call(rpc_url, contract, &calldata)?;
+    let decoded = UTXO_PROGRAM_VKEYCall::abi_decode_returns(&ret, true)
+        .map_err(|e| format!("Failed to decode UTXO_PROGRAM_VKEY: {}", e))?;
+    Ok(decoded._0.0)
+}
+
+/// Entry point for the `vkey` subcommand.
+pub fn run(args: &[String]) {
+    let local = local_vkey_hash();
+    let local_hex = format!("0x{}", hex::encode(local));
+    eprintln!("Local Verification Key Hash: {}", local_hex);
+
+    let contract = crate::flag_value(args, "--contract").or_else(rpc::ledger_contract_from_env);
+    let rpc_url = crate::flag_value(args, "--rpc-url").unwrap_or_else(rpc::rpc_url_from_env);
+
+    let mut report = VkeyReport {
+        local_vkey_hash: local_hex,
+        contract: contract.clone(),
+        onchain_vkey_hash: None,
+        matches: None,
+    };
+
+    if let Some(contract) = &contract {
+        let onchain = onchain_vkey_hash(&rpc_url, contract).expect("Failed to query on-chain vkey");
+        report.onchain_vkey_hash = Some(format!("0x{}", hex::encode(onchain)));
+        report.matches = Some(onchain == local);
+
+        if onchain == local {
+            eprintln!("On-chain vkey MATCHES ({})", contract);
+        } else {
+            eprintln!("On-chain vkey MISMATCH ({}): 0x{}", contract, hex::encode(onchain));
+            eprintln!("Proofs from this ELF will be rejected by the verifier; deploy a ledger with the local vkey.");
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    if report.matches == Some(false) {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,163 @@
+//! Guest security checks, exercised in SP1's executor
+//!
+//! Each case runs the embedded guest ELF on a crafted witness without
+//! generating a proof. Rejected witnesses must halt the guest; the specific
+//! reason is asserted through the host-side checks the guest shares
+//! (`Witness::validate_*`, `simulate_witness`), since the executor only
+//! reports a non-zero exit.
+//!
+//! Run with: cargo test --release --test guest_executor
+
+use k256::ecdsa::SigningKey;
+use sp1_sdk::{ExecutionReport, ProverClient, SP1Stdin};
+use ghostclaw_core::merkle::MerkleTree;
+use ghostclaw_core::public_values::decode_public_values;
+use ghostclaw_core::signatures::{nullifier_message, sign_message, tx_fee, tx_message};
+use ghostclaw_core::{commit, compute_nullifier, simulate_witness, GuestInput, Ledger, Note, PublicInputs, Witness};
+
+const ELF: &[u8] = include_bytes!("../../program/elf/sp1-program");
+
+/// Cycle budget for a 1-input, 2-output transfer. A regression past this
+/// usually means a precompile stopped being used.
+const TRANSFER_CYCLE_BUDGET: u64 = 10_000_000;
+
+struct Owner {
+    secret: [u8; 32],
+    pubkey: [u8; 32],
+}
+
+impl Owner {
+    fn new(seed: u8) -> Self {
+        let secret = [seed; 32];
+        let signing_key = SigningKey::from_bytes((&secret).into()).expect("valid key");
+        let mut pubkey = [0u8; 32];
+        pubkey.copy_from_slice(&signing_key.verifying_key().to_encoded_point(true).as_bytes()[1..]);
+        Self { secret, pubkey }
+    }
+
+    fn note(&self, amount: u64, blinding: u8) -> Note {
+        Note::new(amount, self.pubkey, [blinding; 32])
+    }
+}
+
+/// A signed transaction spending `inputs` (all owned by `owner`, already in
+/// a tree) into `outputs`.
+fn signed_witness(owner: &Owner, inputs: Vec<Note>, outputs: Vec<Note>) -> (PublicInputs, Witness) {
+    let mut tree = MerkleTree::new();
+    tree.push_note(&Note::new(1, [0xee; 32], [0xee; 32]));
+    let indices: Vec<usize> = inputs.iter().map(|note| tree.push_note(note).index as usize).collect();
+    let proofs = indices.iter().map(|&i| tree.prove(i).expect("leaf exists")).collect();
+
+    let output_commitments: Vec<[u8; 32]> = outputs.iter().map(commit).collect();
+    let fee = tx_fee(&inputs, &outputs).unwrap_or(0);
+    let mut nullifier_sigs = Vec::new();
+    let mut tx_sigs = Vec::new();
+    for note in &inputs {
+        let nullifier_sig = sign_message(&owner.secret, &nullifier_message(&commit(note))).unwrap();
+        let nullifier = compute_nullifier(&nullifier_sig);
+        tx_sigs.push(sign_message(&owner.secret, &tx_message(&nullifier, fee, &output_commitments)).unwrap().to_vec());
+        nullifier_sigs.push(nullifier_sig.to_vec());
+    }
+
+    let witness = Witness::new(inputs, indices, proofs, nullifier_sigs, tx_sigs, outputs).with_precomputed_values();
+    (PublicInputs::new(tree.root()), witness)
+}
+
+fn execute(public_inputs: &PublicInputs, witness: &Witness) -> Result<(Vec<u8>, ExecutionReport), String> {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestInput::new(public_inputs.clone(), witness.clone()));
+    ProverClient::builder()
+        .cpu()
+        .build()
+        .execute(ELF, &stdin)
+        .run()
+        .map(|(public_values, report)| (public_values.to_vec(), report))
+        .map_err(|e| e.to_string())
+}
+
+fn host_rejection(public_inputs: &PublicInputs, witness: &Witness) -> String {
+    if let Err(e) = witness.validate_structure() {
+        return e;
+    }
+    if let Err(e) = witness.validate_value_conservation() {
+        return e;
+    }
+    simulate_witness(&mut Ledger::new(), witness, public_inputs.old_root)
+        .into_result()
+        .expect_err("host checks should reject the witness")
+}
+
+/// The guest must halt, for the reason the shared checks give.
+fn assert_rejected(public_inputs: &PublicInputs, witness: &Witness, reason: &str) {
+    let rejection = host_rejection(public_inputs, witness);
+    assert!(rejection.contains(reason), "expected {:?}, host reported {:?}", reason, rejection);
+    assert!(execute(public_inputs, witness).is_err(), "guest accepted a witness rejected with {:?}", rejection);
+}
+
+#[test]
+fn valid_transfer_commits_expected_outputs_within_budget() {
+    let alice = Owner::new(1);
+    let outputs = vec![Owner::new(2).note(60, 1), alice.note(40, 2)];
+    let (public_inputs, witness) = signed_witness(&alice, vec![alice.note(100, 9)], outputs);
+
+    let (public_values, report) = execute(&public_inputs, &witness).expect("valid transfer should execute");
+    let decoded = decode_public_values(&public_values, &[], &[]).unwrap();
+
+    assert_eq!(decoded.old_root, public_inputs.old_root);
+    assert_eq!(decoded.nullifiers, witness.precomputed_nullifiers);
+    assert_eq!(decoded.output_commitments, witness.precomputed_output_commitments);
+    assert!(
+        report.total_instruction_count() <= TRANSFER_CYCLE_BUDGET,
+        "transfer took {} cycles (budget {})",
+        report.total_instruction_count(),
+        TRANSFER_CYCLE_BUDGET
+    );
+}
+
+#[test]
+fn merkle_proof_against_wrong_root_is_rejected() {
+    let alice = Owner::new(1);
+    let (mut public_inputs, witness) = signed_witness(&alice, vec![alice.note(100, 9)], vec![alice.note(100, 3)]);
+    public_inputs.old_root = [0x42; 32];
+
+    assert_rejected(&public_inputs, &witness, "Merkle proof failed");
+}
+
+#[test]
+fn missing_tx_signature_is_rejected() {
+    let alice = Owner::new(1);
+    let (public_inputs, mut witness) = signed_witness(
+        &alice,
+        vec![alice.note(60, 8), alice.note(40, 9)],
+        vec![alice.note(100, 3)],
+    );
+    witness.tx_signatures.pop();
+
+    assert_rejected(&public_inputs, &witness, "Mismatched tx signature count");
+}
+
+#[test]
+fn signature_from_another_key_is_rejected() {
+    let alice = Owner::new(1);
+    let mallory = Owner::new(7);
+    let outputs = vec![mallory.note(100, 3)];
+    let (public_inputs, mut witness) = signed_witness(&alice, vec![alice.note(100, 9)], outputs.clone());
+
+    // Mallory re-signs Alice's note with her own key
+    let (_, forged) = signed_witness(&mallory, vec![alice.note(100, 9)], outputs);
+    witness.nullifier_signatures = forged.nullifier_signatures;
+    witness.tx_signatures = forged.tx_signatures;
+    let witness = witness.with_precomputed_values();
+
+    assert_rejected(&public_inputs, &witness, "Nullifier signature mismatch");
+}
+
+#[test]
+fn overflowing_output_amounts_are_rejected() {
+    let alice = Owner::new(1);
+    // u64::MAX + 2 wraps to 1, which a wrapping sum would accept
+    let outputs = vec![alice.note(u64::MAX, 3), alice.note(2, 4)];
+    let (public_inputs, witness) = signed_witness(&alice, vec![alice.note(1, 9)], outputs);
+
+    assert_rejected(&public_inputs, &witness, "overflows");
+}
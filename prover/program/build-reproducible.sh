@@ -0,0 +1,37 @@
+#!/bin/bash
+# Build the guest ELF reproducibly and print its hash.
+#
+# Builds in SP1's docker image (GUEST_TOOLCHAIN_IMAGE in core/src/guest_elf.rs)
+# against the committed Cargo.lock (which keeps the sp1-* crates at the
+# image's SP1 version), so the same sources give the same ELF on any machine. Prints the guest-elf.lock lines for the result; record them
+# with `sp1-host elf-record --write` after rebuilding the host.
+#
+# The guest uses no [patch] crates (SP1 5.x accelerates its crypto natively);
+# any added later must be vendored under prover/program/patches and patched
+# by path, not by git URL, or the build stops being reproducible.
+set -e
+
+TAG=${SP1_TAG:-v5.2.3}
+IMAGE="ghcr.io/succinctlabs/sp1:$TAG"
+OUT=${1:-elf}
+
+cd "$(dirname "$0")"
+
+if [ ! -f Cargo.lock ]; then
+    echo "ERROR: No Cargo.lock; generate it and commit it (git add -f, *.lock is ignored)" >&2
+    exit 1
+fi
+if grep -q 'git *=' Cargo.toml; then
+    echo "ERROR: Cargo.toml has git dependencies; vendor them under patches/" >&2
+    exit 1
+fi
+
+docker pull "$IMAGE" > /dev/null
+DIGEST=$(docker image inspect --format '{{index .RepoDigests 0}}' "$IMAGE")
+DIGEST=${DIGEST#*@sha256:}
+
+cargo prove build --docker --tag "$TAG" --locked --output-directory "$OUT" --elf-name sp1-program >&2
+
+ELF_SHA256=$(sha256sum "$OUT/sp1-program" | cut -d' ' -f1)
+echo "elf-sha256 = 0x$ELF_SHA256"
+echo "toolchain-sha256 = 0x$DIGEST"
@@ -0,0 +1,25 @@
+//! SP1 zkVM Program for balance proofs
+//!
+//! Proves that one owner controls unspent notes totaling at least a
+//! threshold at a given root, without revealing which notes (see core
+//! `balance_proof`). Commits the ABI-encoded `BalanceStatement`.
+//!
+//! The verifier still checks off-chain that:
+//! - root is a root the ledger has had
+//! - spentNullifiersHash matches the ledger's NullifierUsed events up to it
+//! - challenge is the one it issued
+
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use sp1_zkvm::io;
+use ghostclaw_core::balance_proof::{encode_balance_statement, verify_balance_witness, BalanceWitness};
+
+pub fn main() {
+    let witness: BalanceWitness = io::read();
+
+    let statement = verify_balance_witness(&witness)
+        .unwrap_or_else(|e| panic!("SECURITY: balance verification failed: {}", e));
+
+    io::commit_slice(&encode_balance_statement(&statement));
+}
@@ -7,34 +7,65 @@
 //! # Security Model
 //! The ZK circuit enforces:
 //! 1. Merkle membership: Each input note MUST exist in the tree at old_root
-//! 2. Signature validity: Owner must sign to spend
-//! 3. Value conservation: sum(inputs) >= sum(outputs)
-//! 4. Nullifier correctness: Prevents double-spend
+//! 2. Signature validity: Owner must sign to spend; each input's tx signature
+//!    covers its nullifier, the fee and every output commitment
+//! 3. Value conservation: sum(inputs) >= sum(outputs) + public_amount
+//! 4. Nullifier correctness: Prevents double-spend; old_root isn't the
+//!    empty root when spending, and no nullifier or commitment is zero
+//! 5. Dust policy (`dust-policy` feature): outputs are 0 or >= the threshold
+//! 6. Note versions: every note's format is in `ACCEPTED_NOTE_VERSIONS`
+//! 7. Hidden amounts (`hidden-amounts` feature): output amount commitments
+//!    open to u64 amounts and balance against the inputs up to the public amount
+//! 8. Withdrawals: a transaction with no outputs must be one; its amount and
+//!    recipient are signed by every input owner and committed in the public
+//!    values
+//! 9. Scoped use (`externalNullifier` set): the inputs are proven owned and
+//!    in the tree, with nullifiers for that application context only,
+//!    recomputed from the notes rather than the signatures (one per note
+//!    and context); no outputs or withdrawal, and the public values use the
+//!    scoped layout, which the ledger can't decode
+//! 10. Output owners (`owner-check` feature): every output with value is
+//!    owned by a secp256k1 key, so none is burned by a mis-encoded key
+//! 11. Nullifier tree (`nullifier-tree` feature): every nullifier is absent
+//!    from the nullifier tree at the witness's old nullifier root; the old
+//!    and new roots are committed in a trailer section, for a ledger in SMT
+//!    mode
 //!
 //! The contract then verifies:
+//! - chainId is the chain it's deployed on (no cross-chain replay)
 //! - old_root matches currentRoot
 //! - Nullifiers haven't been used
+//! - A withdrawal pays exactly the proven amount to the proven recipient
 //! - Updates state to new_root
 
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
 use sp1_zkvm::io;
-use utxo_prototype::{
-    commit, Ledger, PublicInputs, PublicOutputs, Witness,
-    simulate_tx_with_precomputed,
-    merkle::MerkleTree,
+use ghostclaw_core::{
+    GuestInput, Ledger,
+    simulate_witness,
+    public_values::encode_public_values,
 };
-use alloy_sol_types::{sol, SolValue};
-
-// Define Solidity-compatible struct for ABI encoding
-// This must match the PublicOutputs struct in PrivateUTXOLedger.sol
-sol! {
-    struct PublicOutputsSol {
-        bytes32 oldRoot;
-        bytes32[] nullifiers;
-        bytes32[] outputCommitments;
+
+/// Minimum non-zero output amount, fixed when the ELF is built with the
+/// `dust-policy` feature (`GUEST_DUST_THRESHOLD=<amount>`). Part of the
+/// program, so changing it changes the vkey.
+#[cfg(feature = "dust-policy")]
+const DUST_THRESHOLD: u64 = parse_threshold(env!("GUEST_DUST_THRESHOLD"));
+
+#[cfg(feature = "dust-policy")]
+const fn parse_threshold(s: &str) -> u64 {
+    let bytes = s.as_bytes();
+    assert!(!bytes.is_empty(), "GUEST_DUST_THRESHOLD is empty");
+    let mut value: u64 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "GUEST_DUST_THRESHOLD must be a decimal integer");
+        value = value * 10 + (bytes[i] - b'0') as u64;
+        i += 1;
     }
+    value
 }
 
 pub fn main() {
@@ -42,23 +73,41 @@ pub fn main() {
     // STEP 1: Read inputs from host
     // ========================================================================
 
-    let public_inputs: PublicInputs = io::read();
-    let witness: Witness = io::read();
+    // One envelope, checked for magic and layout version before use
+    let (public_inputs, witness) = io::read::<GuestInput>().open().unwrap_or_else(|e| panic!("{}", e));
 
     // ========================================================================
     // STEP 2: Validate witness structure and constraints
     // ========================================================================
 
-    // Check structural validity (matching array lengths, non-empty tx, etc.)
+    // Check structural validity (matching array lengths, non-empty tx,
+    // accepted note versions, etc.)
     witness
         .validate_structure()
         .expect("Witness validation failed: invalid structure");
 
-    // Check value conservation: sum(inputs) >= sum(outputs)
+    // Check value conservation: sum(inputs) >= sum(outputs) + public_amount
     witness
         .validate_value_conservation()
         .expect("Witness validation failed: value conservation violated");
 
+    // Outputs must be 0 (padding) or at least the dust threshold
+    #[cfg(feature = "dust-policy")]
+    witness
+        .validate_dust(DUST_THRESHOLD)
+        .expect("Witness validation failed: dust output");
+
+    // Outputs with value must be owned by a secp256k1 key
+    #[cfg(feature = "owner-check")]
+    witness
+        .validate_output_owners()
+        .expect("Witness validation failed: unspendable output owner");
+
+    // An input can't be a member of the empty (or an all-zero) root
+    witness
+        .validate_old_root(&public_inputs.old_root)
+        .expect("Witness validation failed: invalid old_root");
+
     // Additional sanity checks
     assert!(
         !witness.input_notes.is_empty() || !witness.output_notes.is_empty(),
@@ -66,98 +115,46 @@ pub fn main() {
     );
 
     // ========================================================================
-    // STEP 3: Verify precomputed values (security check)
+    // STEP 3: Restore precomputed values
     // ========================================================================
 
     let mut ledger = Ledger::new();
 
-    // Verify precomputed input commitments match note data (if provided)
-    // This is a critical security check - ensures host didn't provide fake commitments
-    if witness.has_precomputed_values() {
-        for (i, note) in witness.input_notes.iter().enumerate() {
-            let recomputed = commit(note);
-            assert_eq!(
-                recomputed,
-                witness.precomputed_input_commitments[i],
-                "Input commitment mismatch at index {}: precomputed doesn't match note",
-                i
-            );
-        }
-    }
+    // Recompute any derivable values the host stripped before submitting the
+    // witness to a third-party prover (Blake3 only, cheap in the zkVM).
+    let witness = witness.with_derived_values();
 
     // ========================================================================
-    // STEP 4: CRITICAL - Verify Merkle Inclusion Proofs
+    // STEP 4: CRITICAL - Verify membership, signatures and nullifiers
     // ========================================================================
     //
-    // This is the CRITICAL security step that prevents infinite mint attacks.
-    // For each input note, we verify that its commitment exists in the
-    // Merkle tree at old_root using a membership proof.
+    // `simulate_witness` is the same check the host runs before proving:
+    // - Precomputed commitments match the note data (the host can't supply
+    //   fake commitments)
+    // - Every input has a Merkle proof against old_root. This prevents
+    //   infinite mint attacks: without it an attacker could spend fake notes
+    //   with arbitrary amounts, since signatures and value conservation
+    //   would still check out. The proof binds inputs to the contract state.
+    // - Nullifier and tx signatures recover to each note's owner
+    // - No nullifier is repeated
     //
-    // Without this check, an attacker could:
-    // 1. Create fake notes with arbitrary amounts (e.g., 1 billion USDC)
-    // 2. Generate valid proofs (signatures work, values conserve)
-    // 3. Steal unlimited funds from the contract
-    //
-    // The Merkle proof binds the input notes to the contract's state (old_root).
-
-    // Verify we have Merkle proofs for all input notes
-    assert!(
-        witness.input_proofs.len() == witness.input_notes.len(),
-        "SECURITY: Merkle proofs MUST be provided for all input notes. Got {} proofs for {} inputs.",
-        witness.input_proofs.len(),
-        witness.input_notes.len()
-    );
-
-    // Verify each input note exists in the tree at old_root
-    for (i, (note, proof)) in witness.input_notes.iter().zip(witness.input_proofs.iter()).enumerate() {
-        // Compute the commitment for this note
-        let note_commitment = commit(note);
-
-        // CRITICAL: Verify the Merkle proof against old_root
-        let is_valid = MerkleTree::verify_proof(
-            note_commitment,
-            proof,
-            public_inputs.old_root,
-        );
+    // Every failure is collected, so the panic names all of them.
 
-        assert!(
-            is_valid,
-            "SECURITY VIOLATION: Merkle proof failed for input note {}. Note commitment does NOT exist in tree at old_root. This could be an infinite mint attack.",
-            i
+    let simulation = simulate_witness(&mut ledger, &witness, public_inputs.old_root);
+    if !simulation.is_valid() {
+        panic!(
+            "SECURITY: transaction verification failed: {}",
+            simulation.failure_reasons().join("; ")
         );
     }
 
     // ========================================================================
-    // STEP 5: Execute transaction and compute new state
+    // STEP 5: Public outputs (old_root is the one proven against, bound to
+    // the chain whose ledger it came from)
     // ========================================================================
 
-    // Use optimized path when precomputed values are available
-    let public_outputs = if witness.has_precomputed_values() {
-        // OPTIMIZED PATH: Use precomputed values (no ECDSA in zkVM)
-        let mut outputs = simulate_tx_with_precomputed(
-            &mut ledger,
-            &witness.nullifier_signatures,
-            &witness.tx_signatures,
-            &witness.input_notes,
-            witness.output_notes.clone(),
-            &witness.precomputed_nullifiers,
-            &witness.precomputed_input_commitments,
-            &witness.precomputed_output_commitments,
-        )
-        .expect("Optimized transaction execution failed");
-
-        // Use the provided old_root from public inputs (contract verifies this)
-        // The simulate function uses a fresh ledger so returns 0x0 for old_root
-        outputs.old_root = public_inputs.old_root;
-        outputs
-    } else {
-        // STANDARD PATH: DISABLED FOR SECURITY
-        // The standard path (in-circuit ECDSA) is currently disabled because it
-        // does not enforce full signature verification in this prototype.
-        // We MUST use the optimized path (precomputed values) where the host
-        // verifies signatures and the zkVM checks them via hash matching.
-        panic!("Standard path disabled: Witness must provide precomputed values for security.");
-    };
+    let mut public_outputs = simulation.public_outputs;
+    public_outputs.chain_id = public_inputs.chain_id;
 
     // ========================================================================
     // STEP 6: Final validation before committing
@@ -177,6 +174,11 @@ pub fn main() {
     //     );
     // }
 
+    // Nullifiers and commitments are hashes; zero means something is wrong
+    public_outputs
+        .validate_nonzero()
+        .expect("Public outputs validation failed");
+
     // Verify counts match
     assert_eq!(
         public_outputs.nullifiers.len(),
@@ -190,6 +192,9 @@ pub fn main() {
         "Commitment count mismatch"
     );
 
+    assert_eq!(public_outputs.withdrawal, witness.withdrawal, "Withdrawal mismatch");
+    assert_eq!(public_outputs.external_nullifier, witness.external_nullifier, "External nullifier mismatch");
+
     // ========================================================================
     // STEP 7: Commit public outputs to host (ABI-encoded for Solidity)
     // ========================================================================
@@ -197,12 +202,48 @@ pub fn main() {
     // SECURITY: We ABI-encode the outputs so the contract can decode them
     // directly from publicValues. This binds the proven values to what
     // the contract uses, preventing proof-binding bypass attacks.
+    //
+    // Many-output transactions commit only list roots and counts; the
+    // contract re-hashes the full lists supplied in calldata. Withdrawals
+    // always use the full layout, with the amount and recipient appended.
+
+    io::commit_slice(&encode_public_values(&public_outputs));
+
+    // Modes that commit more go in tagged trailer sections after the ABI
+    // values, so the ledger's layout checks still see those alone (see core
+    // `public_values`)
+    #[allow(unused_mut)]
+    let mut trailer: Vec<(u8, Vec<u8>)> = Vec::new();
+
+    // Hidden-amounts mode: the output amount commitments are computed from
+    // the openings here (so each provably holds a u64) and the balance
+    // equation is checked; only the net public amount is revealed.
+    #[cfg(feature = "hidden-amounts")]
+    {
+        let hidden = ghostclaw_core::amount_commitment::hidden_amounts(&witness)
+            .expect("Witness validation failed: amount commitments");
+        trailer.push((
+            ghostclaw_core::public_values::SECTION_HIDDEN_AMOUNTS,
+            ghostclaw_core::amount_commitment::encode_hidden_amounts(&hidden),
+        ));
+    }
 
-    let sol_outputs = PublicOutputsSol {
-        oldRoot: public_outputs.old_root.into(),
-        nullifiers: public_outputs.nullifiers.iter().map(|n| (*n).into()).collect(),
-        outputCommitments: public_outputs.output_commitments.iter().map(|c| (*c).into()).collect(),
-    };
+    // SMT mode: the nullifiers are proven unspent and inserted here, so the
+    // ledger stores only the nullifier root (see core `nullifier_tree`).
+    #[cfg(feature = "nullifier-tree")]
+    {
+        let update = witness
+            .nullifier_tree
+            .as_ref()
+            .expect("Witness validation failed: SMT mode requires nullifier non-membership proofs");
+        let new_root = update
+            .apply(&public_outputs.nullifiers)
+            .expect("Witness validation failed: nullifier tree");
+        trailer.push((
+            ghostclaw_core::public_values::SECTION_NULLIFIER_ROOTS,
+            ghostclaw_core::nullifier_tree::encode_nullifier_roots(&update.old_root.0, &new_root).to_vec(),
+        ));
+    }
 
-    io::commit_slice(&sol_outputs.abi_encode());
+    io::commit_slice(&ghostclaw_core::public_values::encode_trailer(&trailer));
 }
@@ -3,6 +3,7 @@ import cors from 'cors';
 import { config } from 'dotenv';
 import { fileURLToPath } from 'url';
 import { dirname, join } from 'path';
+import { readFileSync } from 'fs';
 
 // Alchemy Account Kit for gasless transactions
 import { createLightAccountAlchemyClient } from '@account-kit/smart-contracts';
@@ -32,8 +33,12 @@ const PORT = process.env.PORT || 3002;
 const ALCHEMY_API_KEY = process.env.ALCHEMY_API_KEY;
 const RELAYER_PRIVATE_KEY = process.env.RELAYER_PRIVATE_KEY;
 const GAS_POLICY_ID = process.env.GAS_POLICY_ID;
+// `sp1-host deploy` writes DEPLOYMENT_MANIFEST ({ ledger, chainId, ... })
+const DEPLOYMENT = process.env.DEPLOYMENT_MANIFEST
+    ? JSON.parse(readFileSync(process.env.DEPLOYMENT_MANIFEST, 'utf8'))
+    : {};
 // PrivateUTXOLedger contract - proof-required UTXO system
-const CONTRACT_ADDRESS = process.env.CONTRACT_ADDRESS || '0x42ae920DFD0d25Ac014DFd751bd2ff2D2fBa0443';
+const CONTRACT_ADDRESS = process.env.CONTRACT_ADDRESS || DEPLOYMENT.ledger || '0x42ae920DFD0d25Ac014DFd751bd2ff2D2fBa0443';
 
 if (!ALCHEMY_API_KEY || !RELAYER_PRIVATE_KEY || !GAS_POLICY_ID) {
     console.error('Missing required environment variables: ALCHEMY_API_KEY, RELAYER_PRIVATE_KEY, GAS_POLICY_ID');
@@ -137,6 +142,13 @@ const UTXO_LEDGER_ABI = [
         stateMutability: 'view',
         type: 'function'
     },
+    {
+        inputs: [{ name: '', type: 'bytes32' }],
+        name: 'validRoots',
+        outputs: [{ name: '', type: 'bool' }],
+        stateMutability: 'view',
+        type: 'function'
+    },
     {
         inputs: [
             { name: 'commitment', type: 'bytes32' },
@@ -199,6 +211,30 @@ const UTXO_LEDGER_ABI = [
         stateMutability: 'nonpayable',
         type: 'function'
     },
+    // Many-output transactions: publicValues commit list roots, full lists passed as calldata
+    {
+        inputs: [
+            {
+                components: [
+                    { name: 'commitment', type: 'bytes32' },
+                    { name: 'keyType', type: 'uint8' },
+                    { name: 'ephemeralPubkey', type: 'bytes' },
+                    { name: 'nonce', type: 'bytes12' },
+                    { name: 'ciphertext', type: 'bytes' }
+                ],
+                name: 'encryptedOutputs',
+                type: 'tuple[]'
+            },
+            { name: 'proof', type: 'bytes' },
+            { name: 'publicValues', type: 'bytes' },
+            { name: 'nullifiers', type: 'bytes32[]' },
+            { name: 'outputCommitments', type: 'bytes32[]' }
+        ],
+        name: 'submitTxCompressed',
+        outputs: [],
+        stateMutability: 'nonpayable',
+        type: 'function'
+    },
     {
         inputs: [
             { name: 'recipient', type: 'address' },
@@ -224,14 +260,49 @@ const UTXO_LEDGER_ABI = [
     }
 ];
 
+// Prover-server issuing nullifier reservations (two-phase submit); optional
+const PROVER_SERVER_URL = process.env.PROVER_SERVER_URL;
+// Identifies this relayer when claiming reservations
+const RELAYER_ID = process.env.RELAYER_ID || `relayer-${PORT}`;
+
+// The oldRoot committed in publicValues (see core/src/public_values.rs): the
+// second word of the compressed layout, a static tuple; the third of the
+// others, after the tuple's offset. A trailer only follows the layouts.
+function committedOldRoot(publicValues, compressed) {
+    const hex = publicValues.startsWith('0x') ? publicValues.slice(2) : publicValues;
+    const word = compressed ? 1 : 2;
+    return `0x${hex.slice(word * 64, (word + 1) * 64)}`;
+}
+
+// Claim/confirm/release a proof's nullifier reservation on the prover-server,
+// with the claim token the wallet got alongside the proof. Returns null when
+// no reservation is involved.
+async function updateReservation({ reservationId, claimToken }, action, extra = {}) {
+    if (!reservationId || !PROVER_SERVER_URL) return null;
+    const response = await fetch(`${PROVER_SERVER_URL}/api/reservations/${reservationId}/${action}`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ relayer: RELAYER_ID, claimToken, ...extra })
+    });
+    const body = await response.json().catch(() => ({}));
+    if (!response.ok) {
+        const error = new Error(body.error || `Reservation ${action} failed (${response.status})`);
+        error.status = response.status;
+        throw error;
+    }
+    return body;
+}
+
 console.log('Relayer starting...');
 console.log('Contract:', CONTRACT_ADDRESS);
 
 // Submit private transaction (for sends between private addresses)
 // SECURITY FIX: Contract now decodes outputs from publicValues (no separate outputs param)
 app.post('/api/submit-tx', async (req, res) => {
+    const { reservationId, claimToken } = req.body;
+    let claimed = false;
     try {
-        const { encryptedOutputs, proof, publicValues } = req.body;
+        const { encryptedOutputs, proof, publicValues, compressed, publicOutputs, expiresAt, unverifiedRoot } = req.body;
 
         console.log('[Relayer] Processing submit-tx...');
         console.log('[Relayer] publicValues:', publicValues ? `${publicValues.slice(0, 20)}... (${publicValues.length} chars)` : 'MISSING');
@@ -249,30 +320,77 @@ app.post('/api/submit-tx', async (req, res) => {
             throw new Error('Invalid or missing proof - cannot submit transaction without valid proof');
         }
 
+        // Proven with --allow-unverified-root: the root may be one the ledger
+        // never had, so the proof is for offline/dev use only
+        if (unverifiedRoot) {
+            return res.status(422).json({ error: 'Proof was generated without verifying its old root; request a proof from a prover with ledger access' });
+        }
+
+        // Once oldRoot leaves the root history submitTx reverts with "Invalid
+        // old root"; ask the ledger rather than trusting the prover's
+        // expiresAt, which is only an estimate
+        const oldRoot = committedOldRoot(publicValues, compressed);
+        const rootValid = await publicClient.readContract({
+            address: CONTRACT_ADDRESS,
+            abi: UTXO_LEDGER_ABI,
+            functionName: 'validRoots',
+            args: [oldRoot]
+        });
+        if (!rootValid) {
+            console.warn('[Relayer] Proof old root is no longer valid:', oldRoot);
+            return res.status(410).json({ error: 'Proof has expired; request a new proof against a current root', oldRoot, expiresAt });
+        }
+
+        // Proofs are bound to one chain; the ledger would revert with "Wrong chain"
+        if (publicOutputs?.chainId !== undefined && publicOutputs.chainId !== chain.id) {
+            return res.status(400).json({ error: `Proof is bound to chain ${publicOutputs.chainId}, relayer submits to chain ${chain.id}` });
+        }
+
         console.log('[Relayer] Proof length:', proof.length, 'bytes');
         console.log('[Relayer] PublicValues length:', publicValues.length, 'bytes');
         console.log('[Relayer] EncryptedOutputs count:', encryptedOutputs?.length || 0);
 
-        const callData = encodeFunctionData({
-            abi: UTXO_LEDGER_ABI,
-            functionName: 'submitTx',
-            args: [
-                encryptedOutputs.map(eo => ({
-                    commitment: eo.commitment,
-                    keyType: eo.keyType,
-                    ephemeralPubkey: eo.ephemeralPubkey,
-                    nonce: eo.nonce,
-                    ciphertext: eo.ciphertext
-                })),
-                proof,
-                publicValues
-            ]
-        });
+        const outputsArg = encryptedOutputs.map(eo => ({
+            commitment: eo.commitment,
+            keyType: eo.keyType,
+            ephemeralPubkey: eo.ephemeralPubkey,
+            nonce: eo.nonce,
+            ciphertext: eo.ciphertext
+        }));
+
+        // Compressed public values only commit list roots; the contract
+        // re-hashes the full lists from the prover's publicOutputs
+        let callData;
+        if (compressed) {
+            if (!publicOutputs?.nullifiers || !publicOutputs?.outputCommitments) {
+                throw new Error('Compressed publicValues require publicOutputs.nullifiers and publicOutputs.outputCommitments');
+            }
+            console.log('[Relayer] Using submitTxCompressed');
+            callData = encodeFunctionData({
+                abi: UTXO_LEDGER_ABI,
+                functionName: 'submitTxCompressed',
+                args: [outputsArg, proof, publicValues, publicOutputs.nullifiers, publicOutputs.outputCommitments]
+            });
+        } else {
+            callData = encodeFunctionData({
+                abi: UTXO_LEDGER_ABI,
+                functionName: 'submitTx',
+                args: [outputsArg, proof, publicValues]
+            });
+        }
 
         // Log calldata info for debugging
         console.log('[Relayer] CallData length:', callData.length, 'bytes');
         console.log('[Relayer] Target contract:', CONTRACT_ADDRESS);
 
+        // Claim the proof's nullifiers so no other relayer submits it too
+        try {
+            claimed = !!(await updateReservation({ reservationId, claimToken }, 'claim'));
+        } catch (claimError) {
+            console.warn('[Relayer] Reservation claim refused:', claimError.message);
+            return res.status(409).json({ error: claimError.message, reservationId });
+        }
+
         const result = await smartAccountClient.sendUserOperation({
             uo: { target: CONTRACT_ADDRESS, data: callData, value: 0n },
         });
@@ -283,9 +401,18 @@ app.post('/api/submit-tx', async (req, res) => {
         const txHash = await smartAccountClient.waitForUserOperationTransaction(result);
         console.log('[Relayer] Tx confirmed:', txHash);
 
+        if (claimed) {
+            await updateReservation({ reservationId, claimToken }, 'confirm', { txHash })
+                .catch(e => console.warn('[Relayer] Reservation confirm failed:', e.message));
+        }
+
         res.json({ success: true, txHash, userOpHash: result.hash });
     } catch (error) {
         console.error('[Relayer] submit-tx error:', error.message);
+        if (claimed) {
+            await updateReservation({ reservationId, claimToken }, 'release')
+                .catch(e => console.warn('[Relayer] Reservation release failed:', e.message));
+        }
         // Log full error details for debugging
         if (error.cause) {
             console.error('[Relayer] Error cause:', error.cause);
@@ -304,7 +431,7 @@ app.post('/api/submit-tx', async (req, res) => {
 // SECURITY FIX: Contract now decodes outputs from publicValues (no separate outputs param)
 app.post('/api/withdraw', async (req, res) => {
     try {
-        const { recipient, amount, proof, publicValues } = req.body;
+        const { recipient, amount, proof, publicValues, unverifiedRoot } = req.body;
 
         console.log('[Relayer] Processing withdraw...');
         console.log('[Relayer] Recipient:', recipient);
@@ -325,6 +452,10 @@ app.post('/api/withdraw', async (req, res) => {
             throw new Error('Invalid or missing proof - cannot withdraw without valid proof');
         }
 
+        if (unverifiedRoot) {
+            return res.status(422).json({ error: 'Proof was generated without verifying its old root; request a proof from a prover with ledger access' });
+        }
+
         console.log('[Relayer] Proof length:', proof.length, 'bytes');
         console.log('[Relayer] PublicValues length:', publicValues.length, 'bytes');
 